@@ -0,0 +1,315 @@
+//! Find the results of benchmarks in the output of their jobs, and compare them to the results of
+//! the previous run.
+//!
+//! When running benchmarks, each benchmark is run by its own job, which has a whole worker to
+//! itself. We understand the output of both libtest's `#[bench]` harness and criterion. Once all
+//! of the benchmarks have run, the results are saved in `<maelstrom-target-dir>/benchmarks`, with
+//! one JSON file per run, and each result is compared to the one from the most recent earlier run.
+
+use anyhow::Result;
+use maelstrom_test_runner::ui::{UiMessage, UiSender};
+use maelstrom_util::{fs::Fs, root::RootBuf};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, path::PathBuf, sync::Mutex};
+
+/// The directory benchmark results are saved to is `<maelstrom-target-dir>/benchmarks`.
+pub struct BenchmarksDir;
+
+/// How long one iteration of a benchmark took.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Measurement {
+    /// The estimated time of an iteration, in nanoseconds.
+    pub nanoseconds: f64,
+    /// How far off the estimate could be, in nanoseconds.
+    pub error: f64,
+}
+
+/// The results of one run, keyed by the benchmarks' names.
+type RunResults = BTreeMap<String, Measurement>;
+
+fn parse_number(number: &str) -> Option<f64> {
+    number.replace(',', "").parse().ok()
+}
+
+fn unit_nanoseconds(unit: &str) -> Option<f64> {
+    match unit {
+        "ps" => Some(1e-3),
+        "ns" => Some(1.0),
+        "µs" | "us" => Some(1e3),
+        "ms" => Some(1e6),
+        "s" => Some(1e9),
+        _ => None,
+    }
+}
+
+fn parse_time(number: &str, unit: &str) -> Option<f64> {
+    Some(parse_number(number)? * unit_nanoseconds(unit)?)
+}
+
+/// Find the measurement in the output of a benchmark. libtest prints a line like
+/// `test foo ... bench:       1,234 ns/iter (+/- 56)`, while criterion prints a line like
+/// `foo   time:   [1.2034 µs 1.2100 µs 1.2201 µs]`, giving a confidence interval around its
+/// estimate.
+pub fn parse_output(lines: &[String]) -> Option<Measurement> {
+    let libtest = Regex::new(r"bench:\s+([\d,.]+) ns/iter \(\+/- ([\d,.]+)\)").unwrap();
+    let criterion =
+        Regex::new(r"time:\s+\[([\d.]+) (\S+) ([\d.]+) (\S+) ([\d.]+) (\S+)\]").unwrap();
+    lines.iter().find_map(|line| {
+        if let Some(captures) = libtest.captures(line) {
+            Some(Measurement {
+                nanoseconds: parse_number(&captures[1])?,
+                error: parse_number(&captures[2])?,
+            })
+        } else if let Some(captures) = criterion.captures(line) {
+            let low = parse_time(&captures[1], &captures[2])?;
+            let estimate = parse_time(&captures[3], &captures[4])?;
+            let high = parse_time(&captures[5], &captures[6])?;
+            Some(Measurement {
+                nanoseconds: estimate,
+                error: (high - low) / 2.0,
+            })
+        } else {
+            None
+        }
+    })
+}
+
+fn format_nanoseconds(nanoseconds: f64) -> String {
+    let (value, unit) = if nanoseconds >= 1e9 {
+        (nanoseconds / 1e9, "s")
+    } else if nanoseconds >= 1e6 {
+        (nanoseconds / 1e6, "ms")
+    } else if nanoseconds >= 1e3 {
+        (nanoseconds / 1e3, "µs")
+    } else {
+        (nanoseconds, "ns")
+    };
+    format!("{value:.3} {unit}")
+}
+
+/// Describe how a benchmark's result compares to its result in the previous run. A change is only
+/// called an improvement or a regression if it's bigger than the two measurements' errors.
+fn compare(current: &Measurement, previous: Option<&Measurement>) -> String {
+    let Some(previous) = previous else {
+        return "new".into();
+    };
+    let difference = current.nanoseconds - previous.nanoseconds;
+    let percent = if previous.nanoseconds > 0.0 {
+        difference / previous.nanoseconds * 100.0
+    } else {
+        0.0
+    };
+    let verdict = if difference.abs() <= current.error + previous.error {
+        "no change"
+    } else if difference < 0.0 {
+        "improved"
+    } else {
+        "regressed"
+    };
+    format!("{percent:+.1}% ({verdict})")
+}
+
+fn report_lines(current: &RunResults, previous: &RunResults) -> Vec<String> {
+    current
+        .iter()
+        .map(|(name, measurement)| {
+            format!(
+                "{name}: {} (+/- {}), {}",
+                format_nanoseconds(measurement.nanoseconds),
+                format_nanoseconds(measurement.error),
+                compare(measurement, previous.get(name))
+            )
+        })
+        .collect()
+}
+
+pub struct Benchmarks {
+    dir: RootBuf<BenchmarksDir>,
+    results: Mutex<RunResults>,
+}
+
+impl Benchmarks {
+    pub fn new(dir: RootBuf<BenchmarksDir>) -> Self {
+        Self {
+            dir,
+            results: Default::default(),
+        }
+    }
+
+    /// Record the result in the output of a benchmark job. Output without a result, like that of
+    /// a test that was listed as a benchmark, is ignored.
+    pub fn save(&self, name: &str, lines: &[String]) {
+        if let Some(measurement) = parse_output(lines) {
+            self.results
+                .lock()
+                .unwrap()
+                .insert(name.into(), measurement);
+        }
+    }
+
+    /// The number of the most recent saved run, if there is one.
+    fn last_run(&self) -> Result<Option<u64>> {
+        let fs = Fs::new();
+        if !fs.exists(&self.dir) {
+            return Ok(None);
+        }
+        let mut last = None;
+        for entry in fs.read_dir(&self.dir)? {
+            let path = entry?.path();
+            let run = path
+                .file_name()
+                .and_then(|name| name.to_str()?.strip_suffix(".json")?.parse::<u64>().ok());
+            last = last.max(run);
+        }
+        Ok(last)
+    }
+
+    fn run_path(&self, run: u64) -> PathBuf {
+        self.dir.join::<()>(format!("{run}.json")).into_path_buf()
+    }
+
+    /// Save the results collected since the last call as a new run. Returns where they were saved,
+    /// along with the results of the previous run.
+    fn save_run(&self) -> Result<(PathBuf, RunResults, RunResults)> {
+        let fs = Fs::new();
+        let current = std::mem::take(&mut *self.results.lock().unwrap());
+        let last_run = self.last_run()?;
+        let previous = match last_run {
+            Some(run) => serde_json::from_str(&fs.read_to_string(self.run_path(run))?)?,
+            None => RunResults::new(),
+        };
+        fs.create_dir_all(&self.dir)?;
+        let path = self.run_path(last_run.map_or(0, |run| run + 1));
+        fs.write(&path, serde_json::to_string_pretty(&current)?)?;
+        Ok((path, current, previous))
+    }
+
+    /// Save this run's results and show how they compare to the previous run's.
+    pub fn report(&self, ui: &UiSender) -> Result<()> {
+        if self.results.lock().unwrap().is_empty() {
+            ui.send(UiMessage::CollectionOutput(
+                "no benchmark results were found".into(),
+            ));
+            return Ok(());
+        }
+        let (path, current, previous) = self.save_run()?;
+        let mut lines = report_lines(&current, &previous);
+        lines.push(format!("benchmark results saved in {}", path.display()));
+        ui.send(UiMessage::CollectionOutput(lines.join("\n")));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(output: &str) -> Vec<String> {
+        output.lines().map(Into::into).collect()
+    }
+
+    #[test]
+    fn parse_libtest_output() {
+        assert_eq!(
+            parse_output(&lines(
+                "\n\
+                running 1 test\n\
+                test tests::bench_foo ... bench:       1,234.50 ns/iter (+/- 56)\n\
+                \n\
+                test result: ok. 0 passed; 0 failed; 0 ignored; 1 measured; 3 filtered out\n"
+            )),
+            Some(Measurement {
+                nanoseconds: 1234.5,
+                error: 56.0,
+            })
+        );
+    }
+
+    #[test]
+    fn parse_criterion_output() {
+        assert_eq!(
+            parse_output(&lines(
+                "Benchmarking fib 20: Analyzing\n\
+                fib 20                  time:   [26.000 µs 26.250 µs 26.500 µs]\n\
+                Found 11 outliers among 100 measurements (11.00%)\n"
+            )),
+            Some(Measurement {
+                nanoseconds: 26250.0,
+                error: 250.0,
+            })
+        );
+    }
+
+    #[test]
+    fn parse_criterion_output_with_name_on_its_own_line() {
+        assert_eq!(
+            parse_output(&lines(
+                "a rather long benchmark group/with a parameter\n\
+                \x20                       time:   [1.5000 ms 2.0000 ms 2.5000 ms]\n"
+            )),
+            Some(Measurement {
+                nanoseconds: 2e6,
+                error: 5e5,
+            })
+        );
+    }
+
+    #[test]
+    fn parse_output_without_result() {
+        assert_eq!(parse_output(&lines("test tests::foo ... ok\n")), None);
+    }
+
+    #[test]
+    fn format_nanoseconds_picks_unit() {
+        assert_eq!(format_nanoseconds(12.0), "12.000 ns");
+        assert_eq!(format_nanoseconds(1234.5), "1.234 µs");
+        assert_eq!(format_nanoseconds(2.5e6), "2.500 ms");
+        assert_eq!(format_nanoseconds(3e9), "3.000 s");
+    }
+
+    #[test]
+    fn compare_to_previous() {
+        let measurement = |nanoseconds, error| Measurement { nanoseconds, error };
+        assert_eq!(compare(&measurement(100.0, 1.0), None), "new");
+        assert_eq!(
+            compare(&measurement(100.0, 1.0), Some(&measurement(200.0, 1.0))),
+            "-50.0% (improved)"
+        );
+        assert_eq!(
+            compare(&measurement(110.0, 1.0), Some(&measurement(100.0, 1.0))),
+            "+10.0% (regressed)"
+        );
+        assert_eq!(
+            compare(&measurement(101.0, 1.0), Some(&measurement(100.0, 1.0))),
+            "+1.0% (no change)"
+        );
+    }
+
+    #[test]
+    fn save_run_compares_to_last_run() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let benchmarks = Benchmarks::new(RootBuf::new(temp_dir.path().join("benchmarks")));
+
+        benchmarks.save(
+            "foo bench_a",
+            &lines("test bench_a ... bench: 100 ns/iter (+/- 1)"),
+        );
+        benchmarks.save("foo bench_b", &lines("test bench_b ... ok"));
+        let (path, current, previous) = benchmarks.save_run().unwrap();
+        assert_eq!(path, temp_dir.path().join("benchmarks/0.json"));
+        assert_eq!(Vec::from_iter(current.keys()), vec!["foo bench_a"]);
+        assert_eq!(previous, RunResults::new());
+
+        benchmarks.save(
+            "foo bench_a",
+            &lines("test bench_a ... bench: 50 ns/iter (+/- 1)"),
+        );
+        let (path, current, previous) = benchmarks.save_run().unwrap();
+        assert_eq!(path, temp_dir.path().join("benchmarks/1.json"));
+        assert_eq!(
+            report_lines(&current, &previous),
+            vec!["foo bench_a: 50.000 ns (+/- 1.000 ns), -50.0% (improved)"]
+        );
+    }
+}
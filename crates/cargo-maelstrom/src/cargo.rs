@@ -2,17 +2,19 @@ use anyhow::{bail, Context as _, Result};
 use cargo_metadata::{
     Artifact as CargoArtifact, Message as CargoMessage, MessageIter as CargoMessageIter,
     Metadata as CargoMetadata, Package as CargoPackage, PackageId as CargoPackageId,
+    Target as CargoTarget,
 };
-use maelstrom_base::WindowSize;
+use maelstrom_base::{Utf8Path, Utf8PathBuf, WindowSize};
 use maelstrom_linux as linux;
 use maelstrom_macro::Config;
 use maelstrom_test_runner::{
+    find_containing_dir,
     ui::{UiMessage, UiWeakSender},
     WaitStatus,
 };
 use maelstrom_util::{process::ExitCode, tty::open_pseudoterminal};
 use regex::Regex;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::process::Command;
 use std::{
     ffi::{CString, OsString},
@@ -126,7 +128,7 @@ impl<StreamT: io::Read> GenericTestArtifactStream<StreamT> {
         let Some(pkg) = self.packages.get_mut(&artifact.package_id) else {
             return Ok(());
         };
-        if artifact.target.is_test() {
+        if artifact.target.is_test() || artifact.target.is_bench() {
             pkg.built_tests.push(artifact);
         } else if artifact.target.is_bin() && !artifact.profile.test {
             if !pkg.unbuilt_non_test_binaries.remove(&artifact.target.name) {
@@ -258,7 +260,7 @@ impl Child {
     }
 }
 
-fn spawn_cargo(args: Vec<OsString>) -> Result<Child> {
+fn spawn_cargo(args: Vec<OsString>, extra_env: Vec<(&str, String)>) -> Result<Child> {
     let window_size = WindowSize {
         columns: 200,
         rows: 3,
@@ -279,6 +281,8 @@ fn spawn_cargo(args: Vec<OsString>) -> Result<Child> {
     argv.push(None);
 
     let env: Vec<_> = std::env::vars()
+        .filter(|(k, _)| !extra_env.iter().any(|(extra_k, _)| k == extra_k))
+        .chain(extra_env.iter().map(|(k, v)| (k.to_string(), v.clone())))
         .map(|(k, v)| CString::new(format!("{k}={v}")).unwrap())
         .collect();
     let mut envp: Vec<_> = env
@@ -341,17 +345,20 @@ fn handle_cargo_tty(tty: linux::OwnedFd, ui: UiWeakSender) -> Result<String> {
     res
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn run_cargo_test(
     color: bool,
     feature_selection_options: &FeatureSelectionOptions,
     compilation_options: &CompilationOptions,
     manifest_options: &ManifestOptions,
+    coverage: bool,
+    bench: bool,
     packages: Vec<&CargoPackage>,
     ui: UiWeakSender,
     log: slog::Logger,
 ) -> Result<(WaitHandle, TestArtifactStream)> {
     let mut args = vec![
-        "test".into(),
+        if bench { "bench" } else { "test" }.into(),
         "--no-run".into(),
         "--message-format=json-render-diagnostics".into(),
         format!("--color={}", if color { "always" } else { "never" }).into(),
@@ -365,7 +372,12 @@ pub fn run_cargo_test(
         args.push(format!("{}@{}", &p.name, &p.version).into());
     }
 
-    let mut child = spawn_cargo(args)?;
+    let mut extra_env = vec![];
+    if coverage {
+        extra_env.push(("CARGO_ENCODED_RUSTFLAGS", encoded_coverage_rustflags()));
+    }
+
+    let mut child = spawn_cargo(args, extra_env)?;
     let stdout = child.stdout.take().unwrap();
     let tty = child.tty.take().unwrap();
     let stderr_handle = thread::spawn(move || handle_cargo_tty(tty, ui));
@@ -401,6 +413,343 @@ pub fn get_cases_from_binary(binary: &Path, filter: &Option<String>) -> Result<V
         .collect())
 }
 
+/// Get the names of the benchmarks in a binary built by `cargo bench`. Both libtest and criterion
+/// list their benchmarks as `<name>: bench` or `<name>: benchmark`. Benchmark names given to
+/// criterion can have spaces in them.
+pub fn get_benchmarks_from_binary(binary: &Path) -> Result<Vec<String>> {
+    let output = Command::new(binary)
+        .arg("--list")
+        .arg("--format")
+        .arg("terse")
+        .output()?;
+    if !output.status.success() {
+        bail!(
+            "listing benchmarks in {} failed with {}\n{}",
+            binary.display(),
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim_end(),
+        );
+    }
+    Ok(parse_benchmark_listing(str::from_utf8(&output.stdout)?))
+}
+
+fn parse_benchmark_listing(listing: &str) -> Vec<String> {
+    listing
+        .lines()
+        .filter_map(|line| {
+            line.strip_suffix(": benchmark")
+                .or_else(|| line.strip_suffix(": bench"))
+        })
+        .map(Into::into)
+        .collect()
+}
+
+/// A single doctest that was built by rustdoc.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Doctest {
+    /// The name rustdoc gives the doctest, like `src/lib.rs - foo::bar (line 10)`.
+    pub name: String,
+    /// The directory containing the doctest's `rust_out` binary, relative to the persist
+    /// directory.
+    pub dir: String,
+    pub ignored: bool,
+    /// Whether the doctest is marked `should_panic`, and so is expected to fail.
+    pub should_panic: bool,
+}
+
+/// The doctests for one package's library target.
+#[derive(Debug)]
+pub struct DoctestArtifact {
+    pub package_id: CargoPackageId,
+    pub target: CargoTarget,
+    pub persist_dir: Utf8PathBuf,
+    pub doctests: Vec<Doctest>,
+}
+
+/// Parse the output of `cargo test --doc` when rustdoc was given `--no-run`. Returns the names of
+/// the doctests along with whether or not they were ignored. Doctests marked `compile_fail` are
+/// left out, since they were already checked by building them.
+fn parse_doctest_listing(output: &str) -> Vec<(String, bool)> {
+    let re = Regex::new(r"^test (.*?)(?: - compile( fail)?| - should panic)? \.\.\. (ok|ignored)$")
+        .unwrap();
+    output
+        .lines()
+        .filter_map(|line| re.captures(line))
+        .filter(|captures| captures.get(2).is_none())
+        .map(|captures| (captures[1].to_owned(), &captures[3] == "ignored"))
+        .collect()
+}
+
+/// Split a doctest name into the source file and line of the doctest's code block.
+fn parse_doctest_name(name: &str) -> Option<(&str, usize)> {
+    let re = Regex::new(r"^(.+?) - .*\(line (\d+)\)$").unwrap();
+    let captures = re.captures(name)?;
+    Some((captures.get(1).unwrap().as_str(), captures[2].parse().ok()?))
+}
+
+/// The directory `--persist-doctests` puts the given doctest's binary in. We assume there is only
+/// one doctest per source line.
+fn doctest_dir(file: &str, line: usize) -> String {
+    let file: String = file
+        .chars()
+        .map(|c| {
+            if matches!(c, '/' | '\\' | '.') {
+                '_'
+            } else {
+                c
+            }
+        })
+        .collect();
+    format!("{file}_{line}_0")
+}
+
+/// Get the attributes of a code block from its opening fence, like `should_panic` in
+/// "/// ```should_panic".
+fn doctest_fence_attributes(line: &str) -> Vec<&str> {
+    let Some(pos) = line.find("```").or_else(|| line.find("~~~")) else {
+        return vec![];
+    };
+    line[pos..]
+        .trim_start_matches(['`', '~'])
+        .split(|c: char| c == ',' || c == '{' || c == '}' || c.is_whitespace())
+        .map(|attribute| attribute.trim_start_matches('.'))
+        .filter(|attribute| !attribute.is_empty())
+        .collect()
+}
+
+/// Figure out which of the listed doctests should be run. Doctests marked `no_run` are left out.
+/// Doctests marked `should_panic` are run, but are expected to fail.
+fn select_doctests(
+    listing: Vec<(String, bool)>,
+    read_line: impl Fn(&str, usize) -> Option<String>,
+) -> Vec<Doctest> {
+    listing
+        .into_iter()
+        .filter_map(|(name, ignored)| {
+            let (file, line) = parse_doctest_name(&name)?;
+            let fence = read_line(file, line).unwrap_or_default();
+            let attributes = doctest_fence_attributes(&fence);
+            if attributes.contains(&"no_run") {
+                return None;
+            }
+            Some(Doctest {
+                dir: doctest_dir(file, line),
+                ignored,
+                should_panic: attributes.contains(&"should_panic"),
+                name,
+            })
+        })
+        .collect()
+}
+
+/// Whether the output of `rustc -V` is from a toolchain that allows unstable features.
+fn is_nightly_version(version: &str) -> bool {
+    version
+        .split_whitespace()
+        .nth(1)
+        .is_some_and(|version| version.ends_with("-nightly") || version.ends_with("-dev"))
+}
+
+fn rustc_is_nightly() -> bool {
+    let rustc = std::env::var_os("RUSTC").unwrap_or("rustc".into());
+    Command::new(rustc).arg("-V").output().is_ok_and(|output| {
+        output.status.success() && str::from_utf8(&output.stdout).is_ok_and(is_nightly_version)
+    })
+}
+
+fn rustdoc_supports_merge_doctests(rustc_bootstrap: bool) -> bool {
+    let rustdoc = std::env::var_os("RUSTDOC").unwrap_or("rustdoc".into());
+    let mut command = Command::new(rustdoc);
+    command.args(["-Zunstable-options", "--help"]);
+    if rustc_bootstrap {
+        command.env("RUSTC_BOOTSTRAP", "1");
+    }
+    command.output().is_ok_and(|output| {
+        str::from_utf8(&output.stdout).is_ok_and(|s| s.contains("--merge-doctests"))
+    })
+}
+
+/// Get the flags the user has provided in either the encoded or the plain environment variable.
+/// Cargo gives the encoded variable precedence.
+fn existing_flags(encoded_var: &str, var: &str) -> Vec<String> {
+    match std::env::var(encoded_var) {
+        Ok(flags) => flags
+            .split('\x1f')
+            .filter(|f| !f.is_empty())
+            .map(ToOwned::to_owned)
+            .collect(),
+        Err(_) => std::env::var(var)
+            .unwrap_or_default()
+            .split_whitespace()
+            .map(ToOwned::to_owned)
+            .collect(),
+    }
+}
+
+/// The flags to pass to rustc when collecting code coverage, encoded the way
+/// `CARGO_ENCODED_RUSTFLAGS` wants. We keep any flags the user has provided.
+fn encoded_coverage_rustflags() -> String {
+    let mut flags = existing_flags("CARGO_ENCODED_RUSTFLAGS", "RUSTFLAGS");
+    flags.push("-Cinstrument-coverage".into());
+    flags.join("\x1f")
+}
+
+/// The flags to pass to rustdoc, encoded the way `CARGO_ENCODED_RUSTDOCFLAGS` wants. We keep any
+/// flags the user has provided.
+fn encoded_rustdoc_flags(persist_dir: &Utf8Path, merge_doctests: bool, coverage: bool) -> String {
+    let mut flags = existing_flags("CARGO_ENCODED_RUSTDOCFLAGS", "RUSTDOCFLAGS");
+    flags.extend([
+        "-Zunstable-options".into(),
+        "--persist-doctests".into(),
+        persist_dir.to_string(),
+        "--no-run".into(),
+    ]);
+    if merge_doctests {
+        flags.push("--merge-doctests=no".into());
+    }
+    if coverage {
+        flags.push("-Cinstrument-coverage".into());
+    }
+    flags.join("\x1f")
+}
+
+/// Build the doctests for the given packages one at a time, yielding an artifact for each package
+/// with at least one doctest to run.
+///
+/// We use rustdoc's unstable `--persist-doctests` and `--no-run` flags to keep the compiled
+/// doctests around without running them. Each package gets its own directory to persist into, so
+/// that doctests from different packages with the same file name don't collide. These flags need
+/// a nightly toolchain, unless the user has allowed us to set `RUSTC_BOOTSTRAP=1`.
+pub struct DoctestArtifactStream {
+    log: slog::Logger,
+    args: Vec<OsString>,
+    packages: std::vec::IntoIter<CargoPackage>,
+    doctests_dir: PathBuf,
+    allow_rustc_bootstrap: bool,
+    rustc_bootstrap: Option<bool>,
+    merge_doctests: Option<bool>,
+    coverage: bool,
+}
+
+impl DoctestArtifactStream {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        feature_selection_options: &FeatureSelectionOptions,
+        compilation_options: &CompilationOptions,
+        manifest_options: &ManifestOptions,
+        coverage: bool,
+        allow_rustc_bootstrap: bool,
+        packages: Vec<&CargoPackage>,
+        doctests_dir: PathBuf,
+        log: slog::Logger,
+    ) -> Self {
+        let mut args: Vec<OsString> = vec!["test".into(), "--doc".into(), "--color=never".into()];
+        args.extend(feature_selection_options.iter().map(|a| a.into()));
+        args.extend(compilation_options.iter());
+        args.extend(manifest_options.iter());
+        let packages: Vec<_> = packages
+            .into_iter()
+            .filter(|p| p.targets.iter().any(|t| t.doctest))
+            .cloned()
+            .collect();
+        Self {
+            log,
+            args,
+            packages: packages.into_iter(),
+            doctests_dir,
+            allow_rustc_bootstrap,
+            rustc_bootstrap: None,
+            merge_doctests: None,
+            coverage,
+        }
+    }
+
+    /// Whether we need to set `RUSTC_BOOTSTRAP=1` to use unstable rustdoc flags. Returns an error
+    /// if we do, but the user hasn't allowed it.
+    fn rustc_bootstrap(&mut self) -> Result<bool> {
+        let rustc_bootstrap = *self
+            .rustc_bootstrap
+            .get_or_insert_with(|| !rustc_is_nightly());
+        if rustc_bootstrap && !self.allow_rustc_bootstrap {
+            bail!(
+                "building doctests requires unstable rustdoc features, which need a nightly \
+                toolchain; use a nightly toolchain, or set `doctests-rustc-bootstrap` to have \
+                them enabled on this toolchain with `RUSTC_BOOTSTRAP=1`"
+            );
+        }
+        Ok(rustc_bootstrap)
+    }
+
+    fn build(&mut self, package: &CargoPackage) -> Result<Option<DoctestArtifact>> {
+        let rustc_bootstrap = self.rustc_bootstrap()?;
+        let target = package.targets.iter().find(|t| t.doctest).unwrap();
+        let persist_dir = Utf8PathBuf::try_from(self.doctests_dir.join(&package.name))?;
+        if persist_dir.exists() {
+            std::fs::remove_dir_all(&persist_dir)?;
+        }
+        std::fs::create_dir_all(&persist_dir)?;
+
+        let merge_doctests = *self
+            .merge_doctests
+            .get_or_insert_with(|| rustdoc_supports_merge_doctests(rustc_bootstrap));
+        slog::debug!(self.log, "building doctests"; "package" => %package.name);
+        let mut command = Command::new("cargo");
+        command
+            .args(&self.args)
+            .arg("--package")
+            .arg(format!("{}@{}", &package.name, &package.version))
+            .env(
+                "CARGO_ENCODED_RUSTDOCFLAGS",
+                encoded_rustdoc_flags(&persist_dir, merge_doctests, self.coverage),
+            );
+        if rustc_bootstrap {
+            command.env("RUSTC_BOOTSTRAP", "1");
+        }
+        if self.coverage {
+            // Use the same flags as the main build so the library isn't rebuilt.
+            command.env("CARGO_ENCODED_RUSTFLAGS", encoded_coverage_rustflags());
+        }
+        let output = command.output().context("building doctests")?;
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        if !output.status.success() {
+            bail!(
+                "building doctests for package {} failed\n{}{}",
+                package.name,
+                filter_build_output(String::from_utf8_lossy(&output.stderr).into_owned()),
+                stdout,
+            );
+        }
+
+        let package_dir = package.manifest_path.parent().unwrap();
+        let doctests = select_doctests(parse_doctest_listing(&stdout), |file, line| {
+            let contents = std::fs::read_to_string(package_dir.join(file)).ok()?;
+            contents.lines().nth(line - 1).map(ToOwned::to_owned)
+        });
+        if doctests.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(DoctestArtifact {
+            package_id: package.id.clone(),
+            target: target.clone(),
+            persist_dir,
+            doctests,
+        }))
+    }
+}
+
+impl Iterator for DoctestArtifactStream {
+    type Item = Result<DoctestArtifact>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let package = self.packages.next()?;
+            if let Some(result) = self.build(&package).transpose() {
+                return Some(result);
+            }
+        }
+    }
+}
+
 #[derive(Config, Debug, Default)]
 pub struct FeatureSelectionOptions {
     /// Comma-separated list of features to activate.
@@ -532,6 +881,50 @@ pub fn read_metadata(
     Ok(cargo_metadata)
 }
 
+/// Find the workspace packages which could be impacted by changes to the given paths.
+///
+/// A package is impacted if it contains a changed path, or if it depends, directly or indirectly,
+/// on an impacted package through a path dependency. Returns `None` if the changes could impact
+/// every package. This happens when `Cargo.lock` changes, or when a changed path isn't in any
+/// package.
+pub fn find_impacted_packages(
+    packages: &[&CargoPackage],
+    changed_paths: &BTreeSet<PathBuf>,
+) -> Option<BTreeSet<CargoPackageId>> {
+    let package_dirs: Vec<(&Path, &CargoPackage)> = packages
+        .iter()
+        .filter_map(|p| Some((p.manifest_path.parent()?.as_std_path(), *p)))
+        .collect();
+
+    let mut impacted = BTreeSet::new();
+    let mut to_visit = vec![];
+    for path in changed_paths {
+        if path.file_name().is_some_and(|name| name == "Cargo.lock") {
+            return None;
+        }
+        let package = find_containing_dir(path, package_dirs.iter().copied())?;
+        if impacted.insert(&package.id) {
+            to_visit.push(package);
+        }
+    }
+
+    // Walk the reverse dependency graph, marking everything that depends on an impacted package.
+    while let Some(dependency) = to_visit.pop() {
+        let dependency_dir = dependency.manifest_path.parent();
+        for package in packages {
+            let depends_on = package
+                .dependencies
+                .iter()
+                .any(|d| d.path.is_some() && d.path.as_deref() == dependency_dir);
+            if depends_on && impacted.insert(&package.id) {
+                to_visit.push(*package);
+            }
+        }
+    }
+
+    Some(impacted.into_iter().cloned().collect())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -569,6 +962,15 @@ mod tests {
         })
     }
 
+    fn benchmark(package_id: &str, name: &str) -> serde_json::Value {
+        // Benchmarks that use criterion don't use the libtest harness, so they aren't built as
+        // tests.
+        let mut benchmark = integration_test(package_id, name);
+        benchmark["target"]["kind"] = json!(["bench"]);
+        benchmark["profile"]["test"] = json!(false);
+        benchmark
+    }
+
     fn package_test_binary(package_id: &str, name: &str) -> serde_json::Value {
         let profile = json!({
             "opt_level": "3",
@@ -688,6 +1090,19 @@ mod tests {
         assert!(stream.build_done);
     }
 
+    #[test]
+    fn test_artifact_stream_benchmark_before_required_binary() {
+        let mut stream = build_test_artifact_stream(
+            hashmap! { "foo" => hashset! { "foo-bin" } },
+            vec![benchmark("foo", "bench"), package_binary("foo", "foo-bin")],
+        );
+        assert_num_reads(&mut stream, 1 /* reads */, 0 /* num_ready */);
+        assert_num_reads(&mut stream, 1 /* reads */, 1 /* num_ready */); // bench
+
+        stream.read_next_artifact().unwrap();
+        assert!(stream.build_done);
+    }
+
     #[test]
     fn test_artifact_stream_integration_test_after_required_binary() {
         let mut stream = build_test_artifact_stream(
@@ -997,4 +1412,288 @@ mod tests {
             ]),
         );
     }
+
+    fn workspace_package(name: &str, path_dependencies: &[&str]) -> CargoPackage {
+        let dependencies: Vec<_> = path_dependencies
+            .iter()
+            .map(|dep| {
+                json!({
+                    "name": dep,
+                    "req": "*",
+                    "kind": null,
+                    "optional": false,
+                    "uses_default_features": true,
+                    "features": [],
+                    "path": format!("/workspace/crates/{dep}"),
+                })
+            })
+            .collect();
+        serde_json::from_value(json!({
+            "name": name,
+            "version": "0.1.0",
+            "id": name,
+            "dependencies": dependencies,
+            "targets": [],
+            "features": {},
+            "manifest_path": format!("/workspace/crates/{name}/Cargo.toml"),
+        }))
+        .unwrap()
+    }
+
+    #[track_caller]
+    fn find_impacted_packages_test<'a>(
+        changed_paths: impl IntoIterator<Item = &'a str>,
+        expected: Option<Vec<&str>>,
+    ) {
+        let packages = [
+            workspace_package("base", &[]),
+            workspace_package("util", &["base"]),
+            workspace_package("client", &["base", "util"]),
+            workspace_package("worker", &["base"]),
+            workspace_package("nested", &[]),
+            workspace_package("other", &[]),
+        ];
+        let mut packages = packages.to_vec();
+        packages[4].manifest_path = "/workspace/crates/worker/nested/Cargo.toml".into();
+        let packages: Vec<_> = packages.iter().collect();
+        let changed_paths = changed_paths.into_iter().map(PathBuf::from).collect();
+        let expected = expected.map(|expected| {
+            expected
+                .into_iter()
+                .map(|repr| CargoPackageId { repr: repr.into() })
+                .collect()
+        });
+        assert_eq!(find_impacted_packages(&packages, &changed_paths), expected);
+    }
+
+    #[test]
+    fn find_impacted_packages_no_changes() {
+        find_impacted_packages_test([], Some(vec![]));
+    }
+
+    #[test]
+    fn find_impacted_packages_leaf() {
+        find_impacted_packages_test(
+            ["/workspace/crates/client/src/lib.rs"],
+            Some(vec!["client"]),
+        );
+    }
+
+    #[test]
+    fn find_impacted_packages_transitive_dependents() {
+        find_impacted_packages_test(
+            ["/workspace/crates/base/src/lib.rs"],
+            Some(vec!["base", "client", "util", "worker"]),
+        );
+    }
+
+    #[test]
+    fn find_impacted_packages_nested_package() {
+        find_impacted_packages_test(
+            ["/workspace/crates/worker/nested/src/lib.rs"],
+            Some(vec!["nested"]),
+        );
+    }
+
+    #[test]
+    fn find_impacted_packages_multiple_changes() {
+        find_impacted_packages_test(
+            [
+                "/workspace/crates/util/Cargo.toml",
+                "/workspace/crates/other/tests/foo.rs",
+            ],
+            Some(vec!["client", "other", "util"]),
+        );
+    }
+
+    #[test]
+    fn find_impacted_packages_outside_of_packages() {
+        find_impacted_packages_test(
+            [
+                "/workspace/crates/client/src/lib.rs",
+                "/workspace/rust-toolchain.toml",
+            ],
+            None,
+        );
+    }
+
+    #[test]
+    fn find_impacted_packages_cargo_lock() {
+        find_impacted_packages_test(["/workspace/crates/base/Cargo.lock"], None);
+    }
+
+    #[test]
+    fn parse_benchmark_listing_libtest_and_criterion() {
+        let output = "\
+            tests::it_works: test\n\
+            tests::bench_add: bench\n\
+            fib 20: benchmark\n\
+            group/with param: benchmark\n\
+        ";
+        assert_eq!(
+            parse_benchmark_listing(output),
+            vec![
+                "tests::bench_add".to_owned(),
+                "fib 20".into(),
+                "group/with param".into(),
+            ]
+        );
+    }
+
+    #[test]
+    fn get_benchmarks_from_binary_failure() {
+        let err = get_benchmarks_from_binary(Path::new("false")).unwrap_err();
+        assert!(
+            err.to_string()
+                .starts_with("listing benchmarks in false failed with exit status: 1"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn is_nightly_version_basic() {
+        assert!(is_nightly_version(
+            "rustc 1.85.0-nightly (5f23ef7d3 2024-12-20)\n"
+        ));
+        assert!(is_nightly_version("rustc 1.86.0-dev\n"));
+        assert!(!is_nightly_version("rustc 1.84.0 (9fc6b4312 2025-01-07)\n"));
+        assert!(!is_nightly_version(
+            "rustc 1.85.0-beta.1 (e30eefff4 2024-11-29)\n"
+        ));
+        assert!(!is_nightly_version(""));
+    }
+
+    #[test]
+    fn parse_doctest_listing_basic() {
+        let output = "\n\
+            running 5 tests\n\
+            test src/lib.rs - add (line 11) - compile ... ok\n\
+            test src/lib.rs - add (line 15) ... ignored\n\
+            test src/lib.rs - add (line 3) - compile ... ok\n\
+            test src/lib.rs - add (line 7) - compile fail ... ok\n\
+            test src/lib.rs - m::f (line 21) - compile ... ok\n\
+            \n\
+            test result: ok. 4 passed; 0 failed; 1 ignored; 0 measured; 0 filtered out\n\
+        ";
+        assert_eq!(
+            parse_doctest_listing(output),
+            vec![
+                ("src/lib.rs - add (line 11)".into(), false),
+                ("src/lib.rs - add (line 15)".into(), true),
+                ("src/lib.rs - add (line 3)".into(), false),
+                ("src/lib.rs - m::f (line 21)".into(), false),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_doctest_name_item() {
+        assert_eq!(
+            parse_doctest_name("src/lib.rs - foo::Bar<'a>::baz (line 10)"),
+            Some(("src/lib.rs", 10))
+        );
+    }
+
+    #[test]
+    fn parse_doctest_name_crate_level() {
+        assert_eq!(
+            parse_doctest_name("src/lib.rs - (line 3)"),
+            Some(("src/lib.rs", 3))
+        );
+    }
+
+    #[test]
+    fn parse_doctest_name_bad() {
+        assert_eq!(parse_doctest_name("src/lib.rs - foo"), None);
+    }
+
+    #[test]
+    fn doctest_dir_replaces_separators() {
+        assert_eq!(doctest_dir("src/foo/bar.rs", 12), "src_foo_bar_rs_12_0");
+    }
+
+    #[test]
+    fn doctest_fence_attributes_basic() {
+        assert_eq!(doctest_fence_attributes("/// ```"), Vec::<&str>::new());
+        assert_eq!(doctest_fence_attributes("//! ```rust"), vec!["rust"]);
+        assert_eq!(
+            doctest_fence_attributes("    /// ```rust,no_run"),
+            vec!["rust", "no_run"]
+        );
+        assert_eq!(
+            doctest_fence_attributes("/// ~~~{.should_panic edition2021}"),
+            vec!["should_panic", "edition2021"]
+        );
+        assert_eq!(
+            doctest_fence_attributes("no fence here"),
+            Vec::<&str>::new()
+        );
+    }
+
+    #[test]
+    fn select_doctests_basic() {
+        let listing = vec![
+            ("src/lib.rs - a (line 1)".into(), false),
+            ("src/lib.rs - b (line 2)".into(), false),
+            ("src/lib.rs - c (line 3)".into(), false),
+            ("src/lib.rs - d (line 4)".into(), true),
+            ("unparsable".into(), false),
+        ];
+        let lines = hashmap! {
+            1 => "/// ```",
+            2 => "/// ```no_run",
+            3 => "/// ```should_panic",
+            4 => "/// ```ignore",
+        };
+        let doctests = select_doctests(listing, |file, line| {
+            assert_eq!(file, "src/lib.rs");
+            lines.get(&line).map(|l| l.to_string())
+        });
+        assert_eq!(
+            doctests,
+            vec![
+                Doctest {
+                    name: "src/lib.rs - a (line 1)".into(),
+                    dir: "src_lib_rs_1_0".into(),
+                    ignored: false,
+                    should_panic: false,
+                },
+                Doctest {
+                    name: "src/lib.rs - c (line 3)".into(),
+                    dir: "src_lib_rs_3_0".into(),
+                    ignored: false,
+                    should_panic: true,
+                },
+                Doctest {
+                    name: "src/lib.rs - d (line 4)".into(),
+                    dir: "src_lib_rs_4_0".into(),
+                    ignored: true,
+                    should_panic: false,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn encoded_rustdoc_flags_appends() {
+        let flags = encoded_rustdoc_flags(Utf8Path::new("/tmp/persist dir"), true, false);
+        assert!(flags.ends_with(
+            "-Zunstable-options\x1f--persist-doctests\x1f/tmp/persist dir\x1f--no-run\x1f\
+            --merge-doctests=no"
+        ));
+    }
+
+    #[test]
+    fn encoded_rustdoc_flags_coverage() {
+        let flags = encoded_rustdoc_flags(Utf8Path::new("/tmp/persist"), false, true);
+        assert!(flags.ends_with(
+            "-Zunstable-options\x1f--persist-doctests\x1f/tmp/persist\x1f--no-run\x1f\
+            -Cinstrument-coverage"
+        ));
+    }
+
+    #[test]
+    fn encoded_coverage_rustflags_appends() {
+        assert!(encoded_coverage_rustflags().ends_with("-Cinstrument-coverage"));
+    }
 }
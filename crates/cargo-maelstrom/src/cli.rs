@@ -11,7 +11,7 @@ pub struct ExtraCommandLineOptions {
 }
 
 #[derive(Args)]
-#[group(multiple = false)]
+#[group(multiple = false, conflicts_with = "export_image")]
 #[command(next_help_heading = "List Options")]
 pub struct ListOptions {
     #[arg(
@@ -15,13 +15,34 @@ pub struct Config {
     #[config(flatten, next_help_heading = "Manifest Config Options")]
     pub cargo_manifest_options: ManifestOptions,
 
+    /// Also build and run doctests. This requires using unstable rustdoc features.
+    #[config(flag, next_help_heading = "Test Binary Options")]
+    pub doctests: bool,
+
+    /// When building doctests with a stable or beta toolchain, set `RUSTC_BOOTSTRAP=1` to enable
+    /// the unstable rustdoc features they require. Without this, doctests require a nightly
+    /// toolchain.
+    #[config(flag)]
+    pub doctests_rustc_bootstrap: bool,
+
+    /// Build the tests with coverage instrumentation and collect the coverage data from every test
+    /// job. Once the tests have run, generate an lcov and HTML report using llvm-profdata and
+    /// llvm-cov, if they are installed.
+    #[config(flag)]
+    pub coverage: bool,
+
+    /// Build the benchmarks with `cargo bench` and run them instead of the tests. Each benchmark
+    /// runs by itself on a worker. The results are saved and compared to those of the previous
+    /// benchmark run.
+    #[config(flag)]
+    pub bench: bool,
+
     /// Extra arguments to pass to the test binary. See the help text for a test binary to see what
     /// it accepts.
     #[config(
         var_arg,
         value_name = "EXTRA-TEST-BINARY-ARGS",
-        default = r#""no args""#,
-        next_help_heading = "Test Binary Options"
+        default = r#""no args""#
     )]
     pub extra_test_binary_args: Vec<String>,
 }
@@ -0,0 +1,298 @@
+//! Collect code coverage data from test jobs and turn it into a report.
+//!
+//! When collecting coverage, everything is built with `-C instrument-coverage` and each test job
+//! writes its raw profile into [`COVERAGE_DIR`]. The raw profiles come back with the jobs' results,
+//! whether the jobs ran locally or on remote workers, and are saved in
+//! `<maelstrom-target-dir>/coverage/profraw`. Once all of the tests have run, we merge the profiles
+//! and generate lcov and HTML reports using `llvm-profdata` and `llvm-cov`, if we can find them.
+
+use anyhow::{bail, Context as _, Result};
+use maelstrom_base::{CapturedFile, Utf8PathBuf};
+use maelstrom_test_runner::{
+    ui::{UiMessage, UiSender},
+    COVERAGE_DIR,
+};
+use maelstrom_util::{fs::Fs, root::RootBuf};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    ffi::OsString,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+};
+
+/// The directory coverage data and reports are written to is `<maelstrom-target-dir>/coverage`.
+pub struct CoverageDir;
+
+/// The directory raw profiles are saved to is `<coverage-dir>/profraw`.
+struct ProfrawDir;
+
+/// Source files matching this aren't part of the project, so we leave them out of the reports.
+const IGNORE_FILENAME_REGEX: &str = r"/\.cargo/(registry|git)/|^/rustc/";
+
+/// The test binaries we've run, which `llvm-cov` needs to interpret the profiles. These are added
+/// as artifacts are built.
+pub type CoverageObjects = Arc<Mutex<BTreeSet<Utf8PathBuf>>>;
+
+pub struct Coverage {
+    dir: RootBuf<CoverageDir>,
+    next_file_id: AtomicU64,
+    objects: CoverageObjects,
+}
+
+impl Coverage {
+    pub fn new(dir: RootBuf<CoverageDir>) -> Self {
+        Self {
+            dir,
+            next_file_id: AtomicU64::new(0),
+            objects: Default::default(),
+        }
+    }
+
+    /// The environment variables to set for each test job.
+    pub fn environment() -> BTreeMap<String, String> {
+        BTreeMap::from([(
+            "LLVM_PROFILE_FILE".into(),
+            format!("{COVERAGE_DIR}/%p-%m.profraw"),
+        )])
+    }
+
+    pub fn objects(&self) -> CoverageObjects {
+        self.objects.clone()
+    }
+
+    fn profraw_dir(&self) -> RootBuf<ProfrawDir> {
+        self.dir.join("profraw")
+    }
+
+    /// Remove any data from a previous run.
+    pub fn reset(&self) -> Result<()> {
+        let fs = Fs::new();
+        let profraw_dir = self.profraw_dir();
+        if fs.exists(&profraw_dir) {
+            fs.remove_dir_all(&profraw_dir)?;
+        }
+        fs.create_dir_all(&profraw_dir)?;
+        self.objects.lock().unwrap().clear();
+        Ok(())
+    }
+
+    /// Save the raw profiles from a test job. Every job writes its profiles into the same
+    /// directory in its container, so we give each file a unique prefix.
+    pub fn save(&self, files: Vec<CapturedFile>) -> Result<()> {
+        let fs = Fs::new();
+        let profraw_dir = self.profraw_dir();
+        for file in files {
+            let Some(file_name) = file.path.file_name() else {
+                continue;
+            };
+            let id = self.next_file_id.fetch_add(1, Ordering::Relaxed);
+            fs.write(
+                profraw_dir.join::<()>(format!("{id}-{file_name}")),
+                file.contents,
+            )?;
+        }
+        Ok(())
+    }
+
+    fn profraw_files(&self) -> Result<Vec<PathBuf>> {
+        let fs = Fs::new();
+        let profraw_dir = self.profraw_dir();
+        if !fs.exists(&profraw_dir) {
+            return Ok(vec![]);
+        }
+        let mut files = fs
+            .read_dir(&profraw_dir)?
+            .map(|entry| Ok(entry?.path()))
+            .collect::<Result<Vec<_>>>()?;
+        files.sort();
+        Ok(files)
+    }
+
+    /// Merge the raw profiles and generate the reports, telling the user where they are.
+    pub fn report(&self, ui: &UiSender) -> Result<()> {
+        let profraw_files = self.profraw_files()?;
+        if profraw_files.is_empty() {
+            ui.send(UiMessage::CollectionOutput(
+                "no code coverage data was collected".into(),
+            ));
+            return Ok(());
+        }
+
+        let (Some(llvm_profdata), Some(llvm_cov)) =
+            (find_llvm_tool("llvm-profdata"), find_llvm_tool("llvm-cov"))
+        else {
+            ui.send(UiMessage::CollectionOutput(format!(
+                "raw code coverage data saved in {}\n\
+                install llvm-profdata and llvm-cov, for example with \
+                `rustup component add llvm-tools`, to generate a coverage report",
+                self.profraw_dir().display()
+            )));
+            return Ok(());
+        };
+
+        let profdata = self.dir.join::<()>("coverage.profdata");
+        run_tool(
+            Command::new(llvm_profdata)
+                .arg("merge")
+                .arg("-sparse")
+                .args(&profraw_files)
+                .arg("-o")
+                .arg(profdata.as_os_str()),
+        )?;
+
+        let objects = llvm_cov_object_args(&self.objects.lock().unwrap());
+        let common_args: Vec<OsString> = [
+            format!("-instr-profile={}", profdata.display()),
+            format!("-ignore-filename-regex={IGNORE_FILENAME_REGEX}"),
+        ]
+        .into_iter()
+        .map(Into::into)
+        .chain(objects)
+        .collect();
+
+        let lcov = self.dir.join::<()>("lcov.info");
+        let lcov_contents = run_tool(
+            Command::new(&llvm_cov)
+                .arg("export")
+                .arg("-format=lcov")
+                .args(&common_args),
+        )?;
+        Fs::new().write(&lcov, lcov_contents)?;
+
+        let html_dir = self.dir.join::<()>("html");
+        run_tool(
+            Command::new(&llvm_cov)
+                .arg("show")
+                .arg("-format=html")
+                .arg(format!("-output-dir={}", html_dir.display()))
+                .args(&common_args),
+        )?;
+
+        ui.send(UiMessage::CollectionOutput(format!(
+            "code coverage report written to {} and {}",
+            lcov.display(),
+            html_dir.join::<()>("index.html").display()
+        )));
+        Ok(())
+    }
+}
+
+/// `llvm-cov` takes the first object as a positional argument, and the rest with `-object`.
+fn llvm_cov_object_args(objects: &BTreeSet<Utf8PathBuf>) -> Vec<OsString> {
+    let mut args = vec![];
+    for (i, object) in objects.iter().enumerate() {
+        if i > 0 {
+            args.push("-object".into());
+        }
+        args.push(object.as_str().into());
+    }
+    args
+}
+
+fn run_tool(command: &mut Command) -> Result<Vec<u8>> {
+    let program = command.get_program().to_string_lossy().into_owned();
+    let output = command
+        .output()
+        .with_context(|| format!("running {program}"))?;
+    if !output.status.success() {
+        bail!(
+            "{program} failed\n{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+    Ok(output.stdout)
+}
+
+fn rustc_output(arg: &str) -> Option<String> {
+    let rustc = std::env::var("RUSTC").unwrap_or_else(|_| "rustc".into());
+    let output = Command::new(rustc).arg(arg).output().ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8(output.stdout).ok())
+        .flatten()
+}
+
+/// Find an LLVM tool. We prefer the one installed with `rustup component add llvm-tools`, since
+/// it will match the LLVM version used by rustc, but fall back to one in the `PATH`.
+fn find_llvm_tool(name: &str) -> Option<PathBuf> {
+    let sysroot = rustc_output("--print=sysroot");
+    let host = rustc_output("-vV").and_then(|version| {
+        version
+            .lines()
+            .find_map(|line| line.strip_prefix("host: ").map(ToOwned::to_owned))
+    });
+    if let (Some(sysroot), Some(host)) = (sysroot, host) {
+        let path = Path::new(sysroot.trim())
+            .join("lib/rustlib")
+            .join(host)
+            .join("bin")
+            .join(name);
+        if path.is_file() {
+            return Some(path);
+        }
+    }
+    Command::new(name)
+        .arg("--version")
+        .output()
+        .is_ok_and(|output| output.status.success())
+        .then(|| name.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use maelstrom_test::utf8_path_buf;
+
+    #[test]
+    fn save_gives_files_unique_names() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let coverage = Coverage::new(RootBuf::new(temp_dir.path().to_owned()));
+        coverage.reset().unwrap();
+        for _ in 0..2 {
+            coverage
+                .save(vec![CapturedFile {
+                    path: utf8_path_buf!("/maelstrom-coverage/1-abc.profraw"),
+                    contents: Box::new(*b"data"),
+                }])
+                .unwrap();
+        }
+        assert_eq!(
+            coverage.profraw_files().unwrap(),
+            vec![
+                temp_dir.path().join("profraw/0-1-abc.profraw"),
+                temp_dir.path().join("profraw/1-1-abc.profraw"),
+            ]
+        );
+    }
+
+    #[test]
+    fn reset_removes_old_files() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let coverage = Coverage::new(RootBuf::new(temp_dir.path().to_owned()));
+        coverage.reset().unwrap();
+        coverage
+            .save(vec![CapturedFile {
+                path: utf8_path_buf!("/maelstrom-coverage/a.profraw"),
+                contents: Box::new(*b"data"),
+            }])
+            .unwrap();
+        coverage.objects().lock().unwrap().insert("/bin".into());
+        coverage.reset().unwrap();
+        assert_eq!(coverage.profraw_files().unwrap(), Vec::<PathBuf>::new());
+        assert!(coverage.objects().lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn llvm_cov_object_args_first_is_positional() {
+        let objects = BTreeSet::from([utf8_path_buf!("/a"), utf8_path_buf!("/b")]);
+        assert_eq!(
+            llvm_cov_object_args(&objects),
+            vec![OsString::from("/a"), "-object".into(), "/b".into()]
+        );
+    }
+}
@@ -1,16 +1,20 @@
 mod alternative_mains;
+mod benchmarks;
 pub mod cargo;
 pub mod cli;
 pub mod config;
+mod coverage;
 mod pattern;
 
 pub use maelstrom_test_runner::Logger;
 
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, bail, Result};
+use benchmarks::Benchmarks;
 use cargo_metadata::Target as CargoTarget;
 use cli::ExtraCommandLineOptions;
 use config::Config;
-use maelstrom_base::{Timeout, Utf8Path, Utf8PathBuf};
+use coverage::{Coverage, CoverageObjects};
+use maelstrom_base::{CapturedFile, Timeout, Utf8Path, Utf8PathBuf};
 use maelstrom_client::{
     shared_library_dependencies_layer_spec,
     spec::{LayerSpec, PathsLayerSpec, PrefixOptions},
@@ -22,7 +26,7 @@ use maelstrom_test_runner::{
     run_app_with_ui_multithreaded,
     ui::{Ui, UiSender},
     BuildDir, CollectTests, ListAction, LoggingOutput, MainAppDeps, NoCaseMetadata, TestArtifact,
-    TestArtifactKey, TestFilter, TestPackage, TestPackageId, Wait, WaitStatus,
+    TestArtifactKey, TestFilter, TestImpact, TestPackage, TestPackageId, Wait, WaitStatus,
 };
 use maelstrom_util::{
     config::common::{ArtifactTransferStrategy, BrokerAddr, CacheSize, InlineLimit, Slots},
@@ -32,7 +36,12 @@ use maelstrom_util::{
     template::TemplateVars,
 };
 use pattern::ArtifactKind;
-use std::{fmt, io, path::Path, str::FromStr};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt, io,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
 
 pub const TEST_METADATA_FILE_NAME: &str = "cargo-maelstrom.toml";
 pub const DEFAULT_TEST_METADATA_CONTENTS: &str = include_str!("default-test-metadata.toml");
@@ -92,9 +101,16 @@ impl DefaultMainAppDeps {
             artifact_transfer_strategy,
             log.clone(),
         )?;
+        let maelstrom_target_dir = target_dir.as_ref().join::<MaelstromTargetDir>("maelstrom");
         Ok((
             Self {
-                test_collector: CargoTestCollector { log, packages },
+                test_collector: CargoTestCollector {
+                    log,
+                    packages,
+                    doctests_dir: maelstrom_target_dir.join("doctests"),
+                    coverage: Coverage::new(maelstrom_target_dir.join("coverage")),
+                    benchmarks: Benchmarks::new(maelstrom_target_dir.join("benchmarks")),
+                },
                 target_dir: target_dir.as_ref().to_owned(),
             },
             client,
@@ -217,22 +233,67 @@ struct CargoOptions {
     compilation_options: cargo::CompilationOptions,
     manifest_options: cargo::ManifestOptions,
     extra_test_binary_args: Vec<String>,
+    doctests: bool,
+    doctests_rustc_bootstrap: bool,
+    coverage: bool,
+    bench: bool,
 }
 
 struct CargoTestCollector {
     log: slog::Logger,
     packages: Vec<CargoPackage>,
+    doctests_dir: RootBuf<DoctestsDir>,
+    coverage: Coverage,
+    benchmarks: Benchmarks,
 }
 
+/// The directory doctests are persisted to is `<maelstrom-target-dir>/doctests`.
+pub struct DoctestsDir;
+
 #[derive(Debug)]
-struct CargoTestArtifact {
-    artifact: cargo_metadata::Artifact,
-    extra_test_binary_args: Vec<String>,
+enum CargoTestArtifact {
+    Binary {
+        artifact: cargo_metadata::Artifact,
+        extra_test_binary_args: Vec<String>,
+        /// The binary was built by `cargo bench`, and we run its benchmarks instead of its tests.
+        bench: bool,
+    },
+    Doctests(cargo::DoctestArtifact),
 }
 
 impl CargoTestArtifact {
     fn utf8_path(&self) -> &Utf8Path {
-        self.artifact.executable.as_ref().unwrap().as_ref()
+        match self {
+            Self::Binary { artifact, .. } => artifact.executable.as_ref().unwrap().as_ref(),
+            Self::Doctests(doctests) => &doctests.persist_dir,
+        }
+    }
+
+    fn target(&self) -> &CargoTarget {
+        match self {
+            Self::Binary { artifact, .. } => &artifact.target,
+            Self::Doctests(doctests) => &doctests.target,
+        }
+    }
+
+    /// The binaries that run the test cases.
+    fn binary_paths(&self) -> Vec<Utf8PathBuf> {
+        match self {
+            Self::Binary { .. } => vec![self.utf8_path().to_path_buf()],
+            Self::Doctests(doctests) => doctests
+                .doctests
+                .iter()
+                .filter(|d| !d.ignored)
+                .map(|d| doctests.persist_dir.join(&d.dir).join("rust_out"))
+                .collect(),
+        }
+    }
+
+    fn doctest(&self, case_name: &str) -> Option<&cargo::Doctest> {
+        match self {
+            Self::Binary { .. } => None,
+            Self::Doctests(doctests) => doctests.doctests.iter().find(|d| d.name == case_name),
+        }
     }
 }
 
@@ -247,11 +308,19 @@ impl TestArtifact for CargoTestArtifact {
     type CaseMetadata = NoCaseMetadata;
 
     fn package(&self) -> CargoPackageId {
-        CargoPackageId(self.artifact.package_id.clone())
+        match self {
+            Self::Binary { artifact, .. } => CargoPackageId(artifact.package_id.clone()),
+            Self::Doctests(doctests) => CargoPackageId(doctests.package_id.clone()),
+        }
     }
 
     fn to_key(&self) -> CargoArtifactKey {
-        CargoArtifactKey::from(&self.artifact.target)
+        match self {
+            Self::Binary { artifact, .. } => CargoArtifactKey::from(&artifact.target),
+            Self::Doctests(doctests) => {
+                CargoArtifactKey::new(&doctests.target.name, ArtifactKind::Doctest)
+            }
+        }
     }
 
     fn path(&self) -> &Path {
@@ -259,26 +328,66 @@ impl TestArtifact for CargoTestArtifact {
     }
 
     fn list_tests(&self) -> Result<Vec<(String, NoCaseMetadata)>> {
-        Ok(cargo::get_cases_from_binary(self.path(), &None)?
+        let cases = match self {
+            Self::Binary { bench: true, .. } => cargo::get_benchmarks_from_binary(self.path())?,
+            Self::Binary { .. } => cargo::get_cases_from_binary(self.path(), &None)?,
+            Self::Doctests(doctests) => doctests.doctests.iter().map(|d| d.name.clone()).collect(),
+        };
+        Ok(cases
             .into_iter()
             .map(|case| (case, NoCaseMetadata))
             .collect())
     }
 
     fn list_ignored_tests(&self) -> Result<Vec<String>> {
-        cargo::get_cases_from_binary(self.path(), &Some("--ignored".into()))
+        match self {
+            Self::Binary { bench: true, .. } => Ok(vec![]),
+            Self::Binary { .. } => {
+                cargo::get_cases_from_binary(self.path(), &Some("--ignored".into()))
+            }
+            Self::Doctests(doctests) => Ok(doctests
+                .doctests
+                .iter()
+                .filter(|d| d.ignored)
+                .map(|d| d.name.clone())
+                .collect()),
+        }
     }
 
     fn build_command(
         &self,
         case_name: &str,
         _case_metadata: &NoCaseMetadata,
-    ) -> (Utf8PathBuf, Vec<String>) {
-        let binary_name = self.path().file_name().unwrap().to_str().unwrap();
-        let mut args = vec!["--exact".into(), "--nocapture".into()];
-        args.extend(self.extra_test_binary_args.clone());
-        args.push(case_name.into());
-        (format!("/{binary_name}").into(), args)
+    ) -> Result<(Utf8PathBuf, Vec<String>)> {
+        match self {
+            Self::Binary {
+                extra_test_binary_args,
+                bench,
+                ..
+            } => {
+                let binary_name = self.path().file_name().unwrap().to_str().unwrap();
+                let mut args = if *bench {
+                    vec!["--bench".into(), "--exact".into()]
+                } else {
+                    vec!["--exact".into(), "--nocapture".into()]
+                };
+                args.extend(extra_test_binary_args.clone());
+                args.push(case_name.into());
+                Ok((format!("/{binary_name}").into(), args))
+            }
+            Self::Doctests(_) => {
+                // Each doctest is its own binary which just runs the doctest.
+                let doctest = self
+                    .doctest(case_name)
+                    .ok_or_else(|| anyhow!("unknown doctest {case_name:?}"))?;
+                Ok((format!("/{}/rust_out", doctest.dir).into(), vec![]))
+            }
+        }
+    }
+
+    /// A doctest marked `should_panic` passes if its binary panics, just like with rustdoc.
+    fn expects_failure(&self, case_name: &str, _case_metadata: &NoCaseMetadata) -> bool {
+        self.doctest(case_name).is_some_and(|d| d.should_panic)
     }
 
     fn format_case(
@@ -290,7 +399,7 @@ impl TestArtifact for CargoTestArtifact {
         let mut s = package_name.to_string();
         s += " ";
 
-        let artifact_name = &self.artifact.target.name;
+        let artifact_name = &self.target().name;
         if artifact_name.replace('_', "-") != package_name {
             s += artifact_name;
             s += " ";
@@ -300,10 +409,15 @@ impl TestArtifact for CargoTestArtifact {
     }
 
     fn get_test_layers(&self, metadata: &Metadata) -> Vec<LayerSpec> {
-        let mut layers = vec![path_layer_for_binary(self.utf8_path())];
+        let binary_paths = self.binary_paths();
+        let strip_prefix = match self {
+            Self::Binary { .. } => self.utf8_path().parent().unwrap(),
+            Self::Doctests(doctests) => doctests.persist_dir.as_path(),
+        };
+        let mut layers = vec![path_layer_for_binaries(&binary_paths, strip_prefix)];
 
         if metadata.include_shared_libraries {
-            layers.push(so_layer_for_binary(self.utf8_path()));
+            layers.push(so_layer_for_binaries(&binary_paths));
         }
 
         layers
@@ -313,36 +427,56 @@ impl TestArtifact for CargoTestArtifact {
 struct CargoTestArtifactStream {
     stream: cargo::TestArtifactStream,
     extra_test_binary_args: Vec<String>,
+    bench: bool,
+    /// Doctests are built once cargo is done building everything else.
+    doctests: Option<cargo::DoctestArtifactStream>,
+    /// When collecting coverage, we remember every binary we're going to run.
+    coverage_objects: Option<CoverageObjects>,
 }
 
-impl Iterator for CargoTestArtifactStream {
-    type Item = Result<CargoTestArtifact>;
-
-    fn next(&mut self) -> Option<Result<CargoTestArtifact>> {
+impl CargoTestArtifactStream {
+    fn next_artifact(&mut self) -> Option<Result<CargoTestArtifact>> {
         match self.stream.next() {
             Some(Err(e)) => Some(Err(e)),
-            Some(Ok(artifact)) => Some(Ok(CargoTestArtifact {
+            Some(Ok(artifact)) => Some(Ok(CargoTestArtifact::Binary {
                 artifact,
                 extra_test_binary_args: self.extra_test_binary_args.clone(),
+                bench: self.bench,
             })),
-            None => None,
+            None => self
+                .doctests
+                .as_mut()?
+                .next()
+                .map(|res| res.map(CargoTestArtifact::Doctests)),
         }
     }
 }
 
-fn path_layer_for_binary(binary_path: &Utf8Path) -> LayerSpec {
+impl Iterator for CargoTestArtifactStream {
+    type Item = Result<CargoTestArtifact>;
+
+    fn next(&mut self) -> Option<Result<CargoTestArtifact>> {
+        let next = self.next_artifact();
+        if let (Some(Ok(artifact)), Some(objects)) = (&next, &self.coverage_objects) {
+            objects.lock().unwrap().extend(artifact.binary_paths());
+        }
+        next
+    }
+}
+
+fn path_layer_for_binaries(binary_paths: &[Utf8PathBuf], strip_prefix: &Utf8Path) -> LayerSpec {
     LayerSpec::Paths(PathsLayerSpec {
-        paths: vec![binary_path.to_path_buf()],
+        paths: binary_paths.to_vec(),
         prefix_options: PrefixOptions {
-            strip_prefix: Some(binary_path.parent().unwrap().to_path_buf()),
+            strip_prefix: Some(strip_prefix.to_path_buf()),
             ..Default::default()
         },
     })
 }
 
-fn so_layer_for_binary(binary_path: &Utf8Path) -> LayerSpec {
+fn so_layer_for_binaries(binary_paths: &[Utf8PathBuf]) -> LayerSpec {
     shared_library_dependencies_layer_spec! {
-        [binary_path],
+        binary_paths.iter().cloned(),
         follow_symlinks: true,
     }
 }
@@ -359,7 +493,15 @@ impl TestPackage for CargoPackage {
     }
 
     fn artifacts(&self) -> Vec<CargoArtifactKey> {
-        self.0.targets.iter().map(CargoArtifactKey::from).collect()
+        let mut artifacts: Vec<_> = self.0.targets.iter().map(CargoArtifactKey::from).collect();
+        artifacts.extend(
+            self.0
+                .targets
+                .iter()
+                .filter(|t| t.doctest)
+                .map(|t| CargoArtifactKey::new(&t.name, ArtifactKind::Doctest)),
+        );
+        artifacts
     }
 
     fn id(&self) -> CargoPackageId {
@@ -388,11 +530,28 @@ impl CollectTests for CargoTestCollector {
         ui: &UiSender,
     ) -> Result<(cargo::WaitHandle, CargoTestArtifactStream)> {
         let packages: Vec<_> = packages.into_iter().map(|p| &p.0).collect();
+        if options.coverage {
+            self.coverage.reset()?;
+        }
+        let doctests = options.doctests.then(|| {
+            cargo::DoctestArtifactStream::new(
+                &options.feature_selection_options,
+                &options.compilation_options,
+                &options.manifest_options,
+                options.coverage,
+                options.doctests_rustc_bootstrap,
+                packages.clone(),
+                self.doctests_dir.to_path_buf(),
+                self.log.clone(),
+            )
+        });
         let (handle, stream) = cargo::run_cargo_test(
             color,
             &options.feature_selection_options,
             &options.compilation_options,
             &options.manifest_options,
+            options.coverage,
+            options.bench,
             packages,
             ui.downgrade(),
             self.log.clone(),
@@ -402,6 +561,9 @@ impl CollectTests for CargoTestCollector {
             CargoTestArtifactStream {
                 stream,
                 extra_test_binary_args: options.extra_test_binary_args.clone(),
+                bench: options.bench,
+                doctests,
+                coverage_objects: options.coverage.then(|| self.coverage.objects()),
             },
         ))
     }
@@ -410,6 +572,55 @@ impl CollectTests for CargoTestCollector {
         Ok(self.packages.clone())
     }
 
+    fn get_impact(
+        &self,
+        packages: &[CargoPackage],
+        changed_paths: &BTreeSet<PathBuf>,
+        _ui: &UiSender,
+    ) -> Result<Option<TestImpact<CargoPackageId, CargoArtifactKey>>> {
+        let packages: Vec<_> = packages.iter().map(|p| &p.0).collect();
+        Ok(
+            cargo::find_impacted_packages(&packages, changed_paths).map(|impacted| {
+                let mut impact = TestImpact::default();
+                for id in impacted {
+                    impact.add_package(CargoPackageId(id));
+                }
+                impact
+            }),
+        )
+    }
+
+    fn coverage_environment(options: &CargoOptions) -> Option<BTreeMap<String, String>> {
+        options.coverage.then(Coverage::environment)
+    }
+
+    fn save_coverage_data(&self, files: Vec<CapturedFile>) -> Result<()> {
+        self.coverage.save(files)
+    }
+
+    fn report_coverage(&self, options: &CargoOptions, ui: &UiSender) -> Result<()> {
+        if options.coverage {
+            self.coverage.report(ui)?;
+        }
+        Ok(())
+    }
+
+    fn benchmarking(options: &CargoOptions) -> bool {
+        options.bench
+    }
+
+    fn save_benchmark_output(&self, case_str: &str, lines: &[String]) -> Result<()> {
+        self.benchmarks.save(case_str, lines);
+        Ok(())
+    }
+
+    fn report_benchmarks(&self, options: &CargoOptions, ui: &UiSender) -> Result<()> {
+        if options.bench {
+            self.benchmarks.report(ui)?;
+        }
+        Ok(())
+    }
+
     /// The Rust std test fixture prints out some output like "running 1 test" etc. This isn't very
     /// useful, so we want to strip it out.
     fn remove_fixture_output(case_str: &str, mut lines: Vec<String>) -> Vec<String> {
@@ -512,6 +723,17 @@ impl MainAppDeps for DefaultMainAppDeps {
         Ok(TemplateVars::new([("build-dir", build_dir)]))
     }
 
+    fn get_package_metadata_directories(&self) -> Vec<(String, PathBuf)> {
+        self.test_collector
+            .packages
+            .iter()
+            .filter_map(|package| {
+                let directory = package.0.manifest_path.parent()?;
+                Some((package.0.name.clone(), directory.as_std_path().to_owned()))
+            })
+            .collect()
+    }
+
     const TEST_METADATA_FILE_NAME: &'static str = TEST_METADATA_FILE_NAME;
     const DEFAULT_TEST_METADATA_CONTENTS: &'static str = DEFAULT_TEST_METADATA_CONTENTS;
 }
@@ -601,6 +823,9 @@ impl maelstrom_test_runner::TestRunner for TestRunner {
                 &mut io::stdout().lock(),
             )
         } else {
+            if config.bench && (config.doctests || config.coverage) {
+                bail!("benchmarks can't be run along with doctests or with coverage");
+            }
             let cargo_metadata = get_metadata()?;
             let workspace_dir =
                 Root::<ProjectDir>::new(cargo_metadata.workspace_root.as_std_path());
@@ -608,6 +833,7 @@ impl maelstrom_test_runner::TestRunner for TestRunner {
             let log = logger.build(logging_output.clone());
 
             let list_action = extra_options.list.tests.then_some(ListAction::ListTests);
+            let export_image = extra_options.parent.export_image();
             let target_dir = Root::<BuildDir>::new(cargo_metadata.target_directory.as_std_path());
             let maelstrom_target_dir = target_dir.join::<MaelstromTargetDir>("maelstrom");
             let state_dir = maelstrom_target_dir.join::<StateDir>("state");
@@ -644,6 +870,10 @@ impl maelstrom_test_runner::TestRunner for TestRunner {
                 compilation_options: config.cargo_compilation_options,
                 manifest_options: config.cargo_manifest_options,
                 extra_test_binary_args: config.extra_test_binary_args,
+                doctests: config.doctests,
+                doctests_rustc_bootstrap: config.doctests_rustc_bootstrap,
+                coverage: config.coverage,
+                bench: config.bench,
             };
 
             run_app_with_ui_multithreaded(
@@ -654,6 +884,7 @@ impl maelstrom_test_runner::TestRunner for TestRunner {
                 extra_options.parent.include,
                 extra_options.parent.exclude,
                 list_action,
+                export_image,
                 config.parent.repeat,
                 config.parent.stop_after,
                 extra_options.parent.watch,
@@ -28,6 +28,7 @@ pub enum ArtifactKind {
     Test,
     Benchmark,
     Example,
+    Doctest,
 }
 
 impl ArtifactKind {
@@ -52,6 +53,7 @@ impl ArtifactKind {
             ArtifactKind::Test => "test",
             ArtifactKind::Benchmark => "bench",
             ArtifactKind::Example => "example",
+            ArtifactKind::Doctest => "doctest",
         }
     }
 }
@@ -95,6 +97,7 @@ pub fn interpret_simple_selector(s: &SimpleSelector, c: &Context) -> Option<bool
         Compound(Benchmark) => matches!(c.artifact()?.kind, ArtifactKind::Benchmark),
         Compound(Test) => matches!(c.artifact()?.kind, ArtifactKind::Test),
         Compound(Example) => matches!(c.artifact()?.kind, ArtifactKind::Example),
+        Compound(Doctest) => matches!(c.artifact()?.kind, ArtifactKind::Doctest),
         Compound(Name) => unreachable!("should be parser error"),
         Compound(Package) => unreachable!("should be parser error"),
     })
@@ -133,6 +136,10 @@ pub fn interpret_compound_selector(s: &CompoundSelector, c: &Context) -> Option<
             matches!(&c.artifact()?.kind, ArtifactKind::Test)
                 && interpret_matcher(&c.artifact()?.name, &s.matcher)
         }
+        Doctest => {
+            matches!(&c.artifact()?.kind, ArtifactKind::Doctest)
+                && interpret_matcher(&c.artifact()?.name, &s.matcher)
+        }
     })
 }
 
@@ -202,7 +209,7 @@ fn simple_expression_simple_selector() {
 
     // for all inputs, these expression evaluate as true
     for w in ["all", "any", "true"] {
-        for a in [Library, Binary, Test, Benchmark, Example] {
+        for a in [Library, Binary, Test, Benchmark, Example, Doctest] {
             test_it(w, Some(a), Some(true));
         }
         test_it(w, None, Some(true));
@@ -210,7 +217,7 @@ fn simple_expression_simple_selector() {
 
     // for all inputs, these expression evaluate as false
     for w in ["none", "false"] {
-        for a in [Library, Binary, Test, Benchmark, Example] {
+        for a in [Library, Binary, Test, Benchmark, Example, Doctest] {
             test_it(w, Some(a), Some(false));
         }
         test_it(w, None, Some(false));
@@ -235,6 +242,10 @@ fn simple_expression_simple_selector() {
     test_it("example", Some(Library), Some(false));
     test_it("example", Some(Example), Some(true));
     test_it("example", None, None);
+
+    test_it("doctest", Some(Library), Some(false));
+    test_it("doctest", Some(Doctest), Some(true));
+    test_it("doctest", None, None);
 }
 
 #[cfg(test)]
@@ -344,7 +355,7 @@ fn simple_expression_compound_selector_packge() {
     use ArtifactKind::*;
 
     let p = "package.matches(^[a-z]*$)";
-    for k in [Library, Binary, Test, Benchmark, Example] {
+    for k in [Library, Binary, Test, Benchmark, Example, Doctest] {
         test_compound_sel_case(p, Some(k), "bazbarbin", "", "", Some(true));
         test_compound_sel_case(p, Some(k), "baz-bin", "", "", Some(false));
     }
@@ -356,7 +367,7 @@ fn simple_expression_compound_selector_name() {
     use ArtifactKind::*;
 
     let p = "name.matches(^[a-z]*$)";
-    for k in [Library, Binary, Test, Benchmark, Example] {
+    for k in [Library, Binary, Test, Benchmark, Example, Doctest] {
         test_compound_sel_case(p, Some(k), "", "", "bazbarbin", Some(true));
         test_compound_sel_case(p, Some(k), "", "", "baz-bin", Some(false));
     }
@@ -402,6 +413,16 @@ fn simple_expression_compound_selector_test() {
     test_compound_sel_case(p, Some(Binary), "", "bazbarbin", "", Some(false));
 }
 
+#[test]
+fn simple_expression_compound_selector_doctest() {
+    use ArtifactKind::*;
+
+    let p = "doctest.matches(^[a-z]*$)";
+    test_compound_sel_case(p, Some(Doctest), "", "bazbarbin", "", Some(true));
+    test_compound_sel_case(p, Some(Doctest), "", "baz-bin", "", Some(false));
+    test_compound_sel_case(p, Some(Library), "", "bazbarbin", "", Some(false));
+}
+
 #[test]
 fn and_or_not_diff_expressions() {
     fn test_it(s: &str, expected: bool) {
@@ -208,6 +208,7 @@ pub enum CompoundSelectorName {
     Benchmark,
     Example,
     Test,
+    Doctest,
     Package,
 }
 
@@ -226,6 +227,7 @@ impl CompoundSelectorName {
             attempt(prefix("binary", 2)).map(|_| Self::Binary),
             attempt(prefix("benchmark", 2)).map(|_| Self::Benchmark),
             attempt(prefix("example", 1)).map(|_| Self::Example),
+            attempt(prefix("doctest", 1)).map(|_| Self::Doctest),
             prefix("test", 2).map(|_| Self::Test),
         ))
     }
@@ -504,6 +506,8 @@ fn simple_expr() {
     test_it("example()", Example);
     test_it("test", Test);
     test_it("test()", Test);
+    test_it("doctest", Doctest);
+    test_it("doctest()", Doctest);
 
     fn test_it_err(a: &str) {
         assert!(parse_str!(SimpleExpression, a).is_err());
@@ -537,6 +541,7 @@ fn simple_expr_prefix() {
     test_it("benchmark", 2, Benchmark);
     test_it("example", 1, Example);
     test_it("test", 2, Test);
+    test_it("doctest", 1, Doctest);
 }
 
 #[test]
@@ -567,6 +572,7 @@ fn simple_expr_compound() {
         Example,
         Contains("s(oi)l".into()),
     );
+    test_it("doctest.equals(foo)", Doctest, Equals("foo".into()));
 }
 
 #[test]
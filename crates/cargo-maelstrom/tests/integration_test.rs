@@ -60,6 +60,10 @@ fn do_cargo_maelstrom_test(source_contents: &str) -> String {
             manifest_path: Some(temp_dir.path().join("project/Cargo.toml")),
             ..Default::default()
         },
+        doctests: false,
+        doctests_rustc_bootstrap: false,
+        coverage: false,
+        bench: false,
         extra_test_binary_args: vec![],
     };
     let extra_options = ExtraCommandLineOptions {
@@ -202,6 +202,9 @@ pub enum JobMountForTomlAndJson {
     Tmp {
         mount_point: NonRootUtf8PathBuf,
     },
+    Capture {
+        mount_point: NonRootUtf8PathBuf,
+    },
 }
 
 #[pocket_definition(export)]
@@ -230,6 +233,11 @@ pub enum JobMount {
     Tmp {
         mount_point: Utf8PathBuf,
     },
+    /// An empty, writable directory whose contents are sent back with the job's results. See
+    /// [`JobEffects::captured_files`].
+    Capture {
+        mount_point: Utf8PathBuf,
+    },
 }
 
 #[macro_export]
@@ -259,6 +267,15 @@ macro_rules! tmp_mount {
     };
 }
 
+#[macro_export]
+macro_rules! capture_mount {
+    ($mount_point:expr) => {
+        $crate::JobMount::Capture {
+            mount_point: $mount_point.into(),
+        }
+    };
+}
+
 #[macro_export]
 macro_rules! devices_mount {
     ($devices:expr) => {
@@ -298,6 +315,9 @@ impl From<JobMountForTomlAndJson> for JobMount {
             JobMountForTomlAndJson::Tmp { mount_point } => JobMount::Tmp {
                 mount_point: mount_point.into(),
             },
+            JobMountForTomlAndJson::Capture { mount_point } => JobMount::Capture {
+                mount_point: mount_point.into(),
+            },
         }
     }
 }
@@ -312,6 +332,30 @@ pub enum JobNetwork {
     Local,
 }
 
+/// A line to add to a job's `/etc/hosts`: an IP address and the host names it has.
+#[pocket_definition(export)]
+#[derive(Clone, Debug, Default, Deserialize, Eq, Ord, PartialEq, PartialOrd, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct JobHostsEntry {
+    pub address: String,
+    pub names: Vec<String>,
+}
+
+/// How host names are resolved in a job's container. When the job has network access, the worker
+/// writes `/etc/resolv.conf` and `/etc/hosts` files for the job based on this, and mounts them over
+/// the container's. The default leaves the container's files alone.
+#[pocket_definition(export)]
+#[derive(Clone, Debug, Default, Deserialize, Eq, Ord, PartialEq, PartialOrd, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct JobDns {
+    /// Start the job's `/etc/resolv.conf` with the contents of the worker's.
+    pub inherit_host: bool,
+    pub nameservers: Vec<String>,
+    pub search: Vec<String>,
+    /// Entries for the job's `/etc/hosts`, which are added after the usual `localhost` entries.
+    pub hosts: Vec<JobHostsEntry>,
+}
+
 #[pocket_definition(export)]
 #[derive(Clone, Debug, Default, Deserialize, Eq, Ord, PartialEq, PartialOrd, Serialize)]
 pub struct CaptureFileSystemChanges {
@@ -452,6 +496,7 @@ pub struct JobSpec {
     pub layers: NonEmpty<(Sha256Digest, ArtifactType)>,
     pub mounts: Vec<JobMount>,
     pub network: JobNetwork,
+    pub dns: JobDns,
     pub root_overlay: JobRootOverlay,
     pub working_directory: Utf8PathBuf,
     pub user: UserId,
@@ -460,6 +505,10 @@ pub struct JobSpec {
     pub estimated_duration: Option<Duration>,
     pub allocate_tty: Option<JobTty>,
     pub priority: i8,
+    /// The job reserves a whole worker. It isn't started until nothing else is running on the
+    /// worker, and nothing else is started on the worker until it completes. This is useful for
+    /// jobs, like benchmarks, whose results depend on not sharing the machine.
+    pub exclusive: bool,
 }
 
 impl JobSpec {
@@ -486,6 +535,7 @@ macro_rules! job_spec {
                 layers: $crate::nonempty![$($layer),+],
                 mounts: Default::default(),
                 network: Default::default(),
+                dns: Default::default(),
                 root_overlay: Default::default(),
                 working_directory: "/".into(),
                 user: 0.into(),
@@ -494,6 +544,7 @@ macro_rules! job_spec {
                 estimated_duration: Default::default(),
                 allocate_tty: Default::default(),
                 priority: Default::default(),
+                exclusive: Default::default(),
             }
         }
     };
@@ -515,6 +566,10 @@ macro_rules! job_spec {
         $crate::job_spec!(@expand [$($required)+] [$($($field_in)*)?] ->
             [$($($field_out)+,)? network: $network])
     };
+    (@expand [$($required:tt)+] [dns: $dns:expr $(,$($field_in:tt)*)?] -> [$($($field_out:tt)+)?]) => {
+        $crate::job_spec!(@expand [$($required)+] [$($($field_in)*)?] ->
+            [$($($field_out)+,)? dns: $dns])
+    };
     (@expand [$($required:tt)+] [root_overlay: $root_overlay:expr $(,$($field_in:tt)*)?] -> [$($($field_out:tt)+)?]) => {
         $crate::job_spec!(@expand [$($required)+] [$($($field_in)*)?] ->
             [$($($field_out)+,)? root_overlay: $root_overlay])
@@ -547,6 +602,10 @@ macro_rules! job_spec {
         $crate::job_spec!(@expand [$($required)+] [$($($field_in)*)?] ->
             [$($($field_out)+,)? priority: $priority])
     };
+    (@expand [$($required:tt)+] [exclusive: $exclusive:expr $(,$($field_in:tt)*)?] -> [$($($field_out:tt)+)?]) => {
+        $crate::job_spec!(@expand [$($required)+] [$($($field_in)*)?] ->
+            [$($($field_out)+,)? exclusive: $exclusive])
+    };
     ($program:expr, [$($layer:expr),+ $(,)?] $(,$($field_in:tt)*)?) => {
         $crate::job_spec!(@expand [$program, [$($layer),+]] [$($($field_in)*)?] -> [])
     };
@@ -588,6 +647,16 @@ pub enum JobOutputResult {
     */
 }
 
+/// A file the job wrote into one of its [`JobMount::Capture`] mounts.
+#[pocket_definition(export)]
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct CapturedFile {
+    /// The path of the file in the job's file system.
+    pub path: Utf8PathBuf,
+    #[debug("<{} bytes>", contents.len())]
+    pub contents: Box<[u8]>,
+}
+
 /// The output and duration of a job that ran for some amount of time. This is generated regardless
 /// of how the job terminated. From our point of view, it doesn't matter. We ran the job until it
 /// was terminated, and gathered its output.
@@ -597,6 +666,8 @@ pub struct JobEffects {
     pub stdout: JobOutputResult,
     pub stderr: JobOutputResult,
     pub duration: Duration,
+    /// The files left in the job's [`JobMount::Capture`] mounts when it terminated.
+    pub captured_files: Vec<CapturedFile>,
 }
 
 /// The outcome of a completed job. That is, a job that ran to completion, instead of timing out,
@@ -1039,6 +1110,7 @@ mod tests {
                 { type = "proc", mount_point = "/proc" },
                 { type = "sys", mount_point = "/sys" },
                 { type = "tmp", mount_point = "/tmp" },
+                { type = "capture", mount_point = "/out" },
             ]"#,
         );
         let job_mounts: Vec<JobMount> = job_mounts.into_iter().map(|mount| mount.into()).collect();
@@ -1068,6 +1140,9 @@ mod tests {
                 JobMount::Tmp {
                     mount_point: "/tmp".into(),
                 },
+                JobMount::Capture {
+                    mount_point: "/out".into(),
+                },
             ]
         );
     }
@@ -1128,6 +1203,27 @@ mod tests {
         deserialize_value_error::<JobNetwork>(r#""foo""#).assert_error("unknown variant `foo`");
     }
 
+    #[test]
+    fn job_dns_deserialize() {
+        assert_eq!(deserialize_value::<JobDns>("{}"), JobDns::default());
+        assert_eq!(
+            deserialize_value::<JobDns>(concat!(
+                r#"{ nameservers = ["10.0.0.1"], "#,
+                r#"hosts = [{ address = "10.0.0.2", names = ["db", "db.internal"] }] }"#,
+            )),
+            JobDns {
+                nameservers: vec!["10.0.0.1".into()],
+                hosts: vec![JobHostsEntry {
+                    address: "10.0.0.2".into(),
+                    names: vec!["db".into(), "db.internal".into()],
+                }],
+                ..Default::default()
+            }
+        );
+        deserialize_value_error::<JobDns>("{ nameserver = [] }")
+            .assert_error("unknown field `nameserver`");
+    }
+
     #[test]
     fn job_spec_must_be_run_locally_network() {
         let spec = job_spec!("foo", [tar_digest!(0)]);
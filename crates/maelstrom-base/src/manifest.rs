@@ -1,8 +1,8 @@
-use crate::{Sha256Digest, Utf8PathBuf};
+use crate::{GroupId, Sha256Digest, UserId, Utf8PathBuf};
 use derive_more::Debug;
 use serde::{Deserialize, Serialize};
 use serde_repr::{Deserialize_repr, Serialize_repr};
-use std::fmt;
+use std::{collections::BTreeMap, fmt};
 
 #[derive(Debug)]
 #[debug("{_0:o}")]
@@ -52,11 +52,17 @@ impl From<UnixTimestamp> for std::time::SystemTime {
     }
 }
 
+/// Extended attributes of a file, keyed by their full name (e.g. `security.capability`).
+pub type ExtendedAttributes = BTreeMap<String, Vec<u8>>;
+
 #[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
 pub struct ManifestEntryMetadata {
     pub size: u64,
     pub mode: Mode,
     pub mtime: UnixTimestamp,
+    /// The file's user and group. A file without an owner is owned by the job's user and group.
+    pub owner: Option<(UserId, GroupId)>,
+    pub xattrs: ExtendedAttributes,
 }
 
 #[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
@@ -86,6 +92,8 @@ pub struct ManifestEntry {
 #[repr(u32)]
 pub enum ManifestVersion {
     V0 = 0,
-    #[default]
     V1 = 1,
+    /// Adds file ownership and extended attributes to [`ManifestEntryMetadata`].
+    #[default]
+    V2 = 2,
 }
@@ -1,18 +1,25 @@
 //! Messages sent between various binaries.
 
 use crate::{
-    stats::BrokerStatistics, ArtifactUploadLocation, ClientJobId, JobBrokerStatus, JobId,
-    JobOutcomeResult, JobSpec, JobWorkerStatus, Sha256Digest,
+    stats::{BrokerStatistics, ClientStatistics, JobStatistics},
+    ArtifactUploadLocation, ClientId, ClientJobId, JobBrokerStatus, JobId, JobOutcomeResult,
+    JobSpec, JobWorkerStatus, Sha256Digest,
 };
 use bincode::Options;
 use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
 
 /// The first message sent by a connector to the broker. It identifies what the connector is, and
 /// provides any relevant information.
 #[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Debug)]
 pub enum Hello {
     Client,
-    Worker { slots: u32 },
+    /// A worker with the given number of slots. If the worker serves artifacts from its cache to
+    /// other workers, `artifact_server_port` is the port it serves them on.
+    Worker {
+        slots: u32,
+        artifact_server_port: Option<u16>,
+    },
     Monitor,
     ArtifactPusher,
     ArtifactFetcher,
@@ -34,6 +41,10 @@ pub enum BrokerToWorker {
 pub enum WorkerToBroker {
     JobResponse(JobId, JobOutcomeResult),
     JobStatusUpdate(JobId, JobWorkerStatus),
+    /// The free space on the worker's cache file system has dropped below (`true`), or risen back
+    /// above (`false`), the worker's configured minimum. While it's below, the worker doesn't start
+    /// new jobs, so the broker shouldn't send it any.
+    DiskSpaceLow(bool),
 }
 
 /// Message sent from the broker to a client. The broker won't send a message until it has received
@@ -44,6 +55,9 @@ pub enum BrokerToClient {
     JobStatusUpdate(ClientJobId, JobBrokerStatus),
     TransferArtifact(Sha256Digest),
     GeneralError(String),
+    /// The reply to a [`ClientToBroker::FindMissingArtifacts`]: the artifacts the client should
+    /// upload. The broker won't send [`BrokerToClient::TransferArtifact`] for these.
+    MissingArtifacts(Vec<Sha256Digest>),
 }
 
 /// Message sent from a client to the broker. After sending the initial [`Hello`], a client will
@@ -53,13 +67,20 @@ pub enum BrokerToClient {
 pub enum ClientToBroker {
     JobRequest(ClientJobId, JobSpec),
     ArtifactTransferred(Sha256Digest, ArtifactUploadLocation),
+    /// Ask which of the given artifacts the broker doesn't have. This lets a client start
+    /// uploading artifacts for a batch of jobs before the broker asks for them one at a time.
+    FindMissingArtifacts(Vec<Sha256Digest>),
 }
 
 /// Message sent from the broker to a monitor. The broker won't send a message until it has
 /// recevied a [`Hello`] and determined the type of its interlocutor.
 #[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[allow(clippy::large_enum_variant)]
 pub enum BrokerToMonitor {
     StatisticsResponse(BrokerStatistics),
+    JobsResponse(Vec<JobStatistics>),
+    /// The statistics for the given client, or `None` if the client isn't connected.
+    ClientStatisticsResponse(ClientId, Option<ClientStatistics>),
 }
 
 /// Message sent from a monitor to the broker. After sending the initial [`Hello`], a monitor will
@@ -67,6 +88,30 @@ pub enum BrokerToMonitor {
 #[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
 pub enum MonitorToBroker {
     StatisticsRequest,
+    JobsRequest,
+    ClientStatisticsRequest(ClientId),
+}
+
+/// How the body of an artifact is encoded while it is being transferred. Digests, and the sizes
+/// kept by caches, always refer to the uncompressed contents.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub enum ArtifactCompression {
+    #[default]
+    None,
+    /// The body is a single zstd frame.
+    Zstd,
+}
+
+/// Where the broker wants an artifact fetcher to get an artifact from.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub enum ArtifactSource {
+    /// The body of the artifact will immediately follow. It contains the uncompressed size of the
+    /// artifact and the compression the broker chose for the body.
+    Body(u64, ArtifactCompression),
+
+    /// The artifact should be fetched from the worker serving artifacts at the given address. If
+    /// that fails, the fetcher should ask the broker again, this time without accepting a peer.
+    Peer(SocketAddr),
 }
 
 /// Message sent from the broker to an artifact fetcher. This will be in response to an
@@ -74,12 +119,27 @@ pub enum MonitorToBroker {
 /// details about what went wrong. After a failure, the broker will close the artifact fetcher
 /// connection.
 #[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
-pub struct BrokerToArtifactFetcher(pub Result<u64, String>);
+pub struct BrokerToArtifactFetcher(pub Result<ArtifactSource, String>);
+
+/// Message sent from an artifact fetcher to the broker. It contains the digest of the artifact,
+/// the compression the fetcher is willing to accept, and whether the fetcher is willing to be
+/// sent to a peer for the artifact. The broker may always choose to send the artifact itself,
+/// uncompressed. It will be answered with a [`BrokerToArtifactFetcher`].
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ArtifactFetcherToBroker(pub Sha256Digest, pub ArtifactCompression, pub bool);
+
+/// Message sent from a worker's artifact server to an artifact fetcher on another worker. This
+/// will be in response to an [`ArtifactFetcherToPeer`] message. On success, it contains the
+/// uncompressed size of the artifact and the compression chosen for the body, which will
+/// immediately follow this message. On failure, the peer will close the connection.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct PeerToArtifactFetcher(pub Result<(u64, ArtifactCompression), String>);
 
-/// Message sent from an artifact fetcher to the broker. It will be answered with a
-/// [`BrokerToArtifactFetcher`].
+/// Message sent from an artifact fetcher to another worker's artifact server. It contains the
+/// digest of the artifact and the compression the fetcher is willing to accept. There is no
+/// [`Hello`] on these connections. It will be answered with a [`PeerToArtifactFetcher`].
 #[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
-pub struct ArtifactFetcherToBroker(pub Sha256Digest);
+pub struct ArtifactFetcherToPeer(pub Sha256Digest, pub ArtifactCompression);
 
 /// Message sent from the broker to an artifact pusher. This will be in response to an
 /// [`ArtifactPusherToBroker`] message and the artifact's body. On success, the message contains no
@@ -89,11 +149,13 @@ pub struct ArtifactFetcherToBroker(pub Sha256Digest);
 #[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
 pub struct BrokerToArtifactPusher(pub Result<(), String>);
 
-/// Message sent from an artifact pusher to the broker. It contains the digest and size of the
-/// artifact. The body of the artifact will immediately follow this message. It will be answered
-/// with a [`BrokerToArtifactPusher`].
+/// Message sent from an artifact pusher to the broker. It contains the digest of the artifact, the
+/// uncompressed size of the artifact, and how the body is compressed. The body of the artifact
+/// will immediately follow this message. An uncompressed body is exactly the size of the artifact,
+/// while a compressed body is a single zstd frame, which is compressed while it's being sent. It
+/// will be answered with a [`BrokerToArtifactPusher`].
 #[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
-pub struct ArtifactPusherToBroker(pub Sha256Digest, pub u64);
+pub struct ArtifactPusherToBroker(pub Sha256Digest, pub u64, pub ArtifactCompression);
 
 fn bincode() -> impl Options {
     bincode::options().with_big_endian()
@@ -2,7 +2,7 @@
 
 use crate::{
     ring_buffer::{self, RingBuffer},
-    ClientId, WorkerId,
+    ClientId, ClientJobId, JobBrokerStatus, JobId, WorkerId,
 };
 use enum_map::EnumMap;
 use serde::{Deserialize, Serialize};
@@ -67,11 +67,21 @@ fn job_state_iter() {
 /// For a single client, counts of jobs in various states
 pub type JobStateCounts = EnumMap<JobState, u64>;
 
+/// For a single worker, the state of its jobs
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct WorkerStatisticsSample {
+    /// Jobs sent to the worker that haven't completed yet, including those queued at the worker.
+    pub jobs: usize,
+    /// Jobs the worker has completed since it connected.
+    pub completed: u64,
+}
+
 /// Single point-in-time snapshot
 /// TODO: This should contain a timestamp
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
 pub struct JobStatisticsSample {
     pub client_to_stats: HashMap<ClientId, JobStateCounts>,
+    pub worker_to_stats: HashMap<WorkerId, WorkerStatisticsSample>,
 }
 
 /// The number of data-points to save before it is deleted
@@ -134,3 +144,71 @@ pub struct BrokerStatistics {
     pub worker_statistics: HashMap<WorkerId, WorkerStatistics>,
     pub job_statistics: JobStatisticsTimeSeries,
 }
+
+/// An outstanding job, as shown in a monitor's job table.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct JobStatistics {
+    pub id: JobId,
+    pub priority: i8,
+    pub status: JobBrokerStatus,
+    /// How long ago the broker received the job.
+    pub elapsed: Duration,
+}
+
+/// The number of completed jobs remembered for each client
+pub const RECENT_COMPLETIONS: usize = 100;
+
+/// A job that was completed, either successfully or not.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct JobCompletion {
+    pub cjid: ClientJobId,
+    /// The worker that ran the job, if it got that far.
+    pub worker: Option<WorkerId>,
+    /// Whether the job ran and exited with a status of 0.
+    pub succeeded: bool,
+    /// The time from the broker receiving the job to it sending the result to the client.
+    pub latency: Duration,
+}
+
+/// Details about a single client.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct ClientStatistics {
+    /// The client's most recently completed jobs, oldest first. There will be no more than
+    /// [`RECENT_COMPLETIONS`] of these.
+    pub recent_completions: Vec<JobCompletion>,
+}
+
+impl ClientStatistics {
+    /// The fraction of recently completed jobs that didn't succeed, or `None` if there aren't any.
+    pub fn failure_rate(&self) -> Option<f64> {
+        if self.recent_completions.is_empty() {
+            return None;
+        }
+        let failed = self
+            .recent_completions
+            .iter()
+            .filter(|completion| !completion.succeeded)
+            .count();
+        Some(failed as f64 / self.recent_completions.len() as f64)
+    }
+}
+
+#[test]
+fn client_statistics_failure_rate() {
+    let completion = |cjid: u32, succeeded| JobCompletion {
+        cjid: cjid.into(),
+        worker: None,
+        succeeded,
+        latency: Duration::from_secs(1),
+    };
+    assert_eq!(ClientStatistics::default().failure_rate(), None);
+    let stats = ClientStatistics {
+        recent_completions: vec![
+            completion(1, true),
+            completion(2, false),
+            completion(3, true),
+            completion(4, true),
+        ],
+    };
+    assert_eq!(stats.failure_rate(), Some(0.25));
+}
@@ -1,7 +1,9 @@
 use crate::scheduler_task;
 use anyhow::{anyhow, Result};
 use maelstrom_base::{
-    proto::{ArtifactFetcherToBroker, BrokerToArtifactFetcher},
+    proto::{
+        ArtifactCompression, ArtifactFetcherToBroker, ArtifactSource, BrokerToArtifactFetcher,
+    },
     Sha256Digest,
 };
 use maelstrom_util::{
@@ -11,9 +13,10 @@ use maelstrom_util::{
 use slog::{debug, Logger};
 use std::{
     io::{self, Read as _},
-    net::TcpStream,
+    net::{SocketAddr, TcpStream},
     sync::mpsc,
 };
+use tokio::sync::oneshot;
 
 fn get_file<'fs, TempFileT>(
     fs: &'fs Fs,
@@ -36,17 +39,41 @@ where
     Ok((f, size))
 }
 
+fn get_peer<TempFileT>(
+    digest: &Sha256Digest,
+    scheduler_task_sender: &scheduler_task::Sender<TempFileT>,
+) -> Result<Option<SocketAddr>>
+where
+    TempFileT: Send + Sync + 'static,
+{
+    let (sender, receiver) = oneshot::channel();
+    scheduler_task_sender.send(scheduler_task::Message::GetPeerForArtifact(
+        digest.clone(),
+        sender,
+    ))?;
+    Ok(receiver.blocking_recv()?)
+}
+
 fn send_artifact<TempFileT>(
     scheduler_task_sender: &scheduler_task::Sender<TempFileT>,
     file: File,
-    mut socket: &mut impl io::Write,
+    socket: &mut impl io::Write,
     size: u64,
+    compression: ArtifactCompression,
     digest: Sha256Digest,
 ) -> Result<()>
 where
     TempFileT: Send + Sync + 'static,
 {
-    let copied = io::copy(&mut file.take(size), &mut socket)?;
+    let copied = match compression {
+        ArtifactCompression::None => io::copy(&mut file.take(size), socket),
+        ArtifactCompression::Zstd => zstd::Encoder::new(socket, zstd::DEFAULT_COMPRESSION_LEVEL)
+            .and_then(|mut encoder| {
+                let copied = io::copy(&mut file.take(size), &mut encoder)?;
+                encoder.finish()?;
+                Ok(copied)
+            }),
+    }?;
     scheduler_task_sender.send(scheduler_task::Message::DecrementRefcount(digest))?;
     if copied == size {
         Ok(())
@@ -64,19 +91,35 @@ fn handle_one_message<TempFileT>(
 where
     TempFileT: Send + Sync + 'static,
 {
-    let ArtifactFetcherToBroker(digest) = msg;
+    let ArtifactFetcherToBroker(digest, compression, accept_peer) = msg;
+    if accept_peer {
+        if let Some(peer) = get_peer(&digest, scheduler_task_sender)? {
+            debug!(log, "sending artifact fetcher to peer"; "digest" => %digest, "peer" => %peer);
+            let msg = BrokerToArtifactFetcher(Ok(ArtifactSource::Peer(peer)));
+            net::write_message_to_socket(&mut socket, msg, log)?;
+            return Ok(());
+        }
+    }
+
     let fs = Fs::new();
     let result = get_file(&fs, &digest, scheduler_task_sender);
     let msg = BrokerToArtifactFetcher(
         result
             .as_ref()
-            .map(|(_, size)| *size)
+            .map(|(_, size)| ArtifactSource::Body(*size, compression))
             .map_err(|e| e.to_string()),
     );
     net::write_message_to_socket(&mut socket, msg, log)?;
 
     let (f, size) = result?;
-    send_artifact(scheduler_task_sender, f, &mut socket, size, digest)?;
+    send_artifact(
+        scheduler_task_sender,
+        f,
+        &mut socket,
+        size,
+        compression,
+        digest,
+    )?;
 
     Ok(())
 }
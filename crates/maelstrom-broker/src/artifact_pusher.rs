@@ -1,36 +1,72 @@
 use crate::{cache::TempFileFactory, scheduler_task};
-use anyhow::{Error, Result};
-use maelstrom_base::proto::{ArtifactPusherToBroker, BrokerToArtifactPusher};
+use anyhow::{bail, Error, Result};
+use maelstrom_base::{
+    proto::{ArtifactCompression, ArtifactPusherToBroker, BrokerToArtifactPusher},
+    Sha256Digest,
+};
 use maelstrom_util::{
     cache::fs::TempFile as _,
     io::{FixedSizeReader, Sha256Stream},
     net,
 };
 use slog::{debug, Logger};
-use std::{fs::File, io, net::TcpStream};
+use std::{
+    fs::File,
+    io::{self, Read},
+    net::TcpStream,
+};
 
 fn handle_one_message<TempFileFactoryT: TempFileFactory>(
     msg: ArtifactPusherToBroker,
-    socket: &mut TcpStream,
+    socket: &mut impl Read,
     scheduler_task_sender: &scheduler_task::Sender<TempFileFactoryT::TempFile>,
     temp_file_factory: &TempFileFactoryT,
 ) -> Result<()>
 where
     TempFileFactoryT::TempFile: Send + Sync + 'static,
 {
-    let ArtifactPusherToBroker(digest, size) = msg;
+    let ArtifactPusherToBroker(digest, size, compression) = msg;
     let temp_file = temp_file_factory.temp_file()?;
     let mut temp_file_inner = File::create(temp_file.path())?;
-    let fixed_size_reader = FixedSizeReader::new(socket, size);
-    let mut sha_reader = Sha256Stream::new(fixed_size_reader);
-    let copied = io::copy(&mut sha_reader, &mut temp_file_inner)?;
-    assert_eq!(copied, size);
-    let (_, actual_digest) = sha_reader.finalize();
+    let (copied, actual_digest) = match compression {
+        ArtifactCompression::None => {
+            // We always need to consume the whole body, even if writing it fails, so that the
+            // connection is left in a state where we can write the response.
+            let mut fixed_size_reader = FixedSizeReader::new(socket, size);
+            let result = copy_and_digest(&mut fixed_size_reader, &mut temp_file_inner);
+            io::copy(&mut fixed_size_reader, &mut io::sink())?;
+            result
+        }
+        ArtifactCompression::Zstd => {
+            // The body is a single frame, so the decoder stops reading at the end of it. The
+            // pusher doesn't send anything else until it gets our response, so it doesn't matter
+            // if the decoder buffers more than it needs. If decompression fails, we can't tell
+            // where the body ends, but the connection is closed after the response anyway.
+            //
+            // Don't let a small body decompress into something that fills up our disk. Reading one
+            // extra byte is enough to tell that the body holds more than the pusher said it would.
+            let decoder = zstd::Decoder::new(socket)?.single_frame();
+            copy_and_digest(decoder.take(size.saturating_add(1)), &mut temp_file_inner)
+        }
+    };
+    let copied = copied?;
+    if copied != size {
+        bail!("artifact was {copied} bytes instead of the expected {size}");
+    }
     actual_digest.verify(&digest)?;
-    scheduler_task_sender.send(scheduler_task::Message::GotArtifact(digest, temp_file))?;
+    scheduler_task_sender.send(scheduler_task::Message::GotArtifact(
+        digest, size, temp_file,
+    ))?;
     Ok(())
 }
 
+fn copy_and_digest(body: impl Read, file: &mut File) -> (io::Result<u64>, Sha256Digest) {
+    let mut sha_reader = Sha256Stream::new(body);
+    let copied = io::copy(&mut sha_reader, file);
+    let (_, digest) = sha_reader.finalize();
+    (copied, digest)
+}
+
 fn connection_loop<TempFileFactoryT: TempFileFactory>(
     mut socket: TcpStream,
     scheduler_task_sender: &scheduler_task::Sender<TempFileFactoryT::TempFile>,
@@ -64,3 +100,176 @@ where
     debug!(log, "artifact pusher disconnected"; "error" => %err);
     Err(err)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use maelstrom_util::cache::fs::TempFile;
+    use std::{
+        cell::Cell,
+        io::Write as _,
+        path::{Path, PathBuf},
+        rc::Rc,
+    };
+    use tempfile::TempDir;
+    use tokio::sync::mpsc;
+
+    #[derive(Debug)]
+    struct TestTempFile(PathBuf);
+
+    impl TempFile for TestTempFile {
+        fn path(&self) -> &Path {
+            &self.0
+        }
+    }
+
+    #[derive(Clone)]
+    struct TestTempFileFactory {
+        dir: PathBuf,
+        next: Rc<Cell<u32>>,
+    }
+
+    impl TempFileFactory for TestTempFileFactory {
+        type TempFile = TestTempFile;
+
+        fn temp_file(&self) -> Result<Self::TempFile> {
+            let next = self.next.get();
+            self.next.set(next + 1);
+            Ok(TestTempFile(self.dir.join(next.to_string())))
+        }
+    }
+
+    fn digest_of(contents: &[u8]) -> Sha256Digest {
+        let mut stream = Sha256Stream::new(io::sink());
+        stream.write_all(contents).unwrap();
+        stream.finalize().1
+    }
+
+    fn push(
+        digest: Sha256Digest,
+        size: u64,
+        body: &[u8],
+        compression: ArtifactCompression,
+    ) -> (Result<()>, Option<(u64, Vec<u8>)>) {
+        let dir = TempDir::new().unwrap();
+        let temp_file_factory = TestTempFileFactory {
+            dir: dir.path().to_owned(),
+            next: Default::default(),
+        };
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        let msg = ArtifactPusherToBroker(digest.clone(), size, compression);
+        let mut socket = body;
+        let result = handle_one_message(msg, &mut socket, &sender, &temp_file_factory);
+        assert!(socket.is_empty());
+        let got = receiver.try_recv().ok().map(|msg| {
+            let scheduler_task::Message::GotArtifact(got_digest, size, temp_file) = msg else {
+                panic!("unexpected message");
+            };
+            assert_eq!(got_digest, digest);
+            (size, std::fs::read(temp_file.path()).unwrap())
+        });
+        (result, got)
+    }
+
+    #[test]
+    fn uncompressed() {
+        let contents = b"hello world".repeat(100);
+        let (result, got) = push(
+            digest_of(&contents),
+            contents.len() as u64,
+            &contents,
+            ArtifactCompression::None,
+        );
+        result.unwrap();
+        assert_eq!(got, Some((contents.len() as u64, contents)));
+    }
+
+    #[test]
+    fn zstd() {
+        let contents = b"hello world".repeat(100);
+        let compressed = zstd::encode_all(&contents[..], 0).unwrap();
+        assert!(compressed.len() < contents.len());
+        let (result, got) = push(
+            digest_of(&contents),
+            contents.len() as u64,
+            &compressed,
+            ArtifactCompression::Zstd,
+        );
+        result.unwrap();
+        assert_eq!(got, Some((contents.len() as u64, contents)));
+    }
+
+    #[test]
+    fn zstd_digest_mismatch() {
+        let contents = b"hello world".repeat(100);
+        let compressed = zstd::encode_all(&contents[..], 0).unwrap();
+        let (result, got) = push(
+            digest_of(&compressed),
+            contents.len() as u64,
+            &compressed,
+            ArtifactCompression::Zstd,
+        );
+        result.unwrap_err();
+        assert_eq!(got, None);
+    }
+
+    #[test]
+    fn zstd_corrupt_body() {
+        let body = b"definitely not zstd".to_vec();
+        let (result, got) = push(
+            digest_of(&body),
+            body.len() as u64,
+            &body,
+            ArtifactCompression::Zstd,
+        );
+        result.unwrap_err();
+        assert_eq!(got, None);
+    }
+
+    #[test]
+    fn zstd_larger_than_declared_size() {
+        let contents = b"hello world".repeat(100);
+        let compressed = zstd::encode_all(&contents[..], 0).unwrap();
+        let (result, got) = push(
+            digest_of(&contents),
+            contents.len() as u64 - 1,
+            &compressed,
+            ArtifactCompression::Zstd,
+        );
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "artifact was 1100 bytes instead of the expected 1099"
+        );
+        assert_eq!(got, None);
+    }
+
+    #[test]
+    fn zstd_smaller_than_declared_size() {
+        let contents = b"hello world".repeat(100);
+        let compressed = zstd::encode_all(&contents[..], 0).unwrap();
+        let (result, got) = push(
+            digest_of(&contents),
+            contents.len() as u64 + 1,
+            &compressed,
+            ArtifactCompression::Zstd,
+        );
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "artifact was 1100 bytes instead of the expected 1101"
+        );
+        assert_eq!(got, None);
+    }
+
+    #[test]
+    fn uncompressed_size_mismatch() {
+        let contents = b"hello world".repeat(100);
+        let (result, got) = push(
+            digest_of(&contents),
+            contents.len() as u64 + 1,
+            &contents,
+            ArtifactCompression::None,
+        );
+        result.unwrap_err();
+        assert_eq!(got, None);
+    }
+}
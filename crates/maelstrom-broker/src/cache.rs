@@ -1,11 +1,12 @@
 pub mod github;
 pub mod local;
 mod remote;
+pub mod store;
 
 use crate::Config;
 use anyhow::{Error, Result};
 use maelstrom_base::{ClientId, JobId, Sha256Digest};
-use maelstrom_util::cache::GetArtifact;
+use maelstrom_util::cache::{CacheMetrics, GetArtifact};
 use slog::Logger;
 use std::future::Future;
 use std::io;
@@ -35,6 +36,9 @@ pub trait SchedulerCache {
         file: Option<Self::TempFile>,
     ) -> Result<Vec<JobId>, (Error, Vec<JobId>)>;
 
+    /// Return whether the artifact is in the cache. This doesn't affect the refcount.
+    fn contains_artifact(&self, digest: &Sha256Digest) -> bool;
+
     /// Decrement the refcount for the given artifact.
     fn decrement_refcount(&mut self, digest: &Sha256Digest);
 
@@ -49,6 +53,9 @@ pub trait SchedulerCache {
     /// the artifact before calling this function and keep it the whole time the stream is being
     /// used.
     fn read_artifact(&mut self, digest: &Sha256Digest) -> Self::ArtifactStream;
+
+    /// Get a snapshot of the cache's activity, or `None` if the cache doesn't keep track of it.
+    fn metrics(&self) -> Option<CacheMetrics>;
 }
 
 pub trait TempFileFactory: Clone {
@@ -1,12 +1,14 @@
 use crate::cache::LazyRead;
 use crate::cache::{remote, BrokerCache};
 use anyhow::{anyhow, Result};
+use async_compression::tokio::bufread::ZstdDecoder;
 use maelstrom_base::Sha256Digest;
 use maelstrom_github::GitHubClient;
+use maelstrom_util::io::{read_zstd_size_frame, ExactSizeReader};
 use std::io;
 use std::pin::Pin;
 use std::sync::Arc;
-use tokio::io::AsyncRead;
+use tokio::io::{AsyncRead, BufReader};
 
 pub struct GitHubArtifactReader {
     client: Arc<GitHubClient>,
@@ -26,7 +28,7 @@ async fn download_artifact(
     client: Arc<GitHubClient>,
     digest: Sha256Digest,
 ) -> Result<GitHubArtifactStream> {
-    let artifact_name = format!("maelstrom-cache-sha256-{digest}");
+    let artifact_name = format!("maelstrom-cache-sha256-{digest}.zst");
     let artifact = client
         .get(&artifact_name)
         .await?
@@ -34,7 +36,13 @@ async fn download_artifact(
     let stream = client
         .download(artifact.backend_ids, &artifact_name)
         .await?;
-    Ok(Box::pin(stream))
+    let mut stream = BufReader::new(stream);
+    let size = read_zstd_size_frame(&mut stream).await?;
+    // Don't let a small artifact decompress into something much bigger than it's supposed to be.
+    Ok(Box::pin(ExactSizeReader::new(
+        ZstdDecoder::new(stream),
+        size,
+    )))
 }
 
 impl remote::RemoteArtifactReader for GitHubArtifactReader {
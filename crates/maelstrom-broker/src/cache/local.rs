@@ -4,7 +4,9 @@ use crate::{
 };
 use anyhow::{anyhow, Error, Result};
 use maelstrom_base::{ClientId, JobId, Sha256Digest};
-use maelstrom_util::cache::{fs::Fs, Cache, GetArtifact, GetStrategy, GotArtifact, Key};
+use maelstrom_util::cache::{
+    fs::Fs, Cache, CacheMetrics, GetArtifact, GetStrategy, GotArtifact, Key,
+};
 use ref_cast::RefCast;
 use slog::Logger;
 use std::io;
@@ -88,6 +90,10 @@ impl<FsT: Fs> SchedulerCache for Cache<FsT, BrokerKey, BrokerGetStrategy> {
         }
     }
 
+    fn contains_artifact(&self, digest: &Sha256Digest) -> bool {
+        self.contains(BrokerKey::ref_cast(digest))
+    }
+
     fn decrement_refcount(&mut self, digest: &Sha256Digest) {
         self.decrement_ref_count(BrokerKey::ref_cast(digest))
     }
@@ -112,6 +118,10 @@ impl<FsT: Fs> SchedulerCache for Cache<FsT, BrokerKey, BrokerGetStrategy> {
                 .map_err(|err| io::Error::new(io::ErrorKind::Other, err))
         }))
     }
+
+    fn metrics(&self) -> Option<CacheMetrics> {
+        Some(Cache::metrics(self))
+    }
 }
 
 impl TempFileFactory
@@ -1,7 +1,7 @@
 use crate::cache::{SchedulerCache, TempFileFactory};
 use anyhow::{anyhow, bail, Error, Result};
 use maelstrom_base::{ClientId, JobId, Sha256Digest};
-use maelstrom_util::cache::{fs::TempFile, GetArtifact};
+use maelstrom_util::cache::{fs::TempFile, CacheMetrics, GetArtifact};
 use std::collections::{hash_map::Entry as HashEntry, HashMap, HashSet};
 use std::mem;
 use std::path::{Path, PathBuf};
@@ -106,6 +106,10 @@ impl<ArtifactReaderT: RemoteArtifactReader> SchedulerCache for RemoteCache<Artif
         }
     }
 
+    fn contains_artifact(&self, digest: &Sha256Digest) -> bool {
+        matches!(self.entries.get(digest), Some(Entry::InCache))
+    }
+
     fn decrement_refcount(&mut self, _digest: &Sha256Digest) {
         // nothing to do
     }
@@ -126,6 +130,10 @@ impl<ArtifactReaderT: RemoteArtifactReader> SchedulerCache for RemoteCache<Artif
     fn read_artifact(&mut self, digest: &Sha256Digest) -> Self::ArtifactStream {
         self.artifact_reader.read(digest)
     }
+
+    fn metrics(&self) -> Option<CacheMetrics> {
+        None
+    }
 }
 
 #[cfg(test)]
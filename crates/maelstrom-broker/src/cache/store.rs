@@ -0,0 +1,220 @@
+//! An external content-addressed store that backs the broker's cache. Artifacts are stored by
+//! their SHA-256 digest at `<url>/<digest>`, and are read and written with plain HTTP `GET` and
+//! `PUT` requests. This works with simple HTTP CAS servers, and with S3-compatible object stores
+//! whose bucket policy allows unsigned requests.
+//!
+//! The store lets a broker's cache outlive the broker, and lets multiple brokers share artifacts.
+//! Artifacts that clients upload to the broker are copied to the store. When the broker
+//! needs an artifact that it doesn't have, it looks in the store before asking the client.
+
+use anyhow::{bail, Result};
+use maelstrom_base::Sha256Digest;
+use maelstrom_util::io::Sha256Stream;
+use reqwest::{header::CONTENT_LENGTH, Client, StatusCode};
+use std::path::Path;
+use tokio::{fs::File, io::AsyncWriteExt as _};
+use url::Url;
+
+#[derive(Clone)]
+pub struct ArtifactStore {
+    client: Client,
+    url: Url,
+}
+
+impl ArtifactStore {
+    /// Create a new store rooted at the given URL.
+    pub fn new(url: &str) -> Result<Self> {
+        let mut url = Url::parse(url)?;
+        if url.cannot_be_a_base() {
+            bail!("artifact store URL {url} can't be used as a base");
+        }
+        if !url.path().ends_with('/') {
+            url.set_path(&format!("{}/", url.path()));
+        }
+        Ok(Self {
+            client: Client::new(),
+            url,
+        })
+    }
+
+    fn artifact_url(&self, digest: &Sha256Digest) -> Url {
+        self.url.join(&digest.to_string()).unwrap()
+    }
+
+    /// Download the artifact into the file at `path`, returning its size. If the store doesn't
+    /// have the artifact, return `None`. The store isn't trusted, so the digest of the downloaded
+    /// contents is checked.
+    pub async fn get(&self, digest: &Sha256Digest, path: &Path) -> Result<Option<u64>> {
+        let mut response = self.client.get(self.artifact_url(digest)).send().await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        response = response.error_for_status()?;
+
+        let mut file = Sha256Stream::new(File::create(path).await?);
+        let mut size = 0;
+        while let Some(chunk) = response.chunk().await? {
+            file.write_all(&chunk).await?;
+            size += chunk.len() as u64;
+        }
+        file.flush().await?;
+        let (_, actual_digest) = file.finalize();
+        actual_digest.verify(digest)?;
+        Ok(Some(size))
+    }
+
+    /// Upload the artifact in the file at `path`.
+    pub async fn put(&self, digest: &Sha256Digest, path: &Path) -> Result<()> {
+        let file = File::open(path).await?;
+        let size = file.metadata().await?.len();
+        self.client
+            .put(self.artifact_url(digest))
+            .header(CONTENT_LENGTH, size)
+            .body(file)
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        collections::HashMap,
+        io::{BufRead as _, BufReader, Read as _, Write as _},
+        net::{TcpListener, TcpStream},
+        sync::{Arc, Mutex},
+        thread,
+    };
+    use tempfile::TempDir;
+
+    type Objects = Arc<Mutex<HashMap<String, Vec<u8>>>>;
+
+    /// A minimal stand-in for an HTTP CAS. It stores the bodies of `PUT`s in memory and returns
+    /// them for `GET`s. It doesn't check digests, so tests can plant bad objects.
+    struct TestServer {
+        url: String,
+        objects: Objects,
+    }
+
+    impl TestServer {
+        fn new() -> Self {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let url = format!("http://{}/cas", listener.local_addr().unwrap());
+            let objects = Objects::default();
+            let objects_clone = objects.clone();
+            thread::spawn(move || {
+                for stream in listener.incoming() {
+                    let objects = objects_clone.clone();
+                    thread::spawn(move || Self::handle_connection(stream.unwrap(), objects));
+                }
+            });
+            Self { url, objects }
+        }
+
+        fn handle_connection(stream: TcpStream, objects: Objects) {
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut stream = stream;
+            loop {
+                let mut request_line = String::new();
+                if reader.read_line(&mut request_line).unwrap() == 0 {
+                    return;
+                }
+                let mut parts = request_line.split_whitespace();
+                let method = parts.next().unwrap().to_owned();
+                let path = parts.next().unwrap().to_owned();
+                let mut content_length = 0;
+                loop {
+                    let mut header = String::new();
+                    reader.read_line(&mut header).unwrap();
+                    let header = header.trim_end();
+                    if header.is_empty() {
+                        break;
+                    }
+                    let (name, value) = header.split_once(':').unwrap();
+                    if name.eq_ignore_ascii_case("content-length") {
+                        content_length = value.trim().parse().unwrap();
+                    }
+                }
+                let mut body = vec![0; content_length];
+                reader.read_exact(&mut body).unwrap();
+
+                let (status, body) = match method.as_str() {
+                    "PUT" => {
+                        objects.lock().unwrap().insert(path, body);
+                        ("200 OK", vec![])
+                    }
+                    "GET" => match objects.lock().unwrap().get(&path) {
+                        Some(body) => ("200 OK", body.clone()),
+                        None => ("404 Not Found", vec![]),
+                    },
+                    _ => ("405 Method Not Allowed", vec![]),
+                };
+                write!(
+                    stream,
+                    "HTTP/1.1 {status}\r\nContent-Length: {}\r\n\r\n",
+                    body.len()
+                )
+                .unwrap();
+                stream.write_all(&body).unwrap();
+            }
+        }
+    }
+
+    fn digest_of(contents: &[u8]) -> Sha256Digest {
+        let mut stream = Sha256Stream::new(std::io::sink());
+        stream.write_all(contents).unwrap();
+        stream.finalize().1
+    }
+
+    #[tokio::test]
+    async fn get_missing() {
+        let server = TestServer::new();
+        let store = ArtifactStore::new(&server.url).unwrap();
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("artifact");
+        assert_eq!(store.get(&digest_of(b"foo"), &path).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn put_then_get() {
+        let server = TestServer::new();
+        let store = ArtifactStore::new(&server.url).unwrap();
+        let dir = TempDir::new().unwrap();
+        let digest = digest_of(b"some artifact contents");
+        let source = dir.path().join("source");
+        std::fs::write(&source, b"some artifact contents").unwrap();
+
+        store.put(&digest, &source).await.unwrap();
+        assert!(server
+            .objects
+            .lock()
+            .unwrap()
+            .contains_key(&format!("/cas/{digest}")));
+
+        let target = dir.path().join("target");
+        assert_eq!(store.get(&digest, &target).await.unwrap(), Some(22));
+        assert_eq!(std::fs::read(&target).unwrap(), b"some artifact contents");
+    }
+
+    #[tokio::test]
+    async fn get_wrong_contents() {
+        let server = TestServer::new();
+        let store = ArtifactStore::new(&format!("{}/", server.url)).unwrap();
+        let dir = TempDir::new().unwrap();
+        let digest = digest_of(b"foo");
+        server
+            .objects
+            .lock()
+            .unwrap()
+            .insert(format!("/cas/{digest}"), b"bar".to_vec());
+
+        let err = store
+            .get(&digest, &dir.path().join("artifact"))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("mismatch"), "{err}");
+    }
+}
@@ -67,6 +67,12 @@ pub struct Config {
     #[config(short = 'H', value_name = "PORT", default = "0")]
     pub http_port: HttpPort,
 
+    /// Enable the `POST` endpoints of the HTTP API, which drain and disconnect workers and cancel
+    /// clients' jobs. Requests to them must have a `Content-Type` of `application/json`.
+    #[cfg(feature = "web-ui")]
+    #[config(flag)]
+    pub http_admin_api: bool,
+
     /// The directory to use for the cache.
     #[config(
         value_name = "PATH",
@@ -84,6 +90,11 @@ pub struct Config {
     #[config(value_name = "BYTES", default = "bytesize::ByteSize::gb(1)")]
     pub cache_size: CacheSize,
 
+    /// The URL of an external content-addressed store to back the cache with. Artifacts are read
+    /// and written with HTTP GET and PUT requests at `<URL>/<DIGEST>`.
+    #[config(option, value_name = "URL", default = r#""no artifact store""#)]
+    pub artifact_store_url: Option<String>,
+
     /// Controls how we deal with artifacts when communicating with clients and workers.
     #[config(
         value_name = "ARTIFACT_TRANSFER_STRATEGY",
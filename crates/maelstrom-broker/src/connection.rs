@@ -11,6 +11,7 @@ use serde::Serialize;
 use slog::{debug, error, info, o, warn, Logger};
 use std::{
     future::Future,
+    net::SocketAddr,
     sync::{Arc, Mutex},
     thread,
 };
@@ -155,6 +156,11 @@ async fn unassigned_tcp_connection_main<TempFileFactoryT>(
                                     cid, digest, location,
                                 )
                             }
+                            ClientToBroker::FindMissingArtifacts(digests) => {
+                                scheduler_task::Message::FindMissingArtifactsFromClient(
+                                    cid, digests,
+                                )
+                            }
                         },
                         &log_clone,
                     )
@@ -172,18 +178,31 @@ async fn unassigned_tcp_connection_main<TempFileFactoryT>(
             .await;
             debug!(log, "client disconnected");
         }
-        Ok(Hello::Worker { slots }) => {
+        Ok(Hello::Worker {
+            slots,
+            artifact_server_port,
+        }) => {
+            // The worker serves artifacts on the same address it connected to us from.
+            let artifact_server = artifact_server_port
+                .and_then(|port| Some(SocketAddr::new(socket.peer_addr().ok()?.ip(), port)));
             let (read_stream, write_stream) = socket.into_split();
             let read_stream = BufReader::new(read_stream);
             let wid: WorkerId = id_vendor.vend();
             let log = log.new(o!("wid" => wid.to_string(), "slots" => slots));
-            info!(log, "worker connected");
+            info!(log, "worker connected"; "artifact_server" => ?artifact_server);
             let log_clone = log.clone();
             let log_clone2 = log.clone();
             connection_main(
                 scheduler_task_sender,
                 wid,
-                |id, sender| scheduler_task::Message::WorkerConnected(id, slots as usize, sender),
+                |id, sender| {
+                    scheduler_task::Message::WorkerConnected(
+                        id,
+                        slots as usize,
+                        artifact_server,
+                        sender,
+                    )
+                },
                 scheduler_task::Message::WorkerDisconnected,
                 |scheduler_task_sender| async move {
                     let _ = net::async_socket_reader(
@@ -196,6 +215,9 @@ async fn unassigned_tcp_connection_main<TempFileFactoryT>(
                             WorkerToBroker::JobStatusUpdate(jid, status) => {
                                 scheduler_task::Message::JobStatusUpdateFromWorker(wid, jid, status)
                             }
+                            WorkerToBroker::DiskSpaceLow(low) => {
+                                scheduler_task::Message::DiskSpaceLowFromWorker(wid, low)
+                            }
                         },
                         &log_clone,
                     )
@@ -234,6 +256,14 @@ async fn unassigned_tcp_connection_main<TempFileFactoryT>(
                             MonitorToBroker::StatisticsRequest => {
                                 scheduler_task::Message::StatisticsRequestFromMonitor(mid)
                             }
+                            MonitorToBroker::JobsRequest => {
+                                scheduler_task::Message::JobsRequestFromMonitor(mid)
+                            }
+                            MonitorToBroker::ClientStatisticsRequest(cid) => {
+                                scheduler_task::Message::ClientStatisticsRequestFromMonitor(
+                                    mid, cid,
+                                )
+                            }
                         },
                         &log_clone,
                     )
@@ -322,7 +352,8 @@ async fn unassigned_github_connection_main<TempFileT>(
         Ok(Some(Hello::Client)) => {
             warn!(log, "github queue said it was client");
         }
-        Ok(Some(Hello::Worker { slots })) => {
+        // Workers connected through GitHub can't serve artifacts to each other.
+        Ok(Some(Hello::Worker { slots, .. })) => {
             let wid: WorkerId = id_vendor.vend();
             let log = log.new(o!("wid" => wid.to_string(), "slots" => slots));
             info!(log, "worker connected");
@@ -334,7 +365,9 @@ async fn unassigned_github_connection_main<TempFileT>(
             connection_main(
                 scheduler_task_sender,
                 wid,
-                |id, sender| scheduler_task::Message::WorkerConnected(id, slots as usize, sender),
+                |id, sender| {
+                    scheduler_task::Message::WorkerConnected(id, slots as usize, None, sender)
+                },
                 scheduler_task::Message::WorkerDisconnected,
                 |scheduler_task_sender| async move {
                     let _ = net::github_queue_reader(
@@ -347,6 +380,9 @@ async fn unassigned_github_connection_main<TempFileT>(
                             WorkerToBroker::JobStatusUpdate(jid, status) => {
                                 scheduler_task::Message::JobStatusUpdateFromWorker(wid, jid, status)
                             }
+                            WorkerToBroker::DiskSpaceLow(low) => {
+                                scheduler_task::Message::DiskSpaceLowFromWorker(wid, low)
+                            }
                         },
                         &log_clone,
                     )
@@ -1,24 +1,35 @@
 //! The task main and all associated code to implement the HTTP server for the broker.
 //!
-//! The HTTP server is responsible for doing two things.
+//! The HTTP server is responsible for doing four things.
 //!
 //! First, it serves up the actual website. This is prebuilt, including all of the Wasm, and put in
 //! a tar file. The tar file is then embedded in this module as compile time.
 //!
 //! Second, it handles WebSockets. These are treated just like monitor connections.
+//!
+//! Third, it serves `/metrics` in the Prometheus text format. The metrics are rendered by the
+//! scheduler task on request.
+//!
+//! Fourth, it serves a JSON API under `/api`. This exposes snapshots of the scheduler task's state,
+//! and, if enabled, lets administrators drain or disconnect workers and cancel clients' jobs.
 use crate::{connection, scheduler_task, IdVendor};
-use anyhow::{Error, Result};
+use anyhow::{anyhow, Error, Result};
 use futures::{
     sink::SinkExt,
     stream::{SplitSink, SplitStream, StreamExt},
 };
-use hyper::{server::conn::Http, service::Service, upgrade::Upgraded, Body, Request, Response};
+use hyper::{
+    header::CONTENT_TYPE, server::conn::Http, service::Service, upgrade::Upgraded, Body, HeaderMap,
+    Method, Request, Response,
+};
 use hyper_tungstenite::{tungstenite, HyperWebsocket, WebSocketStream};
 use maelstrom_base::{
     proto::{self, BrokerToMonitor, MonitorToBroker},
-    MonitorId,
+    ClientId, MonitorId, WorkerId,
 };
+use maelstrom_util::metrics;
 use maelstrom_web::WASM_TAR;
+use serde::Serialize;
 use slog::{debug, error, o, Logger};
 use std::{
     collections::HashMap,
@@ -29,7 +40,10 @@ use std::{
     task::{Context, Poll},
 };
 use tar::Archive;
-use tokio::{net::TcpListener, sync::mpsc::UnboundedReceiver};
+use tokio::{
+    net::TcpListener,
+    sync::{mpsc::UnboundedReceiver, oneshot},
+};
 use tungstenite::Message;
 
 pub struct TarHandler {
@@ -119,15 +133,17 @@ async fn websocket_reader<TempFileT>(
         let Ok(msg) = proto::deserialize(&msg) else {
             break;
         };
-        match msg {
+        let msg = match msg {
             MonitorToBroker::StatisticsRequest => {
-                if scheduler_task_sender
-                    .send(scheduler_task::Message::StatisticsRequestFromMonitor(mid))
-                    .is_err()
-                {
-                    break;
-                }
+                scheduler_task::Message::StatisticsRequestFromMonitor(mid)
             }
+            MonitorToBroker::JobsRequest => scheduler_task::Message::JobsRequestFromMonitor(mid),
+            MonitorToBroker::ClientStatisticsRequest(cid) => {
+                scheduler_task::Message::ClientStatisticsRequestFromMonitor(mid, cid)
+            }
+        };
+        if scheduler_task_sender.send(msg).is_err() {
+            break;
         }
     }
 }
@@ -163,10 +179,148 @@ async fn websocket_main<TempFileT>(
     debug!(log, "received websocket monitor disconnect")
 }
 
+/// Ask the scheduler task to render the current metrics, and return them as a response.
+async fn metrics_response<TempFileT>(
+    scheduler_task_sender: scheduler_task::Sender<TempFileT>,
+    log: Logger,
+) -> Result<Response<Body>> {
+    let (sender, receiver) = oneshot::channel();
+    scheduler_task_sender
+        .send(scheduler_task::Message::MetricsRequest(sender))
+        .map_err(|_| anyhow!("scheduler task has exited"))?;
+    let body = receiver.await?;
+    debug!(log, "received http get request"; "path" => "/metrics", "resp" => 200);
+    Ok(Response::builder()
+        .status(200)
+        .header("Content-Type", metrics::CONTENT_TYPE)
+        .body(Body::from(body))
+        .unwrap())
+}
+
+/// Send a request to the scheduler task and wait for its response.
+async fn scheduler_task_request<TempFileT, T>(
+    scheduler_task_sender: &scheduler_task::Sender<TempFileT>,
+    message: impl FnOnce(oneshot::Sender<T>) -> scheduler_task::Message<TempFileT>,
+) -> Result<T> {
+    let (sender, receiver) = oneshot::channel();
+    scheduler_task_sender
+        .send(message(sender))
+        .map_err(|_| anyhow!("scheduler task has exited"))?;
+    Ok(receiver.await?)
+}
+
+fn json_response(status: u16, body: &impl Serialize) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header("Content-Type", "application/json")
+        .body(Body::from(serde_json::to_vec(body).unwrap()))
+        .unwrap()
+}
+
+fn empty_response(status: u16) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .body(Body::from(&b""[..]))
+        .unwrap()
+}
+
+/// Return whether the request's `Content-Type` is `application/json`, ignoring any parameters.
+fn has_json_content_type(headers: &HeaderMap) -> bool {
+    headers
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(';').next())
+        .is_some_and(|mime| mime.trim().eq_ignore_ascii_case("application/json"))
+}
+
+/// Handle a request for the JSON API. The `path` has already had its `/api/` prefix removed.
+///
+/// The `GET` endpoints return snapshots of the scheduler task's state. The `POST` endpoints take
+/// administrative actions, and return 404 if the given worker or client doesn't exist. They return
+/// 403 unless `admin_api` is set. They also return 415 unless the request has a JSON
+/// `Content-Type`. Browsers won't send a cross-origin request with that content type without a
+/// CORS preflight, which we never approve, so other web sites can't use a visitor's browser to
+/// take these actions.
+async fn api_response<TempFileT>(
+    method: Method,
+    path: String,
+    json_content_type: bool,
+    admin_api: bool,
+    scheduler_task_sender: scheduler_task::Sender<TempFileT>,
+    log: Logger,
+) -> Result<Response<Body>> {
+    use scheduler_task::Message::*;
+    let sender = &scheduler_task_sender;
+    let segments: Vec<_> = path.split('/').collect();
+    let resp = match (&method, segments.as_slice()) {
+        (&Method::GET, ["clients"]) => {
+            json_response(200, &scheduler_task_request(sender, ClientsRequest).await?)
+        }
+        (&Method::GET, ["workers"]) => {
+            json_response(200, &scheduler_task_request(sender, WorkersRequest).await?)
+        }
+        (&Method::GET, ["jobs"]) => {
+            json_response(200, &scheduler_task_request(sender, JobsRequest).await?)
+        }
+        (&Method::GET, ["artifacts"]) => json_response(
+            200,
+            &scheduler_task_request(sender, ArtifactGathererRequest).await?,
+        ),
+        (&Method::POST, ["workers", _, "drain" | "disconnect"] | ["clients", _, "cancel"])
+            if !admin_api =>
+        {
+            empty_response(403)
+        }
+        (&Method::POST, ["workers", _, "drain" | "disconnect"] | ["clients", _, "cancel"])
+            if !json_content_type =>
+        {
+            empty_response(415)
+        }
+        (&Method::POST, ["workers", wid, action @ ("drain" | "disconnect")]) => {
+            let found = match wid.parse::<u32>() {
+                Ok(wid) => {
+                    let wid = WorkerId::from(wid);
+                    if *action == "drain" {
+                        scheduler_task_request(sender, |s| DrainWorker(wid, s)).await?
+                    } else {
+                        scheduler_task_request(sender, |s| DisconnectWorker(wid, s)).await?
+                    }
+                }
+                Err(_) => false,
+            };
+            empty_response(if found { 204 } else { 404 })
+        }
+        (&Method::POST, ["clients", cid, "cancel"]) => {
+            let canceled = match cid.parse::<u32>() {
+                Ok(cid) => {
+                    let cid = ClientId::from(cid);
+                    scheduler_task_request(sender, |s| CancelClientJobs(cid, s)).await?
+                }
+                Err(_) => None,
+            };
+            match canceled {
+                Some(canceled) => json_response(200, &HashMap::from([("canceled", canceled)])),
+                None => empty_response(404),
+            }
+        }
+        (
+            _,
+            ["clients" | "workers" | "jobs" | "artifacts"]
+            | ["workers", _, "drain" | "disconnect"]
+            | ["clients", _, "cancel"],
+        ) => empty_response(405),
+        _ => empty_response(404),
+    };
+    debug!(log, "received http api request";
+        "method" => %method, "path" => %path, "resp" => resp.status().as_u16());
+    Ok(resp)
+}
+
 struct Handler<TempFileT> {
     tar_handler: Arc<TarHandler>,
     scheduler_task_sender: scheduler_task::Sender<TempFileT>,
     id_vendor: Arc<IdVendor>,
+    admin_api: bool,
     log: Logger,
 }
 
@@ -183,6 +337,23 @@ where
     }
 
     fn call(&mut self, mut request: Request<Body>) -> Self::Future {
+        if request.uri().path() == "/metrics" {
+            return Box::pin(metrics_response(
+                self.scheduler_task_sender.clone(),
+                self.log.clone(),
+            ));
+        }
+        if let Some(path) = request.uri().path().strip_prefix("/api/") {
+            return Box::pin(api_response(
+                request.method().clone(),
+                path.to_owned(),
+                has_json_content_type(request.headers()),
+                self.admin_api,
+                self.scheduler_task_sender.clone(),
+                self.log.clone(),
+            ));
+        }
+
         let resp = (|| {
             if hyper_tungstenite::is_upgrade_request(&request) {
                 let (response, websocket) = hyper_tungstenite::upgrade(&mut request, None)?;
@@ -208,6 +379,7 @@ pub async fn listener_main<TempFileT>(
     listener: TcpListener,
     scheduler_task_sender: scheduler_task::Sender<TempFileT>,
     id_vendor: Arc<IdVendor>,
+    admin_api: bool,
     log: Logger,
 ) where
     TempFileT: Send + Sync + 'static,
@@ -230,6 +402,7 @@ pub async fn listener_main<TempFileT>(
                             tar_handler: tar_handler.clone(),
                             scheduler_task_sender: scheduler_task_sender.clone(),
                             id_vendor: id_vendor.clone(),
+                            admin_api,
                             log,
                         },
                     )
@@ -9,13 +9,16 @@ mod connection;
 mod http;
 mod scheduler_task;
 
-use anyhow::{anyhow, Context as _, Result};
-use cache::{github::GithubCache, local::TcpUploadLocalCache, BrokerCache, SchedulerCache};
+use anyhow::{anyhow, bail, Context as _, Result};
+use cache::{
+    github::GithubCache, local::TcpUploadLocalCache, store::ArtifactStore, BrokerCache,
+    SchedulerCache, TempFileFactory as _,
+};
 use config::Config;
 use maelstrom_base::stats::BROKER_STATISTICS_INTERVAL;
 use maelstrom_github::GitHubClient;
 use maelstrom_util::config::common::ArtifactTransferStrategy;
-use scheduler_task::SchedulerTask;
+use scheduler_task::{ArtifactStoreAccess, SchedulerTask};
 use slog::{error, info, Logger};
 use std::{
     net::{Ipv6Addr, SocketAddrV6},
@@ -89,12 +92,29 @@ async fn main_inner_inner<BrokerCacheT>(
 ) -> Result<()>
 where
     BrokerCacheT: BrokerCache,
-    <BrokerCacheT::Cache as SchedulerCache>::TempFile: Send + Sync + 'static,
+    <BrokerCacheT::Cache as SchedulerCache>::TempFile:
+        maelstrom_util::cache::fs::TempFile + Send + Sync + 'static,
     <BrokerCacheT::Cache as SchedulerCache>::ArtifactStream:
         tokio::io::AsyncRead + Unpin + Send + 'static,
 {
+    let artifact_store = config
+        .artifact_store_url
+        .as_deref()
+        .map(ArtifactStore::new)
+        .transpose()
+        .context("creating artifact store")?;
+    #[cfg(feature = "web-ui")]
+    let http_admin_api = config.http_admin_api;
     let (cache, temp_file_factory) = BrokerCacheT::new(config, log.clone())?;
-    let scheduler_task = SchedulerTask::new(cache);
+    let artifact_store_access = artifact_store.map(|store| {
+        let temp_file_factory = temp_file_factory.clone();
+        ArtifactStoreAccess {
+            store,
+            temp_file_factory: Box::new(move || temp_file_factory.temp_file()),
+            log: log.clone(),
+        }
+    });
+    let scheduler_task = SchedulerTask::new(cache, artifact_store_access);
     let id_vendor = Arc::new(IdVendor {
         id: AtomicU32::new(0),
     });
@@ -107,6 +127,7 @@ where
         listeners.http_listener,
         scheduler_task.scheduler_task_sender().clone(),
         id_vendor.clone(),
+        http_admin_api,
         log.clone(),
     ));
     join_set.spawn(connection::tcp_listener_main(
@@ -215,6 +236,9 @@ async fn main_inner(config: Config, log: Logger) -> Result<()> {
 
     match config.artifact_transfer_strategy {
         ArtifactTransferStrategy::GitHub => {
+            if config.artifact_store_url.is_some() {
+                bail!("an artifact store can't be used with the GitHub artifact transfer strategy");
+            }
             main_inner_inner::<GithubCache>(listeners, config, log.clone()).await?;
         }
         ArtifactTransferStrategy::TcpUpload => {
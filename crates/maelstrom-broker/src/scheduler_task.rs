@@ -1,21 +1,28 @@
+pub mod api;
 mod artifact_gatherer;
 mod scheduler;
 
-use crate::cache::SchedulerCache;
+use crate::cache::{store::ArtifactStore, SchedulerCache};
+use api::{ArtifactGathererInfo, ClientInfo, JobInfo, WorkerInfo};
 use artifact_gatherer::{ArtifactGatherer, StartJob};
+use futures::{stream, StreamExt as _};
 use maelstrom_base::{
     manifest::{ManifestEntryData, ManifestFileData},
     proto::{BrokerToClient, BrokerToMonitor, BrokerToWorker},
-    stats::BrokerStatistics,
+    stats::{BrokerStatistics, ClientStatistics, JobStatistics},
     ArtifactType, ArtifactUploadLocation, ClientId, ClientJobId, JobBrokerStatus, JobId,
     JobOutcomeResult, JobSpec, JobWorkerStatus, MonitorId, NonEmpty, Sha256Digest, WorkerId,
 };
-use maelstrom_util::{manifest::AsyncManifestReader, sync};
+use maelstrom_util::{cache::fs::TempFile, manifest::AsyncManifestReader, metrics::Encoder, sync};
 use scheduler::Scheduler;
-use std::{path::PathBuf, sync::mpsc::Sender as SyncSender};
+use slog::{warn, Logger};
+use std::{net::SocketAddr, path::PathBuf, sync::mpsc::Sender as SyncSender, time::Instant};
 use tokio::{
     io::AsyncRead,
-    sync::mpsc::{self as tokio_mpsc, UnboundedReceiver, UnboundedSender},
+    sync::{
+        mpsc::{self as tokio_mpsc, UnboundedReceiver, UnboundedSender},
+        oneshot,
+    },
     task::{self, JoinSet},
 };
 
@@ -81,6 +88,27 @@ impl scheduler::Deps for PassThroughSchedulerDeps {
     ) {
         let _ = sender.send(BrokerToMonitor::StatisticsResponse(statistics));
     }
+
+    fn send_jobs_response_to_monitor(
+        &mut self,
+        sender: &mut Self::MonitorSender,
+        jobs: Vec<JobStatistics>,
+    ) {
+        let _ = sender.send(BrokerToMonitor::JobsResponse(jobs));
+    }
+
+    fn send_client_statistics_response_to_monitor(
+        &mut self,
+        sender: &mut Self::MonitorSender,
+        cid: ClientId,
+        statistics: Option<ClientStatistics>,
+    ) {
+        let _ = sender.send(BrokerToMonitor::ClientStatisticsResponse(cid, statistics));
+    }
+
+    fn now(&mut self) -> Instant {
+        Instant::now()
+    }
 }
 
 impl<CacheT, DepsT> scheduler::ArtifactGatherer for ArtifactGatherer<CacheT, DepsT>
@@ -125,20 +153,125 @@ where
  *  FIGLET: AritifactGatherer Dependencies
  */
 
-#[derive(Debug)]
+/// What's needed to look for artifacts in an [`ArtifactStore`] before asking clients for them,
+/// and to copy the artifacts clients upload to it.
+pub struct ArtifactStoreAccess<TempFileT> {
+    pub store: ArtifactStore,
+    pub temp_file_factory: Box<dyn Fn() -> anyhow::Result<TempFileT> + Send>,
+    pub log: Logger,
+}
+
+/// How many artifacts we look for in the store at once when looking for a batch of them.
+const MAX_SIMULTANEOUS_STORE_LOOKUPS: usize = 16;
+
+/// How to tell a client about the artifacts that weren't in the store.
+#[derive(Clone, Copy)]
+enum StoreMisses {
+    /// Send a [`BrokerToClient::TransferArtifact`] for each one.
+    TransferArtifact,
+    /// Send a single [`BrokerToClient::MissingArtifacts`] listing all of them.
+    MissingArtifacts,
+}
+
+impl<TempFileT> ArtifactStoreAccess<TempFileT>
+where
+    TempFileT: TempFile + Send + Sync + 'static,
+{
+    /// Look for the artifacts in the store. Those that are there are incorporated as if a client
+    /// had uploaded them. The client is asked to upload the rest.
+    fn lookup(
+        &self,
+        task_sender: &Sender<TempFileT>,
+        client_sender: &UnboundedSender<BrokerToClient>,
+        digests: Vec<Sha256Digest>,
+        misses: StoreMisses,
+    ) {
+        let lookups: Vec<_> = digests
+            .into_iter()
+            .map(|digest| {
+                let temp_file = (self.temp_file_factory)().ok();
+                let store = self.store.clone();
+                async move {
+                    if let Some(temp_file) = temp_file {
+                        if let Ok(Some(size)) = store.get(&digest, temp_file.path()).await {
+                            return Ok((digest, size, temp_file));
+                        }
+                    }
+                    Err(digest)
+                }
+            })
+            .collect();
+        let task_sender = task_sender.clone();
+        let client_sender = client_sender.clone();
+        task::spawn(async move {
+            let mut results =
+                stream::iter(lookups).buffer_unordered(MAX_SIMULTANEOUS_STORE_LOOKUPS);
+            let mut missing = vec![];
+            while let Some(result) = results.next().await {
+                match result {
+                    Ok((digest, size, temp_file)) => {
+                        let _ = task_sender
+                            .send(Message::ArtifactFetchedFromStore(digest, size, temp_file));
+                    }
+                    Err(digest) => missing.push(digest),
+                }
+            }
+            match misses {
+                StoreMisses::TransferArtifact => {
+                    for digest in missing {
+                        let _ = client_sender.send(BrokerToClient::TransferArtifact(digest));
+                    }
+                }
+                StoreMisses::MissingArtifacts => {
+                    let _ = client_sender.send(BrokerToClient::MissingArtifacts(missing));
+                }
+            }
+        });
+    }
+}
+
+/// Copy the artifact at `path` to the store in the background. Failing to do so isn't fatal: we
+/// still have the artifact, it just won't outlive us. Either way, we tell the task when we're done,
+/// so that it can release its reference on the artifact.
+fn write_artifact_to_store<TempFileT: Send + 'static>(
+    store: ArtifactStore,
+    task_sender: Sender<TempFileT>,
+    digest: Sha256Digest,
+    path: PathBuf,
+    log: Logger,
+) {
+    task::spawn(async move {
+        if let Err(err) = store.put(&digest, &path).await {
+            warn!(log, "error writing artifact to artifact store";
+                "digest" => %digest, "error" => %err);
+        }
+        let _ = task_sender.send(Message::ArtifactWrittenToStore(digest));
+    });
+}
+
+type ArtifactStoreLookup =
+    Box<dyn Fn(&UnboundedSender<BrokerToClient>, Vec<Sha256Digest>, StoreMisses) + Send>;
+type ArtifactStoreWrite = Box<dyn Fn(Sha256Digest, PathBuf) + Send>;
+
 struct PassThroughArtifactGathererDeps<TempFileT, ArtifactStreamT> {
     task_sender: Sender<TempFileT>,
     manifest_reader_sender: UnboundedSender<ManifestReadRequest<ArtifactStreamT>>,
+    artifact_store_lookup: Option<ArtifactStoreLookup>,
+    artifact_store_write: Option<ArtifactStoreWrite>,
 }
 
 impl<TempFileT, ArtifactStreamT> PassThroughArtifactGathererDeps<TempFileT, ArtifactStreamT> {
     fn new(
         task_sender: Sender<TempFileT>,
         manifest_reader_sender: UnboundedSender<ManifestReadRequest<ArtifactStreamT>>,
+        artifact_store_lookup: Option<ArtifactStoreLookup>,
+        artifact_store_write: Option<ArtifactStoreWrite>,
     ) -> Self {
         Self {
             task_sender,
             manifest_reader_sender,
+            artifact_store_lookup,
+            artifact_store_write,
         }
     }
 }
@@ -174,7 +307,30 @@ impl<TempFileT, ArtifactStreamT> artifact_gatherer::Deps
         sender: &mut Self::ClientSender,
         digest: Sha256Digest,
     ) {
-        let _ = sender.send(BrokerToClient::TransferArtifact(digest));
+        // If we have an artifact store, look there first, and only ask the client if the store
+        // doesn't have the artifact.
+        match &self.artifact_store_lookup {
+            Some(lookup) => lookup(sender, vec![digest], StoreMisses::TransferArtifact),
+            None => {
+                let _ = sender.send(BrokerToClient::TransferArtifact(digest));
+            }
+        }
+    }
+
+    fn send_missing_artifacts_to_client(
+        &mut self,
+        sender: &mut Self::ClientSender,
+        digests: Vec<Sha256Digest>,
+    ) {
+        // Like above, only tell the client about the artifacts the store doesn't have.
+        match &self.artifact_store_lookup {
+            Some(lookup) if !digests.is_empty() => {
+                lookup(sender, digests, StoreMisses::MissingArtifacts)
+            }
+            _ => {
+                let _ = sender.send(BrokerToClient::MissingArtifacts(digests));
+            }
+        }
     }
 
     fn send_general_error_to_client(&mut self, sender: &mut Self::ClientSender, error: String) {
@@ -192,6 +348,12 @@ impl<TempFileT, ArtifactStreamT> artifact_gatherer::Deps
             .task_sender
             .send(Message::JobsFailedFromArtifactGatherer(jobs, err));
     }
+
+    fn send_artifact_to_store(&mut self, digest: Sha256Digest, path: PathBuf) {
+        if let Some(write) = &self.artifact_store_write {
+            write(digest, path);
+        }
+    }
 }
 
 /*   ____           _          __  __             _  __           _
@@ -282,6 +444,7 @@ where
 ///
 /// If [`Scheduler`] weren't implement as an async state machine, these would be its methods.
 #[derive(Debug)]
+#[allow(clippy::large_enum_variant)]
 pub enum Message<
     TempFileT,
     ClientSenderT = UnboundedSender<BrokerToClient>,
@@ -298,10 +461,11 @@ pub enum Message<
     /// The given client has sent us the given message.
     JobRequestFromClient(ClientId, ClientJobId, JobSpec),
     ArtifactTransferredFromClient(ClientId, Sha256Digest, ArtifactUploadLocation),
+    FindMissingArtifactsFromClient(ClientId, Vec<Sha256Digest>),
 
-    /// The given worker connected. It has the given number of slots and messages can be sent to it
-    /// on the given sender.
-    WorkerConnected(WorkerId, usize, WorkerSenderT),
+    /// The given worker connected. It has the given number of slots, serves artifacts to other
+    /// workers on the given address, if any, and messages can be sent to it on the given sender.
+    WorkerConnected(WorkerId, usize, Option<SocketAddr>, WorkerSenderT),
 
     /// The given worker disconnected.
     WorkerDisconnected(WorkerId),
@@ -309,6 +473,7 @@ pub enum Message<
     /// The given worker has sent us the given message.
     JobResponseFromWorker(WorkerId, JobId, JobOutcomeResult),
     JobStatusUpdateFromWorker(WorkerId, JobId, JobWorkerStatus),
+    DiskSpaceLowFromWorker(WorkerId, bool),
 
     /// The given monitor connected, and messages can be sent to it on the given sender.
     MonitorConnected(MonitorId, MonitorSenderT),
@@ -316,12 +481,18 @@ pub enum Message<
     /// The given monitor disconnected.
     MonitorDisconnected(MonitorId),
 
-    /// The given client has sent us the given message.
+    /// The given monitor has sent us the given message.
     StatisticsRequestFromMonitor(MonitorId),
+    JobsRequestFromMonitor(MonitorId),
+    ClientStatisticsRequestFromMonitor(MonitorId, ClientId),
 
     /// An artifact has been pushed to us. The artifact has the given digest and length. It is
     /// temporarily stored at the given path.
-    GotArtifact(Sha256Digest, TempFileT),
+    GotArtifact(Sha256Digest, u64, TempFileT),
+
+    /// An artifact was found in the artifact store instead of being requested from a client. The
+    /// artifact has the given digest and length. It is temporarily stored at the given path.
+    ArtifactFetchedFromStore(Sha256Digest, u64, TempFileT),
 
     /// A worker has requested the given artifact be sent to it over the given sender. After the
     /// contents are sent to the worker, the refcount needs to be decremented with a
@@ -332,6 +503,15 @@ pub enum Message<
     /// the artifact from being removed while being transferred.
     DecrementRefcount(Sha256Digest),
 
+    /// An artifact that a client uploaded has been copied to the artifact store, or we gave up
+    /// trying. We can now release the refcount that was keeping the artifact from being removed
+    /// while being copied.
+    ArtifactWrittenToStore(Sha256Digest),
+
+    /// A worker has requested the given artifact, and is willing to get it from another worker.
+    /// The address of a worker that should have it, if any, is sent back on the given sender.
+    GetPeerForArtifact(Sha256Digest, oneshot::Sender<Option<SocketAddr>>),
+
     /// The stats heartbeat task has decided it's time to take another statistics sample.
     StatisticsHeartbeat,
 
@@ -352,6 +532,37 @@ pub enum Message<
 
     /// The ArtifactGatherer has encountered an error gatherering artifacts for the given job.
     JobsFailedFromArtifactGatherer(NonEmpty<JobId>, String),
+
+    /// Someone wants the current metrics, encoded in the Prometheus text format, sent back on the
+    /// given sender. These come from the HTTP listener, which only exists with the `web-ui`
+    /// feature.
+    #[cfg_attr(not(feature = "web-ui"), allow(dead_code))]
+    MetricsRequest(oneshot::Sender<String>),
+
+    /// The following are requests from the broker's JSON API, which is served by the HTTP
+    /// listener. Each gets its response sent back on the given sender.
+    #[cfg_attr(not(feature = "web-ui"), allow(dead_code))]
+    ClientsRequest(oneshot::Sender<Vec<ClientInfo>>),
+    #[cfg_attr(not(feature = "web-ui"), allow(dead_code))]
+    WorkersRequest(oneshot::Sender<Vec<WorkerInfo>>),
+    #[cfg_attr(not(feature = "web-ui"), allow(dead_code))]
+    JobsRequest(oneshot::Sender<Vec<JobInfo>>),
+    #[cfg_attr(not(feature = "web-ui"), allow(dead_code))]
+    ArtifactGathererRequest(oneshot::Sender<ArtifactGathererInfo>),
+
+    /// Stop sending jobs to the given worker. The response is false if there is no such worker.
+    #[cfg_attr(not(feature = "web-ui"), allow(dead_code))]
+    DrainWorker(WorkerId, oneshot::Sender<bool>),
+
+    /// Close the connection to the given worker, requeueing its jobs. The response is false if
+    /// there is no such worker.
+    #[cfg_attr(not(feature = "web-ui"), allow(dead_code))]
+    DisconnectWorker(WorkerId, oneshot::Sender<bool>),
+
+    /// Cancel all of the given client's outstanding jobs. The response is the number of jobs
+    /// canceled, or `None` if there is no such client.
+    #[cfg_attr(not(feature = "web-ui"), allow(dead_code))]
+    CancelClientJobs(ClientId, oneshot::Sender<Option<usize>>),
 }
 
 /// This type is used often enough to warrant an alias.
@@ -383,11 +594,36 @@ pub struct SchedulerTask<CacheT: SchedulerCache> {
 impl<CacheT: SchedulerCache> SchedulerTask<CacheT>
 where
     CacheT::ArtifactStream: AsyncRead + Unpin + Send + 'static,
-    CacheT::TempFile: Send + Sync + 'static,
+    CacheT::TempFile: TempFile + Send + Sync + 'static,
 {
-    pub fn new(cache: CacheT) -> Self {
+    pub fn new(
+        cache: CacheT,
+        artifact_store: Option<ArtifactStoreAccess<CacheT::TempFile>>,
+    ) -> Self {
         let (sender, receiver) = tokio_mpsc::unbounded_channel();
 
+        let write_to_store = artifact_store.is_some();
+        let (artifact_store_lookup, artifact_store_write) = artifact_store
+            .map(|access| {
+                let (store, log, task_sender) =
+                    (access.store.clone(), access.log.clone(), sender.clone());
+                let write = Box::new(move |digest, path| {
+                    write_artifact_to_store(
+                        store.clone(),
+                        task_sender.clone(),
+                        digest,
+                        path,
+                        log.clone(),
+                    )
+                }) as ArtifactStoreWrite;
+                let task_sender = sender.clone();
+                let lookup = Box::new(move |client_sender: &_, digests, misses| {
+                    access.lookup(&task_sender, client_sender, digests, misses)
+                }) as ArtifactStoreLookup;
+                (Some(lookup), Some(write))
+            })
+            .unwrap_or_default();
+
         let (manifest_reader_sender, manifest_reader_receiver) = tokio_mpsc::unbounded_channel();
         let mut manifest_reader =
             CacheManifestReader::new(manifest_reader_receiver, sender.clone());
@@ -396,9 +632,15 @@ where
         SchedulerTask {
             artifact_gatherer: ArtifactGatherer::new(
                 cache,
-                PassThroughArtifactGathererDeps::new(sender.clone(), manifest_reader_sender),
+                PassThroughArtifactGathererDeps::new(
+                    sender.clone(),
+                    manifest_reader_sender,
+                    artifact_store_lookup,
+                    artifact_store_write,
+                ),
                 10_000_000,
                 16.try_into().unwrap(),
+                write_to_store,
             ),
             scheduler: Scheduler::new(PassThroughSchedulerDeps),
             sender,
@@ -436,9 +678,12 @@ where
             Message::ArtifactTransferredFromClient(cid, digest, location) => self
                 .artifact_gatherer
                 .receive_artifact_transferred(cid, digest, location),
-            Message::WorkerConnected(id, slots, sender) => {
-                self.scheduler.receive_worker_connected(id, slots, sender)
-            }
+            Message::FindMissingArtifactsFromClient(cid, digests) => self
+                .artifact_gatherer
+                .receive_find_missing_artifacts(cid, digests),
+            Message::WorkerConnected(id, slots, artifact_server, sender) => self
+                .scheduler
+                .receive_worker_connected(id, slots, artifact_server, sender),
             Message::WorkerDisconnected(id) => self.scheduler.receive_worker_disconnected(id),
             Message::JobResponseFromWorker(wid, jid, result) => self
                 .scheduler
@@ -446,6 +691,9 @@ where
             Message::JobStatusUpdateFromWorker(wid, jid, status) => self
                 .scheduler
                 .receive_job_status_update_from_worker(wid, jid, status),
+            Message::DiskSpaceLowFromWorker(wid, low) => {
+                self.scheduler.receive_disk_space_low_from_worker(wid, low)
+            }
             Message::MonitorConnected(id, sender) => {
                 self.scheduler.receive_monitor_connected(id, sender)
             }
@@ -453,15 +701,30 @@ where
             Message::StatisticsRequestFromMonitor(mid) => {
                 self.scheduler.receive_statistics_request_from_monitor(mid)
             }
-            Message::GotArtifact(digest, file) => {
-                self.artifact_gatherer.receive_got_artifact(digest, file)
+            Message::JobsRequestFromMonitor(mid) => {
+                self.scheduler.receive_jobs_request_from_monitor(mid)
             }
+            Message::ClientStatisticsRequestFromMonitor(mid, cid) => self
+                .scheduler
+                .receive_client_statistics_request_from_monitor(mid, cid),
+            Message::GotArtifact(digest, size, file) => self
+                .artifact_gatherer
+                .receive_got_artifact(digest, size, file),
+            Message::ArtifactFetchedFromStore(digest, size, file) => self
+                .artifact_gatherer
+                .receive_artifact_fetched_from_store(digest, size, file),
             Message::GetArtifactForWorker(digest, sender) => self
                 .artifact_gatherer
                 .receive_get_artifact_for_worker(digest, sender),
             Message::DecrementRefcount(digest) => self
                 .artifact_gatherer
                 .receive_decrement_refcount_from_worker(digest),
+            Message::ArtifactWrittenToStore(digest) => self
+                .artifact_gatherer
+                .receive_artifact_written_to_store(digest),
+            Message::GetPeerForArtifact(digest, sender) => {
+                let _ = sender.send(self.scheduler.peer_for_artifact(&digest));
+            }
             Message::StatisticsHeartbeat => self.scheduler.receive_statistics_heartbeat(),
             Message::GotManifestEntry {
                 manifest_digest,
@@ -475,12 +738,42 @@ where
                 .receive_finished_reading_manifest(digest, result),
             Message::JobsReadyFromArtifactGatherer(jobs) => {
                 self.scheduler
-                    .receive_jobs_ready_from_artifact_gatherer(jobs);
+                    .receive_jobs_ready_from_artifact_gatherer(&mut self.artifact_gatherer, jobs);
             }
             Message::JobsFailedFromArtifactGatherer(jobs, err) => {
                 self.scheduler
                     .receive_jobs_failed_from_artifact_gatherer(jobs, err);
             }
+            Message::MetricsRequest(sender) => {
+                let mut encoder = Encoder::new();
+                self.scheduler.encode_metrics(&mut encoder);
+                self.artifact_gatherer.encode_metrics(&mut encoder);
+                let _ = sender.send(encoder.finish());
+            }
+            Message::ClientsRequest(sender) => {
+                let _ = sender.send(self.scheduler.clients());
+            }
+            Message::WorkersRequest(sender) => {
+                let _ = sender.send(self.scheduler.workers());
+            }
+            Message::JobsRequest(sender) => {
+                let _ = sender.send(self.scheduler.jobs());
+            }
+            Message::ArtifactGathererRequest(sender) => {
+                let _ = sender.send(self.artifact_gatherer.info());
+            }
+            Message::DrainWorker(wid, sender) => {
+                let _ = sender.send(self.scheduler.receive_drain_worker(wid));
+            }
+            Message::DisconnectWorker(wid, sender) => {
+                let _ = sender.send(self.scheduler.receive_disconnect_worker(wid));
+            }
+            Message::CancelClientJobs(cid, sender) => {
+                let _ = sender.send(
+                    self.scheduler
+                        .receive_cancel_client_jobs(&mut self.artifact_gatherer, cid),
+                );
+            }
         })
         .await
         .unwrap();
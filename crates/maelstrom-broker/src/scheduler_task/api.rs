@@ -0,0 +1,51 @@
+//! Snapshots of the scheduler task's state, as returned by the broker's JSON API. These are
+//! produced in response to the API request messages in [`super::Message`].
+
+use maelstrom_base::{stats::JobStateCounts, ClientId, JobBrokerStatus, JobId, WorkerId};
+use serde::Serialize;
+
+/// A connected client and the number of its jobs in each state.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct ClientInfo {
+    pub id: ClientId,
+    pub jobs: JobStateCounts,
+}
+
+/// A connected worker and the jobs that have been sent to it.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct WorkerInfo {
+    pub id: WorkerId,
+    pub slots: usize,
+    pub jobs: Vec<JobId>,
+    pub exclusive: bool,
+    pub draining: bool,
+    pub disk_space_low: bool,
+}
+
+/// An outstanding job and what it's currently waiting on.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct JobInfo {
+    pub id: JobId,
+    pub priority: i8,
+    pub exclusive: bool,
+    pub status: JobBrokerStatus,
+}
+
+/// The artifacts a job is still waiting on before it can be scheduled.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct JobArtifactsInfo {
+    pub id: JobId,
+    pub acquired: usize,
+    pub being_acquired: Vec<String>,
+    pub manifests_being_read: Vec<String>,
+}
+
+/// The state of the artifact gatherer.
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct ArtifactGathererInfo {
+    pub jobs_waiting: Vec<JobArtifactsInfo>,
+    pub manifest_reads_in_progress: usize,
+    pub manifest_reads_waiting: usize,
+    pub manifests_cached: usize,
+    pub uploads_pending: usize,
+}
@@ -1,4 +1,7 @@
-use crate::cache::SchedulerCache;
+use crate::{
+    cache::SchedulerCache,
+    scheduler_task::api::{ArtifactGathererInfo, JobArtifactsInfo},
+};
 use derive_more::{Deref, DerefMut};
 use get_size::GetSize;
 use maelstrom_base::{
@@ -8,6 +11,7 @@ use maelstrom_util::{
     cache::GetArtifact,
     ext::{BoolExt as _, OptionExt as _},
     heap::{Heap, HeapDeps, HeapIndex},
+    metrics::Encoder,
 };
 use std::{
     collections::{hash_map::Entry, HashMap, HashSet, VecDeque},
@@ -35,8 +39,14 @@ pub trait Deps {
         digest: Sha256Digest,
     );
     fn send_general_error_to_client(&mut self, sender: &mut Self::ClientSender, error: String);
+    fn send_missing_artifacts_to_client(
+        &mut self,
+        sender: &mut Self::ClientSender,
+        digests: Vec<Sha256Digest>,
+    );
     fn send_jobs_ready_to_scheduler(&mut self, jobs: NonEmpty<JobId>);
     fn send_jobs_failed_to_scheduler(&mut self, jobs: NonEmpty<JobId>, err: String);
+    fn send_artifact_to_store(&mut self, digest: Sha256Digest, path: PathBuf);
 }
 
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
@@ -75,15 +85,27 @@ impl Job {
     }
 }
 
-struct Client<DepsT: Deps> {
+/// What we need to ask a client for artifacts.
+struct ClientConnection<DepsT: Deps> {
     sender: DepsT::ClientSender,
+    /// The artifacts we told the client were missing in response to a
+    /// [`ArtifactGatherer::receive_find_missing_artifacts`], and which it hasn't finished
+    /// uploading. We don't ask the client to transfer these, since they're already on their way.
+    artifacts_being_uploaded: HashSet<Sha256Digest>,
+}
+
+struct Client<DepsT: Deps> {
+    connection: ClientConnection<DepsT>,
     jobs: HashMap<ClientJobId, Job>,
 }
 
 impl<DepsT: Deps> Client<DepsT> {
     fn new(sender: DepsT::ClientSender) -> Self {
         Self {
-            sender,
+            connection: ClientConnection {
+                sender,
+                artifacts_being_uploaded: Default::default(),
+            },
             jobs: Default::default(),
         }
     }
@@ -144,6 +166,10 @@ pub struct ArtifactGatherer<CacheT: SchedulerCache, DepsT: Deps> {
     clients: HashMap<ClientId, Client<DepsT>>,
     tcp_upload_landing_pad: HashMap<Sha256Digest, CacheT::TempFile>,
     manifest_reads: ManifestReads,
+    write_to_store: bool,
+    bytes_received_from_clients: u64,
+    bytes_received_from_store: u64,
+    bytes_sent_to_workers: u64,
 }
 
 impl<CacheT, DepsT> ArtifactGatherer<CacheT, DepsT>
@@ -156,6 +182,7 @@ where
         deps: DepsT,
         max_cache_size: usize,
         max_simultaneous_manifest_reads: NonZeroUsize,
+        write_to_store: bool,
     ) -> Self {
         Self {
             deps,
@@ -173,6 +200,10 @@ where
                 cache_size: 0,
                 max_cache_size,
             },
+            write_to_store,
+            bytes_received_from_clients: 0,
+            bytes_received_from_store: 0,
+            bytes_sent_to_workers: 0,
         }
     }
 
@@ -257,7 +288,7 @@ where
         for (digest, type_) in layers {
             Self::start_acquiring_artifact_for_job(
                 &mut self.cache,
-                &mut client.sender,
+                &mut client.connection,
                 &mut self.deps,
                 digest,
                 IsManifest::new(type_ == ArtifactType::Manifest, &mut self.manifest_reads),
@@ -283,7 +314,7 @@ where
     /// all artifacts it depends on, and then acquire references on them as well.
     fn start_acquiring_artifact_for_job(
         cache: &mut CacheT,
-        client_sender: &mut DepsT::ClientSender,
+        client: &mut ClientConnection<DepsT>,
         deps: &mut DepsT,
         digest: Sha256Digest,
         is_manifest: IsManifest<&mut ManifestReads>,
@@ -302,7 +333,7 @@ where
                     .assert_is_true();
                 Self::potentially_start_reading_manifest_for_job(
                     cache,
-                    client_sender,
+                    client,
                     deps,
                     &digest,
                     is_manifest,
@@ -319,14 +350,17 @@ where
                 job.artifacts_being_acquired
                     .insert(digest.clone(), is_manifest.map(drop))
                     .assert_is_none();
-                deps.send_transfer_artifact_to_client(client_sender, digest);
+                // If the client is already uploading the artifact, there's no need to ask for it.
+                if !client.artifacts_being_uploaded.contains(&digest) {
+                    deps.send_transfer_artifact_to_client(&mut client.sender, digest);
+                }
             }
         }
     }
 
     fn start_acquiring_manifest_entries_for_job<'a>(
         cache: &mut CacheT,
-        client_sender: &mut DepsT::ClientSender,
+        client: &mut ClientConnection<DepsT>,
         deps: &mut DepsT,
         digests: impl IntoIterator<Item = &'a Sha256Digest>,
         jid: JobId,
@@ -335,7 +369,7 @@ where
         for digest in digests.into_iter() {
             Self::start_acquiring_artifact_for_job(
                 cache,
-                client_sender,
+                client,
                 deps,
                 digest.clone(),
                 IsManifest::NotManifest,
@@ -349,7 +383,7 @@ where
     /// reading the manifest to incorporate the manifests dependencies as well.
     fn potentially_start_reading_manifest_for_job(
         cache: &mut CacheT,
-        client_sender: &mut DepsT::ClientSender,
+        client: &mut ClientConnection<DepsT>,
         deps: &mut DepsT,
         digest: &Sha256Digest,
         is_manifest: IsManifest<&mut ManifestReads>,
@@ -360,15 +394,7 @@ where
             return;
         };
 
-        if Self::get_manifest_from_cache(
-            cache,
-            client_sender,
-            deps,
-            digest,
-            jid,
-            job,
-            manifest_reads,
-        ) {
+        if Self::get_manifest_from_cache(cache, client, deps, digest, jid, job, manifest_reads) {
             return;
         }
 
@@ -382,7 +408,7 @@ where
                 entry.jobs.insert(jid).assert_is_true();
                 Self::start_acquiring_manifest_entries_for_job(
                     cache,
-                    client_sender,
+                    client,
                     deps,
                     &entry.entries,
                     jid,
@@ -402,7 +428,7 @@ where
     #[must_use]
     fn get_manifest_from_cache(
         cache: &mut CacheT,
-        client_sender: &mut DepsT::ClientSender,
+        client: &mut ClientConnection<DepsT>,
         deps: &mut DepsT,
         digest: &Sha256Digest,
         jid: JobId,
@@ -416,7 +442,7 @@ where
         entry.last_read = manifest_reads.next_read;
         Self::start_acquiring_manifest_entries_for_job(
             cache,
-            client_sender,
+            client,
             deps,
             &entry.entries,
             jid,
@@ -485,6 +511,22 @@ where
         deps.send_read_request_to_manifest_reader(manifest_stream, digest);
     }
 
+    /// Called when a client wants to know which of the given artifacts we don't have, so that it
+    /// can start uploading them before its jobs need them. We remember what we told the client so
+    /// that we don't also ask for those artifacts one at a time while they're being uploaded.
+    pub fn receive_find_missing_artifacts(&mut self, cid: ClientId, digests: Vec<Sha256Digest>) {
+        let client = &mut self.clients.get_mut(&cid).unwrap().connection;
+        let missing = digests
+            .into_iter()
+            .filter(|digest| {
+                !self.cache.contains_artifact(digest)
+                    && client.artifacts_being_uploaded.insert(digest.clone())
+            })
+            .collect();
+        self.deps
+            .send_missing_artifacts_to_client(&mut client.sender, missing);
+    }
+
     /// Called when a client finishes an upload of an artifact. We must notify the cache, which
     /// will attempt to incorporate the artifact. Then, depending on whether the cache is
     /// successful or not, we need to advance all affected jobs.
@@ -497,19 +539,50 @@ where
         let file = (location == ArtifactUploadLocation::TcpUpload)
             .then(|| self.tcp_upload_landing_pad.remove(&digest))
             .flatten();
+        self.incorporate_artifact(Some(cid), digest, file);
+    }
 
+    /// Called when an artifact we were going to ask a client for was instead found in the
+    /// artifact store. This is just like a client uploading it, except there is no client to tell
+    /// if incorporating the artifact fails.
+    pub fn receive_artifact_fetched_from_store(
+        &mut self,
+        digest: Sha256Digest,
+        size: u64,
+        file: CacheT::TempFile,
+    ) {
+        self.bytes_received_from_store += size;
+        self.incorporate_artifact(None, digest, Some(file));
+    }
+
+    fn incorporate_artifact(
+        &mut self,
+        cid: Option<ClientId>,
+        digest: Sha256Digest,
+        file: Option<CacheT::TempFile>,
+    ) {
         // We have to be careful with the jobs we get back from the cache. Currently, the cache
         // doesn't provide us a way to remove jobs that are waiting on cache reads. We can remove
         // a whole client, but not an individual job. This means that the jobs we get back from the
         // cache may no longer exist, as we may have encountered a failure looking reading another
         // artifact from cache or enumerating a manifest.
+        // Whether it came from a client or the store, the artifact is no longer being uploaded. If
+        // it came from the store, it may have been one a client was told was missing.
+        for (client_id, client) in &mut self.clients {
+            if cid.is_none() || cid == Some(*client_id) {
+                client.connection.artifacts_being_uploaded.remove(&digest);
+            }
+        }
+
         match self.cache.got_artifact(&digest, file) {
             Err((err, jobs)) => {
-                let client = self.clients.get_mut(&cid).unwrap();
-                self.deps.send_general_error_to_client(
-                    &mut client.sender,
-                    format!("error incorporating artifact {digest} into cache: {err}"),
-                );
+                if let Some(cid) = cid {
+                    let client = self.clients.get_mut(&cid).unwrap();
+                    self.deps.send_general_error_to_client(
+                        &mut client.connection.sender,
+                        format!("error incorporating artifact {digest} into cache: {err}"),
+                    );
+                }
                 let jobs = jobs.into_iter().filter(|jid| {
                     // It's possible that the job failed for some other reason while we were
                     // waiting on the cache. Until we update the cache's API to allow us to cancel
@@ -527,6 +600,14 @@ where
                 }
             }
             Ok(jobs) => {
+                // Artifacts that came from clients are copied to the store once they're in the
+                // cache. We hold a reference to the artifact until the copy is done so that it
+                // isn't removed out from under it.
+                if cid.is_some() && self.write_to_store {
+                    if let Some((path, _)) = self.cache.get_artifact_for_worker(&digest) {
+                        self.deps.send_artifact_to_store(digest.clone(), path);
+                    }
+                }
                 let ready = jobs.into_iter().filter(|jid| {
                     let client = self.clients.get_mut(&jid.cid).unwrap();
                     // It's possible that the job failed for some other reason while we were
@@ -540,7 +621,7 @@ where
                     job.artifacts_acquired.insert(digest_clone).assert_is_true();
                     Self::potentially_start_reading_manifest_for_job(
                         &mut self.cache,
-                        &mut client.sender,
+                        &mut client.connection,
                         &mut self.deps,
                         &digest,
                         is_manifest.map(|()| &mut self.manifest_reads),
@@ -575,7 +656,7 @@ where
                 let job = client.jobs.get_mut(&jid.cjid).unwrap();
                 Self::start_acquiring_artifact_for_job(
                     &mut self.cache,
-                    &mut client.sender,
+                    &mut client.connection,
                     &mut self.deps,
                     entry_digest.clone(),
                     IsManifest::NotManifest,
@@ -648,10 +729,12 @@ where
         digest: Sha256Digest,
         mut sender: DepsT::WorkerArtifactFetcherSender,
     ) {
-        self.deps.send_response_to_worker_artifact_fetcher(
-            &mut sender,
-            self.cache.get_artifact_for_worker(&digest),
-        );
+        let response = self.cache.get_artifact_for_worker(&digest);
+        if let Some((_, size)) = &response {
+            self.bytes_sent_to_workers += size;
+        }
+        self.deps
+            .send_response_to_worker_artifact_fetcher(&mut sender, response);
     }
 
     /// Called when the worker is done reading an artifact directly from the broker. We just
@@ -660,16 +743,84 @@ where
         self.cache.decrement_refcount(&digest);
     }
 
+    /// Called when an artifact has been copied to the artifact store, whether successfully or
+    /// not. We release the reference we took on the artifact before starting the copy.
+    pub fn receive_artifact_written_to_store(&mut self, digest: Sha256Digest) {
+        self.cache.decrement_refcount(&digest);
+    }
+
     /// Called when the clien artifact fetcher is done uploading an artifact.
-    pub fn receive_got_artifact(&mut self, digest: Sha256Digest, file: CacheT::TempFile) {
+    pub fn receive_got_artifact(
+        &mut self,
+        digest: Sha256Digest,
+        size: u64,
+        file: CacheT::TempFile,
+    ) {
+        self.bytes_received_from_clients += size;
         self.tcp_upload_landing_pad.insert(digest, file);
     }
+
+    /// Return a snapshot of the jobs still gathering artifacts and of the manifest reads.
+    pub fn info(&self) -> ArtifactGathererInfo {
+        fn sorted_strings<'a>(digests: impl Iterator<Item = &'a Sha256Digest>) -> Vec<String> {
+            let mut digests: Vec<_> = digests.map(ToString::to_string).collect();
+            digests.sort();
+            digests
+        }
+        let mut jobs_waiting: Vec<_> = self
+            .clients
+            .iter()
+            .flat_map(|(&cid, client)| {
+                client
+                    .jobs
+                    .iter()
+                    .filter(|(_, job)| !job.have_all_artifacts())
+                    .map(move |(&cjid, job)| JobArtifactsInfo {
+                        id: JobId { cid, cjid },
+                        acquired: job.artifacts_acquired.len(),
+                        being_acquired: sorted_strings(job.artifacts_being_acquired.keys()),
+                        manifests_being_read: sorted_strings(job.manifests_being_read.iter()),
+                    })
+            })
+            .collect();
+        jobs_waiting.sort_by_key(|job| job.id);
+        ArtifactGathererInfo {
+            jobs_waiting,
+            manifest_reads_in_progress: self.manifest_reads.in_progress,
+            manifest_reads_waiting: self.manifest_reads.waiting.len(),
+            manifests_cached: self.manifest_reads.cache.len(),
+            uploads_pending: self.tcp_upload_landing_pad.len(),
+        }
+    }
+
+    /// Encode metrics about artifact transfers and the cache.
+    pub fn encode_metrics(&self, encoder: &mut Encoder) {
+        encoder.counter(
+            "maelstrom_broker_artifact_bytes_received_total",
+            "Number of artifact bytes uploaded to the broker by clients.",
+            self.bytes_received_from_clients,
+        );
+        encoder.counter(
+            "maelstrom_broker_artifact_store_bytes_received_total",
+            "Number of artifact bytes fetched by the broker from the artifact store.",
+            self.bytes_received_from_store,
+        );
+        encoder.counter(
+            "maelstrom_broker_artifact_bytes_sent_total",
+            "Number of artifact bytes sent by the broker to workers.",
+            self.bytes_sent_to_workers,
+        );
+        if let Some(metrics) = self.cache.metrics() {
+            metrics.encode(encoder, "maelstrom_broker");
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use anyhow::{anyhow, Error, Result};
+    use maelstrom_util::cache::CacheMetrics;
     use std::{
         cell::RefCell,
         ops::{Deref, DerefMut},
@@ -684,14 +835,19 @@ mod tests {
         send_message_to_worker_artifact_fetcher: HashSet<(i32, Option<(PathBuf, u64)>)>,
         send_transfer_artifact_to_client: Vec<(ClientId, Sha256Digest)>,
         send_general_error_to_client: Vec<(ClientId, String)>,
+        send_missing_artifacts_to_client: Vec<(ClientId, Vec<Sha256Digest>)>,
         send_jobs_ready_to_scheduler: Vec<HashSet<JobId>>,
         send_jobs_failed_to_scheduler: Vec<(HashSet<JobId>, String)>,
+        send_artifact_to_store: HashSet<(Sha256Digest, PathBuf)>,
         client_sender_dropped: HashSet<ClientId>,
         // Cache.
         get_artifact: HashMap<(JobId, Sha256Digest), GetArtifact>,
         got_artifact:
             HashMap<(Sha256Digest, Option<String>), Result<Vec<JobId>, (Error, Vec<JobId>)>>,
+        contains_artifact: HashMap<Sha256Digest, bool>,
         decrement_refcount: Vec<Sha256Digest>,
+        get_artifact_for_worker: HashMap<Sha256Digest, Option<(PathBuf, u64)>>,
+        metrics: Option<CacheMetrics>,
         client_disconnected: HashSet<ClientId>,
         read_artifact: HashMap<Sha256Digest, i32>,
     }
@@ -718,6 +874,11 @@ mod tests {
                 "unused mock entries for Deps::send_general_error_to_client: {:?}",
                 self.send_general_error_to_client,
             );
+            assert!(
+                self.send_missing_artifacts_to_client.is_empty(),
+                "unused mock entries for Deps::send_missing_artifacts_to_client: {:?}",
+                self.send_missing_artifacts_to_client,
+            );
             assert!(
                 self.send_jobs_ready_to_scheduler.is_empty(),
                 "unused mock entries for Deps::send_jobs_ready_to_scheduler: {:?}",
@@ -728,6 +889,11 @@ mod tests {
                 "unused mock entries for Deps::send_job_failure_to_scheduler: {:?}",
                 self.send_jobs_failed_to_scheduler,
             );
+            assert!(
+                self.send_artifact_to_store.is_empty(),
+                "unused mock entries for Deps::send_artifact_to_store: {:?}",
+                self.send_artifact_to_store,
+            );
             assert!(
                 self.client_sender_dropped.is_empty(),
                 "unused mock entries for Deps::ClientSender::drop: {:?}",
@@ -743,11 +909,21 @@ mod tests {
                 "unused mock entries for Cache::got_artifact: {:?}",
                 self.got_artifact,
             );
+            assert!(
+                self.contains_artifact.is_empty(),
+                "unused mock entries for Cache::contains_artifact: {:?}",
+                self.contains_artifact,
+            );
             assert!(
                 self.decrement_refcount.is_empty(),
                 "unused mock entries for Cache::decrement_refcount: {:?}",
                 self.decrement_refcount,
             );
+            assert!(
+                self.get_artifact_for_worker.is_empty(),
+                "unused mock entries for Cache::get_artifact_for_worker: {:?}",
+                self.get_artifact_for_worker,
+            );
             assert!(
                 self.client_disconnected.is_empty(),
                 "unused mock entries for Cache::client_disconnected: {:?}",
@@ -836,6 +1012,22 @@ mod tests {
             let _ = vec.remove(index);
         }
 
+        fn send_missing_artifacts_to_client(
+            &mut self,
+            sender: &mut Self::ClientSender,
+            digests: Vec<Sha256Digest>,
+        ) {
+            let vec = &mut self.borrow_mut().send_missing_artifacts_to_client;
+            let index = vec
+                .iter()
+                .position(|e| e.0 == sender.cid && e.1 == digests)
+                .expect(&format!(
+                    "sending unexpected missing_artifacts to client {cid}: {digests:?}",
+                    cid = sender.cid,
+                ));
+            let _ = vec.remove(index);
+        }
+
         fn send_jobs_ready_to_scheduler(&mut self, jobs: NonEmpty<JobId>) {
             let jobs = HashSet::from_iter(jobs);
             let vec = &mut self.borrow_mut().send_jobs_ready_to_scheduler;
@@ -856,6 +1048,15 @@ mod tests {
                 ));
             let _ = vec.remove(index);
         }
+
+        fn send_artifact_to_store(&mut self, digest: Sha256Digest, path: PathBuf) {
+            assert!(
+                self.borrow_mut()
+                    .send_artifact_to_store
+                    .remove(&(digest.clone(), path.clone())),
+                "sending unexpected artifact to store: {digest} {path:?}"
+            );
+        }
     }
 
     impl SchedulerCache for Rc<RefCell<Mock>> {
@@ -884,6 +1085,15 @@ mod tests {
                 ))
         }
 
+        fn contains_artifact(&self, digest: &Sha256Digest) -> bool {
+            self.borrow_mut()
+                .contains_artifact
+                .remove(digest)
+                .unwrap_or_else(|| {
+                    panic!("sending unexpected contains_artifact to cache: {digest}")
+                })
+        }
+
         fn decrement_refcount(&mut self, digest: &Sha256Digest) {
             let vec = &mut self.borrow_mut().decrement_refcount;
             let index = vec.iter().position(|e| e == digest).expect(&format!(
@@ -899,13 +1109,22 @@ mod tests {
                 .assert_is_true()
         }
 
-        fn get_artifact_for_worker(&mut self, _digest: &Sha256Digest) -> Option<(PathBuf, u64)> {
-            todo!();
+        fn get_artifact_for_worker(&mut self, digest: &Sha256Digest) -> Option<(PathBuf, u64)> {
+            self.borrow_mut()
+                .get_artifact_for_worker
+                .remove(digest)
+                .unwrap_or_else(|| {
+                    panic!("sending unexpected get_artifact_for_worker to cache: {digest}")
+                })
         }
 
         fn read_artifact(&mut self, digest: &Sha256Digest) -> Self::ArtifactStream {
             self.borrow_mut().read_artifact.remove(digest).unwrap()
         }
+
+        fn metrics(&self) -> Option<CacheMetrics> {
+            self.borrow().metrics
+        }
     }
 
     struct Fixture {
@@ -938,6 +1157,7 @@ mod tests {
                 mock.clone(),
                 1_000_000,
                 max_simultaneous_manifest_reads.try_into().unwrap(),
+                false,
             );
             Self {
                 mock,
@@ -953,6 +1173,23 @@ mod tests {
                 mock.clone(),
                 max_cache_size,
                 100.try_into().unwrap(),
+                false,
+            );
+            Self {
+                mock,
+                sut,
+                connected_clients: Default::default(),
+            }
+        }
+
+        fn with_artifact_store() -> Self {
+            let mock = Rc::new(RefCell::new(Default::default()));
+            let sut = ArtifactGatherer::new(
+                mock.clone(),
+                mock.clone(),
+                1_000_000,
+                100.try_into().unwrap(),
+                true,
             );
             Self {
                 mock,
@@ -1013,10 +1250,31 @@ mod tests {
                 .receive_artifact_transferred(cid.into(), digest.into(), location.into());
         }
 
+        fn receive_artifact_fetched_from_store(
+            &mut self,
+            digest: impl Into<Sha256Digest>,
+            size: u64,
+            file: &str,
+        ) {
+            self.sut
+                .receive_artifact_fetched_from_store(digest.into(), size, file.into());
+        }
+
         fn client_disconnected(&mut self, cid: impl Into<ClientId>) {
             self.sut.client_disconnected(cid.into());
         }
 
+        fn receive_find_missing_artifacts<DigestT: Into<Sha256Digest>>(
+            &mut self,
+            cid: impl Into<ClientId>,
+            digests: impl IntoIterator<Item = DigestT>,
+        ) {
+            self.sut.receive_find_missing_artifacts(
+                cid.into(),
+                digests.into_iter().map(Into::into).collect(),
+            );
+        }
+
         fn receive_artifact_transferred(
             &mut self,
             cid: impl Into<ClientId>,
@@ -1048,6 +1306,10 @@ mod tests {
         fn job_completed(&mut self, jid: impl Into<JobId>) {
             self.sut.job_completed(jid.into());
         }
+
+        fn receive_artifact_written_to_store(&mut self, digest: impl Into<Sha256Digest>) {
+            self.sut.receive_artifact_written_to_store(digest.into());
+        }
     }
 
     struct Expect<'a> {
@@ -1105,6 +1367,19 @@ mod tests {
             self
         }
 
+        fn send_missing_artifacts_to_client<DigestT: Into<Sha256Digest>>(
+            self,
+            cid: impl Into<ClientId>,
+            digests: impl IntoIterator<Item = DigestT>,
+        ) -> Self {
+            self.fixture
+                .mock
+                .borrow_mut()
+                .send_missing_artifacts_to_client
+                .push((cid.into(), digests.into_iter().map(Into::into).collect()));
+            self
+        }
+
         fn send_jobs_ready_to_scheduler(
             self,
             jobs: impl IntoIterator<Item = impl Into<JobId>>,
@@ -1185,6 +1460,43 @@ mod tests {
             self
         }
 
+        fn contains_artifact(self, digest: impl Into<Sha256Digest>, result: bool) -> Self {
+            self.fixture
+                .mock
+                .borrow_mut()
+                .contains_artifact
+                .insert(digest.into(), result)
+                .assert_is_none();
+            self
+        }
+
+        fn get_artifact_for_worker(
+            self,
+            digest: impl Into<Sha256Digest>,
+            result: Option<(&str, u64)>,
+        ) -> Self {
+            self.fixture
+                .mock
+                .borrow_mut()
+                .get_artifact_for_worker
+                .insert(
+                    digest.into(),
+                    result.map(|(path, size)| (path.into(), size)),
+                )
+                .assert_is_none();
+            self
+        }
+
+        fn send_artifact_to_store(self, digest: impl Into<Sha256Digest>, path: &str) -> Self {
+            self.fixture
+                .mock
+                .borrow_mut()
+                .send_artifact_to_store
+                .insert((digest.into(), path.into()))
+                .assert_is_true();
+            self
+        }
+
         fn decrement_refcount(self, digest: impl Into<Sha256Digest>) -> Self {
             self.fixture
                 .mock
@@ -1559,6 +1871,211 @@ mod tests {
             .receive_artifact_transferred(1, 5, ArtifactUploadLocation::Remote);
     }
 
+    #[test]
+    fn receive_artifact_fetched_from_store_success() {
+        let mut fixture = Fixture::new().with_client(1);
+        fixture
+            .expect()
+            .get_artifact((1, 2), 5, GetArtifact::Get)
+            .send_transfer_artifact_to_client(1, 5)
+            .when()
+            .start_job((1, 2), [(5, Tar)], StartJob::NotReady);
+        fixture
+            .expect()
+            .got_artifact_success(5, Some("/z/tmp/foo"), [(1, 2)])
+            .send_jobs_ready_to_scheduler([(1, 2)])
+            .when()
+            .receive_artifact_fetched_from_store(5, 100, "/z/tmp/foo");
+        assert_eq!(fixture.sut.bytes_received_from_store, 100);
+    }
+
+    #[test]
+    fn receive_artifact_fetched_from_store_failure_fails_jobs_without_client_error() {
+        let mut fixture = Fixture::new().with_client(1);
+        fixture
+            .expect()
+            .get_artifact((1, 2), 5, GetArtifact::Get)
+            .send_transfer_artifact_to_client(1, 5)
+            .when()
+            .start_job((1, 2), [(5, Tar)], StartJob::NotReady);
+        fixture
+            .expect()
+            .got_artifact_failure(5, Some("/z/tmp/foo"), "error", [(1, 2)])
+            .send_jobs_failed_to_scheduler([(1, 2)], "error")
+            .when()
+            .receive_artifact_fetched_from_store(5, 100, "/z/tmp/foo");
+    }
+
+    #[test]
+    fn receive_artifact_transferred_with_artifact_store_writes_artifact_to_store() {
+        let mut fixture = Fixture::with_artifact_store().with_client(1);
+        fixture
+            .expect()
+            .get_artifact((1, 2), 5, GetArtifact::Get)
+            .send_transfer_artifact_to_client(1, 5)
+            .when()
+            .start_job((1, 2), [(5, Tar)], StartJob::NotReady);
+        fixture
+            .expect()
+            .got_artifact_success(5, None, [(1, 2)])
+            .get_artifact_for_worker(5, Some(("/z/cache/5", 100)))
+            .send_artifact_to_store(5, "/z/cache/5")
+            .send_jobs_ready_to_scheduler([(1, 2)])
+            .when()
+            .receive_artifact_transferred(1, 5, ArtifactUploadLocation::Remote);
+        fixture
+            .expect()
+            .decrement_refcount(5)
+            .when()
+            .receive_artifact_written_to_store(5);
+    }
+
+    #[test]
+    fn receive_artifact_transferred_with_artifact_store_not_in_cache_is_not_written() {
+        let mut fixture = Fixture::with_artifact_store().with_client(1);
+        fixture
+            .expect()
+            .got_artifact_success(5, None, [] as [JobId; 0])
+            .get_artifact_for_worker(5, None)
+            .when()
+            .receive_artifact_transferred(1, 5, ArtifactUploadLocation::Remote);
+    }
+
+    #[test]
+    fn receive_artifact_transferred_failure_with_artifact_store_is_not_written() {
+        let mut fixture = Fixture::with_artifact_store().with_client(1);
+        fixture
+            .expect()
+            .got_artifact_failure(5, None, "error", [] as [JobId; 0])
+            .send_general_error_to_client(
+                1,
+                format!(
+                    "error incorporating artifact {} into cache: error",
+                    Sha256Digest::from(5)
+                ),
+            )
+            .when()
+            .receive_artifact_transferred(1, 5, ArtifactUploadLocation::Remote);
+    }
+
+    #[test]
+    fn receive_artifact_fetched_from_store_is_not_written_back_to_store() {
+        let mut fixture = Fixture::with_artifact_store().with_client(1);
+        fixture
+            .expect()
+            .got_artifact_success(5, Some("/z/tmp/foo"), [] as [JobId; 0])
+            .when()
+            .receive_artifact_fetched_from_store(5, 100, "/z/tmp/foo");
+    }
+
+    #[test]
+    fn receive_find_missing_artifacts_replies_with_artifacts_not_in_cache() {
+        let mut fixture = Fixture::new().with_client(1);
+        fixture
+            .expect()
+            .contains_artifact(1, true)
+            .contains_artifact(2, false)
+            .contains_artifact(3, false)
+            .send_missing_artifacts_to_client(1, [2, 3])
+            .when()
+            .receive_find_missing_artifacts(1, [1, 2, 3]);
+    }
+
+    #[test]
+    fn receive_find_missing_artifacts_does_not_repeat_artifacts_being_uploaded() {
+        let mut fixture = Fixture::new().with_client(1);
+        fixture
+            .expect()
+            .contains_artifact(2, false)
+            .send_missing_artifacts_to_client(1, [2])
+            .when()
+            .receive_find_missing_artifacts(1, [2]);
+        fixture
+            .expect()
+            .contains_artifact(2, false)
+            .contains_artifact(3, false)
+            .send_missing_artifacts_to_client(1, [3])
+            .when()
+            .receive_find_missing_artifacts(1, [2, 3]);
+    }
+
+    #[test]
+    fn start_job_does_not_request_artifacts_being_uploaded() {
+        let mut fixture = Fixture::new().with_client(1).with_client(2);
+        fixture
+            .expect()
+            .contains_artifact(5, false)
+            .send_missing_artifacts_to_client(1, [5])
+            .when()
+            .receive_find_missing_artifacts(1, [5]);
+        fixture
+            .expect()
+            .get_artifact((1, 2), 5, GetArtifact::Get)
+            .get_artifact((1, 2), 6, GetArtifact::Get)
+            .send_transfer_artifact_to_client(1, 6)
+            .when()
+            .start_job((1, 2), [(5, Tar), (6, Tar)], StartJob::NotReady);
+
+        // Only the client that was told about the artifact is uploading it.
+        fixture
+            .expect()
+            .get_artifact((2, 2), 5, GetArtifact::Get)
+            .send_transfer_artifact_to_client(2, 5)
+            .when()
+            .start_job((2, 2), [(5, Tar)], StartJob::NotReady);
+
+        fixture
+            .expect()
+            .got_artifact_success(5, None, [(1, 2), (2, 2)])
+            .send_jobs_ready_to_scheduler([(2, 2)])
+            .when()
+            .receive_artifact_transferred(1, 5, ArtifactUploadLocation::Remote);
+    }
+
+    #[test]
+    fn artifact_transferred_before_job_is_requested_again() {
+        let mut fixture = Fixture::new().with_client(1);
+        fixture
+            .expect()
+            .contains_artifact(5, false)
+            .send_missing_artifacts_to_client(1, [5])
+            .when()
+            .receive_find_missing_artifacts(1, [5]);
+        fixture
+            .expect()
+            .got_artifact_success(5, None, [] as [JobId; 0])
+            .when()
+            .receive_artifact_transferred(1, 5, ArtifactUploadLocation::Remote);
+        fixture
+            .expect()
+            .get_artifact((1, 2), 5, GetArtifact::Get)
+            .send_transfer_artifact_to_client(1, 5)
+            .when()
+            .start_job((1, 2), [(5, Tar)], StartJob::NotReady);
+    }
+
+    #[test]
+    fn artifact_fetched_from_store_is_no_longer_being_uploaded() {
+        let mut fixture = Fixture::new().with_client(1);
+        fixture
+            .expect()
+            .contains_artifact(5, false)
+            .send_missing_artifacts_to_client(1, [5])
+            .when()
+            .receive_find_missing_artifacts(1, [5]);
+        fixture
+            .expect()
+            .got_artifact_success(5, Some("/z/tmp/foo"), [] as [JobId; 0])
+            .when()
+            .receive_artifact_fetched_from_store(5, 100, "/z/tmp/foo");
+        fixture
+            .expect()
+            .contains_artifact(5, false)
+            .send_missing_artifacts_to_client(1, [5])
+            .when()
+            .receive_find_missing_artifacts(1, [5]);
+    }
+
     #[test]
     fn manifest_read_for_job_entry_from_disconnected_client() {
         let mut fixture = Fixture::new().with_client(1);
@@ -2075,4 +2592,83 @@ mod tests {
             .when()
             .start_job((1, 7), [(4, Manifest)], StartJob::NotReady);
     }
+
+    #[test]
+    fn encode_metrics() {
+        let mut fixture = Fixture::new();
+        fixture.mock.borrow_mut().metrics = Some(CacheMetrics {
+            hits: 3,
+            misses: 1,
+            ..Default::default()
+        });
+        fixture
+            .sut
+            .receive_got_artifact(Sha256Digest::from(1), 100, "file1".into());
+        fixture
+            .sut
+            .receive_got_artifact(Sha256Digest::from(2), 20, "file2".into());
+
+        let mut encoder = Encoder::new();
+        fixture.sut.encode_metrics(&mut encoder);
+        let metrics = encoder.finish();
+        for line in [
+            "maelstrom_broker_artifact_bytes_received_total 120",
+            "maelstrom_broker_artifact_bytes_sent_total 0",
+            r#"maelstrom_broker_cache_lookups_total{result="hit"} 3"#,
+            r#"maelstrom_broker_cache_lookups_total{result="miss"} 1"#,
+        ] {
+            assert!(
+                metrics.lines().any(|l| l == line),
+                "{line:?} not in {metrics}"
+            );
+        }
+    }
+
+    #[test]
+    fn info() {
+        let mut fixture = Fixture::new().with_client(1);
+        fixture
+            .expect()
+            .get_artifact((1, 2), 3, GetArtifact::Success)
+            .get_artifact((1, 2), 4, GetArtifact::Get)
+            .send_transfer_artifact_to_client(1, 4)
+            .when()
+            .start_job((1, 2), [(3, Tar), (4, Tar)], StartJob::NotReady);
+        fixture
+            .expect()
+            .get_artifact((1, 3), 3, GetArtifact::Success)
+            .when()
+            .start_job((1, 3), [(3, Tar)], StartJob::Ready);
+        fixture
+            .expect()
+            .get_artifact((1, 4), 5, GetArtifact::Success)
+            .read_artifact(5, 55)
+            .send_message_to_manifest_reader(5, 55)
+            .when()
+            .start_job((1, 4), [(5, Manifest)], StartJob::NotReady);
+
+        assert_eq!(
+            fixture.sut.info(),
+            ArtifactGathererInfo {
+                jobs_waiting: vec![
+                    JobArtifactsInfo {
+                        id: JobId::from((1, 2)),
+                        acquired: 1,
+                        being_acquired: vec![Sha256Digest::from(4).to_string()],
+                        manifests_being_read: vec![],
+                    },
+                    JobArtifactsInfo {
+                        id: JobId::from((1, 4)),
+                        acquired: 1,
+                        being_acquired: vec![],
+                        manifests_being_read: vec![Sha256Digest::from(5).to_string()],
+                    },
+                ],
+                manifest_reads_in_progress: 1,
+                manifest_reads_waiting: 0,
+                manifests_cached: 0,
+                uploads_pending: 0,
+            }
+        );
+    }
 }
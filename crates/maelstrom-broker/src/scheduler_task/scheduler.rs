@@ -1,26 +1,33 @@
 //! Central processing module for the broker. Receives and sends messages to and from clients and
 //! workers.
 
-use crate::scheduler_task::artifact_gatherer::StartJob;
+use crate::scheduler_task::{
+    api::{ClientInfo, JobInfo, WorkerInfo},
+    artifact_gatherer::StartJob,
+};
 use derivative::Derivative;
 use derive_more::{Constructor, Deref, DerefMut};
 use maelstrom_base::{
     stats::{
-        BrokerStatistics, JobState, JobStateCounts, JobStatisticsSample, JobStatisticsTimeSeries,
-        WorkerStatistics,
+        BrokerStatistics, ClientStatistics, JobCompletion, JobState, JobStateCounts, JobStatistics,
+        JobStatisticsSample, JobStatisticsTimeSeries, WorkerStatistics, WorkerStatisticsSample,
+        RECENT_COMPLETIONS,
     },
-    ArtifactType, ClientId, ClientJobId, JobBrokerStatus, JobError, JobId, JobOutcomeResult,
-    JobSpec, JobWorkerStatus, MonitorId, NonEmpty, Sha256Digest, WorkerId,
+    ArtifactType, ClientId, ClientJobId, JobBrokerStatus, JobCompleted, JobError, JobId,
+    JobOutcome, JobOutcomeResult, JobSpec, JobTerminationStatus, JobWorkerStatus, MonitorId,
+    NonEmpty, Sha256Digest, WorkerId,
 };
 use maelstrom_util::{
     duration,
     ext::{BoolExt as _, OptionExt as _},
     heap::{Heap, HeapDeps, HeapIndex},
+    metrics::{Encoder, Histogram, MetricType, DURATION_BUCKETS},
 };
 use std::{
     cmp::Ordering,
-    collections::{BinaryHeap, HashMap, HashSet},
-    time::Duration,
+    collections::{BinaryHeap, HashMap, HashSet, VecDeque},
+    net::SocketAddr,
+    time::{Duration, Instant},
 };
 
 /// The dependencies for [`Scheduler`] on the
@@ -68,12 +75,33 @@ pub trait Deps {
         sender: &mut Self::MonitorSender,
         statistics: BrokerStatistics,
     );
+    fn send_jobs_response_to_monitor(
+        &mut self,
+        sender: &mut Self::MonitorSender,
+        jobs: Vec<JobStatistics>,
+    );
+    fn send_client_statistics_response_to_monitor(
+        &mut self,
+        sender: &mut Self::MonitorSender,
+        cid: ClientId,
+        statistics: Option<ClientStatistics>,
+    );
+    /// The current time. Job latencies are measured with this.
+    fn now(&mut self) -> Instant;
 }
 
 struct Client<DepsT: Deps> {
     sender: DepsT::ClientSender,
     jobs: HashMap<ClientJobId, JobSpec>,
+    /// When each of the entries in `jobs` was received from the client.
+    received: HashMap<ClientJobId, Instant>,
     counts: JobStateCounts,
+    /// Jobs that were canceled by an administrator while the artifact gatherer was still working
+    /// on them. Their responses have already been sent, so we ignore them when the artifact
+    /// gatherer is done.
+    canceled: HashSet<ClientJobId>,
+    /// The most recently completed jobs, oldest first.
+    recent_completions: VecDeque<JobCompletion>,
 }
 
 impl<DepsT: Deps> Client<DepsT> {
@@ -81,9 +109,36 @@ impl<DepsT: Deps> Client<DepsT> {
         Client {
             sender,
             jobs: Default::default(),
+            received: Default::default(),
             counts: Default::default(),
+            canceled: Default::default(),
+            recent_completions: Default::default(),
         }
     }
+
+    /// Remove a job that has completed, returning how long before `now` it was received. The
+    /// `worker` is the worker that ran the job, if any.
+    fn remove_job(
+        &mut self,
+        cjid: ClientJobId,
+        worker: Option<WorkerId>,
+        succeeded: bool,
+        now: Instant,
+    ) -> Duration {
+        self.jobs.remove(&cjid).assert_is_some();
+        self.counts[JobState::Complete] += 1;
+        let latency = now.saturating_duration_since(self.received.remove(&cjid).unwrap());
+        if self.recent_completions.len() == RECENT_COMPLETIONS {
+            self.recent_completions.pop_front();
+        }
+        self.recent_completions.push_back(JobCompletion {
+            cjid,
+            worker,
+            succeeded,
+            latency,
+        });
+        latency
+    }
 }
 
 type ClientMap<DepsT> = HashMap<ClientId, Client<DepsT>>;
@@ -93,17 +148,68 @@ struct Worker<DepsT: Deps> {
     sender: DepsT::WorkerSender,
     pending: HashSet<JobId>,
     heap_index: HeapIndex,
+    /// Whether the worker is running an exclusive job. If it is, that job is its only pending job.
+    exclusive: bool,
+    /// Whether an administrator has asked that no new jobs be sent to the worker.
+    draining: bool,
+    /// Whether the worker is being emptied so that a queued exclusive job can run on it. It isn't
+    /// sent any other jobs in the meantime.
+    reserved: bool,
+    /// Whether the worker has told us that it's low on disk space. It won't start new jobs until
+    /// it tells us otherwise.
+    disk_space_low: bool,
+    /// The most recent status update received for each of the entries in `pending`.
+    statuses: HashMap<JobId, JobWorkerStatus>,
+    /// The number of jobs the worker has completed.
+    completed: u64,
+    /// Where the worker serves artifacts from its cache to other workers, if it does.
+    artifact_server: Option<SocketAddr>,
+    /// The layer artifacts the worker is known to have fetched. It may have since removed some of
+    /// them from its cache.
+    artifacts: HashSet<Sha256Digest>,
+    /// The number of times we've sent another worker to this one for an artifact.
+    peer_fetches: u64,
 }
 
 impl<DepsT: Deps> Worker<DepsT> {
-    fn new(slots: usize, sender: DepsT::WorkerSender) -> Self {
+    fn new(slots: usize, artifact_server: Option<SocketAddr>, sender: DepsT::WorkerSender) -> Self {
         Worker {
             slots,
             sender,
             pending: HashSet::default(),
             heap_index: HeapIndex::default(),
+            exclusive: false,
+            draining: false,
+            reserved: false,
+            disk_space_low: false,
+            statuses: HashMap::default(),
+            completed: 0,
+            artifact_server,
+            artifacts: HashSet::default(),
+            peer_fetches: 0,
+        }
+    }
+
+    /// Remove a job that is no longer pending on the worker, returning whether it was.
+    fn remove_pending(&mut self, jid: &JobId) -> bool {
+        self.statuses.remove(jid);
+        self.pending.remove(jid)
+    }
+
+    /// The number of jobs the worker is treated as having. A worker running an exclusive job, one
+    /// that is being drained or reserved, or one that is low on disk space, is treated as full.
+    fn load(&self) -> usize {
+        if self.exclusive || self.draining || self.reserved || self.disk_space_low {
+            2 * self.slots
+        } else {
+            self.pending.len()
         }
     }
+
+    /// Whether the worker has nothing to do and can take jobs, and so can run an exclusive job.
+    fn is_idle(&self) -> bool {
+        self.pending.is_empty() && !self.draining && !self.disk_space_low
+    }
 }
 
 #[derive(Deref, DerefMut)]
@@ -115,8 +221,8 @@ impl<DepsT: Deps> HeapDeps for WorkerMap<DepsT> {
     fn is_element_less_than(&self, lhs_id: &WorkerId, rhs_id: &WorkerId) -> bool {
         let lhs_worker = self.get(lhs_id).unwrap();
         let rhs_worker = self.get(rhs_id).unwrap();
-        let lhs = (lhs_worker.pending.len() * rhs_worker.slots, *lhs_id);
-        let rhs = (rhs_worker.pending.len() * lhs_worker.slots, *rhs_id);
+        let lhs = (lhs_worker.load() * rhs_worker.slots, *lhs_id);
+        let rhs = (rhs_worker.load() * lhs_worker.slots, *rhs_id);
         lhs.cmp(&rhs) == Ordering::Less
     }
 
@@ -144,6 +250,15 @@ pub struct Scheduler<DepsT: Deps> {
     monitors: HashMap<MonitorId, DepsT::MonitorSender>,
     queued_jobs: BinaryHeap<QueuedJob>,
     job_statistics: JobStatisticsTimeSeries,
+    /// How long jobs wait after being received before being sent to a worker.
+    job_queue_time: Histogram,
+    /// How long jobs take from being received to their result being sent to the client.
+    job_latency: Histogram,
+    /// Workers that an administrator has disconnected, but whose connections haven't yet closed.
+    /// Any further messages from them are ignored.
+    disconnected_workers: HashSet<WorkerId>,
+    /// The worker being emptied for the exclusive job at the head of the queue, if any.
+    reserved_worker: Option<WorkerId>,
 }
 
 impl<DepsT: Deps> Scheduler<DepsT> {
@@ -158,38 +273,144 @@ impl<DepsT: Deps> Scheduler<DepsT> {
             monitors: Default::default(),
             queued_jobs: Default::default(),
             job_statistics: Default::default(),
+            job_queue_time: Histogram::new(DURATION_BUCKETS),
+            job_latency: Histogram::new(DURATION_BUCKETS),
+            disconnected_workers: Default::default(),
+            reserved_worker: None,
         }
     }
 
     fn start_job(
         clients: &mut ClientMap<DepsT>,
         deps: &mut DepsT,
+        job_queue_time: &mut Histogram,
         jid: JobId,
         worker: &mut Worker<DepsT>,
     ) {
         let client = clients.get_mut(&jid.cid).unwrap();
         let spec = client.jobs.get(&jid.cjid).unwrap();
+        let received = client.received.get(&jid.cjid).unwrap();
+        job_queue_time.observe_duration(deps.now().saturating_duration_since(*received));
         client.counts[JobState::Pending] -= 1;
         client.counts[JobState::Running] += 1;
         worker.pending.insert(jid).assert_is_true();
+        worker.exclusive = spec.exclusive;
         deps.send_enqueue_job_to_worker(&mut worker.sender, jid, spec.clone());
     }
 
+    fn is_exclusive(clients: &ClientMap<DepsT>, jid: JobId) -> bool {
+        clients
+            .get(&jid.cid)
+            .unwrap()
+            .jobs
+            .get(&jid.cjid)
+            .unwrap()
+            .exclusive
+    }
+
+    fn release_reserved_worker(&mut self) {
+        if let Some(wid) = self.reserved_worker.take() {
+            let worker = self.workers.get_mut(&wid).unwrap();
+            worker.reserved = false;
+            let heap_index = worker.heap_index;
+            self.worker_heap.sift_up(&mut self.workers, heap_index);
+        }
+    }
+
+    /// Release the reserved worker if there's no longer an exclusive job waiting for it, or if it
+    /// can no longer take jobs.
+    fn release_reserved_worker_if_unneeded(&mut self) {
+        let Some(wid) = self.reserved_worker else {
+            return;
+        };
+        let worker = self.workers.get(&wid).unwrap();
+        if worker.draining
+            || worker.disk_space_low
+            || !self
+                .queued_jobs
+                .iter()
+                .any(|QueuedJob { jid, .. }| Self::is_exclusive(&self.clients, *jid))
+        {
+            self.release_reserved_worker();
+        }
+    }
+
     fn possibly_start_jobs(&mut self, mut just_enqueued: HashSet<JobId>) {
-        while !self.queued_jobs.is_empty() && !self.workers.is_empty() {
-            let wid = self.worker_heap.peek().unwrap();
+        self.release_reserved_worker_if_unneeded();
+
+        // Exclusive jobs that are waiting for the reserved worker to become idle. We set them
+        // aside so that the jobs queued behind them can still be started on other workers, and put
+        // them back when we're done.
+        let mut waiting_exclusive = vec![];
+
+        while let Some(QueuedJob { jid, .. }) = self.queued_jobs.peek() {
+            if Self::is_exclusive(&self.clients, *jid) {
+                // An exclusive job has to wait for a worker with nothing else to do. If there isn't
+                // one, we reserve the least loaded worker and stop sending it jobs, so that it
+                // will eventually become idle. The other workers keep getting jobs.
+                let idle = self
+                    .worker_heap
+                    .peek()
+                    .into_iter()
+                    .chain(&self.reserved_worker)
+                    .find(|wid| self.workers.get(wid).unwrap().is_idle())
+                    .copied();
+                if let Some(wid) = idle {
+                    self.release_reserved_worker();
+                    let QueuedJob { jid, .. } = self.queued_jobs.pop().unwrap();
+                    let worker = self.workers.get_mut(&wid).unwrap();
+                    Self::start_job(
+                        &mut self.clients,
+                        &mut self.deps,
+                        &mut self.job_queue_time,
+                        jid,
+                        worker,
+                    );
+                    let heap_index = worker.heap_index;
+                    self.worker_heap.sift_down(&mut self.workers, heap_index);
+                    just_enqueued.remove(&jid);
+                    continue;
+                }
+                if self.reserved_worker.is_none() {
+                    let Some(&wid) = self.worker_heap.peek() else {
+                        break;
+                    };
+                    let worker = self.workers.get_mut(&wid).unwrap();
+                    if worker.draining || worker.disk_space_low {
+                        // The least loaded worker can't take jobs, so none of them can.
+                        break;
+                    }
+                    worker.reserved = true;
+                    let heap_index = worker.heap_index;
+                    self.worker_heap.sift_down(&mut self.workers, heap_index);
+                    self.reserved_worker = Some(wid);
+                }
+                waiting_exclusive.push(self.queued_jobs.pop().unwrap());
+                continue;
+            }
+
+            let Some(wid) = self.worker_heap.peek() else {
+                break;
+            };
             let worker = self.workers.get_mut(wid).unwrap();
 
-            if worker.pending.len() == 2 * worker.slots {
+            if worker.load() == 2 * worker.slots {
                 break;
             }
 
             let QueuedJob { jid, .. } = self.queued_jobs.pop().unwrap();
-            Self::start_job(&mut self.clients, &mut self.deps, jid, worker);
+            Self::start_job(
+                &mut self.clients,
+                &mut self.deps,
+                &mut self.job_queue_time,
+                jid,
+                worker,
+            );
             let heap_index = worker.heap_index;
             self.worker_heap.sift_down(&mut self.workers, heap_index);
             just_enqueued.remove(&jid);
         }
+        self.queued_jobs.extend(waiting_exclusive);
 
         for jid in just_enqueued {
             let client = self.clients.get_mut(&jid.cid).unwrap();
@@ -235,6 +456,10 @@ impl<DepsT: Deps> Scheduler<DepsT> {
                 }
                 !cancel
             });
+            worker.statuses.retain(|jid, _| jid.cid != cid);
+            if worker.pending.is_empty() {
+                worker.exclusive = false;
+            }
         }
         self.worker_heap.rebuild(&mut self.workers);
         self.possibly_start_jobs(HashSet::default());
@@ -260,6 +485,7 @@ impl<DepsT: Deps> Scheduler<DepsT> {
             .jobs
             .insert(cjid, spec)
             .expect_is_none(|_| format!("received job_request for duplicate job ID: {jid}"));
+        client.received.insert(cjid, self.deps.now());
 
         match artifact_gatherer.start_job(jid, layers) {
             StartJob::Ready => {
@@ -279,11 +505,20 @@ impl<DepsT: Deps> Scheduler<DepsT> {
         }
     }
 
-    pub fn receive_jobs_ready_from_artifact_gatherer(&mut self, ready: NonEmpty<JobId>) {
+    pub fn receive_jobs_ready_from_artifact_gatherer(
+        &mut self,
+        artifact_gatherer: &mut impl ArtifactGatherer,
+        ready: NonEmpty<JobId>,
+    ) {
         let just_enqueued = ready
             .into_iter()
             .filter(|jid| match self.clients.get_mut(&jid.cid) {
                 None => false,
+                Some(client) if client.canceled.contains(&jid.cjid) => {
+                    client.canceled.remove(&jid.cjid);
+                    artifact_gatherer.job_completed(*jid);
+                    false
+                }
                 Some(client) => {
                     let spec = client.jobs.get(&jid.cjid).unwrap();
                     self.queued_jobs.push(QueuedJob::new(
@@ -309,14 +544,17 @@ impl<DepsT: Deps> Scheduler<DepsT> {
             let Some(client) = self.clients.get_mut(&jid.cid) else {
                 continue;
             };
+            if client.canceled.remove(&jid.cjid) {
+                continue;
+            }
             self.deps.send_job_response_to_client(
                 &mut client.sender,
                 jid.cjid,
                 Err(JobError::System(err.clone())),
             );
-            client.jobs.remove(&jid.cjid).assert_is_some();
             client.counts[JobState::WaitingForArtifacts] -= 1;
-            client.counts[JobState::Complete] += 1;
+            let latency = client.remove_job(jid.cjid, None, false, self.deps.now());
+            self.job_latency.observe_duration(latency);
         }
     }
 
@@ -324,10 +562,11 @@ impl<DepsT: Deps> Scheduler<DepsT> {
         &mut self,
         wid: WorkerId,
         slots: usize,
+        artifact_server: Option<SocketAddr>,
         sender: DepsT::WorkerSender,
     ) {
         self.workers
-            .insert(wid, Worker::new(slots, sender))
+            .insert(wid, Worker::new(slots, artifact_server, sender))
             .expect_is_none(|_| {
                 format!("received worker_connected message for duplicate worker: {wid}")
             });
@@ -336,11 +575,23 @@ impl<DepsT: Deps> Scheduler<DepsT> {
     }
 
     pub fn receive_worker_disconnected(&mut self, wid: WorkerId) {
+        if self.disconnected_workers.remove(&wid) {
+            // We already removed this worker when we disconnected it.
+            return;
+        }
+        self.remove_worker(wid);
+    }
+
+    /// Remove a worker and requeue all of its jobs.
+    fn remove_worker(&mut self, wid: WorkerId) {
         let worker = self.workers.remove(&wid).expect_is_some(|| {
             format!("received worker_disconnected message for unknown worker: {wid}")
         });
         self.worker_heap
             .remove(&mut self.workers, worker.heap_index);
+        if self.reserved_worker == Some(wid) {
+            self.reserved_worker = None;
+        }
 
         for jid in &worker.pending {
             let client = self.clients.get_mut(&jid.cid).unwrap();
@@ -360,30 +611,67 @@ impl<DepsT: Deps> Scheduler<DepsT> {
         jid: JobId,
         result: JobOutcomeResult,
     ) {
+        if self.disconnected_workers.contains(&wid) {
+            return;
+        }
         let worker = self
             .workers
             .get_mut(&wid)
             .expect_is_some(|| format!("received job_response message from unknown worker: {wid}"));
-        if !worker.pending.remove(&jid) {
+        if !worker.remove_pending(&jid) {
             // This indicates that the client isn't around anymore. Just ignore this response from
             // the worker. When the client disconnected, we canceled all of the outstanding
             // requests and updated our version of the worker's pending requests.
             return;
         }
 
+        // If the worker was running an exclusive job, this was it.
+        let was_exclusive = std::mem::take(&mut worker.exclusive);
+
+        worker.completed += 1;
+
         artifact_gatherer.job_completed(jid);
         let client = self.clients.get_mut(&jid.cid).unwrap();
+        let succeeded = matches!(
+            result,
+            Ok(JobOutcome::Completed(JobCompleted {
+                status: JobTerminationStatus::Exited(0),
+                ..
+            }))
+        );
         self.deps
             .send_job_response_to_client(&mut client.sender, jid.cjid, result);
-        client.jobs.remove(&jid.cjid).assert_is_some();
         client.counts[JobState::Running] -= 1;
-        client.counts[JobState::Complete] += 1;
-
-        if let Some(QueuedJob { jid, .. }) = self.queued_jobs.pop() {
+        let latency = client.remove_job(jid.cjid, Some(wid), succeeded, self.deps.now());
+        self.job_latency.observe_duration(latency);
+
+        let next_is_exclusive = self
+            .queued_jobs
+            .peek()
+            .is_some_and(|QueuedJob { jid, .. }| Self::is_exclusive(&self.clients, *jid));
+        if was_exclusive
+            || next_is_exclusive
+            || worker.draining
+            || worker.reserved
+            || worker.disk_space_low
+        {
+            // The worker may now have room for a lot of jobs, or the next job may not be able to
+            // run on it, or it shouldn't get any more jobs for now, so we go through the normal
+            // process of starting jobs.
+            let heap_index = worker.heap_index;
+            self.worker_heap.sift_up(&mut self.workers, heap_index);
+            self.possibly_start_jobs(HashSet::default());
+        } else if let Some(QueuedJob { jid, .. }) = self.queued_jobs.pop() {
             // If there are any queued_requests, we can just pop one off of the front of
             // the queue and not have to update the worker's used slot count or position in the
             // workers list.
-            Self::start_job(&mut self.clients, &mut self.deps, jid, worker);
+            Self::start_job(
+                &mut self.clients,
+                &mut self.deps,
+                &mut self.job_queue_time,
+                jid,
+                worker,
+            );
         } else {
             // Since there are no queued_jobs, we're going to have to update the worker's position
             // in the workers list.
@@ -398,6 +686,9 @@ impl<DepsT: Deps> Scheduler<DepsT> {
         jid: JobId,
         status: JobWorkerStatus,
     ) {
+        if self.disconnected_workers.contains(&wid) {
+            return;
+        }
         let worker = self.workers.get_mut(&wid).expect_is_some(|| {
             format!("received job_status_update message from unknown worker: {wid}")
         });
@@ -407,6 +698,19 @@ impl<DepsT: Deps> Scheduler<DepsT> {
         }
 
         let client = self.clients.get_mut(&jid.cid).unwrap();
+        if worker.artifact_server.is_some()
+            && matches!(
+                status,
+                JobWorkerStatus::WaitingToExecute | JobWorkerStatus::Executing
+            )
+        {
+            // Once a job is ready to execute, the worker must have all of its layers.
+            let spec = &client.jobs[&jid.cjid];
+            worker
+                .artifacts
+                .extend(spec.layers.iter().map(|(digest, _)| digest.clone()));
+        }
+        worker.statuses.insert(jid, status.clone());
         self.deps.send_job_status_update_to_client(
             &mut client.sender,
             jid.cjid,
@@ -414,6 +718,21 @@ impl<DepsT: Deps> Scheduler<DepsT> {
         );
     }
 
+    /// Choose a worker that should have the given artifact in its cache, and return the address it
+    /// serves artifacts on. Requests are spread evenly across all of the workers that have the
+    /// artifact.
+    pub fn peer_for_artifact(&mut self, digest: &Sha256Digest) -> Option<SocketAddr> {
+        let (_, worker) = self
+            .workers
+            .iter_mut()
+            .filter(|(_, worker)| {
+                worker.artifact_server.is_some() && worker.artifacts.contains(digest)
+            })
+            .min_by_key(|(wid, worker)| (worker.peer_fetches, **wid))?;
+        worker.peer_fetches += 1;
+        worker.artifact_server
+    }
+
     pub fn receive_monitor_connected(&mut self, mid: MonitorId, sender: DepsT::MonitorSender) {
         self.monitors.insert(mid, sender).expect_is_none(|_| {
             format!("received monitor_connected message for duplicate monitor: {mid}")
@@ -443,6 +762,40 @@ impl<DepsT: Deps> Scheduler<DepsT> {
         );
     }
 
+    pub fn receive_jobs_request_from_monitor(&mut self, mid: MonitorId) {
+        let now = self.deps.now();
+        let jobs = self
+            .jobs()
+            .into_iter()
+            .map(|job| JobStatistics {
+                id: job.id,
+                priority: job.priority,
+                status: job.status,
+                elapsed: now
+                    .saturating_duration_since(self.clients[&job.id.cid].received[&job.id.cjid]),
+            })
+            .collect();
+        let sender = self.monitors.get_mut(&mid).expect_is_some(|| {
+            format!("received jobs_request message from unknown monitor: {mid}")
+        });
+        self.deps.send_jobs_response_to_monitor(sender, jobs);
+    }
+
+    pub fn receive_client_statistics_request_from_monitor(
+        &mut self,
+        mid: MonitorId,
+        cid: ClientId,
+    ) {
+        let sender = self.monitors.get_mut(&mid).expect_is_some(|| {
+            format!("received client_statistics_request message from unknown monitor: {mid}")
+        });
+        let statistics = self.clients.get(&cid).map(|client| ClientStatistics {
+            recent_completions: client.recent_completions.iter().cloned().collect(),
+        });
+        self.deps
+            .send_client_statistics_response_to_monitor(sender, cid, statistics);
+    }
+
     pub fn receive_statistics_heartbeat(&mut self) {
         self.job_statistics.insert(JobStatisticsSample {
             client_to_stats: self
@@ -450,7 +803,299 @@ impl<DepsT: Deps> Scheduler<DepsT> {
                 .iter()
                 .map(|(&cid, client)| (cid, client.counts))
                 .collect(),
+            worker_to_stats: self
+                .workers
+                .iter()
+                .map(|(&wid, worker)| {
+                    (
+                        wid,
+                        WorkerStatisticsSample {
+                            jobs: worker.pending.len(),
+                            completed: worker.completed,
+                        },
+                    )
+                })
+                .collect(),
+        });
+    }
+
+    /// The given worker's free disk space has dropped below, or risen back above, its minimum. While
+    /// it's low, we don't send the worker any new jobs, but jobs it already has are left alone.
+    pub fn receive_disk_space_low_from_worker(&mut self, wid: WorkerId, low: bool) {
+        if self.disconnected_workers.contains(&wid) {
+            return;
+        }
+        let worker = self.workers.get_mut(&wid).expect_is_some(|| {
+            format!("received disk_space_low message from unknown worker: {wid}")
+        });
+        if worker.disk_space_low == low {
+            return;
+        }
+        worker.disk_space_low = low;
+        let heap_index = worker.heap_index;
+        if low {
+            self.worker_heap.sift_down(&mut self.workers, heap_index);
+        } else {
+            self.worker_heap.sift_up(&mut self.workers, heap_index);
+        }
+        // Either the worker can take jobs again, or, if it was reserved for an exclusive job,
+        // another one has to be found.
+        self.possibly_start_jobs(HashSet::default());
+    }
+
+    /// Stop sending new jobs to the given worker. Jobs it already has are allowed to finish.
+    /// Returns false if there is no such worker.
+    pub fn receive_drain_worker(&mut self, wid: WorkerId) -> bool {
+        let Some(worker) = self.workers.get_mut(&wid) else {
+            return false;
+        };
+        worker.draining = true;
+        let heap_index = worker.heap_index;
+        self.worker_heap.sift_down(&mut self.workers, heap_index);
+        // If the worker was reserved for an exclusive job, another one has to be found.
+        self.possibly_start_jobs(HashSet::default());
+        true
+    }
+
+    /// Drop our connection to the given worker, requeueing all of its jobs. Returns false if there
+    /// is no such worker.
+    pub fn receive_disconnect_worker(&mut self, wid: WorkerId) -> bool {
+        if !self.workers.contains_key(&wid) {
+            return false;
+        }
+        // Dropping the worker's sender will cause its connection to be closed. We'll eventually
+        // get a worker_disconnected message for it, which we'll ignore.
+        self.remove_worker(wid);
+        self.disconnected_workers.insert(wid).assert_is_true();
+        true
+    }
+
+    /// Cancel all of the given client's outstanding jobs, sending an error response to the client
+    /// for each. Returns the number of jobs canceled, or `None` if there is no such client.
+    pub fn receive_cancel_client_jobs(
+        &mut self,
+        artifact_gatherer: &mut impl ArtifactGatherer,
+        cid: ClientId,
+    ) -> Option<usize> {
+        let client = self.clients.get_mut(&cid)?;
+
+        let mut queued = HashSet::new();
+        self.queued_jobs.retain(|qj| {
+            let cancel = qj.jid.cid == cid;
+            if cancel {
+                queued.insert(qj.jid.cjid);
+            }
+            !cancel
         });
+        let mut running = HashMap::new();
+        for (&wid, worker) in self.workers.iter_mut() {
+            worker.pending.retain(|jid| {
+                let cancel = jid.cid == cid;
+                if cancel {
+                    self.deps
+                        .send_cancel_job_to_worker(&mut worker.sender, *jid);
+                    running.insert(jid.cjid, wid);
+                }
+                !cancel
+            });
+            worker.statuses.retain(|jid, _| jid.cid != cid);
+            if worker.pending.is_empty() {
+                worker.exclusive = false;
+            }
+        }
+
+        let mut cjids: Vec<_> = client.jobs.keys().copied().collect();
+        cjids.sort();
+        for &cjid in &cjids {
+            let state = if queued.contains(&cjid) {
+                JobState::Pending
+            } else if running.contains_key(&cjid) {
+                JobState::Running
+            } else {
+                client.canceled.insert(cjid);
+                JobState::WaitingForArtifacts
+            };
+            if state != JobState::WaitingForArtifacts {
+                artifact_gatherer.job_completed(JobId { cid, cjid });
+            }
+            self.deps.send_job_response_to_client(
+                &mut client.sender,
+                cjid,
+                Err(JobError::System("canceled by broker administrator".into())),
+            );
+            client.counts[state] -= 1;
+            let latency =
+                client.remove_job(cjid, running.get(&cjid).copied(), false, self.deps.now());
+            self.job_latency.observe_duration(latency);
+        }
+
+        self.worker_heap.rebuild(&mut self.workers);
+        self.possibly_start_jobs(HashSet::default());
+        Some(cjids.len())
+    }
+
+    /// A snapshot of the connected clients, sorted by ID.
+    pub fn clients(&self) -> Vec<ClientInfo> {
+        let mut clients: Vec<_> = self
+            .clients
+            .iter()
+            .map(|(&id, client)| ClientInfo {
+                id,
+                jobs: client.counts,
+            })
+            .collect();
+        clients.sort_by_key(|client| client.id);
+        clients
+    }
+
+    /// A snapshot of the connected workers, sorted by ID.
+    pub fn workers(&self) -> Vec<WorkerInfo> {
+        let mut workers: Vec<_> = self
+            .workers
+            .iter()
+            .map(|(&id, worker)| {
+                let mut jobs = Vec::from_iter(worker.pending.iter().copied());
+                jobs.sort();
+                WorkerInfo {
+                    id,
+                    slots: worker.slots,
+                    jobs,
+                    exclusive: worker.exclusive,
+                    draining: worker.draining,
+                    disk_space_low: worker.disk_space_low,
+                }
+            })
+            .collect();
+        workers.sort_by_key(|worker| worker.id);
+        workers
+    }
+
+    /// A snapshot of all outstanding jobs, sorted by ID.
+    pub fn jobs(&self) -> Vec<JobInfo> {
+        let at_worker: HashMap<JobId, WorkerId> = self
+            .workers
+            .iter()
+            .flat_map(|(&wid, worker)| worker.pending.iter().map(move |&jid| (jid, wid)))
+            .collect();
+        let queued = HashSet::<JobId>::from_iter(self.queued_jobs.iter().map(|qj| qj.jid));
+        let mut jobs: Vec<_> = self
+            .clients
+            .iter()
+            .flat_map(|(&cid, client)| {
+                client
+                    .jobs
+                    .iter()
+                    .map(move |(&cjid, spec)| (JobId { cid, cjid }, spec))
+            })
+            .map(|(id, spec)| {
+                let status = if let Some(&wid) = at_worker.get(&id) {
+                    let status = self.workers[&wid]
+                        .statuses
+                        .get(&id)
+                        .cloned()
+                        .unwrap_or(JobWorkerStatus::WaitingForLayers);
+                    JobBrokerStatus::AtWorker(wid, status)
+                } else if queued.contains(&id) {
+                    JobBrokerStatus::WaitingForWorker
+                } else {
+                    JobBrokerStatus::WaitingForLayers
+                };
+                JobInfo {
+                    id,
+                    priority: spec.priority,
+                    exclusive: spec.exclusive,
+                    status,
+                }
+            })
+            .collect();
+        jobs.sort_by_key(|job| job.id);
+        jobs
+    }
+
+    /// Encode metrics about queued and running jobs, workers, and job latencies.
+    pub fn encode_metrics(&self, encoder: &mut Encoder) {
+        encoder.header(
+            "maelstrom_broker_jobs",
+            "Number of jobs per client and state. Completed jobs are counted cumulatively.",
+            MetricType::Gauge,
+        );
+        let mut clients: Vec<_> = self.clients.iter().collect();
+        clients.sort_by_key(|(cid, _)| **cid);
+        for (cid, client) in clients {
+            let cid = cid.to_string();
+            for (state, count) in &client.counts {
+                let state = state.to_string().replace(' ', "_");
+                encoder.sample(
+                    "maelstrom_broker_jobs",
+                    &[("client", &cid), ("state", &state)],
+                    count,
+                );
+            }
+        }
+        encoder.gauge(
+            "maelstrom_broker_queued_jobs",
+            "Number of jobs waiting for a worker.",
+            self.queued_jobs.len(),
+        );
+
+        let mut workers: Vec<_> = self.workers.iter().collect();
+        workers.sort_by_key(|(wid, _)| **wid);
+        encoder.gauge(
+            "maelstrom_broker_workers",
+            "Number of connected workers.",
+            workers.len(),
+        );
+        encoder.header(
+            "maelstrom_broker_worker_slots",
+            "Number of job slots per worker.",
+            MetricType::Gauge,
+        );
+        for (wid, worker) in &workers {
+            let wid = wid.to_string();
+            encoder.sample(
+                "maelstrom_broker_worker_slots",
+                &[("worker", &wid)],
+                worker.slots,
+            );
+        }
+        encoder.header(
+            "maelstrom_broker_worker_jobs",
+            "Number of jobs sent to each worker, including those queued at the worker.",
+            MetricType::Gauge,
+        );
+        for (wid, worker) in &workers {
+            let wid = wid.to_string();
+            encoder.sample(
+                "maelstrom_broker_worker_jobs",
+                &[("worker", &wid)],
+                worker.pending.len(),
+            );
+        }
+        let slots: usize = workers.iter().map(|(_, worker)| worker.slots).sum();
+        let busy: usize = workers
+            .iter()
+            .map(|(_, worker)| worker.pending.len().min(worker.slots))
+            .sum();
+        encoder.gauge(
+            "maelstrom_broker_slot_utilization",
+            "Fraction of all worker slots that are running a job.",
+            if slots == 0 {
+                0.0
+            } else {
+                busy as f64 / slots as f64
+            },
+        );
+
+        encoder.histogram(
+            "maelstrom_broker_job_queue_seconds",
+            "Time from a job being received to it being sent to a worker.",
+            &self.job_queue_time,
+        );
+        encoder.histogram(
+            "maelstrom_broker_job_latency_seconds",
+            "Time from a job being received to its result being sent to the client.",
+            &self.job_latency,
+        );
     }
 
     #[cfg(test)]
@@ -471,7 +1116,7 @@ impl<DepsT: Deps> Scheduler<DepsT> {
 mod tests {
     use super::*;
     use enum_map::enum_map;
-    use maelstrom_base::tar_digest;
+    use maelstrom_base::{digest, tar_digest};
     use maelstrom_test::{outcome, spec};
     use maplit::hashmap;
     use rstest::rstest;
@@ -538,6 +1183,11 @@ mod tests {
         send_enqueue_job_to_worker: Vec<(WorkerId, JobId, JobSpec)>,
         send_cancel_job_to_worker: HashSet<(WorkerId, JobId)>,
         send_statistics_response_to_monitor: Vec<(MonitorId, BrokerStatistics)>,
+        send_jobs_response_to_monitor: Vec<(MonitorId, Vec<JobStatistics>)>,
+        send_client_statistics_response_to_monitor:
+            Vec<(MonitorId, ClientId, Option<ClientStatistics>)>,
+        #[derivative(Default(value = "Instant::now()"))]
+        now: Instant,
         // Drops
         #[derivative(Default(value = "true"))]
         check_drops: bool,
@@ -594,6 +1244,16 @@ mod tests {
                 "unused mock entries for Deps::send_statistics_response_to_monitor: {:?}",
                 self.send_statistics_response_to_monitor,
             );
+            assert!(
+                self.send_jobs_response_to_monitor.is_empty(),
+                "unused mock entries for Deps::send_jobs_response_to_monitor: {:?}",
+                self.send_jobs_response_to_monitor,
+            );
+            assert!(
+                self.send_client_statistics_response_to_monitor.is_empty(),
+                "unused mock entries for Deps::send_client_statistics_response_to_monitor: {:?}",
+                self.send_client_statistics_response_to_monitor,
+            );
             assert!(
                 self.client_sender_clone.is_empty(),
                 "unused mock entries for ClientSender::clone: {:?}",
@@ -740,6 +1400,46 @@ mod tests {
                 ));
             vec.remove(index);
         }
+
+        fn send_jobs_response_to_monitor(
+            &mut self,
+            sender: &mut Self::MonitorSender,
+            jobs: Vec<JobStatistics>,
+        ) {
+            let vec = &mut self.borrow_mut().send_jobs_response_to_monitor;
+            let mid = sender.mid;
+            let index = vec
+                .iter()
+                .position(|e| e.0 == mid && e.1 == jobs)
+                .unwrap_or_else(|| {
+                    panic!("sending unexpected jobs_response to monitor {sender:?}: {jobs:#?}")
+                });
+            vec.remove(index);
+        }
+
+        fn send_client_statistics_response_to_monitor(
+            &mut self,
+            sender: &mut Self::MonitorSender,
+            cid: ClientId,
+            statistics: Option<ClientStatistics>,
+        ) {
+            let vec = &mut self.borrow_mut().send_client_statistics_response_to_monitor;
+            let mid = sender.mid;
+            let index = vec
+                .iter()
+                .position(|e| e.0 == mid && e.1 == cid && e.2 == statistics)
+                .unwrap_or_else(|| {
+                    panic!(
+                        "sending unexpected client_statistics_response to monitor {sender:?}: \
+                        {cid} {statistics:#?}"
+                    )
+                });
+            vec.remove(index);
+        }
+
+        fn now(&mut self) -> Instant {
+            self.borrow().now
+        }
     }
 
     #[derive(derive_more::Debug)]
@@ -908,6 +1608,7 @@ mod tests {
             ready: impl IntoIterator<Item = impl Into<JobId>>,
         ) {
             self.sut.receive_jobs_ready_from_artifact_gatherer(
+                &mut self.mock,
                 NonEmpty::collect(ready.into_iter().map(Into::into)).unwrap(),
             );
         }
@@ -924,10 +1625,20 @@ mod tests {
         }
 
         fn receive_worker_connected(&mut self, wid: impl Into<WorkerId>, slots: usize) {
+            self.receive_worker_connected_with_artifact_server(wid, slots, None);
+        }
+
+        fn receive_worker_connected_with_artifact_server(
+            &mut self,
+            wid: impl Into<WorkerId>,
+            slots: usize,
+            artifact_server: Option<SocketAddr>,
+        ) {
             let wid = wid.into();
             self.sut.receive_worker_connected(
                 wid,
                 slots,
+                artifact_server,
                 TestWorkerSender::new(wid, self.mock.clone()),
             );
         }
@@ -960,6 +1671,10 @@ mod tests {
                 .receive_job_status_update_from_worker(wid.into(), jid.into(), status.into());
         }
 
+        fn advance_time(&mut self, secs: u64) {
+            self.mock.borrow_mut().now += Duration::from_secs(secs);
+        }
+
         fn receive_monitor_connected(&mut self, mid: impl Into<MonitorId>) {
             let mid = mid.into();
             self.sut
@@ -974,9 +1689,39 @@ mod tests {
             self.sut.receive_statistics_request_from_monitor(mid.into());
         }
 
+        fn receive_jobs_request_from_monitor(&mut self, mid: impl Into<MonitorId>) {
+            self.sut.receive_jobs_request_from_monitor(mid.into());
+        }
+
+        fn receive_client_statistics_request_from_monitor(
+            &mut self,
+            mid: impl Into<MonitorId>,
+            cid: impl Into<ClientId>,
+        ) {
+            self.sut
+                .receive_client_statistics_request_from_monitor(mid.into(), cid.into());
+        }
+
         pub fn receive_statistics_heartbeat(&mut self) {
             self.sut.receive_statistics_heartbeat();
         }
+
+        fn receive_disk_space_low_from_worker(&mut self, wid: impl Into<WorkerId>, low: bool) {
+            self.sut.receive_disk_space_low_from_worker(wid.into(), low);
+        }
+
+        fn receive_drain_worker(&mut self, wid: impl Into<WorkerId>) -> bool {
+            self.sut.receive_drain_worker(wid.into())
+        }
+
+        fn receive_disconnect_worker(&mut self, wid: impl Into<WorkerId>) -> bool {
+            self.sut.receive_disconnect_worker(wid.into())
+        }
+
+        fn receive_cancel_client_jobs(&mut self, cid: impl Into<ClientId>) -> Option<usize> {
+            self.sut
+                .receive_cancel_client_jobs(&mut self.mock, cid.into())
+        }
     }
 
     struct Expect<'a> {
@@ -1152,6 +1897,33 @@ mod tests {
                 .push((mid.into(), statistics));
             self
         }
+
+        fn send_jobs_response_to_monitor(
+            self,
+            mid: impl Into<MonitorId>,
+            jobs: Vec<JobStatistics>,
+        ) -> Self {
+            self.fixture
+                .mock
+                .borrow_mut()
+                .send_jobs_response_to_monitor
+                .push((mid.into(), jobs));
+            self
+        }
+
+        fn send_client_statistics_response_to_monitor(
+            self,
+            mid: impl Into<MonitorId>,
+            cid: impl Into<ClientId>,
+            statistics: Option<ClientStatistics>,
+        ) -> Self {
+            self.fixture
+                .mock
+                .borrow_mut()
+                .send_client_statistics_response_to_monitor
+                .push((mid.into(), cid.into(), statistics));
+            self
+        }
     }
 
     struct When<'a> {
@@ -1383,13 +2155,52 @@ mod tests {
     }
 
     #[test]
-    fn receive_jobs_ready_from_artifact_gatherer_ignores_disconnected_clients() {
-        let mut fixture = Fixture::new();
-        fixture.receive_jobs_ready_from_artifact_gatherer([(1, 2), (2, 1)]);
-    }
-
-    #[test]
-    fn receive_jobs_ready_from_artifact_gatherer_batches() {
+    fn encode_metrics() {
+        let mut fixture = Fixture::new().with_client(1).with_worker(1, 2);
+        fixture
+            .expect()
+            .start_job((1, 1), [tar_digest!(1)], StartJob::Ready)
+            .send_enqueue_job_to_worker(1, (1, 1), spec!(1))
+            .when()
+            .receive_job_request_from_client(1, 1, spec!(1));
+        fixture
+            .expect()
+            .start_job((1, 2), [tar_digest!(2)], StartJob::NotReady)
+            .send_job_status_update_to_client(1, 2, JobBrokerStatus::WaitingForLayers)
+            .when()
+            .receive_job_request_from_client(1, 2, spec!(2));
+
+        let mut encoder = Encoder::new();
+        fixture.sut.encode_metrics(&mut encoder);
+        let metrics = encoder.finish();
+        for line in [
+            r#"maelstrom_broker_jobs{client="1",state="waiting_for_artifacts"} 1"#,
+            r#"maelstrom_broker_jobs{client="1",state="pending"} 0"#,
+            r#"maelstrom_broker_jobs{client="1",state="running"} 1"#,
+            r#"maelstrom_broker_jobs{client="1",state="complete"} 0"#,
+            "maelstrom_broker_queued_jobs 0",
+            "maelstrom_broker_workers 1",
+            r#"maelstrom_broker_worker_slots{worker="1"} 2"#,
+            r#"maelstrom_broker_worker_jobs{worker="1"} 1"#,
+            "maelstrom_broker_slot_utilization 0.5",
+            "maelstrom_broker_job_queue_seconds_count 1",
+            "maelstrom_broker_job_latency_seconds_count 0",
+        ] {
+            assert!(
+                metrics.lines().any(|l| l == line),
+                "{line:?} not in {metrics}"
+            );
+        }
+    }
+
+    #[test]
+    fn receive_jobs_ready_from_artifact_gatherer_ignores_disconnected_clients() {
+        let mut fixture = Fixture::new();
+        fixture.receive_jobs_ready_from_artifact_gatherer([(1, 2), (2, 1)]);
+    }
+
+    #[test]
+    fn receive_jobs_ready_from_artifact_gatherer_batches() {
         let mut fixture = Fixture::new().with_client(1).with_worker(1, 1);
         let job_spec_1 = spec!(1, priority: 1);
         let job_spec_2 = spec!(2, priority: 2);
@@ -1746,6 +2557,208 @@ mod tests {
         );
     }
 
+    #[test]
+    fn exclusive_job_waits_for_idle_worker_and_runs_alone() {
+        let mut fixture = Fixture::new().with_client(1).with_worker(1, 1);
+
+        fixture
+            .expect()
+            .start_job((1, 1), [tar_digest!(1)], StartJob::Ready)
+            .send_enqueue_job_to_worker(1, (1, 1), spec!(1))
+            .when()
+            .receive_job_request_from_client(1, 1, spec!(1));
+        fixture
+            .expect()
+            .start_job((1, 2), [tar_digest!(2)], StartJob::Ready)
+            .send_job_status_update_to_client(1, 2, JobBrokerStatus::WaitingForWorker)
+            .when()
+            .receive_job_request_from_client(1, 2, spec!(2, priority: 1, exclusive: true));
+        fixture
+            .expect()
+            .start_job((1, 3), [tar_digest!(3)], StartJob::Ready)
+            .send_job_status_update_to_client(1, 3, JobBrokerStatus::WaitingForWorker)
+            .when()
+            .receive_job_request_from_client(1, 3, spec!(3));
+        fixture.assert_job_state_counts_for_client(
+            1,
+            enum_map! {
+                JobState::Running => 1,
+                JobState::Pending => 2,
+                _ => 0,
+            },
+        );
+
+        fixture
+            .expect()
+            .complete_job((1, 1))
+            .send_job_response_to_client(1, 1, Ok(outcome!(1)))
+            .send_enqueue_job_to_worker(1, (1, 2), spec!(2, priority: 1, exclusive: true))
+            .when()
+            .receive_job_response_from_worker(1, (1, 1), Ok(outcome!(1)));
+        fixture.assert_job_state_counts_for_client(
+            1,
+            enum_map! {
+                JobState::Running => 1,
+                JobState::Pending => 1,
+                JobState::Complete => 1,
+                _ => 0,
+            },
+        );
+
+        fixture
+            .expect()
+            .complete_job((1, 2))
+            .send_job_response_to_client(1, 2, Ok(outcome!(1)))
+            .send_enqueue_job_to_worker(1, (1, 3), spec!(3))
+            .when()
+            .receive_job_response_from_worker(1, (1, 2), Ok(outcome!(1)));
+        fixture.assert_job_state_counts_for_client(
+            1,
+            enum_map! {
+                JobState::Running => 1,
+                JobState::Complete => 2,
+                _ => 0,
+            },
+        );
+    }
+
+    #[test]
+    fn exclusive_job_only_blocks_the_worker_reserved_for_it() {
+        let mut fixture = Fixture::new()
+            .with_client(1)
+            .with_worker(1, 1)
+            .with_worker(2, 1);
+
+        fixture
+            .expect()
+            .start_job((1, 1), [tar_digest!(1)], StartJob::Ready)
+            .send_enqueue_job_to_worker(1, (1, 1), spec!(1))
+            .when()
+            .receive_job_request_from_client(1, 1, spec!(1));
+        fixture
+            .expect()
+            .start_job((1, 2), [tar_digest!(2)], StartJob::Ready)
+            .send_enqueue_job_to_worker(2, (1, 2), spec!(2))
+            .when()
+            .receive_job_request_from_client(1, 2, spec!(2));
+
+        // Neither worker is idle, so worker 1 is reserved for the exclusive job.
+        fixture
+            .expect()
+            .start_job((1, 3), [tar_digest!(3)], StartJob::Ready)
+            .send_job_status_update_to_client(1, 3, JobBrokerStatus::WaitingForWorker)
+            .when()
+            .receive_job_request_from_client(1, 3, spec!(3, priority: 1, exclusive: true));
+
+        // Worker 2 still gets the jobs queued behind the exclusive job.
+        fixture
+            .expect()
+            .start_job((1, 4), [tar_digest!(4)], StartJob::Ready)
+            .send_enqueue_job_to_worker(2, (1, 4), spec!(4))
+            .when()
+            .receive_job_request_from_client(1, 4, spec!(4));
+        fixture
+            .expect()
+            .start_job((1, 5), [tar_digest!(5)], StartJob::Ready)
+            .send_job_status_update_to_client(1, 5, JobBrokerStatus::WaitingForWorker)
+            .when()
+            .receive_job_request_from_client(1, 5, spec!(5));
+
+        // Worker 2 finishing a job gets another one, not the exclusive job.
+        fixture
+            .expect()
+            .complete_job((1, 2))
+            .send_job_response_to_client(1, 2, Ok(outcome!(2)))
+            .send_enqueue_job_to_worker(2, (1, 5), spec!(5))
+            .when()
+            .receive_job_response_from_worker(2, (1, 2), Ok(outcome!(2)));
+
+        // Once worker 1 is idle, it gets the exclusive job.
+        fixture
+            .expect()
+            .complete_job((1, 1))
+            .send_job_response_to_client(1, 1, Ok(outcome!(1)))
+            .send_enqueue_job_to_worker(1, (1, 3), spec!(3, priority: 1, exclusive: true))
+            .when()
+            .receive_job_response_from_worker(1, (1, 1), Ok(outcome!(1)));
+        assert_eq!(fixture.sut.reserved_worker, None);
+    }
+
+    #[test]
+    fn reserved_worker_is_released_when_exclusive_job_goes_away() {
+        let mut fixture = Fixture::new()
+            .with_client(1)
+            .with_client(2)
+            .with_worker(1, 1);
+
+        fixture
+            .expect()
+            .start_job((1, 1), [tar_digest!(1)], StartJob::Ready)
+            .send_enqueue_job_to_worker(1, (1, 1), spec!(1))
+            .when()
+            .receive_job_request_from_client(1, 1, spec!(1));
+        fixture
+            .expect()
+            .start_job((2, 1), [tar_digest!(2)], StartJob::Ready)
+            .send_job_status_update_to_client(2, 1, JobBrokerStatus::WaitingForWorker)
+            .when()
+            .receive_job_request_from_client(2, 1, spec!(2, priority: 1, exclusive: true));
+        fixture
+            .expect()
+            .start_job((1, 2), [tar_digest!(3)], StartJob::Ready)
+            .send_job_status_update_to_client(1, 2, JobBrokerStatus::WaitingForWorker)
+            .when()
+            .receive_job_request_from_client(1, 2, spec!(3));
+
+        fixture
+            .expect()
+            .client_disconnected(2)
+            .client_sender_drop(2)
+            .send_enqueue_job_to_worker(1, (1, 2), spec!(3))
+            .when()
+            .receive_client_disconnected(2);
+        assert_eq!(fixture.sut.reserved_worker, None);
+    }
+
+    #[test]
+    fn draining_reserved_worker_reserves_another() {
+        let mut fixture = Fixture::new()
+            .with_client(1)
+            .with_worker(1, 1)
+            .with_worker(2, 1);
+
+        fixture
+            .expect()
+            .start_job((1, 1), [tar_digest!(1)], StartJob::Ready)
+            .send_enqueue_job_to_worker(1, (1, 1), spec!(1))
+            .when()
+            .receive_job_request_from_client(1, 1, spec!(1));
+        fixture
+            .expect()
+            .start_job((1, 2), [tar_digest!(2)], StartJob::Ready)
+            .send_enqueue_job_to_worker(2, (1, 2), spec!(2))
+            .when()
+            .receive_job_request_from_client(1, 2, spec!(2));
+        fixture
+            .expect()
+            .start_job((1, 3), [tar_digest!(3)], StartJob::Ready)
+            .send_job_status_update_to_client(1, 3, JobBrokerStatus::WaitingForWorker)
+            .when()
+            .receive_job_request_from_client(1, 3, spec!(3, priority: 1, exclusive: true));
+        assert_eq!(fixture.sut.reserved_worker, Some(1.into()));
+
+        assert!(fixture.receive_drain_worker(1));
+        assert_eq!(fixture.sut.reserved_worker, Some(2.into()));
+
+        fixture
+            .expect()
+            .complete_job((1, 2))
+            .send_job_response_to_client(1, 2, Ok(outcome!(2)))
+            .send_enqueue_job_to_worker(2, (1, 3), spec!(3, priority: 1, exclusive: true))
+            .when()
+            .receive_job_response_from_worker(2, (1, 2), Ok(outcome!(2)));
+    }
+
     #[rstest]
     #[case("1")]
     #[case("2")]
@@ -1852,6 +2865,83 @@ mod tests {
             .receive_job_status_update_from_worker(2, (1, 1), JobWorkerStatus::WaitingToExecute);
     }
 
+    #[test]
+    fn peer_for_artifact() {
+        let server_1 = SocketAddr::from(([10, 0, 0, 1], 1234));
+        let server_2 = SocketAddr::from(([10, 0, 0, 2], 1234));
+        let mut fixture = Fixture::new().with_client(1);
+        fixture.receive_worker_connected_with_artifact_server(1, 1, Some(server_1));
+        fixture.receive_worker_connected_with_artifact_server(2, 1, Some(server_2));
+        fixture.receive_worker_connected(3, 1);
+
+        fixture
+            .expect()
+            .start_job((1, 1), [tar_digest!(1)], StartJob::Ready)
+            .send_enqueue_job_to_worker(1, (1, 1), spec!(1))
+            .when()
+            .receive_job_request_from_client(1, 1, spec!(1));
+        fixture
+            .expect()
+            .start_job((1, 2), [tar_digest!(1)], StartJob::Ready)
+            .send_enqueue_job_to_worker(2, (1, 2), spec!(1))
+            .when()
+            .receive_job_request_from_client(1, 2, spec!(1));
+        fixture
+            .expect()
+            .start_job((1, 3), [tar_digest!(1)], StartJob::Ready)
+            .send_enqueue_job_to_worker(3, (1, 3), spec!(1))
+            .when()
+            .receive_job_request_from_client(1, 3, spec!(1));
+        assert_eq!(fixture.sut.peer_for_artifact(&digest!(1)), None);
+
+        // A worker that's still waiting for layers may not have the artifact yet.
+        fixture
+            .expect()
+            .send_job_status_update_to_client(
+                1,
+                2,
+                JobBrokerStatus::AtWorker(2.into(), JobWorkerStatus::WaitingForLayers),
+            )
+            .when()
+            .receive_job_status_update_from_worker(2, (1, 2), JobWorkerStatus::WaitingForLayers);
+        assert_eq!(fixture.sut.peer_for_artifact(&digest!(1)), None);
+
+        fixture
+            .expect()
+            .send_job_status_update_to_client(
+                1,
+                1,
+                JobBrokerStatus::AtWorker(1.into(), JobWorkerStatus::WaitingToExecute),
+            )
+            .when()
+            .receive_job_status_update_from_worker(1, (1, 1), JobWorkerStatus::WaitingToExecute);
+        assert_eq!(fixture.sut.peer_for_artifact(&digest!(1)), Some(server_1));
+        assert_eq!(fixture.sut.peer_for_artifact(&digest!(2)), None);
+
+        // Workers that don't serve artifacts are never chosen.
+        fixture
+            .expect()
+            .send_job_status_update_to_client(
+                1,
+                3,
+                JobBrokerStatus::AtWorker(3.into(), JobWorkerStatus::Executing),
+            )
+            .when()
+            .receive_job_status_update_from_worker(3, (1, 3), JobWorkerStatus::Executing);
+        fixture
+            .expect()
+            .send_job_status_update_to_client(
+                1,
+                2,
+                JobBrokerStatus::AtWorker(2.into(), JobWorkerStatus::Executing),
+            )
+            .when()
+            .receive_job_status_update_from_worker(2, (1, 2), JobWorkerStatus::Executing);
+        assert_eq!(fixture.sut.peer_for_artifact(&digest!(1)), Some(server_2));
+        assert_eq!(fixture.sut.peer_for_artifact(&digest!(1)), Some(server_1));
+        assert_eq!(fixture.sut.peer_for_artifact(&digest!(1)), Some(server_2));
+    }
+
     #[test]
     #[should_panic(expected = "received monitor_connected message for duplicate monitor: 1")]
     fn receive_monitor_connected_for_duplicate_monitor() {
@@ -1918,6 +3008,9 @@ mod tests {
                         client_to_stats: hashmap! {
                             1.into() => enum_map! { _ => 0 },
                         },
+                        worker_to_stats: hashmap! {
+                            10.into() => WorkerStatisticsSample { jobs: 0, completed: 0 },
+                        },
                     }]),
                 },
             )
@@ -1976,6 +3069,9 @@ mod tests {
                             client_to_stats: hashmap! {
                                 1.into() => enum_map! { _ => 0 },
                             },
+                            worker_to_stats: hashmap! {
+                                10.into() => WorkerStatisticsSample { jobs: 0, completed: 0 },
+                            },
                         },
                         JobStatisticsSample {
                             client_to_stats: hashmap! {
@@ -1986,6 +3082,9 @@ mod tests {
                                     JobState::Complete => 1,
                                 },
                             },
+                            worker_to_stats: hashmap! {
+                                10.into() => WorkerStatisticsSample { jobs: 2, completed: 1 },
+                            },
                         },
                     ]),
                 },
@@ -1995,13 +3094,136 @@ mod tests {
     }
 
     #[test]
-    fn requests_go_to_workers_based_on_subscription_percentage() {
-        let mut fixture = Fixture::new()
-            .with_client(1)
-            .with_worker(1, 2)
-            .with_worker(2, 2)
-            .with_worker(3, 3);
-
+    #[should_panic(expected = "received jobs_request message from unknown monitor: 1")]
+    fn receive_jobs_request_from_monitor_for_unknown_monitor() {
+        let mut fixture = Fixture::new();
+        fixture.receive_jobs_request_from_monitor(1);
+    }
+
+    #[test]
+    fn receive_jobs_request_from_monitor() {
+        let mut fixture = Fixture::new().with_worker(10, 1).with_client(1);
+        fixture.receive_monitor_connected(1);
+        fixture
+            .expect()
+            .start_job((1, 1), [tar_digest!(1)], StartJob::Ready)
+            .send_enqueue_job_to_worker(10, (1, 1), spec!(1, priority: 1))
+            .when()
+            .receive_job_request_from_client(1, 1, spec!(1, priority: 1));
+        fixture.advance_time(2);
+        fixture
+            .expect()
+            .start_job((1, 2), [tar_digest!(2)], StartJob::NotReady)
+            .send_job_status_update_to_client(1, 2, JobBrokerStatus::WaitingForLayers)
+            .when()
+            .receive_job_request_from_client(1, 2, spec!(2));
+        fixture.advance_time(1);
+
+        fixture
+            .expect()
+            .send_jobs_response_to_monitor(
+                1,
+                vec![
+                    JobStatistics {
+                        id: (1, 1).into(),
+                        priority: 1,
+                        status: JobBrokerStatus::AtWorker(
+                            10.into(),
+                            JobWorkerStatus::WaitingForLayers,
+                        ),
+                        elapsed: Duration::from_secs(3),
+                    },
+                    JobStatistics {
+                        id: (1, 2).into(),
+                        priority: 0,
+                        status: JobBrokerStatus::WaitingForLayers,
+                        elapsed: Duration::from_secs(1),
+                    },
+                ],
+            )
+            .when()
+            .receive_jobs_request_from_monitor(1);
+    }
+
+    #[test]
+    fn receive_client_statistics_request_from_monitor() {
+        let mut fixture = Fixture::new().with_worker(10, 1).with_client(1);
+        fixture.receive_monitor_connected(1);
+        fixture
+            .expect()
+            .send_client_statistics_response_to_monitor(1, 2, None)
+            .when()
+            .receive_client_statistics_request_from_monitor(1, 2);
+
+        fixture
+            .expect()
+            .start_job((1, 1), [tar_digest!(1)], StartJob::Ready)
+            .send_enqueue_job_to_worker(10, (1, 1), spec!(1))
+            .when()
+            .receive_job_request_from_client(1, 1, spec!(1));
+        fixture
+            .expect()
+            .start_job((1, 2), [tar_digest!(2)], StartJob::Ready)
+            .send_enqueue_job_to_worker(10, (1, 2), spec!(2))
+            .when()
+            .receive_job_request_from_client(1, 2, spec!(2));
+        fixture
+            .expect()
+            .start_job((1, 3), [tar_digest!(3)], StartJob::NotReady)
+            .send_job_status_update_to_client(1, 3, JobBrokerStatus::WaitingForLayers)
+            .when()
+            .receive_job_request_from_client(1, 3, spec!(3));
+        fixture.advance_time(1);
+        fixture
+            .expect()
+            .send_job_response_to_client(1, 2, Ok(outcome!(2)))
+            .complete_job((1, 2))
+            .when()
+            .receive_job_response_from_worker(10, (1, 2), Ok(outcome!(2)));
+        fixture.advance_time(1);
+        fixture
+            .expect()
+            .send_job_response_to_client(1, 1, Ok(outcome!(1)))
+            .complete_job((1, 1))
+            .when()
+            .receive_job_response_from_worker(10, (1, 1), Ok(outcome!(1)));
+        fixture
+            .expect()
+            .send_job_response_to_client(1, 3, Err(JobError::System("error".into())))
+            .when()
+            .receive_jobs_failed_from_artifact_gatherer([(1, 3)], "error");
+
+        let completion = |cjid: u32, worker: Option<u32>, succeeded, secs| JobCompletion {
+            cjid: cjid.into(),
+            worker: worker.map(Into::into),
+            succeeded,
+            latency: Duration::from_secs(secs),
+        };
+        fixture
+            .expect()
+            .send_client_statistics_response_to_monitor(
+                1,
+                1,
+                Some(ClientStatistics {
+                    recent_completions: vec![
+                        completion(2, Some(10), false, 1),
+                        completion(1, Some(10), true, 2),
+                        completion(3, None, false, 2),
+                    ],
+                }),
+            )
+            .when()
+            .receive_client_statistics_request_from_monitor(1, 1);
+    }
+
+    #[test]
+    fn requests_go_to_workers_based_on_subscription_percentage() {
+        let mut fixture = Fixture::new()
+            .with_client(1)
+            .with_worker(1, 2)
+            .with_worker(2, 2)
+            .with_worker(3, 3);
+
         // 0/2 0/2 0/3
         fixture
             .expect()
@@ -2200,4 +3422,406 @@ mod tests {
             .when()
             .receive_worker_connected(5, 1);
     }
+
+    #[test]
+    fn worker_low_on_disk_space_does_not_get_next_job_when_it_completes_one() {
+        let mut fixture = Fixture::new()
+            .with_client(1)
+            .with_worker(1, 1)
+            .with_worker(2, 1);
+
+        fixture
+            .expect()
+            .start_job((1, 1), [tar_digest!(1)], StartJob::Ready)
+            .send_enqueue_job_to_worker(1, (1, 1), spec!(1))
+            .when()
+            .receive_job_request_from_client(1, 1, spec!(1));
+        fixture
+            .expect()
+            .start_job((1, 2), [tar_digest!(2)], StartJob::Ready)
+            .send_enqueue_job_to_worker(2, (1, 2), spec!(2))
+            .when()
+            .receive_job_request_from_client(1, 2, spec!(2));
+        fixture
+            .expect()
+            .start_job((1, 3), [tar_digest!(3)], StartJob::Ready)
+            .send_enqueue_job_to_worker(1, (1, 3), spec!(3))
+            .when()
+            .receive_job_request_from_client(1, 3, spec!(3));
+        fixture
+            .expect()
+            .start_job((1, 4), [tar_digest!(4)], StartJob::Ready)
+            .send_enqueue_job_to_worker(2, (1, 4), spec!(4))
+            .when()
+            .receive_job_request_from_client(1, 4, spec!(4));
+        fixture
+            .expect()
+            .start_job((1, 5), [tar_digest!(5)], StartJob::Ready)
+            .send_job_status_update_to_client(1, 5, JobBrokerStatus::WaitingForWorker)
+            .when()
+            .receive_job_request_from_client(1, 5, spec!(5));
+
+        fixture.receive_disk_space_low_from_worker(1, true);
+
+        fixture
+            .expect()
+            .complete_job((1, 1))
+            .send_job_response_to_client(1, 1, Ok(outcome!(1)))
+            .when()
+            .receive_job_response_from_worker(1, (1, 1), Ok(outcome!(1)));
+        fixture
+            .expect()
+            .complete_job((1, 2))
+            .send_job_response_to_client(1, 2, Ok(outcome!(2)))
+            .send_enqueue_job_to_worker(2, (1, 5), spec!(5))
+            .when()
+            .receive_job_response_from_worker(2, (1, 2), Ok(outcome!(2)));
+    }
+
+    #[test]
+    fn receive_drain_worker() {
+        let mut fixture = Fixture::new()
+            .with_client(1)
+            .with_worker(1, 1)
+            .with_worker(2, 1);
+        fixture
+            .expect()
+            .start_job((1, 1), [tar_digest!(1)], StartJob::Ready)
+            .send_enqueue_job_to_worker(1, (1, 1), spec!(1))
+            .when()
+            .receive_job_request_from_client(1, 1, spec!(1));
+
+        assert!(fixture.receive_drain_worker(1));
+        assert!(!fixture.receive_drain_worker(3));
+
+        fixture
+            .expect()
+            .start_job((1, 2), [tar_digest!(2)], StartJob::Ready)
+            .send_enqueue_job_to_worker(2, (1, 2), spec!(2))
+            .when()
+            .receive_job_request_from_client(1, 2, spec!(2));
+        fixture
+            .expect()
+            .start_job((1, 3), [tar_digest!(3)], StartJob::Ready)
+            .send_enqueue_job_to_worker(2, (1, 3), spec!(3))
+            .when()
+            .receive_job_request_from_client(1, 3, spec!(3));
+        fixture
+            .expect()
+            .start_job((1, 4), [tar_digest!(4)], StartJob::Ready)
+            .send_job_status_update_to_client(1, 4, JobBrokerStatus::WaitingForWorker)
+            .when()
+            .receive_job_request_from_client(1, 4, spec!(4));
+
+        // The draining worker finishing a job doesn't give it a new one.
+        fixture
+            .expect()
+            .send_job_response_to_client(1, 1, Ok(outcome!(1)))
+            .complete_job((1, 1))
+            .when()
+            .receive_job_response_from_worker(1, (1, 1), Ok(outcome!(1)));
+
+        fixture
+            .expect()
+            .send_job_response_to_client(1, 2, Ok(outcome!(2)))
+            .complete_job((1, 2))
+            .send_enqueue_job_to_worker(2, (1, 4), spec!(4))
+            .when()
+            .receive_job_response_from_worker(2, (1, 2), Ok(outcome!(2)));
+
+        assert_eq!(
+            fixture.sut.workers(),
+            vec![
+                WorkerInfo {
+                    id: 1.into(),
+                    slots: 1,
+                    jobs: vec![],
+                    exclusive: false,
+                    draining: true,
+                    disk_space_low: false,
+                },
+                WorkerInfo {
+                    id: 2.into(),
+                    slots: 1,
+                    jobs: vec![(1, 3).into(), (1, 4).into()],
+                    exclusive: false,
+                    draining: false,
+                    disk_space_low: false,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn receive_disk_space_low_from_worker() {
+        let mut fixture = Fixture::new()
+            .with_client(1)
+            .with_worker(1, 1)
+            .with_worker(2, 1);
+
+        fixture.receive_disk_space_low_from_worker(1, true);
+
+        fixture
+            .expect()
+            .start_job((1, 1), [tar_digest!(1)], StartJob::Ready)
+            .send_enqueue_job_to_worker(2, (1, 1), spec!(1))
+            .when()
+            .receive_job_request_from_client(1, 1, spec!(1));
+        fixture
+            .expect()
+            .start_job((1, 2), [tar_digest!(2)], StartJob::Ready)
+            .send_enqueue_job_to_worker(2, (1, 2), spec!(2))
+            .when()
+            .receive_job_request_from_client(1, 2, spec!(2));
+        fixture
+            .expect()
+            .start_job((1, 3), [tar_digest!(3)], StartJob::Ready)
+            .send_job_status_update_to_client(1, 3, JobBrokerStatus::WaitingForWorker)
+            .when()
+            .receive_job_request_from_client(1, 3, spec!(3));
+
+        assert_eq!(
+            fixture.sut.workers(),
+            vec![
+                WorkerInfo {
+                    id: 1.into(),
+                    slots: 1,
+                    jobs: vec![],
+                    exclusive: false,
+                    draining: false,
+                    disk_space_low: true,
+                },
+                WorkerInfo {
+                    id: 2.into(),
+                    slots: 1,
+                    jobs: vec![(1, 1).into(), (1, 2).into()],
+                    exclusive: false,
+                    draining: false,
+                    disk_space_low: false,
+                },
+            ]
+        );
+
+        // Once the worker has room again, it gets the queued job.
+        fixture
+            .expect()
+            .send_enqueue_job_to_worker(1, (1, 3), spec!(3))
+            .when()
+            .receive_disk_space_low_from_worker(1, false);
+    }
+
+    #[test]
+    fn receive_disconnect_worker() {
+        let mut fixture = Fixture::new()
+            .with_client(1)
+            .with_worker(1, 1)
+            .with_worker(2, 1);
+        fixture
+            .expect()
+            .start_job((1, 1), [tar_digest!(1)], StartJob::Ready)
+            .send_enqueue_job_to_worker(1, (1, 1), spec!(1))
+            .when()
+            .receive_job_request_from_client(1, 1, spec!(1));
+
+        assert!(!fixture.receive_disconnect_worker(3));
+        fixture
+            .expect()
+            .send_enqueue_job_to_worker(2, (1, 1), spec!(1))
+            .worker_sender_drop(1)
+            .when()
+            .receive_disconnect_worker(1)
+            .assert_is_true();
+
+        // Messages from the worker that were already in flight are ignored.
+        fixture.receive_job_status_update_from_worker(1, (1, 1), JobWorkerStatus::Executing);
+        fixture.receive_job_response_from_worker(1, (1, 1), Ok(outcome!(1)));
+        fixture.receive_worker_disconnected(1);
+
+        fixture.assert_job_state_counts_for_client(
+            1,
+            enum_map! {
+                JobState::Running => 1,
+                _ => 0,
+            },
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "received worker_disconnected message for unknown worker: 1")]
+    fn receive_worker_disconnected_twice_after_disconnect_worker() {
+        let mut fixture = Fixture::new().with_worker(1, 1);
+        fixture
+            .expect()
+            .worker_sender_drop(1)
+            .when()
+            .receive_disconnect_worker(1)
+            .assert_is_true();
+        fixture.receive_worker_disconnected(1);
+        fixture.receive_worker_disconnected(1);
+    }
+
+    #[test]
+    fn receive_cancel_client_jobs() {
+        let mut fixture = Fixture::new().with_client(1).with_worker(1, 1);
+        fixture
+            .expect()
+            .start_job((1, 1), [tar_digest!(1)], StartJob::Ready)
+            .send_enqueue_job_to_worker(1, (1, 1), spec!(1))
+            .when()
+            .receive_job_request_from_client(1, 1, spec!(1));
+        fixture
+            .expect()
+            .start_job((1, 2), [tar_digest!(2)], StartJob::Ready)
+            .send_enqueue_job_to_worker(1, (1, 2), spec!(2))
+            .when()
+            .receive_job_request_from_client(1, 2, spec!(2));
+        fixture
+            .expect()
+            .start_job((1, 3), [tar_digest!(3)], StartJob::Ready)
+            .send_job_status_update_to_client(1, 3, JobBrokerStatus::WaitingForWorker)
+            .when()
+            .receive_job_request_from_client(1, 3, spec!(3));
+        fixture
+            .expect()
+            .start_job((1, 4), [tar_digest!(4)], StartJob::NotReady)
+            .send_job_status_update_to_client(1, 4, JobBrokerStatus::WaitingForLayers)
+            .when()
+            .receive_job_request_from_client(1, 4, spec!(4));
+        fixture
+            .expect()
+            .start_job((1, 5), [tar_digest!(5)], StartJob::NotReady)
+            .send_job_status_update_to_client(1, 5, JobBrokerStatus::WaitingForLayers)
+            .when()
+            .receive_job_request_from_client(1, 5, spec!(5));
+
+        assert_eq!(fixture.receive_cancel_client_jobs(2), None);
+
+        let canceled = || Err(JobError::System("canceled by broker administrator".into()));
+        assert_eq!(
+            fixture
+                .expect()
+                .send_cancel_job_to_worker(1, (1, 1))
+                .send_cancel_job_to_worker(1, (1, 2))
+                .complete_job((1, 1))
+                .complete_job((1, 2))
+                .complete_job((1, 3))
+                .send_job_response_to_client(1, 1, canceled())
+                .send_job_response_to_client(1, 2, canceled())
+                .send_job_response_to_client(1, 3, canceled())
+                .send_job_response_to_client(1, 4, canceled())
+                .send_job_response_to_client(1, 5, canceled())
+                .when()
+                .receive_cancel_client_jobs(1),
+            Some(5)
+        );
+        fixture.assert_job_state_counts_for_client(
+            1,
+            enum_map! {
+                JobState::Complete => 5,
+                _ => 0,
+            },
+        );
+
+        // The artifact gatherer finishing with the canceled jobs doesn't produce another response.
+        fixture
+            .expect()
+            .complete_job((1, 4))
+            .when()
+            .receive_jobs_ready_from_artifact_gatherer([(1, 4)]);
+        fixture.receive_jobs_failed_from_artifact_gatherer([(1, 5)], "error");
+        fixture.receive_job_response_from_worker(1, (1, 1), Ok(outcome!(1)));
+
+        assert_eq!(fixture.sut.jobs(), vec![]);
+    }
+
+    #[test]
+    fn snapshots() {
+        let mut fixture = Fixture::new().with_client(1).with_worker(1, 1);
+        fixture
+            .expect()
+            .start_job((1, 1), [tar_digest!(1)], StartJob::Ready)
+            .send_enqueue_job_to_worker(1, (1, 1), spec!(1))
+            .when()
+            .receive_job_request_from_client(1, 1, spec!(1));
+        fixture
+            .expect()
+            .send_job_status_update_to_client(
+                1,
+                1,
+                JobBrokerStatus::AtWorker(1.into(), JobWorkerStatus::Executing),
+            )
+            .when()
+            .receive_job_status_update_from_worker(1, (1, 1), JobWorkerStatus::Executing);
+        fixture
+            .expect()
+            .start_job((1, 2), [tar_digest!(2)], StartJob::Ready)
+            .send_enqueue_job_to_worker(1, (1, 2), spec!(2, priority: 1))
+            .when()
+            .receive_job_request_from_client(1, 2, spec!(2, priority: 1));
+        fixture
+            .expect()
+            .start_job((1, 3), [tar_digest!(3)], StartJob::Ready)
+            .send_job_status_update_to_client(1, 3, JobBrokerStatus::WaitingForWorker)
+            .when()
+            .receive_job_request_from_client(1, 3, spec!(3));
+        fixture
+            .expect()
+            .start_job((1, 4), [tar_digest!(4)], StartJob::NotReady)
+            .send_job_status_update_to_client(1, 4, JobBrokerStatus::WaitingForLayers)
+            .when()
+            .receive_job_request_from_client(1, 4, spec!(4));
+
+        assert_eq!(
+            fixture.sut.clients(),
+            vec![ClientInfo {
+                id: 1.into(),
+                jobs: enum_map! {
+                    JobState::WaitingForArtifacts => 1,
+                    JobState::Pending => 1,
+                    JobState::Running => 2,
+                    JobState::Complete => 0,
+                },
+            }]
+        );
+        assert_eq!(
+            fixture.sut.workers(),
+            vec![WorkerInfo {
+                id: 1.into(),
+                slots: 1,
+                jobs: vec![(1, 1).into(), (1, 2).into()],
+                exclusive: false,
+                draining: false,
+                disk_space_low: false,
+            }]
+        );
+        assert_eq!(
+            fixture.sut.jobs(),
+            vec![
+                JobInfo {
+                    id: (1, 1).into(),
+                    priority: 0,
+                    exclusive: false,
+                    status: JobBrokerStatus::AtWorker(1.into(), JobWorkerStatus::Executing),
+                },
+                JobInfo {
+                    id: (1, 2).into(),
+                    priority: 1,
+                    exclusive: false,
+                    status: JobBrokerStatus::AtWorker(1.into(), JobWorkerStatus::WaitingForLayers),
+                },
+                JobInfo {
+                    id: (1, 3).into(),
+                    priority: 0,
+                    exclusive: false,
+                    status: JobBrokerStatus::WaitingForWorker,
+                },
+                JobInfo {
+                    id: (1, 4).into(),
+                    priority: 0,
+                    exclusive: false,
+                    status: JobBrokerStatus::WaitingForLayers,
+                },
+            ]
+        );
+    }
 }
@@ -0,0 +1,362 @@
+use anyhow::{bail, Context as _, Result};
+use bytesize::ByteSize;
+use clap::{Args, Parser, Subcommand};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use maelstrom_base::{JobId, Sha256Digest};
+use maelstrom_util::{
+    cache::{
+        self,
+        fs::{std::Fs as StdFs, FileType},
+        GetStrategy, Key,
+    },
+    config::common::{CacheSize, LogLevel},
+    io::Sha256Stream,
+    root::RootBuf,
+};
+use slog::Logger;
+use std::{
+    fs::{self, File},
+    io,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+    time::{Duration, SystemTime},
+};
+
+#[derive(Subcommand)]
+enum CliCommands {
+    /// List the entries in the cache.
+    List {
+        #[command(flatten)]
+        cache: CacheOptions,
+    },
+    /// Re-hash the entries in the cache and check them against their digests. Only file entries
+    /// can be checked, since the digests of directory entries aren't of their contents.
+    Verify {
+        #[command(flatten)]
+        cache: CacheOptions,
+        /// Remove any entry that fails verification.
+        #[arg(long)]
+        remove: bool,
+    },
+    /// Remove entries from the cache. Entries that are pinned are never removed.
+    Evict {
+        #[command(flatten)]
+        cache: CacheOptions,
+        /// Only remove entries matching one of these glob patterns. Patterns are matched against
+        /// both the entry's digest and its "<kind>/<digest>", so "blob/*" and "3f2a*" both work.
+        patterns: Vec<String>,
+        /// Only remove entries that haven't been used in this many days.
+        #[arg(long, value_name = "DAYS")]
+        unused_for: Option<u64>,
+        /// Print what would be removed without removing anything.
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Pin digests so that their entries are never evicted to reclaim space. Digests that aren't
+    /// in the cache yet can be pinned too, and will be kept once they're added.
+    Pin {
+        #[command(flatten)]
+        cache: CacheOptions,
+        #[arg(required = true)]
+        digests: Vec<Sha256Digest>,
+    },
+    /// Unpin digests that were pinned with `pin`.
+    Unpin {
+        #[command(flatten)]
+        cache: CacheOptions,
+        #[arg(required = true)]
+        digests: Vec<Sha256Digest>,
+    },
+    /// List the pinned digests.
+    Pinned {
+        #[command(flatten)]
+        cache: CacheOptions,
+    },
+}
+
+#[derive(Args)]
+struct CacheOptions {
+    /// The cache directory. This can also be a worker's `cache-root`, in which case its artifact
+    /// cache is used.
+    #[arg(long, value_name = "PATH")]
+    cache_root: PathBuf,
+}
+
+/// The kinds of entries in the cache being worked on. Workers, brokers, and clients all use
+/// different kinds, so we get them from the cache directory instead of hard-coding them. This has
+/// to be set before the cache is opened, since [`cache::Cache::new`] removes the directories of
+/// any kinds it doesn't know about.
+static KINDS: OnceLock<Vec<&'static str>> = OnceLock::new();
+
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+struct CacheKey {
+    kind: &'static str,
+    digest: Sha256Digest,
+}
+
+impl Key for CacheKey {
+    fn kinds() -> impl Iterator<Item = &'static str> {
+        KINDS.get().unwrap().iter().copied()
+    }
+
+    fn from_kind_and_digest(kind: &'static str, digest: Sha256Digest) -> Self {
+        Self { kind, digest }
+    }
+
+    fn kind(&self) -> &'static str {
+        self.kind
+    }
+
+    fn digest(&self) -> &Sha256Digest {
+        &self.digest
+    }
+}
+
+/// Nothing is ever gotten through the cache by this tool, so there are no getters.
+enum NoGetStrategy {}
+
+impl GetStrategy for NoGetStrategy {
+    type Getter = ();
+    fn getter_from_job_id(_jid: JobId) -> Self::Getter {}
+}
+
+type Cache = cache::Cache<StdFs, CacheKey, NoGetStrategy>;
+
+impl CacheOptions {
+    /// Open the cache. This takes the cache's lock file, so it fails if a worker, broker, or
+    /// client is currently using the cache.
+    fn cache(&self, log: Logger) -> Result<Cache> {
+        let mut root = self.cache_root.clone();
+        if !is_cache_dir(&root) && is_cache_dir(&root.join("artifacts")) {
+            root = root.join("artifacts");
+        }
+        if !is_cache_dir(&root) {
+            bail!("{root:?} doesn't look like a Maelstrom cache directory");
+        }
+
+        let mut kinds = vec![];
+        for entry in fs::read_dir(root.join("sha256"))? {
+            let entry = entry?;
+            if entry.file_type()?.is_dir() {
+                let Ok(kind) = entry.file_name().into_string() else {
+                    continue;
+                };
+                kinds.push(&*String::leak(kind));
+            }
+        }
+        KINDS.set(kinds).unwrap();
+
+        // We don't want opening the cache to evict anything.
+        let size = CacheSize::from(ByteSize::b(u64::MAX));
+        let (cache, _) = Cache::new(StdFs, RootBuf::new(root.clone()), size, log, false)
+            .with_context(|| {
+                format!("opening cache {root:?}; is a worker, broker, or client using it?")
+            })?;
+        Ok(cache)
+    }
+}
+
+fn is_cache_dir(path: &Path) -> bool {
+    path.join("CACHEDIR.TAG").is_file() && path.join("sha256").is_dir()
+}
+
+/// The information about an entry that we need. This is separate from [`cache::EntryInfo`] so
+/// that we don't hold a borrow on the cache while modifying it.
+struct Entry {
+    key: CacheKey,
+    file_type: FileType,
+    bytes_used: u64,
+    pinned: bool,
+    last_used: Option<SystemTime>,
+}
+
+fn entries(cache: &Cache) -> Result<Vec<Entry>> {
+    let mut entries = cache
+        .entries()
+        .map(|entry| {
+            let last_used = cache
+                .last_use_path(entry.key)
+                .map(|path| fs::metadata(path)?.modified())
+                .transpose()?;
+            Ok(Entry {
+                key: entry.key.clone(),
+                file_type: entry.file_type,
+                bytes_used: entry.bytes_used,
+                pinned: entry.pinned,
+                last_used,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    entries
+        .sort_by(|lhs, rhs| (lhs.key.kind, &lhs.key.digest).cmp(&(rhs.key.kind, &rhs.key.digest)));
+    Ok(entries)
+}
+
+fn days_since(time: SystemTime) -> u64 {
+    SystemTime::now()
+        .duration_since(time)
+        .unwrap_or_default()
+        .as_secs()
+        / (24 * 60 * 60)
+}
+
+fn verify_entry(cache: &Cache, entry: &Entry) -> Result<()> {
+    let path = cache.cache_path(&entry.key);
+    let mut reader = Sha256Stream::new(File::open(&path)?);
+    io::copy(&mut reader, &mut io::sink())?;
+    let (_, digest) = reader.finalize();
+    if digest != entry.key.digest {
+        bail!("contents have digest {digest}");
+    }
+    Ok(())
+}
+
+/// Remove the entry even if it's pinned. The digest stays pinned, so the entry will be kept once
+/// it's added again.
+fn remove_entry(cache: &mut Cache, entry: &Entry) -> Result<()> {
+    if entry.pinned {
+        cache.unpin(&entry.key.digest)?;
+    }
+    cache.remove(&entry.key)?;
+    if entry.pinned {
+        cache.pin(entry.key.digest.clone())?;
+    }
+    Ok(())
+}
+
+fn build_glob_set(patterns: &[String]) -> Result<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        builder.add(Glob::new(pattern)?);
+    }
+    Ok(builder.build()?)
+}
+
+/// Inspect and manage a Maelstrom artifact cache. The cache must not be in use: this tool takes the
+/// cache's lock, so the worker, broker, or client using it has to be stopped first.
+#[derive(Parser)]
+#[command(styles=maelstrom_util::clap::styles())]
+struct CliOptions {
+    #[command(subcommand)]
+    command: CliCommands,
+}
+
+fn run(opt: CliOptions, log: Logger) -> Result<()> {
+    match opt.command {
+        CliCommands::List { cache } => {
+            let cache = cache.cache(log)?;
+            let entries = entries(&cache)?;
+            let mut total = 0;
+            for entry in &entries {
+                let last_used = match entry.last_used {
+                    Some(last_used) => format!("last used {} days ago", days_since(last_used)),
+                    None => "last use unknown".into(),
+                };
+                let pinned = if entry.pinned { " (pinned)" } else { "" };
+                println!(
+                    "{} {} {} {}{pinned}",
+                    entry.key.kind,
+                    entry.key.digest,
+                    ByteSize::b(entry.bytes_used),
+                    last_used,
+                );
+                total += entry.bytes_used;
+            }
+            println!("{} entries using {}", entries.len(), ByteSize::b(total));
+        }
+        CliCommands::Verify { cache, remove } => {
+            let mut cache = cache.cache(log)?;
+            let mut failed = 0;
+            for entry in entries(&cache)? {
+                if entry.file_type != FileType::File {
+                    continue;
+                }
+                if let Err(err) = verify_entry(&cache, &entry) {
+                    println!("{}/{}: {err:#}", entry.key.kind, entry.key.digest);
+                    failed += 1;
+                    if remove {
+                        remove_entry(&mut cache, &entry)?;
+                    }
+                }
+            }
+            if failed > 0 && !remove {
+                bail!("{failed} entries failed verification");
+            }
+        }
+        CliCommands::Evict {
+            cache,
+            patterns,
+            unused_for,
+            dry_run,
+        } => {
+            if patterns.is_empty() && unused_for.is_none() {
+                bail!("nothing to evict; specify patterns and/or --unused-for");
+            }
+            let glob_set = build_glob_set(&patterns)?;
+            let unused_since =
+                unused_for.map(|days| SystemTime::now() - Duration::from_secs(days * 24 * 60 * 60));
+            let mut cache = cache.cache(log)?;
+            let mut freed = 0;
+            for entry in entries(&cache)? {
+                let digest = entry.key.digest.to_string();
+                let matches_patterns = patterns.is_empty()
+                    || glob_set.is_match(&digest)
+                    || glob_set.is_match(format!("{}/{digest}", entry.key.kind));
+                let matches_unused = unused_since
+                    .is_none_or(|since| entry.last_used.is_some_and(|last_used| last_used < since));
+                if !matches_patterns || !matches_unused {
+                    continue;
+                }
+                if entry.pinned {
+                    println!("skipping pinned {}/{digest}", entry.key.kind);
+                    continue;
+                }
+                println!(
+                    "{} {}/{digest} ({})",
+                    if dry_run { "would remove" } else { "removing" },
+                    entry.key.kind,
+                    ByteSize::b(entry.bytes_used)
+                );
+                if !dry_run {
+                    cache.remove(&entry.key)?;
+                }
+                freed += entry.bytes_used;
+            }
+            if dry_run {
+                println!("would free {}", ByteSize::b(freed));
+            } else {
+                println!("freed {}", ByteSize::b(freed));
+            }
+        }
+        CliCommands::Pin { cache, digests } => {
+            let mut cache = cache.cache(log)?;
+            for digest in digests {
+                if !cache.pin(digest.clone())? {
+                    println!("{digest} is already pinned");
+                }
+            }
+        }
+        CliCommands::Unpin { cache, digests } => {
+            let mut cache = cache.cache(log)?;
+            for digest in digests {
+                if !cache.unpin(&digest)? {
+                    println!("{digest} isn't pinned");
+                }
+            }
+        }
+        CliCommands::Pinned { cache } => {
+            let cache = cache.cache(log)?;
+            let mut pinned = cache.pinned().collect::<Vec<_>>();
+            pinned.sort();
+            for digest in pinned {
+                println!("{digest}");
+            }
+        }
+    }
+    Ok(())
+}
+
+fn main() -> Result<()> {
+    let opt = CliOptions::parse();
+    maelstrom_util::log::run_with_logger(LogLevel::Info, |log| run(opt, log))
+}
@@ -23,6 +23,7 @@ use maelstrom_util::{
     root::RootBuf,
 };
 use serde::Deserialize;
+use std::path::PathBuf;
 
 /// The project directory is used for two things. First, any relative paths in layer specifications
 /// are resolved based on this path. Second, it's where the client process looks for the
@@ -50,6 +51,7 @@ pub const MANIFEST_DIR: &str = "manifests";
 pub const STUB_MANIFEST_DIR: &str = "manifests/stubs";
 pub const SYMLINK_MANIFEST_DIR: &str = "manifests/symlinks";
 pub const SO_LISTINGS_DIR: &str = "so-listings";
+pub const SETUP_LAYERS_DIR: &str = "setup-layers";
 
 impl From<proto::Error> for anyhow::Error {
     fn from(e: proto::Error) -> Self {
@@ -234,3 +236,11 @@ pub struct AddContainerRequest {
     #[proto(option)]
     pub container: spec::ContainerSpec,
 }
+
+#[derive(IntoProtoBuf, TryFromProtoBuf)]
+#[proto(proto_buf_type = "proto::ExportImageRequest")]
+pub struct ExportImageRequest {
+    #[proto(option)]
+    pub spec: spec::JobSpec,
+    pub path: PathBuf,
+}
@@ -2,16 +2,19 @@ use crate::proto;
 use anyhow::{anyhow, Result};
 use enumset::{EnumSet, EnumSetType};
 use maelstrom_base::{
-    capture_file_system_changes_pocket_definition, client_job_id_pocket_definition,
-    group_id_pocket_definition, job_broker_status_pocket_definition,
-    job_completed_pocket_definition, job_device_pocket_definition, job_effects_pocket_definition,
-    job_mount_pocket_definition, job_network_pocket_definition, job_outcome_pocket_definition,
-    job_output_result_pocket_definition, job_termination_status_pocket_definition,
-    job_tty_pocket_definition, job_worker_status_pocket_definition, timeout_pocket_definition,
-    user_id_pocket_definition, window_size_pocket_definition, worker_id_pocket_definition,
-    CaptureFileSystemChanges, ClientJobId, GroupId, JobBrokerStatus, JobCompleted, JobDevice,
-    JobEffects, JobMount, JobNetwork, JobOutcome, JobOutputResult, JobTerminationStatus, JobTty,
-    JobWorkerStatus, Timeout, UserId, Utf8PathBuf, WindowSize, WorkerId,
+    capture_file_system_changes_pocket_definition, captured_file_pocket_definition,
+    client_job_id_pocket_definition, group_id_pocket_definition,
+    job_broker_status_pocket_definition, job_completed_pocket_definition,
+    job_device_pocket_definition, job_dns_pocket_definition, job_effects_pocket_definition,
+    job_hosts_entry_pocket_definition, job_mount_pocket_definition, job_network_pocket_definition,
+    job_outcome_pocket_definition, job_output_result_pocket_definition,
+    job_termination_status_pocket_definition, job_tty_pocket_definition,
+    job_worker_status_pocket_definition, timeout_pocket_definition, user_id_pocket_definition,
+    window_size_pocket_definition, worker_id_pocket_definition, CaptureFileSystemChanges,
+    CapturedFile, ClientJobId, GroupId, JobBrokerStatus, JobCompleted, JobDevice, JobDns,
+    JobEffects, JobHostsEntry, JobMount, JobNetwork, JobOutcome, JobOutputResult,
+    JobTerminationStatus, JobTty, JobWorkerStatus, Timeout, UserId, Utf8PathBuf, WindowSize,
+    WorkerId,
 };
 use maelstrom_macro::{
     into_proto_buf_remote_derive, remote_derive, try_from_proto_buf_remote_derive,
@@ -509,16 +512,37 @@ remote_derive!(
     proto(proto_buf_type = "proto::JobNetwork")
 );
 
+remote_derive!(
+    JobHostsEntry,
+    (IntoProtoBuf, TryFromProtoBuf),
+    proto(proto_buf_type = "proto::JobHostsEntry")
+);
+
+remote_derive!(
+    JobDns,
+    (IntoProtoBuf, TryFromProtoBuf),
+    proto(proto_buf_type = "proto::JobDns")
+);
+
 remote_derive!(
     CaptureFileSystemChanges,
     (IntoProtoBuf, TryFromProtoBuf),
     proto(proto_buf_type = "proto::CaptureFileSystemChanges")
 );
 
+remote_derive!(
+    CapturedFile,
+    (IntoProtoBuf, TryFromProtoBuf),
+    proto(proto_buf_type = "proto::CapturedFile")
+);
+
 remote_derive!(
     JobEffects,
     (IntoProtoBuf, TryFromProtoBuf),
-    proto(proto_buf_type = "proto::JobEffects", option_all)
+    proto(proto_buf_type = "proto::JobEffects"),
+    @stdout: proto(option),
+    @stderr: proto(option),
+    @duration: proto(option)
 );
 
 remote_derive!(
@@ -539,6 +563,7 @@ remote_derive!(
     @Proc: proto(proto_buf_type = "proto::ProcMount"),
     @Sys: proto(proto_buf_type = "proto::SysMount"),
     @Tmp: proto(proto_buf_type = "proto::TmpMount"),
+    @Capture: proto(proto_buf_type = "proto::CaptureMount"),
 );
 
 remote_derive!(
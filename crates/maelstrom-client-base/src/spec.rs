@@ -11,8 +11,8 @@ use anyhow::{Error, Result};
 use derive_more::From;
 use enumset::{EnumSet, EnumSetType};
 use maelstrom_base::{
-    CaptureFileSystemChanges, GroupId, JobMount, JobMountForTomlAndJson, JobNetwork, JobTty,
-    Timeout, UserId, Utf8PathBuf,
+    CaptureFileSystemChanges, GroupId, JobDns, JobMount, JobMountForTomlAndJson, JobNetwork,
+    JobTty, Timeout, UserId, Utf8PathBuf,
 };
 use maelstrom_util::template::TemplateVars;
 use serde::{
@@ -23,6 +23,7 @@ use serde::{
 use std::{
     collections::BTreeMap,
     env::{self, VarError},
+    net::IpAddr,
     path::PathBuf,
     time::Duration,
 };
@@ -212,6 +213,12 @@ macro_rules! container_ref {
     (@expand [-group $(, $($field_in:tt)*)?] -> [$name:literal, $use:expr]) => {
         $crate::container_ref!(@expand [$($($field_in)*)?] -> [$name, $use - $crate::spec::ContainerUse::Group])
     };
+    (@expand [dns $(, $($field_in:tt)*)?] -> [$name:literal, $use:expr]) => {
+        $crate::container_ref!(@expand [$($($field_in)*)?] -> [$name, $use | $crate::spec::ContainerUse::Dns])
+    };
+    (@expand [-dns $(, $($field_in:tt)*)?] -> [$name:literal, $use:expr]) => {
+        $crate::container_ref!(@expand [$($($field_in)*)?] -> [$name, $use - $crate::spec::ContainerUse::Dns])
+    };
     ($name:literal $(, $($field:tt)*)?) => {
         $crate::container_ref!(@expand [$($($field)*)?] -> [$name, ::maelstrom_base::EnumSet::empty()])
     };
@@ -323,6 +330,15 @@ macro_rules! environment_spec {
     };
 }
 
+impl EnvironmentSpec {
+    pub fn replace_template_vars(&mut self, vars: &TemplateVars) -> Result<()> {
+        for value in self.vars.values_mut() {
+            *value = vars.replace(value)?;
+        }
+        Ok(())
+    }
+}
+
 pub fn environment_eval(
     inital_env: BTreeMap<String, String>,
     env: Vec<EnvironmentSpec>,
@@ -385,6 +401,7 @@ pub struct ContainerSpec {
     pub network: Option<JobNetwork>,
     pub user: Option<UserId>,
     pub group: Option<GroupId>,
+    pub dns: Option<JobDns>,
 }
 
 #[macro_export]
@@ -422,6 +439,9 @@ macro_rules! container_spec {
     (@expand [group: $group:expr $(,$($field_in:tt)*)?] -> [$($($field_out:tt)+)?]) => {
         $crate::container_spec!(@expand [$($($field_in)*)?] -> [$($($field_out)+,)? group: Some(::maelstrom_base::GroupId::new($group))])
     };
+    (@expand [dns: $dns:expr $(,$($field_in:tt)*)?] -> [$($($field_out:tt)+)?]) => {
+        $crate::container_spec!(@expand [$($($field_in)*)?] -> [$($($field_out)+,)? dns: Some($dns)])
+    };
     ($($field_in:tt)*) => {
         $crate::container_spec!(@expand [$($field_in)*] -> [])
     };
@@ -443,6 +463,7 @@ pub struct ContainerSpecForTomlAndJson {
     pub network: Option<JobNetwork>,
     pub user: Option<UserId>,
     pub group: Option<GroupId>,
+    pub dns: Option<JobDns>,
 }
 
 impl TryFrom<ContainerSpecForTomlAndJson> for ContainerSpec {
@@ -463,6 +484,7 @@ impl TryFrom<ContainerSpecForTomlAndJson> for ContainerSpec {
             network,
             user,
             group,
+            dns,
         } = container;
 
         let mut to_remove_from_image_use = EnumSet::default();
@@ -716,6 +738,20 @@ impl TryFrom<ContainerSpecForTomlAndJson> for ContainerSpec {
             }
         }
 
+        if let Some(dns) = &dns {
+            check_dns(dns)?;
+            if let Some(parent) = &parent {
+                if parent.r#use.explicit().contains(ContainerUse::Dns) {
+                    return Err(concat!(
+                        "field `dns` cannot be set if `parent` with an ",
+                        "explicit `use` of `dns` is also specified",
+                    )
+                    .into());
+                }
+                to_remove_from_parent_use.insert(ContainerUse::Dns);
+            }
+        }
+
         Ok(ContainerSpec {
             parent: match (image, parent) {
                 (Some(image), _) => Some(ContainerParent::Image(ImageRef {
@@ -736,10 +772,33 @@ impl TryFrom<ContainerSpecForTomlAndJson> for ContainerSpec {
             network,
             user,
             group,
+            dns,
         })
     }
 }
 
+/// Make sure the addresses in a [`JobDns`] are IP addresses, and that every hosts entry has a name,
+/// so that mistakes are caught before the worker writes them into the job's files.
+pub fn check_dns(dns: &JobDns) -> Result<(), String> {
+    for nameserver in &dns.nameservers {
+        if nameserver.parse::<IpAddr>().is_err() {
+            return Err(format!("name server {nameserver:?} isn't an IP address"));
+        }
+    }
+    for entry in &dns.hosts {
+        if entry.address.parse::<IpAddr>().is_err() {
+            return Err(format!(
+                "hosts address {:?} isn't an IP address",
+                entry.address
+            ));
+        }
+        if entry.names.is_empty() {
+            return Err(format!("hosts entry for {} has no names", entry.address));
+        }
+    }
+    Ok(())
+}
+
 #[derive(Deserialize)]
 #[serde(untagged)]
 pub enum EnvSelector {
@@ -771,6 +830,7 @@ pub struct JobSpec {
     pub allocate_tty: Option<JobTty>,
     pub priority: i8,
     pub capture_file_system_changes: Option<CaptureFileSystemChanges>,
+    pub exclusive: bool,
 }
 
 #[macro_export]
@@ -787,6 +847,7 @@ macro_rules! job_spec {
                 allocate_tty: Default::default(),
                 priority: Default::default(),
                 capture_file_system_changes: Default::default(),
+                exclusive: Default::default(),
             }
         }
     };
@@ -815,6 +876,10 @@ macro_rules! job_spec {
         $crate::job_spec!(@expand [$program] [$($($field_in)*)?] ->
             [$($($field_out)+,)? capture_file_system_changes: Some($capture_file_system_changes)] [$($container_field)*])
     };
+    (@expand [$program:expr] [exclusive: $exclusive:expr $(,$($field_in:tt)*)?] -> [$($($field_out:tt)+)?] [$($container_field:tt)*]) => {
+        $crate::job_spec!(@expand [$program] [$($($field_in)*)?] ->
+            [$($($field_out)+,)? exclusive: $exclusive] [$($container_field)*])
+    };
 
     (@expand [$program:expr] [$container_field_name:ident: $container_field_value:expr $(,$($field_in:tt)*)?] -> [$($field_out:tt)*] [$($($container_field:tt)+)?]) => {
         $crate::job_spec!(@expand [$program] [$($($field_in)*)?] ->
@@ -837,6 +902,7 @@ struct JobSpecForTomlAndJson {
     arguments: Option<Vec<String>>,
     timeout: Option<u32>,
     priority: Option<i8>,
+    exclusive: Option<bool>,
 }
 
 impl From<JobSpecForTomlAndJson> for JobSpec {
@@ -847,6 +913,7 @@ impl From<JobSpecForTomlAndJson> for JobSpec {
             arguments,
             timeout,
             priority,
+            exclusive,
         } = job_spec;
         JobSpec {
             container,
@@ -857,6 +924,7 @@ impl From<JobSpecForTomlAndJson> for JobSpec {
             allocate_tty: None,
             priority: priority.unwrap_or_default(),
             capture_file_system_changes: None,
+            exclusive: exclusive.unwrap_or_default(),
         }
     }
 }
@@ -883,6 +951,8 @@ pub struct PrefixOptions {
     pub canonicalize: bool,
     #[serde(default)]
     pub follow_symlinks: bool,
+    #[serde(default)]
+    pub preserve_ownership: bool,
 }
 
 #[macro_export]
@@ -905,6 +975,9 @@ macro_rules! prefix_options {
     (@expand [follow_symlinks: $follow_symlinks:expr $(,$($field_in:tt)*)?] -> [$($($field_out:tt)+)?]) => {
         $crate::prefix_options!(@expand [$($($field_in)*)?] -> [$($($field_out)+,)? follow_symlinks: $follow_symlinks.into()])
     };
+    (@expand [preserve_ownership: $preserve_ownership:expr $(,$($field_in:tt)*)?] -> [$($($field_out:tt)+)?]) => {
+        $crate::prefix_options!(@expand [$($($field_in)*)?] -> [$($($field_out)+,)? preserve_ownership: $preserve_ownership.into()])
+    };
     ($($field_in:tt)*) => {
         $crate::prefix_options!(@expand [$($field_in)*] -> [])
     };
@@ -956,6 +1029,7 @@ pub enum LayerSpec {
     Stubs(StubsLayerSpec),
     Symlinks(SymlinksLayerSpec),
     SharedLibraryDependencies(SharedLibraryDependenciesLayerSpec),
+    Setup(SetupLayerSpec),
 }
 
 enum LayerSpecType {
@@ -965,6 +1039,7 @@ enum LayerSpecType {
     Stubs,
     Symlinks,
     SharedLibraryDependencies,
+    Setup,
 }
 
 #[derive(
@@ -1147,6 +1222,68 @@ macro_rules! shared_library_dependencies_layer_spec {
     };
 }
 
+/// A layer containing the changes a command makes to the file system when it is run in a container.
+/// This is useful for things like installing packages with `apt-get`, `pip`, or `npm`.
+///
+/// The command is run with local networking and this machine's `/etc/resolv.conf`, starting from
+/// `image` or the named container `parent`, with the `inputs` added relative to `/`. The result is
+/// cached, and the command is only run again when the spec or the contents of the inputs change.
+#[derive(
+    Clone,
+    Debug,
+    Deserialize,
+    Eq,
+    Hash,
+    IntoProtoBuf,
+    Ord,
+    PartialEq,
+    PartialOrd,
+    Serialize,
+    TryFromProtoBuf,
+)]
+#[proto(proto_buf_type = proto::SetupLayer)]
+#[serde(deny_unknown_fields)]
+pub struct SetupLayerSpec {
+    /// The program to run, followed by its arguments.
+    pub run: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent: Option<String>,
+    /// Paths in the project directory that the command uses.
+    #[serde(default)]
+    pub inputs: Vec<Utf8PathBuf>,
+}
+
+#[macro_export]
+macro_rules! setup_layer_spec {
+    (@expand [] -> [$($field_out:tt)*]) => {
+        $crate::spec::LayerSpec::Setup($crate::spec::SetupLayerSpec {
+            $($field_out)*
+            .. $crate::spec::SetupLayerSpec {
+                run: Default::default(),
+                image: Default::default(),
+                parent: Default::default(),
+                inputs: Default::default(),
+            }
+        })
+    };
+    (@expand [image: $image:expr $(,$($field_in:tt)*)?] -> [$($field_out:tt)*]) => {
+        $crate::setup_layer_spec!(@expand [$($($field_in)*)?] -> [$($field_out)* image: Some($image.into()),])
+    };
+    (@expand [parent: $parent:expr $(,$($field_in:tt)*)?] -> [$($field_out:tt)*]) => {
+        $crate::setup_layer_spec!(@expand [$($($field_in)*)?] -> [$($field_out)* parent: Some($parent.into()),])
+    };
+    (@expand [inputs: $inputs:expr $(,$($field_in:tt)*)?] -> [$($field_out:tt)*]) => {
+        $crate::setup_layer_spec!(@expand [$($($field_in)*)?] -> [$($field_out)* inputs: $inputs.into_iter().map(Into::into).collect(),])
+    };
+    ($run:expr $(, $($field_in:tt)*)?) => {
+        $crate::setup_layer_spec!(@expand [$($($field_in)*)?] -> [
+            run: $run.into_iter().map(Into::into).collect(),
+        ])
+    };
+}
+
 impl<'de> Deserialize<'de> for LayerSpec {
     fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
         let content = Content::deserialize(deserializer)?;
@@ -1163,6 +1300,7 @@ impl<'de> Deserialize<'de> for LayerSpec {
                 "stubs" => Some(LayerSpecType::Stubs),
                 "symlinks" => Some(LayerSpecType::Symlinks),
                 "shared_library_dependencies" => Some(LayerSpecType::SharedLibraryDependencies),
+                "run" => Some(LayerSpecType::Setup),
                 _ => None,
             })
         }) else {
@@ -1181,6 +1319,7 @@ impl<'de> Deserialize<'de> for LayerSpec {
                 SharedLibraryDependenciesLayerSpec::deserialize(deserializer)
                     .map(Self::SharedLibraryDependencies)
             }
+            LayerSpecType::Setup => SetupLayerSpec::deserialize(deserializer).map(Self::Setup),
         }
     }
 }
@@ -1214,6 +1353,11 @@ impl LayerSpec {
                     *path = vars.replace(path)?.into();
                 }
             }
+            Self::Setup(SetupLayerSpec { inputs, .. }) => {
+                for path in inputs {
+                    *path = vars.replace(path)?.into();
+                }
+            }
         }
         Ok(())
     }
@@ -1247,6 +1391,7 @@ pub enum ContainerUse {
     Network,
     User,
     Group,
+    Dns,
 }
 
 pub fn project_container_use_set_to_image_use_set(
@@ -1263,6 +1408,7 @@ pub fn project_container_use_set_to_image_use_set(
             ContainerUse::Network => None,
             ContainerUse::User => None,
             ContainerUse::Group => None,
+            ContainerUse::Dns => None,
         })
         .collect()
 }
@@ -1915,7 +2061,8 @@ mod tests {
                     ContainerUse::Mounts |
                     ContainerUse::Network |
                     ContainerUse::User |
-                    ContainerUse::Group
+                    ContainerUse::Group |
+                    ContainerUse::Dns
                 },
             },
         );
@@ -1934,7 +2081,8 @@ mod tests {
                     ContainerUse::Mounts |
                     ContainerUse::Network |
                     ContainerUse::User |
-                    ContainerUse::Group
+                    ContainerUse::Group |
+                    ContainerUse::Dns
                 },
             },
         );
@@ -1986,7 +2134,8 @@ mod tests {
                     ContainerUse::Mounts |
                     ContainerUse::Network |
                     ContainerUse::User |
-                    ContainerUse::Group
+                    ContainerUse::Group |
+                    ContainerUse::Dns
                 },
             },
         );
@@ -2005,7 +2154,8 @@ mod tests {
                     ContainerUse::Mounts |
                     ContainerUse::Network |
                     ContainerUse::User |
-                    ContainerUse::Group
+                    ContainerUse::Group |
+                    ContainerUse::Dns
                 },
             },
         );
@@ -2057,7 +2207,8 @@ mod tests {
                     ContainerUse::Mounts |
                     ContainerUse::Network |
                     ContainerUse::User |
-                    ContainerUse::Group
+                    ContainerUse::Group |
+                    ContainerUse::Dns
                 },
             },
         );
@@ -2076,7 +2227,8 @@ mod tests {
                     ContainerUse::Mounts |
                     ContainerUse::Network |
                     ContainerUse::User |
-                    ContainerUse::Group
+                    ContainerUse::Group |
+                    ContainerUse::Dns
                 },
             },
         );
@@ -2128,7 +2280,8 @@ mod tests {
                     ContainerUse::Mounts |
                     ContainerUse::Network |
                     ContainerUse::User |
-                    ContainerUse::Group
+                    ContainerUse::Group |
+                    ContainerUse::Dns
                 },
             },
         );
@@ -2147,7 +2300,8 @@ mod tests {
                     ContainerUse::Mounts |
                     ContainerUse::Network |
                     ContainerUse::User |
-                    ContainerUse::Group
+                    ContainerUse::Group |
+                    ContainerUse::Dns
                 },
             },
         );
@@ -2199,7 +2353,8 @@ mod tests {
                     ContainerUse::WorkingDirectory |
                     ContainerUse::Network |
                     ContainerUse::User |
-                    ContainerUse::Group
+                    ContainerUse::Group |
+                    ContainerUse::Dns
                 },
             },
         );
@@ -2218,7 +2373,8 @@ mod tests {
                     ContainerUse::WorkingDirectory |
                     ContainerUse::Network |
                     ContainerUse::User |
-                    ContainerUse::Group
+                    ContainerUse::Group |
+                    ContainerUse::Dns
                 },
             },
         );
@@ -2270,7 +2426,8 @@ mod tests {
                     ContainerUse::WorkingDirectory |
                     ContainerUse::Mounts |
                     ContainerUse::User |
-                    ContainerUse::Group
+                    ContainerUse::Group |
+                    ContainerUse::Dns
                 },
             },
         );
@@ -2289,7 +2446,8 @@ mod tests {
                     ContainerUse::WorkingDirectory |
                     ContainerUse::Mounts |
                     ContainerUse::User |
-                    ContainerUse::Group
+                    ContainerUse::Group |
+                    ContainerUse::Dns
                 },
             },
         );
@@ -2341,7 +2499,8 @@ mod tests {
                     ContainerUse::WorkingDirectory |
                     ContainerUse::Mounts |
                     ContainerUse::Network |
-                    ContainerUse::Group
+                    ContainerUse::Group |
+                    ContainerUse::Dns
                 },
             },
         );
@@ -2360,7 +2519,8 @@ mod tests {
                     ContainerUse::WorkingDirectory |
                     ContainerUse::Mounts |
                     ContainerUse::Network |
-                    ContainerUse::Group
+                    ContainerUse::Group |
+                    ContainerUse::Dns
                 },
             },
         );
@@ -2412,7 +2572,8 @@ mod tests {
                     ContainerUse::WorkingDirectory |
                     ContainerUse::Mounts |
                     ContainerUse::Network |
-                    ContainerUse::User
+                    ContainerUse::User |
+                    ContainerUse::Dns
                 },
             },
         );
@@ -2431,7 +2592,39 @@ mod tests {
                     ContainerUse::WorkingDirectory |
                     ContainerUse::Mounts |
                     ContainerUse::Network |
-                    ContainerUse::User
+                    ContainerUse::User |
+                    ContainerUse::Dns
+                },
+            },
+        );
+    }
+
+    #[test]
+    fn container_ref_macro_dns() {
+        assert_eq!(
+            container_ref!("foo", dns),
+            ContainerRef {
+                name: "foo".into(),
+                r#use: enum_set!(ContainerUse::Dns),
+            },
+        );
+    }
+
+    #[test]
+    fn container_ref_macro_all_minus_dns() {
+        assert_eq!(
+            container_ref!("foo", all, -dns),
+            ContainerRef {
+                name: "foo".into(),
+                r#use: enum_set! {
+                    ContainerUse::Layers |
+                    ContainerUse::EnableWritableFileSystem |
+                    ContainerUse::Environment |
+                    ContainerUse::WorkingDirectory |
+                    ContainerUse::Mounts |
+                    ContainerUse::Network |
+                    ContainerUse::User |
+                    ContainerUse::Group
                 },
             },
         );
@@ -3010,6 +3203,20 @@ mod tests {
                 );
             }
 
+            #[test]
+            fn preserve_ownership() {
+                assert_eq!(
+                    parse_toml::<LayerSpec>(indoc! {r#"
+                        paths = [ "/foo/bar", "/bar/foo" ]
+                        preserve_ownership = true
+                    "#}),
+                    paths_layer_spec! {
+                        ["/foo/bar", "/bar/foo"],
+                        preserve_ownership: true,
+                    },
+                );
+            }
+
             #[test]
             fn unknown_field() {
                 assert!(parse_error_toml::<LayerSpec>(indoc! {r#"
@@ -3172,6 +3379,56 @@ mod tests {
             }
         }
 
+        mod setup {
+            use super::*;
+
+            #[test]
+            fn run() {
+                assert_eq!(
+                    parse_toml::<LayerSpec>(indoc! {r#"
+                        run = ["/bin/sh", "-c", "apt-get install -y git"]
+                    "#}),
+                    setup_layer_spec!(["/bin/sh", "-c", "apt-get install -y git"]),
+                );
+            }
+
+            #[test]
+            fn image_and_inputs() {
+                assert_eq!(
+                    parse_json::<LayerSpec>(indoc! {r#"{
+                        "run": ["npm", "ci"],
+                        "image": "docker://node",
+                        "inputs": ["package.json", "package-lock.json"]
+                    }"#}),
+                    setup_layer_spec! {
+                        ["npm", "ci"],
+                        image: "docker://node",
+                        inputs: ["package.json", "package-lock.json"],
+                    },
+                );
+            }
+
+            #[test]
+            fn parent() {
+                assert_eq!(
+                    parse_toml::<LayerSpec>(indoc! {r#"
+                        run = ["pip", "install", "foo"]
+                        parent = "python"
+                    "#}),
+                    setup_layer_spec!(["pip", "install", "foo"], parent: "python"),
+                );
+            }
+
+            #[test]
+            fn unknown_field() {
+                assert!(parse_error_toml::<LayerSpec>(indoc! {r#"
+                    run = ["true"]
+                    foo_bar_baz = 3
+                "#})
+                .contains("unknown field `foo_bar_baz`"));
+            }
+        }
+
         mod replace_template_vars {
             use super::*;
 
@@ -3250,6 +3507,17 @@ mod tests {
                     ]),
                 );
             }
+
+            #[test]
+            fn setup() {
+                replace_template_vars_test(
+                    indoc! {r#"
+                        run = ["<foo>"]
+                        inputs = ["<foo>/requirements.txt"]
+                    "#},
+                    setup_layer_spec!(["<foo>"], inputs: ["foo-value/requirements.txt"]),
+                );
+            }
         }
     }
 
@@ -4740,6 +5008,111 @@ mod tests {
                 )
             }
         }
+
+        mod dns {
+            use super::*;
+            use maelstrom_base::JobHostsEntry;
+
+            #[test]
+            fn dns() {
+                assert_eq!(
+                    parse_container_spec_toml(indoc! {r#"
+                        [dns]
+                        nameservers = [ "10.0.0.1", "fd00::1" ]
+                        search = [ "corp.example.com" ]
+                        hosts = [ { address = "10.0.0.2", names = [ "db" ] } ]
+                    "#}),
+                    container_spec! {
+                        dns: JobDns {
+                            nameservers: vec!["10.0.0.1".into(), "fd00::1".into()],
+                            search: vec!["corp.example.com".into()],
+                            hosts: vec![JobHostsEntry {
+                                address: "10.0.0.2".into(),
+                                names: vec!["db".into()],
+                            }],
+                            ..Default::default()
+                        },
+                    },
+                )
+            }
+
+            #[test]
+            fn inherit_host() {
+                assert_eq!(
+                    parse_container_spec_json(indoc! {r#"{
+                        "dns": { "inherit_host": true }
+                    }"#}),
+                    container_spec! {
+                        dns: JobDns {
+                            inherit_host: true,
+                            ..Default::default()
+                        },
+                    },
+                )
+            }
+
+            #[test]
+            fn bad_nameserver() {
+                assert_eq!(
+                    parse_container_spec_error_toml(indoc! {r#"
+                        dns.nameservers = [ "dns.example.com" ]
+                    "#}),
+                    r#"name server "dns.example.com" isn't an IP address"#,
+                )
+            }
+
+            #[test]
+            fn bad_hosts_address() {
+                assert_eq!(
+                    parse_container_spec_error_toml(indoc! {r#"
+                        dns.hosts = [ { address = "db", names = [ "db" ] } ]
+                    "#}),
+                    r#"hosts address "db" isn't an IP address"#,
+                )
+            }
+
+            #[test]
+            fn hosts_entry_without_names() {
+                assert_eq!(
+                    parse_container_spec_error_toml(indoc! {r#"
+                        dns.hosts = [ { address = "10.0.0.2", names = [] } ]
+                    "#}),
+                    "hosts entry for 10.0.0.2 has no names",
+                )
+            }
+
+            #[test]
+            fn dns_and_parent_with_implicit_dns() {
+                assert_eq!(
+                    parse_container_spec_json(indoc! {r#"{
+                        "dns": { "search": [ "corp.example.com" ] },
+                        "parent": "parent"
+                    }"#}),
+                    container_spec! {
+                        dns: JobDns {
+                            search: vec!["corp.example.com".into()],
+                            ..Default::default()
+                        },
+                        parent: container_container_parent!("parent", all, -dns),
+                    },
+                )
+            }
+
+            #[test]
+            fn dns_and_parent_with_explicit_dns() {
+                assert_eq!(
+                    parse_container_spec_error_toml(indoc! {r#"
+                        dns.inherit_host = true
+                        parent.name = "parent"
+                        parent.use = [ "dns" ]
+                    "#}),
+                    concat!(
+                        "field `dns` cannot be set if `parent` with an explicit `use` of ",
+                        "`dns` is also specified",
+                    ),
+                )
+            }
+        }
     }
 
     mod job_spec {
@@ -4864,6 +5237,20 @@ mod tests {
             );
         }
 
+        #[test]
+        fn exclusive() {
+            assert_eq!(
+                parse_job_spec_toml(indoc! {r#"
+                    program = "/bin/sh"
+                    exclusive = true
+                "#}),
+                job_spec! {
+                    "/bin/sh",
+                    exclusive: true,
+                },
+            );
+        }
+
         #[test]
         fn container_fields() {
             assert_eq!(
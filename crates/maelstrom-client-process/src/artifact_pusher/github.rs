@@ -4,16 +4,41 @@ use anyhow::{anyhow, Result};
 use chrono::{DateTime, Utc};
 use maelstrom_base::{ArtifactUploadLocation, Sha256Digest};
 use maelstrom_github::{FileStreamBuilder, GitHubClient, SeekableStream};
-use maelstrom_util::async_fs::Fs;
+use maelstrom_util::{fs::Fs, io::write_zstd_size_frame};
+use std::io::{Seek as _, SeekFrom};
 use std::time::Duration;
-use std::{path::PathBuf, sync::Arc};
-use tokio::task::JoinSet;
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+use tokio::task::{self, JoinSet};
 use url::Url;
 
 fn two_hours_from_now() -> DateTime<Utc> {
     Utc::now() + Duration::from_secs(60 * 60 * 2)
 }
 
+/// Compress the artifact at `path` with zstd into an anonymous temporary file. Returns the
+/// temporary file, positioned at its start, and its size. We have to know the size of what we
+/// upload before we start, so we can't compress the artifact while we're uploading it.
+///
+/// The compressed artifact is preceded by a frame recording its uncompressed size, so that
+/// whoever downloads it can tell when decompressing it produces more than it should.
+async fn compress_artifact(path: &Path) -> Result<(tokio::fs::File, u64)> {
+    let path = path.to_owned();
+    task::spawn_blocking(move || {
+        let fs = Fs::new();
+        let mut input = fs.open_file(&path)?;
+        let mut output = tempfile::tempfile()?;
+        write_zstd_size_frame(&mut output, input.metadata()?.len())?;
+        zstd::stream::copy_encode(&mut input, &mut output, zstd::DEFAULT_COMPRESSION_LEVEL)?;
+        let size = output.stream_position()?;
+        output.seek(SeekFrom::Start(0))?;
+        Ok((tokio::fs::File::from_std(output), size))
+    })
+    .await?
+}
+
 pub async fn push_one_artifact(
     github_client: Arc<GitHubClient>,
     upload_tracker: ProgressTracker,
@@ -21,15 +46,15 @@ pub async fn push_one_artifact(
     digest: Sha256Digest,
     success_callback: SuccessCb,
 ) -> Result<()> {
-    let fs = Fs::new();
-    let file = fs.open_file(&path).await?;
-    let size = file.metadata().await?.len();
+    // Artifacts are always stored compressed in GitHub, since whoever downloads them has no way to
+    // tell us what they would prefer.
+    let (file, size) = compress_artifact(&path).await?;
 
     let upload_name = construct_upload_name(&digest, &path);
     let prog = upload_tracker.new_task(&upload_name, size);
 
-    let artifact_name = format!("maelstrom-cache-sha256-{digest}");
-    let file_stream = FileStreamBuilder::new(file.into_inner()).build().await?;
+    let artifact_name = format!("maelstrom-cache-sha256-{digest}.zst");
+    let file_stream = FileStreamBuilder::new(file).build().await?;
     let stream = Box::new(UploadProgressReader::new(prog, file_stream)) as Box<dyn SeekableStream>;
     github_client
         .upload(&artifact_name, Some(two_hours_from_now()), stream)
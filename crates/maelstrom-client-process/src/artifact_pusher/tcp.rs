@@ -1,19 +1,46 @@
 use crate::artifact_pusher::{construct_upload_name, start_task_inner, Receiver, SuccessCb};
 use crate::progress::{ProgressTracker, UploadProgressReader};
 use anyhow::{anyhow, Context as _, Result};
+use async_compression::tokio::bufread::ZstdEncoder;
 use maelstrom_base::{
-    proto::{ArtifactPusherToBroker, BrokerToArtifactPusher, Hello},
+    proto::{ArtifactCompression, ArtifactPusherToBroker, BrokerToArtifactPusher, Hello},
     ArtifactUploadLocation, Sha256Digest,
 };
 use maelstrom_util::{async_fs::Fs, config::common::BrokerAddr, net, net::AsRawFdExt as _};
 use slog::Logger;
 use std::path::{Path, PathBuf};
 use tokio::{
-    io::{self, AsyncReadExt as _},
+    io::{self, AsyncReadExt as _, BufReader},
     net::TcpStream,
-    task::JoinSet,
+    task::{self, JoinSet},
 };
 
+/// How much of the start of an artifact we compress to decide whether to compress all of it.
+const COMPRESSION_SAMPLE_SIZE: u64 = 1024 * 1024;
+
+/// Only send the artifact compressed if that's likely to save us something, judging by how well
+/// its start compresses. This lets us compress the artifact while we're sending it, without
+/// spending the time on artifacts that are already compressed.
+async fn choose_compression(fs: &Fs, path: &Path) -> Result<ArtifactCompression> {
+    let mut sample = vec![];
+    fs.open_file(path)
+        .await?
+        .into_inner()
+        .take(COMPRESSION_SAMPLE_SIZE)
+        .read_to_end(&mut sample)
+        .await?;
+    let compressed = task::spawn_blocking(move || {
+        zstd::bulk::compress(&sample, zstd::DEFAULT_COMPRESSION_LEVEL)
+            .map(|compressed| compressed.len() < sample.len())
+    })
+    .await??;
+    Ok(if compressed {
+        ArtifactCompression::Zstd
+    } else {
+        ArtifactCompression::None
+    })
+}
+
 pub async fn push_one_artifact(
     stream: Option<TcpStream>,
     upload_tracker: ProgressTracker,
@@ -57,18 +84,32 @@ async fn push_one_artifact_inner(
     };
 
     let fs = Fs::new();
-    let file = fs.open_file(&path).await?;
-    let size = file.metadata().await?.len();
+    let size = fs.metadata(path).await?.len();
+
+    // The broker computes the digest over the uncompressed contents either way.
+    let compression = choose_compression(&fs, path).await?;
 
     let upload_name = construct_upload_name(&digest, path);
     let prog = upload_tracker.new_task(&upload_name, size);
 
+    let file = fs.open_file(path).await?.into_inner();
     let mut file = UploadProgressReader::new(prog, file.chain(io::repeat(0)).take(size));
 
-    net::write_message_to_async_socket(&mut stream, ArtifactPusherToBroker(digest, size), &log)
-        .await?;
-    let copied = io::copy(&mut file, &mut stream).await?;
-    assert_eq!(copied, size);
+    net::write_message_to_async_socket(
+        &mut stream,
+        ArtifactPusherToBroker(digest, size, compression),
+        &log,
+    )
+    .await?;
+    match compression {
+        ArtifactCompression::None => {
+            let copied = io::copy(&mut file, &mut stream).await?;
+            assert_eq!(copied, size);
+        }
+        ArtifactCompression::Zstd => {
+            io::copy(&mut ZstdEncoder::new(BufReader::new(file)), &mut stream).await?;
+        }
+    }
 
     let BrokerToArtifactPusher(resp) =
         net::read_message_from_async_socket(&mut stream, &log).await?;
@@ -103,3 +144,39 @@ pub fn start_task(
         },
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    async fn compression_for(contents: &[u8]) -> ArtifactCompression {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("artifact");
+        let fs = Fs::new();
+        fs.write(&path, contents).await.unwrap();
+        choose_compression(&fs, &path).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn compressible_artifact_is_compressed() {
+        assert_eq!(
+            compression_for(&b"hello world".repeat(100)).await,
+            ArtifactCompression::Zstd
+        );
+    }
+
+    #[tokio::test]
+    async fn already_compressed_artifact_is_not_compressed() {
+        let compressed = zstd::encode_all(&b"hello world".repeat(100)[..], 0).unwrap();
+        assert_eq!(
+            compression_for(&compressed).await,
+            ArtifactCompression::None
+        );
+    }
+
+    #[tokio::test]
+    async fn empty_artifact_is_not_compressed() {
+        assert_eq!(compression_for(b"").await, ArtifactCompression::None);
+    }
+}
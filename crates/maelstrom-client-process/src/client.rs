@@ -1,3 +1,4 @@
+mod image_exporter;
 pub mod layer_builder;
 mod state_machine;
 
@@ -8,6 +9,7 @@ use anyhow::{anyhow, Context as _, Error, Result};
 use async_trait::async_trait;
 use layer_builder::LayerBuilder;
 use maelstrom_base::{
+    manifest::{ManifestEntryData, ManifestFileData},
     proto::{Hello, WorkerToBroker},
     Sha256Digest,
 };
@@ -20,6 +22,7 @@ use maelstrom_container::{self as container, ContainerImageDepot, ContainerImage
 use maelstrom_util::{
     async_fs,
     config::common::{ArtifactTransferStrategy, BrokerAddr, CacheSize, InlineLimit, Slots},
+    manifest::AsyncManifestReader,
     net::{self, AsRawFdExt as _},
     root::RootBuf,
     signal,
@@ -27,8 +30,15 @@ use maelstrom_util::{
 use maelstrom_worker::local_worker;
 use slog::{debug, warn, Logger};
 use state_machine::StateMachine;
-use std::{collections::HashSet, future::Future, path::Path, pin::Pin, sync::Arc};
+use std::{
+    collections::HashMap,
+    future::Future,
+    path::{Path, PathBuf},
+    pin::Pin,
+    sync::Arc,
+};
 use tokio::{
+    io::BufReader,
     net::TcpStream,
     sync::{mpsc, oneshot, Mutex},
     task::{self, JoinHandle, JoinSet},
@@ -46,11 +56,12 @@ struct ClientState {
     image_download_tracker: ProgressTracker,
     log: Logger,
     preparer_sender: preparer::task::Sender,
+    locked: Arc<Mutex<ClientStateLocked>>,
 }
 
 struct ClientStateLocked {
     digest_repo: DigestRepository,
-    processed_artifact_digests: HashSet<Sha256Digest>,
+    processed_artifacts: HashMap<Sha256Digest, PathBuf>,
 }
 
 #[derive(Default)]
@@ -97,13 +108,33 @@ impl Uploader {
                 .await?;
             digest
         };
-        if !locked.processed_artifact_digests.contains(&digest) {
-            locked.processed_artifact_digests.insert(digest.clone());
+        if !locked.processed_artifacts.contains_key(&digest) {
+            locked
+                .processed_artifacts
+                .insert(digest.clone(), path.clone());
             self.router_sender
                 .send(router::Message::AddArtifact(path, digest.clone()))?;
         }
         Ok(digest)
     }
+
+    /// Upload a manifest, and tell the router which artifacts its entries refer to, so that it
+    /// can ask the broker about them along with the manifest.
+    pub async fn upload_manifest(&self, path: &Path) -> Result<Sha256Digest> {
+        let digest = self.upload(path).await?;
+        let fs = async_fs::Fs::new();
+        let mut reader =
+            AsyncManifestReader::new(BufReader::new(fs.open_file(path).await?)).await?;
+        let mut entries = vec![];
+        while let Some(entry) = reader.next().await? {
+            if let ManifestEntryData::File(ManifestFileData::Digest(entry_digest)) = entry.data {
+                entries.push(entry_digest);
+            }
+        }
+        self.router_sender
+            .send(router::Message::AddManifestEntries(digest.clone(), entries))?;
+        Ok(digest)
+    }
 }
 
 #[async_trait]
@@ -322,12 +353,12 @@ impl Client {
             let layer_builder = LayerBuilder::new(cache_dir, project_dir, MANIFEST_INLINE_LIMIT);
             let locked = Arc::new(Mutex::new(ClientStateLocked {
                 digest_repo,
-                processed_artifact_digests: HashSet::default(),
+                processed_artifacts: HashMap::default(),
             }));
             let uploader = Uploader {
                 log: log.clone(),
                 router_sender: router_sender.clone(),
-                locked,
+                locked: locked.clone(),
             };
 
             let (preparer_sender, preparer_receiver) = preparer::task::channel();
@@ -341,6 +372,7 @@ impl Client {
                 image_download_tracker.clone(),
                 layer_builder,
                 uploader,
+                router_sender.clone(),
             );
 
             Ok((
@@ -350,6 +382,7 @@ impl Client {
                     image_download_tracker,
                     log,
                     preparer_sender,
+                    locked,
                 },
                 join_set,
                 local_worker_handle,
@@ -472,6 +505,25 @@ impl Client {
         Ok(())
     }
 
+    pub async fn export_image(&self, spec: spec::JobSpec, path: PathBuf) -> Result<()> {
+        let state = self.state_machine.active()?;
+        debug!(state.log, "export_image"; "spec" => ?spec, "path" => ?path);
+
+        let (sender, receiver) = oneshot::channel();
+        state
+            .preparer_sender
+            .send(preparer::Message::PrepareJob(sender, spec))?;
+        let spec = receiver.await?.map_err(Error::msg)?;
+
+        // Every artifact the job references went through the uploader, which remembers where it
+        // found each one.
+        let artifact_paths = state.locked.lock().await.processed_artifacts.clone();
+        let temp_dir = tempfile::tempdir()?;
+        image_exporter::export_image(&spec, &artifact_paths, temp_dir.path(), &path)
+            .await
+            .with_context(|| format!("exporting image to {path:?}"))
+    }
+
     pub async fn introspect(&self) -> Result<IntrospectResponse> {
         let state = self.state_machine.active()?;
         let artifact_uploads = state.artifact_upload_tracker.get_remote_progresses();
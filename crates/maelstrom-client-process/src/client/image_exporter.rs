@@ -0,0 +1,527 @@
+//! Export a prepared job's container as an OCI image.
+//!
+//! The resulting tarball contains both an OCI image layout (`oci-layout`, `index.json`, and
+//! `blobs/sha256/...`) and a Docker-style `manifest.json`, so it can be loaded with either `docker
+//! load` or `podman load`, or unpacked and used directly as an OCI layout.
+
+use anyhow::{anyhow, Result};
+use maelstrom_base::{
+    manifest::{ManifestEntry, ManifestEntryData, ManifestFileData},
+    ArtifactType, GroupId, JobSpec, Sha256Digest, UserId, Utf8Path,
+};
+use maelstrom_util::{async_fs, io::Sha256Stream, manifest::AsyncManifestReader};
+use oci_spec::image::{
+    ConfigBuilder, Descriptor, DescriptorBuilder, ImageConfigurationBuilder, ImageIndexBuilder,
+    ImageManifestBuilder, MediaType, OciLayoutBuilder, RootFsBuilder, ANNOTATION_REF_NAME,
+    SCHEMA_VERSION,
+};
+use serde::Serialize;
+use sha2::{Digest as _, Sha256};
+use std::{
+    collections::{HashMap, HashSet},
+    ffi::OsStr,
+    os::unix::ffi::OsStrExt as _,
+    path::{Path, PathBuf},
+};
+use tokio::io::{AsyncRead, AsyncWriteExt as _, BufReader};
+use tokio_tar::{Builder, EntryType, Header};
+
+/// The name the exported image is given when it is loaded.
+pub const EXPORTED_IMAGE_NAME: &str = "maelstrom-export:latest";
+
+/// An entry in Docker's `manifest.json`. This is what `docker save` produces and what `docker
+/// load` expects, in addition to the OCI layout.
+#[derive(Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct DockerManifestEntry {
+    config: String,
+    repo_tags: Vec<String>,
+    layers: Vec<String>,
+}
+
+/// A blob that will be written to `blobs/sha256/`.
+enum Blob {
+    File { path: PathBuf, size: u64 },
+    Data(Vec<u8>),
+}
+
+impl Blob {
+    fn size(&self) -> u64 {
+        match self {
+            Self::File { size, .. } => *size,
+            Self::Data(data) => data.len() as u64,
+        }
+    }
+}
+
+fn blob_path(digest: &Sha256Digest) -> String {
+    format!("blobs/sha256/{digest}")
+}
+
+fn data_blob(data: Vec<u8>) -> (Sha256Digest, Blob) {
+    let digest = Sha256Digest::new(Sha256::digest(&data).into());
+    (digest, Blob::Data(data))
+}
+
+fn descriptor(media_type: MediaType, digest: &Sha256Digest, blob: &Blob) -> Descriptor {
+    Descriptor::new(media_type, blob.size() as i64, format!("sha256:{digest}"))
+}
+
+fn new_header(entry_type: EntryType, size: u64, mode: u32, mtime: u64) -> Header {
+    let mut header = Header::new_gnu();
+    header.set_entry_type(entry_type);
+    header.set_size(size);
+    header.set_mode(mode);
+    header.set_mtime(mtime);
+    header.set_uid(0);
+    header.set_gid(0);
+    header
+}
+
+/// Paths in manifests are usually absolute, but paths in layer tarballs are relative to the root.
+fn layer_path(path: &Utf8Path) -> &Utf8Path {
+    path.strip_prefix("/").unwrap_or(path)
+}
+
+/// Convert one entry of a Maelstrom manifest into one or more tar entries. Directory opaqueness and
+/// whiteouts are translated into the OCI whiteout files. Entries without an owner are given
+/// `job_owner`, since that's who owns them in the job.
+async fn append_manifest_entry<WriteT: tokio::io::AsyncWrite + Unpin + Send>(
+    builder: &mut Builder<WriteT>,
+    entry: ManifestEntry,
+    artifact_paths: &HashMap<Sha256Digest, PathBuf>,
+    job_owner: (UserId, GroupId),
+) -> Result<()> {
+    let fs = async_fs::Fs::new();
+    let path = layer_path(&entry.path);
+    let mode = entry.metadata.mode.0 & 0o7777;
+    let mtime = entry.metadata.mtime.0.max(0) as u64;
+    let (uid, gid) = entry.metadata.owner.unwrap_or(job_owner);
+    let entry_header = |entry_type, size| {
+        let mut header = new_header(entry_type, size, mode, mtime);
+        header.set_uid(u32::from(uid).into());
+        header.set_gid(u32::from(gid).into());
+        header
+    };
+    match entry.data {
+        ManifestEntryData::Directory { opaque } => {
+            let mut header = entry_header(EntryType::Directory, 0);
+            builder
+                .append_data(&mut header, path, tokio::io::empty())
+                .await?;
+            if opaque {
+                let mut header = new_header(EntryType::Regular, 0, 0o644, mtime);
+                builder
+                    .append_data(&mut header, path.join(".wh..wh..opq"), tokio::io::empty())
+                    .await?;
+            }
+        }
+        ManifestEntryData::File(ManifestFileData::Empty) => {
+            let mut header = entry_header(EntryType::Regular, 0);
+            builder
+                .append_data(&mut header, path, tokio::io::empty())
+                .await?;
+        }
+        ManifestEntryData::File(ManifestFileData::Inline(data)) => {
+            let mut header = entry_header(EntryType::Regular, data.len() as u64);
+            builder
+                .append_data(&mut header, path, data.as_slice())
+                .await?;
+        }
+        ManifestEntryData::File(ManifestFileData::Digest(digest)) => {
+            let file_path = artifact_paths
+                .get(&digest)
+                .ok_or_else(|| anyhow!("no local file for {path:?} with digest {digest}"))?;
+            let file = fs.open_file(file_path).await?;
+            let size = file.metadata().await?.len();
+            let mut header = entry_header(EntryType::Regular, size);
+            builder.append_data(&mut header, path, file).await?;
+        }
+        ManifestEntryData::Symlink(target) => {
+            let mut header = entry_header(EntryType::Symlink, 0);
+            header.set_link_name(OsStr::from_bytes(&target))?;
+            builder
+                .append_data(&mut header, path, tokio::io::empty())
+                .await?;
+        }
+        ManifestEntryData::Hardlink(target) => {
+            let mut header = entry_header(EntryType::Link, 0);
+            header.set_link_name(layer_path(&target))?;
+            builder
+                .append_data(&mut header, path, tokio::io::empty())
+                .await?;
+        }
+        ManifestEntryData::Whiteout => {
+            let name = path
+                .file_name()
+                .ok_or_else(|| anyhow!("whiteout with no file name: {path:?}"))?;
+            let whiteout = path.with_file_name(format!(".wh.{name}"));
+            let mut header = new_header(EntryType::Regular, 0, 0o644, mtime);
+            builder
+                .append_data(&mut header, whiteout, tokio::io::empty())
+                .await?;
+        }
+    }
+    Ok(())
+}
+
+/// Convert a Maelstrom manifest into an uncompressed layer tarball written to `out`. Returns the
+/// digest of the tarball.
+async fn manifest_to_layer(
+    manifest: &Path,
+    artifact_paths: &HashMap<Sha256Digest, PathBuf>,
+    job_owner: (UserId, GroupId),
+    out: &Path,
+) -> Result<Sha256Digest> {
+    let fs = async_fs::Fs::new();
+    let mut reader =
+        AsyncManifestReader::new(BufReader::new(fs.open_file(manifest).await?)).await?;
+    let mut builder = Builder::new(Sha256Stream::new(fs.create_file(out).await?));
+    while let Some(entry) = reader.next().await? {
+        append_manifest_entry(&mut builder, entry, artifact_paths, job_owner).await?;
+    }
+    let (mut file, digest) = builder.into_inner().await?.finalize();
+    file.flush().await?;
+    Ok(digest)
+}
+
+async fn append_blob<WriteT: tokio::io::AsyncWrite + Unpin + Send>(
+    builder: &mut Builder<WriteT>,
+    digest: &Sha256Digest,
+    blob: &Blob,
+) -> Result<()> {
+    let mut header = new_header(EntryType::Regular, blob.size(), 0o444, 0);
+    let reader: Box<dyn AsyncRead + Unpin + Send> = match blob {
+        Blob::File { path, .. } => Box::new(async_fs::Fs::new().open_file(path).await?),
+        Blob::Data(data) => Box::new(data.as_slice()),
+    };
+    builder
+        .append_data(&mut header, blob_path(digest), reader)
+        .await?;
+    Ok(())
+}
+
+async fn append_data<WriteT: tokio::io::AsyncWrite + Unpin + Send>(
+    builder: &mut Builder<WriteT>,
+    path: &str,
+    data: &[u8],
+) -> Result<()> {
+    let mut header = new_header(EntryType::Regular, data.len() as u64, 0o444, 0);
+    builder.append_data(&mut header, path, data).await?;
+    Ok(())
+}
+
+/// Write an OCI image tarball to `path` containing the layers, environment, working directory, user,
+/// and program of the given job specification.
+///
+/// `artifact_paths` maps the digests of all artifacts referenced by the job specification, and by
+/// any manifests it contains, to local files. `temp_dir` is used to store layers converted from
+/// manifests while the image is being assembled.
+pub async fn export_image(
+    spec: &JobSpec,
+    artifact_paths: &HashMap<Sha256Digest, PathBuf>,
+    temp_dir: &Path,
+    path: &Path,
+) -> Result<()> {
+    let fs = async_fs::Fs::new();
+
+    let mut layers = vec![];
+    for (i, (digest, type_)) in spec.layers.iter().enumerate() {
+        let artifact_path = artifact_paths
+            .get(digest)
+            .ok_or_else(|| anyhow!("no local file for layer with digest {digest}"))?;
+        let (digest, path) = match type_ {
+            ArtifactType::Tar => (digest.clone(), artifact_path.clone()),
+            ArtifactType::Manifest => {
+                let out = temp_dir.join(format!("layer-{i}.tar"));
+                let job_owner = (spec.user, spec.group);
+                let digest =
+                    manifest_to_layer(artifact_path, artifact_paths, job_owner, &out).await?;
+                (digest, out)
+            }
+        };
+        let size = fs.metadata(&path).await?.len();
+        layers.push((digest, Blob::File { path, size }));
+    }
+
+    let exec_config = ConfigBuilder::default()
+        .user(format!("{}:{}", spec.user, spec.group))
+        .env(spec.environment.clone())
+        .working_dir(spec.working_directory.to_string())
+        .cmd(
+            [spec.program.to_string()]
+                .into_iter()
+                .chain(spec.arguments.iter().cloned())
+                .collect::<Vec<_>>(),
+        )
+        .build()?;
+    let image_config = ImageConfigurationBuilder::default()
+        .config(exec_config)
+        .rootfs(
+            RootFsBuilder::default()
+                .typ("layers")
+                .diff_ids(
+                    layers
+                        .iter()
+                        .map(|(digest, _)| format!("sha256:{digest}"))
+                        .collect::<Vec<_>>(),
+                )
+                .build()?,
+        )
+        .build()?;
+    let (config_digest, config_blob) = data_blob(serde_json::to_vec(&image_config)?);
+
+    let image_manifest = ImageManifestBuilder::default()
+        .schema_version(SCHEMA_VERSION)
+        .media_type(MediaType::ImageManifest)
+        .config(descriptor(
+            MediaType::ImageConfig,
+            &config_digest,
+            &config_blob,
+        ))
+        .layers(
+            layers
+                .iter()
+                .map(|(digest, blob)| descriptor(MediaType::ImageLayer, digest, blob))
+                .collect::<Vec<_>>(),
+        )
+        .build()?;
+    let (manifest_digest, manifest_blob) = data_blob(serde_json::to_vec(&image_manifest)?);
+
+    let image_index = ImageIndexBuilder::default()
+        .schema_version(SCHEMA_VERSION)
+        .media_type(MediaType::ImageIndex)
+        .manifests(vec![DescriptorBuilder::default()
+            .media_type(MediaType::ImageManifest)
+            .size(manifest_blob.size() as i64)
+            .digest(format!("sha256:{manifest_digest}"))
+            .annotations(HashMap::from([(
+                ANNOTATION_REF_NAME.to_owned(),
+                EXPORTED_IMAGE_NAME.to_owned(),
+            )]))
+            .build()?])
+        .build()?;
+    let oci_layout = OciLayoutBuilder::default()
+        .image_layout_version("1.0.0")
+        .build()?;
+    let docker_manifest = vec![DockerManifestEntry {
+        config: blob_path(&config_digest),
+        repo_tags: vec![EXPORTED_IMAGE_NAME.into()],
+        layers: layers.iter().map(|(digest, _)| blob_path(digest)).collect(),
+    }];
+
+    let mut builder = Builder::new(fs.create_file(path).await?);
+    append_data(
+        &mut builder,
+        "oci-layout",
+        &serde_json::to_vec(&oci_layout)?,
+    )
+    .await?;
+    append_data(
+        &mut builder,
+        "index.json",
+        &serde_json::to_vec(&image_index)?,
+    )
+    .await?;
+    append_data(
+        &mut builder,
+        "manifest.json",
+        &serde_json::to_vec(&docker_manifest)?,
+    )
+    .await?;
+    for dir in ["blobs", "blobs/sha256"] {
+        let mut header = new_header(EntryType::Directory, 0, 0o755, 0);
+        builder
+            .append_data(&mut header, dir, tokio::io::empty())
+            .await?;
+    }
+    let mut written = HashSet::new();
+    for (digest, blob) in layers.iter().chain(
+        [
+            (config_digest, config_blob),
+            (manifest_digest, manifest_blob),
+        ]
+        .iter(),
+    ) {
+        if written.insert(digest.clone()) {
+            append_blob(&mut builder, digest, blob).await?;
+        }
+    }
+    builder.into_inner().await?.flush().await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt as _;
+    use maelstrom_base::{
+        job_spec,
+        manifest::{ManifestEntryMetadata, Mode, UnixTimestamp},
+    };
+    use maelstrom_util::manifest::AsyncManifestWriter;
+    use tokio_tar::Archive;
+
+    fn entry(path: &str, mode: u32, data: ManifestEntryData) -> ManifestEntry {
+        ManifestEntry {
+            path: path.into(),
+            metadata: ManifestEntryMetadata {
+                size: 0,
+                mode: Mode(mode),
+                mtime: UnixTimestamp(1705000271),
+                owner: Some((UserId::new(1000), GroupId::new(1000))),
+                xattrs: Default::default(),
+            },
+            data,
+        }
+    }
+
+    async fn read_tar(path: &Path) -> HashMap<String, (EntryType, Vec<u8>, Option<String>)> {
+        let mut archive = Archive::new(async_fs::Fs::new().open_file(path).await.unwrap());
+        let mut entries = archive.entries().unwrap();
+        let mut result = HashMap::new();
+        while let Some(entry) = entries.next().await {
+            let mut entry = entry.unwrap();
+            let mut data = vec![];
+            tokio::io::AsyncReadExt::read_to_end(&mut entry, &mut data)
+                .await
+                .unwrap();
+            let link = entry
+                .link_name()
+                .unwrap()
+                .map(|l| l.to_str().unwrap().to_owned());
+            let path = entry.path().unwrap().to_str().unwrap().to_owned();
+            result.insert(path, (entry.header().entry_type(), data, link));
+        }
+        result
+    }
+
+    #[tokio::test]
+    async fn export_tar_and_manifest_layers() {
+        let fs = async_fs::Fs::new();
+        let temp_dir = tempfile::tempdir().unwrap();
+        let temp = temp_dir.path();
+
+        // A tar layer, built with the same code path we use for everything else.
+        let tar_layer = temp.join("layer.tar");
+        {
+            let mut builder = Builder::new(fs.create_file(&tar_layer).await.unwrap());
+            append_data(&mut builder, "etc/hostname", b"maelstrom\n")
+                .await
+                .unwrap();
+            builder.into_inner().await.unwrap().flush().await.unwrap();
+        }
+        let tar_digest = crate::calculate_digest(&tar_layer).await.unwrap().1;
+
+        // A manifest layer that references an external file.
+        let data_file = temp.join("data");
+        fs.write(&data_file, b"hello world").await.unwrap();
+        let data_digest = crate::calculate_digest(&data_file).await.unwrap().1;
+        let manifest_layer = temp.join("layer.manifest");
+        let mut manifest = vec![];
+        {
+            let mut writer = AsyncManifestWriter::new(&mut manifest).await.unwrap();
+            for e in [
+                entry(
+                    "/bin",
+                    0o40755,
+                    ManifestEntryData::Directory { opaque: true },
+                ),
+                entry(
+                    "/bin/hello",
+                    0o100755,
+                    ManifestEntryData::File(ManifestFileData::Digest(data_digest.clone())),
+                ),
+                entry(
+                    "/bin/inline",
+                    0o100644,
+                    ManifestEntryData::File(ManifestFileData::Inline(b"inline".to_vec())),
+                ),
+                entry(
+                    "/bin/link",
+                    0o120777,
+                    ManifestEntryData::Symlink(b"hello".to_vec()),
+                ),
+                entry("/etc/hostname", 0o100644, ManifestEntryData::Whiteout),
+            ] {
+                writer.write_entry(&e).await.unwrap();
+            }
+        }
+        fs.write(&manifest_layer, &manifest).await.unwrap();
+        let manifest_digest = crate::calculate_digest(&manifest_layer).await.unwrap().1;
+
+        let artifact_paths = HashMap::from([
+            (tar_digest.clone(), tar_layer),
+            (manifest_digest.clone(), manifest_layer),
+            (data_digest, data_file),
+        ]);
+        let spec = job_spec!(
+            "/bin/hello",
+            [
+                (tar_digest.clone(), ArtifactType::Tar),
+                (manifest_digest, ArtifactType::Manifest)
+            ],
+            arguments: ["arg"],
+            environment: ["FOO=bar"],
+            working_directory: "/bin",
+            user: 1000,
+            group: 1001,
+        );
+        let image = temp.join("image.tar");
+        let work_dir = tempfile::tempdir().unwrap();
+        export_image(&spec, &artifact_paths, work_dir.path(), &image)
+            .await
+            .unwrap();
+
+        let image_contents = read_tar(&image).await;
+        let docker_manifest: serde_json::Value =
+            serde_json::from_slice(&image_contents["manifest.json"].1).unwrap();
+        let layer_paths: Vec<&str> = docker_manifest[0]["Layers"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|l| l.as_str().unwrap())
+            .collect();
+        assert_eq!(layer_paths.len(), 2);
+        assert_eq!(layer_paths[0], blob_path(&tar_digest));
+        assert_eq!(
+            docker_manifest[0]["RepoTags"][0].as_str().unwrap(),
+            EXPORTED_IMAGE_NAME
+        );
+
+        let config: serde_json::Value = serde_json::from_slice(
+            &image_contents[docker_manifest[0]["Config"].as_str().unwrap()].1,
+        )
+        .unwrap();
+        assert_eq!(config["config"]["User"], "1000:1001");
+        assert_eq!(config["config"]["Env"][0], "FOO=bar");
+        assert_eq!(config["config"]["WorkingDir"], "/bin");
+        assert_eq!(config["config"]["Cmd"][0], "/bin/hello");
+        assert_eq!(config["config"]["Cmd"][1], "arg");
+        assert_eq!(
+            config["rootfs"]["diff_ids"][1],
+            format!("sha256:{}", &layer_paths[1]["blobs/sha256/".len()..])
+        );
+
+        let index: serde_json::Value =
+            serde_json::from_slice(&image_contents["index.json"].1).unwrap();
+        assert_eq!(
+            index["manifests"][0]["annotations"][ANNOTATION_REF_NAME],
+            EXPORTED_IMAGE_NAME
+        );
+
+        // Unpack the converted manifest layer and check its contents.
+        let converted = temp.join("converted.tar");
+        fs.write(&converted, &image_contents[layer_paths[1]].1)
+            .await
+            .unwrap();
+        let layer_contents = read_tar(&converted).await;
+        assert_eq!(layer_contents["bin"].0, EntryType::Directory);
+        assert!(layer_contents.contains_key("bin/.wh..wh..opq"));
+        assert_eq!(layer_contents["bin/hello"].1, b"hello world");
+        assert_eq!(layer_contents["bin/inline"].1, b"inline");
+        assert_eq!(layer_contents["bin/link"].0, EntryType::Symlink);
+        assert_eq!(layer_contents["bin/link"].2.as_deref(), Some("hello"));
+        assert!(layer_contents.contains_key("etc/.wh.hostname"));
+    }
+}
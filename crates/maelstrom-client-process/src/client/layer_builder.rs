@@ -1,6 +1,7 @@
+mod setup;
 mod shared_libraries;
 
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, bail, Result};
 use futures::StreamExt as _;
 use itertools::Itertools as _;
 use maelstrom_base::{
@@ -12,7 +13,7 @@ use maelstrom_base::{
 };
 use maelstrom_client_base::{
     spec::{
-        GlobLayerSpec, LayerSpec, PathsLayerSpec, PrefixOptions,
+        GlobLayerSpec, LayerSpec, PathsLayerSpec, PrefixOptions, SetupLayerSpec,
         SharedLibraryDependenciesLayerSpec, StubsLayerSpec, SymlinkSpec, SymlinksLayerSpec,
         TarLayerSpec,
     },
@@ -23,6 +24,8 @@ use maelstrom_util::{
     manifest::{AsyncManifestWriter, DataUpload, ManifestBuilder},
     root::RootBuf,
 };
+pub use setup::SetupJobRunner;
+use setup::SetupLayerDirs;
 use sha2::{Digest as _, Sha256};
 use shared_libraries::get_shared_library_dependencies;
 use std::{
@@ -150,10 +153,10 @@ impl LayerBuilder {
         let fs = async_fs::Fs::new();
         let tmp_file_path = self.temp_manifest_path().await?;
         let mut manifest_file = fs.create_file(&tmp_file_path).await?;
-        let follow_symlinks = prefix_options.follow_symlinks;
         let mut builder = ManifestBuilder::new(
             &mut manifest_file,
-            follow_symlinks,
+            prefix_options.follow_symlinks,
+            prefix_options.preserve_ownership,
             data_upload,
             self.inline_limit,
         )
@@ -205,6 +208,8 @@ impl LayerBuilder {
                 size: 0,
                 mode: Mode(0o444 | if is_dir { 0o111 } else { 0 }),
                 mtime: ARBITRARY_TIME,
+                owner: None,
+                xattrs: Default::default(),
             };
             let entry = ManifestEntry {
                 path: stub,
@@ -234,6 +239,8 @@ impl LayerBuilder {
                 size: 0,
                 mode: Mode(0o444),
                 mtime: ARBITRARY_TIME,
+                owner: None,
+                xattrs: Default::default(),
             };
             let entry = ManifestEntry {
                 path: link,
@@ -316,7 +323,77 @@ impl LayerBuilder {
                     .await?;
                 (manifest_path, ArtifactType::Manifest)
             }
+            LayerSpec::Setup(_) => {
+                bail!("setup layers must be built with `build_setup_layer`");
+            }
+        })
+    }
+
+    /// Build a setup layer, running its command with `runner` unless we already have the result
+    /// cached.
+    pub async fn build_setup_layer(
+        &self,
+        spec: SetupLayerSpec,
+        runner: &impl SetupJobRunner,
+        data_upload: impl DataUpload,
+    ) -> Result<(PathBuf, ArtifactType)> {
+        // The key depends on what the job's image or parent container resolve to, so prepare the
+        // job without its inputs to find out. The layers built here are needed by the real job too.
+        let resolved = runner
+            .prepare_setup_job(setup::setup_job_spec(&spec, &[], None)?)
+            .await?;
+
+        let project_dir = self.project_dir.clone();
+        let cache_dir = self.cache_dir.clone();
+        let spec_clone = spec.clone();
+        let (inputs, dirs) = tokio::task::spawn_blocking(move || -> Result<_> {
+            let inputs = setup::setup_layer_inputs(&project_dir, &spec_clone)?;
+            let key = setup::setup_layer_key(&project_dir, &spec_clone, &resolved, &inputs)?;
+            Ok((inputs, SetupLayerDirs::new(&cache_dir, &key)))
+        })
+        .await??;
+
+        // Hold the lock until we've read the layer's contents, so that nobody else is building it
+        // while we do.
+        let _lock = dirs.lock().await?;
+
+        let dirs = if dirs.is_built() {
+            dirs
+        } else {
+            let dirs = tokio::task::spawn_blocking(move || -> Result<_> {
+                dirs.prepare()?;
+                Ok(dirs)
+            })
+            .await??;
+            let job_spec =
+                setup::setup_job_spec(&spec, &inputs, Some(dirs.capture_file_system_changes()?))?;
+            let job_spec = runner.prepare_setup_job(job_spec).await?;
+            let outcome = runner.run_setup_job(job_spec).await?;
+            setup::check_setup_job_outcome(&spec, outcome)?;
+            tokio::task::spawn_blocking(move || -> Result<_> {
+                dirs.finish()?;
+                Ok(dirs)
+            })
+            .await??
+        };
+
+        let (dirs, paths) = tokio::task::spawn_blocking(move || -> Result<_> {
+            let paths = setup::upper_layer_paths(&dirs.upper)?;
+            Ok((dirs, paths))
         })
+        .await??;
+
+        let manifest_path = self
+            .build_manifest(
+                futures::stream::iter(paths.into_iter().map(Ok)),
+                PrefixOptions {
+                    strip_prefix: Some(dirs.upper.try_into()?),
+                    ..Default::default()
+                },
+                data_upload,
+            )
+            .await?;
+        Ok((manifest_path, ArtifactType::Manifest))
     }
 }
 
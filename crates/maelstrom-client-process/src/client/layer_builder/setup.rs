@@ -0,0 +1,482 @@
+//! Support for setup layers: layers made from the file system changes of a command run in a
+//! container.
+//!
+//! Each setup layer is cached in its own directory under [`SETUP_LAYERS_DIR`], named after a digest
+//! of the spec, the resolved job it runs, and the contents of its inputs. The command's changes are
+//! captured into `upper`, which is only put in place once the command has succeeded, so the
+//! presence of `upper` means the cached layer is complete. Builds of the same layer, whether in this
+//! process or another, are serialized by a lock file in the directory.
+
+use anyhow::{anyhow, bail, Result};
+use async_trait::async_trait;
+use maelstrom_base::{
+    enum_set, proc_mount, tmp_mount, CaptureFileSystemChanges, EnumSet, JobDevice, JobDns,
+    JobMount, JobNetwork, JobOutcome, JobOutcomeResult, JobSpec as BaseJobSpec,
+    JobTerminationStatus, Sha256Digest, Utf8PathBuf,
+};
+use maelstrom_client_base::{
+    paths_layer_spec,
+    spec::{ContainerParent, ContainerRef, ImageRef, ImageUse, JobSpec, SetupLayerSpec},
+    stubs_layer_spec, SETUP_LAYERS_DIR,
+};
+use maelstrom_util::{async_fs, fs::Fs};
+use sha2::{Digest as _, Sha256};
+use std::{
+    io,
+    os::unix::fs::PermissionsExt as _,
+    path::{Path, PathBuf},
+};
+
+/// Something that can prepare and run the jobs for setup layers. The jobs must be run locally,
+/// since they capture their file system changes into a local directory.
+#[async_trait]
+pub trait SetupJobRunner {
+    /// Resolve the job's image and parent container, and build its layers.
+    async fn prepare_setup_job(&self, spec: JobSpec) -> Result<BaseJobSpec>;
+    async fn run_setup_job(&self, spec: BaseJobSpec) -> Result<JobOutcomeResult>;
+}
+
+/// Find the files a setup layer's command needs. Inputs are relative to the project directory, and
+/// directories are included recursively. The returned paths are relative to the project directory
+/// and sorted.
+pub fn setup_layer_inputs(project_dir: &Path, spec: &SetupLayerSpec) -> Result<Vec<Utf8PathBuf>> {
+    let fs = Fs::new();
+    let mut inputs = vec![];
+    for input in &spec.inputs {
+        for path in fs.walk(project_dir.join(input)) {
+            let path = path?;
+            let relative = path.strip_prefix(project_dir).unwrap_or(&path);
+            inputs.push(Utf8PathBuf::try_from(relative.to_owned())?);
+        }
+    }
+    inputs.sort();
+    inputs.dedup();
+    Ok(inputs)
+}
+
+/// Compute the digest that identifies a setup layer's cache directory, given the inputs found by
+/// [`setup_layer_inputs`]. The `resolved` job is the prepared version of the job's spec without any
+/// inputs, as made by [`setup_job_spec`]. It has the digests of the layers from the image or parent
+/// container, so changing the parent container changes the key, not just changing its name.
+pub fn setup_layer_key(
+    project_dir: &Path,
+    spec: &SetupLayerSpec,
+    resolved: &BaseJobSpec,
+    inputs: &[Utf8PathBuf],
+) -> Result<Sha256Digest> {
+    let fs = Fs::new();
+    let mut hasher = Sha256::new();
+    hasher.update(serde_json::to_vec(spec)?);
+    hasher.update(serde_json::to_vec(resolved)?);
+    for input in inputs {
+        hasher.update(input.as_str());
+        hasher.update([0]);
+        let path = project_dir.join(input);
+        if fs.symlink_metadata(&path)?.is_file() {
+            io::copy(&mut fs.open_file(&path)?, &mut hasher)?;
+        }
+    }
+    Ok(Sha256Digest::new(hasher.finalize().into()))
+}
+
+/// The directories used while building a setup layer.
+pub struct SetupLayerDirs {
+    pub upper: PathBuf,
+    upper_tmp: PathBuf,
+    work: PathBuf,
+    lock: PathBuf,
+}
+
+impl SetupLayerDirs {
+    pub fn new(cache_dir: &Path, key: &Sha256Digest) -> Self {
+        let dir = cache_dir.join(SETUP_LAYERS_DIR).join(key.to_string());
+        Self {
+            upper: dir.join("upper"),
+            upper_tmp: dir.join("upper.tmp"),
+            work: dir.join("work"),
+            lock: dir.join("lock"),
+        }
+    }
+
+    /// Wait for exclusive access to the layer's directories. The access lasts until the returned
+    /// file is dropped. Whoever gets the lock after a successful build will find the layer built.
+    pub async fn lock(&self) -> Result<async_fs::File> {
+        let fs = async_fs::Fs::new();
+        fs.create_dir_all(self.lock.parent().unwrap()).await?;
+        let lock = fs.create_file(&self.lock).await?;
+        lock.lock_exclusive().await?;
+        Ok(lock)
+    }
+
+    pub fn is_built(&self) -> bool {
+        Fs::new().exists(&self.upper)
+    }
+
+    /// Remove anything left over from a previous attempt and create the directories the job needs.
+    pub fn prepare(&self) -> Result<()> {
+        let fs = Fs::new();
+        remove_work_dir(&fs, &self.work)?;
+        if fs.exists(&self.upper_tmp) {
+            fs.remove_dir_all(&self.upper_tmp)?;
+        }
+        fs.create_dir_all(&self.upper_tmp)?;
+        fs.create_dir_all(&self.work)?;
+        Ok(())
+    }
+
+    pub fn capture_file_system_changes(&self) -> Result<CaptureFileSystemChanges> {
+        Ok(CaptureFileSystemChanges {
+            upper: self.upper_tmp.clone().try_into()?,
+            work: self.work.clone().try_into()?,
+        })
+    }
+
+    /// Clean up after a successful job and put the captured changes in place.
+    pub fn finish(&self) -> Result<()> {
+        let fs = Fs::new();
+
+        // Overlayfs leaves character devices behind as whiteouts. We can't put those in a
+        // manifest, so remove them along with anything else that isn't a file, directory, or
+        // symlink.
+        for path in fs.walk(&self.upper_tmp) {
+            let path = path?;
+            let meta = fs.symlink_metadata(&path)?;
+            if !(meta.is_file() || meta.is_dir() || meta.is_symlink()) {
+                fs.remove_file(path)?;
+            }
+        }
+
+        remove_work_dir(&fs, &self.work)?;
+        fs.rename(&self.upper_tmp, &self.upper)?;
+        Ok(())
+    }
+}
+
+/// Overlayfs creates a `work/work` directory with no permissions, which has to be fixed before the
+/// work directory can be removed.
+fn remove_work_dir(fs: &Fs, work: &Path) -> Result<()> {
+    if !fs.exists(work) {
+        return Ok(());
+    }
+    let inner_work = work.join("work");
+    if fs.exists(&inner_work) {
+        let mut perm = fs.metadata(&inner_work)?.permissions();
+        perm.set_mode(0o777);
+        fs.set_permissions(&inner_work, perm)?;
+    }
+    fs.remove_dir_all(work)
+}
+
+/// Build the spec for the job that runs a setup layer's command. Without
+/// `capture_file_system_changes`, the job is only good for computing the layer's key.
+pub fn setup_job_spec(
+    spec: &SetupLayerSpec,
+    inputs: &[Utf8PathBuf],
+    capture_file_system_changes: Option<CaptureFileSystemChanges>,
+) -> Result<JobSpec> {
+    let Some((program, arguments)) = spec.run.split_first() else {
+        bail!("setup layer must have a command to run");
+    };
+    let parent = match (&spec.image, &spec.parent) {
+        (Some(_), Some(_)) => bail!("both `image` and `parent` cannot be specified"),
+        (Some(image), None) => Some(ContainerParent::Image(ImageRef {
+            name: image.clone(),
+            r#use: ImageUse::Layers | ImageUse::Environment,
+        })),
+        (None, Some(parent)) => Some(ContainerParent::Container(ContainerRef {
+            name: parent.clone(),
+            r#use: EnumSet::all(),
+        })),
+        (None, None) => None,
+    };
+
+    let mut layers = vec![];
+    if !inputs.is_empty() {
+        layers.push(paths_layer_spec!(inputs.iter().cloned()));
+    }
+    layers.push(stubs_layer_spec!([
+        "/dev/{null,random,urandom,zero}",
+        "/etc/resolv.conf",
+        "/proc/",
+        "/tmp/",
+    ]));
+
+    let mut job_spec = JobSpec {
+        program: program.into(),
+        arguments: arguments.to_vec(),
+        timeout: None,
+        estimated_duration: None,
+        allocate_tty: None,
+        priority: 0,
+        capture_file_system_changes,
+        exclusive: false,
+        container: Default::default(),
+    };
+    job_spec.container.parent = parent;
+    job_spec.container.layers = layers;
+    job_spec.container.working_directory = Some("/".into());
+    job_spec.container.network = Some(JobNetwork::Local);
+    // Images usually don't come with a usable `resolv.conf`. The job runs on this machine, so it
+    // can use the same name servers we do.
+    job_spec.container.dns = Some(JobDns {
+        inherit_host: true,
+        ..Default::default()
+    });
+    job_spec.container.mounts = vec![
+        proc_mount!("/proc"),
+        tmp_mount!("/tmp"),
+        JobMount::Devices {
+            devices: enum_set![
+                JobDevice::Null | JobDevice::Random | JobDevice::Urandom | JobDevice::Zero
+            ],
+        },
+    ];
+    Ok(job_spec)
+}
+
+/// Turn the outcome of a setup job into an error if it didn't succeed.
+pub fn check_setup_job_outcome(spec: &SetupLayerSpec, outcome: JobOutcomeResult) -> Result<()> {
+    let command = spec.run.join(" ");
+    let outcome =
+        outcome.map_err(|err| anyhow!("error running setup command `{command}`: {err:?}"))?;
+    match outcome {
+        JobOutcome::Completed(completed) => {
+            if completed.status != JobTerminationStatus::Exited(0) {
+                bail!(
+                    "setup command `{command}` failed with {:?}\nstderr: {}\nstdout: {}",
+                    completed.status,
+                    completed.effects.stderr,
+                    completed.effects.stdout
+                )
+            }
+        }
+        JobOutcome::TimedOut(_) => bail!("setup command `{command}` timed out"),
+    }
+    Ok(())
+}
+
+/// The paths of everything in the captured changes, not including `upper` itself.
+pub fn upper_layer_paths(upper: &Path) -> Result<Vec<PathBuf>> {
+    let fs = Fs::new();
+    let mut paths = vec![];
+    for path in fs.walk(upper) {
+        let path = path?;
+        if path != upper {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+    Ok(paths)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use maelstrom_base::{job_spec, tar_digest};
+    use maelstrom_client_base::{setup_layer_spec, spec::LayerSpec};
+    use tempfile::tempdir;
+
+    fn setup(layer: LayerSpec) -> SetupLayerSpec {
+        let LayerSpec::Setup(spec) = layer else {
+            panic!("not a setup layer");
+        };
+        spec
+    }
+
+    fn key_with_resolved(
+        project_dir: &Path,
+        spec: &SetupLayerSpec,
+        resolved: &BaseJobSpec,
+    ) -> Sha256Digest {
+        let inputs = setup_layer_inputs(project_dir, spec).unwrap();
+        setup_layer_key(project_dir, spec, resolved, &inputs).unwrap()
+    }
+
+    fn key(project_dir: &Path, spec: &SetupLayerSpec) -> Sha256Digest {
+        key_with_resolved(project_dir, spec, &job_spec!("true", [tar_digest!(1)]))
+    }
+
+    #[test]
+    fn key_changes_with_input_contents() {
+        let project_dir = tempdir().unwrap();
+        let fs = Fs::new();
+        fs.write(project_dir.path().join("requirements.txt"), b"foo")
+            .unwrap();
+        let spec = setup(setup_layer_spec!(
+            ["pip", "install", "-r", "requirements.txt"],
+            image: "docker://python",
+            inputs: ["requirements.txt"],
+        ));
+        let key1 = key(project_dir.path(), &spec);
+        assert_eq!(key1, key(project_dir.path(), &spec));
+
+        fs.write(project_dir.path().join("requirements.txt"), b"bar")
+            .unwrap();
+        let key2 = key(project_dir.path(), &spec);
+        assert_ne!(key1, key2);
+    }
+
+    #[test]
+    fn key_changes_with_spec() {
+        let project_dir = tempdir().unwrap();
+        let key1 = key(project_dir.path(), &setup(setup_layer_spec!(["true"])));
+        let key2 = key(
+            project_dir.path(),
+            &setup(setup_layer_spec!(["true"], image: "docker://alpine")),
+        );
+        assert_ne!(key1, key2);
+    }
+
+    #[test]
+    fn key_changes_with_resolved_parent() {
+        let project_dir = tempdir().unwrap();
+        let spec = setup(setup_layer_spec!(["true"], parent: "base"));
+        let key1 = key_with_resolved(
+            project_dir.path(),
+            &spec,
+            &job_spec!("true", [tar_digest!(1)]),
+        );
+        let key2 = key_with_resolved(
+            project_dir.path(),
+            &spec,
+            &job_spec!("true", [tar_digest!(2), tar_digest!(1)]),
+        );
+        assert_ne!(key1, key2);
+    }
+
+    #[test]
+    fn key_includes_directory_inputs() {
+        let project_dir = tempdir().unwrap();
+        let fs = Fs::new();
+        fs.create_dir_all(project_dir.path().join("deps")).unwrap();
+        fs.write(project_dir.path().join("deps/a"), b"a").unwrap();
+        let spec = setup(setup_layer_spec!(["true"], inputs: ["deps"]));
+        let key1 = key(project_dir.path(), &spec);
+
+        fs.write(project_dir.path().join("deps/b"), b"b").unwrap();
+        let key2 = key(project_dir.path(), &spec);
+        assert_ne!(key1, key2);
+    }
+
+    #[test]
+    fn inputs_include_directory_contents() {
+        let project_dir = tempdir().unwrap();
+        let fs = Fs::new();
+        fs.create_dir_all(project_dir.path().join("deps/sub"))
+            .unwrap();
+        fs.write(project_dir.path().join("deps/sub/a"), b"a")
+            .unwrap();
+        fs.write(project_dir.path().join("requirements.txt"), b"")
+            .unwrap();
+        let spec = setup(setup_layer_spec!(
+            ["true"],
+            inputs: ["requirements.txt", "deps"],
+        ));
+        assert_eq!(
+            setup_layer_inputs(project_dir.path(), &spec).unwrap(),
+            vec![
+                Utf8PathBuf::from("deps"),
+                Utf8PathBuf::from("deps/sub"),
+                Utf8PathBuf::from("deps/sub/a"),
+                Utf8PathBuf::from("requirements.txt"),
+            ]
+        );
+    }
+
+    #[test]
+    fn job_spec_image_and_parent() {
+        let spec = setup(setup_layer_spec!(["true"], image: "foo", parent: "bar"));
+        let err = setup_job_spec(&spec, &[], None).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "both `image` and `parent` cannot be specified"
+        );
+    }
+
+    #[test]
+    fn job_spec_empty_command() {
+        let spec = setup(setup_layer_spec!(Vec::<String>::new()));
+        setup_job_spec(&spec, &[], None).unwrap_err();
+    }
+
+    #[test]
+    fn job_spec_runs_command_in_image() {
+        let spec = setup(setup_layer_spec!(
+            ["npm", "ci"],
+            image: "docker://node",
+            inputs: ["package.json"],
+        ));
+        let inputs = vec![Utf8PathBuf::from("package.json")];
+        let job_spec = setup_job_spec(&spec, &inputs, Some(Default::default())).unwrap();
+        assert_eq!(job_spec.program, "npm");
+        assert_eq!(job_spec.arguments, vec!["ci".to_owned()]);
+        assert_eq!(
+            job_spec.container.parent,
+            Some(ContainerParent::Image(ImageRef {
+                name: "docker://node".into(),
+                r#use: ImageUse::Layers | ImageUse::Environment,
+            }))
+        );
+        assert_eq!(
+            job_spec.container.layers[0],
+            paths_layer_spec!(["package.json"])
+        );
+        assert_eq!(job_spec.container.network, Some(JobNetwork::Local));
+        assert_eq!(
+            job_spec.container.dns,
+            Some(JobDns {
+                inherit_host: true,
+                ..Default::default()
+            })
+        );
+        assert!(job_spec.capture_file_system_changes.is_some());
+    }
+
+    #[test]
+    fn job_spec_without_capture() {
+        let spec = setup(setup_layer_spec!(["true"], parent: "base"));
+        let job_spec = setup_job_spec(&spec, &[], None).unwrap();
+        assert!(job_spec.capture_file_system_changes.is_none());
+        assert_eq!(
+            job_spec.container.parent,
+            Some(ContainerParent::Container(ContainerRef {
+                name: "base".into(),
+                r#use: EnumSet::all(),
+            }))
+        );
+    }
+
+    #[tokio::test]
+    async fn lock_is_exclusive() {
+        let cache_dir = tempdir().unwrap();
+        let dirs = SetupLayerDirs::new(cache_dir.path(), &Sha256Digest::from(1u64));
+        let lock = dirs.lock().await.unwrap();
+        let file = async_fs::Fs::new().open_file(&dirs.lock).await.unwrap();
+        file.try_lock_exclusive().await.unwrap_err();
+        drop(lock);
+        file.try_lock_exclusive().await.unwrap();
+    }
+
+    #[test]
+    fn finish_moves_upper_into_place() {
+        let cache_dir = tempdir().unwrap();
+        let dirs = SetupLayerDirs::new(cache_dir.path(), &Sha256Digest::from(1u64));
+        assert!(!dirs.is_built());
+        dirs.prepare().unwrap();
+        let fs = Fs::new();
+        fs.create_dir_all(dirs.upper_tmp.join("usr/lib")).unwrap();
+        fs.write(dirs.upper_tmp.join("usr/lib/foo"), b"foo")
+            .unwrap();
+        dirs.finish().unwrap();
+        assert!(dirs.is_built());
+        assert!(!fs.exists(&dirs.work));
+        assert_eq!(
+            upper_layer_paths(&dirs.upper).unwrap(),
+            vec![
+                dirs.upper.join("usr"),
+                dirs.upper.join("usr/lib"),
+                dirs.upper.join("usr/lib/foo"),
+            ]
+        );
+    }
+}
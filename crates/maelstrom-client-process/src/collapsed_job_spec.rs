@@ -1,7 +1,8 @@
 use indexmap::IndexSet;
 use maelstrom_base::{
-    ArtifactType, CaptureFileSystemChanges, EnumSet, GroupId, JobMount, JobNetwork, JobRootOverlay,
-    JobSpec as BaseJobSpec, JobTty, NonEmpty, Sha256Digest, Timeout, UserId, Utf8PathBuf,
+    ArtifactType, CaptureFileSystemChanges, EnumSet, GroupId, JobDns, JobMount, JobNetwork,
+    JobRootOverlay, JobSpec as BaseJobSpec, JobTty, NonEmpty, Sha256Digest, Timeout, UserId,
+    Utf8PathBuf,
 };
 use maelstrom_client_base::spec::{
     self, ContainerParent, ContainerSpec, ContainerUse, ConvertedImage, EnvironmentSpec, ImageRef,
@@ -35,6 +36,7 @@ pub struct CollapsedJobSpec {
     network: Option<JobNetwork>,
     user: Option<UserId>,
     group: Option<GroupId>,
+    dns: Option<JobDns>,
     image: Option<ImageRef>,
     initial_environment: BTreeMap<String, String>,
     image_layers: Vec<LayerSpec>,
@@ -45,6 +47,7 @@ pub struct CollapsedJobSpec {
     allocate_tty: Option<JobTty>,
     priority: i8,
     capture_file_system_changes: Option<CaptureFileSystemChanges>,
+    exclusive: bool,
 }
 
 /// Easily create a [`CollapsedJobSpec`].
@@ -83,6 +86,7 @@ macro_rules! collapsed_job_spec {
                 network: Default::default(),
                 user: Default::default(),
                 group: Default::default(),
+                dns: Default::default(),
                 image: Default::default(),
                 initial_environment: Default::default(),
                 image_layers: Default::default(),
@@ -93,6 +97,7 @@ macro_rules! collapsed_job_spec {
                 allocate_tty: Default::default(),
                 priority: Default::default(),
                 capture_file_system_changes: Default::default(),
+                exclusive: Default::default(),
             }
         }
     };
@@ -140,6 +145,10 @@ macro_rules! collapsed_job_spec {
         collapsed_job_spec!(@expand [$program] [$($($field_in)*)?] ->
             [$($($field_out)+,)? group: Some($group.into())])
     };
+    (@expand [$program:expr] [dns: $dns:expr $(,$($field_in:tt)*)?] -> [$($($field_out:tt)+)?]) => {
+        collapsed_job_spec!(@expand [$program] [$($($field_in)*)?] ->
+            [$($($field_out)+,)? dns: Some($dns)])
+    };
     (@expand [$program:expr] [arguments: $arguments:expr $(,$($field_in:tt)*)?] -> [$($($field_out:tt)+)?]) => {
         collapsed_job_spec!(@expand [$program] [$($($field_in)*)?] ->
             [$($($field_out)+,)? arguments: $arguments.into_iter().map(Into::into).collect()])
@@ -164,6 +173,10 @@ macro_rules! collapsed_job_spec {
         collapsed_job_spec!(@expand [$program] [$($($field_in)*)?] ->
             [$($($field_out)+,)? capture_file_system_changes: Some($capture_file_system_changes.into())])
     };
+    (@expand [$program:expr] [exclusive: $exclusive:expr $(,$($field_in:tt)*)?] -> [$($($field_out:tt)+)?]) => {
+        collapsed_job_spec!(@expand [$program] [$($($field_in)*)?] ->
+            [$($($field_out)+,)? exclusive: $exclusive])
+    };
     ($program:expr $(,$($field_in:tt)*)?) => {
         collapsed_job_spec!(@expand [$program] [$($($field_in)*)?] -> [])
     };
@@ -197,6 +210,7 @@ impl CollapsedJobSpec {
                     mut network,
                     mut user,
                     mut group,
+                    mut dns,
                 },
             program,
             arguments,
@@ -205,6 +219,7 @@ impl CollapsedJobSpec {
             allocate_tty,
             priority,
             capture_file_system_changes,
+            exclusive,
         } = job_spec;
         let mut image = None;
         let mut ancestors = IndexSet::<String>::default();
@@ -217,6 +232,7 @@ impl CollapsedJobSpec {
                 ContainerUse::Network => network.is_none(),
                 ContainerUse::User => user.is_none(),
                 ContainerUse::Group => group.is_none(),
+                ContainerUse::Dns => dns.is_none(),
             })
             .collect();
 
@@ -303,6 +319,10 @@ impl CollapsedJobSpec {
                                 group = parent.group;
                                 group.is_none()
                             }
+                            ContainerUse::Dns => {
+                                dns = parent.dns.clone();
+                                dns.is_none()
+                            }
                         })
                         .collect();
                     next_parent = parent.parent.clone();
@@ -319,6 +339,7 @@ impl CollapsedJobSpec {
             network,
             user,
             group,
+            dns,
             image,
             initial_environment: Default::default(),
             image_layers: Default::default(),
@@ -329,6 +350,7 @@ impl CollapsedJobSpec {
             allocate_tty,
             priority,
             capture_file_system_changes,
+            exclusive,
         })
     }
 
@@ -416,6 +438,7 @@ impl CollapsedJobSpec {
             network,
             user,
             group,
+            dns,
             image: _,
             initial_environment: _,
             image_layers: _,
@@ -426,6 +449,7 @@ impl CollapsedJobSpec {
             allocate_tty,
             priority,
             capture_file_system_changes,
+            exclusive,
         } = self;
         let root_overlay = capture_file_system_changes
             .map_or_else(
@@ -448,6 +472,7 @@ impl CollapsedJobSpec {
             layers,
             mounts,
             network: network.unwrap_or_default(),
+            dns: dns.unwrap_or_default(),
             root_overlay,
             working_directory: working_directory.unwrap_or_else(|| "/".into()),
             user: user.unwrap_or(0.into()),
@@ -456,6 +481,7 @@ impl CollapsedJobSpec {
             estimated_duration,
             allocate_tty,
             priority,
+            exclusive,
         })
     }
 }
@@ -1371,6 +1397,78 @@ mod tests {
         );
     }
 
+    #[test]
+    fn dns() {
+        let search = |domain: &str| JobDns {
+            search: vec![domain.into()],
+            ..Default::default()
+        };
+        let containers = HashMap::from([
+            (
+                "p1",
+                container_spec! {
+                    network: JobNetwork::Local,
+                    dns: search("p1.example.com"),
+                },
+            ),
+            (
+                "p2",
+                container_spec! {
+                    parent: container_container_parent!("p1", all),
+                    network: JobNetwork::Loopback,
+                },
+            ),
+            (
+                "p3",
+                container_spec! {
+                    parent: container_container_parent!("p1", network),
+                },
+            ),
+        ]);
+        assert_eq!(
+            CollapsedJobSpec::new(
+                job_spec! {
+                    "prog",
+                    parent: container_container_parent!("p2", all),
+                },
+                &|c| containers.get(c)
+            ),
+            Ok(collapsed_job_spec! {
+                "prog",
+                network: JobNetwork::Loopback,
+                dns: search("p1.example.com"),
+            }),
+        );
+        assert_eq!(
+            CollapsedJobSpec::new(
+                job_spec! {
+                    "prog",
+                    parent: container_container_parent!("p2", all),
+                    dns: search("prog.example.com"),
+                },
+                &|c| containers.get(c)
+            ),
+            Ok(collapsed_job_spec! {
+                "prog",
+                network: JobNetwork::Loopback,
+                dns: search("prog.example.com"),
+            }),
+        );
+        assert_eq!(
+            CollapsedJobSpec::new(
+                job_spec! {
+                    "prog",
+                    parent: container_container_parent!("p3", all),
+                },
+                &|c| containers.get(c)
+            ),
+            Ok(collapsed_job_spec! {
+                "prog",
+                network: JobNetwork::Local,
+            }),
+        );
+    }
+
     #[test]
     fn user() {
         let containers = HashMap::from([
@@ -1724,6 +1822,23 @@ mod tests {
         );
     }
 
+    #[test]
+    fn exclusive() {
+        assert_eq!(
+            CollapsedJobSpec::new(
+                job_spec! {
+                    "prog",
+                    exclusive: true,
+                },
+                &|_| None,
+            ),
+            Ok(collapsed_job_spec! {
+                "prog",
+                exclusive: true,
+            }),
+        );
+    }
+
     #[test]
     fn image_parent() {
         assert_eq!(
@@ -76,8 +76,16 @@ impl LayerBuilds {
         }
     }
 
+    /// Setup layers aren't counted against the limit. Building one requires building the layers of
+    /// its job, so if enough of them were waiting, nothing else could be built.
+    fn is_limited(layer: &LayerSpec) -> bool {
+        !matches!(layer, LayerSpec::Setup(_))
+    }
+
     fn push<DepsT: Deps>(&mut self, deps: &DepsT, layer: LayerSpec) {
-        if self.pending < self.max_pending.into() {
+        if !Self::is_limited(&layer) {
+            deps.build_layer(layer);
+        } else if self.pending < self.max_pending.into() {
             self.pending += 1;
             deps.build_layer(layer);
         } else {
@@ -85,7 +93,10 @@ impl LayerBuilds {
         }
     }
 
-    fn pop<DepsT: Deps>(&mut self, deps: &DepsT) {
+    fn pop<DepsT: Deps>(&mut self, deps: &DepsT, layer: &LayerSpec) {
+        if !Self::is_limited(layer) {
+            return;
+        }
         if let Some(layer) = self.waiting.pop_front() {
             deps.build_layer(layer);
         } else {
@@ -301,7 +312,7 @@ impl<DepsT: Deps> Preparer<DepsT> {
                 panic!(r#"received `got_layer` for layer {spec:?} which we already have"#);
             }
             LayerEntry::Getting(waiting) => {
-                self.layer_builds.pop(&self.deps);
+                self.layer_builds.pop(&self.deps, &spec);
                 match result {
                     Ok((digest, artifact_type)) => {
                         for (image, ijid, idx) in waiting {
@@ -405,7 +416,7 @@ mod tests {
     use maelstrom_client::spec;
     use maelstrom_client_base::{
         container_spec, converted_image, environment_spec, image_container_parent,
-        job_spec as client_job_spec, tar_layer_spec,
+        job_spec as client_job_spec, setup_layer_spec, tar_layer_spec,
     };
     use maelstrom_test::{millis, string};
     use std::{cell::RefCell, ffi::OsStr, rc::Rc, time::Duration};
@@ -916,6 +927,7 @@ mod tests {
             estimated_duration: millis!(100),
             allocate_tty: JobTty::new(b"123456", WindowSize::new(50, 100)),
             priority: 42,
+            exclusive: true,
             capture_file_system_changes: CaptureFileSystemChanges {
                 upper: "upper".into(),
                 work: "work".into(),
@@ -951,6 +963,7 @@ mod tests {
                 estimated_duration: millis!(100),
                 allocate_tty: JobTty::new(b"123456", WindowSize::new(50, 100)),
                 priority: 42,
+                exclusive: true,
             })),
         };
     }
@@ -1216,6 +1229,40 @@ mod tests {
         };
     }
 
+    script_test! {
+        prepare_job_setup_layers_not_limited,
+        Fixture::new(NonZeroUsize::new(1).unwrap()),
+
+        PrepareJob(1, client_job_spec! {
+            "one",
+            layers: [
+                setup_layer_spec!(["apt-get", "install", "foo"]),
+                setup_layer_spec!(["apt-get", "install", "bar"]),
+                tar_layer_spec!("foo.tar"),
+                tar_layer_spec!("bar.tar"),
+            ],
+        }) => {
+            BuildLayer(setup_layer_spec!(["apt-get", "install", "foo"])),
+            BuildLayer(setup_layer_spec!(["apt-get", "install", "bar"])),
+            BuildLayer(tar_layer_spec!("foo.tar")),
+        };
+
+        GotLayer(setup_layer_spec!(["apt-get", "install", "foo"]), Ok(tar_digest!(1))) => {};
+
+        GotLayer(tar_layer_spec!("foo.tar"), Ok(tar_digest!(3))) => {
+            BuildLayer(tar_layer_spec!("bar.tar")),
+        };
+
+        GotLayer(tar_layer_spec!("bar.tar"), Ok(tar_digest!(4))) => {};
+
+        GotLayer(setup_layer_spec!(["apt-get", "install", "bar"]), Ok(tar_digest!(2))) => {
+            JobPrepared(1, Ok(job_spec! {
+                "one",
+                [tar_digest!(1), tar_digest!(2), tar_digest!(3), tar_digest!(4)],
+            })),
+        };
+    }
+
     script_test! {
         add_container_duplicate,
         AddContainer(0, string!("foo"), container_spec!{ network: JobNetwork::Loopback }) => {
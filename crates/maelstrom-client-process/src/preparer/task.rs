@@ -1,12 +1,19 @@
 use super::{Deps, Message, Preparer};
 use crate::{
-    client::{layer_builder::LayerBuilder, Uploader},
+    client::{
+        layer_builder::{LayerBuilder, SetupJobRunner},
+        Uploader,
+    },
     progress::{LazyProgress, ProgressTracker},
+    router,
 };
-use anyhow::Result;
-use maelstrom_base::JobSpec;
-use maelstrom_client_base::spec::{
-    self, ContainerSpec, ConvertedImage, EnvironmentSpec, ImageConfig, LayerSpec,
+use anyhow::{bail, Error, Result};
+use async_trait::async_trait;
+use futures::StreamExt as _;
+use maelstrom_base::{ArtifactType, JobOutcomeResult, JobSpec};
+use maelstrom_client_base::{
+    spec::{self, ContainerSpec, ConvertedImage, EnvironmentSpec, ImageConfig, LayerSpec},
+    JobStatus,
 };
 use maelstrom_container::ContainerImageDepot;
 use maelstrom_util::sync;
@@ -36,10 +43,15 @@ pub fn start(
     image_download_tracker: ProgressTracker,
     layer_builder: LayerBuilder,
     uploader: Uploader,
+    router_sender: router::Sender,
 ) {
     let adapter = Adapter {
         container_image_depot: Arc::new(container_image_depot),
         image_download_tracker,
+        setup_job_runner: Runner {
+            preparer_sender: sender.clone(),
+            router_sender,
+        },
         sender,
         layer_builder: Arc::new(layer_builder),
         uploader,
@@ -56,6 +68,37 @@ pub struct Adapter {
     pub sender: Sender,
     pub uploader: Uploader,
     pub layer_builder: Arc<LayerBuilder>,
+    pub setup_job_runner: Runner,
+}
+
+/// Runs the jobs for setup layers the same way [`crate::client::Client::run_job`] runs jobs: by
+/// having the preparer prepare them, then handing them to the router.
+#[derive(Clone)]
+pub struct Runner {
+    preparer_sender: Sender,
+    router_sender: router::Sender,
+}
+
+#[async_trait]
+impl SetupJobRunner for Runner {
+    async fn prepare_setup_job(&self, spec: spec::JobSpec) -> Result<JobSpec> {
+        let (sender, receiver) = oneshot::channel();
+        self.preparer_sender
+            .send(Message::PrepareJob(sender, spec))?;
+        receiver.await?.map_err(Error::msg)
+    }
+
+    async fn run_setup_job(&self, spec: JobSpec) -> Result<JobOutcomeResult> {
+        let (sender, mut receiver) = futures::channel::mpsc::unbounded();
+        self.router_sender
+            .send(router::Message::RunJob(spec, sender))?;
+        while let Some(status) = receiver.next().await {
+            if let JobStatus::Completed { result, .. } = status {
+                return Ok(result);
+            }
+        }
+        bail!("setup job was canceled")
+    }
 }
 
 impl Deps for Adapter {
@@ -113,13 +156,23 @@ impl Deps for Adapter {
     fn build_layer(&self, spec: LayerSpec) {
         let uploader = self.uploader.clone();
         let layer_builder = self.layer_builder.clone();
+        let setup_job_runner = self.setup_job_runner.clone();
         let sender_clone = self.sender.clone();
         task::spawn(async move {
             let spec_clone = spec.clone();
             let build_fn = async {
-                let (artifact_path, artifact_type) =
-                    layer_builder.build_layer(spec_clone, &uploader).await?;
-                let artifact_digest = uploader.upload(&artifact_path).await?;
+                let (artifact_path, artifact_type) = match spec_clone {
+                    LayerSpec::Setup(setup) => {
+                        layer_builder
+                            .build_setup_layer(setup, &setup_job_runner, &uploader)
+                            .await?
+                    }
+                    spec_clone => layer_builder.build_layer(spec_clone, &uploader).await?,
+                };
+                let artifact_digest = match artifact_type {
+                    ArtifactType::Manifest => uploader.upload_manifest(&artifact_path).await?,
+                    _ => uploader.upload(&artifact_path).await?,
+                };
                 Result::<_>::Ok((artifact_digest, artifact_type))
             };
             let _ = sender_clone.send(Message::GotLayer(
@@ -7,7 +7,10 @@ use maelstrom_base::{
 use maelstrom_client_base::{JobRunningStatus, JobStatus};
 use maelstrom_util::ext::OptionExt as _;
 use maelstrom_worker::local_worker;
-use std::{collections::HashMap, path::PathBuf};
+use std::{
+    collections::{HashMap, HashSet},
+    path::PathBuf,
+};
 use tokio::{
     sync::mpsc::{self, UnboundedReceiver, UnboundedSender},
     task::JoinSet,
@@ -19,6 +22,7 @@ pub trait Deps {
 
     // Only in remote-broker mode.
     fn send_job_request_to_broker(&self, cjid: ClientJobId, spec: JobSpec);
+    fn send_find_missing_artifacts_to_broker(&self, digests: Vec<Sha256Digest>);
     fn start_artifact_transfer_to_broker(&self, digest: Sha256Digest, path: PathBuf);
 
     // For local jobs, which can happen in standalone mode or remote-broker mode.
@@ -31,9 +35,13 @@ pub trait Deps {
     fn shutdown_local_worker(&self, error: Error);
 }
 
+#[allow(clippy::large_enum_variant)]
 pub enum Message<DepsT: Deps> {
     // These are requests from the client.
     AddArtifact(PathBuf, Sha256Digest),
+    /// The artifacts referred to by the entries of a manifest. These are added with
+    /// [`Message::AddArtifact`] separately.
+    AddManifestEntries(Sha256Digest, Vec<Sha256Digest>),
     RunJob(JobSpec, DepsT::JobHandle),
     Shutdown(Error),
 
@@ -49,6 +57,14 @@ struct Router<DepsT: Deps> {
     deps: DepsT,
     standalone: bool,
     artifacts: HashMap<Sha256Digest, PathBuf>,
+    /// The artifacts referred to by each manifest's entries.
+    manifest_entries: HashMap<Sha256Digest, Vec<Sha256Digest>>,
+    /// Artifacts we've already asked the broker about with
+    /// [`ClientToBroker::FindMissingArtifacts`].
+    negotiated_artifacts: HashSet<Sha256Digest>,
+    /// Job requests that will be sent to the broker on the next call to
+    /// [`Router::send_pending_job_requests`].
+    pending_job_requests: Vec<(ClientJobId, JobSpec)>,
     next_client_job_id: u32,
     jobs: HashMap<ClientJobId, JobEntry<DepsT::JobHandle>>,
     completed_jobs: u64,
@@ -74,6 +90,9 @@ impl<DepsT: Deps> Router<DepsT> {
             deps,
             standalone,
             artifacts: Default::default(),
+            manifest_entries: Default::default(),
+            negotiated_artifacts: Default::default(),
+            pending_job_requests: Default::default(),
             next_client_job_id: Default::default(),
             jobs: Default::default(),
             completed_jobs: Default::default(),
@@ -101,6 +120,9 @@ impl<DepsT: Deps> Router<DepsT> {
             Message::AddArtifact(path, digest) => {
                 self.artifacts.insert(digest, path);
             }
+            Message::AddManifestEntries(digest, entries) => {
+                self.manifest_entries.insert(digest, entries);
+            }
             Message::RunJob(spec, handle) => {
                 let cjid = self.next_client_job_id.into();
                 self.next_client_job_id = self.next_client_job_id.checked_add(1).unwrap();
@@ -118,7 +140,7 @@ impl<DepsT: Deps> Router<DepsT> {
                         spec,
                     );
                 } else {
-                    self.deps.send_job_request_to_broker(cjid, spec);
+                    self.pending_job_requests.push((cjid, spec));
                 }
             }
             Message::Broker(BrokerToClient::JobResponse(cjid, result)) => {
@@ -139,6 +161,16 @@ impl<DepsT: Deps> Router<DepsT> {
                 self.deps
                     .start_artifact_transfer_to_broker(digest, path.to_owned());
             }
+            Message::Broker(BrokerToClient::MissingArtifacts(digests)) => {
+                assert!(!self.standalone);
+                for digest in digests {
+                    let path = self.artifacts.get(&digest).ok_or_else(|| {
+                        anyhow!("got request for unknown artifact with digest {digest}")
+                    })?;
+                    self.deps
+                        .start_artifact_transfer_to_broker(digest, path.to_owned());
+                }
+            }
             Message::Broker(BrokerToClient::GeneralError(err)) => {
                 bail!("received error from broker: {err}");
             }
@@ -151,6 +183,9 @@ impl<DepsT: Deps> Router<DepsT> {
                 job.status = Some(status.clone());
                 self.deps.job_update(&job.handle, status.into());
             }
+            Message::LocalWorker(WorkerToBroker::DiskSpaceLow(_)) => {
+                // The local worker doesn't watch its free disk space.
+            }
             Message::LocalWorkerStartArtifactFetch(digest) => {
                 let result = match self.artifacts.get(&digest) {
                     None => Err(anyhow!("no artifact found for digest {digest}")),
@@ -163,6 +198,34 @@ impl<DepsT: Deps> Router<DepsT> {
         };
         Ok(())
     }
+
+    /// Send the job requests queued up since the last call to the broker. Before doing so, ask the
+    /// broker in one message which of the artifacts they use it doesn't have yet, so that it can
+    /// tell us to start uploading all of them at once instead of asking for them one job at a time.
+    /// This includes the artifacts referred to by the entries of the jobs' manifests, which the
+    /// broker would otherwise only ask for after it has read the manifests.
+    fn send_pending_job_requests(&mut self) {
+        if self.pending_job_requests.is_empty() {
+            return;
+        }
+        let mut digests = vec![];
+        for (_, spec) in &self.pending_job_requests {
+            for (digest, _) in &spec.layers {
+                let entries = self.manifest_entries.get(digest).into_iter().flatten();
+                for digest in std::iter::once(digest).chain(entries) {
+                    if self.negotiated_artifacts.insert(digest.clone()) {
+                        digests.push(digest.clone());
+                    }
+                }
+            }
+        }
+        if !digests.is_empty() {
+            self.deps.send_find_missing_artifacts_to_broker(digests);
+        }
+        for (cjid, spec) in self.pending_job_requests.drain(..) {
+            self.deps.send_job_request_to_broker(cjid, spec);
+        }
+    }
 }
 
 pub struct Adapter {
@@ -198,6 +261,12 @@ impl Deps for Adapter {
             .send(ClientToBroker::JobRequest(cjid, spec));
     }
 
+    fn send_find_missing_artifacts_to_broker(&self, digests: Vec<Sha256Digest>) {
+        let _ = self
+            .broker_sender
+            .send(ClientToBroker::FindMissingArtifacts(digests));
+    }
+
     fn start_artifact_transfer_to_broker(&self, digest: Sha256Digest, path: PathBuf) {
         let broker_sender = self.broker_sender.clone();
         let cb_digest = digest.clone();
@@ -256,7 +325,13 @@ pub fn start_task(
     let mut router = Router::new(adapter, standalone);
     join_set.spawn(async move {
         while let Some(msg) = receiver.recv().await {
-            router.receive_message(msg)?
+            router.receive_message(msg)?;
+            // Handle everything that's already queued up before talking to the broker, so that a
+            // burst of jobs results in a single artifact negotiation.
+            while let Ok(msg) = receiver.try_recv() {
+                router.receive_message(msg)?;
+            }
+            router.send_pending_job_requests();
         }
         Ok(())
     });
@@ -275,6 +350,7 @@ mod tests {
     enum TestMessage {
         JobUpdate(ClientJobId, JobStatus),
         JobRequestToBroker(ClientJobId, JobSpec),
+        FindMissingArtifactsToBroker(Vec<Sha256Digest>),
         StartArtifactTransferToBroker(Sha256Digest, PathBuf),
         EnqueueJobToLocalWorker(JobId, JobSpec),
         ArtifactFetchCompletedToLocalWorker(Sha256Digest, result::Result<PathBuf, String>),
@@ -300,6 +376,12 @@ mod tests {
                 .push(TestMessage::JobRequestToBroker(cjid, spec));
         }
 
+        fn send_find_missing_artifacts_to_broker(&self, digests: Vec<Sha256Digest>) {
+            self.borrow_mut()
+                .messages
+                .push(TestMessage::FindMissingArtifactsToBroker(digests));
+        }
+
         fn start_artifact_transfer_to_broker(&self, digest: Sha256Digest, path: PathBuf) {
             self.borrow_mut()
                 .messages
@@ -360,6 +442,23 @@ mod tests {
                 .receive_message(message)
                 .err()
                 .map(|e| e.to_string());
+            self.router.send_pending_job_requests();
+        }
+
+        fn receive_messages_then_send_pending_job_requests(
+            &mut self,
+            messages: Vec<Message<Rc<RefCell<TestState>>>>,
+        ) {
+            for message in messages {
+                self.router.receive_message(message).unwrap();
+            }
+            self.router.send_pending_job_requests();
+        }
+
+        fn expect_messages_in_order(&mut self, expected: Vec<TestMessage>) {
+            let messages = &mut self.test_state.borrow_mut().messages;
+            assert_eq!(expected, *messages);
+            messages.clear();
         }
 
         fn expect_messages_in_any_order(&mut self, mut expected: Vec<TestMessage>) {
@@ -522,9 +621,11 @@ mod tests {
         run_job_clustered,
         Fixture::new(false, None),
         RunJob(spec!(0), cjid!(0)) => {
+            FindMissingArtifactsToBroker(vec![digest!(0)]),
             JobRequestToBroker(cjid!(0), spec!(0)),
         };
         RunJob(spec!(1), cjid!(1)) => {
+            FindMissingArtifactsToBroker(vec![digest!(1)]),
             JobRequestToBroker(cjid!(1), spec!(1)),
         };
     }
@@ -532,6 +633,7 @@ mod tests {
         run_job_must_be_local_clustered,
         Fixture::new(false, None),
         RunJob(spec!(0), cjid!(0)) => {
+            FindMissingArtifactsToBroker(vec![digest!(0)]),
             JobRequestToBroker(cjid!(0), spec!(0)),
         };
         RunJob(spec!(1, network: JobNetwork::Local), cjid!(1)) => {
@@ -620,6 +722,7 @@ mod tests {
         job_response_from_broker_known_clustered,
         Fixture::new(false, None),
         RunJob(spec!(0), cjid!(0)) => {
+            FindMissingArtifactsToBroker(vec![digest!(0)]),
             JobRequestToBroker(cjid!(0), spec!(0)),
         };
         Broker(BrokerToClient::JobResponse(cjid!(0), Ok(outcome!(0)))) => {
@@ -645,13 +748,96 @@ mod tests {
         shutdown_clustered,
         Fixture::new(false, None),
         RunJob(spec!(0), cjid!(0)) => {
+            FindMissingArtifactsToBroker(vec![digest!(0)]),
             JobRequestToBroker(cjid!(0), spec!(0)),
         };
         RunJob(spec!(1), cjid!(1)) => {
+            FindMissingArtifactsToBroker(vec![digest!(1)]),
             JobRequestToBroker(cjid!(1), spec!(1)),
         };
         Shutdown(anyhow!("test error")) => {
             ShutdownLocalWorker("test error".into())
         };
     }
+
+    #[test]
+    fn run_jobs_clustered_negotiates_artifacts_once_per_batch() {
+        let mut fixture = Fixture::new(false, None);
+        fixture.receive_messages_then_send_pending_job_requests(vec![
+            RunJob(spec!(1), cjid!(0)),
+            RunJob(spec!(2), cjid!(1)),
+            RunJob(spec!(1), cjid!(2)),
+        ]);
+        fixture.expect_messages_in_order(vec![
+            FindMissingArtifactsToBroker(vec![digest!(1), digest!(2)]),
+            JobRequestToBroker(cjid!(0), spec!(1)),
+            JobRequestToBroker(cjid!(1), spec!(2)),
+            JobRequestToBroker(cjid!(2), spec!(1)),
+        ]);
+    }
+
+    script_test! {
+        run_job_clustered_does_not_renegotiate_artifacts,
+        Fixture::new(false, None),
+        RunJob(spec!(1), cjid!(0)) => {
+            FindMissingArtifactsToBroker(vec![digest!(1)]),
+            JobRequestToBroker(cjid!(0), spec!(1)),
+        };
+        RunJob(spec!(1), cjid!(1)) => {
+            JobRequestToBroker(cjid!(1), spec!(1)),
+        };
+    }
+
+    script_test! {
+        run_job_clustered_negotiates_manifest_entries,
+        Fixture::new(false, None),
+        AddManifestEntries(digest!(1), vec![digest!(2), digest!(3)]) => {};
+        RunJob(spec!(1), cjid!(0)) => {
+            FindMissingArtifactsToBroker(vec![digest!(1), digest!(2), digest!(3)]),
+            JobRequestToBroker(cjid!(0), spec!(1)),
+        };
+        AddManifestEntries(digest!(4), vec![digest!(3), digest!(5)]) => {};
+        RunJob(spec!(4), cjid!(1)) => {
+            FindMissingArtifactsToBroker(vec![digest!(4), digest!(5)]),
+            JobRequestToBroker(cjid!(1), spec!(4)),
+        };
+    }
+
+    script_test! {
+        run_job_must_be_local_clustered_does_not_negotiate_artifacts,
+        Fixture::new(false, None),
+        RunJob(spec!(1, network: JobNetwork::Local), cjid!(0)) => {
+            EnqueueJobToLocalWorker(jid!(0, 0), spec!(1, network: JobNetwork::Local)),
+        };
+    }
+
+    #[test]
+    #[should_panic(expected = "assertion failed: !self.standalone")]
+    fn broker_missing_artifacts_standalone() {
+        let mut fixture = Fixture::new(true, None);
+        fixture.receive_message(Broker(MissingArtifacts(vec![digest!(1)])));
+    }
+
+    script_test! {
+        broker_missing_artifacts_unknown_clustered,
+        Fixture::new(
+            false,
+            Some("got request for unknown artifact with digest 0000000000000000000000000000000000000000000000000000000000000002".into()),
+        ),
+        AddArtifact(path_buf!("bar"), digest!(1)) => {};
+        Broker(MissingArtifacts(vec![digest!(1), digest!(2)])) => {
+            StartArtifactTransferToBroker(digest!(1), path_buf!("bar")),
+        };
+    }
+
+    script_test! {
+        broker_missing_artifacts_known_clustered,
+        Fixture::new(false, None),
+        AddArtifact(path_buf!("bar"), digest!(1)) => {};
+        AddArtifact(path_buf!("baz"), digest!(2)) => {};
+        Broker(MissingArtifacts(vec![digest!(1), digest!(2)])) => {
+            StartArtifactTransferToBroker(digest!(1), path_buf!("bar")),
+            StartArtifactTransferToBroker(digest!(2), path_buf!("baz")),
+        };
+    }
 }
@@ -4,7 +4,8 @@ use anyhow::{bail, Result};
 use futures::{Stream, StreamExt as _};
 use maelstrom_client_base::{
     proto::{self, client_process_server::ClientProcess},
-    AddContainerRequest, IntoProtoBuf, RunJobRequest, StartRequest, TryFromProtoBuf,
+    AddContainerRequest, ExportImageRequest, IntoProtoBuf, RunJobRequest, StartRequest,
+    TryFromProtoBuf,
 };
 use maelstrom_util::config::common::LogLevel;
 use slog::Drain as _;
@@ -145,6 +146,24 @@ impl ClientProcess for ArcHandler {
         .map_to_tonic()
     }
 
+    async fn export_image(
+        &self,
+        request: Request<proto::ExportImageRequest>,
+    ) -> TonicResponse<proto::Void> {
+        async {
+            let ExportImageRequest { spec, path } =
+                TryFromProtoBuf::try_from_proto_buf(request.into_inner())?;
+            self.client
+                .read()
+                .await
+                .export_image(spec, path)
+                .await
+                .map(IntoProtoBuf::into_proto_buf)
+        }
+        .await
+        .map_to_tonic()
+    }
+
     async fn introspect(
         &self,
         _request: Request<proto::Void>,
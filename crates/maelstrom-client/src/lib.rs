@@ -1,7 +1,7 @@
 pub use maelstrom_client_base::{
     container_container_parent, container_ref, container_spec, converted_image, environment_spec,
     glob_layer_spec, image_container_parent, image_ref, job_spec, paths_layer_spec, prefix_options,
-    shared_library_dependencies_layer_spec, spec, stubs_layer_spec, symlink_spec,
+    setup_layer_spec, shared_library_dependencies_layer_spec, spec, stubs_layer_spec, symlink_spec,
     symlinks_layer_spec, tar_layer_spec, AcceptInvalidRemoteContainerTlsCerts, CacheDir,
     IntrospectResponse, JobRunningStatus, JobStatus, ProjectDir, RemoteProgress, RpcLogMessage,
     StateDir, MANIFEST_DIR,
@@ -14,7 +14,7 @@ use maelstrom_base::{ClientJobId, JobOutcomeResult};
 use maelstrom_client_base::{
     proto::{self, client_process_client::ClientProcessClient},
     spec::{ContainerSpec, JobSpec},
-    AddContainerRequest, IntoProtoBuf, StartRequest, TryFromProtoBuf,
+    AddContainerRequest, ExportImageRequest, IntoProtoBuf, StartRequest, TryFromProtoBuf,
 };
 use maelstrom_linux::{self as linux, Pid};
 use maelstrom_util::{
@@ -29,7 +29,7 @@ use std::{
     net::Shutdown,
     os::linux::net::SocketAddrExt as _,
     os::unix::net::{SocketAddr, UnixListener, UnixStream as StdUnixStream},
-    path::Path,
+    path::{Path, PathBuf},
     pin::Pin,
     process,
     process::{Command, Stdio},
@@ -68,7 +68,9 @@ async fn run_dispatcher(std_sock: StdUnixStream, mut requester: RequestReceiver)
         .await?;
 
     while let Some(f) = requester.recv().await {
-        task::spawn(f(ClientProcessClient::new(channel.clone())));
+        task::spawn(f(
+            ClientProcessClient::new(channel.clone()).max_decoding_message_size(usize::MAX)
+        ));
     }
 
     std_sock.shutdown(Shutdown::Both)?;
@@ -392,6 +394,16 @@ impl Client {
         })
     }
 
+    /// Build the container for the given job specification and write it out as an OCI image
+    /// tarball at `path`. The tarball can be loaded with `docker load` or `podman load`.
+    pub fn export_image(&self, spec: JobSpec, path: PathBuf) -> Result<()> {
+        self.send_sync_unit(|mut client| async move {
+            client
+                .export_image(ExportImageRequest { spec, path }.into_proto_buf())
+                .await
+        })
+    }
+
     pub fn introspect(&self) -> Result<IntrospectResponse> {
         self.send_sync(move |mut client| async move { client.introspect(proto::Void {}).await })
     }
@@ -97,11 +97,20 @@ impl ClientFixture {
     }
 
     fn run_job(&self, added_layers: Vec<LayerSpec>) -> String {
+        self.run_job_with_writable_file_system(added_layers, false)
+    }
+
+    fn run_job_with_writable_file_system(
+        &self,
+        added_layers: Vec<LayerSpec>,
+        enable_writable_file_system: bool,
+    ) -> String {
         let mut layers = self.layers.clone();
         layers.extend(added_layers);
         let spec = job_spec! {
             self.self_path.clone(),
             layers: layers,
+            enable_writable_file_system: enable_writable_file_system,
             arguments: ["--exact", "single_test", "--nocapture"],
             environment: [
                 environment_spec! {
@@ -310,6 +319,22 @@ fn symlinks_test(fix: &ClientFixture) {
     )
 }
 
+fn writable_paths_layer_directory_test(fix: &ClientFixture) {
+    fix.fs
+        .create_dir_all(fix.temp_dir.path().join("project/writable"))
+        .unwrap();
+    let output = fix.run_job_with_writable_file_system(vec![paths_layer_spec!(["writable"])], true);
+    assert_eq!(output, "hello world\n");
+}
+
+fn writable_paths_layer_directory_test_job() {
+    let fs = Fs::new();
+
+    fs.write("/writable/new.txt", b"hello world").unwrap();
+    let contents = fs.read_to_string("/writable/new.txt").unwrap();
+    println!("{contents}");
+}
+
 fn sys_local_network_error_test(fix: &ClientFixture) {
     let error = fix.run_job_expecting_error(
         vec![stubs_layer_spec!(["/sys/"])],
@@ -339,5 +364,9 @@ fn single_test() {
     fix.run_test(glob_test, paths_test_job);
     fix.run_test(stubs_test, paths_test_job);
     fix.run_test(symlinks_test, paths_test_job);
+    fix.run_test(
+        writable_paths_layer_directory_test,
+        writable_paths_layer_directory_test_job,
+    );
     fix.run_test(sys_local_network_error_test, panic_test_job);
 }
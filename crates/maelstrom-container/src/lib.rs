@@ -28,7 +28,7 @@ use oci_spec::image::{Descriptor, ImageIndex, ImageManifest, Platform};
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use serde_repr::{Deserialize_repr, Serialize_repr};
 use std::{
-    collections::{BTreeMap, HashMap},
+    collections::{BTreeMap, HashMap, HashSet},
     fmt,
     future::Future,
     io::{self, SeekFrom},
@@ -37,6 +37,7 @@ use std::{
     str::FromStr,
     sync::Arc,
     task::Poll,
+    time::{Duration, SystemTime},
 };
 use tokio::{
     io::AsyncWrite,
@@ -60,6 +61,7 @@ macro_rules! parse_str {
 struct DigestDir;
 struct ContainerConfigFile;
 struct ContainerTagFile;
+struct LastUsedFile;
 pub struct ContainerImageDepotDir;
 pub struct ProjectDir;
 
@@ -778,6 +780,10 @@ impl LockedContainerImageTags {
     fn add(&mut self, name: String, tag: String, digest: String) {
         self.map.entry(name).or_default().insert(tag, digest);
     }
+
+    fn digests(&self) -> impl Iterator<Item = &String> {
+        self.map.values().flat_map(|tags| tags.values())
+    }
 }
 
 #[allow(async_fn_in_trait)]
@@ -851,6 +857,37 @@ impl ContainerImageDepot<DefaultContainerImageDepotOps> {
 
 pub const TAG_FILE_NAME: &str = "maelstrom-container-tags.lock";
 
+const LAST_USED_FILE_NAME: &str = "last-used";
+
+/// An image stored in a [`ContainerImageDepot`], as returned by
+/// [`ContainerImageDepot::list_cached_images`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CachedContainerImage {
+    /// The digest the image was resolved to. This is also the name of its directory in the depot.
+    pub digest: String,
+    /// The image's configuration, or `None` if it couldn't be read. An image without a
+    /// configuration will be downloaded again the next time it is used.
+    pub image: Option<ContainerImage>,
+    /// The total size of the image's files on disk.
+    pub size: u64,
+    /// The last time a client used the image.
+    pub last_used: SystemTime,
+}
+
+/// Images used more recently than this are never pruned, since a running client may still be
+/// using them.
+pub const PRUNE_GRACE_PERIOD: Duration = Duration::from_secs(60 * 60);
+
+/// Which images [`ContainerImageDepot::prune_cached_image`] removes. An image is removed if it
+/// matches either criterion, unless it was used within [`PRUNE_GRACE_PERIOD`].
+#[derive(Clone, Debug, Default)]
+pub struct PruneCriteria {
+    /// Remove images not referenced by any of these container tags lock files.
+    pub unreferenced_by: Option<Vec<PathBuf>>,
+    /// Remove images that haven't been used since this time.
+    pub unused_since: Option<SystemTime>,
+}
+
 struct LockedTagsHandle<'a, 'b> {
     locked_tags: LockedContainerImageTags,
     lock_file: fs::File,
@@ -1010,7 +1047,7 @@ impl<ContainerImageDepotOpsT: ContainerImageDepotOps> ContainerImageDepot<Contai
 
         let img = self
             .with_cache_lock(&digest, &cache_fill, async {
-                Ok(if let Some(img) = self.get_cached_image(&digest).await {
+                let img = if let Some(img) = self.get_cached_image(&digest).await {
                     img
                 } else {
                     let output_dir = self.cache_dir.join::<DigestDir>(digest.clone());
@@ -1019,7 +1056,9 @@ impl<ContainerImageDepotOpsT: ContainerImageDepotOps> ContainerImageDepot<Contai
                     specific_ref.digest = Some(digest.clone());
                     self.download_image(&specific_ref, &output_dir, prog)
                         .await?
-                })
+                };
+                self.mark_used(&digest).await?;
+                Ok(img)
             })
             .await?;
         tags.write().await?;
@@ -1028,6 +1067,193 @@ impl<ContainerImageDepotOpsT: ContainerImageDepotOps> ContainerImageDepot<Contai
         self.cache.lock().await.insert(image_name, img.clone());
         Ok(img)
     }
+
+    #[anyhow_trace]
+    async fn mark_used(&self, digest: &str) -> Result<()> {
+        let file = self
+            .fs
+            .create_file(
+                self.cache_dir
+                    .join::<DigestDir>(digest)
+                    .join::<LastUsedFile>(LAST_USED_FILE_NAME),
+            )
+            .await?;
+        file.into_std().await.set_modified(SystemTime::now())?;
+        Ok(())
+    }
+
+    #[anyhow_trace]
+    async fn get_cached_image_info(&self, digest: String) -> Result<CachedContainerImage> {
+        let digest_dir = self.cache_dir.join::<DigestDir>(&digest);
+        let mut size = 0;
+        let mut last_used = self.fs.metadata(&digest_dir).await?.modified()?;
+        let mut read_dir = self.fs.read_dir(&digest_dir).await?;
+        while let Some(entry) = read_dir.next_entry().await? {
+            let metadata = entry.metadata().await?;
+            size += metadata.len();
+            if entry.file_name() == LAST_USED_FILE_NAME {
+                last_used = metadata.modified()?;
+            }
+        }
+        let image = ContainerImage::from_dir(&self.fs, &digest_dir).await;
+        Ok(CachedContainerImage {
+            digest,
+            image,
+            size,
+            last_used,
+        })
+    }
+
+    /// List all of the images stored in the depot, sorted by digest.
+    #[anyhow_trace]
+    pub async fn list_cached_images(&self) -> Result<Vec<CachedContainerImage>> {
+        let mut images = vec![];
+        if !self.fs.exists(&self.cache_dir).await {
+            return Ok(images);
+        }
+        let mut read_dir = self.fs.read_dir(&self.cache_dir).await?;
+        while let Some(entry) = read_dir.next_entry().await? {
+            let Ok(digest) = entry.file_name().into_string() else {
+                continue;
+            };
+            if digest.starts_with('.') || !entry.file_type().await?.is_dir() {
+                continue;
+            }
+            images.push(self.get_cached_image_info(digest).await?);
+        }
+        images.sort_by(|a, b| a.digest.cmp(&b.digest));
+        Ok(images)
+    }
+
+    /// Return the set of digests referenced by the given container tags lock file. The file is
+    /// locked while it is read, so this won't see a partially-written file.
+    #[anyhow_trace]
+    pub async fn locked_digests(&self, lock_file: impl AsRef<Path>) -> Result<HashSet<String>> {
+        let mut lock_file = self.fs.open_file(lock_file).await?;
+        lock_file.lock_shared().await?;
+        let mut contents = String::new();
+        lock_file.read_to_string(&mut contents).await?;
+        let locked_tags = LockedContainerImageTags::from_str(&contents)?;
+        Ok(locked_tags.digests().cloned().collect())
+    }
+
+    /// Remove the image with the given digest from the depot.
+    ///
+    /// This takes the same locks that are taken when filling the depot, so it is safe to call
+    /// while clients are running. A client that needs the image again will download it again.
+    #[anyhow_trace]
+    pub async fn remove_cached_image(&self, digest: &str) -> Result<()> {
+        check_digest_dir_name(digest)?;
+        let cache_fill = self.cache_fill_lock.lock().await;
+        self.with_cache_lock(digest, &cache_fill, async {
+            let digest_dir = self.cache_dir.join::<DigestDir>(digest);
+            if self.fs.exists(&digest_dir).await {
+                self.fs.remove_dir_all(&digest_dir).await?;
+            }
+            Ok(())
+        })
+        .await?;
+        self.cache.lock().await.clear();
+        Ok(())
+    }
+
+    /// Remove the image with the given digest from the depot if it matches `criteria`, returning
+    /// its size if it was (or, with `dry_run`, would have been) removed.
+    ///
+    /// The image's last use and the lock files are checked while holding the image's lock, so an
+    /// image that a client starts using after it was listed is kept.
+    #[anyhow_trace]
+    pub async fn prune_cached_image(
+        &self,
+        digest: &str,
+        criteria: &PruneCriteria,
+        dry_run: bool,
+    ) -> Result<Option<u64>> {
+        check_digest_dir_name(digest)?;
+        let cache_fill = self.cache_fill_lock.lock().await;
+        let pruned = self
+            .with_cache_lock(digest, &cache_fill, async {
+                let digest_dir = self.cache_dir.join::<DigestDir>(digest);
+                if !self.fs.exists(&digest_dir).await {
+                    return Ok(None);
+                }
+                let image = self.get_cached_image_info(digest.into()).await?;
+                let recently_used = SystemTime::now()
+                    .duration_since(image.last_used)
+                    .unwrap_or_default()
+                    < PRUNE_GRACE_PERIOD;
+                if recently_used {
+                    return Ok(None);
+                }
+                let mut unreferenced = false;
+                if let Some(lock_files) = &criteria.unreferenced_by {
+                    unreferenced = true;
+                    for lock_file in lock_files {
+                        if self.locked_digests(lock_file).await?.contains(digest) {
+                            unreferenced = false;
+                            break;
+                        }
+                    }
+                }
+                let unused = criteria
+                    .unused_since
+                    .is_some_and(|since| image.last_used < since);
+                if !unreferenced && !unused {
+                    return Ok(None);
+                }
+                if !dry_run {
+                    self.fs.remove_dir_all(&digest_dir).await?;
+                }
+                Ok(Some(image.size))
+            })
+            .await?;
+        if pruned.is_some() && !dry_run {
+            self.cache.lock().await.clear();
+        }
+        Ok(pruned)
+    }
+
+    /// Check that the image with the given digest is intact. That means its configuration can be
+    /// read, and each of its layers hashes to the corresponding diff ID in that configuration.
+    #[anyhow_trace]
+    pub async fn verify_cached_image(&self, digest: &str) -> Result<()> {
+        check_digest_dir_name(digest)?;
+        let cache_fill = self.cache_fill_lock.lock().await;
+        self.with_cache_lock(digest, &cache_fill, async {
+            let Some(img) = self.get_cached_image(digest).await else {
+                bail!("missing or unreadable image configuration");
+            };
+            let diff_ids = &img.config.rootfs.diff_ids;
+            if img.layers.len() != diff_ids.len() {
+                bail!(
+                    "image has {} layers but {} diff IDs",
+                    img.layers.len(),
+                    diff_ids.len()
+                );
+            }
+            for (layer, diff_id) in img.layers.iter().zip(diff_ids) {
+                let mut hasher = Sha256Stream::new(tokio::io::sink());
+                tokio::io::copy(&mut self.fs.open_file(layer).await?, &mut hasher).await?;
+                let (_, hash) = hasher.finalize();
+                let actual = format!("sha256:{hash}");
+                if &actual != diff_id {
+                    bail!(
+                        "layer {} has digest {actual}, expected {diff_id}",
+                        layer.display()
+                    );
+                }
+            }
+            Ok(())
+        })
+        .await
+    }
+}
+
+fn check_digest_dir_name(digest: &str) -> Result<()> {
+    if digest.is_empty() || digest.starts_with('.') || digest.contains('/') {
+        bail!("invalid image digest {digest:?}");
+    }
+    Ok(())
 }
 
 #[cfg(test)]
@@ -1390,3 +1616,254 @@ async fn container_image_depot_local_registry() {
         vec!["sha256:0d3f3db50eadc1930aa204eef3d21966037b797cdbef2c7446bbdf10541bda4b"]
     );
 }
+
+#[tokio::test]
+async fn container_image_depot_list_cached_images() {
+    let project_dir = tempfile::tempdir().unwrap();
+    let project_dir = Root::<ProjectDir>::new(project_dir.path());
+    let image_dir = tempfile::tempdir().unwrap();
+    let image_dir = Root::<ContainerImageDepotDir>::new(image_dir.path());
+
+    let depot = ContainerImageDepot::new_with(
+        project_dir,
+        image_dir,
+        FakeContainerImageDepotOps(maplit::hashmap! {
+            "foo-latest".into() => "sha256:abcdef".into(),
+            "bar-latest".into() => "sha256:ghijk".into(),
+        }),
+    )
+    .unwrap();
+    assert_eq!(depot.list_cached_images().await.unwrap(), vec![]);
+
+    let before = SystemTime::now();
+    #[allow(clippy::disallowed_names)]
+    let foo = depot
+        .get_container_image("docker://foo", NullProgressTracker)
+        .await
+        .unwrap();
+    let bar = depot
+        .get_container_image("docker://bar", NullProgressTracker)
+        .await
+        .unwrap();
+
+    let images = depot.list_cached_images().await.unwrap();
+    assert_eq!(
+        images
+            .iter()
+            .map(|i| (i.digest.as_str(), i.image.as_ref()))
+            .collect::<Vec<_>>(),
+        vec![("sha256:abcdef", Some(&foo)), ("sha256:ghijk", Some(&bar))]
+    );
+    for image in images {
+        assert!(image.size > 0);
+        assert!(image.last_used >= before);
+    }
+}
+
+#[tokio::test]
+async fn container_image_depot_remove_cached_image() {
+    let fs = Fs::new();
+    let project_dir = tempfile::tempdir().unwrap();
+    let project_dir = Root::<ProjectDir>::new(project_dir.path());
+    let image_dir = tempfile::tempdir().unwrap();
+    let image_dir = Root::<ContainerImageDepotDir>::new(image_dir.path());
+
+    let depot = ContainerImageDepot::new_with(
+        project_dir,
+        image_dir,
+        FakeContainerImageDepotOps(maplit::hashmap! {
+            "foo-latest".into() => "sha256:abcdef".into(),
+            "bar-latest".into() => "sha256:ghijk".into(),
+        }),
+    )
+    .unwrap();
+    depot
+        .get_container_image("docker://foo", NullProgressTracker)
+        .await
+        .unwrap();
+    depot
+        .get_container_image("docker://bar", NullProgressTracker)
+        .await
+        .unwrap();
+
+    depot.remove_cached_image("sha256:abcdef").await.unwrap();
+    assert_eq!(
+        sorted_dir_listing(&fs, image_dir).await,
+        vec!["sha256:ghijk"]
+    );
+
+    // Removing an image that isn't there is fine.
+    depot.remove_cached_image("sha256:abcdef").await.unwrap();
+
+    // The image is downloaded again the next time it is used.
+    depot
+        .get_container_image("docker://foo", NullProgressTracker)
+        .await
+        .unwrap();
+    assert_eq!(
+        sorted_dir_listing(&fs, image_dir).await,
+        vec!["sha256:abcdef", "sha256:ghijk"]
+    );
+
+    depot.remove_cached_image("../foo").await.unwrap_err();
+}
+
+#[tokio::test]
+async fn container_image_depot_prune_cached_image() {
+    let fs = Fs::new();
+    let project_dir = tempfile::tempdir().unwrap();
+    let project_dir = Root::<ProjectDir>::new(project_dir.path());
+    let image_dir = tempfile::tempdir().unwrap();
+    let image_dir = Root::<ContainerImageDepotDir>::new(image_dir.path());
+
+    let depot = ContainerImageDepot::new_with(
+        project_dir,
+        image_dir,
+        FakeContainerImageDepotOps(maplit::hashmap! {
+            "foo-latest".into() => "sha256:abcdef".into(),
+            "bar-latest".into() => "sha256:ghijk".into(),
+        }),
+    )
+    .unwrap();
+    depot
+        .get_container_image("docker://foo", NullProgressTracker)
+        .await
+        .unwrap();
+    depot
+        .get_container_image("docker://bar", NullProgressTracker)
+        .await
+        .unwrap();
+    let lock_file = project_dir.join::<ContainerTagFile>(TAG_FILE_NAME);
+    let criteria = PruneCriteria {
+        unreferenced_by: Some(vec![lock_file.to_path_buf()]),
+        unused_since: None,
+    };
+
+    // Both images were just used, so neither is pruned, even though "foo" isn't referenced.
+    fs.write(
+        &lock_file,
+        "version = 1\n\n[bar]\nlatest = \"sha256:ghijk\"\n",
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        depot
+            .prune_cached_image("sha256:abcdef", &criteria, false)
+            .await
+            .unwrap(),
+        None
+    );
+
+    let long_ago = SystemTime::now() - 2 * PRUNE_GRACE_PERIOD;
+    for digest in ["sha256:abcdef", "sha256:ghijk"] {
+        let last_used = image_dir
+            .join::<DigestDir>(digest)
+            .join::<LastUsedFile>(LAST_USED_FILE_NAME);
+        std::fs::File::options()
+            .write(true)
+            .open(last_used)
+            .unwrap()
+            .set_modified(long_ago)
+            .unwrap();
+    }
+
+    // A referenced image is kept.
+    assert_eq!(
+        depot
+            .prune_cached_image("sha256:ghijk", &criteria, false)
+            .await
+            .unwrap(),
+        None
+    );
+
+    // A dry run doesn't remove anything.
+    let size = depot
+        .prune_cached_image("sha256:abcdef", &criteria, true)
+        .await
+        .unwrap();
+    assert!(size.is_some_and(|size| size > 0));
+    assert_eq!(
+        sorted_dir_listing(&fs, image_dir).await,
+        vec!["sha256:abcdef", "sha256:ghijk"]
+    );
+
+    assert_eq!(
+        depot
+            .prune_cached_image("sha256:abcdef", &criteria, false)
+            .await
+            .unwrap(),
+        size
+    );
+    assert_eq!(
+        sorted_dir_listing(&fs, image_dir).await,
+        vec!["sha256:ghijk"]
+    );
+}
+
+#[tokio::test]
+async fn container_image_depot_locked_digests() {
+    let fs = Fs::new();
+    let project_dir = tempfile::tempdir().unwrap();
+    let project_dir = Root::<ProjectDir>::new(project_dir.path());
+    let image_dir = tempfile::tempdir().unwrap();
+    let image_dir = Root::<ContainerImageDepotDir>::new(image_dir.path());
+
+    let lock_file = project_dir.join::<ContainerTagFile>(TAG_FILE_NAME);
+    fs.write(
+        &lock_file,
+        "\
+            version = 1\n\
+            \n\
+            [foo]\n\
+            latest = \"sha256:abcdef\"\n\
+            v1 = \"sha256:ghijk\"\n\
+            \n\
+            [bar]\n\
+            latest = \"sha256:abcdef\"\n\
+        ",
+    )
+    .await
+    .unwrap();
+
+    let depot =
+        ContainerImageDepot::new_with(project_dir, image_dir, PanicContainerImageDepotOps).unwrap();
+    assert_eq!(
+        depot.locked_digests(&lock_file).await.unwrap(),
+        HashSet::from(["sha256:abcdef".into(), "sha256:ghijk".into()])
+    );
+
+    fs.write(&lock_file, "version = 0\n").await.unwrap();
+    depot.locked_digests(&lock_file).await.unwrap_err();
+}
+
+#[tokio::test]
+async fn container_image_depot_verify_cached_image() {
+    let fs = Fs::new();
+    let project_dir = tempfile::tempdir().unwrap();
+    let project_dir = Root::<ProjectDir>::new(project_dir.path());
+    let image_dir = tempfile::tempdir().unwrap();
+    let image_dir = Root::<ContainerImageDepotDir>::new(image_dir.path());
+
+    let manifest_dir = std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let log = maelstrom_util::log::test_logger();
+    let address = local_registry::LocalRegistry::run(manifest_dir.join("src"), log)
+        .await
+        .unwrap();
+
+    let ops = DefaultContainerImageDepotOps::new(true /* accept_invalid_certs */);
+    let depot = ContainerImageDepot::new_with(project_dir, image_dir, ops).unwrap();
+    let img = depot
+        .get_container_image(&format!("docker://{address}/busybox"), NullProgressTracker)
+        .await
+        .unwrap();
+    let digest = "sha256:0d3f3db50eadc1930aa204eef3d21966037b797cdbef2c7446bbdf10541bda4b";
+    depot.verify_cached_image(digest).await.unwrap();
+
+    fs.write(&img.layers[0], b"corrupt").await.unwrap();
+    depot.verify_cached_image(digest).await.unwrap_err();
+
+    depot
+        .verify_cached_image("sha256:missing")
+        .await
+        .unwrap_err();
+}
@@ -1,10 +1,16 @@
 use anyhow::{bail, Result};
-use clap::{Parser, Subcommand};
+use bytesize::ByteSize;
+use clap::{Args, Parser, Subcommand};
 use maelstrom_container::{
-    local_registry::LocalRegistry, DockerReference, ImageDownloader, ImageName,
+    local_registry::LocalRegistry, ContainerImageDepot, DockerReference, ImageDownloader,
+    ImageName, PruneCriteria, TAG_FILE_NAME,
+};
+use maelstrom_util::{config::common::LogLevel, root::RootBuf};
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
 };
-use maelstrom_util::config::common::LogLevel;
-use std::path::PathBuf;
 
 #[derive(Subcommand)]
 enum CliCommands {
@@ -22,6 +28,91 @@ enum CliCommands {
     Registry {
         source_path: PathBuf,
     },
+    /// List the images stored in the container image depot.
+    List {
+        #[command(flatten)]
+        depot: DepotOptions,
+    },
+    /// Remove images from the container image depot. Images used within the last hour are never
+    /// removed, since a running client may still be using them.
+    Prune {
+        #[command(flatten)]
+        depot: DepotOptions,
+        /// Remove images not referenced by any of the container tags lock files.
+        #[arg(long)]
+        unreferenced: bool,
+        /// Remove images that haven't been used in this many days.
+        #[arg(long, value_name = "DAYS")]
+        unused_for: Option<u64>,
+        /// Print what would be removed without removing anything.
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Check the layers of the images in the container image depot against their digests.
+    Verify {
+        #[command(flatten)]
+        depot: DepotOptions,
+        /// Remove any image that fails verification.
+        #[arg(long)]
+        remove: bool,
+    },
+}
+
+#[derive(Args)]
+struct DepotOptions {
+    /// The container image depot directory. Defaults to the one used by the Maelstrom clients.
+    #[arg(long, value_name = "PATH")]
+    depot_dir: Option<PathBuf>,
+    /// A container tags lock file whose images are considered referenced. May be given multiple
+    /// times. Defaults to the lock file in the current directory, if there is one.
+    #[arg(long, value_name = "PATH")]
+    lock_file: Vec<PathBuf>,
+}
+
+impl DepotOptions {
+    fn depot(&self) -> Result<ContainerImageDepot> {
+        let depot_dir = match &self.depot_dir {
+            Some(depot_dir) => depot_dir.clone(),
+            None => xdg::BaseDirectories::with_prefix("maelstrom")?
+                .get_cache_home()
+                .join("container"),
+        };
+        ContainerImageDepot::new(
+            RootBuf::new(std::env::current_dir()?),
+            RootBuf::new(depot_dir),
+            false, /* accept_invalid_certs */
+        )
+    }
+
+    fn lock_files(&self) -> Option<Vec<PathBuf>> {
+        let mut lock_files = self.lock_file.clone();
+        if lock_files.is_empty() && Path::new(TAG_FILE_NAME).exists() {
+            lock_files.push(TAG_FILE_NAME.into());
+        }
+        (!lock_files.is_empty()).then_some(lock_files)
+    }
+
+    async fn referenced_digests(
+        &self,
+        depot: &ContainerImageDepot,
+    ) -> Result<Option<HashSet<String>>> {
+        let Some(lock_files) = self.lock_files() else {
+            return Ok(None);
+        };
+        let mut digests = HashSet::new();
+        for lock_file in lock_files {
+            digests.extend(depot.locked_digests(lock_file).await?);
+        }
+        Ok(Some(digests))
+    }
+}
+
+fn days_since(time: SystemTime) -> u64 {
+    SystemTime::now()
+        .duration_since(time)
+        .unwrap_or_default()
+        .as_secs()
+        / (24 * 60 * 60)
 }
 
 #[derive(Parser)]
@@ -88,6 +179,80 @@ async fn run(opt: CliOptions, log: slog::Logger) -> Result<()> {
             println!("listening on {}", registry.address()?);
             registry.run_until_error().await?;
         }
+        CliCommands::List { depot } => {
+            let image_depot = depot.depot()?;
+            let referenced = depot.referenced_digests(&image_depot).await?;
+            for image in image_depot.list_cached_images().await? {
+                let name = image.image.as_ref().map(|i| i.name.as_str());
+                let referenced = match &referenced {
+                    Some(referenced) if !referenced.contains(&image.digest) => " (unreferenced)",
+                    _ => "",
+                };
+                println!(
+                    "{} {} {} last used {} days ago{referenced}",
+                    image.digest,
+                    name.unwrap_or("<corrupt>"),
+                    ByteSize::b(image.size),
+                    days_since(image.last_used),
+                );
+            }
+        }
+        CliCommands::Prune {
+            depot,
+            unreferenced,
+            unused_for,
+            dry_run,
+        } => {
+            if !unreferenced && unused_for.is_none() {
+                bail!("nothing to prune; specify --unreferenced and/or --unused-for");
+            }
+            let image_depot = depot.depot()?;
+            let unreferenced_by = if unreferenced {
+                let Some(lock_files) = depot.lock_files() else {
+                    bail!("no container tags lock files found; specify them with --lock-file");
+                };
+                Some(lock_files)
+            } else {
+                None
+            };
+            let criteria = PruneCriteria {
+                unreferenced_by,
+                unused_since: unused_for
+                    .map(|days| SystemTime::now() - Duration::from_secs(days * 24 * 60 * 60)),
+            };
+            let mut freed = 0;
+            for image in image_depot.list_cached_images().await? {
+                if let Some(size) = image_depot
+                    .prune_cached_image(&image.digest, &criteria, dry_run)
+                    .await?
+                {
+                    let action = if dry_run { "would remove" } else { "removing" };
+                    println!("{action} {} ({})", image.digest, ByteSize::b(size));
+                    freed += size;
+                }
+            }
+            if dry_run {
+                println!("would free {}", ByteSize::b(freed));
+            } else {
+                println!("freed {}", ByteSize::b(freed));
+            }
+        }
+        CliCommands::Verify { depot, remove } => {
+            let image_depot = depot.depot()?;
+            let mut failed = 0;
+            for image in image_depot.list_cached_images().await? {
+                if let Err(err) = image_depot.verify_cached_image(&image.digest).await {
+                    println!("{}: {err:#}", image.digest);
+                    failed += 1;
+                    if remove {
+                        image_depot.remove_cached_image(&image.digest).await?;
+                    }
+                }
+            }
+            if failed > 0 && !remove {
+                bail!("{failed} image(s) failed verification");
+            }
+        }
     }
     Ok(())
 }
@@ -6,7 +6,9 @@ pub mod fuser;
 pub use fuser::{FileAttr, FileType};
 
 use anyhow::Result;
-use fuser::{MountOption, ReplyAttr, ReplyData, ReplyDirectory, ReplyEmpty, ReplyEntry};
+use fuser::{
+    MountOption, ReplyAttr, ReplyData, ReplyDirectory, ReplyEmpty, ReplyEntry, ReplyXattr,
+};
 use futures::stream::{Stream, StreamExt};
 use maelstrom_linux::{self as linux, Errno};
 use maelstrom_util::r#async::await_and_every_sec;
@@ -251,6 +253,27 @@ async fn handle_read_dir_resp(
     }
 }
 
+async fn handle_xattr_resp(res: ErrnoResult<XattrResponse>, size: u32, reply: ReplyXattr) {
+    match res {
+        Ok(XattrResponse { data }) => {
+            let Ok(len) = u32::try_from(data.len()) else {
+                reply.error(Errno::E2BIG.as_i32()).await;
+                return;
+            };
+            if size == 0 {
+                reply.size(len).await;
+            } else if len > size {
+                reply.error(Errno::ERANGE.as_i32()).await;
+            } else {
+                reply.data(&data).await;
+            }
+        }
+        Err(e) => {
+            reply.error(e.as_i32()).await;
+        }
+    }
+}
+
 struct DispatchingFs<FileSystemT> {
     handler: Arc<FileSystemT>,
     sem: Arc<Semaphore>,
@@ -356,6 +379,40 @@ impl<FileSystemT: FuseFileSystem + Send + Sync + 'static> fuser::Filesystem
         });
     }
 
+    async fn getxattr(
+        &mut self,
+        req: &fuser::Request<'_>,
+        ino: u64,
+        name: &OsStr,
+        size: u32,
+        reply: ReplyXattr,
+    ) {
+        let handler = self.handler.clone();
+        let permit = self.sem.clone().acquire_owned().await.unwrap();
+        let request = req.into();
+        let name = name.to_owned();
+        tokio::task::spawn(async move {
+            handle_xattr_resp(handler.get_xattr(request, ino, &name).await, size, reply).await;
+            drop(permit);
+        });
+    }
+
+    async fn listxattr(
+        &mut self,
+        req: &fuser::Request<'_>,
+        ino: u64,
+        size: u32,
+        reply: ReplyXattr,
+    ) {
+        let handler = self.handler.clone();
+        let permit = self.sem.clone().acquire_owned().await.unwrap();
+        let request = req.into();
+        tokio::task::spawn(async move {
+            handle_xattr_resp(handler.list_xattr(request, ino).await, size, reply).await;
+            drop(permit);
+        });
+    }
+
     async fn access(&mut self, req: &fuser::Request<'_>, ino: u64, mask: i32, reply: ReplyEmpty) {
         let handler = self.handler.clone();
         let permit = self.sem.clone().acquire_owned().await.unwrap();
@@ -451,6 +508,13 @@ impl Response for ReadResponse {
 /// Response from a [`FuseFileSystem::read_link`] request
 pub type ReadLinkResponse = ReadResponse;
 
+/// Response from a [`FuseFileSystem::get_xattr`] or [`FuseFileSystem::list_xattr`] request. It
+/// contains the whole value, checking it against the size the caller asked for is done for you.
+#[derive(Debug)]
+pub struct XattrResponse {
+    pub data: Vec<u8>,
+}
+
 impl Response for () {
     type Reply = ReplyEmpty;
 
@@ -585,6 +649,26 @@ pub trait FuseFileSystem {
     }
     */
 
+    /// Get the value of the extended attribute with the given name. Should return `ENODATA` if the
+    /// file doesn't have the attribute.
+    fn get_xattr(
+        &self,
+        _req: Request,
+        _ino: u64,
+        _name: &OsStr,
+    ) -> impl Future<Output = ErrnoResult<XattrResponse>> + Send {
+        async move { Err(Errno::ENOSYS) }
+    }
+
+    /// List the names of the file's extended attributes, each one followed by a NUL byte.
+    fn list_xattr(
+        &self,
+        _req: Request,
+        _ino: u64,
+    ) -> impl Future<Output = ErrnoResult<XattrResponse>> + Send {
+        async move { Err(Errno::ENOSYS) }
+    }
+
     fn access(
         &self,
         _req: Request,
@@ -24,6 +24,38 @@ pub struct GoTestOptions {
     #[config(flag)]
     pub fullpath: bool,
 
+    /// Build the test binaries with the data race detector enabled. See `go help build` for
+    /// details.
+    #[config(flag)]
+    pub race: bool,
+
+    /// Build the test binaries with coverage instrumentation and collect a coverage profile from
+    /// each test job. The profiles are merged into `coverage.out` in the project directory.
+    #[config(flag)]
+    pub cover: bool,
+
+    /// Apply coverage analysis to packages matching these patterns, instead of just the package
+    /// being tested. Implies `--cover`. See `go help build` for details.
+    #[config(option, default = r#""the tested package""#, value_name = "PATTERNS")]
+    pub coverpkg: Option<String>,
+
+    /// A comma-separated list of build tags to pass to `go test`. See `go help build` for details.
+    #[config(option, default = r#""no tags""#, value_name = "TAGS")]
+    pub tags: Option<String>,
+
+    /// Arguments to pass on each `go tool compile` invocation. See `go help build` for details.
+    #[config(option, default = r#""no flags""#, value_name = "FLAGS")]
+    pub gcflags: Option<String>,
+
+    /// Arguments to pass on each `go tool link` invocation. See `go help build` for details.
+    #[config(option, default = r#""no flags""#, value_name = "FLAGS")]
+    pub ldflags: Option<String>,
+
+    /// Run each known subtest of a test as its own job, instead of running the test as a whole.
+    /// Subtests become known when their test is run as a whole.
+    #[config(flag)]
+    pub fan_out_subtests: bool,
+
     /// Extra arguments to pass to the test binary. See `go help testflag` for what it normally
     /// accepts.
     #[config(
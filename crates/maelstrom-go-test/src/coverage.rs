@@ -0,0 +1,189 @@
+//! Collect coverage profiles from test jobs and merge them into a single `coverage.out`.
+//!
+//! When collecting coverage, the test binaries are built with `-cover` (or `-coverpkg`) and each
+//! test job is told to write its profile to [`PROFILE_PATH`] with `-test.coverprofile`. The
+//! profiles come back with the jobs' results and are merged as they arrive. Once all of the tests
+//! have run, the merged profile is written to `coverage.out` in the project directory, where it can
+//! be used with `go tool cover`.
+
+use anyhow::{anyhow, bail, Result};
+use maelstrom_base::CapturedFile;
+use maelstrom_client::ProjectDir;
+use maelstrom_test_runner::{
+    ui::{UiMessage, UiSender},
+    COVERAGE_DIR,
+};
+use maelstrom_util::{
+    fs::Fs,
+    root::{Root, RootBuf},
+};
+use std::{collections::BTreeMap, fmt, str, sync::Mutex};
+
+const PROFILE_NAME: &str = "coverage.out";
+
+/// The file in each test job's container that the coverage profile is written to.
+pub fn profile_path() -> String {
+    format!("{COVERAGE_DIR}/{PROFILE_NAME}")
+}
+
+/// A coverage profile in the format written by `-test.coverprofile`. The first line gives the
+/// mode, and every other line gives a block and how many times it was executed:
+///
+/// ```text
+/// mode: set
+/// example.com/mod/foo/foo.go:3.24,5.2 1 1
+/// ```
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Profile {
+    mode: Option<String>,
+    /// Maps each block, including its number of statements, to its count.
+    blocks: BTreeMap<String, u64>,
+}
+
+impl Profile {
+    /// Merge another profile into this one. In "set" mode, a block is covered if it was covered by
+    /// either profile. In the other modes, the counts are added together.
+    pub fn merge(&mut self, contents: &str) -> Result<()> {
+        let mut lines = contents.lines().filter(|line| !line.is_empty());
+        let Some(mode) = lines.next() else {
+            return Ok(());
+        };
+        let mode = mode
+            .strip_prefix("mode: ")
+            .ok_or_else(|| anyhow!("coverage profile missing mode line"))?;
+        match &self.mode {
+            Some(existing) if existing != mode => {
+                bail!("can't merge coverage profiles with modes {existing:?} and {mode:?}")
+            }
+            Some(_) => {}
+            None => self.mode = Some(mode.into()),
+        }
+        for line in lines {
+            let (block, count) = line
+                .rsplit_once(' ')
+                .ok_or_else(|| anyhow!("malformed coverage profile line {line:?}"))?;
+            let count: u64 = count
+                .parse()
+                .map_err(|_| anyhow!("malformed coverage profile line {line:?}"))?;
+            let entry = self.blocks.entry(block.into()).or_default();
+            if mode == "set" {
+                *entry = (*entry).max(count);
+            } else {
+                *entry += count;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.mode.is_none()
+    }
+}
+
+impl fmt::Display for Profile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(mode) = &self.mode {
+            writeln!(f, "mode: {mode}")?;
+        }
+        for (block, count) in &self.blocks {
+            writeln!(f, "{block} {count}")?;
+        }
+        Ok(())
+    }
+}
+
+pub struct Coverage {
+    project_dir: RootBuf<ProjectDir>,
+    profile: Mutex<Profile>,
+}
+
+impl Coverage {
+    pub fn new(project_dir: &Root<ProjectDir>) -> Self {
+        Self {
+            project_dir: project_dir.to_owned(),
+            profile: Default::default(),
+        }
+    }
+
+    /// Remove any data from a previous run.
+    pub fn reset(&self) {
+        *self.profile.lock().unwrap() = Profile::default();
+    }
+
+    /// Merge the profile from a test job into what we've collected so far.
+    pub fn save(&self, files: Vec<CapturedFile>) -> Result<()> {
+        let mut profile = self.profile.lock().unwrap();
+        for file in files {
+            if file.path.file_name() != Some(PROFILE_NAME) {
+                continue;
+            }
+            profile.merge(str::from_utf8(&file.contents)?)?;
+        }
+        Ok(())
+    }
+
+    /// Write the merged profile, telling the user where it is.
+    pub fn report(&self, ui: &UiSender) -> Result<()> {
+        let profile = self.profile.lock().unwrap();
+        if profile.is_empty() {
+            ui.send(UiMessage::CollectionOutput(
+                "no code coverage data was collected".into(),
+            ));
+            return Ok(());
+        }
+        let path = self.project_dir.join::<()>(PROFILE_NAME);
+        Fs::new().write(&path, profile.to_string())?;
+        ui.send(UiMessage::CollectionOutput(format!(
+            "code coverage profile written to {}\n\
+            use `go tool cover -html={}` to view it",
+            path.display(),
+            path.display()
+        )));
+        Ok(())
+    }
+}
+
+#[test]
+fn merge_set_mode() {
+    let mut profile = Profile::default();
+    profile
+        .merge("mode: set\na.go:1.1,2.2 1 1\na.go:3.1,4.2 2 0\n")
+        .unwrap();
+    profile
+        .merge("mode: set\na.go:1.1,2.2 1 1\na.go:3.1,4.2 2 1\nb.go:1.1,2.2 1 0\n")
+        .unwrap();
+    assert_eq!(
+        profile.to_string(),
+        "mode: set\na.go:1.1,2.2 1 1\na.go:3.1,4.2 2 1\nb.go:1.1,2.2 1 0\n"
+    );
+}
+
+#[test]
+fn merge_count_mode() {
+    let mut profile = Profile::default();
+    profile.merge("mode: atomic\na.go:1.1,2.2 1 3\n").unwrap();
+    profile.merge("mode: atomic\na.go:1.1,2.2 1 4\n").unwrap();
+    assert_eq!(profile.to_string(), "mode: atomic\na.go:1.1,2.2 1 7\n");
+}
+
+#[test]
+fn merge_mismatched_modes() {
+    let mut profile = Profile::default();
+    profile.merge("mode: set\n").unwrap();
+    profile.merge("mode: count\n").unwrap_err();
+}
+
+#[test]
+fn merge_malformed() {
+    Profile::default().merge("a.go:1.1,2.2 1 1\n").unwrap_err();
+    Profile::default()
+        .merge("mode: set\na.go:1.1,2.2 1 x\n")
+        .unwrap_err();
+}
+
+#[test]
+fn merge_empty() {
+    let mut profile = Profile::default();
+    profile.merge("").unwrap();
+    assert!(profile.is_empty());
+}
@@ -2,6 +2,7 @@ use crate::{GoImportPath, GoTestOptions};
 use anyhow::{anyhow, Context as _, Result};
 use maelstrom_linux as linux;
 use maelstrom_test_runner::{
+    find_containing_dir,
     ui::{UiMessage, UiWeakSender},
     BuildDir, WaitStatus,
 };
@@ -12,7 +13,7 @@ use maelstrom_util::{
     root::{Root, RootBuf},
 };
 use serde::Deserialize;
-use std::collections::HashSet;
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::os::unix::process::ExitStatusExt as _;
 use std::{
     fmt,
@@ -204,11 +205,36 @@ fn go_build(
         .arg("test")
         .arg("-c")
         .arg("-o")
-        .arg(output);
+        .arg(output)
+        .args(go_build_args(options));
+    run_build_cmd(&killer, &mut cmd, true /* stdout_to_ui */, ui)
+}
+
+/// The arguments to pass to `go test -c` for the given options, beyond the ones that specify what
+/// to build and where to put it.
+fn go_build_args(options: &GoTestOptions) -> Vec<String> {
+    let mut args = vec![];
     if let Some(vet_value) = &options.vet {
-        cmd.arg(format!("--vet={vet_value}"));
+        args.push(format!("--vet={vet_value}"));
     }
-    run_build_cmd(&killer, &mut cmd, true /* stdout_to_ui */, ui)
+    if options.race {
+        args.push("-race".into());
+    }
+    if let Some(coverpkg) = &options.coverpkg {
+        args.push(format!("-coverpkg={coverpkg}"));
+    } else if options.cover {
+        args.push("-cover".into());
+    }
+    if let Some(tags) = &options.tags {
+        args.push(format!("-tags={tags}"));
+    }
+    if let Some(gcflags) = &options.gcflags {
+        args.push(format!("-gcflags={gcflags}"));
+    }
+    if let Some(ldflags) = &options.ldflags {
+        args.push(format!("-ldflags={ldflags}"));
+    }
+    args
 }
 
 fn is_no_go_files_error<V>(res: &Result<V>) -> bool {
@@ -335,6 +361,12 @@ pub(crate) struct GoPackage {
     pub import_path: String,
     pub name: String,
     pub root: PathBuf,
+    #[serde(default)]
+    pub deps: Vec<String>,
+    #[serde(default)]
+    pub test_imports: Vec<String>,
+    #[serde(default)]
+    pub x_test_imports: Vec<String>,
 }
 
 impl GoPackage {
@@ -372,6 +404,63 @@ pub(crate) fn go_list(dir: &Path, ui: UiWeakSender) -> Result<Vec<GoPackage>> {
     Ok(packages)
 }
 
+/// The directories of the given packages, along with their import paths, which are used as their
+/// names. Each package can have its own test metadata file in its directory.
+pub(crate) fn package_directories(packages: &[GoPackage]) -> Vec<(String, PathBuf)> {
+    packages
+        .iter()
+        .map(|package| (package.import_path.clone(), package.dir.clone()))
+        .collect()
+}
+
+/// Find the import paths of the packages whose tests could be impacted by changes to the given
+/// paths.
+///
+/// A package's tests are impacted if the package contains a changed path, or if the package or
+/// its tests depend on a package that does. Returns `None` if the changes could impact every
+/// package. This happens when a changed path isn't in any package, like `go.mod` or `go.sum`.
+pub(crate) fn find_impacted_packages(
+    packages: &[&GoPackage],
+    changed_paths: &BTreeSet<PathBuf>,
+) -> Option<BTreeSet<String>> {
+    let mut changed_packages = HashSet::new();
+    for path in changed_paths {
+        let package = find_containing_dir(
+            path,
+            packages.iter().map(|p| (p.dir.as_path(), &p.import_path)),
+        )?;
+        changed_packages.insert(package.as_str());
+    }
+
+    // "go list" gives us the transitive dependencies of each package, but only the direct imports
+    // of its tests. We need to look up the dependencies of the test imports ourselves.
+    let deps: HashMap<&str, &[String]> = packages
+        .iter()
+        .map(|p| (p.import_path.as_str(), p.deps.as_slice()))
+        .collect();
+    let depends_on_changed = |import_path: &str| {
+        changed_packages.contains(import_path)
+            || deps.get(import_path).is_some_and(|deps| {
+                deps.iter()
+                    .any(|dep| changed_packages.contains(dep.as_str()))
+            })
+    };
+
+    Some(
+        packages
+            .iter()
+            .filter(|p| {
+                depends_on_changed(&p.import_path)
+                    || p.test_imports
+                        .iter()
+                        .chain(&p.x_test_imports)
+                        .any(|import| depends_on_changed(import))
+            })
+            .map(|p| p.import_path.clone())
+            .collect(),
+    )
+}
+
 /// Find the module root by searching upwards from PWD to a path containing a "go.mod"
 pub(crate) fn get_module_root() -> Result<PathBuf> {
     let mut c = Path::new(".").canonicalize()?;
@@ -384,3 +473,139 @@ pub(crate) fn get_module_root() -> Result<PathBuf> {
     }
     Ok(c)
 }
+
+#[cfg(test)]
+fn go_package(name: &str, deps: &[&str], test_imports: &[&str]) -> GoPackage {
+    let to_import_paths = |names: &[&str]| {
+        names
+            .iter()
+            .map(|name| format!("example.com/mod/{name}"))
+            .collect()
+    };
+    GoPackage {
+        dir: format!("/mod/{name}").into(),
+        import_path: format!("example.com/mod/{name}"),
+        name: name.rsplit('/').next().unwrap().into(),
+        root: "/mod".into(),
+        deps: to_import_paths(deps),
+        test_imports: to_import_paths(test_imports),
+        x_test_imports: vec![],
+    }
+}
+
+#[cfg(test)]
+#[track_caller]
+fn find_impacted_packages_test<'a>(
+    changed_paths: impl IntoIterator<Item = &'a str>,
+    expected: Option<Vec<&str>>,
+) {
+    let mut bar_test = go_package("bar_test", &[], &[]);
+    bar_test.x_test_imports = vec!["example.com/mod/testutil".into()];
+    let packages = [
+        go_package("foo", &[], &[]),
+        go_package("foo/sub", &[], &[]),
+        go_package("bar", &["foo"], &[]),
+        go_package("baz", &["bar", "foo"], &[]),
+        go_package("testutil", &["foo/sub"], &[]),
+        go_package("qux", &[], &["testutil"]),
+        bar_test,
+    ];
+    let packages: Vec<_> = packages.iter().collect();
+    let changed_paths = changed_paths.into_iter().map(PathBuf::from).collect();
+    let expected = expected.map(|expected| {
+        expected
+            .into_iter()
+            .map(|name| format!("example.com/mod/{name}"))
+            .collect()
+    });
+    assert_eq!(find_impacted_packages(&packages, &changed_paths), expected);
+}
+
+#[test]
+fn find_impacted_packages_no_changes() {
+    find_impacted_packages_test([], Some(vec![]));
+}
+
+#[test]
+fn find_impacted_packages_dependents() {
+    find_impacted_packages_test(["/mod/foo/foo.go"], Some(vec!["bar", "baz", "foo"]));
+}
+
+#[test]
+fn find_impacted_packages_test_file() {
+    find_impacted_packages_test(["/mod/baz/baz_test.go"], Some(vec!["baz"]));
+}
+
+#[test]
+fn find_impacted_packages_test_data() {
+    find_impacted_packages_test(["/mod/bar/testdata/input.txt"], Some(vec!["bar", "baz"]));
+}
+
+#[test]
+fn find_impacted_packages_through_test_imports() {
+    find_impacted_packages_test(
+        ["/mod/foo/sub/sub.go"],
+        Some(vec!["bar_test", "foo/sub", "qux", "testutil"]),
+    );
+}
+
+#[test]
+fn find_impacted_packages_go_mod() {
+    find_impacted_packages_test(["/mod/foo/foo.go", "/mod/go.mod"], None);
+}
+
+#[test]
+fn package_directories_uses_import_paths() {
+    let packages = [go_package("foo", &[], &[]), go_package("foo/sub", &[], &[])];
+    assert_eq!(
+        package_directories(&packages),
+        vec![
+            ("example.com/mod/foo".into(), PathBuf::from("/mod/foo")),
+            (
+                "example.com/mod/foo/sub".into(),
+                PathBuf::from("/mod/foo/sub")
+            ),
+        ]
+    );
+}
+
+#[test]
+fn go_build_args_default() {
+    assert_eq!(
+        go_build_args(&GoTestOptions::default()),
+        Vec::<String>::new()
+    );
+}
+
+#[test]
+fn go_build_args_all() {
+    let options = GoTestOptions {
+        vet: Some("off".into()),
+        race: true,
+        cover: true,
+        tags: Some("integration,linux".into()),
+        gcflags: Some("all=-N -l".into()),
+        ldflags: Some("-X main.version=1".into()),
+        ..Default::default()
+    };
+    assert_eq!(
+        go_build_args(&options),
+        vec![
+            "--vet=off",
+            "-race",
+            "-cover",
+            "-tags=integration,linux",
+            "-gcflags=all=-N -l",
+            "-ldflags=-X main.version=1",
+        ]
+    );
+}
+
+#[test]
+fn go_build_args_coverpkg_implies_cover() {
+    let options = GoTestOptions {
+        coverpkg: Some("./...".into()),
+        ..Default::default()
+    };
+    assert_eq!(go_build_args(&options), vec!["-coverpkg=./..."]);
+}
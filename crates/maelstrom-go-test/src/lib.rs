@@ -1,6 +1,7 @@
 pub mod alternative_mains;
 pub mod cli;
 mod config;
+mod coverage;
 mod go_test;
 mod pattern;
 
@@ -10,7 +11,8 @@ pub use maelstrom_test_runner::Logger;
 use anyhow::{Context as _, Result};
 use cli::ExtraCommandLineOptions;
 use config::GoTestOptions;
-use maelstrom_base::{Timeout, Utf8Path, Utf8PathBuf};
+use coverage::Coverage;
+use maelstrom_base::{CapturedFile, Timeout, Utf8Path, Utf8PathBuf};
 use maelstrom_client::{
     shared_library_dependencies_layer_spec,
     spec::{LayerSpec, PathsLayerSpec, PrefixOptions},
@@ -20,9 +22,10 @@ use maelstrom_client::{
 use maelstrom_test_runner::{
     metadata::Metadata,
     run_app_with_ui_multithreaded,
-    ui::{Ui, UiSender},
-    BuildDir, CollectTests, ListAction, LoggingOutput, MainAppDeps, NoCaseMetadata, TestArtifact,
-    TestArtifactKey, TestFilter, TestPackage, TestPackageId, Wait, WaitStatus,
+    ui::{Ui, UiJobStatus, UiSender},
+    BuildDir, CollectTests, ListAction, LoggingOutput, MainAppDeps, NoCaseMetadata, SubtestResult,
+    TestArtifact, TestArtifactKey, TestFilter, TestImpact, TestPackage, TestPackageId, Wait,
+    WaitStatus,
 };
 use maelstrom_util::{
     config::common::{ArtifactTransferStrategy, BrokerAddr, CacheSize, InlineLimit, Slots},
@@ -31,7 +34,13 @@ use maelstrom_util::{
     root::{Root, RootBuf},
     template::TemplateVars,
 };
-use std::{fmt, io, path::Path, str::FromStr};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt, io,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
+};
 
 pub const TEST_METADATA_FILE_NAME: &str = "maelstrom-go-test.toml";
 pub const DEFAULT_TEST_METADATA_CONTENTS: &str = include_str!("default-test-metadata.toml");
@@ -84,12 +93,18 @@ fn create_client(
 
 struct DefaultMainAppDeps {
     test_collector: GoTestCollector,
+    package_directories: Vec<(String, PathBuf)>,
 }
 
 impl DefaultMainAppDeps {
     pub fn new(project_dir: &Root<ProjectDir>, cache_dir: &Root<CacheDir>) -> Result<Self> {
+        // The UI hasn't been started yet, so the output of `go list` is just dropped.
+        let ui = UiSender::new(std::sync::mpsc::channel().0);
+        let packages = go_test::go_list(project_dir.as_ref(), ui.downgrade())
+            .with_context(|| "running go list")?;
         Ok(Self {
             test_collector: GoTestCollector::new(project_dir, cache_dir),
+            package_directories: go_test::package_directories(&packages),
         })
     }
 }
@@ -142,6 +157,7 @@ impl TestFilter for pattern::Pattern {
 struct GoTestCollector {
     project_dir: RootBuf<ProjectDir>,
     cache_dir: RootBuf<CacheDir>,
+    coverage: Coverage,
 }
 
 impl GoTestCollector {
@@ -149,6 +165,7 @@ impl GoTestCollector {
         Self {
             project_dir: project_dir.to_owned(),
             cache_dir: cache_dir.to_owned(),
+            coverage: Coverage::new(project_dir),
         }
     }
 }
@@ -241,13 +258,11 @@ impl TestArtifact for GoTestArtifact {
         &self,
         case_name: &str,
         _case_metadata: &NoCaseMetadata,
-    ) -> (Utf8PathBuf, Vec<String>) {
+    ) -> Result<(Utf8PathBuf, Vec<String>)> {
         let binary_name = self.path().file_name().unwrap().to_str().unwrap();
         let mut args = vec![
             "-test.run".into(),
-            // This argument is a regular expression and we want an exact match for our test
-            // name. We shouldn't have to worry about escaping the test name.
-            format!("^{case_name}$"),
+            test_run_pattern(case_name),
             // We have our own mechanism for timeouts, so we disable the one built into the
             // test binary.
             "-test.timeout=0".into(),
@@ -258,8 +273,11 @@ impl TestArtifact for GoTestArtifact {
             format!("-test.short={}", self.options.short),
             format!("-test.fullpath={}", self.options.fullpath),
         ];
+        if collecting_coverage(&self.options) {
+            args.push(format!("-test.coverprofile={}", coverage::profile_path()));
+        }
         args.extend(self.options.extra_test_binary_args.clone());
-        (format!("/{binary_name}").into(), args)
+        Ok((format!("/{binary_name}").into(), args))
     }
 
     fn format_case(
@@ -274,9 +292,11 @@ impl TestArtifact for GoTestArtifact {
     fn get_test_layers(&self, metadata: &Metadata) -> Vec<LayerSpec> {
         let mut layers = vec![path_layer_for_binary(&self.path)];
 
-        if metadata.include_shared_libraries {
-            // Go binaries usually are statically linked, but on the off-chance they use some OS
-            // library or something, doesn't hurt to check.
+        // Go binaries usually are statically linked, but on the off-chance they use some OS library
+        // or something, doesn't hurt to check. Binaries built with the race detector are always
+        // dynamically linked against the host's C library, which the race runtime needs, so we
+        // include their shared libraries regardless.
+        if metadata.include_shared_libraries || self.options.race {
             layers.push(so_layer_for_binary(&self.path));
         }
 
@@ -325,10 +345,11 @@ impl GoTestCollector {
         {
             lines = lines[(pos + 1)..].to_vec();
         }
-        if let Some(pos) = lines
-            .iter()
-            .rposition(|s| s.starts_with(&format!("--- FAIL: {case_str} ")))
-        {
+        // Subtests' results are indented.
+        if let Some(pos) = lines.iter().rposition(|s| {
+            s.trim_start()
+                .starts_with(&format!("--- FAIL: {case_str} "))
+        }) {
             lines = lines[..pos].to_vec();
         }
         lines
@@ -355,6 +376,16 @@ impl GoTestCollector {
     }
 }
 
+/// Build the `-test.run` argument that selects exactly the given test or subtest. Go splits the
+/// argument on slashes and matches each regular expression against one level of the name.
+fn test_run_pattern(case_name: &str) -> String {
+    case_name
+        .split('/')
+        .map(|part| format!("^{}$", regex::escape(part)))
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
 fn path_layer_for_binary(binary_path: &Utf8Path) -> LayerSpec {
     LayerSpec::Paths(PathsLayerSpec {
         paths: vec![binary_path.to_path_buf()],
@@ -365,6 +396,10 @@ fn path_layer_for_binary(binary_path: &Utf8Path) -> LayerSpec {
     })
 }
 
+fn collecting_coverage(options: &GoTestOptions) -> bool {
+    options.cover || options.coverpkg.is_some()
+}
+
 fn so_layer_for_binary(binary_path: &Utf8Path) -> LayerSpec {
     shared_library_dependencies_layer_spec! {
         [binary_path],
@@ -393,6 +428,7 @@ impl CollectTests for GoTestCollector {
         ui: &UiSender,
     ) -> Result<(go_test::WaitHandle, TestArtifactStream)> {
         let packages = packages.into_iter().map(|m| &m.0).collect();
+        self.coverage.reset();
 
         let build_dir = self.cache_dir.join::<BuildDir>("test-binaries");
         let (wait, stream) =
@@ -408,6 +444,24 @@ impl CollectTests for GoTestCollector {
             .collect())
     }
 
+    fn get_impact(
+        &self,
+        packages: &[GoPackage],
+        changed_paths: &BTreeSet<PathBuf>,
+        _ui: &UiSender,
+    ) -> Result<Option<TestImpact<GoImportPath, GoTestArtifactKey>>> {
+        let packages: Vec<_> = packages.iter().map(|p| &p.0).collect();
+        Ok(
+            go_test::find_impacted_packages(&packages, changed_paths).map(|impacted| {
+                let mut impact = TestImpact::default();
+                for import_path in impacted {
+                    impact.add_package(GoImportPath(import_path));
+                }
+                impact
+            }),
+        )
+    }
+
     fn remove_fixture_output(case_str: &str, lines: Vec<String>) -> Vec<String> {
         if case_str.starts_with("Fuzz") {
             Self::remove_fixture_output_fuzz(case_str, lines)
@@ -420,14 +474,73 @@ impl CollectTests for GoTestCollector {
 
     fn was_test_ignored(case_str: &str, lines: &[String]) -> bool {
         if let Some(last) = lines.iter().rposition(|s| !s.is_empty()) {
-            if last == 0 {
-                return false;
-            }
-            lines[last - 1].starts_with(&format!("--- SKIP: {case_str} ")) && lines[last] == "PASS"
+            // When a subtest is skipped, the results of its parents come between its result and
+            // the final "PASS".
+            lines[last] == "PASS"
+                && lines[..last]
+                    .iter()
+                    .rev()
+                    .map(|s| s.trim_start())
+                    .take_while(|s| s.starts_with("--- "))
+                    .any(|s| s.starts_with(&format!("--- SKIP: {case_str} ")))
         } else {
             false
         }
     }
+
+    fn get_subtest_results(case_name: &str, lines: &[String]) -> Vec<SubtestResult> {
+        let prefix = format!("{case_name}/");
+        let mut results: Vec<_> = lines
+            .iter()
+            .filter_map(|line| {
+                let (status, rest) = line.trim_start().strip_prefix("--- ")?.split_once(": ")?;
+                let status = match status {
+                    "PASS" => UiJobStatus::Ok,
+                    "FAIL" => UiJobStatus::Failure(None),
+                    "SKIP" => UiJobStatus::Ignored,
+                    _ => return None,
+                };
+                let (name, duration) = rest.split_once(' ')?;
+                if !name.starts_with(&prefix) {
+                    return None;
+                }
+                let (parent, _) = name.rsplit_once('/')?;
+                let duration = duration.strip_prefix('(')?.strip_suffix("s)")?;
+                let duration = Duration::try_from_secs_f64(duration.parse().ok()?).ok()?;
+                Some(SubtestResult {
+                    name: name.into(),
+                    parent: parent.into(),
+                    status,
+                    duration,
+                })
+            })
+            .collect();
+        // Go prints a subtest's result after the results of its own subtests, but we need parents
+        // to come first. Sorting by name does that.
+        results.sort_by(|a, b| a.name.cmp(&b.name));
+        results
+    }
+
+    fn fan_out_subtests(options: &GoTestOptions) -> bool {
+        options.fan_out_subtests
+    }
+
+    fn coverage_environment(options: &GoTestOptions) -> Option<BTreeMap<String, String>> {
+        // The profile's location is given to the test binary as a flag, so no environment
+        // variables are needed.
+        collecting_coverage(options).then(BTreeMap::new)
+    }
+
+    fn save_coverage_data(&self, files: Vec<CapturedFile>) -> Result<()> {
+        self.coverage.save(files)
+    }
+
+    fn report_coverage(&self, options: &GoTestOptions, ui: &UiSender) -> Result<()> {
+        if collecting_coverage(options) {
+            self.coverage.report(ui)?;
+        }
+        Ok(())
+    }
 }
 
 #[test]
@@ -500,6 +613,105 @@ fn test_skip_output_different_case_str() {
     assert!(!ignored);
 }
 
+#[test]
+fn test_skipped_subtest() {
+    let example = indoc::indoc! {"
+    === RUN   TestAdd
+    === RUN   TestAdd/small
+        foo_test.go:11: HELLO
+        --- SKIP: TestAdd/small (0.00s)
+    --- PASS: TestAdd (0.00s)
+    PASS
+    "};
+    let lines = example
+        .split('\n')
+        .map(ToOwned::to_owned)
+        .collect::<Vec<_>>();
+    assert!(GoTestCollector::was_test_ignored("TestAdd/small", &lines));
+    assert!(!GoTestCollector::was_test_ignored("TestAdd", &lines));
+}
+
+#[test]
+fn subtest_results() {
+    let example = indoc::indoc! {"
+    === RUN   TestAdd
+    === RUN   TestAdd/small
+    === RUN   TestAdd/small/zero
+    === RUN   TestAdd/small/one
+        foo_test.go:9: 1 + 1 != 3
+        --- FAIL: TestAdd/small/one (0.10s)
+        --- PASS: TestAdd/small/zero (0.00s)
+        --- FAIL: TestAdd/small (0.10s)
+    === RUN   TestAdd/large
+        --- SKIP: TestAdd/large (1.50s)
+    --- PASS: TestAdd2/other (0.00s)
+    --- FAIL: TestAdd (1.60s)
+    FAIL
+    "};
+    let lines = example
+        .split('\n')
+        .map(ToOwned::to_owned)
+        .collect::<Vec<_>>();
+    let result = |name: &str, parent: &str, status, millis| SubtestResult {
+        name: name.into(),
+        parent: parent.into(),
+        status,
+        duration: Duration::from_millis(millis),
+    };
+    assert_eq!(
+        GoTestCollector::get_subtest_results("TestAdd", &lines),
+        vec![
+            result("TestAdd/large", "TestAdd", UiJobStatus::Ignored, 1500),
+            result("TestAdd/small", "TestAdd", UiJobStatus::Failure(None), 100),
+            result(
+                "TestAdd/small/one",
+                "TestAdd/small",
+                UiJobStatus::Failure(None),
+                100
+            ),
+            result("TestAdd/small/zero", "TestAdd/small", UiJobStatus::Ok, 0),
+        ]
+    );
+    assert_eq!(
+        GoTestCollector::get_subtest_results("TestAdd/small", &lines),
+        vec![
+            result(
+                "TestAdd/small/one",
+                "TestAdd/small",
+                UiJobStatus::Failure(None),
+                100
+            ),
+            result("TestAdd/small/zero", "TestAdd/small", UiJobStatus::Ok, 0),
+        ]
+    );
+}
+
+#[test]
+fn test_run_pattern_escapes_each_level() {
+    assert_eq!(test_run_pattern("TestAdd"), "^TestAdd$");
+    assert_eq!(
+        test_run_pattern("TestAdd/1+1=2/(a|b)"),
+        r"^TestAdd$/^1\+1=2$/^\(a\|b\)$"
+    );
+}
+
+#[test]
+fn remove_fixture_output_subtest() {
+    let example = indoc::indoc! {"
+    === RUN   TestAdd
+    === RUN   TestAdd/small
+        foo_test.go:9: 1 + 2 != 3
+        --- FAIL: TestAdd/small (0.00s)
+    --- FAIL: TestAdd (0.00s)
+    FAIL
+    "};
+    let cleansed = GoTestCollector::remove_fixture_output(
+        "TestAdd/small",
+        example.split('\n').map(ToOwned::to_owned).collect(),
+    );
+    assert_eq!(cleansed, vec!["    foo_test.go:9: 1 + 2 != 3"]);
+}
+
 #[test]
 fn remove_fixture_output_basic_case() {
     let example = indoc::indoc! {"
@@ -679,6 +891,10 @@ impl MainAppDeps for DefaultMainAppDeps {
         Ok(TemplateVars::default())
     }
 
+    fn get_package_metadata_directories(&self) -> Vec<(String, PathBuf)> {
+        self.package_directories.clone()
+    }
+
     const TEST_METADATA_FILE_NAME: &'static str = TEST_METADATA_FILE_NAME;
     const DEFAULT_TEST_METADATA_CONTENTS: &'static str = DEFAULT_TEST_METADATA_CONTENTS;
 }
@@ -728,7 +944,7 @@ pub fn main_with_stderr_and_project_dir(
     let log = logger.build(logging_output.clone());
 
     if extra_options.list.packages {
-        let (ui_handle, ui) = ui.start_ui_thread(logging_output, log);
+        let (ui_handle, ui, _) = ui.start_ui_thread(logging_output, log);
 
         let list_res = alternative_mains::list_packages(
             ui,
@@ -743,6 +959,7 @@ pub fn main_with_stderr_and_project_dir(
         Ok(exit_code)
     } else {
         let list_action = extra_options.list.tests.then_some(ListAction::ListTests);
+        let export_image = extra_options.parent.export_image();
 
         let client = create_client(
             bg_proc,
@@ -768,6 +985,7 @@ pub fn main_with_stderr_and_project_dir(
             extra_options.parent.include,
             extra_options.parent.exclude,
             list_action,
+            export_image,
             config.parent.repeat,
             config.parent.stop_after,
             extra_options.parent.watch,
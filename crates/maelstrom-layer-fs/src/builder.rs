@@ -6,20 +6,23 @@ use crate::ty::{
 };
 use crate::{BlobDir, LayerFs};
 use anyhow::bail;
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, Context as _, Result};
 use anyhow_trace::anyhow_trace;
 use futures::stream::{Peekable, StreamExt as _};
 use lru::LruCache;
 use maelstrom_base::{
-    manifest::{ManifestEntryData, ManifestFileData, Mode, UnixTimestamp},
-    Sha256Digest, Utf8Component, Utf8Path,
+    manifest::{ExtendedAttributes, ManifestEntryData, ManifestFileData, Mode, UnixTimestamp},
+    GroupId, Sha256Digest, UserId, Utf8Component, Utf8Path,
 };
 use maelstrom_util::{async_fs::Fs, ext::BoolExt as _, manifest::AsyncManifestReader, root::Root};
 use std::cmp::Ordering;
 use std::path::Path;
 use std::pin::Pin;
-use tokio::io::{AsyncRead, AsyncSeek};
-use tokio_tar::{Archive, EntryType};
+use tokio::io::{AsyncRead, AsyncReadExt as _, AsyncSeek};
+use tokio_tar::{Archive, Entry, EntryType, PaxExtension};
+
+/// The prefix used for extended attributes in PAX extension records.
+const PAX_XATTR_PREFIX: &str = "SCHILY.xattr.";
 
 pub const DIRECTORY_DATA_WRITER_CACHE_SIZE: usize = 20;
 
@@ -103,7 +106,9 @@ impl<'fs> BottomLayerBuilder<'fs> {
                     size: 0,
                     mode: Mode(0o777),
                     mtime: time,
+                    owner: None,
                 },
+                &ExtendedAttributes::new(),
                 FileDataInput::Empty,
             )
             .await?;
@@ -147,6 +152,16 @@ impl<'fs> BottomLayerBuilder<'fs> {
         dir_reader.look_up_entry(name).await
     }
 
+    /// Attributes for entries we create which don't have any attributes specified by the input.
+    fn default_attrs(&self) -> FileAttributes {
+        FileAttributes {
+            size: 0,
+            mode: Mode(0o777),
+            mtime: self.time,
+            owner: None,
+        }
+    }
+
     async fn ensure_path(&mut self, path: &Utf8Path) -> Result<FileId> {
         let comp_iter = path.components();
 
@@ -162,12 +177,9 @@ impl<'fs> BottomLayerBuilder<'fs> {
                 Some(new_dir_id) => dir_id = new_dir_id,
                 None => {
                     dir_id = {
-                        let attrs = FileAttributes {
-                            size: 0,
-                            mode: Mode(0o777),
-                            mtime: self.time,
-                        };
-                        self.add_dir(dir_id, comp, attrs).await?
+                        let attrs = self.default_attrs();
+                        self.add_dir(dir_id, comp, attrs, &ExtendedAttributes::new())
+                            .await?
                     }
                 }
             }
@@ -180,10 +192,11 @@ impl<'fs> BottomLayerBuilder<'fs> {
         parent: FileId,
         name: &str,
         attrs: FileAttributes,
+        xattrs: &ExtendedAttributes,
     ) -> Result<FileId> {
         let file_id = self
             .file_writer
-            .insert_file(FileType::Directory, attrs, FileDataInput::Empty)
+            .insert_file(FileType::Directory, attrs, xattrs, FileDataInput::Empty)
             .await?;
         self.add_link(parent, name, file_id, FileType::Directory)
             .await?
@@ -236,11 +249,12 @@ impl<'fs> BottomLayerBuilder<'fs> {
         &mut self,
         path: &Utf8Path,
         attrs: FileAttributes,
+        xattrs: &ExtendedAttributes,
         data: FileDataInput<'_>,
     ) -> Result<FileId> {
         let file_id = self
             .file_writer
-            .insert_file(FileType::RegularFile, attrs, data)
+            .insert_file(FileType::RegularFile, attrs, xattrs, data)
             .await?;
 
         let parent_id = if let Some(parent) = path.parent() {
@@ -280,13 +294,23 @@ impl<'fs> BottomLayerBuilder<'fs> {
     }
 
     /// Set the attributes for the given existing file in the new layer.
-    pub async fn set_attr(&mut self, id: FileId, attrs: FileAttributes) -> Result<()> {
-        self.file_writer.update_attributes(id, attrs).await
+    pub async fn set_attr(
+        &mut self,
+        id: FileId,
+        attrs: FileAttributes,
+        xattrs: &ExtendedAttributes,
+    ) -> Result<()> {
+        self.file_writer.update_attributes(id, attrs, xattrs).await
     }
 
     /// Add a directory at the given path in the new layer. Creates any intermediate directories
     /// that don't exist.
-    pub async fn add_dir_path(&mut self, path: &Utf8Path, attrs: FileAttributes) -> Result<FileId> {
+    pub async fn add_dir_path(
+        &mut self,
+        path: &Utf8Path,
+        attrs: FileAttributes,
+        xattrs: &ExtendedAttributes,
+    ) -> Result<FileId> {
         let parent_id = if let Some(parent) = path.parent() {
             self.ensure_path(parent).await?
         } else {
@@ -294,14 +318,14 @@ impl<'fs> BottomLayerBuilder<'fs> {
         };
         if let Some(name) = path.file_name() {
             if let Some(existing) = self.look_up(parent_id, name).await? {
-                self.set_attr(existing, attrs).await?;
+                self.set_attr(existing, attrs, xattrs).await?;
                 Ok(existing)
             } else {
-                self.add_dir(parent_id, name, attrs).await
+                self.add_dir(parent_id, name, attrs, xattrs).await
             }
         } else {
             let file_id = FileId::root(LayerId::BOTTOM);
-            self.set_attr(file_id, attrs).await?;
+            self.set_attr(file_id, attrs, xattrs).await?;
             Ok(file_id)
         }
     }
@@ -321,12 +345,9 @@ impl<'fs> BottomLayerBuilder<'fs> {
             .ok_or_else(|| anyhow!("missing file name"))?;
 
         if self.look_up(parent_id, name).await?.is_none() {
-            let attrs = FileAttributes {
-                size: 0,
-                mode: Mode(0o777),
-                mtime: self.time,
-            };
-            self.add_dir(parent_id, name, attrs).await?;
+            let attrs = self.default_attrs();
+            self.add_dir(parent_id, name, attrs, &ExtendedAttributes::new())
+                .await?;
         }
 
         self.set_opaque_dir(parent_id, name, true).await?;
@@ -336,21 +357,28 @@ impl<'fs> BottomLayerBuilder<'fs> {
 
     /// Add a symlink at the given path in the new layer. Creates any intermediate directories
     /// that don't exist.
+    ///
+    /// Only the ownership and extended attributes are taken from `attrs`, symlinks always have
+    /// mode `0o777` and the layer's timestamp.
     pub async fn add_symlink_path(
         &mut self,
         path: &Utf8Path,
+        attrs: FileAttributes,
+        xattrs: &ExtendedAttributes,
         target: impl AsRef<[u8]>,
     ) -> Result<FileId> {
         let attrs = FileAttributes {
             size: 0,
             mode: Mode(0o777),
             mtime: self.time,
+            ..attrs
         };
         let file_id = self
             .file_writer
             .insert_file(
                 FileType::Symlink,
                 attrs,
+                xattrs,
                 FileDataInput::Inline(target.as_ref()),
             )
             .await?;
@@ -420,16 +448,48 @@ impl<'fs> BottomLayerBuilder<'fs> {
         tar_stream: impl AsyncRead + Unpin,
     ) -> Result<()> {
         let mut ar = Archive::new(tar_stream);
-        let mut entries = ar.entries()?;
+        let mut entries = ar.entries_raw()?;
+        let mut extensions = TarExtensions::default();
         while let Some(entry) = entries.next().await {
-            let entry = entry?;
+            let mut entry = entry?;
+            if extensions.add(&mut entry).await? {
+                continue;
+            }
+            let TarExtensions {
+                path: extension_path,
+                link_name: extension_link_name,
+                uid,
+                gid,
+                size,
+                mtime,
+                xattrs,
+            } = std::mem::take(&mut extensions);
+            let xattrs = &xattrs;
             let header = entry.header();
-            let entry_path = entry.path()?;
-            let utf8_path: &Utf8Path = entry_path
-                .to_str()
-                .ok_or_else(|| anyhow!("non-UTF8 path in tar"))?
+            // Some tar writers leave the owner fields blank. Files from those entries are owned
+            // by the job's user and group, the same as files without a stored owner.
+            let owner = || {
+                let uid = uid.or_else(|| header.uid().ok());
+                let gid = gid.or_else(|| header.gid().ok());
+                let uid = uid.and_then(|uid| uid.try_into().ok()).map(UserId::new);
+                let gid = gid.and_then(|gid| gid.try_into().ok()).map(GroupId::new);
+                uid.zip(gid)
+            };
+            let attrs = || -> Result<_> {
+                Ok(FileAttributes {
+                    size: size.map_or_else(|| header.size(), Ok)?,
+                    mode: Mode(header.mode()?),
+                    mtime: UnixTimestamp(mtime.map_or_else(|| header.mtime(), Ok)?.try_into()?),
+                    owner: owner(),
+                })
+            };
+            let path_bytes = extension_path.unwrap_or_else(|| header.path_bytes().into());
+            let utf8_path: &Utf8Path = std::str::from_utf8(&path_bytes)
+                .map_err(|_| anyhow!("non-UTF8 path in tar"))?
                 .as_ref();
             let path = Utf8Path::new("/").join(utf8_path);
+            let link_name =
+                extension_link_name.or_else(|| header.link_name_bytes().map(Into::into));
             match header.entry_type() {
                 EntryType::Regular => {
                     let file_name = path.file_name().unwrap();
@@ -448,44 +508,35 @@ impl<'fs> BottomLayerBuilder<'fs> {
 
                     self.add_file_path(
                         &path,
-                        FileAttributes {
-                            size: header.size()?,
-                            mode: Mode(header.mode()?),
-                            mtime: UnixTimestamp(header.mtime()?.try_into()?),
-                        },
+                        attrs()?,
+                        xattrs,
                         FileDataInput::Digest {
                             digest: digest.clone(),
                             offset: entry.raw_file_position(),
-                            length: header.entry_size()?,
+                            length: size.map_or_else(|| header.entry_size(), Ok)?,
                         },
                     )
                     .await?;
                 }
                 EntryType::Directory => {
-                    self.add_dir_path(
-                        &path,
-                        FileAttributes {
-                            size: header.size()?,
-                            mode: Mode(header.mode()?),
-                            mtime: UnixTimestamp(header.mtime()?.try_into()?),
-                        },
-                    )
-                    .await?;
+                    self.add_dir_path(&path, attrs()?, xattrs).await?;
                 }
                 EntryType::Symlink => {
                     self.add_symlink_path(
                         &path,
-                        header.link_name_bytes().expect("empty symlink in tar"),
+                        FileAttributes {
+                            owner: owner(),
+                            ..self.default_attrs()
+                        },
+                        xattrs,
+                        link_name.expect("empty symlink in tar"),
                     )
                     .await?;
                 }
                 EntryType::Link => {
                     self.add_link_path(
                         &path,
-                        std::str::from_utf8(
-                            &header.link_name_bytes().expect("empty symlink in tar"),
-                        )?
-                        .into(),
+                        std::str::from_utf8(&link_name.expect("empty symlink in tar"))?.into(),
                     )
                     .await?;
                 }
@@ -511,11 +562,13 @@ impl<'fs> BottomLayerBuilder<'fs> {
                 size: entry.metadata.size,
                 mode: entry.metadata.mode,
                 mtime: entry.metadata.mtime,
+                owner: entry.metadata.owner,
             };
+            let xattrs = &entry.metadata.xattrs;
             let path = Utf8Path::new("/").join(&entry.path);
             match entry.data {
                 ManifestEntryData::Directory { opaque } => {
-                    self.add_dir_path(&path, attrs).await?;
+                    self.add_dir_path(&path, attrs, xattrs).await?;
                     if opaque {
                         self.set_opaque_dir_path(&path).await?;
                     }
@@ -530,10 +583,10 @@ impl<'fs> BottomLayerBuilder<'fs> {
                         ManifestFileData::Inline(data) => FileDataInput::Inline(data),
                         ManifestFileData::Empty => FileDataInput::Empty,
                     };
-                    self.add_file_path(&path, attrs, data).await?;
+                    self.add_file_path(&path, attrs, xattrs, data).await?;
                 }
                 ManifestEntryData::Symlink(data) => {
-                    self.add_symlink_path(&path, data).await?;
+                    self.add_symlink_path(&path, attrs, xattrs, data).await?;
                 }
                 ManifestEntryData::Hardlink(target) => {
                     self.add_link_path(&path, &target).await?;
@@ -557,6 +610,83 @@ impl<'fs> BottomLayerBuilder<'fs> {
     }
 }
 
+/// The information from GNU and PAX extension entries which applies to the entry following them.
+///
+/// We read these entries ourselves instead of letting `tokio_tar` do it. It drops them if reading
+/// their contents doesn't complete on the first poll, and it ignores most PAX records anyway.
+#[derive(Default)]
+struct TarExtensions {
+    path: Option<Vec<u8>>,
+    link_name: Option<Vec<u8>>,
+    uid: Option<u64>,
+    gid: Option<u64>,
+    size: Option<u64>,
+    mtime: Option<u64>,
+    xattrs: ExtendedAttributes,
+}
+
+impl TarExtensions {
+    /// If the given entry is an extension entry, record what it contains and return true.
+    async fn add(&mut self, entry: &mut Entry<Archive<impl AsyncRead + Unpin>>) -> Result<bool> {
+        match entry.header().entry_type() {
+            EntryType::GNULongName => {
+                self.path = Some(read_gnu_long_name(entry).await?);
+            }
+            EntryType::GNULongLink => {
+                self.link_name = Some(read_gnu_long_name(entry).await?);
+            }
+            EntryType::XHeader => {
+                for extension in entry.pax_extensions().await?.into_iter().flatten() {
+                    self.add_pax_extension(extension?)?;
+                }
+            }
+            EntryType::XGlobalHeader => {}
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    fn add_pax_extension(&mut self, extension: PaxExtension<'_>) -> Result<()> {
+        let key = extension
+            .key()
+            .map_err(|_| anyhow!("non-UTF8 PAX extension key in tar"))?;
+        let number = || -> Result<u64> {
+            let value = extension
+                .value()
+                .map_err(|_| anyhow!("non-UTF8 PAX extension {key:?} in tar"))?;
+            // Times can have a fractional part, which we drop.
+            let value = value.split_once('.').map_or(value, |(whole, _)| whole);
+            value
+                .parse()
+                .with_context(|| format!("invalid PAX extension {key:?} in tar"))
+        };
+        match key {
+            "path" => self.path = Some(extension.value_bytes().into()),
+            "linkpath" => self.link_name = Some(extension.value_bytes().into()),
+            "uid" => self.uid = Some(number()?),
+            "gid" => self.gid = Some(number()?),
+            "size" => self.size = Some(number()?),
+            "mtime" => self.mtime = Some(number()?),
+            _ => {
+                if let Some(name) = key.strip_prefix(PAX_XATTR_PREFIX) {
+                    self.xattrs
+                        .insert(name.into(), extension.value_bytes().into());
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+async fn read_gnu_long_name(entry: &mut (impl AsyncRead + Unpin)) -> Result<Vec<u8>> {
+    let mut name = vec![];
+    entry.read_to_end(&mut name).await?;
+    if name.last() == Some(&0) {
+        name.pop();
+    }
+    Ok(name)
+}
+
 /// Walks the `right_fs` and yields together with it any matching entries from `left_fs`
 pub struct DoubleFsWalk<'fs> {
     streams: Vec<(Option<WalkStream>, WalkStream)>,
@@ -1,11 +1,12 @@
 use crate::ty::{
-    decode_with_rich_error, encode_with_rich_error, AttributesId, FileAttributes, FileData, FileId,
-    FileTableEntry, FileType, LayerFsVersion, LayerId,
+    decode_with_rich_error, encode_with_rich_error, AttributesId, AttributesTableEntry,
+    ExtendedAttributesData, FileAttributes, FileData, FileId, FileTableEntry, FileType,
+    LayerFsVersion, LayerId,
 };
 use crate::LayerFs;
 use anyhow::Result;
 use anyhow_trace::anyhow_trace;
-use maelstrom_base::Sha256Digest;
+use maelstrom_base::{manifest::ExtendedAttributes, proto, Sha256Digest};
 use maelstrom_util::async_fs::{File, Fs};
 use maelstrom_util::io::BufferedStream;
 use serde::{Deserialize, Serialize};
@@ -61,7 +62,10 @@ impl FileMetadataReader {
         })
     }
 
-    pub async fn get_attr(&mut self, id: FileId) -> Result<(FileType, FileAttributes)> {
+    async fn get_attributes_table_entry(
+        &mut self,
+        id: FileId,
+    ) -> Result<(FileType, AttributesTableEntry)> {
         assert_eq!(id.layer(), self.layer_id);
 
         self.file_table
@@ -74,11 +78,23 @@ impl FileMetadataReader {
                 self.attr_table_start + entry.attr_id.offset() - 1,
             ))
             .await?;
-        let attrs: FileAttributes = decode_with_rich_error(&mut self.attr_table).await?;
+        let attrs: AttributesTableEntry = decode_with_rich_error(&mut self.attr_table).await?;
 
         Ok((entry.kind, attrs))
     }
 
+    pub async fn get_attr(&mut self, id: FileId) -> Result<(FileType, FileAttributes)> {
+        let (kind, entry) = self.get_attributes_table_entry(id).await?;
+        Ok((kind, entry.attrs))
+    }
+
+    /// Returns where the file's extended attributes are stored in the layer's inline data. Use
+    /// [`decode_extended_attributes`] to decode them once read.
+    pub async fn get_xattrs(&mut self, id: FileId) -> Result<ExtendedAttributesData> {
+        let (_, entry) = self.get_attributes_table_entry(id).await?;
+        Ok(entry.xattrs)
+    }
+
     pub async fn get_data(&mut self, id: FileId) -> Result<(FileType, FileData)> {
         assert_eq!(id.layer(), self.layer_id);
 
@@ -91,6 +107,14 @@ impl FileMetadataReader {
     }
 }
 
+/// Decode extended attributes read from the location given by [`FileMetadataReader::get_xattrs`].
+pub fn decode_extended_attributes(data: &[u8]) -> Result<ExtendedAttributes> {
+    if data.is_empty() {
+        return Ok(ExtendedAttributes::new());
+    }
+    Ok(proto::fixint_deserialize(data)?)
+}
+
 #[derive(Copy, Clone, Default, Debug, Deserialize, Serialize)]
 pub struct FileTableHeader {
     pub version: LayerFsVersion,
@@ -165,17 +189,44 @@ impl FileMetadataWriter {
         })
     }
 
+    async fn write_inline_data(&mut self, data: &[u8]) -> Result<u64> {
+        let offset = self.inline_data.stream_position().await?;
+        self.inline_data.write_all(data).await?;
+        Ok(offset)
+    }
+
+    async fn write_xattrs(
+        &mut self,
+        xattrs: &ExtendedAttributes,
+    ) -> Result<ExtendedAttributesData> {
+        if xattrs.is_empty() {
+            return Ok(ExtendedAttributesData::default());
+        }
+        let encoded = proto::fixint_serialize(xattrs)?;
+        let offset = self.write_inline_data(&encoded).await?;
+        Ok(ExtendedAttributesData {
+            offset,
+            length: encoded.len() as u64,
+        })
+    }
+
     pub async fn insert_file(
         &mut self,
         kind: FileType,
         attrs: FileAttributes,
+        xattrs: &ExtendedAttributes,
         data: FileDataInput<'_>,
     ) -> Result<FileId> {
+        let xattrs = self.write_xattrs(xattrs).await?;
         let attr_id = AttributesId::try_from(
             self.attr_table.stream_position().await? - self.attr_table_start + 1,
         )
         .unwrap();
-        encode_with_rich_error(&mut self.attr_table, &attrs).await?;
+        encode_with_rich_error(
+            &mut self.attr_table,
+            &AttributesTableEntry { attrs, xattrs },
+        )
+        .await?;
 
         let data = match data {
             FileDataInput::Empty => FileData::Empty,
@@ -188,14 +239,10 @@ impl FileMetadataWriter {
                 offset,
                 length,
             },
-            FileDataInput::Inline(data) => {
-                let offset = self.inline_data.stream_position().await?;
-                self.inline_data.write_all(data).await?;
-                FileData::Inline {
-                    offset,
-                    length: data.len() as u64,
-                }
-            }
+            FileDataInput::Inline(data) => FileData::Inline {
+                offset: self.write_inline_data(data).await?,
+                length: data.len() as u64,
+            },
         };
 
         let entry = FileTableEntry {
@@ -213,7 +260,13 @@ impl FileMetadataWriter {
         Ok(file_id)
     }
 
-    pub async fn update_attributes(&mut self, id: FileId, attrs: FileAttributes) -> Result<()> {
+    pub async fn update_attributes(
+        &mut self,
+        id: FileId,
+        attrs: FileAttributes,
+        xattrs: &ExtendedAttributes,
+    ) -> Result<()> {
+        let xattrs = self.write_xattrs(xattrs).await?;
         let old_file_table_pos = self.file_table.stream_position().await?;
         let old_attr_table_pos = self.attr_table.stream_position().await?;
 
@@ -233,7 +286,11 @@ impl FileMetadataWriter {
             old_len
         };
 
-        encode_with_rich_error(&mut self.attr_table, &attrs).await?;
+        encode_with_rich_error(
+            &mut self.attr_table,
+            &AttributesTableEntry { attrs, xattrs },
+        )
+        .await?;
 
         #[cfg(debug_assertions)]
         {
@@ -11,6 +11,7 @@
 //! - `super.bin` contains information about the layer including any layers it is stacked on top of
 //! - `file_table.bin` contains a listing of all the files in the layer
 //! - `attributes_table.bin` contains the attributes for all the files in the layer
+//! - `inline_data.bin` contains small file contents and the encoded extended attributes of files
 //! - `<offset>.dir_data.bin` contains directory contents for the directory found at `<offset>` in
 //!    the file table.
 //!
@@ -112,10 +113,11 @@ use anyhow::{anyhow, Result};
 use anyhow_trace::anyhow_trace;
 use futures::stream::StreamExt as _;
 use lru::LruCache;
+use maelstrom_base::manifest::ExtendedAttributes;
 use maelstrom_base::Sha256Digest;
 use maelstrom_fuse::{
     AttrResponse, EntryResponse, ErrnoResult, FileAttr, FuseFileSystem, ReadLinkResponse,
-    ReadResponse, Request,
+    ReadResponse, Request, XattrResponse,
 };
 use maelstrom_linux::Errno;
 use maelstrom_util::{
@@ -124,6 +126,7 @@ use maelstrom_util::{
 };
 use std::{
     ffi::OsStr,
+    os::unix::fs::FileExt as _,
     path::{Path, PathBuf},
     pin::Pin,
     sync::Arc,
@@ -446,6 +449,36 @@ impl LayerFsFuseAdapter {
             length: read_length as usize,
         })
     }
+
+    async fn get_xattrs(&self, ino: u64) -> ErrnoResult<ExtendedAttributes> {
+        let file = to_einval(self.log.clone(), FileId::try_from(ino))?;
+        let reader = to_eio(
+            self.log.clone(),
+            self.cache
+                .lock()
+                .await
+                .files(&self.layer_fs, file.layer())
+                .await,
+        )?;
+        let data = to_eio(self.log.clone(), reader.lock().await.get_xattrs(file).await)?;
+        if data.length == 0 {
+            return Ok(ExtendedAttributes::new());
+        }
+        let inline_data = to_eio(
+            self.log.clone(),
+            self.cache
+                .lock()
+                .await
+                .inline_data(&self.layer_fs, file.layer())
+                .await,
+        )?;
+        let mut buffer = vec![0; data.length as usize];
+        to_eio(
+            self.log.clone(),
+            inline_data.read_exact_at(&mut buffer, data.offset),
+        )?;
+        to_eio(self.log.clone(), file::decode_extended_attributes(&buffer))
+    }
 }
 
 impl FuseFileSystem for LayerFsFuseAdapter {
@@ -481,6 +514,9 @@ impl FuseFileSystem for LayerFsFuseAdapter {
                 .await,
         )?;
         let (kind, attrs) = to_eio(self.log.clone(), reader.lock().await.get_attr(file).await)?;
+        let (uid, gid) = attrs
+            .owner
+            .map_or((req.uid, req.gid), |(uid, gid)| (uid.into(), gid.into()));
         Ok(AttrResponse {
             ttl: TTL,
             attr: FileAttr {
@@ -493,8 +529,8 @@ impl FuseFileSystem for LayerFsFuseAdapter {
                 kind,
                 perm: u32::from(attrs.mode) as u16,
                 nlink: 1,
-                uid: req.uid,
-                gid: req.gid,
+                uid,
+                gid,
                 rdev: 0,
                 blksize: 512,
             },
@@ -633,6 +669,22 @@ impl FuseFileSystem for LayerFsFuseAdapter {
         }
     }
 
+    async fn get_xattr(&self, _req: Request, ino: u64, name: &OsStr) -> ErrnoResult<XattrResponse> {
+        let name = name.to_str().ok_or(Errno::ENODATA)?;
+        let mut xattrs = self.get_xattrs(ino).await?;
+        let data = xattrs.remove(name).ok_or(Errno::ENODATA)?;
+        Ok(XattrResponse { data })
+    }
+
+    async fn list_xattr(&self, _req: Request, ino: u64) -> ErrnoResult<XattrResponse> {
+        let mut data = vec![];
+        for name in self.get_xattrs(ino).await?.into_keys() {
+            data.extend(name.into_bytes());
+            data.push(0);
+        }
+        Ok(XattrResponse { data })
+    }
+
     async fn access(&self, _req: Request, _ino: u64, _mask: i32) -> ErrnoResult<()> {
         Ok(())
     }
@@ -647,7 +699,7 @@ mod tests {
         manifest::{
             ManifestEntry, ManifestEntryData, ManifestEntryMetadata, ManifestFileData, Mode,
         },
-        Utf8PathBuf,
+        GroupId, UserId, Utf8PathBuf,
     };
     use maelstrom_util::manifest::AsyncManifestWriter;
     use std::collections::HashMap;
@@ -677,9 +729,30 @@ mod tests {
     struct BuildEntry {
         path: String,
         data: BuildEntryData,
+        owner: Option<(u32, u32)>,
+        xattrs: ExtendedAttributes,
     }
 
     impl BuildEntry {
+        fn new(path: impl Into<String>, data: BuildEntryData) -> Self {
+            Self {
+                path: path.into(),
+                data,
+                owner: None,
+                xattrs: ExtendedAttributes::new(),
+            }
+        }
+
+        fn owner(mut self, uid: u32, gid: u32) -> Self {
+            self.owner = Some((uid, gid));
+            self
+        }
+
+        fn xattr(mut self, name: &str, value: &[u8]) -> Self {
+            self.xattrs.insert(name.into(), value.into());
+            self
+        }
+
         fn reg(path: impl Into<String>, data: &'static [u8]) -> Self {
             Self::reg_mode(path, FileDataInput::Inline(data), 0o555)
         }
@@ -706,27 +779,27 @@ mod tests {
         }
 
         fn reg_empty_mode(path: impl Into<String>, mode: u32) -> Self {
-            Self {
-                path: path.into(),
-                data: BuildEntryData::Regular {
+            Self::new(
+                path,
+                BuildEntryData::Regular {
                     type_: FileType::RegularFile,
                     data: FileDataInput::Empty,
                     mode,
                     opaque_dir: false,
                 },
-            }
+            )
         }
 
         fn reg_mode(path: impl Into<String>, data: FileDataInput<'static>, mode: u32) -> Self {
-            Self {
-                path: path.into(),
-                data: BuildEntryData::Regular {
+            Self::new(
+                path,
+                BuildEntryData::Regular {
                     type_: FileType::RegularFile,
                     data,
                     mode,
                     opaque_dir: false,
                 },
-            }
+            )
         }
 
         fn dir(path: impl Into<String>) -> Self {
@@ -738,42 +811,39 @@ mod tests {
         }
 
         fn dir_args(path: impl Into<String>, mode: u32, opaque: bool) -> Self {
-            Self {
-                path: path.into(),
-                data: BuildEntryData::Regular {
+            Self::new(
+                path,
+                BuildEntryData::Regular {
                     type_: FileType::Directory,
                     data: FileDataInput::Empty,
                     mode,
                     opaque_dir: opaque,
                 },
-            }
+            )
         }
 
         fn sym(path: impl Into<String>, target: impl Into<String>) -> Self {
-            Self {
-                path: path.into(),
-                data: BuildEntryData::Link {
+            Self::new(
+                path,
+                BuildEntryData::Link {
                     target: target.into(),
                     hard: false,
                 },
-            }
+            )
         }
 
         fn link(path: impl Into<String>, target: impl Into<String>) -> Self {
-            Self {
-                path: path.into(),
-                data: BuildEntryData::Link {
+            Self::new(
+                path,
+                BuildEntryData::Link {
                     target: target.into(),
                     hard: true,
                 },
-            }
+            )
         }
 
         fn whiteout(path: impl Into<String>) -> Self {
-            Self {
-                path: path.into(),
-                data: BuildEntryData::Whiteout,
-            }
+            Self::new(path, BuildEntryData::Whiteout)
         }
 
         fn from_str(s: &str) -> Self {
@@ -820,6 +890,7 @@ mod tests {
         mode: Option<Mode>,
         mtime: Option<UnixTimestamp>,
         ino: Option<u64>,
+        owner: Option<(u32, u32)>,
     }
 
     impl From<Mode> for ExpectedAttrs {
@@ -846,6 +917,9 @@ mod tests {
             if let Some(ino) = &self.ino {
                 assert_eq!(attrs.ino(), *ino);
             }
+            if let Some((uid, gid)) = &self.owner {
+                assert_eq!((attrs.uid(), attrs.gid()), (*uid, *gid));
+            }
         }
     }
 
@@ -861,6 +935,7 @@ mod tests {
         Attrs(&'static str, ExpectedAttrs),
         Contents(&'static str, &'static str),
         IsSymlink(&'static str),
+        Xattrs(&'static str, Vec<(&'static str, &'static [u8])>),
     }
 
     async fn assert_expectations(fs: &Fs, root: &Path, expected: Vec<Expect>) {
@@ -875,10 +950,60 @@ mod tests {
                     let sym_meta = fs.symlink_metadata(root.join(e)).await.unwrap();
                     assert!(sym_meta.is_symlink(), "/{e}");
                 }
+                Expect::Xattrs(e, xattrs) => {
+                    let actual = fs.symlink_extended_attributes(root.join(e)).await.unwrap();
+                    let expected = ExtendedAttributes::from_iter(
+                        xattrs.into_iter().map(|(k, v)| (k.into(), v.into())),
+                    );
+                    assert_eq!(actual, expected, "/{e}");
+                }
             }
         }
     }
 
+    /// Append a PAX extended header entry to the tar which gives the next entry the given extended
+    /// attributes.
+    async fn append_pax_xattrs(
+        ar: &mut tokio_tar::Builder<tokio::fs::File>,
+        path: &str,
+        xattrs: &ExtendedAttributes,
+    ) {
+        let records = xattrs
+            .iter()
+            .map(|(name, value)| (format!("SCHILY.xattr.{name}"), value.as_slice()));
+        append_pax_records(ar, path, records).await
+    }
+
+    /// Append a PAX extended header entry to the tar with the given records, which apply to the
+    /// next entry.
+    async fn append_pax_records<'a>(
+        ar: &mut tokio_tar::Builder<tokio::fs::File>,
+        path: &str,
+        records: impl IntoIterator<Item = (String, &'a [u8])>,
+    ) {
+        let mut data = vec![];
+        for (key, value) in records {
+            let mut record = format!(" {key}=").into_bytes();
+            record.extend(value);
+            record.push(b'\n');
+            // The length at the front of the record includes its own digits.
+            let mut len = record.len();
+            while len.to_string().len() + record.len() != len {
+                len = len.to_string().len() + record.len();
+            }
+            data.extend(len.to_string().into_bytes());
+            data.extend(record);
+        }
+        let mut header = tokio_tar::Header::new_ustar();
+        header.set_entry_type(tokio_tar::EntryType::XHeader);
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        let pax_path = format!("PaxHeaders/{}", path.trim_start_matches('/'));
+        ar.append_data(&mut header, pax_path, &data[..])
+            .await
+            .unwrap();
+    }
+
     struct Fixture {
         fs: Fs,
         temp: tempfile::TempDir,
@@ -945,7 +1070,19 @@ mod tests {
             let data_dir = self.new_data_dir().await;
             let mut builder = self.bottom_layer_builder(&data_dir).await;
 
-            for BuildEntry { path, data } in files {
+            for BuildEntry {
+                path,
+                data,
+                owner,
+                xattrs,
+            } in files
+            {
+                let attrs = |size, mode| ty::FileAttributes {
+                    size,
+                    mode: Mode(mode),
+                    mtime: ARBITRARY_TIME,
+                    owner: owner.map(|(uid, gid)| (UserId::new(uid), GroupId::new(gid))),
+                };
                 let size = match &data {
                     BuildEntryData::Regular {
                         data: FileDataInput::Inline(data),
@@ -969,7 +1106,7 @@ mod tests {
                         target,
                     } => {
                         builder
-                            .add_symlink_path(path.as_ref(), target)
+                            .add_symlink_path(path.as_ref(), attrs(0, 0o777), &xattrs, target)
                             .await
                             .unwrap();
                     }
@@ -981,28 +1118,13 @@ mod tests {
                     } => match type_ {
                         FileType::RegularFile => {
                             builder
-                                .add_file_path(
-                                    path.as_ref(),
-                                    ty::FileAttributes {
-                                        size,
-                                        mode: Mode(mode),
-                                        mtime: ARBITRARY_TIME,
-                                    },
-                                    data,
-                                )
+                                .add_file_path(path.as_ref(), attrs(size, mode), &xattrs, data)
                                 .await
                                 .unwrap();
                         }
                         FileType::Directory => {
                             builder
-                                .add_dir_path(
-                                    path.as_ref(),
-                                    ty::FileAttributes {
-                                        size,
-                                        mode: Mode(mode),
-                                        mtime: ARBITRARY_TIME,
-                                    },
-                                )
+                                .add_dir_path(path.as_ref(), attrs(size, mode), &xattrs)
                                 .await
                                 .unwrap();
                             if opaque_dir {
@@ -1031,12 +1153,46 @@ mod tests {
             builder.finish().await.unwrap()
         }
 
-        async fn build_tar(&self, files: Vec<BuildEntry>) -> (Sha256Digest, PathBuf) {
+        async fn start_tar(&self) -> tokio_tar::Builder<tokio::fs::File> {
             let tar_path = self.blob_dir.join::<BlobFile>("temp.tar");
             let f = self.fs.create_file(&tar_path).await.unwrap();
-            let mut ar = tokio_tar::Builder::new(f.into_inner());
-            for BuildEntry { path, data } in files {
+            tokio_tar::Builder::new(f.into_inner())
+        }
+
+        /// Finish the tar started with `start_tar`, and move it to where it belongs in the blob
+        /// directory.
+        async fn finish_tar(
+            &self,
+            mut ar: tokio_tar::Builder<tokio::fs::File>,
+        ) -> (Sha256Digest, PathBuf) {
+            ar.finish().await.unwrap();
+
+            let tar_path = self.blob_dir.join::<BlobFile>("temp.tar");
+            let digest = calc_digest(&self.fs, &tar_path).await;
+
+            let final_path = self.blob_dir.join::<BlobFile>(digest.to_string());
+            self.fs.rename(tar_path, &final_path).await.unwrap();
+
+            (digest, final_path.into_path_buf())
+        }
+
+        async fn build_tar(&self, files: Vec<BuildEntry>) -> (Sha256Digest, PathBuf) {
+            let mut ar = self.start_tar().await;
+            for BuildEntry {
+                path,
+                data,
+                owner,
+                xattrs,
+            } in files
+            {
+                if !xattrs.is_empty() {
+                    append_pax_xattrs(&mut ar, &path, &xattrs).await;
+                }
+                // Every entry in a tar has an owner.
+                let (uid, gid) = owner.unwrap_or_default();
                 let mut header = tokio_tar::Header::new_gnu();
+                header.set_uid(uid.into());
+                header.set_gid(gid.into());
                 match data {
                     BuildEntryData::Regular {
                         data,
@@ -1094,22 +1250,28 @@ mod tests {
                     }
                 }
             }
-            ar.finish().await.unwrap();
-
-            let digest = calc_digest(&self.fs, &tar_path).await;
-
-            let final_path = self.blob_dir.join::<BlobFile>(digest.to_string());
-            self.fs.rename(tar_path, &final_path).await.unwrap();
-
-            (digest, final_path.into_path_buf())
+            self.finish_tar(ar).await
         }
 
         async fn build_manifest(&self, files: Vec<BuildEntry>) -> PathBuf {
             let manifest_path = self.blob_dir.join::<BlobFile>("temp.manifest");
             let f = self.fs.create_file(&manifest_path).await.unwrap();
             let mut builder = AsyncManifestWriter::new(f).await.unwrap();
-            for BuildEntry { path, data } in files {
+            for BuildEntry {
+                path,
+                data,
+                owner,
+                xattrs,
+            } in files
+            {
                 let path: Utf8PathBuf = path.into();
+                let metadata = |size, mode| ManifestEntryMetadata {
+                    size,
+                    mode: Mode(mode),
+                    mtime: ARBITRARY_TIME,
+                    owner: owner.map(|(uid, gid)| (UserId::new(uid), GroupId::new(gid))),
+                    xattrs: xattrs.clone(),
+                };
                 match data {
                     BuildEntryData::Regular {
                         data,
@@ -1122,11 +1284,7 @@ mod tests {
                             FileDataInput::Inline(d) => d.len() as u64,
                             FileDataInput::Digest { length, .. } => *length,
                         };
-                        let metadata = ManifestEntryMetadata {
-                            size,
-                            mode: Mode(mode),
-                            mtime: ARBITRARY_TIME,
-                        };
+                        let metadata = metadata(size, mode);
                         match type_ {
                             FileType::Directory => builder
                                 .write_entry(&ManifestEntry {
@@ -1158,11 +1316,7 @@ mod tests {
                         }
                     }
                     BuildEntryData::Link { hard: true, target } => {
-                        let metadata = ManifestEntryMetadata {
-                            size: 0,
-                            mode: Mode(0o777),
-                            mtime: ARBITRARY_TIME,
-                        };
+                        let metadata = metadata(0, 0o777);
                         builder
                             .write_entry(&ManifestEntry {
                                 path,
@@ -1176,11 +1330,7 @@ mod tests {
                         hard: false,
                         target,
                     } => {
-                        let metadata = ManifestEntryMetadata {
-                            size: 0,
-                            mode: Mode(0o777),
-                            mtime: ARBITRARY_TIME,
-                        };
+                        let metadata = metadata(0, 0o777);
                         builder
                             .write_entry(&ManifestEntry {
                                 path,
@@ -1191,11 +1341,7 @@ mod tests {
                             .unwrap();
                     }
                     BuildEntryData::Whiteout => {
-                        let metadata = ManifestEntryMetadata {
-                            size: 0,
-                            mode: Mode(0o777),
-                            mtime: ARBITRARY_TIME,
-                        };
+                        let metadata = metadata(0, 0o777);
                         builder
                             .write_entry(&ManifestEntry {
                                 path,
@@ -1218,10 +1364,19 @@ mod tests {
         }
 
         async fn build_bottom_layer_from_tar(&mut self, input: Vec<BuildEntry>) -> LayerFs {
+            let (tar_digest, tar_path) = self.build_tar(input).await;
+            self.build_bottom_layer_from_tar_file(tar_digest, tar_path)
+                .await
+        }
+
+        async fn build_bottom_layer_from_tar_file(
+            &mut self,
+            tar_digest: Sha256Digest,
+            tar_path: PathBuf,
+        ) -> LayerFs {
             let data_dir = self.new_data_dir().await;
             let mut builder = self.bottom_layer_builder(&data_dir).await;
 
-            let (tar_digest, tar_path) = self.build_tar(input).await;
             builder
                 .add_from_tar(tar_digest, self.fs.open_file(tar_path).await.unwrap())
                 .await
@@ -1332,6 +1487,7 @@ mod tests {
             mode: Some(Mode(0o555)),
             mtime: Some(ARBITRARY_TIME),
             ino: None,
+            owner: None,
         };
         assert_expectations(
             &fix.fs,
@@ -1368,6 +1524,7 @@ mod tests {
             mode: Some(Mode(0o555)),
             mtime: Some(ARBITRARY_TIME),
             ino: Some(foo_attrs.ino()),
+            owner: None,
         };
         assert_expectations(
             &fix.fs,
@@ -1596,6 +1753,247 @@ mod tests {
         .await
     }
 
+    #[cfg(test)]
+    async fn layer_from_tar_or_manifest_with_ownership_and_xattrs(
+        mut populate_fn: impl for<'a> FnMut(
+            &'a mut Fixture,
+            Vec<BuildEntry>,
+        ) -> Pin<Box<dyn Future<Output = LayerFs> + 'a>>,
+    ) {
+        let mut fix = Fixture::new().await;
+
+        let input = vec![
+            BuildEntry::dir("Foo").owner(1000, 1001),
+            BuildEntry::reg("Foo/Bar", b"hello world")
+                .owner(1002, 1003)
+                .xattr("user.foo", b"foo value")
+                .xattr("user.bar", b""),
+            BuildEntry::reg_empty("Baz"),
+        ];
+        let layer_fs1 = populate_fn(&mut fix, input).await;
+
+        let input = vec![BuildEntry::reg_empty("Qux")
+            .owner(1004, 1005)
+            .xattr("user.qux", b"qux value")];
+        let layer_fs2 = populate_fn(&mut fix, input).await;
+
+        let layer_fs = fix.build_upper_layer(&layer_fs1, &layer_fs2).await;
+
+        let mount_handle = fix.mount(layer_fs).await;
+        let mount_path = mount_handle.mount_path();
+
+        // Only root in the mount's user namespace maps to an ID outside of it, any other owner shows
+        // up as the overflow ID.
+        let overflow_id = |path| -> u32 {
+            std::fs::read_to_string(path)
+                .unwrap()
+                .trim()
+                .parse()
+                .unwrap()
+        };
+        let root = ExpectedAttrs {
+            owner: Some((
+                maelstrom_linux::getuid().as_u32(),
+                maelstrom_linux::getgid().as_u32(),
+            )),
+            ..Default::default()
+        };
+        let not_root = ExpectedAttrs {
+            owner: Some((
+                overflow_id("/proc/sys/kernel/overflowuid"),
+                overflow_id("/proc/sys/kernel/overflowgid"),
+            )),
+            ..Default::default()
+        };
+        let expectations = vec![
+            Expect::Attrs("Foo", not_root.clone()),
+            Expect::Attrs("Foo/Bar", not_root.clone()),
+            Expect::Attrs("Baz", root),
+            Expect::Attrs("Qux", not_root),
+            Expect::Contents("Foo/Bar", "hello world"),
+            Expect::Xattrs("Foo", vec![]),
+            Expect::Xattrs(
+                "Foo/Bar",
+                vec![("user.bar", b""), ("user.foo", b"foo value")],
+            ),
+            Expect::Xattrs("Qux", vec![("user.qux", b"qux value")]),
+        ];
+
+        assert_expectations(&fix.fs, mount_path, expectations).await;
+
+        mount_handle.umount_and_join().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn layer_from_tar_with_ownership_and_xattrs() {
+        layer_from_tar_or_manifest_with_ownership_and_xattrs(|fix, input| {
+            Box::pin(async move { fix.build_bottom_layer_from_tar(input).await })
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn layer_from_manifest_with_ownership_and_xattrs() {
+        layer_from_tar_or_manifest_with_ownership_and_xattrs(|fix, input| {
+            Box::pin(async move { fix.build_bottom_layer_from_manifest(input).await })
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn layer_from_tar_with_pax_path_and_attributes() {
+        let mut fix = Fixture::new().await;
+
+        // These are too long for a tar header, and are replaced in the header by the short names.
+        let dir: &'static str = "d".repeat(80).leak();
+        let file: &'static str = format!("{dir}/{}", "f".repeat(80)).leak();
+        let link: &'static str = format!("{dir}/{}", "l".repeat(80)).leak();
+        let target = "f".repeat(80);
+
+        let mut ar = fix.start_tar().await;
+        let mut header = tokio_tar::Header::new_ustar();
+        header.set_entry_type(tokio_tar::EntryType::Directory);
+        header.set_size(0);
+        header.set_mode(0o755);
+        header.set_uid(1000);
+        header.set_gid(1000);
+        append_pax_records(
+            &mut ar,
+            "short_dir",
+            [
+                ("path".into(), dir.as_bytes()),
+                ("uid".into(), b"0".as_slice()),
+                ("gid".into(), b"0".as_slice()),
+            ],
+        )
+        .await;
+        ar.append_data(&mut header, "short_dir", tokio::io::empty())
+            .await
+            .unwrap();
+
+        header.set_entry_type(tokio_tar::EntryType::Regular);
+        header.set_size(11);
+        header.set_mode(0o644);
+        header.set_mtime(1);
+        append_pax_records(
+            &mut ar,
+            "short_file",
+            [
+                ("path".into(), file.as_bytes()),
+                ("mtime".into(), b"1234567.25".as_slice()),
+                ("SCHILY.xattr.user.foo".into(), b"foo value".as_slice()),
+            ],
+        )
+        .await;
+        ar.append_data(&mut header, "short_file", &b"hello world"[..])
+            .await
+            .unwrap();
+
+        header.set_entry_type(tokio_tar::EntryType::Symlink);
+        header.set_size(0);
+        header.set_link_name("short_target").unwrap();
+        append_pax_records(
+            &mut ar,
+            "short_link",
+            [
+                ("path".into(), link.as_bytes()),
+                ("linkpath".into(), target.as_bytes()),
+            ],
+        )
+        .await;
+        ar.append_data(&mut header, "short_link", tokio::io::empty())
+            .await
+            .unwrap();
+
+        let (tar_digest, tar_path) = fix.finish_tar(ar).await;
+        let layer_fs = fix
+            .build_bottom_layer_from_tar_file(tar_digest, tar_path)
+            .await;
+
+        let mount_handle = fix.mount(layer_fs).await;
+        let mount_path = mount_handle.mount_path();
+
+        let root_owner = Some((
+            maelstrom_linux::getuid().as_u32(),
+            maelstrom_linux::getgid().as_u32(),
+        ));
+        assert_expectations(
+            &fix.fs,
+            mount_path,
+            vec![
+                Expect::NotExists("short_dir"),
+                Expect::NotExists("short_file"),
+                Expect::NotExists("short_link"),
+                Expect::Attrs(
+                    dir,
+                    ExpectedAttrs {
+                        owner: root_owner,
+                        ..Default::default()
+                    },
+                ),
+                Expect::Attrs(
+                    file,
+                    ExpectedAttrs {
+                        len: Some(11),
+                        mtime: Some(UnixTimestamp(1234567)),
+                        ..Default::default()
+                    },
+                ),
+                Expect::Xattrs(file, vec![("user.foo", b"foo value")]),
+                Expect::IsSymlink(link),
+                Expect::Contents(link, "hello world"),
+            ],
+        )
+        .await;
+
+        mount_handle.umount_and_join().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn layer_from_tar_with_blank_owner() {
+        let mut fix = Fixture::new().await;
+
+        let mut ar = fix.start_tar().await;
+        let mut header = tokio_tar::Header::new_gnu();
+        header.set_entry_type(tokio_tar::EntryType::Regular);
+        header.set_size(11);
+        header.set_mode(0o644);
+        header.set_mtime(1);
+        ar.append_data(&mut header, "foo.bin", &b"hello world"[..])
+            .await
+            .unwrap();
+
+        let (tar_digest, tar_path) = fix.finish_tar(ar).await;
+        let layer_fs = fix
+            .build_bottom_layer_from_tar_file(tar_digest, tar_path)
+            .await;
+
+        let mount_handle = fix.mount(layer_fs).await;
+        let mount_path = mount_handle.mount_path();
+
+        // A file without an owner is owned by whoever is looking at it.
+        assert_expectations(
+            &fix.fs,
+            mount_path,
+            vec![
+                Expect::Attrs(
+                    "foo.bin",
+                    ExpectedAttrs {
+                        owner: Some((
+                            maelstrom_linux::getuid().as_u32(),
+                            maelstrom_linux::getgid().as_u32(),
+                        )),
+                        ..Default::default()
+                    },
+                ),
+                Expect::Contents("foo.bin", "hello world"),
+            ],
+        )
+        .await;
+
+        mount_handle.umount_and_join().await.unwrap();
+    }
+
     async fn two_layer_test(lower: Vec<&str>, upper: Vec<&str>, expected: Vec<Expect>) {
         let mut fix = Fixture::new().await;
 
@@ -4,7 +4,7 @@ use anyhow::{bail, Context as _, Result};
 use derive_more::{From, Into};
 use maelstrom_base::{
     manifest::{Mode, UnixTimestamp},
-    Sha256Digest,
+    GroupId, Sha256Digest, UserId,
 };
 use maelstrom_fuse::ErrnoResult;
 use maelstrom_linux::Errno;
@@ -160,8 +160,10 @@ impl DirectoryEntryData {
 #[derive(Copy, Clone, Default, Debug, Deserialize_repr, Serialize_repr)]
 #[repr(u32)]
 pub enum LayerFsVersion {
-    #[default]
     V0 = 0,
+    /// Adds file ownership and extended attributes.
+    #[default]
+    V1 = 1,
 }
 
 #[derive(Copy, Clone, Debug, Deserialize, Serialize)]
@@ -187,21 +189,72 @@ pub struct FileAttributes {
     pub size: u64,
     pub mode: Mode,
     pub mtime: UnixTimestamp,
+    /// The file's user and group. A file without an owner is shown as owned by whoever is looking
+    /// at it, which in a job is the job's user and group.
+    #[serde(with = "fixed_size_owner")]
+    pub owner: Option<(UserId, GroupId)>,
+}
+
+/// Encode a file's owner so that it's the same size whether or not there is one.
+mod fixed_size_owner {
+    use super::*;
+    use serde::{Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        owner: &Option<(UserId, GroupId)>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let (uid, gid) = owner.unwrap_or((UserId::new(0), GroupId::new(0)));
+        (owner.is_some(), uid, gid).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<(UserId, GroupId)>, D::Error> {
+        let (present, uid, gid) = <(bool, UserId, GroupId)>::deserialize(deserializer)?;
+        Ok(present.then_some((uid, gid)))
+    }
+}
+
+/// Where a file's encoded extended attributes live in the layer's inline data. A length of zero
+/// means the file has none.
+#[derive(Copy, Clone, Debug, Default, Deserialize, Serialize)]
+pub struct ExtendedAttributesData {
+    pub offset: u64,
+    pub length: u64,
+}
+
+/// What is stored in the attribute-table about each file. This has to always encode to the same
+/// size, since it gets overwritten in place.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct AttributesTableEntry {
+    pub attrs: FileAttributes,
+    pub xattrs: ExtendedAttributesData,
 }
 
 #[test]
 fn file_attributes_encoding_size_remains_same() {
     use maelstrom_base::proto;
 
-    let mut a = FileAttributes {
-        size: 1,
-        mode: Mode(1),
-        mtime: UnixTimestamp(1),
+    let mut a = AttributesTableEntry {
+        attrs: FileAttributes {
+            size: 1,
+            mode: Mode(1),
+            mtime: UnixTimestamp(1),
+            owner: None,
+        },
+        xattrs: ExtendedAttributesData {
+            offset: 1,
+            length: 1,
+        },
     };
     let start_size = proto::fixint_serialized_size(&a).unwrap();
-    a.size = u64::MAX;
-    a.mode = Mode(u32::MAX);
-    a.mtime = UnixTimestamp(i64::MAX);
+    a.attrs.size = u64::MAX;
+    a.attrs.mode = Mode(u32::MAX);
+    a.attrs.mtime = UnixTimestamp(i64::MAX);
+    a.attrs.owner = Some((UserId::new(u32::MAX), GroupId::new(u32::MAX)));
+    a.xattrs.offset = u64::MAX;
+    a.xattrs.length = u64::MAX;
     let end_size = proto::fixint_serialized_size(&a).unwrap();
     assert_eq!(start_size, end_size);
 }
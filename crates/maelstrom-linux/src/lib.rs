@@ -199,6 +199,8 @@ impl Errno {
     pub const EPIPE: Self = Self(libc::EPIPE);
     pub const EDOM: Self = Self(libc::EDOM);
     pub const ERANGE: Self = Self(libc::ERANGE);
+    pub const ENODATA: Self = Self(libc::ENODATA);
+    pub const EOPNOTSUPP: Self = Self(libc::EOPNOTSUPP);
     pub const EWOULDBLOCK: Self = Self::EAGAIN;
 }
 
@@ -1369,6 +1371,27 @@ pub fn listen(fd: &impl AsFd, backlog: u32) -> Result<(), Errno> {
     Errno::result(unsafe { libc::listen(fd.0, backlog as c_int) }).map(drop)
 }
 
+/// Read the value of the extended attribute `name` of the file at `path`, without following
+/// symlinks. If `value` is empty, the size of the value is returned instead.
+pub fn lgetxattr(path: &CStr, name: &CStr, value: &mut [u8]) -> Result<usize, Errno> {
+    let path_ptr = path.as_ptr();
+    let name_ptr = name.as_ptr();
+    let value_ptr = value.as_mut_ptr() as *mut c_void;
+    let value_len = value.len();
+    Errno::result(unsafe { libc::lgetxattr(path_ptr, name_ptr, value_ptr, value_len) })
+        .map(|ret| ret as usize)
+}
+
+/// List the names of the extended attributes of the file at `path`, without following symlinks.
+/// The names are written to `list` separated by NUL bytes. If `list` is empty, the size of the
+/// list is returned instead.
+pub fn llistxattr(path: &CStr, list: &mut [u8]) -> Result<usize, Errno> {
+    let path_ptr = path.as_ptr();
+    let list_ptr = list.as_mut_ptr() as *mut c_char;
+    let list_len = list.len();
+    Errno::result(unsafe { libc::llistxattr(path_ptr, list_ptr, list_len) }).map(|ret| ret as usize)
+}
+
 pub fn lseek(fd: &impl AsFd, offset: i64, whence: Whence) -> Result<i64, Errno> {
     let fd = fd.fd();
     Errno::result(unsafe { libc::lseek(fd.0, offset, whence.as_i32()) })
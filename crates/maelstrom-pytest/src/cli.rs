@@ -8,6 +8,7 @@ pub struct ExtraCommandLineOptions {
 
     #[arg(
         long = "list",
+        conflicts_with = "export_image",
         help = "Instead of running tests, print the tests that would have been run.",
         help_heading = "List Options"
     )]
@@ -0,0 +1,325 @@
+//! Figure out which Python files in the project could be impacted by changes to other files.
+//!
+//! This is used in watch mode to only rerun the tests in files that import, directly or
+//! indirectly, a changed module. We don't run Python to do this. Instead, we look for `import`
+//! statements and resolve them against the files in the project. This is a heuristic, but it
+//! errs on the side of considering things impacted.
+
+use regex::Regex;
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    path::{Path, PathBuf},
+};
+
+/// A single module imported by a Python file.
+#[derive(Debug, PartialEq, Eq)]
+struct Import {
+    /// The number of leading dots for relative imports. Zero for absolute imports.
+    level: usize,
+    /// The dotted module path, which may be empty for things like `from . import foo`.
+    module: String,
+    /// The names imported with `from ... import`. These may be modules themselves.
+    names: Vec<String>,
+}
+
+fn split_names(names: &str) -> impl Iterator<Item = String> + '_ {
+    names
+        .split(',')
+        .filter_map(|name| name.split_whitespace().next())
+        .filter(|name| *name != "*")
+        .map(ToOwned::to_owned)
+}
+
+/// Find the import statements in the given Python source.
+fn parse_imports(source: &str) -> Vec<Import> {
+    let import_re = Regex::new(r"^\s*import\s+(.+)$").unwrap();
+    let from_import_re = Regex::new(r"^\s*from\s+(\.*)([\w.]*)\s+import\s+(.+)$").unwrap();
+
+    let mut imports = vec![];
+    let mut lines = source.lines().map(|line| match line.find('#') {
+        Some(pos) => &line[..pos],
+        None => line,
+    });
+    while let Some(line) = lines.next() {
+        if let Some(captures) = from_import_re.captures(line) {
+            let mut names = captures[3].trim().to_owned();
+            if let Some(rest) = names.strip_prefix('(') {
+                // Parenthesized names can span multiple lines.
+                names = rest.to_owned();
+                while !names.contains(')') {
+                    let Some(line) = lines.next() else {
+                        break;
+                    };
+                    names.push(',');
+                    names.push_str(line);
+                }
+                names = names.split(')').next().unwrap().to_owned();
+            }
+            imports.push(Import {
+                level: captures[1].len(),
+                module: captures[2].to_owned(),
+                names: split_names(&names).collect(),
+            });
+        } else if let Some(captures) = import_re.captures(line) {
+            imports.extend(split_names(&captures[1]).map(|module| Import {
+                level: 0,
+                module,
+                names: vec![],
+            }));
+        }
+    }
+    imports
+}
+
+/// Return the candidate files for the given module, relative to the given directory. This
+/// includes the `__init__.py` files of all of the packages the module is in, since importing a
+/// module imports those too.
+fn module_candidates(dir: &Path, module: &[&str]) -> Vec<PathBuf> {
+    let mut candidates = vec![];
+    let mut path = dir.to_owned();
+    for (i, component) in module.iter().enumerate() {
+        path.push(component);
+        candidates.push(path.join("__init__.py"));
+        if i == module.len() - 1 {
+            candidates.push(path.with_extension("py"));
+        }
+    }
+    candidates
+}
+
+/// Resolve an import in the given file to the project files it could refer to.
+fn resolve_import(file: &Path, import: &Import, is_file: impl Fn(&Path) -> bool) -> Vec<PathBuf> {
+    let file_dir = file.parent().unwrap_or(Path::new(""));
+    let roots: Vec<PathBuf> = if import.level > 0 {
+        match file_dir.ancestors().nth(import.level - 1) {
+            Some(dir) => vec![dir.to_owned()],
+            None => return vec![],
+        }
+    } else {
+        // Pytest puts the test file's directory in `sys.path`. We also check the project root and
+        // the common "src" layout.
+        vec!["".into(), "src".into(), file_dir.to_owned()]
+    };
+
+    let module: Vec<&str> = import.module.split('.').filter(|c| !c.is_empty()).collect();
+    let mut resolved = vec![];
+    for root in &roots {
+        resolved.extend(module_candidates(root, &module));
+        for name in &import.names {
+            let mut submodule = module.clone();
+            submodule.push(name);
+            resolved.extend(module_candidates(root, &submodule));
+        }
+    }
+    resolved.retain(|path| path != file && is_file(path));
+    resolved
+}
+
+/// Find the files that could be impacted by changes to the given files.
+///
+/// `sources` contains the contents of every Python file in the project, keyed by path relative to
+/// the project directory. `changed_paths` are also relative to the project directory. A file is
+/// impacted if it changed, if it imports an impacted file, or if a `conftest.py` in one of its
+/// ancestor directories changed. Returns `None` if a non-Python file changed, since that could
+/// impact anything.
+pub fn find_impacted_files(
+    sources: &BTreeMap<PathBuf, String>,
+    changed_paths: &BTreeSet<PathBuf>,
+) -> Option<BTreeSet<PathBuf>> {
+    let changed_paths: Vec<&PathBuf> = changed_paths
+        .iter()
+        .filter(|path| !path.components().any(|c| c.as_os_str() == "__pycache__"))
+        .collect();
+    if changed_paths
+        .iter()
+        .any(|path| path.extension().is_none_or(|ext| ext != "py"))
+    {
+        return None;
+    }
+
+    // Removed files won't be in `sources`, but files that imported them are still impacted.
+    let is_file =
+        |path: &Path| sources.contains_key(path) || changed_paths.iter().any(|p| *p == path);
+
+    let mut importers: HashMap<PathBuf, Vec<&PathBuf>> = HashMap::new();
+    for (file, source) in sources {
+        for import in parse_imports(source) {
+            for imported in resolve_import(file, &import, is_file) {
+                importers.entry(imported).or_default().push(file);
+            }
+        }
+    }
+
+    let mut impacted = BTreeSet::new();
+    let mut to_visit = vec![];
+    for path in &changed_paths {
+        if path.file_name().is_some_and(|name| name == "conftest.py") {
+            let dir = path.parent().unwrap_or(Path::new(""));
+            impacted.extend(sources.keys().filter(|p| p.starts_with(dir)).cloned());
+        }
+        if impacted.insert((*path).clone()) {
+            to_visit.push((*path).clone());
+        }
+    }
+    while let Some(path) = to_visit.pop() {
+        for importer in importers.get(&path).into_iter().flatten() {
+            if impacted.insert((*importer).clone()) {
+                to_visit.push((*importer).clone());
+            }
+        }
+    }
+    Some(impacted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indoc::indoc;
+
+    fn import(level: usize, module: &str, names: &[&str]) -> Import {
+        Import {
+            level,
+            module: module.into(),
+            names: names.iter().map(|n| n.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn parse_imports_all_forms() {
+        let source = indoc! {"
+            import os
+            import foo.bar as fb, baz  # comment
+            from qux import a, b as c
+            from . import sibling
+            from ..parent.mod import (
+                x,
+                y as z,
+            )
+            from star import *
+
+            def f():
+                import inner
+            # import commented_out
+        "};
+        assert_eq!(
+            parse_imports(source),
+            vec![
+                import(0, "os", &[]),
+                import(0, "foo.bar", &[]),
+                import(0, "baz", &[]),
+                import(0, "qux", &["a", "b"]),
+                import(1, "", &["sibling"]),
+                import(2, "parent.mod", &["x", "y"]),
+                import(0, "star", &[]),
+                import(0, "inner", &[]),
+            ]
+        );
+    }
+
+    #[track_caller]
+    fn find_impacted_files_test<'a>(
+        changed_paths: impl IntoIterator<Item = &'a str>,
+        expected: Option<Vec<&str>>,
+    ) {
+        let sources: BTreeMap<PathBuf, String> = [
+            ("conftest.py", ""),
+            ("src/lib/__init__.py", ""),
+            ("src/lib/core.py", "import os\n"),
+            ("src/lib/util.py", "from .core import thing\n"),
+            ("src/other.py", ""),
+            ("tests/conftest.py", ""),
+            ("tests/helpers.py", "from lib import util\n"),
+            ("tests/test_core.py", "import lib.core\n"),
+            ("tests/test_util.py", "import helpers\n"),
+            ("tests/unit/test_other.py", "from other import *\n"),
+            ("tests/unit/test_removed.py", "import tests.removed\n"),
+        ]
+        .into_iter()
+        .map(|(path, source)| (path.into(), source.into()))
+        .collect();
+        let changed_paths = changed_paths.into_iter().map(PathBuf::from).collect();
+        let expected = expected.map(|e| e.into_iter().map(PathBuf::from).collect());
+        assert_eq!(find_impacted_files(&sources, &changed_paths), expected);
+    }
+
+    #[test]
+    fn find_impacted_files_no_changes() {
+        find_impacted_files_test([], Some(vec![]));
+    }
+
+    #[test]
+    fn find_impacted_files_test_file() {
+        find_impacted_files_test(["tests/test_core.py"], Some(vec!["tests/test_core.py"]));
+    }
+
+    #[test]
+    fn find_impacted_files_transitive() {
+        find_impacted_files_test(
+            ["src/lib/core.py"],
+            Some(vec![
+                "src/lib/core.py",
+                "src/lib/util.py",
+                "tests/helpers.py",
+                "tests/test_core.py",
+                "tests/test_util.py",
+            ]),
+        );
+    }
+
+    #[test]
+    fn find_impacted_files_package_init() {
+        find_impacted_files_test(
+            ["src/lib/__init__.py"],
+            Some(vec![
+                "src/lib/__init__.py",
+                "tests/helpers.py",
+                "tests/test_core.py",
+                "tests/test_util.py",
+            ]),
+        );
+    }
+
+    #[test]
+    fn find_impacted_files_star_import() {
+        find_impacted_files_test(
+            ["src/other.py"],
+            Some(vec!["src/other.py", "tests/unit/test_other.py"]),
+        );
+    }
+
+    #[test]
+    fn find_impacted_files_removed_file() {
+        find_impacted_files_test(
+            ["tests/removed.py"],
+            Some(vec!["tests/removed.py", "tests/unit/test_removed.py"]),
+        );
+    }
+
+    #[test]
+    fn find_impacted_files_conftest() {
+        find_impacted_files_test(
+            ["tests/conftest.py"],
+            Some(vec![
+                "tests/conftest.py",
+                "tests/helpers.py",
+                "tests/test_core.py",
+                "tests/test_util.py",
+                "tests/unit/test_other.py",
+                "tests/unit/test_removed.py",
+            ]),
+        );
+    }
+
+    #[test]
+    fn find_impacted_files_pycache_ignored() {
+        find_impacted_files_test(
+            ["tests/__pycache__/test_core.cpython-312.pyc"],
+            Some(vec![]),
+        );
+    }
+
+    #[test]
+    fn find_impacted_files_non_python_file() {
+        find_impacted_files_test(["src/other.py", "pyproject.toml"], None);
+    }
+}
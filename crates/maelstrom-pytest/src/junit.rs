@@ -0,0 +1,263 @@
+//! Read the JUnit XML reports that pytest writes with `--junitxml`.
+//!
+//! When test cases are grouped, a single pytest invocation runs all of the cases in a group. It
+//! writes a report to [`report_path`], which comes back with the job's result. Each case's result
+//! is found in the report by the `classname` and `name` attributes that pytest derives from the
+//! case's node ID.
+
+use crate::PytestCaseMetadata;
+use anyhow::Result;
+use maelstrom_test_runner::{ui::UiJobStatus, GroupCaseResult, GROUP_REPORT_DIR};
+use serde::Deserialize;
+use std::{collections::HashMap, time::Duration};
+
+pub const REPORT_NAME: &str = "junit.xml";
+
+/// The file in a group's job's container that the report is written to.
+pub fn report_path() -> String {
+    format!("{GROUP_REPORT_DIR}/{REPORT_NAME}")
+}
+
+/// Compute the `classname` and `name` attributes pytest uses for a test case in a JUnit report.
+/// This mirrors `mangle_test_address` in pytest's `junitxml` plugin.
+fn junit_names(node_id: &str) -> (String, String) {
+    let (path, params) = match node_id.find('[') {
+        Some(index) => node_id.split_at(index),
+        None => (node_id, ""),
+    };
+    let mut names: Vec<String> = path.split("::").map(Into::into).collect();
+    let module = names[0].replace('/', ".");
+    names[0] = module.strip_suffix(".py").unwrap_or(&module).into();
+    let name = names.pop().unwrap() + params;
+    (names.join("."), name)
+}
+
+#[derive(Deserialize)]
+struct TestSuites {
+    #[serde(default, rename = "testsuite")]
+    test_suites: Vec<TestSuite>,
+}
+
+#[derive(Deserialize)]
+struct TestSuite {
+    #[serde(default, rename = "testcase")]
+    test_cases: Vec<TestCase>,
+}
+
+#[derive(Deserialize)]
+struct TestCase {
+    #[serde(default, rename = "@classname")]
+    classname: String,
+    #[serde(rename = "@name")]
+    name: String,
+    #[serde(default, rename = "@time")]
+    time: f64,
+    #[serde(default)]
+    failure: Vec<Problem>,
+    #[serde(default)]
+    error: Vec<Problem>,
+    #[serde(default)]
+    skipped: Vec<Problem>,
+    #[serde(default, rename = "system-out")]
+    system_out: Vec<String>,
+    #[serde(default, rename = "system-err")]
+    system_err: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct Problem {
+    #[serde(default, rename = "@message")]
+    message: String,
+    #[serde(default, rename = "$text")]
+    text: String,
+}
+
+impl TestCase {
+    fn status(&self) -> UiJobStatus {
+        if !self.failure.is_empty() || !self.error.is_empty() {
+            UiJobStatus::Failure(None)
+        } else if !self.skipped.is_empty() {
+            UiJobStatus::Ignored
+        } else {
+            UiJobStatus::Ok
+        }
+    }
+
+    /// The output to show for a failed test case: the failures' and errors' tracebacks, followed
+    /// by anything the test case printed.
+    fn output(&self) -> Vec<String> {
+        let problems = self.failure.iter().chain(&self.error).map(|problem| {
+            if problem.text.is_empty() {
+                &problem.message
+            } else {
+                &problem.text
+            }
+        });
+        problems
+            .chain(&self.system_out)
+            .chain(&self.system_err)
+            .flat_map(|text| text.lines())
+            .map(Into::into)
+            .collect()
+    }
+}
+
+/// Read the results of the given test cases from a report. Test cases that aren't in the report
+/// are left out. Test cases in the report that weren't given are ignored.
+pub fn read_report(
+    contents: &str,
+    cases: &[(String, PytestCaseMetadata)],
+) -> Result<Vec<GroupCaseResult>> {
+    let mut test_suites = quick_xml::de::from_str::<TestSuites>(contents)?.test_suites;
+    if test_suites.is_empty() {
+        // Older versions of pytest don't have a `testsuites` element.
+        test_suites.push(quick_xml::de::from_str(contents)?);
+    }
+    let names: HashMap<_, _> = cases
+        .iter()
+        .map(|(case_name, case_metadata)| (junit_names(&case_metadata.node_id), case_name))
+        .collect();
+
+    let mut results: Vec<GroupCaseResult> = vec![];
+    for test_case in test_suites.into_iter().flat_map(|suite| suite.test_cases) {
+        let Some(&case_name) = names.get(&(test_case.classname.clone(), test_case.name.clone()))
+        else {
+            continue;
+        };
+        let status = test_case.status();
+        let output = if matches!(status, UiJobStatus::Failure(_)) {
+            test_case.output()
+        } else {
+            vec![]
+        };
+        let result = GroupCaseResult {
+            name: case_name.clone(),
+            status,
+            duration: Duration::from_secs_f64(test_case.time.max(0.0)),
+            output,
+        };
+        // A test case can show up more than once, like when pytest is asked to rerun failures.
+        // The last result wins, but the durations are added together.
+        match results
+            .iter_mut()
+            .find(|existing| existing.name == result.name)
+        {
+            Some(existing) => {
+                let duration = existing.duration + result.duration;
+                *existing = GroupCaseResult { duration, ..result };
+            }
+            None => results.push(result),
+        }
+    }
+    Ok(results)
+}
+
+#[cfg(test)]
+fn case(name: &str, node_id: &str) -> (String, PytestCaseMetadata) {
+    (
+        name.into(),
+        PytestCaseMetadata {
+            node_id: node_id.into(),
+            markers: vec![],
+        },
+    )
+}
+
+#[test]
+fn junit_names_function() {
+    assert_eq!(
+        junit_names("tests/test_foo.py::test_a"),
+        ("tests.test_foo".into(), "test_a".into())
+    );
+}
+
+#[test]
+fn junit_names_class_and_params() {
+    assert_eq!(
+        junit_names("test_foo.py::TestA::TestB::test_a[x::y/z.py]"),
+        ("test_foo.TestA.TestB".into(), "test_a[x::y/z.py]".into())
+    );
+}
+
+#[test]
+fn read_report_results() {
+    let report = r#"<?xml version="1.0" encoding="utf-8"?>
+<testsuites>
+  <testsuite name="pytest" errors="1" failures="1" skipped="1" tests="4" time="0.5">
+    <testcase classname="tests.test_foo.TestA" name="test_a" time="0.25" />
+    <testcase classname="tests.test_foo.TestA" name="test_b[1]" time="0.125">
+      <failure message="assert 1 == 2">def test_b():
+&gt;       assert 1 == 2</failure>
+      <system-out>some output</system-out>
+    </testcase>
+    <testcase classname="tests.test_foo.TestA" name="test_c" time="0">
+      <skipped type="pytest.skip" message="not today">skipped</skipped>
+    </testcase>
+    <testcase classname="tests.test_foo.TestA" name="test_d" time="0">
+      <error message="failed on setup with &quot;boom&quot;" />
+    </testcase>
+    <testcase classname="tests.test_foo" name="test_other" time="1" />
+  </testsuite>
+</testsuites>"#;
+    let cases = [
+        case("TestA.test_a", "tests/test_foo.py::TestA::test_a"),
+        case("TestA.test_b[1]", "tests/test_foo.py::TestA::test_b[1]"),
+        case("TestA.test_c", "tests/test_foo.py::TestA::test_c"),
+        case("TestA.test_d", "tests/test_foo.py::TestA::test_d"),
+        case("TestA.test_e", "tests/test_foo.py::TestA::test_e"),
+    ];
+    assert_eq!(
+        read_report(report, &cases).unwrap(),
+        vec![
+            GroupCaseResult {
+                name: "TestA.test_a".into(),
+                status: UiJobStatus::Ok,
+                duration: Duration::from_millis(250),
+                output: vec![],
+            },
+            GroupCaseResult {
+                name: "TestA.test_b[1]".into(),
+                status: UiJobStatus::Failure(None),
+                duration: Duration::from_millis(125),
+                output: vec![
+                    "def test_b():".into(),
+                    ">       assert 1 == 2".into(),
+                    "some output".into(),
+                ],
+            },
+            GroupCaseResult {
+                name: "TestA.test_c".into(),
+                status: UiJobStatus::Ignored,
+                duration: Duration::ZERO,
+                output: vec![],
+            },
+            GroupCaseResult {
+                name: "TestA.test_d".into(),
+                status: UiJobStatus::Failure(None),
+                duration: Duration::ZERO,
+                output: vec!["failed on setup with \"boom\"".into()],
+            },
+        ]
+    );
+}
+
+#[test]
+fn read_report_without_testsuites() {
+    let report = r#"<testsuite name="pytest" tests="1">
+    <testcase classname="test_foo" name="test_a" time="1.5" />
+</testsuite>"#;
+    assert_eq!(
+        read_report(report, &[case("test_a", "test_foo.py::test_a")]).unwrap(),
+        vec![GroupCaseResult {
+            name: "test_a".into(),
+            status: UiJobStatus::Ok,
+            duration: Duration::from_millis(1500),
+            output: vec![],
+        }]
+    );
+}
+
+#[test]
+fn read_report_malformed() {
+    read_report("<testsuites><testsuite>", &[]).unwrap_err();
+}
@@ -1,30 +1,30 @@
 pub mod cli;
 mod config;
+mod imports;
+mod junit;
 pub mod pattern;
 mod pytest;
 
 pub use config::{Config, PytestConfigValues};
 pub use maelstrom_test_runner::Logger;
 
-use anyhow::{anyhow, bail, Result};
+use anyhow::Result;
 use cli::ExtraCommandLineOptions;
-use maelstrom_base::{
-    enum_set, CaptureFileSystemChanges, JobDevice, JobMount, JobNetwork, JobOutcome,
-    JobTerminationStatus, Timeout, Utf8PathBuf,
-};
+use maelstrom_base::{CapturedFile, Timeout, Utf8PathBuf};
 use maelstrom_client::{
-    glob_layer_spec, job_spec,
-    spec::{ContainerParent, ImageRef, LayerSpec, PathsLayerSpec, PrefixOptions, StubsLayerSpec},
+    setup_layer_spec,
+    spec::{ContainerParent, ImageRef, LayerSpec},
     AcceptInvalidRemoteContainerTlsCerts, CacheDir, Client, ClientBgProcess,
     ContainerImageDepotDir, ProjectDir, StateDir,
 };
-use maelstrom_container::{DockerReference, ImageName};
+use maelstrom_container::ImageName;
 use maelstrom_test_runner::{
-    metadata::Metadata,
+    metadata::{GroupBy, Metadata},
     run_app_with_ui_multithreaded,
-    ui::{Ui, UiMessage, UiSender},
-    BuildDir, CollectTests, ListAction, LoggingOutput, MainAppDeps, TestArtifact, TestArtifactKey,
-    TestCaseMetadata, TestFilter, TestPackage, TestPackageId, Wait, WaitStatus,
+    ui::{Ui, UiSender},
+    BuildDir, CollectTests, GroupCaseResult, ListAction, LoggingOutput, MainAppDeps, TestArtifact,
+    TestArtifactKey, TestCaseMetadata, TestFilter, TestImpact, TestPackage, TestPackageId, Wait,
+    WaitStatus,
 };
 use maelstrom_util::{
     config::common::{ArtifactTransferStrategy, BrokerAddr, CacheSize, InlineLimit, Slots},
@@ -37,11 +37,10 @@ use regex::Regex;
 use serde::{Deserialize, Serialize};
 use std::{
     collections::{
-        HashSet,
+        BTreeMap, BTreeSet, HashSet,
         {hash_map::Entry, HashMap},
     },
     fmt, io,
-    os::unix::fs::PermissionsExt as _,
     path::{Path, PathBuf},
     str::FromStr,
     sync::Mutex,
@@ -96,23 +95,16 @@ fn create_client(
     )
 }
 
-struct DefaultMainAppDeps<'client> {
-    test_collector: PytestTestCollector<'client>,
+struct DefaultMainAppDeps {
+    test_collector: PytestTestCollector,
 }
 
-impl<'client> DefaultMainAppDeps<'client> {
-    pub fn new(
-        project_dir: &Root<ProjectDir>,
-        build_dir: &Root<BuildDir>,
-        cache_dir: &Root<CacheDir>,
-        client: &'client Client,
-    ) -> Result<Self> {
+impl DefaultMainAppDeps {
+    pub fn new(project_dir: &Root<ProjectDir>, build_dir: &Root<BuildDir>) -> Result<Self> {
         Ok(Self {
             test_collector: PytestTestCollector {
-                client,
                 project_dir: project_dir.to_owned(),
                 build_dir: build_dir.to_owned(),
-                cache_dir: cache_dir.to_owned(),
                 test_layers: Mutex::new(HashMap::new()),
             },
         })
@@ -167,151 +159,17 @@ impl TestFilter for pattern::Pattern {
     }
 }
 
-struct PytestTestCollector<'client> {
+struct PytestTestCollector {
     project_dir: RootBuf<ProjectDir>,
     build_dir: RootBuf<BuildDir>,
-    cache_dir: RootBuf<CacheDir>,
-    client: &'client Client,
     test_layers: Mutex<HashMap<ImageRef, LayerSpec>>,
 }
 
-impl PytestTestCollector<'_> {
-    fn get_pip_packages(
-        &self,
-        image_spec: ImageRef,
-        ref_: &DockerReference,
-        ui: &UiSender,
-    ) -> Result<Utf8PathBuf> {
-        let fs = Fs::new();
-
-        // Build some paths
-        let cache_dir: &Path = self.cache_dir.as_ref();
-        let project_dir: &Path = self.project_dir.as_ref();
-        let packages_path: PathBuf = cache_dir.join(format!("pip_packages/{ref_}"));
-        if !fs.exists(&packages_path) {
-            fs.create_dir_all(&packages_path)?;
-        }
-        let source_req_path = project_dir.join("test-requirements.txt");
-        let saved_req_path = packages_path.join("requirements.txt");
-        let upper = packages_path.join("root");
-
-        // Are the existing packages up-to-date
-        let source_req = fs.read_to_string(&source_req_path)?;
-        let saved_req = fs.read_to_string_if_exists(&saved_req_path)?;
-        if Some(source_req) == saved_req {
-            return Ok(upper.try_into()?);
-        }
-
-        ui.send(UiMessage::UpdateEnqueueStatus(
-            "installing pip packages".into(),
-        ));
-
-        // Delete the work dir in case we have leaked it
-        let work = packages_path.join("work");
-        if fs.exists(&work) {
-            let inner_work = work.join("work");
-            if fs.exists(&inner_work) {
-                let mut work_perm = fs.metadata(&inner_work)?.permissions();
-                work_perm.set_mode(0o777);
-                fs.set_permissions(work.join("work"), work_perm)?;
-            }
-            fs.remove_dir_all(&work)?;
-        }
-
-        // Ensure the work and upper exist now
-        fs.create_dir_all(&work)?;
-        if !fs.exists(&upper) {
-            fs.create_dir_all(&upper)?;
-        }
-
-        // We need to install our own resolv.conf to get internet access
-        // These two addresses are for cloudflare's DNS server
-        let resolv_conf = packages_path.join("resolv.conf");
-        fs.write(&resolv_conf, b"nameserver 1.1.1.1\nnameserver 1.0.0.1")?;
-
-        // Run a local job to install the packages
-        let layers = vec![
-            LayerSpec::Paths(PathsLayerSpec {
-                paths: vec![source_req_path.clone().try_into()?],
-                prefix_options: Default::default(),
-            }),
-            LayerSpec::Stubs(StubsLayerSpec {
-                stubs: vec!["/dev/null".into()],
-            }),
-            LayerSpec::Paths(PathsLayerSpec {
-                paths: vec![resolv_conf.clone().try_into()?],
-                prefix_options: PrefixOptions {
-                    strip_prefix: Some(resolv_conf.parent().unwrap().to_owned().try_into()?),
-                    prepend_prefix: Some("/etc/".into()),
-                    canonicalize: false,
-                    follow_symlinks: false,
-                },
-            }),
-        ];
-        let (_, outcome) = self.client.run_job(job_spec! {
-            "/bin/sh",
-            layers: layers,
-            arguments: [
-                "-c".to_owned(),
-                format!(
-                    "
-                        set -ex
-                        pip install --requirement {}
-                        python -m compileall /usr/lib/python* /usr/local/lib/python*
-                        ",
-                    source_req_path
-                        .to_str()
-                        .ok_or_else(|| anyhow!("non-UTF8 path"))?
-                ),
-            ],
-            parent: ContainerParent::Image(image_spec),
-            network: JobNetwork::Local,
-            mounts: [
-                JobMount::Devices {
-                    devices: enum_set![JobDevice::Null],
-                },
-            ],
-            capture_file_system_changes: CaptureFileSystemChanges {
-                upper: upper.clone().try_into()?,
-                work: work.clone().try_into()?,
-            },
-        })?;
-        let outcome = outcome.map_err(|err| anyhow!("error installing pip packages: {err:?}"))?;
-        match outcome {
-            JobOutcome::Completed(completed) => {
-                if completed.status != JobTerminationStatus::Exited(0) {
-                    bail!(
-                        "pip install failed:\nstderr: {}\nstdout{}",
-                        completed.effects.stderr,
-                        completed.effects.stdout
-                    )
-                }
-            }
-            JobOutcome::TimedOut(_) => bail!("pip install timed out"),
-        }
-
-        // Delete any special character files
-        for path in fs.walk(&upper) {
-            let path = path?;
-            let meta = fs.symlink_metadata(&path)?;
-            if !(meta.is_file() || meta.is_dir() || meta.is_symlink()) {
-                fs.remove_file(path)?;
-            }
-        }
-
-        // Remove work
-        let mut work_perm = fs.metadata(work.join("work"))?.permissions();
-        work_perm.set_mode(0o777);
-        fs.set_permissions(work.join("work"), work_perm)?;
-        fs.remove_dir_all(work)?;
-
-        // Save requirements
-        fs.copy(source_req_path, saved_req_path)?;
-
-        Ok(upper.try_into()?)
-    }
-
-    fn build_test_layer(&self, image: ImageRef, ui: &UiSender) -> Result<Option<LayerSpec>> {
+impl PytestTestCollector {
+    /// Python images get a layer with the packages from `test-requirements.txt` installed. The
+    /// layer is built by the client the first time a test needs it, and rebuilt whenever
+    /// `test-requirements.txt` changes.
+    fn build_test_layer(&self, image: ImageRef) -> Result<Option<LayerSpec>> {
         let image_name: ImageName = image.name.parse()?;
         let ImageName::Docker(ref_) = image_name else {
             return Ok(None);
@@ -320,11 +178,18 @@ impl PytestTestCollector<'_> {
             return Ok(None);
         }
 
-        let packages_path = self.get_pip_packages(image, &ref_, ui)?;
-        let packages_path = packages_path.strip_prefix(&self.project_dir).unwrap();
-        Ok(Some(glob_layer_spec! {
-            format!("{packages_path}/**"),
-            strip_prefix: packages_path,
+        Ok(Some(setup_layer_spec! {
+            [
+                "/bin/sh",
+                "-c",
+                "
+                    set -ex
+                    pip install --requirement /test-requirements.txt
+                    python -m compileall /usr/lib/python* /usr/local/lib/python*
+                ",
+            ],
+            image: image.name,
+            inputs: ["test-requirements.txt"],
         }))
     }
 }
@@ -383,12 +248,10 @@ impl TestArtifact for PytestTestArtifact {
         &self,
         _case_name: &str,
         case_metadata: &PytestCaseMetadata,
-    ) -> (Utf8PathBuf, Vec<String>) {
-        let mut args = vec!["-m".into(), "pytest".into(), "--verbose".into()];
-        args.extend(self.pytest_options.extra_pytest_args.clone());
-        args.extend(self.pytest_options.extra_pytest_test_args.clone());
+    ) -> Result<(Utf8PathBuf, Vec<String>)> {
+        let mut args = self.pytest_args();
         args.push(case_metadata.node_id.clone());
-        ("/usr/local/bin/python".into(), args)
+        Ok(("/usr/local/bin/python".into(), args))
     }
 
     fn format_case(
@@ -411,6 +274,120 @@ impl TestArtifact for PytestTestArtifact {
             _ => vec![],
         }
     }
+
+    /// The module is the file a test case is in. The class is the innermost class a test case is
+    /// in, if any. Test cases that aren't in a class aren't grouped by class.
+    fn case_group(
+        &self,
+        _case_name: &str,
+        case_metadata: &PytestCaseMetadata,
+        group_by: GroupBy,
+    ) -> Option<String> {
+        let node_id = &case_metadata.node_id;
+        let path = node_id
+            .split_once('[')
+            .map_or(node_id.as_str(), |(path, _)| path);
+        let (parent, _) = path.rsplit_once("::")?;
+        match group_by {
+            GroupBy::Module => Some(parent.split("::").next().unwrap().into()),
+            GroupBy::Class => parent.contains("::").then(|| parent.into()),
+        }
+    }
+
+    fn build_group_command(
+        &self,
+        cases: &[(String, PytestCaseMetadata)],
+    ) -> Result<(Utf8PathBuf, Vec<String>)> {
+        let mut args = self.pytest_args();
+        args.push(format!("--junitxml={}", junit::report_path()));
+        args.push("--override-ini=junit_logging=all".into());
+        args.extend(
+            cases
+                .iter()
+                .map(|(_, case_metadata)| case_metadata.node_id.clone()),
+        );
+        Ok(("/usr/local/bin/python".into(), args))
+    }
+}
+
+impl PytestTestArtifact {
+    fn pytest_args(&self) -> Vec<String> {
+        let mut args = vec!["-m".into(), "pytest".into(), "--verbose".into()];
+        args.extend(self.pytest_options.extra_pytest_args.clone());
+        args.extend(self.pytest_options.extra_pytest_test_args.clone());
+        args
+    }
+}
+
+#[cfg(test)]
+fn artifact_for_grouping() -> PytestTestArtifact {
+    PytestTestArtifact {
+        path: "tests/test_foo.py".into(),
+        tests: vec![],
+        ignored_tests: vec![],
+        package: PytestPackageId("default".into()),
+        pytest_options: Default::default(),
+        test_layers: HashMap::new(),
+    }
+}
+
+#[cfg(test)]
+fn case_metadata(node_id: &str) -> PytestCaseMetadata {
+    PytestCaseMetadata {
+        node_id: node_id.into(),
+        markers: vec![],
+    }
+}
+
+#[test]
+fn case_group_module_and_class() {
+    let artifact = artifact_for_grouping();
+    let group = |node_id, group_by| artifact.case_group("", &case_metadata(node_id), group_by);
+    assert_eq!(
+        group("tests/test_foo.py::test_a", GroupBy::Module).as_deref(),
+        Some("tests/test_foo.py")
+    );
+    assert_eq!(group("tests/test_foo.py::test_a", GroupBy::Class), None);
+    assert_eq!(
+        group(
+            "tests/test_foo.py::TestA::TestB::test_a[x::y]",
+            GroupBy::Module
+        )
+        .as_deref(),
+        Some("tests/test_foo.py")
+    );
+    assert_eq!(
+        group(
+            "tests/test_foo.py::TestA::TestB::test_a[x::y]",
+            GroupBy::Class
+        )
+        .as_deref(),
+        Some("tests/test_foo.py::TestA::TestB")
+    );
+}
+
+#[test]
+fn build_group_command_writes_report() {
+    let artifact = artifact_for_grouping();
+    let (program, args) = artifact
+        .build_group_command(&[
+            ("a".into(), case_metadata("tests/test_foo.py::test_a")),
+            ("b".into(), case_metadata("tests/test_foo.py::test_b")),
+        ])
+        .unwrap();
+    assert_eq!(program, "/usr/local/bin/python");
+    assert_eq!(
+        args,
+        vec![
+            "-m",
+            "pytest",
+            "--verbose",
+            "--junitxml=/maelstrom-group-report/junit.xml",
+            "--override-ini=junit_logging=all",
+            "tests/test_foo.py::test_a",
+            "tests/test_foo.py::test_b",
+        ]
+    );
 }
 
 #[derive(Clone, Debug)]
@@ -437,7 +414,7 @@ impl TestPackage for PytestPackage {
     }
 }
 
-impl CollectTests for PytestTestCollector<'_> {
+impl CollectTests for PytestTestCollector {
     const ENQUEUE_MESSAGE: &'static str = "collecting tests...";
 
     type BuildHandle = pytest::WaitHandle;
@@ -468,11 +445,11 @@ impl CollectTests for PytestTestCollector<'_> {
         Ok((handle, stream))
     }
 
-    fn build_test_layers(&self, images: HashSet<ImageRef>, ui: &UiSender) -> Result<()> {
+    fn build_test_layers(&self, images: HashSet<ImageRef>, _ui: &UiSender) -> Result<()> {
         let mut test_layers = self.test_layers.lock().unwrap();
         for image in images {
             if let Entry::Vacant(e) = test_layers.entry(image.clone()) {
-                if let Some(layer) = self.build_test_layer(image, ui)? {
+                if let Some(layer) = self.build_test_layer(image)? {
                     e.insert(layer);
                 }
             }
@@ -488,6 +465,64 @@ impl CollectTests for PytestTestCollector<'_> {
         }])
     }
 
+    fn get_impact(
+        &self,
+        packages: &[PytestPackage],
+        changed_paths: &BTreeSet<PathBuf>,
+        _ui: &UiSender,
+    ) -> Result<Option<TestImpact<PytestPackageId, PytestArtifactKey>>> {
+        // Artifact paths are relative to the canonicalized project directory.
+        let project_dir: &Path = self.project_dir.as_ref();
+        let canonical_project_dir = project_dir.canonicalize()?;
+        let mut relative_changed_paths = BTreeSet::new();
+        for path in changed_paths {
+            let Ok(path) = path
+                .strip_prefix(&canonical_project_dir)
+                .or_else(|_| path.strip_prefix(project_dir))
+            else {
+                return Ok(None);
+            };
+            relative_changed_paths.insert(path.to_owned());
+        }
+
+        let mut impact = TestImpact::default();
+        for package in packages {
+            let mut sources = BTreeMap::new();
+            for artifact in &package.artifacts {
+                if artifact.path.extension().is_some_and(|ext| ext == "py") {
+                    let path = canonical_project_dir.join(&artifact.path);
+                    if let Some(source) = Fs.read_to_string_if_exists(path)? {
+                        sources.insert(artifact.path.clone(), source);
+                    }
+                }
+            }
+            let Some(impacted) = imports::find_impacted_files(&sources, &relative_changed_paths)
+            else {
+                return Ok(None);
+            };
+            for path in impacted {
+                impact.add_artifact(package.id.clone(), PytestArtifactKey { path });
+            }
+        }
+        Ok(Some(impact))
+    }
+
+    fn get_group_results(
+        cases: &[(String, PytestCaseMetadata)],
+        files: &[CapturedFile],
+    ) -> Result<Vec<GroupCaseResult>> {
+        let mut results = vec![];
+        for file in files {
+            if file.path.file_name() == Some(junit::REPORT_NAME) {
+                results.extend(junit::read_report(
+                    std::str::from_utf8(&file.contents)?,
+                    cases,
+                )?);
+            }
+        }
+        Ok(results)
+    }
+
     fn remove_fixture_output(_case_str: &str, mut lines: Vec<String>) -> Vec<String> {
         let start_re = Regex::new("=+ FAILURES =+").unwrap();
         let end_re = Regex::new("=+ short test summary info =+").unwrap();
@@ -552,10 +587,10 @@ fn remove_fixture_output_basic_case() {
     );
 }
 
-impl<'client> MainAppDeps for DefaultMainAppDeps<'client> {
-    type TestCollector = PytestTestCollector<'client>;
+impl MainAppDeps for DefaultMainAppDeps {
+    type TestCollector = PytestTestCollector;
 
-    fn test_collector(&self) -> &PytestTestCollector<'client> {
+    fn test_collector(&self) -> &PytestTestCollector {
         &self.test_collector
     }
 
@@ -613,6 +648,7 @@ pub fn main_with_stderr_and_project_dir(
     let log = logger.build(logging_output.clone());
 
     let list_action = extra_options.list.then_some(ListAction::ListTests);
+    let export_image = extra_options.parent.export_image();
     let build_dir = AsRef::<Path>::as_ref(project_dir).join(".maelstrom-pytest");
     let build_dir = Root::<BuildDir>::new(&build_dir);
     let state_dir = build_dir.join::<StateDir>("state");
@@ -635,7 +671,7 @@ pub fn main_with_stderr_and_project_dir(
         config.parent.artifact_transfer_strategy,
         log.clone(),
     )?;
-    let deps = DefaultMainAppDeps::new(project_dir, build_dir, &cache_dir, &client)?;
+    let deps = DefaultMainAppDeps::new(project_dir, build_dir)?;
 
     run_app_with_ui_multithreaded(
         logging_output,
@@ -645,6 +681,7 @@ pub fn main_with_stderr_and_project_dir(
         extra_options.parent.include,
         extra_options.parent.exclude,
         list_action,
+        export_image,
         config.parent.repeat,
         config.parent.stop_after,
         extra_options.parent.watch,
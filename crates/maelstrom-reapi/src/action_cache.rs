@@ -0,0 +1,259 @@
+//! The action cache. It maps the digests of actions to their results, so build systems can skip
+//! executing actions that have already been executed. Results are kept on disk, one file per
+//! action. A result is only returned if all of the outputs it refers to are still in the CAS.
+
+use crate::{
+    cas::{parse_digest, Cas},
+    proto::reapi::{
+        self, action_cache_server, ActionResult, GetActionResultRequest, UpdateActionResultRequest,
+    },
+};
+use anyhow::Result;
+use maelstrom_base::Sha256Digest;
+use maelstrom_util::async_fs::Fs;
+use prost::Message as _;
+use std::{
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+use tonic::{Request, Response, Status};
+
+pub struct ActionCache {
+    fs: Fs,
+    root: PathBuf,
+    next_temp: AtomicU64,
+    cas: Arc<Cas>,
+}
+
+impl ActionCache {
+    pub async fn new(root: impl AsRef<Path>, cas: Arc<Cas>) -> Result<Self> {
+        let fs = Fs::new();
+        fs.create_dir_all(root.as_ref()).await?;
+        Ok(Self {
+            fs,
+            root: root.as_ref().to_owned(),
+            next_temp: Default::default(),
+            cas,
+        })
+    }
+
+    fn path(&self, action_digest: &Sha256Digest) -> PathBuf {
+        self.root.join(action_digest.to_string())
+    }
+
+    pub async fn get(&self, action_digest: &Sha256Digest) -> Result<Option<ActionResult>> {
+        let path = self.path(action_digest);
+        if !self.fs.exists(&path).await {
+            return Ok(None);
+        }
+        let result = ActionResult::decode(self.fs.read(&path).await?.as_slice())?;
+        if !self.outputs_present(&result).await? {
+            return Ok(None);
+        }
+        Ok(Some(result))
+    }
+
+    /// Return whether all of the blobs referred to by `result` are in the CAS. Some of them may
+    /// have been evicted since the result was stored.
+    async fn outputs_present(&self, result: &ActionResult) -> Result<bool> {
+        let mut digests = vec![];
+        digests.extend(&result.stdout_digest);
+        digests.extend(&result.stderr_digest);
+        digests.extend(result.output_files.iter().filter_map(|f| f.digest.as_ref()));
+        let mut trees = vec![];
+        for directory in &result.output_directories {
+            let digest = parse_digest(directory.tree_digest.as_ref())?;
+            let Some(tree) = self.cas.read_message::<reapi::Tree>(&digest).await? else {
+                return Ok(false);
+            };
+            trees.push(tree);
+        }
+        for tree in &trees {
+            for directory in tree.root.iter().chain(&tree.children) {
+                digests.extend(directory.files.iter().filter_map(|f| f.digest.as_ref()));
+            }
+        }
+        for digest in digests {
+            if !self.cas.contains(&parse_digest(Some(digest))?).await {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    pub async fn put(&self, action_digest: &Sha256Digest, result: &ActionResult) -> Result<()> {
+        // Write to a temporary file first so that readers never see a partial result.
+        let temp_path = self.root.join(format!(
+            ".tmp-{}",
+            self.next_temp.fetch_add(1, Ordering::Relaxed)
+        ));
+        self.fs.write(&temp_path, result.encode_to_vec()).await?;
+        self.fs.rename(&temp_path, self.path(action_digest)).await
+    }
+}
+
+pub struct ActionCacheService {
+    action_cache: Arc<ActionCache>,
+    allow_updates: bool,
+}
+
+impl ActionCacheService {
+    pub fn new(action_cache: Arc<ActionCache>, allow_updates: bool) -> Self {
+        Self {
+            action_cache,
+            allow_updates,
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl action_cache_server::ActionCache for ActionCacheService {
+    async fn get_action_result(
+        &self,
+        request: Request<GetActionResultRequest>,
+    ) -> Result<Response<ActionResult>, Status> {
+        let digest = parse_digest(request.into_inner().action_digest.as_ref())?;
+        match self.action_cache.get(&digest).await {
+            Ok(Some(result)) => Ok(Response::new(result)),
+            Ok(None) => Err(Status::not_found(format!("no result for action {digest}"))),
+            Err(err) => Err(Status::internal(err.to_string())),
+        }
+    }
+
+    async fn update_action_result(
+        &self,
+        request: Request<UpdateActionResultRequest>,
+    ) -> Result<Response<ActionResult>, Status> {
+        if !self.allow_updates {
+            return Err(Status::permission_denied(
+                "action cache updates are disabled; see the allow-action-cache-updates option",
+            ));
+        }
+        let request = request.into_inner();
+        let digest = parse_digest(request.action_digest.as_ref())?;
+        let result = request
+            .action_result
+            .ok_or_else(|| Status::invalid_argument("missing action result"))?;
+        self.action_cache
+            .put(&digest, &result)
+            .await
+            .map_err(|err| Status::internal(err.to_string()))?;
+        Ok(Response::new(result))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{cas::digest_of, proto::reapi::action_cache_server::ActionCache as _};
+    use tempfile::TempDir;
+    use tonic::Code;
+
+    async fn service(temp_dir: &TempDir, cas_size: u64, allow_updates: bool) -> ActionCacheService {
+        let cas = Arc::new(
+            Cas::new(temp_dir.path().join("cas"), cas_size)
+                .await
+                .unwrap(),
+        );
+        let action_cache = ActionCache::new(temp_dir.path().join("action-cache"), cas)
+            .await
+            .unwrap();
+        ActionCacheService::new(Arc::new(action_cache), allow_updates)
+    }
+
+    async fn get(
+        service: &ActionCacheService,
+        action_digest: &reapi::Digest,
+    ) -> Result<Response<ActionResult>, Status> {
+        service
+            .get_action_result(Request::new(GetActionResultRequest {
+                action_digest: Some(action_digest.clone()),
+                ..Default::default()
+            }))
+            .await
+    }
+
+    async fn update(
+        service: &ActionCacheService,
+        action_digest: &reapi::Digest,
+        result: ActionResult,
+    ) -> Result<Response<ActionResult>, Status> {
+        service
+            .update_action_result(Request::new(UpdateActionResultRequest {
+                action_digest: Some(action_digest.clone()),
+                action_result: Some(result),
+                ..Default::default()
+            }))
+            .await
+    }
+
+    #[tokio::test]
+    async fn update_then_get() {
+        let temp_dir = TempDir::new().unwrap();
+        let service = service(&temp_dir, u64::MAX, true).await;
+        let action_digest = digest_of(b"action");
+
+        assert_eq!(
+            get(&service, &action_digest).await.unwrap_err().code(),
+            Code::NotFound
+        );
+
+        let result = ActionResult {
+            exit_code: 3,
+            ..Default::default()
+        };
+        update(&service, &action_digest, result.clone())
+            .await
+            .unwrap();
+        assert_eq!(
+            get(&service, &action_digest).await.unwrap().into_inner(),
+            result
+        );
+    }
+
+    #[tokio::test]
+    async fn update_disabled() {
+        let temp_dir = TempDir::new().unwrap();
+        let service = service(&temp_dir, u64::MAX, false).await;
+        let action_digest = digest_of(b"action");
+
+        let err = update(&service, &action_digest, ActionResult::default())
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::PermissionDenied);
+        assert_eq!(
+            get(&service, &action_digest).await.unwrap_err().code(),
+            Code::NotFound
+        );
+    }
+
+    #[tokio::test]
+    async fn get_with_evicted_output() {
+        let temp_dir = TempDir::new().unwrap();
+        let service = service(&temp_dir, 3, true).await;
+        let cas = &service.action_cache.cas;
+        let action_digest = digest_of(b"action");
+        let stdout_digest = cas.insert(b"out").await.unwrap();
+
+        let result = ActionResult {
+            stdout_digest: Some(stdout_digest),
+            ..Default::default()
+        };
+        update(&service, &action_digest, result.clone())
+            .await
+            .unwrap();
+        assert_eq!(
+            get(&service, &action_digest).await.unwrap().into_inner(),
+            result
+        );
+
+        cas.insert(b"new").await.unwrap();
+        assert_eq!(
+            get(&service, &action_digest).await.unwrap_err().code(),
+            Code::NotFound
+        );
+    }
+}
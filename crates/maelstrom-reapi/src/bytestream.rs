@@ -0,0 +1,213 @@
+//! The ByteStream service, which build systems use to read and write CAS blobs that are too large
+//! for batch requests. Blobs are named by resource names of the form
+//! `[{instance_name}/]blobs/{hash}/{size}` for reads, and
+//! `[{instance_name}/]uploads/{uuid}/blobs/{hash}/{size}[/{metadata}]` for writes.
+
+use crate::{
+    cas::Cas,
+    proto::google::bytestream::{
+        byte_stream_server::ByteStream, QueryWriteStatusRequest, QueryWriteStatusResponse,
+        ReadRequest, ReadResponse, WriteRequest, WriteResponse,
+    },
+};
+use futures::{Stream, StreamExt as _};
+use maelstrom_base::Sha256Digest;
+use maelstrom_util::{async_fs::Fs, io::Sha256Stream};
+use std::{io::SeekFrom, pin::Pin, sync::Arc};
+use tokio::io::{AsyncReadExt as _, AsyncSeekExt as _, AsyncWriteExt as _};
+use tonic::{Request, Response, Status, Streaming};
+
+const READ_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Find the digest and size in a resource name. We ignore the instance name and everything else.
+#[allow(clippy::result_large_err)]
+fn parse_resource_name(resource_name: &str) -> Result<(Sha256Digest, u64), Status> {
+    let invalid = || Status::invalid_argument(format!("invalid resource name {resource_name:?}"));
+    let mut parts = resource_name.split('/');
+    parts.find(|part| *part == "blobs").ok_or_else(invalid)?;
+    let digest = parts
+        .next()
+        .and_then(|hash| hash.parse().ok())
+        .ok_or_else(invalid)?;
+    let size = parts
+        .next()
+        .and_then(|size| size.parse().ok())
+        .ok_or_else(invalid)?;
+    Ok((digest, size))
+}
+
+fn internal(err: impl ToString) -> Status {
+    Status::internal(err.to_string())
+}
+
+pub struct ByteStreamService {
+    cas: Arc<Cas>,
+}
+
+impl ByteStreamService {
+    pub fn new(cas: Arc<Cas>) -> Self {
+        Self { cas }
+    }
+}
+
+#[tonic::async_trait]
+impl ByteStream for ByteStreamService {
+    type ReadStream = Pin<Box<dyn Stream<Item = Result<ReadResponse, Status>> + Send>>;
+
+    async fn read(
+        &self,
+        request: Request<ReadRequest>,
+    ) -> Result<Response<Self::ReadStream>, Status> {
+        let request = request.into_inner();
+        let (digest, size) = parse_resource_name(&request.resource_name)?;
+        let offset = u64::try_from(request.read_offset)
+            .ok()
+            .filter(|offset| *offset <= size)
+            .ok_or_else(|| Status::out_of_range("read offset out of range"))?;
+        let limit = match u64::try_from(request.read_limit) {
+            Ok(0) => size - offset,
+            Ok(limit) => limit.min(size - offset),
+            Err(_) => return Err(Status::invalid_argument("negative read limit")),
+        };
+        if !self.cas.contains(&digest).await {
+            return Err(Status::not_found(format!("blob {digest} not found")));
+        }
+
+        let mut file = Fs::new()
+            .open_file(self.cas.blob_path(&digest))
+            .await
+            .map_err(internal)?
+            .into_inner();
+        file.seek(SeekFrom::Start(offset)).await?;
+        let stream = futures::stream::unfold((file, limit), |(mut file, remaining)| async move {
+            if remaining == 0 {
+                return None;
+            }
+            let mut data = vec![0; READ_CHUNK_SIZE.min(remaining as usize)];
+            match file.read(&mut data).await {
+                Ok(0) => Some((Err(Status::data_loss("unexpected end of blob")), (file, 0))),
+                Ok(read) => {
+                    data.truncate(read);
+                    Some((Ok(ReadResponse { data }), (file, remaining - read as u64)))
+                }
+                Err(err) => Some((Err(internal(err)), (file, 0))),
+            }
+        });
+        Ok(Response::new(Box::pin(stream)))
+    }
+
+    async fn write(
+        &self,
+        request: Request<Streaming<WriteRequest>>,
+    ) -> Result<Response<WriteResponse>, Status> {
+        let mut stream = request.into_inner();
+        let Some(first) = stream.next().await.transpose()? else {
+            return Err(Status::invalid_argument("empty write"));
+        };
+        let (digest, size) = parse_resource_name(&first.resource_name)?;
+        if self.cas.contains(&digest).await {
+            // We already have the blob, so we can short-circuit the upload.
+            return Ok(Response::new(WriteResponse {
+                committed_size: size as i64,
+            }));
+        }
+
+        let temp_path = self.cas.temp_path();
+        let file = Fs::new()
+            .create_file(&temp_path)
+            .await
+            .map_err(internal)?
+            .into_inner();
+        let mut file = Sha256Stream::new(file);
+        let mut committed = 0;
+        let mut request = Some(first);
+        loop {
+            let Some(WriteRequest {
+                write_offset,
+                finish_write,
+                data,
+                ..
+            }) = request.take()
+            else {
+                return Err(Status::invalid_argument(
+                    "write ended before it was finished",
+                ));
+            };
+            if write_offset != committed as i64 {
+                return Err(Status::invalid_argument(format!(
+                    "write offset {write_offset} doesn't match committed size {committed}"
+                )));
+            }
+            file.write_all(&data).await?;
+            committed += data.len() as u64;
+            if finish_write {
+                break;
+            }
+            request = stream.next().await.transpose()?;
+        }
+        file.flush().await?;
+
+        let (_, actual) = file.finalize();
+        if committed != size {
+            return Err(Status::invalid_argument(format!(
+                "wrote {committed} bytes, but expected {size}"
+            )));
+        }
+        actual
+            .verify(&digest)
+            .map_err(|err| Status::invalid_argument(err.to_string()))?;
+        self.cas
+            .insert_file(&temp_path, &digest)
+            .await
+            .map_err(internal)?;
+        Ok(Response::new(WriteResponse {
+            committed_size: committed as i64,
+        }))
+    }
+
+    /// We don't support resuming uploads, so the only writes we know about are complete ones.
+    async fn query_write_status(
+        &self,
+        request: Request<QueryWriteStatusRequest>,
+    ) -> Result<Response<QueryWriteStatusResponse>, Status> {
+        let (digest, size) = parse_resource_name(&request.into_inner().resource_name)?;
+        if self.cas.contains(&digest).await {
+            Ok(Response::new(QueryWriteStatusResponse {
+                committed_size: size as i64,
+                complete: true,
+            }))
+        } else {
+            Err(Status::not_found(format!("no write for blob {digest}")))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cas::digest_of;
+
+    #[test]
+    fn parse_resource_names() {
+        let digest = digest_of(b"foo");
+        let expected = (digest.hash.parse().unwrap(), 3);
+        assert_eq!(
+            parse_resource_name(&format!("blobs/{}/3", digest.hash)).unwrap(),
+            expected
+        );
+        assert_eq!(
+            parse_resource_name(&format!("instance/blobs/{}/3", digest.hash)).unwrap(),
+            expected
+        );
+        assert_eq!(
+            parse_resource_name(&format!(
+                "instance/uploads/4c5d/blobs/{}/3/metadata",
+                digest.hash
+            ))
+            .unwrap(),
+            expected
+        );
+        assert!(parse_resource_name("instance/blobs/abc/3").is_err());
+        assert!(parse_resource_name(&format!("blobs/{}", digest.hash)).is_err());
+    }
+}
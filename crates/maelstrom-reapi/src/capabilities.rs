@@ -0,0 +1,58 @@
+//! The Capabilities service, which build systems query to find out what we support.
+
+use crate::proto::{
+    build::bazel::semver::SemVer,
+    reapi::{
+        capabilities_server::Capabilities, digest_function::Value as DigestFunction,
+        symlink_absolute_path_strategy::Value as SymlinkAbsolutePathStrategy,
+        ActionCacheUpdateCapabilities, CacheCapabilities, ExecutionCapabilities,
+        GetCapabilitiesRequest, ServerCapabilities,
+    },
+};
+use tonic::{Request, Response, Status};
+
+/// The largest batch request we advertise. Build systems use ByteStream for anything larger. This
+/// keeps batch requests under gRPC's default 4 MiB message limit.
+const MAX_BATCH_TOTAL_SIZE_BYTES: i64 = 4 * 1024 * 1024 - 64 * 1024;
+
+fn version(major: i32, minor: i32) -> SemVer {
+    SemVer {
+        major,
+        minor,
+        ..Default::default()
+    }
+}
+
+pub struct CapabilitiesService {
+    /// Whether build systems may store their own results in the action cache.
+    pub update_enabled: bool,
+}
+
+#[tonic::async_trait]
+impl Capabilities for CapabilitiesService {
+    async fn get_capabilities(
+        &self,
+        _request: Request<GetCapabilitiesRequest>,
+    ) -> Result<Response<ServerCapabilities>, Status> {
+        Ok(Response::new(ServerCapabilities {
+            cache_capabilities: Some(CacheCapabilities {
+                digest_functions: vec![DigestFunction::Sha256.into()],
+                action_cache_update_capabilities: Some(ActionCacheUpdateCapabilities {
+                    update_enabled: self.update_enabled,
+                }),
+                cache_priority_capabilities: None,
+                max_batch_total_size_bytes: MAX_BATCH_TOTAL_SIZE_BYTES,
+                symlink_absolute_path_strategy: SymlinkAbsolutePathStrategy::Allowed.into(),
+            }),
+            execution_capabilities: Some(ExecutionCapabilities {
+                digest_function: DigestFunction::Sha256.into(),
+                exec_enabled: true,
+                execution_priority_capabilities: None,
+                digest_functions: vec![DigestFunction::Sha256.into()],
+            }),
+            deprecated_api_version: None,
+            low_api_version: Some(version(2, 0)),
+            high_api_version: Some(version(2, 3)),
+        }))
+    }
+}
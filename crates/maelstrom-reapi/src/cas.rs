@@ -0,0 +1,510 @@
+//! The content-addressable storage (CAS). Blobs are kept on disk, one file per blob, named by their
+//! SHA-256 digest. Build systems upload actions and their inputs here before executing them, and
+//! the outputs of executed actions are stored here for build systems to download. When the blobs
+//! grow larger than the target size, the least recently used ones are evicted.
+
+use crate::proto::{
+    google::rpc,
+    reapi::{
+        self, batch_read_blobs_response, batch_update_blobs_response,
+        compressor::Value as Compressor,
+        content_addressable_storage_server::ContentAddressableStorage, BatchReadBlobsRequest,
+        BatchReadBlobsResponse, BatchUpdateBlobsRequest, BatchUpdateBlobsResponse,
+        FindMissingBlobsRequest, FindMissingBlobsResponse, GetTreeRequest, GetTreeResponse,
+    },
+};
+use anyhow::{anyhow, Result};
+use futures::Stream;
+use maelstrom_base::Sha256Digest;
+use maelstrom_util::async_fs::Fs;
+use sha2::{Digest as _, Sha256};
+use std::{
+    collections::{BTreeSet, HashMap, HashSet, VecDeque},
+    path::{Path, PathBuf},
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+use tokio::sync::Mutex;
+use tonic::{Code, Request, Response, Status};
+
+/// Parse a REAPI digest into one of ours. We only support SHA-256.
+#[allow(clippy::result_large_err)]
+pub fn parse_digest(digest: Option<&reapi::Digest>) -> Result<Sha256Digest, Status> {
+    let digest = digest.ok_or_else(|| Status::invalid_argument("missing digest"))?;
+    digest
+        .hash
+        .parse()
+        .map_err(|_| Status::invalid_argument(format!("invalid SHA-256 digest {:?}", digest.hash)))
+}
+
+/// Compute the REAPI digest of `data`.
+pub fn digest_of(data: &[u8]) -> reapi::Digest {
+    reapi::Digest {
+        hash: Sha256Digest::new(Sha256::digest(data).into()).to_string(),
+        size_bytes: data.len() as i64,
+    }
+}
+
+/// Create a [`rpc::Status`] to embed in a response.
+pub fn rpc_status(code: Code, message: impl Into<String>) -> rpc::Status {
+    rpc::Status {
+        code: code as i32,
+        message: message.into(),
+        details: vec![],
+    }
+}
+
+/// The blobs in the CAS, ordered by when they were last used.
+#[derive(Default)]
+struct Index {
+    /// The size and last use of each blob.
+    blobs: HashMap<Sha256Digest, (u64, u64)>,
+    by_last_use: BTreeSet<(u64, Sha256Digest)>,
+    next_use: u64,
+    bytes_used: u64,
+}
+
+impl Index {
+    /// Mark the blob as the most recently used. Returns false if the blob isn't in the index.
+    fn touch(&mut self, digest: &Sha256Digest) -> bool {
+        let Some((_, last_use)) = self.blobs.get_mut(digest) else {
+            return false;
+        };
+        self.by_last_use.remove(&(*last_use, digest.clone()));
+        *last_use = self.next_use;
+        self.next_use += 1;
+        self.by_last_use.insert((*last_use, digest.clone()));
+        true
+    }
+
+    fn insert(&mut self, digest: Sha256Digest, size: u64) {
+        if self.touch(&digest) {
+            return;
+        }
+        self.blobs.insert(digest.clone(), (size, self.next_use));
+        self.by_last_use.insert((self.next_use, digest));
+        self.next_use += 1;
+        self.bytes_used += size;
+    }
+
+    /// Remove least recently used blobs until the index fits in `target_bytes`. The most recently
+    /// used blob is never removed. Returns the removed blobs.
+    fn evict(&mut self, target_bytes: u64) -> Vec<Sha256Digest> {
+        let mut evicted = vec![];
+        while self.bytes_used > target_bytes && self.by_last_use.len() > 1 {
+            let (_, digest) = self.by_last_use.pop_first().unwrap();
+            let (size, _) = self.blobs.remove(&digest).unwrap();
+            self.bytes_used -= size;
+            evicted.push(digest);
+        }
+        evicted
+    }
+}
+
+pub struct Cas {
+    fs: Fs,
+    blobs: PathBuf,
+    temp: PathBuf,
+    next_temp: AtomicU64,
+    target_bytes: u64,
+    index: Mutex<Index>,
+}
+
+impl Cas {
+    /// Open the CAS rooted at `root`, creating it if necessary. Any partial uploads left over from
+    /// a previous run are removed. Blobs left over from a previous run are kept, ordered by their
+    /// modification times, and the CAS is then trimmed to `target_bytes`.
+    pub async fn new(root: impl AsRef<Path>, target_bytes: u64) -> Result<Self> {
+        let fs = Fs::new();
+        let blobs = root.as_ref().join("blobs");
+        let temp = root.as_ref().join("tmp");
+        fs.create_dir_all(&blobs).await?;
+        if fs.exists(&temp).await {
+            fs.remove_dir_all(&temp).await?;
+        }
+        fs.create_dir_all(&temp).await?;
+
+        let mut existing = vec![];
+        let mut entries = fs.read_dir(&blobs).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let Some(digest) = entry
+                .file_name()
+                .to_str()
+                .and_then(|name| name.parse::<Sha256Digest>().ok())
+            else {
+                continue;
+            };
+            let metadata = entry.metadata().await?;
+            let size = metadata.len();
+            existing.push((metadata.into_inner().modified()?, digest, size));
+        }
+        existing.sort();
+        let mut index = Index::default();
+        for (_, digest, size) in existing {
+            index.insert(digest, size);
+        }
+
+        let cas = Self {
+            fs,
+            blobs,
+            temp,
+            next_temp: Default::default(),
+            target_bytes,
+            index: Mutex::new(index),
+        };
+        cas.evict().await?;
+        Ok(cas)
+    }
+
+    async fn evict(&self) -> Result<()> {
+        let mut index = self.index.lock().await;
+        // Remove the files while holding the lock, so that a blob being evicted can't be added
+        // back before its file is gone.
+        for digest in index.evict(self.target_bytes) {
+            self.fs.remove_file(self.blob_path(&digest)).await?;
+        }
+        Ok(())
+    }
+
+    pub fn blob_path(&self, digest: &Sha256Digest) -> PathBuf {
+        self.blobs.join(digest.to_string())
+    }
+
+    /// Return a new path in the CAS's temporary directory. Files written there can be moved into
+    /// the CAS with [`Self::insert_file`].
+    pub fn temp_path(&self) -> PathBuf {
+        self.temp
+            .join(self.next_temp.fetch_add(1, Ordering::Relaxed).to_string())
+    }
+
+    /// Return whether the blob is in the CAS. This counts as a use of the blob, since it's
+    /// usually followed by one.
+    pub async fn contains(&self, digest: &Sha256Digest) -> bool {
+        self.index.lock().await.touch(digest)
+    }
+
+    pub async fn read(&self, digest: &Sha256Digest) -> Result<Option<Vec<u8>>> {
+        if !self.contains(digest).await {
+            return Ok(None);
+        }
+        Ok(Some(self.fs.read(self.blob_path(digest)).await?))
+    }
+
+    /// Read and decode a protobuf message. Returns `None` if the blob isn't in the CAS.
+    pub async fn read_message<MessageT: prost::Message + Default>(
+        &self,
+        digest: &Sha256Digest,
+    ) -> Result<Option<MessageT>> {
+        let Some(data) = self.read(digest).await? else {
+            return Ok(None);
+        };
+        Ok(Some(MessageT::decode(data.as_slice())?))
+    }
+
+    /// Store `data`, which must have the given digest.
+    pub async fn put(&self, digest: &Sha256Digest, data: &[u8]) -> Result<()> {
+        let actual: Sha256Digest = digest_of(data).hash.parse()?;
+        actual.verify(digest)?;
+        if self.contains(digest).await {
+            return Ok(());
+        }
+        let temp_path = self.temp_path();
+        self.fs.write(&temp_path, data).await?;
+        self.insert_file(&temp_path, digest).await
+    }
+
+    /// Store `data`, returning its digest.
+    pub async fn insert(&self, data: &[u8]) -> Result<reapi::Digest> {
+        let digest = digest_of(data);
+        self.put(&digest.hash.parse()?, data).await?;
+        Ok(digest)
+    }
+
+    /// Store a protobuf message, returning its digest.
+    pub async fn insert_message(&self, message: &impl prost::Message) -> Result<reapi::Digest> {
+        self.insert(&message.encode_to_vec()).await
+    }
+
+    /// Move the file at `path` into the CAS. The caller must have verified that the file has the
+    /// given digest. This may evict other blobs.
+    pub async fn insert_file(&self, path: &Path, digest: &Sha256Digest) -> Result<()> {
+        let size = self.fs.metadata(path).await?.len();
+        {
+            let mut index = self.index.lock().await;
+            self.fs.rename(path, self.blob_path(digest)).await?;
+            index.insert(digest.clone(), size);
+        }
+        self.evict().await
+    }
+
+    /// Read all of the directories in the tree rooted at `root`, in breadth-first order. Each
+    /// directory is returned along with its digest. If a directory is missing from the CAS, its
+    /// digest is returned as the inner error.
+    pub async fn read_tree(
+        &self,
+        root: &reapi::Digest,
+    ) -> Result<Result<Vec<(Sha256Digest, reapi::Directory)>, reapi::Digest>> {
+        let mut directories = vec![];
+        let mut seen = HashSet::new();
+        let mut queue = VecDeque::from([root.clone()]);
+        while let Some(reapi_digest) = queue.pop_front() {
+            let digest = parse_digest(Some(&reapi_digest)).map_err(|s| anyhow!("{s}"))?;
+            if !seen.insert(digest.clone()) {
+                continue;
+            }
+            let Some(directory) = self.read_message::<reapi::Directory>(&digest).await? else {
+                return Ok(Err(reapi_digest));
+            };
+            queue.extend(
+                directory
+                    .directories
+                    .iter()
+                    .filter_map(|d| d.digest.clone()),
+            );
+            directories.push((digest, directory));
+        }
+        Ok(Ok(directories))
+    }
+}
+
+pub struct CasService {
+    cas: Arc<Cas>,
+}
+
+impl CasService {
+    pub fn new(cas: Arc<Cas>) -> Self {
+        Self { cas }
+    }
+}
+
+#[tonic::async_trait]
+impl ContentAddressableStorage for CasService {
+    async fn find_missing_blobs(
+        &self,
+        request: Request<FindMissingBlobsRequest>,
+    ) -> Result<Response<FindMissingBlobsResponse>, Status> {
+        let mut missing_blob_digests = vec![];
+        for reapi_digest in request.into_inner().blob_digests {
+            let digest = parse_digest(Some(&reapi_digest))?;
+            if !self.cas.contains(&digest).await {
+                missing_blob_digests.push(reapi_digest);
+            }
+        }
+        Ok(Response::new(FindMissingBlobsResponse {
+            missing_blob_digests,
+        }))
+    }
+
+    async fn batch_update_blobs(
+        &self,
+        request: Request<BatchUpdateBlobsRequest>,
+    ) -> Result<Response<BatchUpdateBlobsResponse>, Status> {
+        let mut responses = vec![];
+        for blob in request.into_inner().requests {
+            let status = if blob.compressor() != Compressor::Identity {
+                rpc_status(Code::InvalidArgument, "compressed blobs aren't supported")
+            } else {
+                match parse_digest(blob.digest.as_ref()) {
+                    Err(status) => rpc_status(status.code(), status.message()),
+                    Ok(digest) => match self.cas.put(&digest, &blob.data).await {
+                        Ok(()) => rpc_status(Code::Ok, ""),
+                        Err(err) => rpc_status(Code::InvalidArgument, err.to_string()),
+                    },
+                }
+            };
+            responses.push(batch_update_blobs_response::Response {
+                digest: blob.digest,
+                status: Some(status),
+            });
+        }
+        Ok(Response::new(BatchUpdateBlobsResponse { responses }))
+    }
+
+    async fn batch_read_blobs(
+        &self,
+        request: Request<BatchReadBlobsRequest>,
+    ) -> Result<Response<BatchReadBlobsResponse>, Status> {
+        let mut responses = vec![];
+        for reapi_digest in request.into_inner().digests {
+            let digest = parse_digest(Some(&reapi_digest))?;
+            let (data, status) = match self.cas.read(&digest).await {
+                Ok(Some(data)) => (data, rpc_status(Code::Ok, "")),
+                Ok(None) => (vec![], rpc_status(Code::NotFound, "blob not found")),
+                Err(err) => (vec![], rpc_status(Code::Internal, err.to_string())),
+            };
+            responses.push(batch_read_blobs_response::Response {
+                digest: Some(reapi_digest),
+                data,
+                status: Some(status),
+                compressor: Compressor::Identity.into(),
+            });
+        }
+        Ok(Response::new(BatchReadBlobsResponse { responses }))
+    }
+
+    type GetTreeStream = Pin<Box<dyn Stream<Item = Result<GetTreeResponse, Status>> + Send>>;
+
+    /// Return the whole tree in one page. We don't support pagination.
+    async fn get_tree(
+        &self,
+        request: Request<GetTreeRequest>,
+    ) -> Result<Response<Self::GetTreeStream>, Status> {
+        let root = request
+            .into_inner()
+            .root_digest
+            .ok_or_else(|| Status::invalid_argument("missing root digest"))?;
+        let directories = self
+            .cas
+            .read_tree(&root)
+            .await
+            .map_err(|err| Status::internal(err.to_string()))?
+            .map_err(|missing| {
+                Status::not_found(format!("directory {} not found", missing.hash))
+            })?;
+        let response = GetTreeResponse {
+            directories: directories.into_iter().map(|(_, d)| d).collect(),
+            next_page_token: String::new(),
+        };
+        Ok(Response::new(Box::pin(futures::stream::iter([Ok(
+            response,
+        )]))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    async fn cas() -> (TempDir, CasService) {
+        let temp_dir = TempDir::new().unwrap();
+        let cas = Cas::new(temp_dir.path(), u64::MAX).await.unwrap();
+        (temp_dir, CasService::new(Arc::new(cas)))
+    }
+
+    #[tokio::test]
+    async fn update_then_find_missing_and_read() {
+        let (_temp_dir, service) = cas().await;
+        let foo = digest_of(b"foo");
+        let bar = digest_of(b"bar");
+
+        let response = service
+            .batch_update_blobs(Request::new(BatchUpdateBlobsRequest {
+                requests: vec![
+                    batch_update_blobs_request_entry(&foo, b"foo"),
+                    batch_update_blobs_request_entry(&bar, b"not bar"),
+                ],
+                ..Default::default()
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        let codes = Vec::from_iter(
+            response
+                .responses
+                .iter()
+                .map(|r| r.status.as_ref().unwrap().code),
+        );
+        assert_eq!(codes, vec![Code::Ok as i32, Code::InvalidArgument as i32]);
+
+        let response = service
+            .find_missing_blobs(Request::new(FindMissingBlobsRequest {
+                blob_digests: vec![foo.clone(), bar.clone()],
+                ..Default::default()
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(response.missing_blob_digests, vec![bar.clone()]);
+
+        let response = service
+            .batch_read_blobs(Request::new(BatchReadBlobsRequest {
+                digests: vec![foo, bar],
+                ..Default::default()
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(response.responses[0].data, b"foo");
+        assert_eq!(
+            response.responses[1].status.as_ref().unwrap().code,
+            Code::NotFound as i32
+        );
+    }
+
+    fn batch_update_blobs_request_entry(
+        digest: &reapi::Digest,
+        data: &[u8],
+    ) -> reapi::batch_update_blobs_request::Request {
+        reapi::batch_update_blobs_request::Request {
+            digest: Some(digest.clone()),
+            data: data.to_vec(),
+            compressor: Compressor::Identity.into(),
+        }
+    }
+
+    #[tokio::test]
+    async fn evicts_least_recently_used() {
+        let temp_dir = TempDir::new().unwrap();
+        let cas = Cas::new(temp_dir.path(), 6).await.unwrap();
+        let digest = |data: &[u8]| digest_of(data).hash.parse::<Sha256Digest>().unwrap();
+        let (a, b, c) = (digest(b"aaa"), digest(b"bbb"), digest(b"ccc"));
+        cas.put(&a, b"aaa").await.unwrap();
+        cas.put(&b, b"bbb").await.unwrap();
+        assert!(cas.contains(&a).await);
+        cas.put(&c, b"ccc").await.unwrap();
+
+        assert!(cas.contains(&a).await);
+        assert!(!cas.contains(&b).await);
+        assert!(cas.contains(&c).await);
+        assert!(!Fs::new().exists(cas.blob_path(&b)).await);
+
+        // The blobs are found again when the CAS is reopened, oldest first.
+        drop(cas);
+        let cas = Cas::new(temp_dir.path(), 3).await.unwrap();
+        assert_eq!(cas.index.lock().await.blobs.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn read_tree() {
+        let (_temp_dir, service) = cas().await;
+        let cas = &service.cas;
+        let leaf = reapi::Directory {
+            files: vec![reapi::FileNode {
+                name: "file".into(),
+                digest: Some(cas.insert(b"contents").await.unwrap()),
+                is_executable: false,
+            }],
+            ..Default::default()
+        };
+        let leaf_digest = cas.insert_message(&leaf).await.unwrap();
+        let missing = digest_of(b"missing");
+        let root = reapi::Directory {
+            directories: vec![
+                reapi::DirectoryNode {
+                    name: "a".into(),
+                    digest: Some(leaf_digest.clone()),
+                },
+                reapi::DirectoryNode {
+                    name: "b".into(),
+                    digest: Some(leaf_digest.clone()),
+                },
+            ],
+            ..Default::default()
+        };
+        let root_digest = cas.insert_message(&root).await.unwrap();
+
+        let tree = cas.read_tree(&root_digest).await.unwrap().unwrap();
+        assert_eq!(
+            tree,
+            vec![
+                (root_digest.hash.parse().unwrap(), root),
+                (leaf_digest.hash.parse().unwrap(), leaf),
+            ]
+        );
+        assert_eq!(cas.read_tree(&missing).await.unwrap(), Err(missing));
+    }
+}
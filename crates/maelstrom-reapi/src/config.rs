@@ -0,0 +1,122 @@
+use maelstrom_client::{
+    AcceptInvalidRemoteContainerTlsCerts, CacheDir, ContainerImageDepotDir, StateDir,
+};
+use maelstrom_macro::Config;
+use maelstrom_util::{
+    config::common::{
+        ArtifactTransferStrategy, BrokerAddr, CacheSize, InlineLimit, LogLevel, Slots,
+    },
+    root::RootBuf,
+};
+use std::net::{IpAddr, Ipv4Addr};
+use xdg::BaseDirectories;
+
+#[derive(Config, Debug)]
+pub struct Config {
+    /// The port to serve the Remote Execution API on.
+    #[config(short = 'p', value_name = "PORT", default = "8980")]
+    pub port: u16,
+
+    /// The address to serve the Remote Execution API on. There is no authentication, so anyone
+    /// who can connect can run actions, so only listen on other interfaces if they're trusted.
+    #[config(value_name = "ADDRESS", default = "IpAddr::from(Ipv4Addr::LOCALHOST)")]
+    pub address: IpAddr,
+
+    /// Allow build systems to store the results of actions they ran themselves in the action
+    /// cache. These results are trusted as-is, so only enable this if all clients are trusted.
+    #[config(flag)]
+    pub allow_action_cache_updates: bool,
+
+    /// Socket address of broker. If not provided, all actions will be run locally.
+    #[config(
+        option,
+        short = 'b',
+        value_name = "SOCKADDR",
+        default = r#""standalone mode""#
+    )]
+    pub broker: Option<BrokerAddr>,
+
+    /// Minimum log level to output.
+    #[config(short = 'l', value_name = "LEVEL", default = r#""info""#)]
+    pub log_level: LogLevel,
+
+    /// The container image to run actions in when they don't specify one with the
+    /// `container-image` platform property.
+    #[config(option, value_name = "IMAGE", default = r#""no default image""#)]
+    pub default_container_image: Option<String>,
+
+    /// Directory for state that persists between runs, including the client's log file.
+    #[config(
+        value_name = "PATH",
+        default = r#"|bd: &BaseDirectories| {
+            bd.get_state_home()
+                .into_os_string()
+                .into_string()
+                .unwrap()
+        }"#
+    )]
+    pub state_root: RootBuf<StateDir>,
+
+    /// Directory to use for the cache. The CAS, the action cache, and the local worker's cache
+    /// will be contained within it.
+    #[config(
+        value_name = "PATH",
+        default = r#"|bd: &BaseDirectories| {
+            bd.get_cache_home()
+                .into_os_string()
+                .into_string()
+                .unwrap()
+        }"#
+    )]
+    pub cache_root: RootBuf<CacheDir>,
+
+    /// The target amount of disk space to use for the CAS. When it grows past this, the least
+    /// recently used blobs are evicted. SI and binary suffixes are supported.
+    #[config(value_name = "BYTES", default = "CacheSize::from(10_000_000_000)")]
+    pub cas_size: CacheSize,
+
+    /// The target amount of disk space to use for the local worker's cache. This bound won't be
+    /// followed strictly, so it's best to be conservative. SI and binary suffixes are supported.
+    #[config(
+        value_name = "BYTES",
+        default = "CacheSize::default()",
+        next_help_heading = "Local Worker Config Options"
+    )]
+    pub cache_size: CacheSize,
+
+    /// The maximum amount of bytes to return inline for captured stdout and stderr.
+    #[config(value_name = "BYTES", default = "InlineLimit::default()")]
+    pub inline_limit: InlineLimit,
+
+    /// The number of job slots available.
+    #[config(value_name = "N", default = "Slots::default()")]
+    pub slots: Slots,
+
+    /// Directory in which to put cached container images.
+    #[config(
+        value_name = "PATH",
+        default = r#"|bd: &BaseDirectories| {
+            bd.get_cache_home()
+                .parent()
+                .unwrap()
+                .join("container/")
+                .into_os_string()
+                .into_string()
+                .unwrap()
+        }"#,
+        next_help_heading = "Container Image Config Options"
+    )]
+    pub container_image_depot_root: RootBuf<ContainerImageDepotDir>,
+
+    /// Accept invalid TLS certificates when downloading container images.
+    #[config(flag)]
+    pub accept_invalid_remote_container_tls_certs: AcceptInvalidRemoteContainerTlsCerts,
+
+    /// Controls how we upload artifacts when communicating with a remote broker.
+    #[config(
+        value_name = "ARTIFACT_TRANSFER_STRATEGY",
+        default = r#""tcp-upload""#,
+        hide
+    )]
+    pub artifact_transfer_strategy: ArtifactTransferStrategy,
+}
@@ -0,0 +1,1524 @@
+//! The Execution service. Each action is translated into a Maelstrom job. The action's input root
+//! is materialized from the CAS into a local directory, which the Maelstrom client turns into
+//! manifest layers on top of the action's container image. The job's program is a small shell
+//! script that runs the action's command with its standard output and standard error redirected to
+//! files, then copies its outputs into capture mounts, which are sent back with the job's results.
+//! The outputs are then stored in the CAS.
+
+use crate::{
+    action_cache::ActionCache,
+    cas::{parse_digest, rpc_status, Cas},
+    proto::{
+        google::{longrunning::Operation, rpc},
+        reapi::{
+            self, execution_server::Execution, execution_stage::Value as ExecutionStage, Action,
+            ActionResult, Command, ExecuteOperationMetadata, ExecuteRequest, ExecuteResponse,
+            OutputDirectory, OutputFile, OutputSymlink, WaitExecutionRequest,
+        },
+    },
+};
+use anyhow::{anyhow, Result};
+use futures::Stream;
+use maelstrom_base::{
+    CapturedFile, EnumSet, JobCompleted, JobDevice, JobEffects, JobError, JobMount, JobOutcome,
+    JobOutcomeResult, JobOutputResult, JobTerminationStatus, Sha256Digest, Timeout, Utf8Path,
+    Utf8PathBuf,
+};
+use maelstrom_client::{
+    spec::{
+        ContainerParent, ContainerSpec, EnvironmentSpec, ImageRef, ImageUse, JobSpec, LayerSpec,
+        PathsLayerSpec, PrefixOptions, StubsLayerSpec, SymlinkSpec, SymlinksLayerSpec,
+    },
+    Client, JobStatus,
+};
+use maelstrom_util::async_fs::Fs;
+use prost::Message as _;
+use std::{
+    collections::{BTreeMap, HashMap},
+    os::unix::fs::PermissionsExt as _,
+    path::{Path, PathBuf},
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+use tokio::sync::{oneshot, watch};
+use tonic::{Code, Request, Response, Status};
+
+/// Where the action's input root is placed in the container.
+const INPUT_ROOT: &str = "/maelstrom-reapi/input";
+
+/// The capture mount that the action's outputs are copied into.
+const OUTPUTS_DIR: &str = "/maelstrom-reapi/outputs";
+
+/// The capture mount for information about the outputs. Capture mounts only preserve the contents
+/// of regular files, so we record which outputs are directories, which files are executable, and
+/// the targets of symlinks.
+/// The action's standard output and standard error are also written here, so that they aren't
+/// truncated at the inline limit.
+const METADATA_DIR: &str = "/maelstrom-reapi/metadata";
+
+/// How long a completed operation can still be waited on. A client whose `Execute` stream was cut
+/// off just before the operation completed can still get its result with `WaitExecution`.
+const COMPLETED_OPERATION_TTL: Duration = Duration::from_secs(60);
+
+/// How we run jobs. This is a trait so that tests can run jobs without a Maelstrom client.
+pub trait JobRunner: Send + Sync + 'static {
+    fn run_job(&self, spec: JobSpec, done: oneshot::Sender<Result<JobOutcomeResult>>);
+}
+
+impl JobRunner for Client {
+    fn run_job(&self, spec: JobSpec, done: oneshot::Sender<Result<JobOutcomeResult>>) {
+        let done = Arc::new(Mutex::new(Some(done)));
+        let handler_done = done.clone();
+        let handler = move |status: Result<JobStatus>| {
+            let result = match status {
+                Ok(JobStatus::Running(_)) => return,
+                Ok(JobStatus::Completed { result, .. }) => Ok(result),
+                Err(err) => Err(err),
+            };
+            if let Some(done) = handler_done.lock().unwrap().take() {
+                let _ = done.send(result);
+            }
+        };
+        if let Err(err) = self.add_job(spec, handler) {
+            if let Some(done) = done.lock().unwrap().take() {
+                let _ = done.send(Err(err));
+            }
+        }
+    }
+}
+
+/// Choose the container image to run the action in. The action's platform takes precedence over
+/// the command's, which is deprecated.
+fn container_image(
+    action: &Action,
+    command: &Command,
+    default: Option<&str>,
+) -> Result<String, String> {
+    [&action.platform, &command.platform]
+        .into_iter()
+        .flatten()
+        .flat_map(|platform| &platform.properties)
+        .find(|property| property.name == "container-image")
+        .map(|property| property.value.as_str())
+        .or(default)
+        .map(|image| image.strip_prefix("docker://").unwrap_or(image).to_owned())
+        .ok_or_else(|| {
+            "action has no container-image platform property and there is no default container \
+                image"
+                .to_owned()
+        })
+}
+
+/// The paths of the action's outputs, relative to the working directory.
+fn output_paths(command: &Command) -> Vec<String> {
+    if command.output_paths.is_empty() {
+        command
+            .output_files
+            .iter()
+            .chain(&command.output_directories)
+            .cloned()
+            .collect()
+    } else {
+        command.output_paths.clone()
+    }
+}
+
+fn shell_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', r"'\''"))
+}
+
+/// The script that runs the action's command and then copies its outputs into the capture mounts.
+/// It exits with the command's exit status.
+fn wrapper_script(outputs: &[String]) -> String {
+    let outputs = outputs
+        .iter()
+        .map(|output| shell_quote(output))
+        .collect::<Vec<_>>()
+        .join(" ");
+    format!(
+        r#"o={OUTPUTS_DIR}
+m={METADATA_DIR}
+"$@" >"$m/stdout" 2>"$m/stderr"
+status=$?
+PATH=/usr/local/sbin:/usr/local/bin:/usr/sbin:/usr/bin:/sbin:/bin
+symlink() {{
+  printf '%s\n%s\n' "$1" "$(readlink "$1")" >>"$m/symlinks"
+}}
+walk() {{
+  for f in "$1"/* "$1"/.[!.]* "$1"/..?*; do
+    if [ -L "$f" ]; then
+      symlink "$f"
+    elif [ -d "$f" ]; then
+      printf '%s\n' "$f" >>"$m/directories"
+      walk "$f"
+    elif [ -f "$f" ] && [ -x "$f" ]; then
+      printf '%s\n' "$f" >>"$m/executables"
+    fi
+  done
+}}
+for p in {outputs}; do
+  if [ -L "$p" ]; then
+    symlink "$p"
+  elif [ -d "$p" ]; then
+    mkdir -p "$o/$p" && cp -R "$p/." "$o/$p" && printf '%s\n' "$p" >>"$m/directories"
+    walk "$p"
+  elif [ -f "$p" ]; then
+    mkdir -p "$o/$(dirname "$p")" && cp "$p" "$o/$p"
+    if [ -x "$p" ]; then printf '%s\n' "$p" >>"$m/executables"; fi
+  fi
+done
+exit $status
+"#
+    )
+}
+
+/// The contents of an action's input root, flattened. Paths are relative to the input root.
+#[derive(Debug, Default, PartialEq)]
+struct InputRoot {
+    directories: Vec<Utf8PathBuf>,
+    files: Vec<(Utf8PathBuf, Sha256Digest, bool)>,
+    symlinks: Vec<(Utf8PathBuf, String)>,
+}
+
+fn check_name(name: &str) -> Result<&str, String> {
+    if name.is_empty() || name == "." || name == ".." || name.contains('/') {
+        Err(format!("invalid name {name:?} in input root"))
+    } else {
+        Ok(name)
+    }
+}
+
+impl InputRoot {
+    fn new(
+        root: &Sha256Digest,
+        directories: &HashMap<Sha256Digest, reapi::Directory>,
+    ) -> Result<Self, String> {
+        let mut input_root = Self::default();
+        input_root.add_directory(Utf8Path::new(""), root, directories)?;
+        Ok(input_root)
+    }
+
+    fn add_directory(
+        &mut self,
+        path: &Utf8Path,
+        digest: &Sha256Digest,
+        directories: &HashMap<Sha256Digest, reapi::Directory>,
+    ) -> Result<(), String> {
+        let directory = &directories[digest];
+        for file in &directory.files {
+            let digest = parse_digest(file.digest.as_ref()).map_err(|s| s.message().to_owned())?;
+            self.files.push((
+                path.join(check_name(&file.name)?),
+                digest,
+                file.is_executable,
+            ));
+        }
+        for symlink in &directory.symlinks {
+            self.symlinks.push((
+                path.join(check_name(&symlink.name)?),
+                symlink.target.clone(),
+            ));
+        }
+        for child in &directory.directories {
+            let child_path = path.join(check_name(&child.name)?);
+            let digest = parse_digest(child.digest.as_ref()).map_err(|s| s.message().to_owned())?;
+            self.directories.push(child_path.clone());
+            self.add_directory(&child_path, &digest, directories)?;
+        }
+        Ok(())
+    }
+
+    /// Write the input root into `dir`. Files are hard-linked from the CAS when possible.
+    /// Executable files are always copied, since they need different permissions than the blobs
+    /// in the CAS.
+    async fn materialize(&self, cas: &Cas, dir: &Path) -> Result<()> {
+        let fs = Fs::new();
+        for directory in &self.directories {
+            fs.create_dir_all(dir.join(directory)).await?;
+        }
+        for (path, digest, executable) in &self.files {
+            let blob = cas.blob_path(digest);
+            let path = dir.join(path);
+            if *executable {
+                fs.copy(&blob, &path).await?;
+                fs.open_file(&path)
+                    .await?
+                    .set_permissions(std::fs::Permissions::from_mode(0o755))
+                    .await?;
+            } else if fs.hard_link(&blob, &path).await.is_err() {
+                fs.copy(&blob, &path).await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Build the job for the action. `input_root_dir` is where the input root was materialized.
+fn job_spec(
+    action: &Action,
+    command: &Command,
+    image: String,
+    input_root: &InputRoot,
+    input_root_dir: &Utf8Path,
+) -> Result<JobSpec, String> {
+    let (program, arguments) = command
+        .arguments
+        .split_first()
+        .ok_or_else(|| "command has no arguments".to_owned())?;
+
+    let working_directory = Utf8Path::new(INPUT_ROOT).join(&command.working_directory);
+    let mut stubs = vec![
+        "/proc/".to_owned(),
+        "/tmp/".to_owned(),
+        "/dev/full".to_owned(),
+        "/dev/null".to_owned(),
+        "/dev/random".to_owned(),
+        "/dev/urandom".to_owned(),
+        "/dev/zero".to_owned(),
+        format!("{OUTPUTS_DIR}/"),
+        format!("{METADATA_DIR}/"),
+        format!("{INPUT_ROOT}/"),
+        format!("{working_directory}/"),
+    ];
+    stubs.extend(
+        input_root
+            .directories
+            .iter()
+            .map(|directory| format!("{INPUT_ROOT}/{directory}/")),
+    );
+    let mut layers = vec![LayerSpec::Stubs(StubsLayerSpec { stubs })];
+    if !input_root.files.is_empty() {
+        layers.push(LayerSpec::Paths(PathsLayerSpec {
+            paths: input_root
+                .files
+                .iter()
+                .map(|(path, _, _)| input_root_dir.join(path))
+                .collect(),
+            prefix_options: PrefixOptions {
+                strip_prefix: Some(input_root_dir.to_owned()),
+                prepend_prefix: Some(INPUT_ROOT.into()),
+                ..Default::default()
+            },
+        }));
+    }
+    if !input_root.symlinks.is_empty() {
+        layers.push(LayerSpec::Symlinks(SymlinksLayerSpec {
+            symlinks: input_root
+                .symlinks
+                .iter()
+                .map(|(link, target)| SymlinkSpec {
+                    link: Utf8Path::new(INPUT_ROOT).join(link),
+                    target: target.into(),
+                })
+                .collect(),
+        }));
+    }
+
+    let timeout = action.timeout.as_ref().and_then(|timeout| {
+        let seconds = timeout.seconds + i64::from(timeout.nanos > 0);
+        Timeout::new(seconds.try_into().unwrap_or(u32::MAX))
+    });
+
+    Ok(JobSpec {
+        container: ContainerSpec {
+            parent: Some(ContainerParent::Image(ImageRef {
+                name: image,
+                r#use: EnumSet::only(ImageUse::Layers),
+            })),
+            layers,
+            environment: vec![EnvironmentSpec {
+                vars: command
+                    .environment_variables
+                    .iter()
+                    .map(|var| (var.name.clone(), var.value.clone()))
+                    .collect(),
+                extend: false,
+            }],
+            working_directory: Some(working_directory),
+            enable_writable_file_system: Some(true),
+            mounts: vec![
+                JobMount::Devices {
+                    devices: JobDevice::Full
+                        | JobDevice::Null
+                        | JobDevice::Random
+                        | JobDevice::Urandom
+                        | JobDevice::Zero,
+                },
+                JobMount::Proc {
+                    mount_point: "/proc".into(),
+                },
+                JobMount::Tmp {
+                    mount_point: "/tmp".into(),
+                },
+                JobMount::Capture {
+                    mount_point: OUTPUTS_DIR.into(),
+                },
+                JobMount::Capture {
+                    mount_point: METADATA_DIR.into(),
+                },
+            ],
+            ..Default::default()
+        },
+        program: "/bin/sh".into(),
+        arguments: [
+            "-c".to_owned(),
+            wrapper_script(&output_paths(command)),
+            "sh".to_owned(),
+            program.clone(),
+        ]
+        .into_iter()
+        .chain(arguments.iter().cloned())
+        .collect(),
+        timeout,
+        estimated_duration: None,
+        allocate_tty: None,
+        priority: 0,
+        capture_file_system_changes: None,
+        exclusive: false,
+    })
+}
+
+/// The action's standard output or standard error. The wrapper script normally redirects them to
+/// files in the metadata capture mount, in which case they're in `captured`. If the script didn't
+/// get that far, we fall back to what the job wrote, which may have been truncated. In that case,
+/// a note saying so is added to `truncated`.
+fn output_bytes(
+    name: &str,
+    captured: Option<Box<[u8]>>,
+    output: JobOutputResult,
+    truncated: &mut Vec<String>,
+) -> Box<[u8]> {
+    if let Some(captured) = captured {
+        return captured;
+    }
+    match output {
+        JobOutputResult::None => Box::new([]),
+        JobOutputResult::Inline(bytes) => bytes,
+        JobOutputResult::Truncated {
+            first,
+            truncated: dropped,
+        } => {
+            truncated.push(format!("{name} was truncated, dropping {dropped} bytes"));
+            first
+        }
+    }
+}
+
+/// The contents of an output directory. Paths are relative to the output directory.
+#[derive(Default)]
+struct OutputTree {
+    files: Vec<(Utf8PathBuf, Box<[u8]>, bool)>,
+    directories: Vec<Utf8PathBuf>,
+    symlinks: Vec<(Utf8PathBuf, String)>,
+}
+
+/// Build a [`reapi::Tree`] out of an output directory, storing it and its files in the CAS.
+async fn output_tree(cas: &Cas, tree: OutputTree) -> Result<reapi::Digest> {
+    #[derive(Default)]
+    struct Node {
+        files: BTreeMap<String, (reapi::Digest, bool)>,
+        directories: BTreeMap<String, Node>,
+        symlinks: BTreeMap<String, String>,
+    }
+
+    impl Node {
+        /// Return the node for the directory at `path`, creating it and its ancestors if needed.
+        fn directory(&mut self, path: &Utf8Path) -> &mut Self {
+            path.components().fold(self, |node, component| {
+                node.directories
+                    .entry(component.as_str().to_owned())
+                    .or_default()
+            })
+        }
+    }
+
+    let mut root = Node::default();
+    for path in tree.directories {
+        root.directory(&path);
+    }
+    for (path, contents, executable) in tree.files {
+        let (Some(parent), Some(name)) = (path.parent(), path.file_name()) else {
+            continue;
+        };
+        let digest = cas.insert(&contents).await?;
+        root.directory(parent)
+            .files
+            .insert(name.to_owned(), (digest, executable));
+    }
+    for (path, target) in tree.symlinks {
+        let (Some(parent), Some(name)) = (path.parent(), path.file_name()) else {
+            continue;
+        };
+        root.directory(parent)
+            .symlinks
+            .insert(name.to_owned(), target);
+    }
+
+    /// Turn a node into a [`reapi::Directory`], adding all of its descendants to `children`.
+    fn directory(node: Node, children: &mut Vec<reapi::Directory>) -> reapi::Directory {
+        reapi::Directory {
+            files: node
+                .files
+                .into_iter()
+                .map(|(name, (digest, is_executable))| reapi::FileNode {
+                    name,
+                    digest: Some(digest),
+                    is_executable,
+                })
+                .collect(),
+            directories: node
+                .directories
+                .into_iter()
+                .map(|(name, node)| {
+                    let child = directory(node, children);
+                    let digest = crate::cas::digest_of(&child.encode_to_vec());
+                    children.push(child);
+                    reapi::DirectoryNode {
+                        name,
+                        digest: Some(digest),
+                    }
+                })
+                .collect(),
+            symlinks: node
+                .symlinks
+                .into_iter()
+                .map(|(name, target)| reapi::SymlinkNode { name, target })
+                .collect(),
+        }
+    }
+
+    let mut children = vec![];
+    let root = directory(root, &mut children);
+    cas.insert_message(&reapi::Tree {
+        root: Some(root),
+        children,
+    })
+    .await
+}
+
+/// Turn the job's effects into an [`ActionResult`], storing outputs in the CAS. Also returns notes
+/// about any of the action's output that was truncated. Results with truncated output shouldn't be
+/// cached.
+async fn action_result(
+    cas: &Cas,
+    command: &Command,
+    exit_code: i32,
+    effects: JobEffects,
+) -> Result<(ActionResult, Vec<String>)> {
+    let mut outputs = HashMap::new();
+    let mut directories = vec![];
+    let mut executables = vec![];
+    let mut symlinks = HashMap::new();
+    let mut stdout = None;
+    let mut stderr = None;
+    for CapturedFile { path, contents } in effects.captured_files {
+        if let Ok(path) = path.strip_prefix(OUTPUTS_DIR) {
+            outputs.insert(path.to_owned(), contents);
+        } else if path == Utf8Path::new(METADATA_DIR).join("stdout") {
+            stdout = Some(contents);
+        } else if path == Utf8Path::new(METADATA_DIR).join("stderr") {
+            stderr = Some(contents);
+        } else if path == Utf8Path::new(METADATA_DIR).join("directories") {
+            directories = String::from_utf8_lossy(&contents)
+                .lines()
+                .map(Utf8PathBuf::from)
+                .collect();
+        } else if path == Utf8Path::new(METADATA_DIR).join("executables") {
+            executables = String::from_utf8_lossy(&contents)
+                .lines()
+                .map(Utf8PathBuf::from)
+                .collect();
+        } else if path == Utf8Path::new(METADATA_DIR).join("symlinks") {
+            // Each symlink is a line with its path followed by a line with its target.
+            let contents = String::from_utf8_lossy(&contents);
+            let mut lines = contents.lines();
+            while let (Some(path), Some(target)) = (lines.next(), lines.next()) {
+                symlinks.insert(Utf8PathBuf::from(path), target.to_owned());
+            }
+        }
+    }
+
+    let mut output_files = vec![];
+    let mut output_directories = vec![];
+    let mut output_symlinks = vec![];
+    let mut output_file_symlinks = vec![];
+    let mut output_directory_symlinks = vec![];
+    for output in output_paths(command) {
+        let output = Utf8PathBuf::from(output);
+        if let Some(target) = symlinks.get(&output) {
+            let symlink = OutputSymlink {
+                path: output.to_string(),
+                target: target.clone(),
+            };
+            // Clients that use the deprecated output fields expect symlinks in the matching
+            // deprecated result fields.
+            if command.output_paths.is_empty() {
+                if command.output_files.contains(&symlink.path) {
+                    output_file_symlinks.push(symlink.clone());
+                } else {
+                    output_directory_symlinks.push(symlink.clone());
+                }
+            }
+            output_symlinks.push(symlink);
+        } else if directories.contains(&output) {
+            let relative = |path: &Utf8PathBuf| {
+                path.strip_prefix(&output)
+                    .ok()
+                    .filter(|relative| !relative.as_str().is_empty())
+                    .map(Utf8Path::to_owned)
+            };
+            let tree = OutputTree {
+                files: outputs
+                    .iter()
+                    .filter_map(|(path, contents)| {
+                        Some((
+                            relative(path)?,
+                            contents.clone(),
+                            executables.contains(path),
+                        ))
+                    })
+                    .collect(),
+                directories: directories.iter().filter_map(relative).collect(),
+                symlinks: symlinks
+                    .iter()
+                    .filter_map(|(path, target)| Some((relative(path)?, target.clone())))
+                    .collect(),
+            };
+            output_directories.push(OutputDirectory {
+                path: output.into_string(),
+                tree_digest: Some(output_tree(cas, tree).await?),
+                is_topologically_sorted: false,
+            });
+        } else if let Some(contents) = outputs.get(&output) {
+            output_files.push(OutputFile {
+                digest: Some(cas.insert(contents).await?),
+                is_executable: executables.contains(&output),
+                path: output.into_string(),
+                contents: vec![],
+            });
+        }
+    }
+
+    let mut truncated = vec![];
+    let stdout = output_bytes("standard output", stdout, effects.stdout, &mut truncated);
+    let stderr = output_bytes("standard error", stderr, effects.stderr, &mut truncated);
+    let result = ActionResult {
+        output_files,
+        output_directories,
+        exit_code,
+        stdout_raw: vec![],
+        stdout_digest: Some(cas.insert(&stdout).await?),
+        stderr_raw: vec![],
+        stderr_digest: Some(cas.insert(&stderr).await?),
+        execution_metadata: None,
+        output_file_symlinks,
+        output_directory_symlinks,
+        output_symlinks,
+    };
+    Ok((result, truncated))
+}
+
+fn missing_blobs_status(digests: impl IntoIterator<Item = reapi::Digest>) -> rpc::Status {
+    let violations = digests
+        .into_iter()
+        .map(|digest| rpc::precondition_failure::Violation {
+            r#type: "MISSING".into(),
+            subject: format!("blobs/{}/{}", digest.hash, digest.size_bytes),
+            description: String::new(),
+        })
+        .collect();
+    rpc::Status {
+        details: vec![prost_types::Any {
+            type_url: "type.googleapis.com/google.rpc.PreconditionFailure".into(),
+            value: rpc::PreconditionFailure { violations }.encode_to_vec(),
+        }],
+        ..rpc_status(Code::FailedPrecondition, "missing blobs")
+    }
+}
+
+fn error_response(status: rpc::Status) -> ExecuteResponse {
+    ExecuteResponse {
+        status: Some(status),
+        ..Default::default()
+    }
+}
+
+pub struct Executor<JobRunnerT> {
+    runner: JobRunnerT,
+    cas: Arc<Cas>,
+    action_cache: Arc<ActionCache>,
+    inputs_dir: PathBuf,
+    default_container_image: Option<String>,
+}
+
+impl<JobRunnerT: JobRunner> Executor<JobRunnerT> {
+    pub fn new(
+        runner: JobRunnerT,
+        cas: Arc<Cas>,
+        action_cache: Arc<ActionCache>,
+        inputs_dir: PathBuf,
+        default_container_image: Option<String>,
+    ) -> Self {
+        Self {
+            runner,
+            cas,
+            action_cache,
+            inputs_dir,
+            default_container_image,
+        }
+    }
+
+    /// Execute the action, calling `executing` once its job has been submitted. Errors are
+    /// reported in the response's status.
+    async fn execute(&self, request: ExecuteRequest, executing: impl FnOnce()) -> ExecuteResponse {
+        match self.execute_inner(request, executing).await {
+            Ok(response) => response,
+            Err(err) => error_response(rpc_status(Code::Internal, format!("{err:#}"))),
+        }
+    }
+
+    async fn execute_inner(
+        &self,
+        request: ExecuteRequest,
+        executing: impl FnOnce(),
+    ) -> Result<ExecuteResponse> {
+        let invalid =
+            |message: String| Ok(error_response(rpc_status(Code::InvalidArgument, message)));
+        let reapi_action_digest = request.action_digest.unwrap_or_default();
+        let action_digest = match parse_digest(Some(&reapi_action_digest)) {
+            Ok(digest) => digest,
+            Err(status) => return invalid(status.message().into()),
+        };
+
+        if !request.skip_cache_lookup {
+            if let Some(result) = self.action_cache.get(&action_digest).await? {
+                return Ok(ExecuteResponse {
+                    result: Some(result),
+                    cached_result: true,
+                    ..Default::default()
+                });
+            }
+        }
+
+        let Some(action) = self.cas.read_message::<Action>(&action_digest).await? else {
+            return Ok(error_response(missing_blobs_status([reapi_action_digest])));
+        };
+        let reapi_command_digest = action.command_digest.clone().unwrap_or_default();
+        let command_digest = match parse_digest(Some(&reapi_command_digest)) {
+            Ok(digest) => digest,
+            Err(status) => return invalid(status.message().into()),
+        };
+        let Some(command) = self.cas.read_message::<Command>(&command_digest).await? else {
+            return Ok(error_response(missing_blobs_status([reapi_command_digest])));
+        };
+        let reapi_input_root_digest = action.input_root_digest.clone().unwrap_or_default();
+        let directories = match self.cas.read_tree(&reapi_input_root_digest).await? {
+            Ok(directories) => directories,
+            Err(missing) => return Ok(error_response(missing_blobs_status([missing]))),
+        };
+        let Some((input_root_digest, _)) = directories.first().cloned() else {
+            return invalid("empty input root".into());
+        };
+        let input_root = match InputRoot::new(&input_root_digest, &HashMap::from_iter(directories))
+        {
+            Ok(input_root) => input_root,
+            Err(message) => return invalid(message),
+        };
+        let mut missing = vec![];
+        for (_, digest, _) in &input_root.files {
+            if !self.cas.contains(digest).await {
+                missing.push(digest.clone());
+            }
+        }
+        if !missing.is_empty() {
+            // We don't know the sizes of the missing blobs without going back through the
+            // directories, so look them up.
+            let mut sizes = HashMap::new();
+            for (_, directory) in self
+                .cas
+                .read_tree(&reapi_input_root_digest)
+                .await?
+                .unwrap_or_default()
+            {
+                for file in directory.files {
+                    if let Some(digest) = file.digest {
+                        sizes.insert(digest.hash.clone(), digest);
+                    }
+                }
+            }
+            missing.sort();
+            missing.dedup();
+            return Ok(error_response(missing_blobs_status(
+                missing
+                    .into_iter()
+                    .filter_map(|digest| sizes.remove(&digest.to_string())),
+            )));
+        }
+
+        let image =
+            match container_image(&action, &command, self.default_container_image.as_deref()) {
+                Ok(image) => image,
+                Err(message) => {
+                    return Ok(error_response(rpc_status(
+                        Code::FailedPrecondition,
+                        message,
+                    )))
+                }
+            };
+
+        let input_root_dir = tempfile::Builder::new()
+            .prefix(&action_digest.to_string())
+            .tempdir_in(&self.inputs_dir)?;
+        input_root
+            .materialize(&self.cas, input_root_dir.path())
+            .await?;
+        let input_root_path = Utf8Path::from_path(input_root_dir.path())
+            .ok_or_else(|| anyhow!("input directory path isn't UTF-8"))?;
+        let spec = match job_spec(&action, &command, image, &input_root, input_root_path) {
+            Ok(spec) => spec,
+            Err(message) => return invalid(message),
+        };
+
+        let (done_sender, done_receiver) = oneshot::channel();
+        self.runner.run_job(spec, done_sender);
+        executing();
+        let outcome = done_receiver.await.map_err(|_| anyhow!("job canceled"))??;
+
+        let (status, exit_code, effects) = match outcome {
+            Ok(JobOutcome::Completed(JobCompleted { status, effects })) => {
+                let exit_code = match status {
+                    JobTerminationStatus::Exited(code) => i32::from(code),
+                    JobTerminationStatus::Signaled(signo) => 128 + i32::from(signo),
+                };
+                (rpc_status(Code::Ok, ""), exit_code, effects)
+            }
+            Ok(JobOutcome::TimedOut(effects)) => (
+                rpc_status(Code::DeadlineExceeded, "action timed out"),
+                -1,
+                effects,
+            ),
+            Err(JobError::Execution(message)) => {
+                return invalid(format!("error executing action: {message}"))
+            }
+            Err(JobError::System(message)) => {
+                return Ok(error_response(rpc_status(
+                    Code::Internal,
+                    format!("system error executing action: {message}"),
+                )))
+            }
+        };
+        let (result, truncated) = action_result(&self.cas, &command, exit_code, effects).await?;
+        let mut message = truncated.join("; ");
+        if status.code == Code::Ok as i32 && exit_code == 0 && !action.do_not_cache {
+            if truncated.is_empty() {
+                self.action_cache.put(&action_digest, &result).await?;
+            } else {
+                message += ", so the result wasn't cached";
+            }
+        }
+        Ok(ExecuteResponse {
+            result: Some(result),
+            cached_result: false,
+            status: Some(status),
+            message,
+        })
+    }
+}
+
+fn any(type_name: &str, message: &impl prost::Message) -> prost_types::Any {
+    prost_types::Any {
+        type_url: format!("type.googleapis.com/{type_name}"),
+        value: message.encode_to_vec(),
+    }
+}
+
+fn operation(
+    name: &str,
+    action_digest: Option<&reapi::Digest>,
+    stage: ExecutionStage,
+    response: Option<&ExecuteResponse>,
+) -> Operation {
+    let metadata = ExecuteOperationMetadata {
+        stage: stage.into(),
+        action_digest: action_digest.cloned(),
+        digest_function: reapi::digest_function::Value::Sha256.into(),
+    };
+    Operation {
+        name: name.into(),
+        metadata: Some(any(
+            "build.bazel.remote.execution.v2.ExecuteOperationMetadata",
+            &metadata,
+        )),
+        done: response.is_some(),
+        result: response.map(|response| {
+            crate::proto::google::longrunning::operation::Result::Response(any(
+                "build.bazel.remote.execution.v2.ExecuteResponse",
+                response,
+            ))
+        }),
+    }
+}
+
+type OperationStream = Pin<Box<dyn Stream<Item = Result<Operation, Status>> + Send>>;
+
+/// Stream the operation's current state, then every update until it's done.
+fn operation_stream(receiver: watch::Receiver<Operation>) -> OperationStream {
+    Box::pin(futures::stream::unfold(
+        (Some(receiver), true),
+        |(receiver, first)| async move {
+            let mut receiver = receiver?;
+            if !first && receiver.changed().await.is_err() {
+                return None;
+            }
+            let operation = receiver.borrow_and_update().clone();
+            let receiver = (!operation.done).then_some(receiver);
+            Some((Ok(operation), (receiver, false)))
+        },
+    ))
+}
+
+pub struct ExecutionService<JobRunnerT> {
+    executor: Arc<Executor<JobRunnerT>>,
+    operations: Arc<Mutex<HashMap<String, watch::Receiver<Operation>>>>,
+    next_operation: AtomicU64,
+}
+
+impl<JobRunnerT> ExecutionService<JobRunnerT> {
+    pub fn new(executor: Executor<JobRunnerT>) -> Self {
+        Self {
+            executor: Arc::new(executor),
+            operations: Default::default(),
+            next_operation: Default::default(),
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl<JobRunnerT: JobRunner> Execution for ExecutionService<JobRunnerT> {
+    type ExecuteStream = OperationStream;
+
+    async fn execute(
+        &self,
+        request: Request<ExecuteRequest>,
+    ) -> Result<Response<Self::ExecuteStream>, Status> {
+        let request = request.into_inner();
+        let name = format!(
+            "operations/{}",
+            self.next_operation.fetch_add(1, Ordering::Relaxed)
+        );
+        let action_digest = request.action_digest.clone();
+        let (sender, receiver) = watch::channel(operation(
+            &name,
+            action_digest.as_ref(),
+            ExecutionStage::Queued,
+            None,
+        ));
+        self.operations
+            .lock()
+            .unwrap()
+            .insert(name.clone(), receiver.clone());
+
+        let executor = self.executor.clone();
+        let operations = self.operations.clone();
+        tokio::spawn(async move {
+            let response = executor
+                .execute(request, || {
+                    sender.send_replace(operation(
+                        &name,
+                        action_digest.as_ref(),
+                        ExecutionStage::Executing,
+                        None,
+                    ));
+                })
+                .await;
+            sender.send_replace(operation(
+                &name,
+                action_digest.as_ref(),
+                ExecutionStage::Completed,
+                Some(&response),
+            ));
+            drop(sender);
+            tokio::time::sleep(COMPLETED_OPERATION_TTL).await;
+            operations.lock().unwrap().remove(&name);
+        });
+        Ok(Response::new(operation_stream(receiver)))
+    }
+
+    type WaitExecutionStream = OperationStream;
+
+    async fn wait_execution(
+        &self,
+        request: Request<WaitExecutionRequest>,
+    ) -> Result<Response<Self::WaitExecutionStream>, Status> {
+        let name = request.into_inner().name;
+        let receiver = self
+            .operations
+            .lock()
+            .unwrap()
+            .get(&name)
+            .cloned()
+            .ok_or_else(|| Status::not_found(format!("no operation named {name:?}")))?;
+        Ok(Response::new(operation_stream(receiver)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        cas::digest_of,
+        proto::reapi::{command, platform, Directory, DirectoryNode, FileNode, Platform},
+    };
+    use futures::StreamExt as _;
+    use tempfile::TempDir;
+
+    struct FakeRunner {
+        specs: Arc<Mutex<Vec<JobSpec>>>,
+        outcome: JobOutcomeResult,
+    }
+
+    impl JobRunner for FakeRunner {
+        fn run_job(&self, spec: JobSpec, done: oneshot::Sender<Result<JobOutcomeResult>>) {
+            self.specs.lock().unwrap().push(spec);
+            done.send(Ok(self.outcome.clone())).unwrap();
+        }
+    }
+
+    fn completed(code: u8, captured_files: Vec<(&str, &[u8])>) -> JobOutcomeResult {
+        Ok(JobOutcome::Completed(JobCompleted {
+            status: JobTerminationStatus::Exited(code),
+            effects: JobEffects {
+                stdout: JobOutputResult::Inline(Box::new(*b"out")),
+                stderr: JobOutputResult::None,
+                duration: Duration::from_secs(1),
+                captured_files: captured_files
+                    .into_iter()
+                    .map(|(path, contents)| CapturedFile {
+                        path: path.into(),
+                        contents: contents.into(),
+                    })
+                    .collect(),
+            },
+        }))
+    }
+
+    fn platform(image: &str) -> Option<Platform> {
+        Some(Platform {
+            properties: vec![platform::Property {
+                name: "container-image".into(),
+                value: image.into(),
+            }],
+        })
+    }
+
+    struct Fixture {
+        _temp_dir: TempDir,
+        cas: Arc<Cas>,
+        action_cache: Arc<ActionCache>,
+        specs: Arc<Mutex<Vec<JobSpec>>>,
+        executor: Executor<FakeRunner>,
+    }
+
+    impl Fixture {
+        async fn new(outcome: JobOutcomeResult) -> Self {
+            let temp_dir = TempDir::new().unwrap();
+            let cas = Arc::new(
+                Cas::new(temp_dir.path().join("cas"), u64::MAX)
+                    .await
+                    .unwrap(),
+            );
+            let action_cache = Arc::new(
+                ActionCache::new(temp_dir.path().join("action-cache"), cas.clone())
+                    .await
+                    .unwrap(),
+            );
+            let inputs = temp_dir.path().join("inputs");
+            Fs::new().create_dir_all(&inputs).await.unwrap();
+            let specs = Arc::new(Mutex::new(vec![]));
+            let executor = Executor::new(
+                FakeRunner {
+                    specs: specs.clone(),
+                    outcome,
+                },
+                cas.clone(),
+                action_cache.clone(),
+                inputs,
+                Some("alpine".into()),
+            );
+            Self {
+                _temp_dir: temp_dir,
+                cas,
+                action_cache,
+                specs,
+                executor,
+            }
+        }
+
+        /// Store an action that runs `build.sh` in `src` and outputs `out/bin` and `out/lib`.
+        async fn insert_action(&self, do_not_cache: bool) -> reapi::Digest {
+            let script = self.cas.insert(b"#!/bin/sh").await.unwrap();
+            let data = self.cas.insert(b"data").await.unwrap();
+            let src = self
+                .cas
+                .insert_message(&Directory {
+                    files: vec![
+                        FileNode {
+                            name: "build.sh".into(),
+                            digest: Some(script),
+                            is_executable: true,
+                        },
+                        FileNode {
+                            name: "data".into(),
+                            digest: Some(data),
+                            is_executable: false,
+                        },
+                    ],
+                    ..Default::default()
+                })
+                .await
+                .unwrap();
+            let input_root = self
+                .cas
+                .insert_message(&Directory {
+                    directories: vec![DirectoryNode {
+                        name: "src".into(),
+                        digest: Some(src),
+                    }],
+                    ..Default::default()
+                })
+                .await
+                .unwrap();
+            let command = self
+                .cas
+                .insert_message(&Command {
+                    arguments: vec!["./build.sh".into(), "--release".into()],
+                    environment_variables: vec![command::EnvironmentVariable {
+                        name: "FOO".into(),
+                        value: "bar".into(),
+                    }],
+                    output_paths: vec!["out/bin".into(), "out/lib".into()],
+                    working_directory: "src".into(),
+                    ..Default::default()
+                })
+                .await
+                .unwrap();
+            self.cas
+                .insert_message(&Action {
+                    command_digest: Some(command),
+                    input_root_digest: Some(input_root),
+                    do_not_cache,
+                    platform: platform("docker://debian"),
+                    ..Default::default()
+                })
+                .await
+                .unwrap()
+        }
+
+        async fn execute(&self, action_digest: &reapi::Digest) -> ExecuteResponse {
+            self.executor
+                .execute(
+                    ExecuteRequest {
+                        action_digest: Some(action_digest.clone()),
+                        ..Default::default()
+                    },
+                    || {},
+                )
+                .await
+        }
+    }
+
+    #[test]
+    fn container_image_precedence() {
+        let action = Action {
+            platform: platform("docker://action"),
+            ..Default::default()
+        };
+        let command = Command {
+            platform: platform("command"),
+            ..Default::default()
+        };
+        assert_eq!(
+            container_image(&action, &command, Some("default")).unwrap(),
+            "action"
+        );
+        assert_eq!(
+            container_image(&Action::default(), &command, Some("default")).unwrap(),
+            "command"
+        );
+        assert_eq!(
+            container_image(&Action::default(), &Command::default(), Some("default")).unwrap(),
+            "default"
+        );
+        assert!(container_image(&Action::default(), &Command::default(), None).is_err());
+    }
+
+    #[test]
+    fn output_paths_fall_back_to_deprecated_fields() {
+        let command = Command {
+            output_files: vec!["a".into()],
+            output_directories: vec!["b".into()],
+            ..Default::default()
+        };
+        assert_eq!(output_paths(&command), vec!["a", "b"]);
+        let command = Command {
+            output_paths: vec!["c".into()],
+            ..command
+        };
+        assert_eq!(output_paths(&command), vec!["c"]);
+    }
+
+    #[test]
+    fn shell_quoting() {
+        assert_eq!(shell_quote("a b"), "'a b'");
+        assert_eq!(shell_quote("it's"), r"'it'\''s'");
+    }
+
+    #[test]
+    fn invalid_names_in_input_root() {
+        let root = Directory {
+            files: vec![FileNode {
+                name: "..".into(),
+                digest: Some(digest_of(b"")),
+                is_executable: false,
+            }],
+            ..Default::default()
+        };
+        let digest: Sha256Digest = digest_of(&root.encode_to_vec()).hash.parse().unwrap();
+        assert!(InputRoot::new(&digest, &HashMap::from([(digest.clone(), root)])).is_err());
+    }
+
+    #[tokio::test]
+    async fn execute_runs_job_and_stores_outputs() {
+        let fixture = Fixture::new(completed(
+            0,
+            vec![
+                ("/maelstrom-reapi/outputs/out/bin", b"binary"),
+                ("/maelstrom-reapi/outputs/out/lib/a/liba.so", b"a"),
+                ("/maelstrom-reapi/outputs/out/lib/b", b"b"),
+                ("/maelstrom-reapi/metadata/directories", b"out/lib\n"),
+                (
+                    "/maelstrom-reapi/metadata/executables",
+                    b"out/bin\nout/lib/a/liba.so\n",
+                ),
+            ],
+        ))
+        .await;
+        let action_digest = fixture.insert_action(false).await;
+        let response = fixture.execute(&action_digest).await;
+        assert_eq!(response.status.unwrap().code, Code::Ok as i32);
+        assert!(!response.cached_result);
+
+        let specs = fixture.specs.lock().unwrap().clone();
+        assert_eq!(specs.len(), 1);
+        let spec = &specs[0];
+        assert_eq!(spec.program, Utf8PathBuf::from("/bin/sh"));
+        assert_eq!(spec.arguments[3..], ["./build.sh", "--release"]);
+        assert_eq!(
+            spec.container.working_directory,
+            Some("/maelstrom-reapi/input/src".into())
+        );
+        assert_eq!(
+            spec.container.environment,
+            vec![EnvironmentSpec {
+                vars: BTreeMap::from([("FOO".into(), "bar".into())]),
+                extend: false,
+            }]
+        );
+        assert_eq!(
+            spec.container.parent,
+            Some(ContainerParent::Image(ImageRef {
+                name: "debian".into(),
+                r#use: EnumSet::only(ImageUse::Layers),
+            }))
+        );
+        let LayerSpec::Paths(paths) = &spec.container.layers[1] else {
+            panic!("expected paths layer");
+        };
+        assert_eq!(paths.prefix_options.prepend_prefix, Some(INPUT_ROOT.into()));
+        assert_eq!(paths.paths.len(), 2);
+
+        let result = response.result.unwrap();
+        assert_eq!(result.exit_code, 0);
+        let stdout = parse_digest(result.stdout_digest.as_ref()).unwrap();
+        assert_eq!(fixture.cas.read(&stdout).await.unwrap().unwrap(), b"out");
+
+        assert_eq!(result.output_files.len(), 1);
+        assert_eq!(result.output_files[0].path, "out/bin");
+        assert!(result.output_files[0].is_executable);
+        assert_eq!(result.output_files[0].digest, Some(digest_of(b"binary")));
+
+        assert_eq!(result.output_directories.len(), 1);
+        assert_eq!(result.output_directories[0].path, "out/lib");
+        let tree = parse_digest(result.output_directories[0].tree_digest.as_ref()).unwrap();
+        let tree = fixture
+            .cas
+            .read_message::<reapi::Tree>(&tree)
+            .await
+            .unwrap()
+            .unwrap();
+        let root = tree.root.unwrap();
+        assert_eq!(root.files.len(), 1);
+        assert_eq!(root.files[0].name, "b");
+        assert!(!root.files[0].is_executable);
+        assert_eq!(root.directories.len(), 1);
+        assert_eq!(root.directories[0].name, "a");
+        assert_eq!(tree.children.len(), 1);
+        assert_eq!(tree.children[0].files[0].name, "liba.so");
+        assert!(tree.children[0].files[0].is_executable);
+        assert_eq!(
+            root.directories[0].digest,
+            Some(digest_of(&tree.children[0].encode_to_vec()))
+        );
+
+        let action_digest = parse_digest(Some(&action_digest)).unwrap();
+        assert_eq!(
+            fixture.action_cache.get(&action_digest).await.unwrap(),
+            Some(result)
+        );
+    }
+
+    #[tokio::test]
+    async fn execute_returns_symlinks_and_empty_directories() {
+        let fixture = Fixture::new(completed(
+            0,
+            vec![
+                ("/maelstrom-reapi/outputs/out/lib/b", b"b"),
+                (
+                    "/maelstrom-reapi/metadata/directories",
+                    b"out/lib\nout/lib/empty\n",
+                ),
+                (
+                    "/maelstrom-reapi/metadata/symlinks",
+                    b"out/bin\n../tool\nout/lib/link\nb\n",
+                ),
+            ],
+        ))
+        .await;
+        let action_digest = fixture.insert_action(false).await;
+        let result = fixture.execute(&action_digest).await.result.unwrap();
+
+        assert_eq!(result.output_files, vec![]);
+        assert_eq!(
+            result.output_symlinks,
+            vec![OutputSymlink {
+                path: "out/bin".into(),
+                target: "../tool".into(),
+            }]
+        );
+        assert_eq!(result.output_file_symlinks, vec![]);
+        assert_eq!(result.output_directory_symlinks, vec![]);
+
+        let tree = parse_digest(result.output_directories[0].tree_digest.as_ref()).unwrap();
+        let tree = fixture
+            .cas
+            .read_message::<reapi::Tree>(&tree)
+            .await
+            .unwrap()
+            .unwrap();
+        let root = tree.root.unwrap();
+        assert_eq!(root.files[0].name, "b");
+        assert_eq!(
+            root.symlinks,
+            vec![reapi::SymlinkNode {
+                name: "link".into(),
+                target: "b".into(),
+            }]
+        );
+        assert_eq!(root.directories[0].name, "empty");
+        assert_eq!(tree.children, vec![Directory::default()]);
+    }
+
+    #[tokio::test]
+    async fn execute_uses_action_cache() {
+        let fixture = Fixture::new(completed(0, vec![])).await;
+        let action_digest = fixture.insert_action(false).await;
+        fixture.execute(&action_digest).await;
+        let response = fixture.execute(&action_digest).await;
+        assert!(response.cached_result);
+        assert_eq!(fixture.specs.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn execute_does_not_cache_failures_or_do_not_cache() {
+        let fixture = Fixture::new(completed(1, vec![])).await;
+        let action_digest = fixture.insert_action(false).await;
+        let response = fixture.execute(&action_digest).await;
+        assert_eq!(response.result.unwrap().exit_code, 1);
+        let action_digest = parse_digest(Some(&action_digest)).unwrap();
+        assert_eq!(
+            fixture.action_cache.get(&action_digest).await.unwrap(),
+            None
+        );
+
+        let fixture = Fixture::new(completed(0, vec![])).await;
+        let action_digest = fixture.insert_action(true).await;
+        fixture.execute(&action_digest).await;
+        let action_digest = parse_digest(Some(&action_digest)).unwrap();
+        assert_eq!(
+            fixture.action_cache.get(&action_digest).await.unwrap(),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn execute_uses_captured_stdout_and_stderr() {
+        let fixture = Fixture::new(completed(
+            0,
+            vec![
+                ("/maelstrom-reapi/metadata/stdout", b"all of stdout"),
+                ("/maelstrom-reapi/metadata/stderr", b"all of stderr"),
+            ],
+        ))
+        .await;
+        let action_digest = fixture.insert_action(false).await;
+        let response = fixture.execute(&action_digest).await;
+        let result = response.result.unwrap();
+        assert_eq!(result.stdout_digest, Some(digest_of(b"all of stdout")));
+        assert_eq!(result.stderr_digest, Some(digest_of(b"all of stderr")));
+        assert_eq!(response.message, "");
+    }
+
+    #[tokio::test]
+    async fn execute_does_not_cache_truncated_output() {
+        let fixture = Fixture::new(Ok(JobOutcome::Completed(JobCompleted {
+            status: JobTerminationStatus::Exited(0),
+            effects: JobEffects {
+                stdout: JobOutputResult::Truncated {
+                    first: Box::new(*b"out"),
+                    truncated: 10,
+                },
+                stderr: JobOutputResult::None,
+                duration: Duration::from_secs(1),
+                captured_files: vec![],
+            },
+        })))
+        .await;
+        let action_digest = fixture.insert_action(false).await;
+        let response = fixture.execute(&action_digest).await;
+        assert_eq!(
+            response.message,
+            "standard output was truncated, dropping 10 bytes, so the result wasn't cached"
+        );
+        assert_eq!(
+            response.result.unwrap().stdout_digest,
+            Some(digest_of(b"out"))
+        );
+        let action_digest = parse_digest(Some(&action_digest)).unwrap();
+        assert_eq!(
+            fixture.action_cache.get(&action_digest).await.unwrap(),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn execute_reports_missing_blobs() {
+        let fixture = Fixture::new(completed(0, vec![])).await;
+        let missing = digest_of(b"missing action");
+        let status = fixture.execute(&missing).await.status.unwrap();
+        assert_eq!(status.code, Code::FailedPrecondition as i32);
+        let failure = rpc::PreconditionFailure::decode(status.details[0].value.as_slice()).unwrap();
+        assert_eq!(failure.violations[0].r#type, "MISSING");
+        assert_eq!(
+            failure.violations[0].subject,
+            format!("blobs/{}/{}", missing.hash, missing.size_bytes)
+        );
+        assert!(fixture.specs.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn execute_maps_timeouts_and_errors() {
+        let fixture = Fixture::new(Ok(JobOutcome::TimedOut(JobEffects {
+            stdout: JobOutputResult::None,
+            stderr: JobOutputResult::None,
+            duration: Duration::from_secs(1),
+            captured_files: vec![],
+        })))
+        .await;
+        let action_digest = fixture.insert_action(false).await;
+        let response = fixture.execute(&action_digest).await;
+        assert_eq!(response.status.unwrap().code, Code::DeadlineExceeded as i32);
+
+        let fixture = Fixture::new(Err(JobError::Execution("no such file".into()))).await;
+        let action_digest = fixture.insert_action(false).await;
+        let response = fixture.execute(&action_digest).await;
+        assert_eq!(response.status.unwrap().code, Code::InvalidArgument as i32);
+
+        let fixture = Fixture::new(Err(JobError::System("out of disk".into()))).await;
+        let action_digest = fixture.insert_action(false).await;
+        let response = fixture.execute(&action_digest).await;
+        assert_eq!(response.status.unwrap().code, Code::Internal as i32);
+    }
+
+    #[tokio::test]
+    async fn execute_operation_stream_ends_when_done() {
+        let fixture = Fixture::new(completed(0, vec![])).await;
+        let action_digest = fixture.insert_action(false).await;
+        let service = ExecutionService::new(fixture.executor);
+        let operations: Vec<_> = service
+            .execute(Request::new(ExecuteRequest {
+                action_digest: Some(action_digest),
+                ..Default::default()
+            }))
+            .await
+            .unwrap()
+            .into_inner()
+            .collect()
+            .await;
+        let last = operations.last().unwrap().as_ref().unwrap();
+        assert!(last.done);
+        assert!(operations[..operations.len() - 1]
+            .iter()
+            .all(|operation| !operation.as_ref().unwrap().done));
+        let Some(crate::proto::google::longrunning::operation::Result::Response(response)) =
+            &last.result
+        else {
+            panic!("expected response");
+        };
+        let response = ExecuteResponse::decode(response.value.as_slice()).unwrap();
+        assert_eq!(response.result.unwrap().exit_code, 0);
+
+        let status = service
+            .wait_execution(Request::new(WaitExecutionRequest {
+                name: "operations/1000".into(),
+            }))
+            .await
+            .map(|_| ())
+            .unwrap_err();
+        assert_eq!(status.code(), Code::NotFound);
+    }
+
+    #[tokio::test]
+    async fn wait_execution_returns_completed_operation() {
+        let fixture = Fixture::new(completed(0, vec![])).await;
+        let action_digest = fixture.insert_action(false).await;
+        let service = ExecutionService::new(fixture.executor);
+        let executed: Vec<_> = service
+            .execute(Request::new(ExecuteRequest {
+                action_digest: Some(action_digest),
+                ..Default::default()
+            }))
+            .await
+            .unwrap()
+            .into_inner()
+            .collect()
+            .await;
+        let last = executed.last().unwrap().as_ref().unwrap();
+        assert!(last.done);
+
+        let waited: Vec<_> = service
+            .wait_execution(Request::new(WaitExecutionRequest {
+                name: last.name.clone(),
+            }))
+            .await
+            .unwrap()
+            .into_inner()
+            .collect()
+            .await;
+        assert_eq!(waited.len(), 1);
+        assert_eq!(waited[0].as_ref().unwrap(), last);
+    }
+}
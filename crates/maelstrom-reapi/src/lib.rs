@@ -0,0 +1,92 @@
+//! A frontend that implements the Remote Execution API, so that build systems like Bazel and Buck2
+//! can run their actions as Maelstrom jobs.
+
+mod action_cache;
+mod bytestream;
+mod capabilities;
+mod cas;
+pub mod config;
+mod execution;
+mod proto;
+
+use action_cache::{ActionCache, ActionCacheService};
+use anyhow::{Context as _, Result};
+use bytestream::ByteStreamService;
+use capabilities::CapabilitiesService;
+use cas::{Cas, CasService};
+use config::Config;
+use execution::{ExecutionService, Executor};
+use maelstrom_client::{Client, ClientBgProcess, ProjectDir};
+use maelstrom_util::fs::Fs;
+use proto::{
+    google::bytestream::byte_stream_server::ByteStreamServer,
+    reapi::{
+        action_cache_server::ActionCacheServer, capabilities_server::CapabilitiesServer,
+        content_addressable_storage_server::ContentAddressableStorageServer,
+        execution_server::ExecutionServer,
+    },
+};
+use slog::{info, Logger};
+use std::{net::SocketAddr, sync::Arc};
+
+pub fn main(config: Config, bg_proc: ClientBgProcess, log: Logger) -> Result<()> {
+    let fs = Fs::new();
+    let reapi_root = config.cache_root.join::<()>("reapi");
+    let cas_root = reapi_root.join::<()>("cas");
+    let action_cache_root = reapi_root.join::<()>("action-cache");
+    let inputs_root = reapi_root.join::<ProjectDir>("inputs");
+    fs.create_dir_all(&config.cache_root)?;
+    fs.create_dir_all(&config.state_root)?;
+    fs.create_dir_all(&config.container_image_depot_root)?;
+    // Input roots are materialized in temporary directories, so anything left here is from a
+    // previous run.
+    if fs.exists(&inputs_root) {
+        fs.remove_dir_all(&inputs_root)?;
+    }
+    fs.create_dir_all(&inputs_root)?;
+
+    let client = Client::new(
+        bg_proc,
+        config.broker,
+        &inputs_root,
+        config.state_root,
+        config.container_image_depot_root,
+        config.cache_root,
+        config.cache_size,
+        config.inline_limit,
+        config.slots,
+        config.accept_invalid_remote_container_tls_certs,
+        config.artifact_transfer_strategy,
+        log.clone(),
+    )?;
+
+    tokio::runtime::Runtime::new()?.block_on(async move {
+        let cas = Arc::new(Cas::new(&cas_root, config.cas_size.into()).await?);
+        let action_cache = Arc::new(ActionCache::new(&action_cache_root, cas.clone()).await?);
+        let executor = Executor::new(
+            client,
+            cas.clone(),
+            action_cache.clone(),
+            inputs_root.into_path_buf(),
+            config.default_container_image,
+        );
+        let addr = SocketAddr::from((config.address, config.port));
+        info!(log, "started"; "addr" => %addr);
+        tonic::transport::Server::builder()
+            .add_service(CapabilitiesServer::new(CapabilitiesService {
+                update_enabled: config.allow_action_cache_updates,
+            }))
+            .add_service(ContentAddressableStorageServer::new(CasService::new(
+                cas.clone(),
+            )))
+            .add_service(ByteStreamServer::new(ByteStreamService::new(cas)))
+            .add_service(ActionCacheServer::new(ActionCacheService::new(
+                action_cache,
+                config.allow_action_cache_updates,
+            )))
+            .add_service(ExecutionServer::new(ExecutionService::new(executor)))
+            .serve(addr)
+            .await
+            .with_context(|| format!("serving on {addr}"))
+    })
+}
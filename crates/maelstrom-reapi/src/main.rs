@@ -0,0 +1,11 @@
+use anyhow::Result;
+use maelstrom_client::ClientBgProcess;
+use maelstrom_reapi::config::Config;
+
+fn main() -> Result<()> {
+    let config = Config::new("maelstrom/reapi", "MAELSTROM_REAPI")?;
+    let bg_proc = ClientBgProcess::new_from_fork(config.log_level)?;
+    maelstrom_util::log::run_with_logger(config.log_level, |log| {
+        maelstrom_reapi::main(config, bg_proc, log)
+    })
+}
@@ -0,0 +1,36 @@
+//! Types and services generated from the Remote Execution API protos. The modules mirror the
+//! protobuf packages, since the generated code refers to types in other packages by relative path.
+
+pub mod build {
+    pub mod bazel {
+        pub mod remote {
+            pub mod execution {
+                // Some messages only exist to namespace enums.
+                #[allow(dead_code)]
+                pub mod v2 {
+                    tonic::include_proto!("build.bazel.remote.execution.v2");
+                }
+            }
+        }
+
+        pub mod semver {
+            tonic::include_proto!("build.bazel.semver");
+        }
+    }
+}
+
+pub mod google {
+    pub mod bytestream {
+        tonic::include_proto!("google.bytestream");
+    }
+
+    pub mod longrunning {
+        tonic::include_proto!("google.longrunning");
+    }
+
+    pub mod rpc {
+        tonic::include_proto!("google.rpc");
+    }
+}
+
+pub use build::bazel::remote::execution::v2 as reapi;
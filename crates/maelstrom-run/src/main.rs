@@ -151,9 +151,9 @@ pub struct ExtraCommandLineOptions {
         num_args = 0..,
         requires = "OneOrTty",
         value_name = "PROGRAM-AND-ARGUMENTS",
-        help = "Program and arguments override. Can only be used with --one or --tty. If provided \
-            these will be used for the program and arguments, ignoring whatever is in the job \
-            specification."
+        help = "Program and arguments override. Can only be used with --one, --tty, or \
+            --export-image. If provided these will be used for the program and arguments, \
+            ignoring whatever is in the job specification."
     )]
     pub args: Vec<String>,
 }
@@ -180,11 +180,22 @@ pub struct OneOrTty {
             arguments can be provided to override the job's program and arguments."
     )]
     pub tty: bool,
+
+    #[arg(
+        long,
+        value_name = "PATH",
+        help = "Instead of executing a job, build the container for the first job and write it to \
+            the given path as an OCI image tarball. The tarball can be loaded with `docker load` \
+            or `podman load`. If multiple job specifications are provided, all but the first are \
+            ignored. Optionally, positional arguments can be provided to override the job's \
+            program and arguments, which become the image's command."
+    )]
+    pub export_image: Option<PathBuf>,
 }
 
 impl OneOrTty {
     fn any(&self) -> bool {
-        self.one || self.tty
+        self.one || self.tty || self.export_image.is_some()
     }
 }
 
@@ -194,6 +205,7 @@ fn print_effects(
         stdout,
         stderr,
         duration: _,
+        captured_files: _,
     }: JobEffects,
 ) -> Result<()> {
     match stdout {
@@ -804,7 +816,10 @@ fn main_with_logger(
                 job_spec.arguments = arguments.to_vec();
             }
         }
-        if extra_options.one_or_tty.tty {
+        if let Some(path) = extra_options.one_or_tty.export_image {
+            client.export_image(job_spec, env::current_dir()?.join(path))?;
+            Ok(ExitCode::SUCCESS)
+        } else if extra_options.one_or_tty.tty {
             // Re-block the signals for the local thread.
             linux::pthread_sigmask(SigprocmaskHow::BLOCK, Some(&blocked_signals))?;
             tty_main(blocked_signals, client, config.escape_char, job_spec)
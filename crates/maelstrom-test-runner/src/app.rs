@@ -8,17 +8,17 @@ mod tests;
 use crate::{
     config::{Repeat, StopAfter},
     deps::{
-        CollectTests, KillOnDrop, MainAppDeps, TestArtifact as _, TestFilter as _, Wait as _,
-        WaitStatus,
+        CollectTests, KillOnDrop, MainAppDeps, TestArtifact as _, TestFilter as _, TestImpact,
+        Wait as _, WaitStatus,
     },
     metadata::Store as MetadataStore,
     test_db::{TestDb, TestDbStore},
     ui::UiSender,
-    ui::{Ui, UiJobId as JobId, UiMessage},
+    ui::{Ui, UiJobId as JobId, UiMessage, UiRequest},
     ListAction, LoggingOutput,
 };
-use anyhow::{Context as _, Result};
-use maelstrom_base::Timeout;
+use anyhow::Result;
+use maelstrom_base::{CapturedFile, Timeout};
 use maelstrom_client::{spec::JobSpec, Client, JobStatus, ProjectDir, StateDir};
 use maelstrom_util::{
     fs::Fs,
@@ -28,6 +28,7 @@ use maelstrom_util::{
 };
 use main_app::MainApp;
 use std::{
+    collections::BTreeSet,
     path::PathBuf,
     sync::{
         mpsc::{Receiver, Sender},
@@ -36,7 +37,7 @@ use std::{
     time::Duration,
 };
 use std_semaphore::Semaphore;
-use watch::Watcher;
+use watch::{Change, Changes, Watcher};
 
 type ArtifactM<DepsT> = <<DepsT as Deps>::TestCollector as CollectTests>::Artifact;
 type ArtifactKeyM<DepsT> = <<DepsT as Deps>::TestCollector as CollectTests>::ArtifactKey;
@@ -46,6 +47,7 @@ type PackageM<DepsT> = <<DepsT as Deps>::TestCollector as CollectTests>::Package
 type PackageIdM<DepsT> = <<DepsT as Deps>::TestCollector as CollectTests>::PackageId;
 type TestFilterM<DepsT> = <<DepsT as Deps>::TestCollector as CollectTests>::TestFilter;
 type TestDbM<DepsT> = TestDb<ArtifactKeyM<DepsT>, CaseMetadataM<DepsT>>;
+type TestImpactM<DepsT> = TestImpact<PackageIdM<DepsT>, ArtifactKeyM<DepsT>>;
 type TestingOptionsM<DepsT> = TestingOptions<TestFilterM<DepsT>, CollectOptionsM<DepsT>>;
 
 trait Deps {
@@ -59,9 +61,12 @@ trait Deps {
     );
     fn get_packages(&self);
     fn add_job(&self, job_id: JobId, spec: JobSpec);
+    fn export_image(&self, spec: JobSpec, path: PathBuf);
     fn list_tests(&self, artifact: ArtifactM<Self>);
     fn start_shutdown(&self);
     fn send_ui_msg(&self, msg: UiMessage);
+    fn save_coverage_data(&self, files: Vec<CapturedFile>);
+    fn save_benchmark_output(&self, case_str: &str, lines: Vec<String>);
 }
 
 /// Immutable information used to control the testing invocation.
@@ -74,10 +79,31 @@ struct TestingOptions<TestFilterT, CollectOptionsT> {
     repeat: Repeat,
     stop_after: Option<StopAfter>,
     listing: bool,
+    /// Instead of running tests, export the container of the test with this name to this path.
+    export_image: Option<(String, PathBuf)>,
 }
 
-enum MainAppMessage<PackageT: 'static, ArtifactT: 'static, CaseMetadataT: 'static> {
+impl<TestFilterT, CollectOptionsT> TestingOptions<TestFilterT, CollectOptionsT> {
+    /// Whether the selected tests are being run, as opposed to being listed or having one of
+    /// their containers exported.
+    fn running_tests(&self) -> bool {
+        !self.listing && self.export_image.is_none()
+    }
+}
+
+enum MainAppMessage<
+    PackageT: 'static,
+    ArtifactT: 'static,
+    CaseMetadataT: 'static,
+    TestImpactT: 'static,
+> {
     Start,
+    TestImpact {
+        impact: TestImpactT,
+    },
+    RerunTests {
+        names: BTreeSet<String>,
+    },
     Packages {
         packages: Vec<PackageT>,
     },
@@ -102,7 +128,7 @@ enum MainAppMessage<PackageT: 'static, ArtifactT: 'static, CaseMetadataT: 'stati
 }
 
 type MainAppMessageM<DepsT> =
-    MainAppMessage<PackageM<DepsT>, ArtifactM<DepsT>, CaseMetadataM<DepsT>>;
+    MainAppMessage<PackageM<DepsT>, ArtifactM<DepsT>, CaseMetadataM<DepsT>, TestImpactM<DepsT>>;
 
 enum ControlMessage<MessageT> {
     Shutdown,
@@ -125,6 +151,7 @@ struct MainAppDepsAdapter<'deps, 'scope, MainAppDepsT: MainAppDeps> {
     collect_killer: Mutex<Option<KillOnDrop<WaitM<Self>>>>,
     semaphore: &'deps Semaphore,
     client: &'deps Client,
+    changed_paths: Option<Arc<BTreeSet<PathBuf>>>,
 }
 
 const MAX_NUM_BACKGROUND_THREADS: isize = 200;
@@ -137,6 +164,7 @@ impl<'deps, 'scope, MainAppDepsT: MainAppDeps> MainAppDepsAdapter<'deps, 'scope,
         ui: UiSender,
         semaphore: &'deps Semaphore,
         client: &'deps Client,
+        changed_paths: Option<Arc<BTreeSet<PathBuf>>>,
     ) -> Self {
         Self {
             deps,
@@ -146,6 +174,7 @@ impl<'deps, 'scope, MainAppDepsT: MainAppDeps> MainAppDepsAdapter<'deps, 'scope,
             collect_killer: Mutex::new(None),
             semaphore,
             client,
+            changed_paths,
         }
     }
 }
@@ -211,10 +240,22 @@ impl<MainAppDepsT: MainAppDeps> Deps for MainAppDepsAdapter<'_, '_, MainAppDepsT
         let deps = self.deps;
         let sender = self.main_app_sender.clone();
         let ui = self.ui.clone();
+        let changed_paths = self.changed_paths.clone();
         self.scope.spawn(move || {
             let _guard = sem.access();
-            match deps.test_collector().get_packages(&ui) {
-                Ok(packages) => {
+            let collector = deps.test_collector();
+            let res = collector.get_packages(&ui).and_then(|packages| {
+                let impact = match &changed_paths {
+                    Some(changed_paths) => collector.get_impact(&packages, changed_paths, &ui)?,
+                    None => None,
+                };
+                Ok((packages, impact))
+            });
+            match res {
+                Ok((packages, impact)) => {
+                    if let Some(impact) = impact {
+                        let _ = sender.send(MainAppMessage::TestImpact { impact }.into());
+                    }
                     let _ = sender.send(MainAppMessage::Packages { packages }.into());
                 }
                 Err(error) => {
@@ -236,6 +277,14 @@ impl<MainAppDepsT: MainAppDeps> Deps for MainAppDepsAdapter<'_, '_, MainAppDepsT
         }
     }
 
+    fn export_image(&self, spec: JobSpec, path: PathBuf) {
+        if let Err(error) = self.client.export_image(spec, path) {
+            let _ = self
+                .main_app_sender
+                .send(MainAppMessage::FatalError { error }.into());
+        }
+    }
+
     fn list_tests(&self, artifact: ArtifactM<Self>) {
         let sem = self.semaphore;
         let sender = self.main_app_sender.clone();
@@ -274,6 +323,28 @@ impl<MainAppDepsT: MainAppDeps> Deps for MainAppDepsAdapter<'_, '_, MainAppDepsT
     fn send_ui_msg(&self, msg: UiMessage) {
         self.ui.send(msg);
     }
+
+    fn save_coverage_data(&self, files: Vec<CapturedFile>) {
+        // This is done synchronously so that all of the data has been saved by the time we
+        // generate the coverage report.
+        if let Err(error) = self.deps.test_collector().save_coverage_data(files) {
+            let _ = self
+                .main_app_sender
+                .send(MainAppMessage::FatalError { error }.into());
+        }
+    }
+
+    fn save_benchmark_output(&self, case_str: &str, lines: Vec<String>) {
+        if let Err(error) = self
+            .deps
+            .test_collector()
+            .save_benchmark_output(case_str, &lines)
+        {
+            let _ = self
+                .main_app_sender
+                .send(MainAppMessage::FatalError { error }.into());
+        }
+    }
 }
 
 fn main_app_channel_reader<DepsT: Deps>(
@@ -308,6 +379,7 @@ fn introspect_loop(done: &Event, client: &maelstrom_client::Client, ui: UiSender
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn run_app_once<'scope, 'deps, MainAppDepsT: MainAppDeps>(
     abstract_deps: &'deps MainAppDepsT,
     test_db_store: &'deps TestDbStore<
@@ -322,6 +394,7 @@ fn run_app_once<'scope, 'deps, MainAppDepsT: MainAppDeps>(
     sem: &'deps Semaphore,
     ui: UiSender,
     client: &'deps Client,
+    change: Option<Change>,
 ) -> Result<ExitCode> {
     let (main_app_sender, main_app_receiver) = std::sync::mpsc::channel();
 
@@ -330,6 +403,11 @@ fn run_app_once<'scope, 'deps, MainAppDepsT: MainAppDeps>(
         inner_scope.spawn(|| introspect_loop(&done, client, ui.clone()));
 
         let res = (|| -> Result<_> {
+            let (changed_paths, rerun_tests) = match change {
+                None => (None, None),
+                Some(Change::Files(paths)) => (Some(Arc::new(paths)), None),
+                Some(Change::RerunTests(names)) => (None, Some(names)),
+            };
             let deps = MainAppDepsAdapter::new(
                 abstract_deps,
                 scope,
@@ -337,8 +415,14 @@ fn run_app_once<'scope, 'deps, MainAppDepsT: MainAppDeps>(
                 ui.clone(),
                 sem,
                 client,
+                changed_paths,
             );
 
+            if let Some(names) = rerun_tests {
+                main_app_sender
+                    .send(MainAppMessage::RerunTests { names }.into())
+                    .unwrap();
+            }
             main_app_sender.send(MainAppMessage::Start.into()).unwrap();
 
             let test_db = test_db_store.load()?;
@@ -368,6 +452,7 @@ fn run_app_in_loop<MainAppDepsT: MainAppDeps>(
     watch: bool,
     watch_exclude_paths: Vec<PathBuf>,
     ui: UiSender,
+    ui_requests: Receiver<UiRequest>,
     client: &Client,
 ) -> Result<ExitCode> {
     // This is where the pytest runner builds pip packages.
@@ -377,7 +462,7 @@ fn run_app_in_loop<MainAppDepsT: MainAppDeps>(
 
     let sem = Semaphore::new(MAX_NUM_BACKGROUND_THREADS);
     let done = Event::new();
-    let files_changed = Event::new();
+    let changes = Changes::default();
 
     std::thread::scope(|scope| {
         let res = (|| -> Result<_> {
@@ -388,12 +473,20 @@ fn run_app_in_loop<MainAppDepsT: MainAppDeps>(
                 &watch_exclude_paths,
                 &sem,
                 &done,
-                &files_changed,
+                &changes,
             );
             if watch {
-                watcher.watch_for_changes()?;
+                watcher.watch_for_changes(ui_requests)?;
+            } else {
+                // Without a watcher, nothing will ever rerun tests. Dropping the receiver lets the
+                // UI tell the user that rerunning requires --watch.
+                drop(ui_requests);
             }
 
+            // The first run always runs every selected test. Subsequent runs only run the tests
+            // impacted by the files that changed, along with any tests that previously failed, or
+            // just the tests the user asked to rerun.
+            let mut change = None;
             loop {
                 let exit_code = run_app_once(
                     &abstract_deps,
@@ -403,15 +496,25 @@ fn run_app_in_loop<MainAppDepsT: MainAppDeps>(
                     &sem,
                     ui.clone(),
                     client,
+                    change.take(),
                 )?;
 
+                if options.running_tests() {
+                    abstract_deps
+                        .test_collector()
+                        .report_coverage(&options.collector_options, &ui)?;
+                    abstract_deps
+                        .test_collector()
+                        .report_benchmarks(&options.collector_options, &ui)?;
+                }
+
                 if watch {
                     client.restart()?;
 
                     ui.send(UiMessage::UpdateEnqueueStatus(
                         "waiting for changes...".into(),
                     ));
-                    watcher.wait_for_changes();
+                    change = Some(watcher.wait_for_changes());
 
                     continue;
                 } else {
@@ -436,6 +539,7 @@ pub fn run_app_with_ui_multithreaded<MainAppDepsT: MainAppDeps>(
     include_filter: Vec<String>,
     exclude_filter: Vec<String>,
     list_action: Option<ListAction>,
+    export_image: Option<(String, PathBuf)>,
     repeat: Repeat,
     stop_after: Option<StopAfter>,
     watch: bool,
@@ -451,18 +555,20 @@ pub fn run_app_with_ui_multithreaded<MainAppDepsT: MainAppDeps>(
 
     let project_dir = project_dir.as_ref().to_owned();
 
+    let export_image = match export_image {
+        Some((test, path)) => Some((test, std::env::current_dir()?.join(path))),
+        None => None,
+    };
+
     let metadata_template_vars = abstract_deps.get_template_vars(&collector_options)?;
     let metadata_path = project_dir.join::<()>(MainAppDepsT::TEST_METADATA_FILE_NAME);
-    let metadata_store = if let Some(contents) = fs.read_to_string_if_exists(&metadata_path)? {
-        MetadataStore::load(&contents, &metadata_template_vars)
-            .with_context(|| format!("parsing metadata file {}", metadata_path.display()))?
-    } else {
-        MetadataStore::load(
-            MainAppDepsT::DEFAULT_TEST_METADATA_CONTENTS,
-            &metadata_template_vars,
-        )
-        .expect("embedded default test metadata TOML to be valid")
-    };
+    let metadata_store = MetadataStore::load_files(
+        &fs,
+        &metadata_path,
+        MainAppDepsT::DEFAULT_TEST_METADATA_CONTENTS,
+        abstract_deps.get_package_metadata_directories(),
+        &metadata_template_vars,
+    )?;
 
     // TODO: There are a few things wrong with this from an efficiency point of view.
     //
@@ -495,7 +601,7 @@ pub fn run_app_with_ui_multithreaded<MainAppDepsT: MainAppDeps>(
     );
     watch_exclude_paths.push(project_dir.to_path_buf().join(".git"));
 
-    let (ui_handle, ui) = ui.start_ui_thread(logging_output, log.clone());
+    let (ui_handle, ui, ui_requests) = ui.start_ui_thread(logging_output, log.clone());
 
     let main_res = run_app_in_loop(
         abstract_deps,
@@ -511,10 +617,12 @@ pub fn run_app_with_ui_multithreaded<MainAppDepsT: MainAppDeps>(
             repeat,
             stop_after,
             listing: list_action.is_some(),
+            export_image,
         },
         watch,
         watch_exclude_paths,
         ui,
+        ui_requests,
         client,
     );
     ui_handle.join()?;
@@ -1,5 +1,5 @@
 use crate::ui::{UiJobId as JobId, UiJobResult, UiJobStatus};
-use crate::CollectTests;
+use crate::{CollectTests, SubtestResult};
 use anyhow::Result;
 use maelstrom_base::{
     ClientJobId, JobCompleted, JobEffects, JobError, JobOutcome, JobOutcomeResult, JobOutputResult,
@@ -41,14 +41,45 @@ fn split_into_lines() {
     );
 }
 
-fn was_ignored<TestCollectorT: CollectTests>(res: &JobOutputResult, case_str: &str) -> bool {
-    let (_, case_str) = case_str.rsplit_once(' ').unwrap_or(("", case_str));
-    let lines = match res {
+fn output_lines(res: &JobOutputResult) -> Vec<String> {
+    match res {
         JobOutputResult::None => vec![],
         JobOutputResult::Inline(bytes) => split_test_output_into_lines(bytes),
         JobOutputResult::Truncated { first, .. } => split_test_output_into_lines(first),
-    };
-    TestCollectorT::was_test_ignored(case_str, &lines)
+    }
+}
+
+fn was_ignored<TestCollectorT: CollectTests>(res: &JobOutputResult, case_str: &str) -> bool {
+    let (_, case_str) = case_str.rsplit_once(' ').unwrap_or(("", case_str));
+    TestCollectorT::was_test_ignored(case_str, &output_lines(res))
+}
+
+/// Find the results of any subtests in the output of a finished test job.
+pub fn get_subtest_results<TestCollectorT: CollectTests>(
+    case_name: &str,
+    res: &Result<(ClientJobId, JobOutcomeResult)>,
+) -> Vec<SubtestResult> {
+    match res {
+        Ok((
+            _,
+            Ok(JobOutcome::Completed(JobCompleted { effects, .. }) | JobOutcome::TimedOut(effects)),
+        )) => TestCollectorT::get_subtest_results(case_name, &output_lines(&effects.stdout)),
+        _ => vec![],
+    }
+}
+
+/// Get the output of a finished benchmark job, as long as the benchmark succeeded.
+pub fn get_benchmark_output(res: &Result<(ClientJobId, JobOutcomeResult)>) -> Option<Vec<String>> {
+    match res {
+        Ok((
+            _,
+            Ok(JobOutcome::Completed(JobCompleted {
+                status: JobTerminationStatus::Exited(0),
+                effects,
+            })),
+        )) => Some(output_lines(&effects.stdout)),
+        _ => None,
+    }
 }
 
 fn format_test_output<TestCollectorT: CollectTests>(
@@ -96,6 +127,7 @@ pub fn build_ignored_ui_job_result(job_id: JobId, case_str: &str) -> UiJobResult
 pub fn build_ui_job_result_and_exit_code<TestCollectorT: CollectTests>(
     job_id: JobId,
     case_str: &str,
+    expects_failure: bool,
     res: Result<(ClientJobId, JobOutcomeResult)>,
 ) -> (UiJobResult, ExitCode) {
     let test_status: UiJobStatus;
@@ -112,12 +144,25 @@ pub fn build_ui_job_result_and_exit_code<TestCollectorT: CollectTests>(
                         stdout,
                         stderr,
                         duration,
+                        ..
                     },
             })),
         )) => {
             test_duration = Some(duration);
             let mut job_failed = true;
             let exit_code = match status {
+                JobTerminationStatus::Exited(code) if expects_failure => {
+                    if code != 0 {
+                        job_failed = false;
+                        test_status = UiJobStatus::Ok;
+                        ExitCode::SUCCESS
+                    } else {
+                        test_status = UiJobStatus::Failure(Some(
+                            "test succeeded, but it was expected to fail".into(),
+                        ));
+                        ExitCode::FAILURE
+                    }
+                }
                 JobTerminationStatus::Exited(code) => {
                     test_status = if code == 0 {
                         job_failed = false;
@@ -156,6 +201,7 @@ pub fn build_ui_job_result_and_exit_code<TestCollectorT: CollectTests>(
                 stdout,
                 stderr,
                 duration,
+                ..
             })),
         )) => {
             test_duration = Some(duration);
@@ -194,3 +240,64 @@ pub fn build_ui_job_result_and_exit_code<TestCollectorT: CollectTests>(
         exit_code,
     )
 }
+
+#[cfg(test)]
+fn completed_with_status(status: JobTerminationStatus) -> Result<(ClientJobId, JobOutcomeResult)> {
+    Ok((
+        ClientJobId::from(1),
+        Ok(JobOutcome::Completed(JobCompleted {
+            status,
+            effects: JobEffects {
+                stdout: JobOutputResult::None,
+                stderr: JobOutputResult::None,
+                duration: std::time::Duration::from_secs(1),
+                captured_files: vec![],
+            },
+        })),
+    ))
+}
+
+#[test]
+fn expected_failure_passes_on_non_zero_exit() {
+    let (res, exit_code) =
+        build_ui_job_result_and_exit_code::<crate::fake_test_framework::TestCollector>(
+            JobId::from(1),
+            "foo_pkg test_a",
+            true,
+            completed_with_status(JobTerminationStatus::Exited(101)),
+        );
+    assert_eq!(res.status, UiJobStatus::Ok);
+    assert_eq!(exit_code, ExitCode::SUCCESS);
+}
+
+#[test]
+fn expected_failure_fails_on_zero_exit() {
+    let (res, exit_code) =
+        build_ui_job_result_and_exit_code::<crate::fake_test_framework::TestCollector>(
+            JobId::from(1),
+            "foo_pkg test_a",
+            true,
+            completed_with_status(JobTerminationStatus::Exited(0)),
+        );
+    assert_eq!(
+        res.status,
+        UiJobStatus::Failure(Some("test succeeded, but it was expected to fail".into()))
+    );
+    assert_eq!(exit_code, ExitCode::FAILURE);
+}
+
+#[test]
+fn expected_failure_fails_when_signaled() {
+    let (res, exit_code) =
+        build_ui_job_result_and_exit_code::<crate::fake_test_framework::TestCollector>(
+            JobId::from(1),
+            "foo_pkg test_a",
+            true,
+            completed_with_status(JobTerminationStatus::Signaled(6)),
+        );
+    assert_eq!(
+        res.status,
+        UiJobStatus::Failure(Some("killed by signal 6".into()))
+    );
+    assert_eq!(exit_code, ExitCode::FAILURE);
+}
@@ -1,27 +1,52 @@
 use super::{
-    job_output::{build_ignored_ui_job_result, build_ui_job_result_and_exit_code},
+    job_output::{
+        build_ignored_ui_job_result, build_ui_job_result_and_exit_code, get_benchmark_output,
+        get_subtest_results,
+    },
     ArtifactKeyM, ArtifactM, CaseMetadataM, Deps, MainAppMessage, MainAppMessageM, PackageIdM,
-    PackageM, TestDbM, TestingOptionsM,
+    PackageM, TestDbM, TestImpactM, TestingOptionsM,
 };
 use crate::metadata::Metadata;
 use crate::test_db::CaseOutcome;
 use crate::ui::{
-    UiJobEnqueued, UiJobId as JobId, UiJobStatus, UiJobSummary, UiJobUpdate, UiMessage,
+    UiJobEnqueued, UiJobId as JobId, UiJobResult, UiJobStatus, UiJobSummary, UiJobUpdate, UiMessage,
 };
 use crate::*;
-use maelstrom_base::{ClientJobId, JobOutcomeResult};
+use anyhow::anyhow;
+use maelstrom_base::{
+    capture_mount, CapturedFile, ClientJobId, JobCompleted, JobOutcome, JobOutcomeResult, Timeout,
+};
 use maelstrom_client::{
-    spec::{ContainerSpec, JobSpec},
-    JobStatus,
+    spec::{ContainerSpec, EnvironmentSpec, JobSpec},
+    stubs_layer_spec, JobStatus,
 };
 use maelstrom_util::{ext::OptionExt as _, process::ExitCode};
-use std::collections::{BTreeMap, HashMap};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    path::Path,
+    sync::Arc,
+    time::Duration,
+};
 
-struct JobInfo<ArtifactKeyT> {
+struct JobInfo<ArtifactT: TestArtifact> {
     case_name: String,
+    case_metadata: ArtifactT::CaseMetadata,
     package_name: String,
-    artifact_key: ArtifactKeyT,
+    artifact: Arc<ArtifactT>,
     case_str: String,
+    /// If the job runs a group of test cases, these are the cases. In that case, the case name
+    /// and metadata above are the group's name and its first case's metadata.
+    group: Vec<(String, ArtifactT::CaseMetadata)>,
+}
+
+/// Selected test cases that will be run together by a single job, once the whole listing of their
+/// artifact has been seen.
+struct PendingGroup<ArtifactT: TestArtifact> {
+    name: String,
+    package_name: String,
+    artifact: Arc<ArtifactT>,
+    metadata: Metadata,
+    cases: Vec<(String, ArtifactT::CaseMetadata)>,
 }
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
@@ -46,7 +71,7 @@ pub struct MainApp<'deps, DepsT: Deps> {
     options: &'deps TestingOptionsM<DepsT>,
     packages: BTreeMap<PackageIdM<DepsT>, PackageM<DepsT>>,
     next_job_id: u32,
-    jobs: HashMap<JobId, JobInfo<ArtifactKeyM<DepsT>>>,
+    jobs: HashMap<JobId, JobInfo<ArtifactM<DepsT>>>,
     collection_finished: bool,
     collection_failed: bool,
     pending_listings: u64,
@@ -56,6 +81,10 @@ pub struct MainApp<'deps, DepsT: Deps> {
     fatal_error: Result<()>,
     exit_code: ExitCode,
     test_db: TestDbM<DepsT>,
+    impact: Option<TestImpactM<DepsT>>,
+    rerun_tests: Option<BTreeSet<String>>,
+    pending_groups: Vec<PendingGroup<ArtifactM<DepsT>>>,
+    image_exported: bool,
 }
 
 impl<'deps, DepsT: Deps> MainApp<'deps, DepsT> {
@@ -79,6 +108,10 @@ impl<'deps, DepsT: Deps> MainApp<'deps, DepsT> {
             test_results: vec![],
             fatal_error: Ok(()),
             exit_code: ExitCode::SUCCESS,
+            impact: None,
+            rerun_tests: None,
+            pending_groups: vec![],
+            image_exported: false,
         }
     }
 
@@ -133,12 +166,19 @@ impl<'deps, DepsT: Deps> MainApp<'deps, DepsT> {
             self.jobs.is_empty() && self.pending_listings == 0 && self.collection_finished;
         let failure_limit_reached = self.failure_limit_reached();
 
-        all_pending_stuff_done || failure_limit_reached || self.collection_failed
+        all_pending_stuff_done
+            || failure_limit_reached
+            || self.collection_failed
+            || self.image_exported
     }
 
     fn check_for_done(&mut self) {
         if self.is_done() {
-            if !self.options.listing {
+            if let Some((test, _)) = &self.options.export_image {
+                if !self.image_exported && !self.collection_failed && self.fatal_error.is_ok() {
+                    self.fatal_error = Err(anyhow!("no selected test is named {test:?}"));
+                }
+            } else if !self.options.listing {
                 let all_pending_stuff_done =
                     self.jobs.is_empty() && self.pending_listings == 0 && self.collection_finished;
                 let stuff_not_run = !all_pending_stuff_done || self.collection_failed;
@@ -155,6 +195,43 @@ impl<'deps, DepsT: Deps> MainApp<'deps, DepsT> {
         }
     }
 
+    fn receive_test_impact(&mut self, impact: TestImpactM<DepsT>) {
+        self.impact = Some(impact);
+    }
+
+    fn receive_rerun_tests(&mut self, names: BTreeSet<String>) {
+        self.rerun_tests = Some(names);
+    }
+
+    /// Could any test in the package have been impacted by the changes we're reacting to? Packages
+    /// with previously-failing tests are always considered impacted, so those tests get rerun.
+    fn is_package_impacted(&self, package: &PackageM<DepsT>) -> bool {
+        let Some(impact) = &self.impact else {
+            return true;
+        };
+        impact.includes_package(&package.id(), &package.artifacts())
+            || self.test_db.package_has_failures(package.name())
+    }
+
+    /// Could the test case have been impacted by the changes we're reacting to? Test cases that
+    /// failed the last time they were run are always considered impacted.
+    fn is_case_impacted(
+        &self,
+        package: &PackageM<DepsT>,
+        artifact_key: &ArtifactKeyM<DepsT>,
+        case_name: &str,
+    ) -> bool {
+        let Some(impact) = &self.impact else {
+            return true;
+        };
+        impact.includes_artifact(&package.id(), artifact_key)
+            || matches!(
+                self.test_db
+                    .get_case(package.name(), artifact_key, case_name),
+                Some((CaseOutcome::Failure, _))
+            )
+    }
+
     fn receive_packages(&mut self, packages: Vec<PackageM<DepsT>>) {
         self.test_db
             .retain_packages_and_artifacts(packages.iter().map(|p| (p.name(), p.artifacts())));
@@ -162,6 +239,7 @@ impl<'deps, DepsT: Deps> MainApp<'deps, DepsT> {
         self.packages = packages
             .into_iter()
             .filter(|p| self.options.filter.filter(p, None, None).unwrap_or(true))
+            .filter(|p| self.is_package_impacted(p))
             .map(|p| (p.id(), p))
             .collect();
 
@@ -179,9 +257,16 @@ impl<'deps, DepsT: Deps> MainApp<'deps, DepsT> {
             .values()
             .map(|p| (p.name().into(), p.clone()))
             .collect();
-        self.expected_job_count = self
-            .test_db
-            .count_matching_cases(&package_name_map, &self.options.filter);
+        self.expected_job_count = match &self.rerun_tests {
+            Some(names) => names.len() as u64,
+            None => self.test_db.count_matching_cases_where(
+                &package_name_map,
+                &self.options.filter,
+                |package, artifact_key, case_name| {
+                    self.is_case_impacted(package, artifact_key, case_name)
+                },
+            ),
+        };
         if self.expected_job_count > 0 {
             self.deps
                 .send_ui_msg(UiMessage::UpdatePendingJobsCount(self.expected_job_count));
@@ -203,35 +288,64 @@ impl<'deps, DepsT: Deps> MainApp<'deps, DepsT> {
         id
     }
 
-    fn enqueue_test(
-        &mut self,
-        test_metadata: Metadata,
+    /// Look up the test cases' timings from the last time they were run. The returned priority is
+    /// higher if any of the cases are new or failed last time. The returned duration is the sum
+    /// of the cases' durations, if any of them are known.
+    fn get_priority_and_estimated_duration<'a>(
+        &self,
         package_name: &str,
         artifact: &ArtifactM<DepsT>,
-        case_name: &str,
-        case_metadata: &CaseMetadataM<DepsT>,
-    ) {
-        let case_str = artifact.format_case(package_name, case_name, case_metadata);
+        case_names: impl IntoIterator<Item = &'a str>,
+    ) -> (i8, Option<Duration>) {
+        let artifact_key = artifact.to_key();
+        let mut priority = 0;
+        let mut estimated_duration = None;
+        for case_name in case_names {
+            let (case_priority, case_duration) =
+                match self
+                    .test_db
+                    .get_case(package_name, &artifact_key, case_name)
+                {
+                    None => (1, None),
+                    Some((CaseOutcome::Success, duration)) => (0, Some(duration)),
+                    Some((CaseOutcome::Failure, duration)) => (1, Some(duration)),
+                };
+            priority = priority.max(case_priority);
+            if let Some(case_duration) = case_duration {
+                *estimated_duration.get_or_insert(Duration::ZERO) += case_duration;
+            }
+        }
+        (priority, estimated_duration)
+    }
 
+    fn build_job_spec(
+        &self,
+        test_metadata: Metadata,
+        artifact: &ArtifactM<DepsT>,
+        (program, mut arguments): (Utf8PathBuf, Vec<String>),
+        (priority, estimated_duration): (i8, Option<Duration>),
+    ) -> JobSpec {
+        arguments.extend(test_metadata.added_arguments.iter().cloned());
         let test_layers = artifact.get_test_layers(&test_metadata);
         let mut layers = test_metadata.container.layers;
         layers.extend(test_layers);
 
-        let get_timing_result = self
-            .test_db
-            .get_case(package_name, &artifact.to_key(), case_name);
-        let (priority, estimated_duration) = match get_timing_result {
-            None => (1, None),
-            Some((CaseOutcome::Success, duration)) => (0, Some(duration)),
-            Some((CaseOutcome::Failure, duration)) => (1, Some(duration)),
-        };
-
-        let (program, arguments) = artifact.build_command(case_name, case_metadata);
-        let container = ContainerSpec {
+        let mut container = ContainerSpec {
             layers,
             ..test_metadata.container
         };
-        let spec = JobSpec {
+        if let Some(vars) =
+            DepsT::TestCollector::coverage_environment(&self.options.collector_options)
+        {
+            container
+                .layers
+                .push(stubs_layer_spec!([format!("{COVERAGE_DIR}/")]));
+            container
+                .environment
+                .push(EnvironmentSpec { vars, extend: true });
+            container.mounts.push(capture_mount!(COVERAGE_DIR));
+        }
+        JobSpec {
             container,
             program,
             arguments,
@@ -243,21 +357,17 @@ impl<'deps, DepsT: Deps> MainApp<'deps, DepsT> {
             allocate_tty: None,
             priority,
             capture_file_system_changes: None,
-        };
+            exclusive: DepsT::TestCollector::benchmarking(&self.options.collector_options),
+        }
+    }
 
+    fn add_job(&mut self, spec: JobSpec, job_info: JobInfo<ArtifactM<DepsT>>) {
         let job_id = self.vend_job_id();
         self.deps.add_job(job_id, spec);
-        let job_info = JobInfo {
-            case_name: case_name.into(),
-            case_str: case_str.clone(),
-            package_name: package_name.into(),
-            artifact_key: artifact.to_key(),
-        };
+        let name = job_info.case_str.clone();
         self.jobs.insert(job_id, job_info).assert_is_none();
-        self.deps.send_ui_msg(UiMessage::JobEnqueued(UiJobEnqueued {
-            job_id,
-            name: case_str,
-        }));
+        self.deps
+            .send_ui_msg(UiMessage::JobEnqueued(UiJobEnqueued { job_id, name }));
 
         self.jobs_queued += 1;
         if self.jobs_queued > self.expected_job_count {
@@ -266,6 +376,147 @@ impl<'deps, DepsT: Deps> MainApp<'deps, DepsT> {
         }
     }
 
+    fn enqueue_test(
+        &mut self,
+        test_metadata: Metadata,
+        package_name: &str,
+        artifact: &Arc<ArtifactM<DepsT>>,
+        case_name: &str,
+        case_metadata: &CaseMetadataM<DepsT>,
+    ) {
+        let case_str = artifact.format_case(package_name, case_name, case_metadata);
+        let timing = self.get_priority_and_estimated_duration(package_name, artifact, [case_name]);
+        let command = match artifact.build_command(case_name, case_metadata) {
+            Ok(command) => command,
+            Err(err) => {
+                let err = err.context(format!("building command for {case_str}"));
+                self.receive_fatal_error(err);
+                return;
+            }
+        };
+        let spec = self.build_job_spec(test_metadata, artifact, command, timing);
+        let job_info = JobInfo {
+            case_name: case_name.into(),
+            case_metadata: case_metadata.clone(),
+            case_str,
+            package_name: package_name.into(),
+            artifact: artifact.clone(),
+            group: vec![],
+        };
+        self.add_job(spec, job_info);
+    }
+
+    /// Export the container the test case would run in, instead of running it.
+    fn export_image(
+        &mut self,
+        test_metadata: Metadata,
+        artifact: &ArtifactM<DepsT>,
+        case_name: &str,
+        case_metadata: &CaseMetadataM<DepsT>,
+        case_str: &str,
+        path: &Path,
+    ) {
+        let command = match artifact.build_command(case_name, case_metadata) {
+            Ok(command) => command,
+            Err(err) => {
+                let err = err.context(format!("building command for {case_str}"));
+                self.receive_fatal_error(err);
+                return;
+            }
+        };
+        let spec = self.build_job_spec(test_metadata, artifact, command, (0, None));
+        self.deps.export_image(spec, path.to_owned());
+        self.image_exported = true;
+    }
+
+    /// Enqueue a single job to run all of the test cases in the group. The job writes a report of
+    /// each case's result, which is sent back with the job's result. The timeout applies to each
+    /// test case, so the job's timeout is scaled by the number of cases.
+    fn enqueue_group(&mut self, group: &PendingGroup<ArtifactM<DepsT>>) {
+        let case_str = format!("{} ({} tests)", group.name, group.cases.len());
+        let timing = self.get_priority_and_estimated_duration(
+            &group.package_name,
+            &group.artifact,
+            group.cases.iter().map(|(case_name, _)| case_name.as_str()),
+        );
+        let command = match group.artifact.build_group_command(&group.cases) {
+            Ok(command) => command,
+            Err(err) => {
+                let err = err.context(format!("building command for {case_str}"));
+                self.receive_fatal_error(err);
+                return;
+            }
+        };
+        let mut spec =
+            self.build_job_spec(group.metadata.clone(), &group.artifact, command, timing);
+        spec.container
+            .layers
+            .push(stubs_layer_spec!([format!("{GROUP_REPORT_DIR}/")]));
+        spec.container.mounts.push(capture_mount!(GROUP_REPORT_DIR));
+        let cases = u32::try_from(group.cases.len()).unwrap_or(u32::MAX);
+        spec.timeout = spec
+            .timeout
+            .and_then(|timeout| Timeout::new(u32::from(timeout).saturating_mul(cases)));
+        let job_info = JobInfo {
+            case_name: group.name.clone(),
+            case_metadata: group.cases[0].1.clone(),
+            case_str,
+            package_name: group.package_name.clone(),
+            artifact: group.artifact.clone(),
+            group: group.cases.clone(),
+        };
+        self.add_job(spec, job_info);
+    }
+
+    /// Add the selected test case to the pending group with the given name, creating the group if
+    /// necessary. Only cases with the same metadata are put in the same group.
+    fn add_to_group(
+        &mut self,
+        name: String,
+        test_metadata: Metadata,
+        package_name: &str,
+        artifact: &Arc<ArtifactM<DepsT>>,
+        case_name: &str,
+        case_metadata: &CaseMetadataM<DepsT>,
+    ) {
+        let case = (case_name.to_owned(), case_metadata.clone());
+        let artifact_key = artifact.to_key();
+        match self.pending_groups.iter_mut().find(|group| {
+            group.name == name
+                && group.artifact.to_key() == artifact_key
+                && group.metadata == test_metadata
+        }) {
+            Some(group) => group.cases.push(case),
+            None => self.pending_groups.push(PendingGroup {
+                name,
+                package_name: package_name.into(),
+                artifact: artifact.clone(),
+                metadata: test_metadata,
+                cases: vec![case],
+            }),
+        }
+    }
+
+    /// Enqueue the jobs for all of the pending groups. A group with only one test case is run like
+    /// any other test case.
+    fn enqueue_pending_groups(&mut self) {
+        for group in std::mem::take(&mut self.pending_groups) {
+            for _ in 0..self.options.repeat.into() {
+                if let [(case_name, case_metadata)] = group.cases.as_slice() {
+                    self.enqueue_test(
+                        group.metadata.clone(),
+                        &group.package_name,
+                        &group.artifact,
+                        case_name,
+                        case_metadata,
+                    );
+                } else {
+                    self.enqueue_group(&group);
+                }
+            }
+        }
+    }
+
     fn handle_ignored_test(
         &mut self,
         package_name: &str,
@@ -286,12 +537,16 @@ impl<'deps, DepsT: Deps> MainApp<'deps, DepsT> {
         self.test_results.push((case_str, TestResult::Ignored));
     }
 
+    /// Enqueue the test case if it was selected, or its known subtests if it wasn't. When a test
+    /// case is selected, so are all of its subtests. If we're fanning out subtests, a selected test
+    /// case with known subtests is run by running each of its subtests as its own job.
     fn maybe_enqueue_test(
         &mut self,
-        artifact: &ArtifactM<DepsT>,
-        case_name: &String,
+        artifact: &Arc<ArtifactM<DepsT>>,
+        case_name: &str,
         case_metadata: &CaseMetadataM<DepsT>,
-        ignored_listing: &[String],
+        ignored: bool,
+        parent_selected: bool,
     ) {
         let package = self
             .packages
@@ -299,36 +554,114 @@ impl<'deps, DepsT: Deps> MainApp<'deps, DepsT> {
             .expect("artifact for unknown package");
         let package_name = package.name().to_owned();
 
-        let case_tuple = (case_name.as_str(), case_metadata);
-        let selected = self
-            .options
-            .filter
-            .filter(package, Some(&artifact.to_key()), Some(case_tuple))
-            .expect("should have case");
+        let selected = parent_selected
+            || self
+                .options
+                .filter
+                .filter(
+                    package,
+                    Some(&artifact.to_key()),
+                    Some((case_name, case_metadata)),
+                )
+                .expect("should have case");
+        let subtests = self
+            .test_db
+            .get_subtests(&package_name, &artifact.to_key(), case_name);
+        let fan_out = !subtests.is_empty()
+            && DepsT::TestCollector::fan_out_subtests(&self.options.collector_options);
+
+        if selected && (!self.options.running_tests() || !fan_out) {
+            self.maybe_enqueue_selected_test(
+                artifact,
+                &package_name,
+                case_name,
+                case_metadata,
+                ignored,
+            );
+        }
+        if !selected || !self.options.running_tests() || fan_out {
+            for subtest in &subtests {
+                self.maybe_enqueue_test(artifact, subtest, case_metadata, ignored, selected);
+            }
+        }
+    }
+
+    fn maybe_enqueue_selected_test(
+        &mut self,
+        artifact: &Arc<ArtifactM<DepsT>>,
+        package_name: &str,
+        case_name: &str,
+        case_metadata: &CaseMetadataM<DepsT>,
+        ignored: bool,
+    ) {
+        let package = self
+            .packages
+            .get(&artifact.package())
+            .expect("artifact for unknown package");
+        if !self.is_case_impacted(package, &artifact.to_key(), case_name) {
+            return;
+        }
 
-        if !selected {
+        let case_str = artifact.format_case(package_name, case_name, case_metadata);
+        if self
+            .rerun_tests
+            .as_ref()
+            .is_some_and(|names| !names.contains(&case_str))
+        {
             return;
         }
 
         if self.options.listing {
-            let case_str = artifact.format_case(&package_name, case_name, case_metadata);
             self.deps.send_ui_msg(UiMessage::List(case_str));
             return;
         }
 
+        if self
+            .options
+            .export_image
+            .as_ref()
+            .is_some_and(|(test, _)| *test != case_str || self.image_exported)
+        {
+            return;
+        }
+
         let test_metadata = self
             .options
             .test_metadata
-            .get_metadata_for_test(package, &artifact.to_key(), case_tuple)
+            .get_metadata_for_test(package, &artifact.to_key(), (case_name, case_metadata))
             .expect("we always parse valid test metadata");
 
-        if ignored_listing.contains(case_name) || test_metadata.ignore {
-            self.handle_ignored_test(&package_name, artifact, case_name, case_metadata);
+        if let Some((_, path)) = &self.options.export_image {
+            self.export_image(
+                test_metadata,
+                artifact,
+                case_name,
+                case_metadata,
+                &case_str,
+                path,
+            );
+            return;
+        }
+
+        let group = test_metadata
+            .group_by
+            .and_then(|group_by| artifact.case_group(case_name, case_metadata, group_by));
+        if ignored || test_metadata.ignore {
+            self.handle_ignored_test(package_name, artifact, case_name, case_metadata);
+        } else if let Some(group) = group {
+            self.add_to_group(
+                group,
+                test_metadata,
+                package_name,
+                artifact,
+                case_name,
+                case_metadata,
+            );
         } else {
             for _ in 0..self.options.repeat.into() {
                 self.enqueue_test(
                     test_metadata.clone(),
-                    &package_name,
+                    package_name,
                     artifact,
                     case_name,
                     case_metadata,
@@ -355,9 +688,12 @@ impl<'deps, DepsT: Deps> MainApp<'deps, DepsT> {
             .update_artifact_cases(package.name(), artifact.to_key(), listing.clone());
 
         self.pending_listings -= 1;
+        let artifact = Arc::new(artifact);
         for (case_name, case_metadata) in &listing {
-            self.maybe_enqueue_test(&artifact, case_name, case_metadata, &ignored_listing);
+            let ignored = ignored_listing.contains(case_name);
+            self.maybe_enqueue_test(&artifact, case_name, case_metadata, ignored, false);
         }
+        self.enqueue_pending_groups();
 
         if self.pending_listings == 0 && self.collection_finished {
             self.deps.send_ui_msg(UiMessage::DoneQueuingJobs);
@@ -374,12 +710,46 @@ impl<'deps, DepsT: Deps> MainApp<'deps, DepsT> {
     fn receive_job_finished(
         &mut self,
         job_id: JobId,
-        result: Result<(ClientJobId, JobOutcomeResult)>,
+        mut result: Result<(ClientJobId, JobOutcomeResult)>,
     ) {
         let job_info = self.jobs.remove(&job_id).expect("job finishes only once");
+
+        let mut group_report = vec![];
+        if let Ok((_, Ok(outcome))) = &mut result {
+            let effects = match outcome {
+                JobOutcome::Completed(JobCompleted { effects, .. }) => effects,
+                JobOutcome::TimedOut(effects) => effects,
+            };
+            let coverage_data;
+            (group_report, coverage_data) = std::mem::take(&mut effects.captured_files)
+                .into_iter()
+                .partition(|file| file.path.starts_with(GROUP_REPORT_DIR));
+            if !coverage_data.is_empty() {
+                self.deps.save_coverage_data(coverage_data);
+            }
+        }
+
+        if !job_info.group.is_empty() {
+            self.receive_group_finished(job_id, job_info, result, group_report);
+            self.check_for_done();
+            return;
+        }
+
+        if DepsT::TestCollector::benchmarking(&self.options.collector_options) {
+            if let Some(lines) = get_benchmark_output(&result) {
+                self.deps.save_benchmark_output(&job_info.case_str, lines);
+            }
+        }
+
+        let subtest_results =
+            get_subtest_results::<DepsT::TestCollector>(&job_info.case_name, &result);
+        let expects_failure = job_info
+            .artifact
+            .expects_failure(&job_info.case_name, &job_info.case_metadata);
         let (ui_job_res, exit_code) = build_ui_job_result_and_exit_code::<DepsT::TestCollector>(
             job_id,
             &job_info.case_str,
+            expects_failure,
             result,
         );
 
@@ -392,18 +762,169 @@ impl<'deps, DepsT: Deps> MainApp<'deps, DepsT> {
         if let Some(duration) = ui_job_res.duration {
             self.test_db.update_case(
                 &job_info.package_name,
-                &job_info.artifact_key,
+                &job_info.artifact.to_key(),
                 &job_info.case_name,
                 matches!(result, TestResult::Failed),
                 duration,
             );
         }
         self.deps.send_ui_msg(UiMessage::JobFinished(ui_job_res));
-        self.test_results.push((job_info.case_str, result));
+        self.test_results.push((job_info.case_str.clone(), result));
+        self.receive_subtest_results(&job_info, subtest_results);
 
         self.check_for_done();
     }
 
+    /// Record and report the result of each test case run by a finished job for a group of test
+    /// cases. The results come from the report the job wrote. The first case missing from the
+    /// report gets the job's own output, and all of the missing cases fail.
+    fn receive_group_finished(
+        &mut self,
+        job_id: JobId,
+        job_info: JobInfo<ArtifactM<DepsT>>,
+        result: Result<(ClientJobId, JobOutcomeResult)>,
+        report: Vec<CapturedFile>,
+    ) {
+        let (mut job_res, exit_code) = build_ui_job_result_and_exit_code::<DepsT::TestCollector>(
+            job_id,
+            &job_info.case_str,
+            false,
+            result,
+        );
+        if self.exit_code == ExitCode::SUCCESS {
+            self.exit_code = exit_code;
+        }
+
+        let (case_results, missing_status) =
+            match DepsT::TestCollector::get_group_results(&job_info.group, &report) {
+                Ok(case_results) => {
+                    let missing_status = match job_res.status {
+                        UiJobStatus::Ok | UiJobStatus::Ignored => {
+                            UiJobStatus::Failure(Some("no result in test report".into()))
+                        }
+                        status => status,
+                    };
+                    (case_results, missing_status)
+                }
+                Err(err) => (
+                    vec![],
+                    UiJobStatus::Error(format!("error reading test report: {err}")),
+                ),
+            };
+        let mut case_results: HashMap<_, _> = case_results
+            .into_iter()
+            .map(|case_result| (case_result.name.clone(), case_result))
+            .collect();
+
+        let artifact_key = job_info.artifact.to_key();
+        for (i, (case_name, case_metadata)) in job_info.group.iter().enumerate() {
+            let job_id = if i == 0 {
+                job_id
+            } else {
+                self.jobs_queued += 1;
+                if self.jobs_queued > self.expected_job_count {
+                    self.deps
+                        .send_ui_msg(UiMessage::UpdatePendingJobsCount(self.jobs_queued));
+                }
+                self.vend_job_id()
+            };
+            let case_str =
+                job_info
+                    .artifact
+                    .format_case(&job_info.package_name, case_name, case_metadata);
+            let ui_job_res = match case_results.remove(case_name) {
+                Some(case_result) => UiJobResult {
+                    name: case_str.clone(),
+                    job_id,
+                    duration: Some(case_result.duration),
+                    status: case_result.status,
+                    stdout: case_result.output,
+                    stderr: vec![],
+                },
+                None => UiJobResult {
+                    name: case_str.clone(),
+                    job_id,
+                    duration: None,
+                    status: missing_status.clone(),
+                    stdout: std::mem::take(&mut job_res.stdout),
+                    stderr: std::mem::take(&mut job_res.stderr),
+                },
+            };
+
+            let result = TestResult::from(ui_job_res.status.clone());
+            if let Some(duration) = ui_job_res.duration {
+                self.test_db.update_case(
+                    &job_info.package_name,
+                    &artifact_key,
+                    case_name,
+                    matches!(result, TestResult::Failed),
+                    duration,
+                );
+            }
+            if result == TestResult::Failed && self.exit_code == ExitCode::SUCCESS {
+                self.exit_code = ExitCode::FAILURE;
+            }
+            self.deps.send_ui_msg(UiMessage::JobFinished(ui_job_res));
+            self.test_results.push((case_str, result));
+        }
+    }
+
+    /// Record and report the results of the subtests found in a finished job's output. Subtests
+    /// run this way don't have jobs of their own, so, like ignored tests, they are reported as
+    /// finished without ever being enqueued.
+    fn receive_subtest_results(
+        &mut self,
+        job_info: &JobInfo<ArtifactM<DepsT>>,
+        subtest_results: Vec<SubtestResult>,
+    ) {
+        let artifact_key = job_info.artifact.to_key();
+        let mut seen = HashSet::new();
+        for subtest in subtest_results {
+            // If we don't know about the parent, just treat this as a subtest of the job's case.
+            let parent = if seen.contains(&subtest.parent) {
+                subtest.parent
+            } else {
+                job_info.case_name.clone()
+            };
+            let result = TestResult::from(subtest.status.clone());
+            self.test_db.add_subtest_case(
+                &job_info.package_name,
+                &artifact_key,
+                &parent,
+                &subtest.name,
+            );
+            self.test_db.update_case(
+                &job_info.package_name,
+                &artifact_key,
+                &subtest.name,
+                matches!(result, TestResult::Failed),
+                subtest.duration,
+            );
+
+            let case_str = job_info.artifact.format_case(
+                &job_info.package_name,
+                &subtest.name,
+                &job_info.case_metadata,
+            );
+            let job_id = self.vend_job_id();
+            self.jobs_queued += 1;
+            if self.jobs_queued > self.expected_job_count {
+                self.deps
+                    .send_ui_msg(UiMessage::UpdatePendingJobsCount(self.jobs_queued));
+            }
+            self.deps.send_ui_msg(UiMessage::JobFinished(UiJobResult {
+                name: case_str.clone(),
+                job_id,
+                duration: Some(subtest.duration),
+                status: subtest.status,
+                stdout: vec![],
+                stderr: vec![],
+            }));
+            self.test_results.push((case_str, result));
+            seen.insert(subtest.name);
+        }
+    }
+
     fn receive_job_update(&mut self, job_id: JobId, result: Result<JobStatus>) {
         if self.is_done() {
             return;
@@ -448,6 +969,8 @@ impl<'deps, DepsT: Deps> MainApp<'deps, DepsT> {
     pub fn receive_message(&mut self, message: MainAppMessageM<DepsT>) {
         match message {
             MainAppMessage::Start => self.start(),
+            MainAppMessage::TestImpact { impact } => self.receive_test_impact(impact),
+            MainAppMessage::RerunTests { names } => self.receive_rerun_tests(names),
             MainAppMessage::Packages { packages } => self.receive_packages(packages),
             MainAppMessage::ArtifactBuilt { artifact } => self.receive_artifact_built(artifact),
             MainAppMessage::TestsListed {
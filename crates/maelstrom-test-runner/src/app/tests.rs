@@ -17,9 +17,9 @@ use crate::{
 use anyhow::anyhow;
 use itertools::Itertools as _;
 use maelstrom_base::{
-    devices_mount, nonempty, proc_mount, sys_mount, tmp_mount, ClientJobId, JobBrokerStatus,
-    JobCompleted, JobDevice, JobEffects, JobError, JobOutcome, JobOutputResult,
-    JobTerminationStatus, JobWorkerStatus, NonEmpty,
+    capture_mount, devices_mount, nonempty, proc_mount, sys_mount, tmp_mount, CapturedFile,
+    ClientJobId, JobBrokerStatus, JobCompleted, JobDevice, JobEffects, JobError, JobOutcome,
+    JobOutputResult, JobTerminationStatus, JobWorkerStatus, NonEmpty,
 };
 use maelstrom_client::{
     container_spec,
@@ -28,15 +28,20 @@ use maelstrom_client::{
 };
 use maelstrom_simex::SimulationExplorer;
 use maelstrom_util::process::ExitCode;
-use std::{cell::RefCell, collections::HashSet, time::Duration};
+use std::{cell::RefCell, collections::HashSet, path::PathBuf, time::Duration};
 use TestMessage::*;
 
 #[derive(Debug, PartialEq, Eq)]
+#[allow(clippy::large_enum_variant)]
 enum TestMessage {
     AddJob {
         job_id: JobId,
         spec: JobSpec,
     },
+    ExportImage {
+        spec: JobSpec,
+        path: PathBuf,
+    },
     ListTests {
         artifact: FakeTestArtifact,
     },
@@ -50,6 +55,13 @@ enum TestMessage {
         msg: UiMessage,
     },
     StartShutdown,
+    SaveCoverageData {
+        files: Vec<CapturedFile>,
+    },
+    SaveBenchmarkOutput {
+        case_str: String,
+        lines: Vec<String>,
+    },
 }
 
 #[derive(Default)]
@@ -87,6 +99,11 @@ impl Deps for TestDeps {
         self_.messages.push(TestMessage::AddJob { job_id, spec });
     }
 
+    fn export_image(&self, spec: JobSpec, path: PathBuf) {
+        let mut self_ = self.0.borrow_mut();
+        self_.messages.push(TestMessage::ExportImage { spec, path });
+    }
+
     fn list_tests(&self, artifact: FakeTestArtifact) {
         let mut self_ = self.0.borrow_mut();
         self_.messages.push(TestMessage::ListTests { artifact });
@@ -101,6 +118,19 @@ impl Deps for TestDeps {
         let mut self_ = self.0.borrow_mut();
         self_.messages.push(TestMessage::SendUiMsg { msg });
     }
+
+    fn save_coverage_data(&self, files: Vec<CapturedFile>) {
+        let mut self_ = self.0.borrow_mut();
+        self_.messages.push(TestMessage::SaveCoverageData { files });
+    }
+
+    fn save_benchmark_output(&self, case_str: &str, lines: Vec<String>) {
+        let mut self_ = self.0.borrow_mut();
+        self_.messages.push(TestMessage::SaveBenchmarkOutput {
+            case_str: case_str.into(),
+            lines,
+        });
+    }
 }
 
 struct Fixture<'deps> {
@@ -166,6 +196,7 @@ struct TestDbEntry {
     package_name: String,
     artifact_name: Option<String>,
     case_name: Option<String>,
+    parent: Option<String>,
     entry_data: Option<(crate::test_db::CaseOutcome, NonEmpty<Duration>)>,
 }
 
@@ -175,6 +206,7 @@ impl TestDbEntry {
             package_name: package_name.into(),
             artifact_name: Some(artifact_name.into()),
             case_name: Some(case_name.into()),
+            parent: None,
             entry_data: None,
         }
     }
@@ -208,9 +240,17 @@ impl TestDbEntry {
             package_name: package_name.into(),
             artifact_name: Some(artifact_name.into()),
             case_name: None,
+            parent: None,
             entry_data: None,
         }
     }
+
+    fn subtest_of(self, parent: &str) -> Self {
+        Self {
+            parent: Some(parent.into()),
+            ..self
+        }
+    }
 }
 
 fn test_db_from_entries<IterT>(entries: IterT) -> TestDb<StringArtifactKey, NoCaseMetadata>
@@ -236,16 +276,17 @@ where
                                     && e.artifact_name.as_ref().is_some_and(|a| a == artifact)
                                         & e.case_name.is_some()
                             })
-                            .map(|e| (e.case_name, e.entry_data))
+                            .map(|e| (e.case_name, e.parent, e.entry_data))
                             .collect();
                         (
                             StringArtifactKey::from(artifact),
                             crate::test_db::Artifact::from_iter(cases.into_iter().map(
-                                |(case, entry_data)| {
+                                |(case, parent, entry_data)| {
                                     (
                                         case.unwrap(),
                                         crate::test_db::CaseData {
                                             metadata: NoCaseMetadata,
+                                            parent,
                                             when_read: entry_data,
                                             this_run: None,
                                         },
@@ -286,6 +327,7 @@ fn default_testing_options() -> TestingOptions<FakeTestFilter, TestOptions> {
         repeat: Repeat::try_from(1).unwrap(),
         stop_after: None,
         listing: false,
+        export_image: None,
     }
 }
 
@@ -426,9 +468,47 @@ fn test_spec(bin: &str, name: &str) -> JobSpec {
         allocate_tty: None,
         priority: 1,
         capture_file_system_changes: None,
+        exclusive: false,
+    }
+}
+
+fn group_spec(bin: &str, names: &[&str]) -> JobSpec {
+    let mut spec = test_spec(bin, names[0]);
+    spec.arguments = names.iter().map(|name| name.to_string()).collect();
+    spec.container
+        .layers
+        .push(stubs_layer_spec!(["/maelstrom-group-report/"]));
+    spec.container
+        .mounts
+        .push(capture_mount!("/maelstrom-group-report"));
+    spec
+}
+
+fn group_report(contents: &str) -> CapturedFile {
+    CapturedFile {
+        path: "/maelstrom-group-report/report".into(),
+        contents: contents.as_bytes().into(),
     }
 }
 
+fn job_status_complete_with_files(
+    exit_code: u8,
+    captured_files: Vec<CapturedFile>,
+) -> anyhow::Result<JobStatus> {
+    Ok(JobStatus::Completed {
+        client_job_id: ClientJobId::from(1),
+        result: Ok(JobOutcome::Completed(JobCompleted {
+            status: JobTerminationStatus::Exited(exit_code),
+            effects: JobEffects {
+                stdout: JobOutputResult::None,
+                stderr: JobOutputResult::None,
+                duration: Duration::from_secs(1),
+                captured_files,
+            },
+        })),
+    })
+}
+
 fn default_container() -> ContainerSpec {
     container_spec! {
         layers: [
@@ -678,6 +758,7 @@ fn job_status_complete(exit_code: u8) -> anyhow::Result<JobStatus> {
                 stdout: JobOutputResult::None,
                 stderr: JobOutputResult::None,
                 duration: Duration::from_secs(1),
+                captured_files: vec![],
             },
         })),
     })
@@ -1071,7 +1152,8 @@ test_output_test_with_error_simex! {
         effects: JobEffects {
             stdout: JobOutputResult::None,
             stderr: JobOutputResult::None,
-            duration: Duration::from_secs(1)
+            duration: Duration::from_secs(1),
+            captured_files: vec![],
         }
     }))),
     UiJobResult {
@@ -1099,7 +1181,8 @@ test_output_test! {
         effects: JobEffects {
             stdout: JobOutputResult::None,
             stderr: JobOutputResult::None,
-            duration: Duration::from_secs(1)
+            duration: Duration::from_secs(1),
+            captured_files: vec![],
         }
     }))),
     UiJobResult {
@@ -1127,7 +1210,8 @@ test_output_test! {
         effects: JobEffects {
             stdout: JobOutputResult::None,
             stderr: JobOutputResult::Inline(b"signal yo".as_slice().into()),
-            duration: Duration::from_secs(1)
+            duration: Duration::from_secs(1),
+            captured_files: vec![],
         }
     }))),
     UiJobResult {
@@ -1154,7 +1238,8 @@ test_output_test! {
         JobEffects {
             stdout: JobOutputResult::None,
             stderr: JobOutputResult::None,
-            duration: Duration::from_secs(1)
+            duration: Duration::from_secs(1),
+            captured_files: vec![],
         }
     ))),
     UiJobResult {
@@ -1245,7 +1330,8 @@ test_output_test! {
         effects: JobEffects {
             stdout: JobOutputResult::Inline(b"hello\nstdout".as_slice().into()),
             stderr: JobOutputResult::Inline(b"hello\nstderr".as_slice().into()),
-            duration: Duration::from_secs(1)
+            duration: Duration::from_secs(1),
+            captured_files: vec![],
         }
     }))),
     UiJobResult {
@@ -1272,7 +1358,8 @@ test_output_test! {
         JobEffects {
             stdout: JobOutputResult::Inline(b"hello\nstdout".as_slice().into()),
             stderr: JobOutputResult::Inline(b"hello\nstderr".as_slice().into()),
-            duration: Duration::from_secs(1)
+            duration: Duration::from_secs(1),
+            captured_files: vec![],
         }
     ))),
     UiJobResult {
@@ -1300,7 +1387,8 @@ test_output_test! {
         effects: JobEffects {
             stdout: JobOutputResult::Inline(b"hello\nstdout".as_slice().into()),
             stderr: JobOutputResult::Inline(b"hello\nstderr".as_slice().into()),
-            duration: Duration::from_secs(1)
+            duration: Duration::from_secs(1),
+            captured_files: vec![],
         }
     }))),
     UiJobResult {
@@ -1332,7 +1420,8 @@ test_output_test! {
             stderr: JobOutputResult::Truncated {
                 first: b"hello\nstderr".as_slice().into(), truncated: 12
             },
-            duration: Duration::from_secs(1)
+            duration: Duration::from_secs(1),
+            captured_files: vec![],
         }
     }))),
     UiJobResult {
@@ -1364,7 +1453,8 @@ test_output_test! {
         effects: JobEffects {
             stdout: JobOutputResult::Inline(b"fixture: ignoring test test_a".as_slice().into()),
             stderr: JobOutputResult::None,
-            duration: Duration::from_secs(1)
+            duration: Duration::from_secs(1),
+            captured_files: vec![],
         }
     }))),
     UiJobResult {
@@ -1395,7 +1485,8 @@ test_output_test! {
                 truncated: 12
             },
             stderr: JobOutputResult::None,
-            duration: Duration::from_secs(1)
+            duration: Duration::from_secs(1),
+            captured_files: vec![],
         }
     }))),
     UiJobResult {
@@ -1425,7 +1516,8 @@ test_output_test! {
                 b"fixture: a\ntest stdout\nfixture: b\ntest_a FAILED\n".as_slice().into()
             ),
             stderr: JobOutputResult::None,
-            duration: Duration::from_secs(1)
+            duration: Duration::from_secs(1),
+            captured_files: vec![],
         }
     }))),
     UiJobResult {
@@ -1456,7 +1548,8 @@ test_output_test! {
                 truncated: 12
             },
             stderr: JobOutputResult::None,
-            duration: Duration::from_secs(1)
+            duration: Duration::from_secs(1),
+            captured_files: vec![],
         }
     }))),
     UiJobResult {
@@ -1742,21 +1835,12 @@ script_test_with_error_simex! {
     };
 }
 
-script_test_with_error_simex! {
-    ignored_tests_via_directive,
-    @ test_metadata = MetadataStore::load(
-        &format!("{DEFAULT_METADATA_STR}{}",
-            r#"
-                [[directives]]
-                filter = "name = \"test_b\""
-                ignore = true
-            "#
-        ),
-        &Default::default(),
-    ).unwrap(),
+script_test! {
+    captured_coverage_data_saved,
+    test_db_in = [],
+    expected_exit_code = ExitCode::SUCCESS,
     expected_test_db_out = [
-        TestDbEntry::success("foo_pkg", "foo_test", "test_a", nonempty![Duration::from_secs(1)]),
-        TestDbEntry::new("foo_pkg", "foo_test", "test_b")
+        TestDbEntry::success("foo_pkg", "foo_test", "test_a", nonempty![Duration::from_secs(1)])
     ],
     Start => {
         SendUiMsg {
@@ -1780,7 +1864,7 @@ script_test_with_error_simex! {
     };
     TestsListed {
         artifact: fake_artifact("foo_test", "foo_pkg"),
-        listing: vec![("test_a".into(), NoCaseMetadata), ("test_b".into(), NoCaseMetadata)],
+        listing: vec![("test_a".into(), NoCaseMetadata)],
         ignored_listing: vec![]
     } => {
         AddJob {
@@ -1795,12 +1879,6 @@ script_test_with_error_simex! {
         },
         SendUiMsg {
             msg: UiMessage::UpdatePendingJobsCount(1)
-        },
-        SendUiMsg {
-            msg: ui_job_result("foo_pkg test_b", 2, UiJobStatus::Ignored)
-        },
-        SendUiMsg {
-            msg: UiMessage::UpdatePendingJobsCount(2)
         }
     };
     CollectionFinished { wait_status: wait_success() } => {
@@ -1810,8 +1888,28 @@ script_test_with_error_simex! {
     };
     JobUpdate {
         job_id: JobId::from(1),
-        result: job_status_complete(0),
+        result: Ok(JobStatus::Completed {
+            client_job_id: ClientJobId::from(1),
+            result: Ok(JobOutcome::Completed(JobCompleted {
+                status: JobTerminationStatus::Exited(0),
+                effects: JobEffects {
+                    stdout: JobOutputResult::None,
+                    stderr: JobOutputResult::None,
+                    duration: Duration::from_secs(1),
+                    captured_files: vec![CapturedFile {
+                        path: "/maelstrom-coverage/a.profraw".into(),
+                        contents: Box::new(*b"data"),
+                    }],
+                },
+            })),
+        }),
     } => {
+        SaveCoverageData {
+            files: vec![CapturedFile {
+                path: "/maelstrom-coverage/a.profraw".into(),
+                contents: Box::new(*b"data"),
+            }],
+        },
         SendUiMsg {
             msg: ui_job_result("foo_pkg test_a", 1, UiJobStatus::Ok)
         },
@@ -1819,7 +1917,7 @@ script_test_with_error_simex! {
             msg: UiMessage::AllJobsFinished(UiJobSummary {
                 succeeded: 1,
                 failed: vec![],
-                ignored: vec!["foo_pkg test_b".into()],
+                ignored: vec![],
                 not_run: None,
             })
         },
@@ -1827,16 +1925,18 @@ script_test_with_error_simex! {
     };
 }
 
-//   __ _ _ _            _
-//  / _(_) | |_ ___ _ __(_)_ __   __ _
-// | |_| | | __/ _ \ '__| | '_ \ / _` |
-// |  _| | | ||  __/ |  | | | | | (_| |
-// |_| |_|_|\__\___|_|  |_|_| |_|\__, |
-//                               |___/
-
 script_test_with_error_simex! {
-    filtering_cases,
-    @ filter = SimpleFilter::Name("test_a".into()).into(),
+    ignored_tests_via_directive,
+    @ test_metadata = MetadataStore::load(
+        &format!("{DEFAULT_METADATA_STR}{}",
+            r#"
+                [[directives]]
+                filter = "name = \"test_b\""
+                ignore = true
+            "#
+        ),
+        &Default::default(),
+    ).unwrap(),
     expected_test_db_out = [
         TestDbEntry::success("foo_pkg", "foo_test", "test_a", nonempty![Duration::from_secs(1)]),
         TestDbEntry::new("foo_pkg", "foo_test", "test_b")
@@ -1879,6 +1979,12 @@ script_test_with_error_simex! {
         SendUiMsg {
             msg: UiMessage::UpdatePendingJobsCount(1)
         },
+        SendUiMsg {
+            msg: ui_job_result("foo_pkg test_b", 2, UiJobStatus::Ignored)
+        },
+        SendUiMsg {
+            msg: UiMessage::UpdatePendingJobsCount(2)
+        }
     };
     CollectionFinished { wait_status: wait_success() } => {
         SendUiMsg {
@@ -1896,7 +2002,7 @@ script_test_with_error_simex! {
             msg: UiMessage::AllJobsFinished(UiJobSummary {
                 succeeded: 1,
                 failed: vec![],
-                ignored: vec![],
+                ignored: vec!["foo_pkg test_b".into()],
                 not_run: None,
             })
         },
@@ -1905,10 +2011,22 @@ script_test_with_error_simex! {
 }
 
 script_test_with_error_simex! {
-    filtering_packages,
-    @ filter = SimpleFilter::Package("bar_pkg".into()).into(),
+    grouped_tests_via_directive,
+    @ test_metadata = MetadataStore::load(
+        &format!("{DEFAULT_METADATA_STR}{}",
+            r#"
+                [[directives]]
+                group_by = "module"
+            "#
+        ),
+        &Default::default(),
+    ).unwrap(),
+    test_db_in = [],
+    expected_exit_code = ExitCode::from(1),
     expected_test_db_out = [
-        TestDbEntry::success("bar_pkg", "bar_test", "test_a", nonempty![Duration::from_secs(1)])
+        TestDbEntry::success("foo_pkg", "foo_test", "test_a", nonempty![Duration::from_secs(1)]),
+        TestDbEntry::failure("foo_pkg", "foo_test", "test_b", nonempty![Duration::from_secs(1)]),
+        TestDbEntry::new("foo_pkg", "foo_test", "test_c")
     ],
     Start => {
         SendUiMsg {
@@ -1916,35 +2034,37 @@ script_test_with_error_simex! {
         },
         GetPackages
     };
-    Packages {
-        packages: vec![fake_pkg("foo_pkg", ["foo_test"]), fake_pkg("bar_pkg", ["bar_test"])]
-    } => {
+    Packages { packages: vec![fake_pkg("foo_pkg", ["foo_test"])] } => {
         StartCollection {
             color: false,
             options: TestOptions,
-            packages: vec![fake_pkg("bar_pkg", ["bar_test"])]
+            packages: vec![fake_pkg("foo_pkg", ["foo_test"])]
         }
     };
     ArtifactBuilt {
-        artifact: fake_artifact("bar_test", "bar_pkg"),
+        artifact: fake_artifact("foo_test", "foo_pkg"),
     } => {
         ListTests {
-            artifact: fake_artifact("bar_test", "bar_pkg"),
+            artifact: fake_artifact("foo_test", "foo_pkg"),
         }
     };
     TestsListed {
-        artifact: fake_artifact("bar_test", "bar_pkg"),
-        listing: vec![("test_a".into(), NoCaseMetadata)],
+        artifact: fake_artifact("foo_test", "foo_pkg"),
+        listing: vec![
+            ("test_a".into(), NoCaseMetadata),
+            ("test_b".into(), NoCaseMetadata),
+            ("test_c".into(), NoCaseMetadata),
+        ],
         ignored_listing: vec![]
     } => {
         AddJob {
             job_id: JobId::from(1),
-            spec: test_spec("bar_test", "test_a"),
+            spec: group_spec("foo_test", &["test_a", "test_b", "test_c"]),
         },
         SendUiMsg {
             msg: UiMessage::JobEnqueued(UiJobEnqueued {
                 job_id: JobId::from(1),
-                name: "bar_pkg test_a".into()
+                name: "foo_test (3 tests)".into()
             })
         },
         SendUiMsg {
@@ -1958,15 +2078,34 @@ script_test_with_error_simex! {
     };
     JobUpdate {
         job_id: JobId::from(1),
-        result: job_status_complete(0),
+        result: job_status_complete_with_files(1, vec![group_report("test_a ok\ntest_b failed\n")]),
     } => {
         SendUiMsg {
-            msg: ui_job_result("bar_pkg test_a", 1, UiJobStatus::Ok)
+            msg: ui_job_result("foo_pkg test_a", 1, UiJobStatus::Ok)
+        },
+        SendUiMsg {
+            msg: UiMessage::UpdatePendingJobsCount(2)
+        },
+        SendUiMsg {
+            msg: ui_job_result("foo_pkg test_b", 2, UiJobStatus::Failure(None))
+        },
+        SendUiMsg {
+            msg: UiMessage::UpdatePendingJobsCount(3)
+        },
+        SendUiMsg {
+            msg: UiMessage::JobFinished(UiJobResult {
+                name: "foo_pkg test_c".into(),
+                job_id: JobId::from(3),
+                duration: None,
+                status: UiJobStatus::Failure(None),
+                stdout: vec![],
+                stderr: vec![],
+            })
         },
         SendUiMsg {
             msg: UiMessage::AllJobsFinished(UiJobSummary {
                 succeeded: 1,
-                failed: vec![],
+                failed: vec!["foo_pkg test_b".into(), "foo_pkg test_c".into()],
                 ignored: vec![],
                 not_run: None,
             })
@@ -1976,14 +2115,20 @@ script_test_with_error_simex! {
 }
 
 script_test_with_error_simex! {
-    filtering_package_and_test,
-    @ filter = SimpleFilter::And(vec![
-        SimpleFilter::Package("bar_pkg".into()),
-        SimpleFilter::Name("test_a".into()),
-    ]).into(),
+    grouped_tests_by_class,
+    @ test_metadata = MetadataStore::load(
+        &format!("{DEFAULT_METADATA_STR}{}",
+            r#"
+                [[directives]]
+                group_by = "class"
+            "#
+        ),
+        &Default::default(),
+    ).unwrap(),
     expected_test_db_out = [
-        TestDbEntry::success("bar_pkg", "bar_test", "test_a", nonempty![Duration::from_secs(1)]),
-        TestDbEntry::new("bar_pkg", "bar_test", "test_b")
+        TestDbEntry::success("foo_pkg", "foo_test", "A::x", nonempty![Duration::from_secs(1)]),
+        TestDbEntry::success("foo_pkg", "foo_test", "A::y", nonempty![Duration::from_secs(1)]),
+        TestDbEntry::success("foo_pkg", "foo_test", "b", nonempty![Duration::from_secs(1)])
     ],
     Start => {
         SendUiMsg {
@@ -1991,56 +2136,85 @@ script_test_with_error_simex! {
         },
         GetPackages
     };
-    Packages {
-        packages: vec![fake_pkg("foo_pkg", ["foo_test"]), fake_pkg("bar_pkg", ["bar_test"])]
-    } => {
+    Packages { packages: vec![fake_pkg("foo_pkg", ["foo_test"])] } => {
         StartCollection {
             color: false,
             options: TestOptions,
-            packages: vec![fake_pkg("bar_pkg", ["bar_test"])]
+            packages: vec![fake_pkg("foo_pkg", ["foo_test"])]
         }
     };
     ArtifactBuilt {
-        artifact: fake_artifact("bar_test", "bar_pkg"),
+        artifact: fake_artifact("foo_test", "foo_pkg"),
     } => {
         ListTests {
-            artifact: fake_artifact("bar_test", "bar_pkg"),
+            artifact: fake_artifact("foo_test", "foo_pkg"),
         }
     };
     TestsListed {
-        artifact: fake_artifact("bar_test", "bar_pkg"),
-        listing: vec![("test_a".into(), NoCaseMetadata), ("test_b".into(), NoCaseMetadata)],
+        artifact: fake_artifact("foo_test", "foo_pkg"),
+        listing: vec![
+            ("A::x".into(), NoCaseMetadata),
+            ("A::y".into(), NoCaseMetadata),
+            ("b".into(), NoCaseMetadata),
+        ],
         ignored_listing: vec![]
     } => {
         AddJob {
             job_id: JobId::from(1),
-            spec: test_spec("bar_test", "test_a"),
+            spec: test_spec("foo_test", "b"),
         },
         SendUiMsg {
             msg: UiMessage::JobEnqueued(UiJobEnqueued {
                 job_id: JobId::from(1),
-                name: "bar_pkg test_a".into()
+                name: "foo_pkg b".into()
             })
         },
         SendUiMsg {
             msg: UiMessage::UpdatePendingJobsCount(1)
         },
+        AddJob {
+            job_id: JobId::from(2),
+            spec: group_spec("foo_test", &["A::x", "A::y"]),
+        },
+        SendUiMsg {
+            msg: UiMessage::JobEnqueued(UiJobEnqueued {
+                job_id: JobId::from(2),
+                name: "foo_test::A (2 tests)".into()
+            })
+        },
+        SendUiMsg {
+            msg: UiMessage::UpdatePendingJobsCount(2)
+        },
     };
     CollectionFinished { wait_status: wait_success() } => {
         SendUiMsg {
             msg: UiMessage::DoneQueuingJobs,
         }
     };
+    JobUpdate {
+        job_id: JobId::from(2),
+        result: job_status_complete_with_files(0, vec![group_report("A::x ok\nA::y ok\n")]),
+    } => {
+        SendUiMsg {
+            msg: ui_job_result("foo_pkg A::x", 2, UiJobStatus::Ok)
+        },
+        SendUiMsg {
+            msg: UiMessage::UpdatePendingJobsCount(3)
+        },
+        SendUiMsg {
+            msg: ui_job_result("foo_pkg A::y", 3, UiJobStatus::Ok)
+        },
+    };
     JobUpdate {
         job_id: JobId::from(1),
         result: job_status_complete(0),
     } => {
         SendUiMsg {
-            msg: ui_job_result("bar_pkg test_a", 1, UiJobStatus::Ok)
+            msg: ui_job_result("foo_pkg b", 1, UiJobStatus::Ok)
         },
         SendUiMsg {
             msg: UiMessage::AllJobsFinished(UiJobSummary {
-                succeeded: 1,
+                succeeded: 3,
                 failed: vec![],
                 ignored: vec![],
                 not_run: None,
@@ -2050,33 +2224,19 @@ script_test_with_error_simex! {
     };
 }
 
-//  _            _          _ _
-// | |_ ___  ___| |_     __| | |__
-// | __/ _ \/ __| __|   / _` | '_ \
-// | ||  __/\__ \ |_   | (_| | |_) |
-//  \__\___||___/\__|___\__,_|_.__/
-//                 |_____|
+//   __ _ _ _            _
+//  / _(_) | |_ ___ _ __(_)_ __   __ _
+// | |_| | | __/ _ \ '__| | '_ \ / _` |
+// |  _| | | ||  __/ |  | | | | | (_| |
+// |_| |_|_|\__\___|_|  |_|_| |_|\__, |
+//                               |___/
 
 script_test_with_error_simex! {
-    expected_test_count,
-    test_db_in = [
-        TestDbEntry::success("foo_pkg", "foo_test", "test_a", nonempty![Duration::from_secs(1)]),
-        TestDbEntry::success("foo_pkg", "foo_test", "test_b", nonempty![Duration::from_secs(1)])
-    ],
-    expected_exit_code = ExitCode::SUCCESS,
+    filtering_cases,
+    @ filter = SimpleFilter::Name("test_a".into()).into(),
     expected_test_db_out = [
-        TestDbEntry::success(
-            "foo_pkg",
-            "foo_test",
-            "test_a",
-            nonempty![Duration::from_secs(1), Duration::from_secs(1)]
-        ),
-        TestDbEntry::success(
-            "foo_pkg",
-            "foo_test",
-            "test_b",
-            nonempty![Duration::from_secs(1), Duration::from_secs(1)]
-        )
+        TestDbEntry::success("foo_pkg", "foo_test", "test_a", nonempty![Duration::from_secs(1)]),
+        TestDbEntry::new("foo_pkg", "foo_test", "test_b")
     ],
     Start => {
         SendUiMsg {
@@ -2084,17 +2244,12 @@ script_test_with_error_simex! {
         },
         GetPackages
     };
-    Packages {
-        packages: vec![fake_pkg("foo_pkg", ["foo_test"])]
-    } => {
+    Packages { packages: vec![fake_pkg("foo_pkg", ["foo_test"])] } => {
         StartCollection {
             color: false,
             options: TestOptions,
             packages: vec![fake_pkg("foo_pkg", ["foo_test"])]
-        },
-        SendUiMsg {
-            msg: UiMessage::UpdatePendingJobsCount(2)
-        },
+        }
     };
     ArtifactBuilt {
         artifact: fake_artifact("foo_test", "foo_pkg"),
@@ -2110,11 +2265,7 @@ script_test_with_error_simex! {
     } => {
         AddJob {
             job_id: JobId::from(1),
-            spec: JobSpec {
-                estimated_duration: Some(Duration::from_secs(1)),
-                priority: 0,
-                ..test_spec("foo_test", "test_a")
-            }
+            spec: test_spec("foo_test", "test_a"),
         },
         SendUiMsg {
             msg: UiMessage::JobEnqueued(UiJobEnqueued {
@@ -2122,16 +2273,812 @@ script_test_with_error_simex! {
                 name: "foo_pkg test_a".into()
             })
         },
-        AddJob {
-            job_id: JobId::from(2),
-            spec: JobSpec {
-                estimated_duration: Some(Duration::from_secs(1)),
-                priority: 0,
-                ..test_spec("foo_test", "test_b")
-            }
+        SendUiMsg {
+            msg: UiMessage::UpdatePendingJobsCount(1)
         },
+    };
+    CollectionFinished { wait_status: wait_success() } => {
         SendUiMsg {
-            msg: UiMessage::JobEnqueued(UiJobEnqueued {
+            msg: UiMessage::DoneQueuingJobs,
+        }
+    };
+    JobUpdate {
+        job_id: JobId::from(1),
+        result: job_status_complete(0),
+    } => {
+        SendUiMsg {
+            msg: ui_job_result("foo_pkg test_a", 1, UiJobStatus::Ok)
+        },
+        SendUiMsg {
+            msg: UiMessage::AllJobsFinished(UiJobSummary {
+                succeeded: 1,
+                failed: vec![],
+                ignored: vec![],
+                not_run: None,
+            })
+        },
+        StartShutdown
+    };
+}
+
+script_test_with_error_simex! {
+    filtering_subtests,
+    @ filter = SimpleFilter::Name("test_a/x".into()).into(),
+    test_db_in = [
+        TestDbEntry::new("foo_pkg", "foo_test", "test_a"),
+        TestDbEntry::new("foo_pkg", "foo_test", "test_a/x").subtest_of("test_a"),
+        TestDbEntry::new("foo_pkg", "foo_test", "test_a/y").subtest_of("test_a")
+    ],
+    expected_exit_code = ExitCode::SUCCESS,
+    expected_test_db_out = [
+        TestDbEntry::new("foo_pkg", "foo_test", "test_a"),
+        TestDbEntry::success(
+            "foo_pkg", "foo_test", "test_a/x", nonempty![Duration::from_secs(1)]
+        ).subtest_of("test_a"),
+        TestDbEntry::new("foo_pkg", "foo_test", "test_a/y").subtest_of("test_a")
+    ],
+    Start => {
+        SendUiMsg {
+            msg: UiMessage::UpdateEnqueueStatus("building artifacts...".into()),
+        },
+        GetPackages
+    };
+    Packages { packages: vec![fake_pkg("foo_pkg", ["foo_test"])] } => {
+        StartCollection {
+            color: false,
+            options: TestOptions,
+            packages: vec![fake_pkg("foo_pkg", ["foo_test"])]
+        },
+        SendUiMsg {
+            msg: UiMessage::UpdatePendingJobsCount(1)
+        }
+    };
+    ArtifactBuilt {
+        artifact: fake_artifact("foo_test", "foo_pkg"),
+    } => {
+        ListTests {
+            artifact: fake_artifact("foo_test", "foo_pkg"),
+        }
+    };
+    TestsListed {
+        artifact: fake_artifact("foo_test", "foo_pkg"),
+        listing: vec![("test_a".into(), NoCaseMetadata)],
+        ignored_listing: vec![]
+    } => {
+        AddJob {
+            job_id: JobId::from(1),
+            spec: test_spec("foo_test", "test_a/x"),
+        },
+        SendUiMsg {
+            msg: UiMessage::JobEnqueued(UiJobEnqueued {
+                job_id: JobId::from(1),
+                name: "foo_pkg test_a/x".into()
+            })
+        },
+    };
+    CollectionFinished { wait_status: wait_success() } => {
+        SendUiMsg {
+            msg: UiMessage::DoneQueuingJobs,
+        }
+    };
+    JobUpdate {
+        job_id: JobId::from(1),
+        result: job_status_complete(0),
+    } => {
+        SendUiMsg {
+            msg: ui_job_result("foo_pkg test_a/x", 1, UiJobStatus::Ok)
+        },
+        SendUiMsg {
+            msg: UiMessage::AllJobsFinished(UiJobSummary {
+                succeeded: 1,
+                failed: vec![],
+                ignored: vec![],
+                not_run: None,
+            })
+        },
+        StartShutdown
+    };
+}
+
+script_test_with_error_simex! {
+    subtests_in_output_are_reported_and_recorded,
+    test_db_in = [],
+    expected_exit_code = ExitCode::from(1),
+    expected_test_db_out = [
+        TestDbEntry::failure("foo_pkg", "foo_test", "test_a", nonempty![Duration::from_secs(1)]),
+        TestDbEntry::success(
+            "foo_pkg", "foo_test", "test_a/x", nonempty![Duration::from_secs(1)]
+        ).subtest_of("test_a"),
+        TestDbEntry::failure(
+            "foo_pkg", "foo_test", "test_a/y", nonempty![Duration::from_secs(1)]
+        ).subtest_of("test_a"),
+        TestDbEntry::failure(
+            "foo_pkg", "foo_test", "test_a/y/z", nonempty![Duration::from_secs(1)]
+        ).subtest_of("test_a/y")
+    ],
+    Start => {
+        SendUiMsg {
+            msg: UiMessage::UpdateEnqueueStatus("building artifacts...".into()),
+        },
+        GetPackages
+    };
+    Packages { packages: vec![fake_pkg("foo_pkg", ["foo_test"])] } => {
+        StartCollection {
+            color: false,
+            options: TestOptions,
+            packages: vec![fake_pkg("foo_pkg", ["foo_test"])]
+        }
+    };
+    ArtifactBuilt {
+        artifact: fake_artifact("foo_test", "foo_pkg"),
+    } => {
+        ListTests {
+            artifact: fake_artifact("foo_test", "foo_pkg"),
+        }
+    };
+    TestsListed {
+        artifact: fake_artifact("foo_test", "foo_pkg"),
+        listing: vec![("test_a".into(), NoCaseMetadata)],
+        ignored_listing: vec![]
+    } => {
+        AddJob {
+            job_id: JobId::from(1),
+            spec: test_spec("foo_test", "test_a"),
+        },
+        SendUiMsg {
+            msg: UiMessage::JobEnqueued(UiJobEnqueued {
+                job_id: JobId::from(1),
+                name: "foo_pkg test_a".into()
+            })
+        },
+        SendUiMsg {
+            msg: UiMessage::UpdatePendingJobsCount(1)
+        },
+    };
+    CollectionFinished { wait_status: wait_success() } => {
+        SendUiMsg {
+            msg: UiMessage::DoneQueuingJobs,
+        }
+    };
+    JobUpdate {
+        job_id: JobId::from(1),
+        result: Ok(JobStatus::Completed {
+            client_job_id: ClientJobId::from(1),
+            result: Ok(JobOutcome::Completed(JobCompleted {
+                status: JobTerminationStatus::Exited(1),
+                effects: JobEffects {
+                    stdout: JobOutputResult::Inline(Box::new(
+                        *b"fixture: subtest test_a/x ok\n\
+                        fixture: subtest test_a/y failed\n\
+                        fixture: subtest test_a/y/z failed\n\
+                        fixture: subtest test_b/w ok\n"
+                    )),
+                    stderr: JobOutputResult::None,
+                    duration: Duration::from_secs(1),
+                    captured_files: vec![],
+                },
+            })),
+        }),
+    } => {
+        SendUiMsg {
+            msg: ui_job_result("foo_pkg test_a", 1, UiJobStatus::Failure(None))
+        },
+        SendUiMsg {
+            msg: UiMessage::UpdatePendingJobsCount(2)
+        },
+        SendUiMsg {
+            msg: ui_job_result("foo_pkg test_a/x", 2, UiJobStatus::Ok)
+        },
+        SendUiMsg {
+            msg: UiMessage::UpdatePendingJobsCount(3)
+        },
+        SendUiMsg {
+            msg: ui_job_result("foo_pkg test_a/y", 3, UiJobStatus::Failure(None))
+        },
+        SendUiMsg {
+            msg: UiMessage::UpdatePendingJobsCount(4)
+        },
+        SendUiMsg {
+            msg: ui_job_result("foo_pkg test_a/y/z", 4, UiJobStatus::Failure(None))
+        },
+        SendUiMsg {
+            msg: UiMessage::AllJobsFinished(UiJobSummary {
+                succeeded: 1,
+                failed: vec![
+                    "foo_pkg test_a".into(),
+                    "foo_pkg test_a/y".into(),
+                    "foo_pkg test_a/y/z".into(),
+                ],
+                ignored: vec![],
+                not_run: None,
+            })
+        },
+        StartShutdown
+    };
+}
+
+script_test_with_error_simex! {
+    filtering_packages,
+    @ filter = SimpleFilter::Package("bar_pkg".into()).into(),
+    expected_test_db_out = [
+        TestDbEntry::success("bar_pkg", "bar_test", "test_a", nonempty![Duration::from_secs(1)])
+    ],
+    Start => {
+        SendUiMsg {
+            msg: UiMessage::UpdateEnqueueStatus("building artifacts...".into()),
+        },
+        GetPackages
+    };
+    Packages {
+        packages: vec![fake_pkg("foo_pkg", ["foo_test"]), fake_pkg("bar_pkg", ["bar_test"])]
+    } => {
+        StartCollection {
+            color: false,
+            options: TestOptions,
+            packages: vec![fake_pkg("bar_pkg", ["bar_test"])]
+        }
+    };
+    ArtifactBuilt {
+        artifact: fake_artifact("bar_test", "bar_pkg"),
+    } => {
+        ListTests {
+            artifact: fake_artifact("bar_test", "bar_pkg"),
+        }
+    };
+    TestsListed {
+        artifact: fake_artifact("bar_test", "bar_pkg"),
+        listing: vec![("test_a".into(), NoCaseMetadata)],
+        ignored_listing: vec![]
+    } => {
+        AddJob {
+            job_id: JobId::from(1),
+            spec: test_spec("bar_test", "test_a"),
+        },
+        SendUiMsg {
+            msg: UiMessage::JobEnqueued(UiJobEnqueued {
+                job_id: JobId::from(1),
+                name: "bar_pkg test_a".into()
+            })
+        },
+        SendUiMsg {
+            msg: UiMessage::UpdatePendingJobsCount(1)
+        },
+    };
+    CollectionFinished { wait_status: wait_success() } => {
+        SendUiMsg {
+            msg: UiMessage::DoneQueuingJobs,
+        }
+    };
+    JobUpdate {
+        job_id: JobId::from(1),
+        result: job_status_complete(0),
+    } => {
+        SendUiMsg {
+            msg: ui_job_result("bar_pkg test_a", 1, UiJobStatus::Ok)
+        },
+        SendUiMsg {
+            msg: UiMessage::AllJobsFinished(UiJobSummary {
+                succeeded: 1,
+                failed: vec![],
+                ignored: vec![],
+                not_run: None,
+            })
+        },
+        StartShutdown
+    };
+}
+
+script_test_with_error_simex! {
+    filtering_package_and_test,
+    @ filter = SimpleFilter::And(vec![
+        SimpleFilter::Package("bar_pkg".into()),
+        SimpleFilter::Name("test_a".into()),
+    ]).into(),
+    expected_test_db_out = [
+        TestDbEntry::success("bar_pkg", "bar_test", "test_a", nonempty![Duration::from_secs(1)]),
+        TestDbEntry::new("bar_pkg", "bar_test", "test_b")
+    ],
+    Start => {
+        SendUiMsg {
+            msg: UiMessage::UpdateEnqueueStatus("building artifacts...".into()),
+        },
+        GetPackages
+    };
+    Packages {
+        packages: vec![fake_pkg("foo_pkg", ["foo_test"]), fake_pkg("bar_pkg", ["bar_test"])]
+    } => {
+        StartCollection {
+            color: false,
+            options: TestOptions,
+            packages: vec![fake_pkg("bar_pkg", ["bar_test"])]
+        }
+    };
+    ArtifactBuilt {
+        artifact: fake_artifact("bar_test", "bar_pkg"),
+    } => {
+        ListTests {
+            artifact: fake_artifact("bar_test", "bar_pkg"),
+        }
+    };
+    TestsListed {
+        artifact: fake_artifact("bar_test", "bar_pkg"),
+        listing: vec![("test_a".into(), NoCaseMetadata), ("test_b".into(), NoCaseMetadata)],
+        ignored_listing: vec![]
+    } => {
+        AddJob {
+            job_id: JobId::from(1),
+            spec: test_spec("bar_test", "test_a"),
+        },
+        SendUiMsg {
+            msg: UiMessage::JobEnqueued(UiJobEnqueued {
+                job_id: JobId::from(1),
+                name: "bar_pkg test_a".into()
+            })
+        },
+        SendUiMsg {
+            msg: UiMessage::UpdatePendingJobsCount(1)
+        },
+    };
+    CollectionFinished { wait_status: wait_success() } => {
+        SendUiMsg {
+            msg: UiMessage::DoneQueuingJobs,
+        }
+    };
+    JobUpdate {
+        job_id: JobId::from(1),
+        result: job_status_complete(0),
+    } => {
+        SendUiMsg {
+            msg: ui_job_result("bar_pkg test_a", 1, UiJobStatus::Ok)
+        },
+        SendUiMsg {
+            msg: UiMessage::AllJobsFinished(UiJobSummary {
+                succeeded: 1,
+                failed: vec![],
+                ignored: vec![],
+                not_run: None,
+            })
+        },
+        StartShutdown
+    };
+}
+
+//  _            _     _                            _
+// | |_ ___  ___| |_  (_)_ __ ___  _ __   __ _  ___| |_
+// | __/ _ \/ __| __| | | '_ ` _ \| '_ \ / _` |/ __| __|
+// | ||  __/\__ \ |_  | | | | | | | |_) | (_| | (__| |_
+//  \__\___||___/\__| |_|_| |_| |_| .__/ \__,_|\___|\__|
+//                                |_|
+
+fn test_impact<'a>(
+    packages: impl IntoIterator<Item = &'a str>,
+    artifacts: impl IntoIterator<Item = (&'a str, &'a str)>,
+) -> crate::TestImpact<FakePackageId, StringArtifactKey> {
+    let mut impact = crate::TestImpact::default();
+    for package in packages {
+        impact.add_package(FakePackageId(package.into()));
+    }
+    for (package, artifact) in artifacts {
+        impact.add_artifact(FakePackageId(package.into()), artifact.into());
+    }
+    impact
+}
+
+script_test_with_error_simex! {
+    test_impact_runs_impacted_and_previously_failing_tests,
+    test_db_in = [
+        TestDbEntry::success("foo_pkg", "foo_test", "test_a", nonempty![Duration::from_secs(1)]),
+        TestDbEntry::failure("bar_pkg", "bar_test", "test_a", nonempty![Duration::from_secs(2)]),
+        TestDbEntry::success("bar_pkg", "bar_test", "test_b", nonempty![Duration::from_secs(3)]),
+        TestDbEntry::success("baz_pkg", "baz_test", "test_a", nonempty![Duration::from_secs(4)])
+    ],
+    expected_exit_code = ExitCode::SUCCESS,
+    expected_test_db_out = [
+        TestDbEntry::success(
+            "foo_pkg",
+            "foo_test",
+            "test_a",
+            nonempty![Duration::from_secs(1), Duration::from_secs(1)]
+        ),
+        TestDbEntry::success(
+            "bar_pkg",
+            "bar_test",
+            "test_a",
+            nonempty![Duration::from_secs(2), Duration::from_secs(1)]
+        ),
+        TestDbEntry::success("bar_pkg", "bar_test", "test_b", nonempty![Duration::from_secs(3)]),
+        TestDbEntry::success("baz_pkg", "baz_test", "test_a", nonempty![Duration::from_secs(4)])
+    ],
+    Start => {
+        SendUiMsg {
+            msg: UiMessage::UpdateEnqueueStatus("building artifacts...".into()),
+        },
+        GetPackages
+    };
+    TestImpact { impact: test_impact([], [("foo_pkg", "foo_test")]) } => {};
+    Packages {
+        packages: vec![
+            fake_pkg("foo_pkg", ["foo_test"]),
+            fake_pkg("bar_pkg", ["bar_test"]),
+            fake_pkg("baz_pkg", ["baz_test"]),
+        ]
+    } => {
+        StartCollection {
+            color: false,
+            options: TestOptions,
+            packages: vec![fake_pkg("bar_pkg", ["bar_test"]), fake_pkg("foo_pkg", ["foo_test"])]
+        },
+        SendUiMsg {
+            msg: UiMessage::UpdatePendingJobsCount(2)
+        },
+    };
+    ArtifactBuilt {
+        artifact: fake_artifact("foo_test", "foo_pkg"),
+    } => {
+        ListTests {
+            artifact: fake_artifact("foo_test", "foo_pkg"),
+        }
+    };
+    TestsListed {
+        artifact: fake_artifact("foo_test", "foo_pkg"),
+        listing: vec![("test_a".into(), NoCaseMetadata)],
+        ignored_listing: vec![]
+    } => {
+        AddJob {
+            job_id: JobId::from(1),
+            spec: JobSpec {
+                estimated_duration: Some(Duration::from_secs(1)),
+                priority: 0,
+                ..test_spec("foo_test", "test_a")
+            },
+        },
+        SendUiMsg {
+            msg: UiMessage::JobEnqueued(UiJobEnqueued {
+                job_id: JobId::from(1),
+                name: "foo_pkg test_a".into()
+            })
+        },
+    };
+    ArtifactBuilt {
+        artifact: fake_artifact("bar_test", "bar_pkg"),
+    } => {
+        ListTests {
+            artifact: fake_artifact("bar_test", "bar_pkg"),
+        }
+    };
+    TestsListed {
+        artifact: fake_artifact("bar_test", "bar_pkg"),
+        listing: vec![("test_a".into(), NoCaseMetadata), ("test_b".into(), NoCaseMetadata)],
+        ignored_listing: vec![]
+    } => {
+        AddJob {
+            job_id: JobId::from(2),
+            spec: JobSpec {
+                estimated_duration: Some(Duration::from_secs(2)),
+                priority: 1,
+                ..test_spec("bar_test", "test_a")
+            },
+        },
+        SendUiMsg {
+            msg: UiMessage::JobEnqueued(UiJobEnqueued {
+                job_id: JobId::from(2),
+                name: "bar_pkg test_a".into()
+            })
+        },
+    };
+    CollectionFinished { wait_status: wait_success() } => {
+        SendUiMsg {
+            msg: UiMessage::DoneQueuingJobs,
+        }
+    };
+    JobUpdate {
+        job_id: JobId::from(1),
+        result: job_status_complete(0),
+    } => {
+        SendUiMsg {
+            msg: ui_job_result("foo_pkg test_a", 1, UiJobStatus::Ok)
+        },
+    };
+    JobUpdate {
+        job_id: JobId::from(2),
+        result: job_status_complete(0),
+    } => {
+        SendUiMsg {
+            msg: ui_job_result("bar_pkg test_a", 2, UiJobStatus::Ok)
+        },
+        SendUiMsg {
+            msg: UiMessage::AllJobsFinished(UiJobSummary {
+                succeeded: 2,
+                failed: vec![],
+                ignored: vec![],
+                not_run: None,
+            })
+        },
+        StartShutdown
+    };
+}
+
+script_test_with_error_simex! {
+    test_impact_whole_package,
+    test_db_in = [
+        TestDbEntry::success("foo_pkg", "foo_test", "test_a", nonempty![Duration::from_secs(1)]),
+        TestDbEntry::success("bar_pkg", "bar_test", "test_a", nonempty![Duration::from_secs(2)])
+    ],
+    expected_exit_code = ExitCode::SUCCESS,
+    expected_test_db_out = [
+        TestDbEntry::success("foo_pkg", "foo_test", "test_a", nonempty![Duration::from_secs(1)]),
+        TestDbEntry::success(
+            "bar_pkg",
+            "bar_test",
+            "test_a",
+            nonempty![Duration::from_secs(2), Duration::from_secs(1)]
+        )
+    ],
+    Start => {
+        SendUiMsg {
+            msg: UiMessage::UpdateEnqueueStatus("building artifacts...".into()),
+        },
+        GetPackages
+    };
+    TestImpact { impact: test_impact(["bar_pkg"], []) } => {};
+    Packages {
+        packages: vec![fake_pkg("foo_pkg", ["foo_test"]), fake_pkg("bar_pkg", ["bar_test"])]
+    } => {
+        StartCollection {
+            color: false,
+            options: TestOptions,
+            packages: vec![fake_pkg("bar_pkg", ["bar_test"])]
+        },
+        SendUiMsg {
+            msg: UiMessage::UpdatePendingJobsCount(1)
+        },
+    };
+    ArtifactBuilt {
+        artifact: fake_artifact("bar_test", "bar_pkg"),
+    } => {
+        ListTests {
+            artifact: fake_artifact("bar_test", "bar_pkg"),
+        }
+    };
+    TestsListed {
+        artifact: fake_artifact("bar_test", "bar_pkg"),
+        listing: vec![("test_a".into(), NoCaseMetadata)],
+        ignored_listing: vec![]
+    } => {
+        AddJob {
+            job_id: JobId::from(1),
+            spec: JobSpec {
+                estimated_duration: Some(Duration::from_secs(2)),
+                priority: 0,
+                ..test_spec("bar_test", "test_a")
+            },
+        },
+        SendUiMsg {
+            msg: UiMessage::JobEnqueued(UiJobEnqueued {
+                job_id: JobId::from(1),
+                name: "bar_pkg test_a".into()
+            })
+        },
+    };
+    CollectionFinished { wait_status: wait_success() } => {
+        SendUiMsg {
+            msg: UiMessage::DoneQueuingJobs,
+        }
+    };
+    JobUpdate {
+        job_id: JobId::from(1),
+        result: job_status_complete(0),
+    } => {
+        SendUiMsg {
+            msg: ui_job_result("bar_pkg test_a", 1, UiJobStatus::Ok)
+        },
+        SendUiMsg {
+            msg: UiMessage::AllJobsFinished(UiJobSummary {
+                succeeded: 1,
+                failed: vec![],
+                ignored: vec![],
+                not_run: None,
+            })
+        },
+        StartShutdown
+    };
+}
+
+script_test_with_error_simex! {
+    test_impact_nothing_impacted,
+    test_db_in = [
+        TestDbEntry::success("foo_pkg", "foo_test", "test_a", nonempty![Duration::from_secs(1)])
+    ],
+    expected_exit_code = ExitCode::SUCCESS,
+    expected_test_db_out = [
+        TestDbEntry::success("foo_pkg", "foo_test", "test_a", nonempty![Duration::from_secs(1)])
+    ],
+    Start => {
+        SendUiMsg {
+            msg: UiMessage::UpdateEnqueueStatus("building artifacts...".into()),
+        },
+        GetPackages
+    };
+    TestImpact { impact: test_impact([], []) } => {};
+    Packages { packages: vec![fake_pkg("foo_pkg", ["foo_test"])] } => {
+        SendUiMsg {
+            msg: UiMessage::AllJobsFinished(UiJobSummary {
+                succeeded: 0,
+                failed: vec![],
+                ignored: vec![],
+                not_run: None,
+            })
+        },
+        StartShutdown
+    };
+}
+
+script_test_with_error_simex! {
+    rerun_tests,
+    test_db_in = [
+        TestDbEntry::failure("foo_pkg", "foo_test", "test_a", nonempty![Duration::from_secs(1)]),
+        TestDbEntry::success("foo_pkg", "foo_test", "test_b", nonempty![Duration::from_secs(2)])
+    ],
+    expected_exit_code = ExitCode::SUCCESS,
+    expected_test_db_out = [
+        TestDbEntry::failure("foo_pkg", "foo_test", "test_a", nonempty![Duration::from_secs(1)]),
+        TestDbEntry::success(
+            "foo_pkg",
+            "foo_test",
+            "test_b",
+            nonempty![Duration::from_secs(2), Duration::from_secs(1)]
+        )
+    ],
+    RerunTests { names: ["foo_pkg test_b".into()].into() } => {};
+    Start => {
+        SendUiMsg {
+            msg: UiMessage::UpdateEnqueueStatus("building artifacts...".into()),
+        },
+        GetPackages
+    };
+    Packages { packages: vec![fake_pkg("foo_pkg", ["foo_test"])] } => {
+        StartCollection {
+            color: false,
+            options: TestOptions,
+            packages: vec![fake_pkg("foo_pkg", ["foo_test"])]
+        },
+        SendUiMsg {
+            msg: UiMessage::UpdatePendingJobsCount(1)
+        },
+    };
+    ArtifactBuilt {
+        artifact: fake_artifact("foo_test", "foo_pkg"),
+    } => {
+        ListTests {
+            artifact: fake_artifact("foo_test", "foo_pkg"),
+        }
+    };
+    TestsListed {
+        artifact: fake_artifact("foo_test", "foo_pkg"),
+        listing: vec![("test_a".into(), NoCaseMetadata), ("test_b".into(), NoCaseMetadata)],
+        ignored_listing: vec![]
+    } => {
+        AddJob {
+            job_id: JobId::from(1),
+            spec: JobSpec {
+                estimated_duration: Some(Duration::from_secs(2)),
+                priority: 0,
+                ..test_spec("foo_test", "test_b")
+            },
+        },
+        SendUiMsg {
+            msg: UiMessage::JobEnqueued(UiJobEnqueued {
+                job_id: JobId::from(1),
+                name: "foo_pkg test_b".into()
+            })
+        },
+    };
+    CollectionFinished { wait_status: wait_success() } => {
+        SendUiMsg {
+            msg: UiMessage::DoneQueuingJobs,
+        }
+    };
+    JobUpdate {
+        job_id: JobId::from(1),
+        result: job_status_complete(0),
+    } => {
+        SendUiMsg {
+            msg: ui_job_result("foo_pkg test_b", 1, UiJobStatus::Ok)
+        },
+        SendUiMsg {
+            msg: UiMessage::AllJobsFinished(UiJobSummary {
+                succeeded: 1,
+                failed: vec![],
+                ignored: vec![],
+                not_run: None,
+            })
+        },
+        StartShutdown
+    };
+}
+
+//  _            _          _ _
+// | |_ ___  ___| |_     __| | |__
+// | __/ _ \/ __| __|   / _` | '_ \
+// | ||  __/\__ \ |_   | (_| | |_) |
+//  \__\___||___/\__|___\__,_|_.__/
+//                 |_____|
+
+script_test_with_error_simex! {
+    expected_test_count,
+    test_db_in = [
+        TestDbEntry::success("foo_pkg", "foo_test", "test_a", nonempty![Duration::from_secs(1)]),
+        TestDbEntry::success("foo_pkg", "foo_test", "test_b", nonempty![Duration::from_secs(1)])
+    ],
+    expected_exit_code = ExitCode::SUCCESS,
+    expected_test_db_out = [
+        TestDbEntry::success(
+            "foo_pkg",
+            "foo_test",
+            "test_a",
+            nonempty![Duration::from_secs(1), Duration::from_secs(1)]
+        ),
+        TestDbEntry::success(
+            "foo_pkg",
+            "foo_test",
+            "test_b",
+            nonempty![Duration::from_secs(1), Duration::from_secs(1)]
+        )
+    ],
+    Start => {
+        SendUiMsg {
+            msg: UiMessage::UpdateEnqueueStatus("building artifacts...".into()),
+        },
+        GetPackages
+    };
+    Packages {
+        packages: vec![fake_pkg("foo_pkg", ["foo_test"])]
+    } => {
+        StartCollection {
+            color: false,
+            options: TestOptions,
+            packages: vec![fake_pkg("foo_pkg", ["foo_test"])]
+        },
+        SendUiMsg {
+            msg: UiMessage::UpdatePendingJobsCount(2)
+        },
+    };
+    ArtifactBuilt {
+        artifact: fake_artifact("foo_test", "foo_pkg"),
+    } => {
+        ListTests {
+            artifact: fake_artifact("foo_test", "foo_pkg"),
+        }
+    };
+    TestsListed {
+        artifact: fake_artifact("foo_test", "foo_pkg"),
+        listing: vec![("test_a".into(), NoCaseMetadata), ("test_b".into(), NoCaseMetadata)],
+        ignored_listing: vec![]
+    } => {
+        AddJob {
+            job_id: JobId::from(1),
+            spec: JobSpec {
+                estimated_duration: Some(Duration::from_secs(1)),
+                priority: 0,
+                ..test_spec("foo_test", "test_a")
+            }
+        },
+        SendUiMsg {
+            msg: UiMessage::JobEnqueued(UiJobEnqueued {
+                job_id: JobId::from(1),
+                name: "foo_pkg test_a".into()
+            })
+        },
+        AddJob {
+            job_id: JobId::from(2),
+            spec: JobSpec {
+                estimated_duration: Some(Duration::from_secs(1)),
+                priority: 0,
+                ..test_spec("foo_test", "test_b")
+            }
+        },
+        SendUiMsg {
+            msg: UiMessage::JobEnqueued(UiJobEnqueued {
                 job_id: JobId::from(2),
                 name: "foo_pkg test_b".into()
             })
@@ -3579,3 +4526,145 @@ script_test_with_error_simex! {
         StartShutdown
     };
 }
+
+//                             _   _
+//   _____  ___ __   ___  _ __| |_(_)_ __   __ _
+//  / _ \ \/ / '_ \ / _ \| '__| __| | '_ \ / _` |
+// |  __/>  <| |_) | (_) | |  | |_| | | | | (_| |
+//  \___/_/\_\ .__/ \___/|_|   \__|_|_| |_|\__, |
+//           |_|                           |___/
+
+script_test_with_error_simex! {
+    exporting_image,
+    @ export_image = Some(("foo_pkg test_b".into(), "/image.tar".into())),
+    expected_test_db_out = [
+        TestDbEntry::new("foo_pkg", "foo_test", "test_a"),
+        TestDbEntry::new("foo_pkg", "foo_test", "test_b")
+    ],
+    Start => {
+        SendUiMsg {
+            msg: UiMessage::UpdateEnqueueStatus("building artifacts...".into()),
+        },
+        GetPackages
+    };
+    Packages { packages: vec![fake_pkg("foo_pkg", ["foo_test"])] } => {
+        StartCollection {
+            color: false,
+            options: TestOptions,
+            packages: vec![fake_pkg("foo_pkg", ["foo_test"])]
+        }
+    };
+    ArtifactBuilt {
+        artifact: fake_artifact("foo_test", "foo_pkg"),
+    } => {
+        ListTests {
+            artifact: fake_artifact("foo_test", "foo_pkg"),
+        }
+    };
+    TestsListed {
+        artifact: fake_artifact("foo_test", "foo_pkg"),
+        listing: vec![("test_a".into(), NoCaseMetadata), ("test_b".into(), NoCaseMetadata)],
+        ignored_listing: vec![]
+    } => {
+        ExportImage {
+            spec: JobSpec {
+                priority: 0,
+                ..test_spec("foo_test", "test_b")
+            },
+            path: "/image.tar".into(),
+        },
+        StartShutdown
+    };
+}
+
+script_test_with_error_simex! {
+    exporting_image_of_ignored_test,
+    @ export_image = Some(("foo_pkg test_a".into(), "/image.tar".into())),
+    expected_test_db_out = [
+        TestDbEntry::new("foo_pkg", "foo_test", "test_a")
+    ],
+    Start => {
+        SendUiMsg {
+            msg: UiMessage::UpdateEnqueueStatus("building artifacts...".into()),
+        },
+        GetPackages
+    };
+    Packages { packages: vec![fake_pkg("foo_pkg", ["foo_test"])] } => {
+        StartCollection {
+            color: false,
+            options: TestOptions,
+            packages: vec![fake_pkg("foo_pkg", ["foo_test"])]
+        }
+    };
+    ArtifactBuilt {
+        artifact: fake_artifact("foo_test", "foo_pkg"),
+    } => {
+        ListTests {
+            artifact: fake_artifact("foo_test", "foo_pkg"),
+        }
+    };
+    CollectionFinished { wait_status: wait_success() } => {};
+    TestsListed {
+        artifact: fake_artifact("foo_test", "foo_pkg"),
+        listing: vec![("test_a".into(), NoCaseMetadata)],
+        ignored_listing: vec!["test_a".into()]
+    } => {
+        ExportImage {
+            spec: JobSpec {
+                priority: 0,
+                ..test_spec("foo_test", "test_a")
+            },
+            path: "/image.tar".into(),
+        },
+        SendUiMsg {
+            msg: UiMessage::DoneQueuingJobs,
+        },
+        StartShutdown
+    };
+}
+
+#[test]
+fn exporting_image_of_unknown_test() {
+    let deps = TestDeps::default();
+    let options = TestingOptions {
+        export_image: Some(("foo_pkg test_c".into(), "/image.tar".into())),
+        ..default_testing_options()
+    };
+    let mut fixture = Fixture::new(&deps, &options, TestDb::default());
+    fixture.receive_message(Start);
+    fixture.receive_message(Packages {
+        packages: vec![fake_pkg("foo_pkg", ["foo_test"])],
+    });
+    fixture.receive_message(ArtifactBuilt {
+        artifact: fake_artifact("foo_test", "foo_pkg"),
+    });
+    fixture.receive_message(CollectionFinished {
+        wait_status: wait_success(),
+    });
+    fixture.expect_messages_in_any_order(vec![
+        SendUiMsg {
+            msg: UiMessage::UpdateEnqueueStatus("building artifacts...".into()),
+        },
+        GetPackages,
+        StartCollection {
+            color: false,
+            options: TestOptions,
+            packages: vec![fake_pkg("foo_pkg", ["foo_test"])],
+        },
+        ListTests {
+            artifact: fake_artifact("foo_test", "foo_pkg"),
+        },
+    ]);
+    fixture.receive_message(TestsListed {
+        artifact: fake_artifact("foo_test", "foo_pkg"),
+        listing: vec![("test_a".into(), NoCaseMetadata)],
+        ignored_listing: vec![],
+    });
+    fixture.expect_messages_in_any_order(vec![
+        SendUiMsg {
+            msg: UiMessage::DoneQueuingJobs,
+        },
+        StartShutdown,
+    ]);
+    fixture.assert_return_value_error(r#"Err(no selected test is named "foo_pkg test_c")"#);
+}
@@ -1,3 +1,4 @@
+use crate::ui::UiRequest;
 use anyhow::Result;
 use maelstrom_client::ProjectDir;
 use maelstrom_util::{root::Root, sync::Event as SyncEvent};
@@ -6,7 +7,17 @@ use notify::{
     RecommendedWatcher, RecursiveMode, Watcher as _,
 };
 use slog::{debug, Logger};
-use std::{collections::BTreeSet, path::PathBuf, sync::mpsc, thread::Scope, time::Duration};
+use std::{
+    collections::BTreeSet,
+    mem,
+    path::PathBuf,
+    sync::{
+        mpsc::{self, Receiver},
+        Mutex,
+    },
+    thread::Scope,
+    time::Duration,
+};
 use std_semaphore::Semaphore;
 
 fn process_watch_events(
@@ -31,6 +42,56 @@ fn process_watch_events(
         .collect()
 }
 
+/// The things that have happened since the last run that the next run needs to react to.
+#[derive(Default)]
+pub struct Changes {
+    event: SyncEvent,
+    paths: Mutex<BTreeSet<PathBuf>>,
+    rerun_tests: Mutex<BTreeSet<String>>,
+}
+
+/// What triggered a run in watch mode.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Change {
+    /// These paths in the project directory changed.
+    Files(BTreeSet<PathBuf>),
+    /// The user asked the UI to rerun these tests.
+    RerunTests(BTreeSet<String>),
+}
+
+impl Changes {
+    fn add_paths(&self, paths: BTreeSet<PathBuf>) {
+        self.paths.lock().unwrap().extend(paths);
+        self.event.set();
+    }
+
+    fn add_rerun_tests(&self, names: BTreeSet<String>) {
+        self.rerun_tests.lock().unwrap().extend(names);
+        self.event.set();
+    }
+
+    /// Wait for something to change, then return everything that has changed since the last
+    /// call. Rerun requests take precedence over changed files. Any changed files will be returned
+    /// by the next call.
+    fn wait(&self) -> Change {
+        loop {
+            self.event.wait_and_unset();
+            let rerun_tests = mem::take(&mut *self.rerun_tests.lock().unwrap());
+            let mut paths = self.paths.lock().unwrap();
+            if !rerun_tests.is_empty() {
+                if !paths.is_empty() {
+                    self.event.set();
+                }
+                break Change::RerunTests(rerun_tests);
+            }
+            // We may have already taken these paths after the event was set by a previous batch.
+            if !paths.is_empty() {
+                break Change::Files(mem::take(&mut *paths));
+            }
+        }
+    }
+}
+
 pub struct Watcher<'deps, 'scope> {
     scope: &'scope Scope<'scope, 'deps>,
     log: Logger,
@@ -38,7 +99,7 @@ pub struct Watcher<'deps, 'scope> {
     watch_exclude_paths: &'deps Vec<PathBuf>,
     semaphore: &'deps Semaphore,
     done: &'deps SyncEvent,
-    files_changed: &'deps SyncEvent,
+    changes: &'deps Changes,
 }
 
 impl<'deps, 'scope> Watcher<'deps, 'scope> {
@@ -49,7 +110,7 @@ impl<'deps, 'scope> Watcher<'deps, 'scope> {
         watch_exclude_paths: &'deps Vec<PathBuf>,
         semaphore: &'deps Semaphore,
         done: &'deps SyncEvent,
-        files_changed: &'deps SyncEvent,
+        changes: &'deps Changes,
     ) -> Self {
         Self {
             scope,
@@ -58,15 +119,16 @@ impl<'deps, 'scope> Watcher<'deps, 'scope> {
             watch_exclude_paths,
             semaphore,
             done,
-            files_changed,
+            changes,
         }
     }
 
-    pub fn watch_for_changes(&self) -> Result<()> {
+    /// Start watching the project directory for changes, and the UI for rerun requests.
+    pub fn watch_for_changes(&self, ui_requests: Receiver<UiRequest>) -> Result<()> {
         let sem = self.semaphore;
         let project_dir = self.project_dir;
         let done = self.done;
-        let files_changed = self.files_changed;
+        let changes = self.changes;
         let watch_exclude_paths = self.watch_exclude_paths;
         let log = self.log.clone();
 
@@ -90,7 +152,16 @@ impl<'deps, 'scope> Watcher<'deps, 'scope> {
                 let changed_paths = process_watch_events(events, watch_exclude_paths);
                 if !changed_paths.is_empty() {
                     debug!(log, "reacting to file changes"; "paths" => ?changed_paths);
-                    files_changed.set();
+                    changes.add_paths(changed_paths);
+                }
+
+                while let Ok(request) = ui_requests.try_recv() {
+                    match request {
+                        UiRequest::RerunTests(names) => {
+                            debug!(log, "reacting to rerun request"; "tests" => ?names);
+                            changes.add_rerun_tests(names);
+                        }
+                    }
                 }
             }
 
@@ -99,8 +170,10 @@ impl<'deps, 'scope> Watcher<'deps, 'scope> {
         Ok(())
     }
 
-    pub fn wait_for_changes(&self) {
-        self.files_changed.wait_and_unset();
+    /// Wait for some files to change or for the user to request a rerun, then return what
+    /// happened since the last call.
+    pub fn wait_for_changes(&self) -> Change {
+        self.changes.wait()
     }
 }
 
@@ -264,4 +337,28 @@ mod tests {
             ["dir/subdir3/baz.rs", "dir/subdir3/quid.rs"],
         )
     }
+
+    #[test]
+    fn changes_files() {
+        let changes = Changes::default();
+        changes.add_paths(["foo.rs".into()].into());
+        changes.add_paths(["bar.rs".into()].into());
+        assert_eq!(
+            changes.wait(),
+            Change::Files(["bar.rs".into(), "foo.rs".into()].into())
+        );
+        assert!(!changes.event.is_set());
+    }
+
+    #[test]
+    fn changes_rerun_tests_take_precedence() {
+        let changes = Changes::default();
+        changes.add_paths(["foo.rs".into()].into());
+        changes.add_rerun_tests(["foo test_a".into()].into());
+        assert_eq!(
+            changes.wait(),
+            Change::RerunTests(["foo test_a".into()].into())
+        );
+        assert_eq!(changes.wait(), Change::Files(["foo.rs".into()].into()));
+    }
 }
@@ -11,6 +11,7 @@ use maelstrom_util::{
 };
 use serde::Deserialize;
 use std::num::NonZeroUsize;
+use std::path::PathBuf;
 use std::{
     fmt::{self, Debug, Formatter},
     result, str,
@@ -182,8 +183,32 @@ pub struct ExtraCommandLineOptions {
 
     #[arg(
         long,
-        hide(true),
-        help = "Keep running tests in a loop waiting for changes in-between"
+        help = "Keep running tests in a loop, waiting for files to change in between. After the \
+            first run, only tests impacted by the changes, and tests that previously failed, are \
+            rerun.",
+        help_heading = "Test Selection Options"
     )]
     pub watch: bool,
+
+    #[arg(
+        long,
+        num_args = 2,
+        value_names = ["TEST", "PATH"],
+        conflicts_with = "watch",
+        help = "Instead of running tests, build the container for the given test and write it to \
+            the given path as an OCI image tarball. The test is named as it is when listing \
+            tests. The tarball can be loaded with `docker load` or `podman load`.",
+        help_heading = "Test Selection Options"
+    )]
+    pub export_image: Option<Vec<String>>,
+}
+
+impl ExtraCommandLineOptions {
+    /// The test and path given to `--export-image`, if it was given.
+    pub fn export_image(&self) -> Option<(String, PathBuf)> {
+        match self.export_image.as_deref()? {
+            [test, path] => Some((test.clone(), path.into())),
+            _ => None,
+        }
+    }
 }
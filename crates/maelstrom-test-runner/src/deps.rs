@@ -1,16 +1,20 @@
-use crate::{metadata::Metadata, ui};
-use anyhow::Result;
-use maelstrom_base::Utf8PathBuf;
+use crate::{
+    metadata::{GroupBy, Metadata},
+    ui,
+};
+use anyhow::{bail, Result};
+use maelstrom_base::{CapturedFile, Utf8PathBuf};
 use maelstrom_client::spec::{ImageRef, LayerSpec};
 use maelstrom_util::{process::ExitCode, template::TemplateVars};
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use std::{
-    collections::HashSet,
+    collections::{BTreeMap, BTreeSet, HashSet},
     fmt,
     hash::Hash,
-    path::Path,
+    path::{Path, PathBuf},
     str::{self, FromStr},
     sync::Arc,
+    time::Duration,
 };
 
 #[derive(PartialEq, Eq, Debug, Clone)]
@@ -19,6 +23,31 @@ pub struct WaitStatus {
     pub output: String,
 }
 
+/// The result of a subtest, as found in the output of the test case that ran it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SubtestResult {
+    /// The full name of the subtest. This is used as its case name.
+    pub name: String,
+    /// The case name of the test or subtest that started this subtest.
+    pub parent: String,
+    /// This should be one of `Ok`, `Failure`, or `Ignored`.
+    pub status: ui::UiJobStatus,
+    pub duration: Duration,
+}
+
+/// The result of one of the test cases run by a job for a group of test cases, as found in the
+/// report the job wrote.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GroupCaseResult {
+    /// The case name of the test case.
+    pub name: String,
+    /// This should be one of `Ok`, `Failure`, `Error`, or `Ignored`.
+    pub status: ui::UiJobStatus,
+    pub duration: Duration,
+    /// Any output the report has for the test case, like the traceback of a failure.
+    pub output: Vec<String>,
+}
+
 /// Wait for some asynchronous thing like a process to finish.
 pub trait Wait {
     /// Block the current thread waiting for whatever thing to finish.
@@ -112,7 +141,7 @@ impl FromStr for StringArtifactKey {
 impl TestArtifactKey for StringArtifactKey {}
 
 /// A handle to a package.
-pub trait TestPackageId: Clone + Ord + fmt::Debug {}
+pub trait TestPackageId: Clone + Ord + fmt::Debug + Send + Sync + 'static {}
 
 /// An artifact is a file that contains and can be used to run test cases.
 pub trait TestArtifact: fmt::Debug + Send + Sync + 'static {
@@ -144,7 +173,14 @@ pub trait TestArtifact: fmt::Debug + Send + Sync + 'static {
         &self,
         case_name: &str,
         case_metadata: &Self::CaseMetadata,
-    ) -> (Utf8PathBuf, Vec<String>);
+    ) -> Result<(Utf8PathBuf, Vec<String>)>;
+
+    /// Whether the given test case is expected to fail. Such a test case passes if its job exits
+    /// with a non-zero status, and fails if it exits with zero. Being killed by a signal is a
+    /// failure either way.
+    fn expects_failure(&self, _case_name: &str, _case_metadata: &Self::CaseMetadata) -> bool {
+        false
+    }
 
     /// Create string that represents the given test case. This should be used for display
     /// purposes.
@@ -157,6 +193,29 @@ pub trait TestArtifact: fmt::Debug + Send + Sync + 'static {
 
     /// Get any layers to add to the jobs using this artifact.
     fn get_test_layers(&self, metadata: &Metadata) -> Vec<LayerSpec>;
+
+    /// Return the name of the group the given test case belongs to when grouping by `group_by`.
+    /// Selected cases from this artifact with the same group name and metadata are run together
+    /// by a single job. Returning `None` means the case is run by its own job.
+    fn case_group(
+        &self,
+        _case_name: &str,
+        _case_metadata: &Self::CaseMetadata,
+        _group_by: GroupBy,
+    ) -> Option<String> {
+        None
+    }
+
+    /// Build a command to run all of the given test cases, which are in the same group. The
+    /// command should write a report of each case's result into [`GROUP_REPORT_DIR`]. This is
+    /// only called if [`Self::case_group`] returns groups, so artifacts that don't group test
+    /// cases can leave the default, which returns an error.
+    fn build_group_command(
+        &self,
+        _cases: &[(String, Self::CaseMetadata)],
+    ) -> Result<(Utf8PathBuf, Vec<String>)> {
+        bail!("this artifact doesn't group test cases")
+    }
 }
 
 /// A package is something that contains artifacts.
@@ -176,6 +235,82 @@ pub trait TestPackage: Clone + fmt::Debug + Send + Sync + 'static {
     fn id(&self) -> Self::PackageId;
 }
 
+/// The set of tests which may have been impacted by some changed files.
+///
+/// A whole package can be impacted, or just individual artifacts in a package.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TestImpact<PackageIdT: TestPackageId, ArtifactKeyT: TestArtifactKey> {
+    packages: BTreeSet<PackageIdT>,
+    artifacts: BTreeSet<(PackageIdT, ArtifactKeyT)>,
+}
+
+impl<PackageIdT: TestPackageId, ArtifactKeyT: TestArtifactKey> Default
+    for TestImpact<PackageIdT, ArtifactKeyT>
+{
+    fn default() -> Self {
+        Self {
+            packages: BTreeSet::new(),
+            artifacts: BTreeSet::new(),
+        }
+    }
+}
+
+impl<PackageIdT: TestPackageId, ArtifactKeyT: TestArtifactKey>
+    TestImpact<PackageIdT, ArtifactKeyT>
+{
+    /// Mark every artifact in the given package as impacted.
+    pub fn add_package(&mut self, package: PackageIdT) {
+        self.packages.insert(package);
+    }
+
+    /// Mark just the given artifact in the given package as impacted.
+    pub fn add_artifact(&mut self, package: PackageIdT, artifact: ArtifactKeyT) {
+        self.artifacts.insert((package, artifact));
+    }
+
+    /// Is the given package, or any of the given artifacts in it, impacted?
+    pub fn includes_package<'a>(
+        &self,
+        package: &PackageIdT,
+        artifacts: impl IntoIterator<Item = &'a ArtifactKeyT>,
+    ) -> bool {
+        self.packages.contains(package)
+            || artifacts
+                .into_iter()
+                .any(|artifact| self.includes_artifact(package, artifact))
+    }
+
+    /// Is the given artifact in the given package impacted?
+    pub fn includes_artifact(&self, package: &PackageIdT, artifact: &ArtifactKeyT) -> bool {
+        self.packages.contains(package)
+            || self
+                .artifacts
+                .contains(&(package.clone(), artifact.clone()))
+    }
+}
+
+/// Find the entry whose directory most specifically contains `path`.
+///
+/// This is useful for mapping a changed file to the package that owns it when packages can be
+/// nested in each other's directories.
+pub fn find_containing_dir<'a, T>(
+    path: &Path,
+    dirs: impl IntoIterator<Item = (&'a Path, T)>,
+) -> Option<T> {
+    dirs.into_iter()
+        .filter(|(dir, _)| path.starts_with(dir))
+        .max_by_key(|(dir, _)| dir.components().count())
+        .map(|(_, value)| value)
+}
+
+/// The directory in each test job's container that coverage data is written to when collecting
+/// code coverage. The contents of this directory are sent back with the job's results.
+pub const COVERAGE_DIR: &str = "/maelstrom-coverage";
+
+/// The directory in the container of a job for a group of test cases that the job writes its
+/// report into. The contents of this directory are sent back with the job's results.
+pub const GROUP_REPORT_DIR: &str = "/maelstrom-group-report";
+
 pub trait CollectTests {
     /// This message is displayed in the UI when tests are being enqueued.
     const ENQUEUE_MESSAGE: &'static str;
@@ -243,6 +378,32 @@ pub trait CollectTests {
         false
     }
 
+    /// Look at the given test output lines and find the results of any subtests the test case
+    /// ran. Only subtests of `case_name` should be returned, and a subtest should come before any
+    /// of its own subtests.
+    ///
+    /// Subtests are recorded in the test database, so they can be selected and run on their own
+    /// later using their names.
+    fn get_subtest_results(_case_name: &str, _lines: &[String]) -> Vec<SubtestResult> {
+        vec![]
+    }
+
+    /// Find the results of the test cases run by a job for a group of test cases, given the report
+    /// files the job wrote into [`GROUP_REPORT_DIR`]. Any case without a result is reported as
+    /// having not finished, along with the job's output.
+    fn get_group_results(
+        _cases: &[(String, Self::CaseMetadata)],
+        _files: &[CapturedFile],
+    ) -> Result<Vec<GroupCaseResult>> {
+        Ok(vec![])
+    }
+
+    /// If the given options call for it, run each known subtest of a test case as its own job,
+    /// instead of running the test case as a whole.
+    fn fan_out_subtests(_options: &Self::Options) -> bool {
+        false
+    }
+
     /// Get all the packages for the project. This function is allowed to block.
     fn get_packages(&self, ui: &ui::UiSender) -> Result<Vec<Self::Package>>;
 
@@ -250,6 +411,57 @@ pub trait CollectTests {
     fn build_test_layers(&self, _images: HashSet<ImageRef>, _ui: &ui::UiSender) -> Result<()> {
         Ok(())
     }
+
+    /// Determine which tests in the given packages could be affected by changes to the given
+    /// paths. This is used in watch mode to only rerun the tests that a change could have
+    /// impacted. Returning `None` means that any test could have been impacted. This function is
+    /// allowed to block.
+    fn get_impact(
+        &self,
+        _packages: &[Self::Package],
+        _changed_paths: &BTreeSet<PathBuf>,
+        _ui: &ui::UiSender,
+    ) -> Result<Option<TestImpact<Self::PackageId, Self::ArtifactKey>>> {
+        Ok(None)
+    }
+
+    /// If the given options call for collecting code coverage, return the environment variables
+    /// to set for every test job. These should direct the test to write its coverage data into
+    /// [`COVERAGE_DIR`]. Returning `None` means coverage isn't being collected.
+    fn coverage_environment(_options: &Self::Options) -> Option<BTreeMap<String, String>> {
+        None
+    }
+
+    /// Save the coverage data files written by a test job into [`COVERAGE_DIR`]. This function is
+    /// allowed to block.
+    fn save_coverage_data(&self, _files: Vec<CapturedFile>) -> Result<()> {
+        Ok(())
+    }
+
+    /// Turn all of the saved coverage data into a report once all of the tests have run. This
+    /// function is allowed to block.
+    fn report_coverage(&self, _options: &Self::Options, _ui: &ui::UiSender) -> Result<()> {
+        Ok(())
+    }
+
+    /// If the given options call for it, run benchmarks instead of tests. Each benchmark job
+    /// reserves a whole worker, so that other jobs don't skew its measurements, and the output of
+    /// each successful job is given to [`Self::save_benchmark_output`].
+    fn benchmarking(_options: &Self::Options) -> bool {
+        false
+    }
+
+    /// Find and save the results in the output of a benchmark job. This function is allowed to
+    /// block.
+    fn save_benchmark_output(&self, _case_str: &str, _lines: &[String]) -> Result<()> {
+        Ok(())
+    }
+
+    /// Record the results of all of the benchmarks once they have run, and compare them to the
+    /// previous run's. This function is allowed to block.
+    fn report_benchmarks(&self, _options: &Self::Options, _ui: &ui::UiSender) -> Result<()> {
+        Ok(())
+    }
 }
 
 /// This filter is something which describes a set of test cases.
@@ -423,6 +635,12 @@ pub trait MainAppDeps: Sync {
         options: &<Self::TestCollector as CollectTests>::Options,
     ) -> Result<TemplateVars>;
 
+    /// The directories of the packages that can have their own test metadata files, along with
+    /// the packages' names. The directives in a package's file only apply to its tests.
+    fn get_package_metadata_directories(&self) -> Vec<(String, PathBuf)> {
+        vec![]
+    }
+
     const TEST_METADATA_FILE_NAME: &'static str;
     const DEFAULT_TEST_METADATA_CONTENTS: &'static str;
 }
@@ -1,13 +1,14 @@
 use crate::{
-    metadata::Metadata, ui, BuildDir, CollectTests, NoCaseMetadata, SimpleFilter,
-    StringArtifactKey, StringPackage, TestArtifact, TestFilter, TestPackage, TestPackageId, Wait,
+    metadata::{GroupBy, Metadata},
+    ui, BuildDir, CollectTests, GroupCaseResult, NoCaseMetadata, SimpleFilter, StringArtifactKey,
+    StringPackage, SubtestResult, TestArtifact, TestFilter, TestPackage, TestPackageId, Wait,
     WaitStatus,
 };
 use anyhow::Result;
 use derive_more::From;
 use maelstrom_base::{
-    stats::JobState, JobCompleted, JobEffects, JobOutcome, JobOutputResult, JobTerminationStatus,
-    Utf8PathBuf,
+    stats::JobState, CapturedFile, JobCompleted, JobEffects, JobOutcome, JobOutputResult,
+    JobTerminationStatus, Utf8PathBuf,
 };
 use maelstrom_client::spec::LayerSpec;
 use maelstrom_util::{fs::Fs, process::ExitCode, root::RootBuf};
@@ -41,6 +42,7 @@ impl Default for FakeTestCase {
                     stdout: JobOutputResult::None,
                     stderr: JobOutputResult::Inline(Box::new(*b"this output should be ignored")),
                     duration: Duration::from_secs(1),
+                    captured_files: vec![],
                 },
             }),
         }
@@ -188,9 +190,9 @@ impl TestArtifact for FakeTestArtifact {
         &self,
         case_name: &str,
         _case_metadata: &NoCaseMetadata,
-    ) -> (Utf8PathBuf, Vec<String>) {
+    ) -> Result<(Utf8PathBuf, Vec<String>)> {
         let binary_name = self.path().file_name().unwrap().to_str().unwrap();
-        (format!("/{binary_name}").into(), vec![case_name.into()])
+        Ok((format!("/{binary_name}").into(), vec![case_name.into()]))
     }
 
     fn format_case(
@@ -205,6 +207,33 @@ impl TestArtifact for FakeTestArtifact {
     fn get_test_layers(&self, _metadata: &Metadata) -> Vec<LayerSpec> {
         vec![]
     }
+
+    /// Modules are artifacts, and classes are the part of a case name before a `::`.
+    fn case_group(
+        &self,
+        case_name: &str,
+        _case_metadata: &NoCaseMetadata,
+        group_by: GroupBy,
+    ) -> Option<String> {
+        match group_by {
+            GroupBy::Module => Some(self.name.clone()),
+            GroupBy::Class => case_name
+                .split_once("::")
+                .map(|(class, _)| format!("{}::{class}", self.name)),
+        }
+    }
+
+    fn build_group_command(
+        &self,
+        cases: &[(String, NoCaseMetadata)],
+    ) -> Result<(Utf8PathBuf, Vec<String>)> {
+        let binary_name = self.path().file_name().unwrap().to_str().unwrap();
+        let arguments = cases
+            .iter()
+            .map(|(case_name, _)| case_name.clone())
+            .collect();
+        Ok((format!("/{binary_name}").into(), arguments))
+    }
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -308,6 +337,54 @@ impl CollectTests for TestCollector {
             .any(|line| line == &format!("fixture: ignoring test {case_str}"))
     }
 
+    fn get_subtest_results(case_name: &str, lines: &[String]) -> Vec<SubtestResult> {
+        lines
+            .iter()
+            .filter_map(|line| {
+                let (name, status) = line.strip_prefix("fixture: subtest ")?.split_once(' ')?;
+                let (parent, _) = name.rsplit_once('/')?;
+                let status = match status {
+                    "ok" => ui::UiJobStatus::Ok,
+                    "failed" => ui::UiJobStatus::Failure(None),
+                    _ => ui::UiJobStatus::Ignored,
+                };
+                name.starts_with(&format!("{case_name}/"))
+                    .then(|| SubtestResult {
+                        name: name.into(),
+                        parent: parent.into(),
+                        status,
+                        duration: Duration::from_secs(1),
+                    })
+            })
+            .collect()
+    }
+
+    /// Each line of a report is a case name followed by `ok` or `failed`.
+    fn get_group_results(
+        _cases: &[(String, NoCaseMetadata)],
+        files: &[CapturedFile],
+    ) -> Result<Vec<GroupCaseResult>> {
+        let mut results = vec![];
+        for file in files {
+            for line in std::str::from_utf8(&file.contents)?.lines() {
+                let (name, status) = line
+                    .split_once(' ')
+                    .ok_or_else(|| anyhow::anyhow!("bad report line {line:?}"))?;
+                let status = match status {
+                    "ok" => ui::UiJobStatus::Ok,
+                    _ => ui::UiJobStatus::Failure(None),
+                };
+                results.push(GroupCaseResult {
+                    name: name.into(),
+                    status,
+                    duration: Duration::from_secs(1),
+                    output: vec![],
+                });
+            }
+        }
+        Ok(results)
+    }
+
     fn get_packages(&self, _ui: &ui::UiSender) -> Result<Vec<FakeTestPackage>> {
         Ok(self.tests.packages())
     }
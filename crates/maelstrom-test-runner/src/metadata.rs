@@ -6,6 +6,7 @@ pub use store::Store;
 use directive::{Directive, DirectiveContainer, DirectiveContainerAugment};
 use maelstrom_base::Timeout;
 use maelstrom_client::spec::ContainerSpec;
+use serde::Deserialize;
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct Metadata {
@@ -13,6 +14,18 @@ pub struct Metadata {
     pub include_shared_libraries: bool,
     pub timeout: Option<Timeout>,
     pub ignore: bool,
+    pub group_by: Option<GroupBy>,
+    pub added_arguments: Vec<String>,
+}
+
+/// How test cases are grouped into jobs. All of the cases in a group are run by a single job, so
+/// they can share expensive fixtures. What a module or class is depends on the test runner. Test
+/// runners that don't support grouping run each case as its own job.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum GroupBy {
+    Module,
+    Class,
 }
 
 #[cfg(test)]
@@ -25,6 +38,8 @@ macro_rules! metadata {
                 include_shared_libraries: Default::default(),
                 timeout: Default::default(),
                 ignore: Default::default(),
+                group_by: Default::default(),
+                added_arguments: Default::default(),
             }
         }
     };
@@ -37,6 +52,12 @@ macro_rules! metadata {
     (@expand [ignore: $ignore:expr $(,$($field_in:tt)*)?] -> [$($($field_out:tt)+)?] [$($container_field:tt)*]) => {
         metadata!(@expand [$($($field_in)*)?] -> [$($($field_out)+,)? ignore: $ignore.into()] [$($container_field)*])
     };
+    (@expand [group_by: $group_by:expr $(,$($field_in:tt)*)?] -> [$($($field_out:tt)+)?] [$($container_field:tt)*]) => {
+        metadata!(@expand [$($($field_in)*)?] -> [$($($field_out)+,)? group_by: Some($group_by)] [$($container_field)*])
+    };
+    (@expand [added_arguments: [$($added_argument:expr),* $(,)?] $(,$($field_in:tt)*)?] -> [$($($field_out:tt)+)?] [$($container_field:tt)*]) => {
+        metadata!(@expand [$($($field_in)*)?] -> [$($($field_out)+,)? added_arguments: vec![$($added_argument.into()),*]] [$($container_field)*])
+    };
     (@expand [$container_field_name:ident: $container_field_value:expr $(,$($field_in:tt)*)?] -> [$($field_out:tt)*] [$($($container_field:tt)+)?]) => {
         metadata!(@expand [$($($field_in)*)?] -> [$($field_out)*] [$($($container_field)+,)? $container_field_name: $container_field_value])
     };
@@ -60,12 +81,16 @@ impl Metadata {
             include_shared_libraries,
             timeout,
             ignore,
+            group_by,
+            added_arguments,
         } = metadata;
         Self {
             container,
             include_shared_libraries: include_shared_libraries.unwrap_or(!uses_image_layers),
             timeout,
             ignore,
+            group_by,
+            added_arguments,
         }
     }
 }
@@ -76,6 +101,8 @@ struct MetadataInternal {
     include_shared_libraries: Option<bool>,
     timeout: Option<Timeout>,
     ignore: bool,
+    group_by: Option<GroupBy>,
+    added_arguments: Vec<String>,
 }
 
 #[cfg(test)]
@@ -88,6 +115,8 @@ macro_rules! metadata_internal {
                 include_shared_libraries: Default::default(),
                 timeout: Default::default(),
                 ignore: Default::default(),
+                group_by: Default::default(),
+                added_arguments: Default::default(),
             }
         }
     };
@@ -100,6 +129,12 @@ macro_rules! metadata_internal {
     (@expand [ignore: $ignore:expr $(,$($field_in:tt)*)?] -> [$($($field_out:tt)+)?] [$($container_field:tt)*]) => {
         metadata_internal!(@expand [$($($field_in)*)?] -> [$($($field_out)+,)? ignore: $ignore.into()] [$($container_field)*])
     };
+    (@expand [group_by: $group_by:expr $(,$($field_in:tt)*)?] -> [$($($field_out:tt)+)?] [$($container_field:tt)*]) => {
+        metadata_internal!(@expand [$($($field_in)*)?] -> [$($($field_out)+,)? group_by: Some($group_by)] [$($container_field)*])
+    };
+    (@expand [added_arguments: [$($added_argument:expr),* $(,)?] $(,$($field_in:tt)*)?] -> [$($($field_out:tt)+)?] [$($container_field:tt)*]) => {
+        metadata_internal!(@expand [$($($field_in)*)?] -> [$($($field_out)+,)? added_arguments: vec![$($added_argument.into()),*]] [$($container_field)*])
+    };
     (@expand [$container_field_name:ident: $container_field_value:expr $(,$($field_in:tt)*)?] -> [$($field_out:tt)*] [$($($container_field:tt)+)?]) => {
         metadata_internal!(@expand [$($($field_in)*)?] -> [$($field_out)*] [$($($container_field)+,)? $container_field_name: $container_field_value])
     };
@@ -115,6 +150,8 @@ impl MetadataInternal {
             mut include_shared_libraries,
             mut timeout,
             mut ignore,
+            mut group_by,
+            mut added_arguments,
         } = self;
 
         let Directive {
@@ -123,6 +160,8 @@ impl MetadataInternal {
             include_shared_libraries: new_include_shared_libraries,
             timeout: new_timeout,
             ignore: new_ignore,
+            group_by: new_group_by,
+            added_arguments: new_added_arguments,
         } = directive;
 
         container = match new_container {
@@ -140,6 +179,7 @@ impl MetadataInternal {
                 network: new_network,
                 user: new_user,
                 group: new_group,
+                dns: new_dns,
             }) => {
                 let ContainerSpec {
                     parent,
@@ -151,6 +191,7 @@ impl MetadataInternal {
                     mut network,
                     mut user,
                     mut group,
+                    mut dns,
                 } = container;
 
                 if let Some(new_layers) = new_layers {
@@ -188,6 +229,10 @@ impl MetadataInternal {
                     group = *new_group;
                 }
 
+                if new_dns.is_some() {
+                    dns = new_dns.clone();
+                }
+
                 ContainerSpec {
                     parent,
                     layers,
@@ -198,6 +243,7 @@ impl MetadataInternal {
                     network,
                     user,
                     group,
+                    dns,
                 }
             }
         };
@@ -214,11 +260,19 @@ impl MetadataInternal {
             ignore = *new_ignore;
         }
 
+        if new_group_by.is_some() {
+            group_by = *new_group_by;
+        }
+
+        added_arguments.extend(new_added_arguments.iter().cloned());
+
         Self {
             container,
             include_shared_libraries,
             timeout,
             ignore,
+            group_by,
+            added_arguments,
         }
     }
 }
@@ -598,4 +652,47 @@ mod tests {
             metadata_internal!(ignore: false),
         );
     }
+    #[test]
+    fn group_by() {
+        fold_test(
+            metadata_internal!(),
+            augment_directive!(group_by: GroupBy::Module),
+            metadata_internal!(group_by: GroupBy::Module),
+        );
+        fold_test(
+            metadata_internal!(group_by: GroupBy::Module),
+            augment_directive!(),
+            metadata_internal!(group_by: GroupBy::Module),
+        );
+        fold_test(
+            metadata_internal!(group_by: GroupBy::Module),
+            augment_directive!(group_by: GroupBy::Class),
+            metadata_internal!(group_by: GroupBy::Class),
+        );
+    }
+
+    #[test]
+    fn added_arguments() {
+        fold_test(
+            metadata_internal!(),
+            augment_directive!(added_arguments: ["--foo"]),
+            metadata_internal!(added_arguments: ["--foo"]),
+        );
+        fold_test(
+            metadata_internal!(added_arguments: ["--foo"]),
+            augment_directive!(added_arguments: ["--bar", "baz"]),
+            metadata_internal!(added_arguments: ["--foo", "--bar", "baz"]),
+        );
+        fold_test(
+            metadata_internal!(added_arguments: ["--foo"]),
+            override_directive!(
+                parent: container_container_parent!("parent", environment),
+                added_arguments: ["--bar"],
+            ),
+            metadata_internal!(
+                parent: container_container_parent!("parent", environment),
+                added_arguments: ["--foo", "--bar"],
+            ),
+        );
+    }
 }
@@ -1,11 +1,12 @@
 #![allow(unused_imports)]
+use super::GroupBy;
 use anyhow::Result;
 use maelstrom_base::{
-    GroupId, JobMount, JobMountForTomlAndJson, JobNetwork, Timeout, UserId, Utf8PathBuf,
+    GroupId, JobDns, JobMount, JobMountForTomlAndJson, JobNetwork, Timeout, UserId, Utf8PathBuf,
 };
 use maelstrom_client::spec::{
-    ContainerRefWithImplicitOrExplicitUse, ContainerSpec, ContainerSpecForTomlAndJson, EnvSelector,
-    EnvironmentSpec, ImageRef, ImageRefWithImplicitOrExplicitUse, ImageUse, LayerSpec,
+    check_dns, ContainerRefWithImplicitOrExplicitUse, ContainerSpec, ContainerSpecForTomlAndJson,
+    EnvSelector, EnvironmentSpec, ImageRef, ImageRefWithImplicitOrExplicitUse, ImageUse, LayerSpec,
 };
 use serde::{de, Deserialize, Deserializer};
 use std::{
@@ -23,6 +24,8 @@ pub struct Directive<FilterT> {
     pub include_shared_libraries: Option<bool>,
     pub timeout: Option<Option<Timeout>>,
     pub ignore: Option<bool>,
+    pub group_by: Option<GroupBy>,
+    pub added_arguments: Vec<String>,
 }
 
 #[cfg(test)]
@@ -41,6 +44,8 @@ macro_rules! augment_directive {
                 include_shared_libraries: Default::default(),
                 timeout: Default::default(),
                 ignore: Default::default(),
+                group_by: Default::default(),
+                added_arguments: Default::default(),
             }
         }
     };
@@ -56,6 +61,12 @@ macro_rules! augment_directive {
     (@expand [ignore: $ignore:expr $(,$($field_in:tt)*)?] -> [$($($field_out:tt)+)?] [$($container_field:tt)*]) => {
         augment_directive!(@expand [$($($field_in)*)?] -> [$($($field_out)+,)? ignore: Some($ignore.into())] [$($container_field)*])
     };
+    (@expand [group_by: $group_by:expr $(,$($field_in:tt)*)?] -> [$($($field_out:tt)+)?] [$($container_field:tt)*]) => {
+        augment_directive!(@expand [$($($field_in)*)?] -> [$($($field_out)+,)? group_by: Some($group_by)] [$($container_field)*])
+    };
+    (@expand [added_arguments: [$($added_argument:expr),* $(,)?] $(,$($field_in:tt)*)?] -> [$($($field_out:tt)+)?] [$($container_field:tt)*]) => {
+        augment_directive!(@expand [$($($field_in)*)?] -> [$($($field_out)+,)? added_arguments: vec![$($added_argument.into()),*]] [$($container_field)*])
+    };
     (@expand [layers: $layers:expr $(,$($field_in:tt)*)?] -> [$($field_out:tt)*] [$($($container_field:tt)+)?]) => {
         augment_directive!(@expand [$($($field_in)*)?] -> [$($field_out)*] [$($($container_field)+,)? layers: Some($layers.into_iter().map(Into::into).collect())])
     };
@@ -89,6 +100,9 @@ macro_rules! augment_directive {
     (@expand [group: $group:expr $(,$($field_in:tt)*)?] -> [$($field_out:tt)*] [$($($container_field:tt)+)?]) => {
         augment_directive!(@expand [$($($field_in)*)?] -> [$($field_out)*] [$($($container_field)+,)? group: Some($group.into())])
     };
+    (@expand [dns: $dns:expr $(,$($field_in:tt)*)?] -> [$($field_out:tt)*] [$($($container_field:tt)+)?]) => {
+        augment_directive!(@expand [$($($field_in)*)?] -> [$($field_out)*] [$($($container_field)+,)? dns: Some($dns)])
+    };
     ($($field_in:tt)*) => {
         augment_directive!(@expand [$($field_in)*] -> [] [])
     };
@@ -110,6 +124,8 @@ macro_rules! override_directive {
                 include_shared_libraries: Default::default(),
                 timeout: Default::default(),
                 ignore: Default::default(),
+                group_by: Default::default(),
+                added_arguments: Default::default(),
             }
         }
     };
@@ -125,6 +141,12 @@ macro_rules! override_directive {
     (@expand [ignore: $ignore:expr $(,$($field_in:tt)*)?] -> [$($($field_out:tt)+)?] [$($container_field:tt)*]) => {
         override_directive!(@expand [$($($field_in)*)?] -> [$($($field_out)+,)? ignore: Some($ignore.into())] [$($container_field)*])
     };
+    (@expand [group_by: $group_by:expr $(,$($field_in:tt)*)?] -> [$($($field_out:tt)+)?] [$($container_field:tt)*]) => {
+        override_directive!(@expand [$($($field_in)*)?] -> [$($($field_out)+,)? group_by: Some($group_by)] [$($container_field)*])
+    };
+    (@expand [added_arguments: [$($added_argument:expr),* $(,)?] $(,$($field_in:tt)*)?] -> [$($($field_out:tt)+)?] [$($container_field:tt)*]) => {
+        override_directive!(@expand [$($($field_in)*)?] -> [$($($field_out)+,)? added_arguments: vec![$($added_argument.into()),*]] [$($container_field)*])
+    };
     (@expand [$container_field_name:ident: $container_field_value:expr $(,$($field_in:tt)*)?] -> [$($field_out:tt)*] [$($($container_field:tt)+)?]) => {
         override_directive!(@expand [$($($field_in)*)?] -> [$($field_out)*] [$($($container_field)+,)? $container_field_name: $container_field_value])
     };
@@ -145,6 +167,8 @@ impl<FilterT> Default for Directive<FilterT> {
             include_shared_libraries: Default::default(),
             timeout: Default::default(),
             ignore: Default::default(),
+            group_by: Default::default(),
+            added_arguments: Default::default(),
         }
     }
 }
@@ -168,6 +192,7 @@ pub struct DirectiveContainerAugment {
     pub network: Option<JobNetwork>,
     pub user: Option<UserId>,
     pub group: Option<GroupId>,
+    pub dns: Option<JobDns>,
 }
 
 #[derive(Deserialize)]
@@ -187,9 +212,12 @@ struct DirectiveForTomlAndJson {
     network: Option<JobNetwork>,
     user: Option<UserId>,
     group: Option<GroupId>,
+    dns: Option<JobDns>,
     include_shared_libraries: Option<bool>,
     timeout: Option<u32>,
     ignore: Option<bool>,
+    group_by: Option<GroupBy>,
+    added_arguments: Option<Vec<String>>,
 }
 
 impl<FilterT> TryFrom<DirectiveForTomlAndJson> for Directive<FilterT>
@@ -213,6 +241,9 @@ where
             .map(|filter| filter.parse::<FilterT>())
             .transpose()
             .map_err(|err| err.to_string())?;
+        if let Some(dns) = &directive.dns {
+            check_dns(dns)?;
+        }
         match directive {
             DirectiveForTomlAndJson {
                 filter: _,
@@ -229,9 +260,12 @@ where
                 network,
                 user,
                 group,
+                dns,
                 include_shared_libraries,
                 timeout,
                 ignore,
+                group_by,
+                added_arguments,
             } => Ok(Directive {
                 filter,
                 container: DirectiveContainer::Augment(DirectiveContainerAugment {
@@ -248,10 +282,13 @@ where
                     network,
                     user,
                     group,
+                    dns,
                 }),
                 include_shared_libraries,
                 timeout: timeout.map(Timeout::new),
                 ignore,
+                group_by,
+                added_arguments: added_arguments.unwrap_or_default(),
             }),
             DirectiveForTomlAndJson {
                 filter: _,
@@ -268,9 +305,12 @@ where
                 network,
                 user,
                 group,
+                dns,
                 include_shared_libraries,
                 timeout,
                 ignore,
+                group_by,
+                added_arguments,
             } => Ok(Directive {
                 filter,
                 container: DirectiveContainer::Override(
@@ -288,12 +328,15 @@ where
                         network,
                         user,
                         group,
+                        dns,
                     }
                     .try_into()?,
                 ),
                 include_shared_libraries,
                 timeout: timeout.map(Timeout::new),
                 ignore,
+                group_by,
+                added_arguments: added_arguments.unwrap_or_default(),
             }),
         }
     }
@@ -420,6 +463,56 @@ mod tests {
         directive_parse_test(r#"ignore = false"#, augment_directive!(ignore: false));
     }
 
+    #[test]
+    fn augment_container_group_by() {
+        directive_parse_test(
+            r#"group_by = "module""#,
+            augment_directive!(group_by: GroupBy::Module),
+        );
+        directive_parse_test(
+            r#"group_by = "class""#,
+            augment_directive!(group_by: GroupBy::Class),
+        );
+        directive_parse_error_test(r#"group_by = "package""#, "unknown variant `package`");
+    }
+
+    #[test]
+    fn augment_container_added_arguments() {
+        directive_parse_test(
+            r#"added_arguments = ["--foo", "<case>"]"#,
+            augment_directive!(added_arguments: ["--foo", "<case>"]),
+        );
+        directive_parse_test(
+            indoc! {r#"
+                image = "image1"
+                added_arguments = ["--foo"]
+            "#},
+            override_directive!(
+                parent: image_container_parent!("image1", all),
+                added_arguments: ["--foo"],
+            ),
+        );
+    }
+
+    #[test]
+    fn augment_container_dns() {
+        directive_parse_test(
+            indoc! {r#"
+                dns.inherit_host = true
+                dns.search = ["corp.example.com"]
+            "#},
+            augment_directive!(dns: JobDns {
+                inherit_host: true,
+                search: vec!["corp.example.com".into()],
+                ..Default::default()
+            }),
+        );
+        directive_parse_error_test(
+            r#"dns.nameservers = ["dns.corp.example.com"]"#,
+            r#"name server "dns.corp.example.com" isn't an IP address"#,
+        );
+    }
+
     #[test]
     fn override_container_image() {
         directive_parse_test(
@@ -628,6 +721,17 @@ mod tests {
             );
         }
 
+        #[test]
+        fn group_by() {
+            assert_eq!(
+                augment_directive!(group_by: GroupBy::Class),
+                Directive::<String> {
+                    group_by: Some(GroupBy::Class),
+                    ..Default::default()
+                },
+            );
+        }
+
         #[test]
         fn layers() {
             assert_eq!(
@@ -833,6 +937,8 @@ mod tests {
                     include_shared_libraries: Some(true),
                     timeout: Some(Timeout::new(1)),
                     ignore: Some(false),
+                    group_by: None,
+                    added_arguments: vec![],
                 },
             );
         }
@@ -962,6 +1068,8 @@ mod tests {
                     include_shared_libraries: Some(true),
                     timeout: Some(Timeout::new(1)),
                     ignore: Some(false),
+                    group_by: None,
+                    added_arguments: vec![],
                 },
             );
         }
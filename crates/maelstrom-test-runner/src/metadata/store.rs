@@ -2,22 +2,26 @@ use super::{
     directive::{Directive, DirectiveContainer, DirectiveContainerAugment},
     Metadata, MetadataInternal,
 };
-use crate::TestFilter;
-use anyhow::{anyhow, Result};
+use crate::{TestFilter, TestPackage as _};
+use anyhow::{anyhow, Context as _, Result};
+use globset::{GlobBuilder, GlobSet};
 use maelstrom_client::spec::{
     ContainerParent, ContainerRef, ContainerSpec, ContainerUse, ImageRef, ImageUse,
 };
-use maelstrom_util::template::TemplateVars;
+use maelstrom_util::{fs::Fs, template::TemplateVars};
 use serde::Deserialize;
 use std::{
     collections::{HashMap, HashSet},
     fmt::Display,
+    path::{Path, PathBuf},
     str::{self, FromStr},
 };
 
 #[derive(Debug, Deserialize, PartialEq)]
 #[serde(deny_unknown_fields)]
 struct FileContents<TestFilterT> {
+    #[serde(default)]
+    include: Vec<String>,
     #[serde(bound(deserialize = "TestFilterT: FromStr, TestFilterT::Err: Display"))]
     #[serde(default = "Vec::default")]
     directives: Vec<Directive<TestFilterT>>,
@@ -27,50 +31,314 @@ struct FileContents<TestFilterT> {
 
 pub struct Store<TestFilterT> {
     directives: Vec<Directive<TestFilterT>>,
+    package_directives: HashMap<String, Vec<Directive<TestFilterT>>>,
     containers: HashMap<String, ContainerSpec>,
+    vars: TemplateVars,
 }
 
-impl<TestFilterT: TestFilter> Store<TestFilterT>
+/// Template variables whose values depend on the test case. They can be used in directives'
+/// environment variables and added arguments, which are only expanded once we know the case.
+fn case_vars(case: &str, package: &str, artifact: &str) -> [(&'static str, String); 3] {
+    [
+        ("case", case.into()),
+        ("package", package.into()),
+        ("artifact", artifact.into()),
+    ]
+}
+
+/// Expand the template variables in the directive-level environment variables and added arguments.
+fn replace_case_template_vars(
+    container: &mut ContainerSpec,
+    added_arguments: &mut [String],
+    vars: &TemplateVars,
+) -> Result<()> {
+    for environment in &mut container.environment {
+        environment.replace_template_vars(vars)?;
+    }
+    for argument in added_arguments {
+        *argument = vars.replace(argument)?;
+    }
+    Ok(())
+}
+
+/// Expand the load-time template variables in a directive. Environment variables and added
+/// arguments are expanded per test case, so they are only checked here, using placeholder case
+/// variables, so that unknown variables are reported up front.
+fn replace_directive_template_vars<TestFilterT>(
+    directive: &mut Directive<TestFilterT>,
+    vars: &TemplateVars,
+    placeholder_vars: &TemplateVars,
+) -> Result<()> {
+    let mut container = ContainerSpec::default();
+    match &mut directive.container {
+        DirectiveContainer::Override(ContainerSpec {
+            layers,
+            environment,
+            ..
+        }) => {
+            for layer in layers {
+                layer.replace_template_vars(vars)?;
+            }
+            container.environment.extend(environment.iter().cloned());
+        }
+        DirectiveContainer::Augment(DirectiveContainerAugment {
+            layers,
+            added_layers,
+            environment,
+            added_environment,
+            ..
+        }) => {
+            if let Some(layers) = layers {
+                for layer in layers {
+                    layer.replace_template_vars(vars)?;
+                }
+            }
+            for added_layer in added_layers {
+                added_layer.replace_template_vars(vars)?;
+            }
+            container
+                .environment
+                .extend(environment.iter().flatten().cloned());
+            container
+                .environment
+                .extend(added_environment.iter().cloned());
+        }
+    }
+    let mut added_arguments = directive.added_arguments.clone();
+    replace_case_template_vars(&mut container, &mut added_arguments, placeholder_vars)
+}
+
+fn parse_contents<TestFilterT: TestFilter>(
+    contents: &str,
+    vars: &TemplateVars,
+) -> Result<FileContents<TestFilterT>>
 where
     TestFilterT::Err: Display,
 {
-    pub fn load(contents: &str, vars: &TemplateVars) -> Result<Self> {
-        let mut contents: FileContents<TestFilterT> = toml::from_str(contents)?;
+    let mut contents: FileContents<TestFilterT> = toml::from_str(contents)?;
 
-        for directive in &mut contents.directives {
-            match &mut directive.container {
-                DirectiveContainer::Override(ContainerSpec { layers, .. }) => {
-                    for layer in layers {
-                        layer.replace_template_vars(vars)?;
-                    }
+    let mut placeholder_vars = vars.clone();
+    placeholder_vars.extend(case_vars("", "", ""));
+    for (index, directive) in contents.directives.iter_mut().enumerate() {
+        replace_directive_template_vars(directive, vars, &placeholder_vars)
+            .with_context(|| format!("directive {}", index + 1))?;
+    }
+
+    for (name, container) in &mut contents.containers {
+        let ContainerSpec { layers, .. } = container;
+        for layer in layers {
+            layer
+                .replace_template_vars(vars)
+                .with_context(|| format!("container {name}"))?;
+        }
+    }
+
+    Ok(contents)
+}
+
+/// Split a glob into the part before the first component with a special character, which is a
+/// plain path, and the rest.
+fn split_glob(glob: &str) -> (PathBuf, String) {
+    let mut base = PathBuf::new();
+    let mut components = glob.split('/').peekable();
+    while let Some(component) = components.next_if(|component| {
+        !component.contains(['*', '?', '[', ']', '{', '}']) && !component.is_empty()
+    }) {
+        base.push(component);
+    }
+    (base, components.collect::<Vec<_>>().join("/"))
+}
+
+struct Loader<'a> {
+    fs: &'a Fs,
+    vars: &'a TemplateVars,
+    /// The files loaded so far in the current scope, which is either the project or a package.
+    loaded: HashSet<PathBuf>,
+    containers: HashMap<String, (PathBuf, ContainerSpec)>,
+}
+
+impl Loader<'_> {
+    /// Returns whether this is the first time the file has been seen in the current scope.
+    fn first_load(&mut self, path: &Path) -> Result<bool> {
+        Ok(self.loaded.insert(self.fs.canonicalize(path)?))
+    }
+
+    fn same_file(&self, lhs: &Path, rhs: &Path) -> bool {
+        match (self.fs.canonicalize(lhs), self.fs.canonicalize(rhs)) {
+            (Ok(lhs), Ok(rhs)) => lhs == rhs,
+            _ => false,
+        }
+    }
+
+    /// Load a file's directives, preceded by the directives of the files it includes.
+    fn load_contents<TestFilterT: TestFilter>(
+        &mut self,
+        path: &Path,
+        contents: &str,
+    ) -> Result<Vec<Directive<TestFilterT>>>
+    where
+        TestFilterT::Err: Display,
+    {
+        let FileContents {
+            include,
+            directives: own_directives,
+            containers,
+        } = parse_contents(contents, self.vars)
+            .with_context(|| format!("parsing metadata file {}", path.display()))?;
+
+        for (name, container) in containers {
+            if let Some((other_path, _)) = self.containers.get(&name) {
+                if self.same_file(other_path, path) {
+                    // The file was already loaded in another scope.
+                    continue;
                 }
-                DirectiveContainer::Augment(DirectiveContainerAugment {
-                    layers,
-                    added_layers,
-                    ..
-                }) => {
-                    if let Some(layers) = layers {
-                        for layer in layers {
-                            layer.replace_template_vars(vars)?;
-                        }
-                    }
-                    for added_layer in added_layers {
-                        added_layer.replace_template_vars(vars)?;
-                    }
+                return Err(anyhow!(
+                    "container {name} is defined in both {} and {}",
+                    other_path.display(),
+                    path.display()
+                ));
+            }
+            self.containers.insert(name, (path.to_owned(), container));
+        }
+
+        let mut directives = vec![];
+        let directory = path.parent().unwrap_or(Path::new(""));
+        for glob in &include {
+            for included in self
+                .find_includes(directory, glob)
+                .with_context(|| format!("including {glob:?} from {}", path.display()))?
+            {
+                if self.first_load(&included)? {
+                    let contents = self.fs.read_to_string(&included)?;
+                    directives.extend(self.load_contents(&included, &contents)?);
                 }
             }
         }
+        directives.extend(own_directives);
+        Ok(directives)
+    }
 
-        for container in contents.containers.values_mut() {
-            let ContainerSpec { layers, .. } = container;
-            for layer in layers {
-                layer.replace_template_vars(vars)?;
+    /// Find the files matching an include glob, in sorted order.
+    fn find_includes(&self, directory: &Path, glob: &str) -> Result<Vec<PathBuf>> {
+        let (base, rest) = split_glob(glob);
+        let base = directory.join(base);
+        if rest.is_empty() {
+            if !self.fs.exists(&base) {
+                return Err(anyhow!("{} doesn't exist", base.display()));
+            }
+            return Ok(vec![base]);
+        }
+        if !self.fs.exists(&base) {
+            return Ok(vec![]);
+        }
+        let mut builder = GlobSet::builder();
+        builder.add(GlobBuilder::new(&rest).literal_separator(true).build()?);
+        let glob = builder.build()?;
+        let mut paths = vec![];
+        for path in self.fs.glob_walk(&base, &glob) {
+            let path = path?;
+            if path.is_file() {
+                paths.push(path);
             }
         }
+        paths.sort();
+        Ok(paths)
+    }
+}
 
+impl<TestFilterT: TestFilter> Store<TestFilterT>
+where
+    TestFilterT::Err: Display,
+{
+    pub fn load(contents: &str, vars: &TemplateVars) -> Result<Self> {
+        let contents = parse_contents(contents, vars)?;
+        if !contents.include.is_empty() {
+            return Err(anyhow!("`include` can only be used in a metadata file"));
+        }
         Ok(Self {
             directives: contents.directives,
+            package_directives: HashMap::new(),
             containers: contents.containers,
+            vars: vars.clone(),
+        })
+    }
+
+    /// Load the project's metadata file, along with the files it includes and the packages' own
+    /// metadata files.
+    ///
+    /// The project's metadata file is at `path`. If it doesn't exist, `default_contents` is used
+    /// instead. A file's `include` globs are relative to the file's directory. The directives of
+    /// the included files come before the including file's own directives, so the including file
+    /// takes precedence.
+    ///
+    /// Each package in `package_directories` can have its own metadata file, with the same name as
+    /// the project's, in its directory. The directives in it, and in the files it includes, only
+    /// apply to that package's tests. They come after the project's directives, so they take
+    /// precedence.
+    ///
+    /// An include without any glob characters must name a file that exists. Within the project's
+    /// files, and within each package's files, each file is only loaded the first time it's
+    /// found. A file included by both the project and a package is loaded for each of them. A
+    /// container can only be defined in one file.
+    pub fn load_files(
+        fs: &Fs,
+        path: &Path,
+        default_contents: &str,
+        package_directories: impl IntoIterator<Item = (String, PathBuf)>,
+        vars: &TemplateVars,
+    ) -> Result<Self> {
+        let mut loader = Loader {
+            fs,
+            vars,
+            loaded: HashSet::new(),
+            containers: HashMap::new(),
+        };
+
+        let (directives, project_file) = match fs.read_to_string_if_exists(path)? {
+            Some(contents) => {
+                loader.first_load(path)?;
+                (
+                    loader.load_contents(path, &contents)?,
+                    Some(fs.canonicalize(path)?),
+                )
+            }
+            None => (
+                loader
+                    .load_contents(path, default_contents)
+                    .expect("embedded default test metadata TOML to be valid"),
+                None,
+            ),
+        };
+
+        let file_name = path
+            .file_name()
+            .ok_or_else(|| anyhow!("metadata file {} has no file name", path.display()))?;
+        let mut package_directives: HashMap<String, Vec<_>> = HashMap::new();
+        for (package, directory) in package_directories {
+            let path = directory.join(file_name);
+            if let Some(contents) = fs.read_to_string_if_exists(&path)? {
+                // Each package is its own scope. The project's own file has already been applied
+                // to every package, though, so it's never loaded again.
+                loader.loaded = project_file.iter().cloned().collect();
+                if loader.first_load(&path)? {
+                    let directives = loader.load_contents(&path, &contents)?;
+                    package_directives
+                        .entry(package)
+                        .or_default()
+                        .extend(directives);
+                }
+            }
+        }
+
+        Ok(Self {
+            directives,
+            package_directives,
+            containers: loader
+                .containers
+                .into_iter()
+                .map(|(name, (_, container))| (name, container))
+                .collect(),
+            vars: vars.clone(),
         })
     }
 
@@ -80,9 +348,15 @@ where
         artifact: &TestFilterT::ArtifactKey,
         case: (&str, &TestFilterT::CaseMetadata),
     ) -> Result<Metadata> {
-        let metadata = self
+        let mut metadata = self
             .directives
             .iter()
+            .chain(
+                self.package_directives
+                    .get(package.name())
+                    .into_iter()
+                    .flatten(),
+            )
             .filter(|directive| match directive {
                 Directive { filter: None, .. } => true,
                 Directive {
@@ -93,6 +367,13 @@ where
                     .expect("should have case"),
             })
             .fold(MetadataInternal::default(), MetadataInternal::fold);
+        let mut vars = self.vars.clone();
+        vars.extend(case_vars(case.0, package.name(), &artifact.to_string()));
+        replace_case_template_vars(
+            &mut metadata.container,
+            &mut metadata.added_arguments,
+            &vars,
+        )?;
         let uses_image_layers = self.parent_uses_image_layers(&metadata.container.parent)?;
         Ok(Metadata::new(metadata, uses_image_layers))
     }
@@ -100,6 +381,7 @@ where
     pub fn get_all_images(&self) -> HashSet<ImageRef> {
         self.directives
             .iter()
+            .chain(self.package_directives.values().flatten())
             .filter_map(|directive| {
                 if let DirectiveContainer::Override(container) = &directive.container {
                     Some(container)
@@ -175,8 +457,8 @@ mod tests {
     use indoc::indoc;
     use maelstrom_base::JobNetwork;
     use maelstrom_client::{
-        container_container_parent, container_spec, image_container_parent, image_ref,
-        tar_layer_spec,
+        container_container_parent, container_spec, environment_spec, image_container_parent,
+        image_ref, tar_layer_spec,
     };
     use maplit::{hashmap, hashset};
 
@@ -203,6 +485,19 @@ mod tests {
             assert_eq!(
                 parse_toml(""),
                 FileContents {
+                    include: Default::default(),
+                    directives: Default::default(),
+                    containers: Default::default(),
+                }
+            );
+        }
+
+        #[test]
+        fn include() {
+            assert_eq!(
+                parse_toml(r#"include = ["crates/*/metadata.toml", "extra.toml"]"#),
+                FileContents {
+                    include: vec!["crates/*/metadata.toml".into(), "extra.toml".into()],
                     directives: Default::default(),
                     containers: Default::default(),
                 }
@@ -231,6 +526,7 @@ mod tests {
                     network = "loopback"
                 "#}),
                 FileContents {
+                    include: Default::default(),
                     directives: vec![
                         override_directive! {
                             filter: "package = \"package1\"",
@@ -258,6 +554,7 @@ mod tests {
                     network = "loopback"
                 "#}),
                 FileContents {
+                    include: Default::default(),
                     directives: Default::default(),
                     containers: hashmap! {
                         "container1".into() => container_spec! {
@@ -292,6 +589,7 @@ mod tests {
                     network = "loopback"
                 "#}),
                 FileContents {
+                    include: Default::default(),
                     directives: vec![
                         override_directive! {
                             filter: "package = \"package1\"",
@@ -363,6 +661,478 @@ mod tests {
         );
     }
 
+    #[test]
+    fn case_template_vars() {
+        let store = Store::<SimpleFilter>::load(
+            indoc! {r#"
+                [[directives]]
+                environment = { RESULTS = "<foo>/<package>/<artifact>/<case>" }
+                added_arguments = ["--name=<case>"]
+
+                [[directives]]
+                filter = "package = \"package1\""
+                added_environment = { PACKAGE = "<package>" }
+                added_arguments = ["--verbose", "<<case>"]
+            "#},
+            &TemplateVars::new([("foo", "bar")]),
+        )
+        .unwrap();
+        assert_eq!(
+            store
+                .get_metadata_for_test(
+                    &"package1".into(),
+                    &"artifact1".into(),
+                    ("test1", &NoCaseMetadata)
+                )
+                .unwrap(),
+            metadata! {
+                environment: [
+                    environment_spec!("RESULTS" => "bar/package1/artifact1/test1"),
+                    environment_spec!("PACKAGE" => "package1"),
+                ],
+                include_shared_libraries: true,
+                added_arguments: ["--name=test1", "--verbose", "<case>"],
+            },
+        );
+        assert_eq!(
+            store
+                .get_metadata_for_test(
+                    &"package2".into(),
+                    &"artifact2".into(),
+                    ("test2", &NoCaseMetadata)
+                )
+                .unwrap(),
+            metadata! {
+                environment: [
+                    environment_spec!("RESULTS" => "bar/package2/artifact2/test2"),
+                ],
+                include_shared_libraries: true,
+                added_arguments: ["--name=test2"],
+            },
+        );
+    }
+
+    #[test]
+    fn case_template_vars_unknown() {
+        let error = Store::<SimpleFilter>::load(
+            indoc! {r#"
+                [[directives]]
+                added_arguments = ["--shard=<shard>"]
+            "#},
+            &Default::default(),
+        )
+        .err()
+        .unwrap();
+        assert_eq!(
+            format!("{error:#}"),
+            r#"directive 1: unknown template variable "shard""#
+        );
+    }
+
+    #[test]
+    fn split_glob() {
+        let split = |glob| {
+            let (base, rest) = super::split_glob(glob);
+            (base.into_os_string().into_string().unwrap(), rest)
+        };
+        assert_eq!(split("foo.toml"), ("foo.toml".into(), "".into()));
+        assert_eq!(split("a/b/*.toml"), ("a/b".into(), "*.toml".into()));
+        assert_eq!(split("a/**/b/*.toml"), ("a".into(), "**/b/*.toml".into()));
+        assert_eq!(split("*/foo.toml"), ("".into(), "*/foo.toml".into()));
+    }
+
+    mod load_files {
+        use super::*;
+        use tempfile::TempDir;
+
+        const DEFAULT_CONTENTS: &str = indoc! {r#"
+            [[directives]]
+            network = "loopback"
+        "#};
+
+        struct Fixture {
+            temp_dir: TempDir,
+            fs: Fs,
+        }
+
+        impl Fixture {
+            fn new(files: &[(&str, &str)]) -> Self {
+                let temp_dir = tempfile::tempdir().unwrap();
+                let fs = Fs::new();
+                for (path, contents) in files {
+                    let path = temp_dir.path().join(path);
+                    fs.create_dir_all(path.parent().unwrap()).unwrap();
+                    fs.write(path, contents).unwrap();
+                }
+                Self { temp_dir, fs }
+            }
+
+            fn path(&self, path: &str) -> PathBuf {
+                self.temp_dir.path().join(path)
+            }
+
+            fn load(&self, packages: &[(&str, &str)]) -> Result<Store<SimpleFilter>> {
+                Store::load_files(
+                    &self.fs,
+                    &self.path("metadata.toml"),
+                    DEFAULT_CONTENTS,
+                    packages
+                        .iter()
+                        .map(|(name, directory)| (name.to_string(), self.path(directory))),
+                    &TemplateVars::new([("foo", "bar")]),
+                )
+            }
+        }
+
+        #[test]
+        fn default_contents() {
+            let store = Fixture::new(&[]).load(&[]).unwrap();
+            assert_eq!(
+                store.directives,
+                vec![augment_directive!(network: JobNetwork::Loopback)]
+            );
+        }
+
+        #[test]
+        fn includes_come_first_in_sorted_order() {
+            let fixture = Fixture::new(&[
+                (
+                    "metadata.toml",
+                    indoc! {r#"
+                        include = ["teams/*/metadata.toml", "extra.toml"]
+
+                        [[directives]]
+                        user = 1
+                    "#},
+                ),
+                (
+                    "teams/b/metadata.toml",
+                    indoc! {r#"
+                        [[directives]]
+                        user = 3
+                    "#},
+                ),
+                (
+                    "teams/a/metadata.toml",
+                    indoc! {r#"
+                        include = ["nested.toml"]
+
+                        [[directives]]
+                        user = 2
+                    "#},
+                ),
+                (
+                    "teams/a/nested.toml",
+                    indoc! {r#"
+                        [[directives]]
+                        added_layers = [{ tar = "<foo>.tar" }]
+                    "#},
+                ),
+                (
+                    "extra.toml",
+                    indoc! {r#"
+                        [[directives]]
+                        user = 4
+                    "#},
+                ),
+                ("teams/c/other.toml", "not valid toml"),
+            ]);
+            let store = fixture.load(&[]).unwrap();
+            assert_eq!(
+                store.directives,
+                vec![
+                    augment_directive!(added_layers: [tar_layer_spec!("bar.tar")]),
+                    augment_directive!(user: 2),
+                    augment_directive!(user: 3),
+                    augment_directive!(user: 4),
+                    augment_directive!(user: 1),
+                ]
+            );
+        }
+
+        #[test]
+        fn files_are_only_loaded_once() {
+            let fixture = Fixture::new(&[
+                (
+                    "metadata.toml",
+                    indoc! {r#"
+                        include = ["a.toml", "b.toml"]
+                    "#},
+                ),
+                ("a.toml", r#"include = ["b.toml"]"#),
+                (
+                    "b.toml",
+                    indoc! {r#"
+                        include = ["a.toml", "metadata.toml"]
+
+                        [[directives]]
+                        user = 1
+                    "#},
+                ),
+            ]);
+            let store = fixture.load(&[]).unwrap();
+            assert_eq!(store.directives, vec![augment_directive!(user: 1)]);
+        }
+
+        #[test]
+        fn missing_literal_include_is_an_error() {
+            let fixture = Fixture::new(&[(
+                "metadata.toml",
+                r#"include = ["shared/*.toml", "misspelled.toml"]"#,
+            )]);
+            let error = fixture.load(&[]).err().unwrap();
+            assert_eq!(
+                format!("{error:#}"),
+                format!(
+                    r#"including "misspelled.toml" from {}: {} doesn't exist"#,
+                    fixture.path("metadata.toml").display(),
+                    fixture.path("misspelled.toml").display(),
+                )
+            );
+        }
+
+        #[test]
+        fn files_included_by_project_and_package_apply_to_both() {
+            let fixture = Fixture::new(&[
+                (
+                    "metadata.toml",
+                    indoc! {r#"
+                        include = ["shared.toml"]
+
+                        [[directives]]
+                        user = 1
+                    "#},
+                ),
+                (
+                    "shared.toml",
+                    indoc! {r#"
+                        [[directives]]
+                        group = 2
+
+                        [containers.container1]
+                        network = "loopback"
+                    "#},
+                ),
+                (
+                    "crates/package1/metadata.toml",
+                    indoc! {r#"
+                        include = ["../../shared.toml"]
+
+                        [[directives]]
+                        user = 3
+                    "#},
+                ),
+            ]);
+            let store = fixture.load(&[("package1", "crates/package1")]).unwrap();
+            assert_eq!(
+                store.directives,
+                vec![augment_directive!(group: 2), augment_directive!(user: 1)]
+            );
+            assert_eq!(
+                store.package_directives["package1"],
+                vec![augment_directive!(group: 2), augment_directive!(user: 3)]
+            );
+            assert_eq!(
+                store.containers,
+                hashmap! {
+                    "container1".into() => container_spec!(network: JobNetwork::Loopback),
+                }
+            );
+        }
+
+        #[test]
+        fn package_files_only_apply_to_their_packages() {
+            let fixture = Fixture::new(&[
+                (
+                    "metadata.toml",
+                    indoc! {r#"
+                        [[directives]]
+                        user = 1
+                    "#},
+                ),
+                (
+                    "crates/package1/metadata.toml",
+                    indoc! {r#"
+                        [[directives]]
+                        group = 2
+                    "#},
+                ),
+            ]);
+            let store = fixture
+                .load(&[
+                    ("root", ""),
+                    ("package1", "crates/package1"),
+                    ("package2", "crates/package2"),
+                ])
+                .unwrap();
+            let get_metadata = |package: &str| {
+                store
+                    .get_metadata_for_test(
+                        &package.into(),
+                        &package.into(),
+                        ("test1", &NoCaseMetadata),
+                    )
+                    .unwrap()
+            };
+            assert_eq!(
+                get_metadata("package1"),
+                metadata!(user: 1, group: 2, include_shared_libraries: true)
+            );
+            assert_eq!(
+                get_metadata("package2"),
+                metadata!(user: 1, include_shared_libraries: true)
+            );
+            assert_eq!(
+                get_metadata("root"),
+                metadata!(user: 1, include_shared_libraries: true)
+            );
+        }
+
+        #[test]
+        fn package_files_without_project_file() {
+            let fixture = Fixture::new(&[(
+                "package1/metadata.toml",
+                indoc! {r#"
+                    [[directives]]
+                    user = 1
+                "#},
+            )]);
+            let store = fixture.load(&[("package1", "package1")]).unwrap();
+            assert_eq!(
+                store
+                    .get_metadata_for_test(
+                        &"package1".into(),
+                        &"package1".into(),
+                        ("test1", &NoCaseMetadata),
+                    )
+                    .unwrap(),
+                metadata! {
+                    network: JobNetwork::Loopback,
+                    user: 1,
+                    include_shared_libraries: true,
+                }
+            );
+        }
+
+        #[test]
+        fn containers_are_merged() {
+            let fixture = Fixture::new(&[
+                (
+                    "metadata.toml",
+                    indoc! {r#"
+                        include = ["other.toml"]
+
+                        [containers.container1]
+                        network = "loopback"
+                    "#},
+                ),
+                (
+                    "other.toml",
+                    indoc! {r#"
+                        [containers.container2]
+                        network = "local"
+                    "#},
+                ),
+            ]);
+            let store = fixture.load(&[]).unwrap();
+            assert_eq!(
+                store.containers,
+                hashmap! {
+                    "container1".into() => container_spec!(network: JobNetwork::Loopback),
+                    "container2".into() => container_spec!(network: JobNetwork::Local),
+                }
+            );
+        }
+
+        #[test]
+        fn duplicate_container() {
+            let fixture = Fixture::new(&[
+                (
+                    "metadata.toml",
+                    indoc! {r#"
+                        include = ["other.toml"]
+
+                        [containers.container1]
+                        network = "loopback"
+                    "#},
+                ),
+                (
+                    "other.toml",
+                    indoc! {r#"
+                        [containers.container1]
+                        network = "local"
+                    "#},
+                ),
+            ]);
+            let error = fixture.load(&[]).err().unwrap();
+            assert_eq!(
+                error.to_string(),
+                format!(
+                    "container container1 is defined in both {} and {}",
+                    fixture.path("metadata.toml").display(),
+                    fixture.path("other.toml").display(),
+                )
+            );
+        }
+
+        #[test]
+        fn errors_name_file_and_directive() {
+            let fixture = Fixture::new(&[
+                ("metadata.toml", r#"include = ["other.toml"]"#),
+                (
+                    "other.toml",
+                    indoc! {r#"
+                        [[directives]]
+                        user = 1
+
+                        [[directives]]
+                        added_layers = [{ tar = "<bad>.tar" }]
+                    "#},
+                ),
+            ]);
+            let error = fixture.load(&[]).err().unwrap();
+            assert_eq!(
+                format!("{error:#}"),
+                format!(
+                    r#"parsing metadata file {}: directive 2: unknown template variable "bad""#,
+                    fixture.path("other.toml").display(),
+                )
+            );
+        }
+
+        #[test]
+        fn parse_errors_name_file() {
+            let fixture = Fixture::new(&[(
+                "package1/metadata.toml",
+                indoc! {r#"
+                    [[directives]]
+                    not_a_field = 1
+                "#},
+            )]);
+            let error = fixture.load(&[("package1", "package1")]).err().unwrap();
+            assert_eq!(
+                error.to_string(),
+                format!(
+                    "parsing metadata file {}",
+                    fixture.path("package1/metadata.toml").display(),
+                )
+            );
+            assert!(format!("{error:#}").contains("unknown field `not_a_field`"));
+        }
+
+        #[test]
+        fn include_not_allowed_in_load() {
+            let error =
+                Store::<SimpleFilter>::load(r#"include = ["foo.toml"]"#, &Default::default())
+                    .err()
+                    .unwrap();
+            assert_eq!(
+                error.to_string(),
+                "`include` can only be used in a metadata file"
+            );
+        }
+    }
+
     #[test]
     fn get_all_images() {
         let store = Store::<SimpleFilter>::load(
@@ -68,6 +68,10 @@ pub(crate) struct CaseData<CaseMetadataT> {
     /// The metadata comes from the test framework. These are things like "tags" or "markers".
     pub(crate) metadata: CaseMetadataT,
 
+    /// If this is a subtest, the name of the test case or subtest that runs it. Subtests aren't
+    /// listed by the test framework. Instead, they are found in the output of their parents.
+    pub(crate) parent: Option<String>,
+
     /// The information about the test case when the db was read. If `None`, it means that the test
     /// case was just introduced, or has never been run.
     pub(crate) when_read: Option<(CaseOutcome, NonEmpty<Duration>)>,
@@ -156,7 +160,7 @@ impl<ArtifactKeyT: TestArtifactKey, CaseMetadataT: TestCaseMetadata>
     ///   - Any other existing data about the case will be preserved.
     ///
     /// Every other pre-existing test case in the artifact that isn't specified in `cases` will be
-    /// removed from the database.
+    /// removed from the database, unless it is a subtest of one of the specified cases.
     pub fn update_artifact_cases<K, I, T>(&mut self, package_name: &str, artifact_key: K, cases: I)
     where
         K: Into<ArtifactKeyT>,
@@ -174,12 +178,35 @@ impl<ArtifactKeyT: TestArtifactKey, CaseMetadataT: TestCaseMetadata>
             .into_iter()
             .map(|(case_name, metadata)| (case_name.into(), metadata))
             .collect();
+        let subtests: HashSet<String> = artifact
+            .0
+            .iter()
+            .filter(|(_, case)| {
+                // Bound the walk in case the database somehow contains a cycle.
+                let mut case = *case;
+                for _ in 0..artifact.0.len() {
+                    let Some(parent) = &case.parent else {
+                        return false;
+                    };
+                    if cases.contains_key(parent) {
+                        return true;
+                    }
+                    let Some(parent) = artifact.0.get(parent) else {
+                        return false;
+                    };
+                    case = parent;
+                }
+                false
+            })
+            .map(|(case_name, _)| case_name.clone())
+            .collect();
         artifact.0.retain(|case_name, case| {
             if let Some(metadata) = cases.remove(case_name) {
                 case.metadata = metadata;
+                case.parent = None;
                 true
             } else {
-                false
+                subtests.contains(case_name)
             }
         });
         artifact
@@ -189,6 +216,7 @@ impl<ArtifactKeyT: TestArtifactKey, CaseMetadataT: TestCaseMetadata>
                     case_name,
                     CaseData {
                         metadata,
+                        parent: None,
                         when_read: None,
                         this_run: None,
                     },
@@ -196,6 +224,70 @@ impl<ArtifactKeyT: TestArtifactKey, CaseMetadataT: TestCaseMetadata>
             }));
     }
 
+    /// Add a subtest of the given test case or subtest, if it isn't already in the database. The
+    /// subtest gets the same metadata as its parent.
+    ///
+    /// The package, artifact, and parent must have been previously added. If not, the function
+    /// will panic.
+    pub fn add_subtest_case(
+        &mut self,
+        package_name: &str,
+        artifact_key: &ArtifactKeyT,
+        parent: &str,
+        case_name: &str,
+    ) {
+        let artifact = self
+            .0
+            .get_mut(package_name)
+            .expect("package should have been added")
+            .0
+            .get_mut(artifact_key)
+            .expect("artifact should have been added");
+        if artifact.0.contains_key(case_name) {
+            return;
+        }
+        let metadata = artifact
+            .0
+            .get(parent)
+            .expect("parent should have been added")
+            .metadata
+            .clone();
+        artifact.0.insert(
+            case_name.into(),
+            CaseData {
+                metadata,
+                parent: Some(parent.into()),
+                when_read: None,
+                this_run: None,
+            },
+        );
+    }
+
+    /// Return the names of the known subtests of the given test case or subtest, in sorted order.
+    /// This doesn't include the subtests' own subtests.
+    pub fn get_subtests(
+        &self,
+        package_name: &str,
+        artifact_key: &ArtifactKeyT,
+        case_name: &str,
+    ) -> Vec<String> {
+        let Some(artifact) = self
+            .0
+            .get(package_name)
+            .and_then(|package| package.0.get(artifact_key))
+        else {
+            return vec![];
+        };
+        let mut subtests: Vec<_> = artifact
+            .0
+            .iter()
+            .filter(|(_, case)| case.parent.as_deref() == Some(case_name))
+            .map(|(name, _)| name.clone())
+            .collect();
+        subtests.sort();
+        subtests
+    }
+
     /// Remove stale test cases (and packages and artifacts).
     ///
     /// Only test cases for the artifacts and packages provided will be retained. The rest will be
@@ -229,6 +321,20 @@ impl<ArtifactKeyT: TestArtifactKey, CaseMetadataT: TestCaseMetadata>
         package_metadata: &BTreeMap<String, TestFilterT::Package>,
         filter: &TestFilterT,
     ) -> u64
+    where
+        TestFilterT: TestFilter<ArtifactKey = ArtifactKeyT, CaseMetadata = CaseMetadataT>,
+    {
+        self.count_matching_cases_where(package_metadata, filter, |_, _, _| true)
+    }
+
+    /// Like [`Self::count_matching_cases`], but test cases must also satisfy `predicate`, which is
+    /// given the package, artifact key, and case name.
+    pub fn count_matching_cases_where<TestFilterT>(
+        &self,
+        package_metadata: &BTreeMap<String, TestFilterT::Package>,
+        filter: &TestFilterT,
+        predicate: impl Fn(&TestFilterT::Package, &ArtifactKeyT, &str) -> bool,
+    ) -> u64
     where
         TestFilterT: TestFilter<ArtifactKey = ArtifactKeyT, CaseMetadata = CaseMetadataT>,
     {
@@ -243,6 +349,7 @@ impl<ArtifactKeyT: TestArtifactKey, CaseMetadataT: TestCaseMetadata>
                     filter
                         .filter(p, Some(a), Some((c, &cd.metadata)))
                         .expect("case is provided")
+                        && predicate(p, a, c)
                 } else {
                     false
                 }
@@ -345,6 +452,20 @@ impl<ArtifactKeyT: TestArtifactKey, CaseMetadataT: TestCaseMetadata>
                 )
             })
     }
+
+    /// Return whether any test case in the given package failed the last time it was run.
+    ///
+    /// Like [`Self::get_case`], this only considers data that was read in, not data from this run.
+    pub fn package_has_failures(&self, package_name: &str) -> bool {
+        self.0.get(package_name).is_some_and(|package| {
+            package.0.values().any(|artifact| {
+                artifact
+                    .0
+                    .values()
+                    .any(|case| matches!(case.when_read, Some((CaseOutcome::Failure, _))))
+            })
+        })
+    }
 }
 
 /*                    _ _     _
@@ -401,6 +522,8 @@ struct OnDiskCaseData<CaseMetadataT: TestCaseMetadata> {
     metadata: CaseMetadataT,
     #[serde(default)]
     outcome: OnDiskCaseOutcome,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    parent: Option<String>,
 }
 
 impl<CaseMetadataT: TestCaseMetadata> From<CaseData<CaseMetadataT>>
@@ -412,18 +535,21 @@ impl<CaseMetadataT: TestCaseMetadata> From<CaseData<CaseMetadataT>>
                 timings: timings.into_iter().collect(),
                 metadata: in_memory.metadata,
                 outcome: outcome.into(),
+                parent: in_memory.parent,
             }
         } else if let Some((outcome, timings)) = in_memory.when_read {
             OnDiskCaseData {
                 timings: timings.into_iter().collect(),
                 metadata: in_memory.metadata,
                 outcome: outcome.into(),
+                parent: in_memory.parent,
             }
         } else {
             OnDiskCaseData {
                 timings: vec![],
                 metadata: in_memory.metadata,
                 outcome: OnDiskCaseOutcome::New,
+                parent: in_memory.parent,
             }
         }
     }
@@ -441,6 +567,7 @@ impl<CaseMetadataT: TestCaseMetadata> From<OnDiskCaseData<CaseMetadataT>>
         });
         Self {
             metadata: on_disk.metadata,
+            parent: on_disk.parent,
             when_read,
             this_run: None,
         }
@@ -695,6 +822,7 @@ mod tests {
                         name,
                         CaseData {
                             metadata,
+                            parent: None,
                             when_read,
                             this_run,
                         },
@@ -1119,6 +1247,20 @@ mod tests {
             ),
             4
         );
+        assert_eq!(
+            db.count_matching_cases_where(&packages, &SimpleFilter::All, |p, _, _| {
+                p.0 == "package-2"
+            }),
+            1
+        );
+        assert_eq!(
+            db.count_matching_cases_where(
+                &packages,
+                &SimpleFilter::Package("package-1".into()),
+                |_, a, c| a.to_string().ends_with(".binary") || c.ends_with("-3")
+            ),
+            3
+        );
     }
 
     #[test]
@@ -1315,6 +1457,132 @@ mod tests {
         assert_eq!(db.get_case("package-2", &artifact_1, "case-1"), None);
     }
 
+    #[test]
+    fn package_has_failures() {
+        let artifact_1 = StringArtifactKey::from("artifact-1.library");
+        let artifact_2 = StringArtifactKey::from("artifact-2.library");
+        let db = TestDb::<StringArtifactKey, NoCaseMetadata>::from_iter([
+            (
+                "package-1",
+                Package::from_iter([
+                    (
+                        artifact_1.clone(),
+                        artifact_from_cases([
+                            ("case-1", None, None),
+                            ("case-2", Some((Success, nonempty![millis!(10)])), None),
+                        ]),
+                    ),
+                    (
+                        artifact_2.clone(),
+                        artifact_from_cases([(
+                            "case-1",
+                            Some((Failure, nonempty![millis!(10)])),
+                            None,
+                        )]),
+                    ),
+                ]),
+            ),
+            (
+                "package-2",
+                Package::from_iter([(
+                    artifact_1.clone(),
+                    artifact_from_cases([
+                        ("case-1", Some((Success, nonempty![millis!(10)])), None),
+                        ("case-2", None, Some((Failure, nonempty![millis!(10)]))),
+                    ]),
+                )]),
+            ),
+        ]);
+
+        assert!(db.package_has_failures("package-1"));
+        assert!(!db.package_has_failures("package-2"));
+        assert!(!db.package_has_failures("package-3"));
+    }
+
+    #[test]
+    fn add_subtest_case_and_get_subtests() {
+        let artifact_1 = StringArtifactKey::from("artifact-1.library");
+        let mut db = TestDb::<StringArtifactKey, NoCaseMetadata>::from_iter([(
+            "package-1",
+            Package::from_iter([(
+                artifact_1.clone(),
+                artifact_from_cases([("case-1", None, None), ("case-2", None, None)]),
+            )]),
+        )]);
+
+        db.add_subtest_case("package-1", &artifact_1, "case-1", "case-1/b");
+        db.add_subtest_case("package-1", &artifact_1, "case-1", "case-1/a");
+        db.add_subtest_case("package-1", &artifact_1, "case-1/a", "case-1/a/x");
+        db.update_case("package-1", &artifact_1, "case-1/a", true, millis!(10));
+        db.add_subtest_case("package-1", &artifact_1, "case-1", "case-1/a");
+
+        assert_eq!(
+            db.get_subtests("package-1", &artifact_1, "case-1"),
+            vec!["case-1/a", "case-1/b"],
+        );
+        assert_eq!(
+            db.get_subtests("package-1", &artifact_1, "case-1/a"),
+            vec!["case-1/a/x"],
+        );
+        assert_eq!(
+            db.get_subtests("package-1", &artifact_1, "case-2"),
+            Vec::<String>::new(),
+        );
+        assert_eq!(
+            db.get_subtests("package-2", &artifact_1, "case-1"),
+            Vec::<String>::new(),
+        );
+
+        // Adding an existing subtest again doesn't clear what we know about it.
+        let on_disk: OnDiskTestDb<_, _> = db.into();
+        let case = &on_disk.packages["package-1"].artifacts[&OnDiskArtifactKey { key: artifact_1 }]
+            .cases["case-1/a"];
+        assert_eq!(case.outcome, OnDiskCaseOutcome::Failure);
+        assert_eq!(case.parent.as_deref(), Some("case-1"));
+    }
+
+    #[test]
+    fn update_artifact_cases_keeps_subtests_of_listed_cases() {
+        let artifact_1 = StringArtifactKey::from("artifact-1.library");
+        let mut db = TestDb::<StringArtifactKey, NoCaseMetadata>::from_iter([(
+            "package-1",
+            Package::from_iter([(
+                artifact_1.clone(),
+                artifact_from_cases([("case-1", None, None), ("case-2", None, None)]),
+            )]),
+        )]);
+        db.add_subtest_case("package-1", &artifact_1, "case-1", "case-1/a");
+        db.add_subtest_case("package-1", &artifact_1, "case-1/a", "case-1/a/x");
+        db.add_subtest_case("package-1", &artifact_1, "case-2", "case-2/a");
+
+        db.update_artifact_cases(
+            "package-1",
+            artifact_1.clone(),
+            [("case-1", NoCaseMetadata), ("case-3", NoCaseMetadata)],
+        );
+
+        assert_eq!(
+            db.get_subtests("package-1", &artifact_1, "case-1"),
+            vec!["case-1/a"],
+        );
+        assert_eq!(
+            db.get_subtests("package-1", &artifact_1, "case-1/a"),
+            vec!["case-1/a/x"],
+        );
+        assert_eq!(
+            db.get_subtests("package-1", &artifact_1, "case-2"),
+            Vec::<String>::new(),
+        );
+        let on_disk: OnDiskTestDb<_, _> = db.into();
+        let cases: Vec<_> = on_disk.packages["package-1"].artifacts
+            [&OnDiskArtifactKey { key: artifact_1 }]
+            .cases
+            .keys()
+            .cloned()
+            .collect();
+        assert_eq!(cases, vec!["case-1", "case-1/a", "case-1/a/x", "case-3"]);
+    }
+
     #[test]
     fn load_passes_proper_path() {
         struct Deps;
@@ -1475,6 +1743,11 @@ mod tests {
 
                         [package-1."artifact-1.library".case-1-1L-7]
                         timings = []
+
+                        [package-1."artifact-1.library"."case-1-1L-7/sub"]
+                        outcome = "success"
+                        timings = [0.04]
+                        parent = "case-1-1L-7"
                     "#}
                     .into(),
                 ))
@@ -1482,24 +1755,28 @@ mod tests {
         }
         let store =
             TestDbStore::<StringArtifactKey, NoCaseMetadata, _>::new(Deps, RootBuf::new("".into()));
+        let mut artifact = artifact_from_cases([
+            (
+                "case-1-1L-1",
+                Some((Success, nonempty![millis!(10), millis!(11)])),
+                None,
+            ),
+            ("case-1-1L-2", Some((Failure, nonempty![millis!(20)])), None),
+            ("case-1-1L-3", Some((Success, nonempty![millis!(30)])), None),
+            ("case-1-1L-4", None, None),
+            ("case-1-1L-5", None, None),
+            ("case-1-1L-6", None, None),
+            ("case-1-1L-7", None, None),
+            (
+                "case-1-1L-7/sub",
+                Some((Success, nonempty![millis!(40)])),
+                None,
+            ),
+        ]);
+        artifact.0.get_mut("case-1-1L-7/sub").unwrap().parent = Some("case-1-1L-7".into());
         let expected = TestDb::<StringArtifactKey, NoCaseMetadata>::from_iter([(
             "package-1",
-            Package::from_iter([(
-                StringArtifactKey::from("artifact-1.library"),
-                artifact_from_cases([
-                    (
-                        "case-1-1L-1",
-                        Some((Success, nonempty![millis!(10), millis!(11)])),
-                        None,
-                    ),
-                    ("case-1-1L-2", Some((Failure, nonempty![millis!(20)])), None),
-                    ("case-1-1L-3", Some((Success, nonempty![millis!(30)])), None),
-                    ("case-1-1L-4", None, None),
-                    ("case-1-1L-5", None, None),
-                    ("case-1-1L-6", None, None),
-                    ("case-1-1L-7", None, None),
-                ]),
-            )]),
+            Package::from_iter([(StringArtifactKey::from("artifact-1.library"), artifact)]),
         )]);
         assert_eq!(store.load().unwrap(), expected);
     }
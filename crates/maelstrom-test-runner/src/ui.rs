@@ -9,7 +9,7 @@ use derive_more::{From, Into};
 use maelstrom_base::stats::{JobState, JobStateCounts};
 use maelstrom_client::{IntrospectResponse, JobRunningStatus};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 use std::panic::{RefUnwindSafe, UnwindSafe};
 use std::sync::mpsc::{Receiver, Sender};
 use std::sync::{Arc, Weak};
@@ -49,19 +49,23 @@ impl UiHandle {
 }
 
 pub trait Ui: Send + Sync + 'static {
-    fn run(&mut self, recv: Receiver<UiMessage>) -> Result<()>;
+    /// Run the UI until `recv` is disconnected. Any requests the user makes of the application are
+    /// sent on `requests`. Sending will fail if the application isn't in a mode where it can honor
+    /// them.
+    fn run(&mut self, recv: Receiver<UiMessage>, requests: Sender<UiRequest>) -> Result<()>;
 
     fn start_ui_thread(
         mut self,
         logging_output: LoggingOutput,
         log: slog::Logger,
-    ) -> (UiHandle, UiSender)
+    ) -> (UiHandle, UiSender, Receiver<UiRequest>)
     where
         Self: Sized,
     {
         let (ui_send, ui_recv) = std::sync::mpsc::channel();
+        let (request_send, request_recv) = std::sync::mpsc::channel();
         let ui_sender = UiSender::new(ui_send);
-        let thread_handle = std::thread::spawn(move || self.run(ui_recv));
+        let thread_handle = std::thread::spawn(move || self.run(ui_recv, request_send));
         logging_output.display_on_ui(ui_sender.clone());
 
         (
@@ -71,13 +75,14 @@ pub trait Ui: Send + Sync + 'static {
                 handle: thread_handle,
             },
             ui_sender,
+            request_recv,
         )
     }
 }
 
 impl Ui for Box<dyn Ui> {
-    fn run(&mut self, recv: Receiver<UiMessage>) -> Result<()> {
-        Ui::run(&mut **self, recv)
+    fn run(&mut self, recv: Receiver<UiMessage>, requests: Sender<UiRequest>) -> Result<()> {
+        Ui::run(&mut **self, recv, requests)
     }
 }
 
@@ -181,7 +186,7 @@ impl UiJobSummary {
     }
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct UiJobResult {
     pub name: String,
     pub job_id: UiJobId,
@@ -257,6 +262,14 @@ pub enum UiMessage {
     AllJobsFinished(UiJobSummary),
 }
 
+/// Requests sent from the UI back to the application.
+#[derive(Debug, PartialEq, Eq)]
+pub enum UiRequest {
+    /// Run the given tests again. The names are the same ones used in [`UiJobEnqueued`] and
+    /// [`UiJobResult`].
+    RerunTests(BTreeSet<String>),
+}
+
 #[derive(Clone)]
 pub struct UiSender {
     send: Arc<Sender<UiMessage>>,
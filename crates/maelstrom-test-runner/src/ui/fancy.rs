@@ -1,7 +1,11 @@
+mod browser;
 mod multi_gauge;
 
-use super::{CompletedJob, JobStatuses, Ui, UiJobResult, UiJobStatus, UiJobSummary, UiMessage};
+use super::{
+    CompletedJob, JobStatuses, Ui, UiJobResult, UiJobStatus, UiJobSummary, UiMessage, UiRequest,
+};
 use anyhow::{bail, Result};
+use browser::{Action as BrowserAction, Browser, Entry as BrowserEntry, Tab as BrowserTab};
 use derive_more::From;
 use indicatif::HumanBytes;
 use maelstrom_client::RemoteProgress;
@@ -25,8 +29,10 @@ use ratatui::{
 use slog::Drain as _;
 use std::{
     cell::RefCell,
+    collections::BTreeMap,
     io::{self, stdout, Write as _},
-    sync::mpsc::{Receiver, RecvTimeoutError},
+    mem,
+    sync::mpsc::{Receiver, RecvTimeoutError, Sender},
     time::{Duration, Instant},
 };
 use unicode_width::UnicodeWidthStr as _;
@@ -229,21 +235,27 @@ fn format_finished(res: &UiJobResult) -> Vec<PrintAbove> {
     }
 
     let mut output = vec![(Row::new(line.into_iter()), test_status_constraints()).into()];
+    output.extend(format_output(res).into_iter().map(PrintAbove::from));
+    output
+}
+
+/// Format the details, stdout, and stderr of a finished test.
+fn format_output(res: &UiJobResult) -> Vec<Line<'static>> {
+    let mut output = vec![];
 
     if let Some(details) = res.status.details() {
-        output.extend(details.split('\n').map(|l| Line::from(l.to_owned()).into()));
+        output.extend(details.split('\n').map(|l| Line::from(l.to_owned())));
     }
 
     for l in &res.stdout {
-        output.push(Line::from(l.clone()).into());
+        output.push(Line::from(l.clone()));
     }
 
     for l in &res.stderr {
         output.push(
             ["stderr: ".red(), l.to_owned().into()]
                 .into_iter()
-                .collect::<Line<'static>>()
-                .into(),
+                .collect(),
         );
     }
     output
@@ -371,6 +383,10 @@ pub struct FancyUi {
     collection_output: String,
     blank: bool,
     height: u16,
+
+    // These are kept across runs in watch mode.
+    results: BTreeMap<String, UiJobResult>,
+    browser: Option<Browser>,
 }
 
 impl FancyUi {
@@ -395,10 +411,17 @@ impl FancyUi {
             collection_output: String::new(),
             blank: false,
             height: 0,
+
+            results: BTreeMap::new(),
+            browser: None,
         })
     }
 
-    pub fn run_inner(&mut self, recv: &Receiver<UiMessage>) -> Result<bool> {
+    pub fn run_inner(
+        &mut self,
+        recv: &Receiver<UiMessage>,
+        requests: &Sender<UiRequest>,
+    ) -> Result<bool> {
         let backend = CrosstermBackend::new(stdout());
         let height = backend.size()?.height / 4;
         let mut terminal = init_terminal(backend, height)?;
@@ -414,7 +437,7 @@ impl FancyUi {
             if last_tick.elapsed() > Duration::from_millis(33) {
                 while crossterm::event::poll(Duration::from_secs(0))? {
                     if let crossterm::event::Event::Key(key) = crossterm::event::read()? {
-                        self.handle_key(key);
+                        self.handle_key(key, requests);
                     }
                 }
 
@@ -457,6 +480,7 @@ impl FancyUi {
                     UiMessage::JobUpdated(msg) => self.jobs.job_updated(msg.job_id, msg.status),
                     UiMessage::JobFinished(res) => {
                         self.print_above.extend(format_finished(&res));
+                        self.results.insert(res.name.clone(), res.clone());
                         self.jobs.job_finished(res);
                     }
                     UiMessage::UpdatePendingJobsCount(count) => {
@@ -490,6 +514,12 @@ impl FancyUi {
                 },
                 Err(RecvTimeoutError::Timeout) => continue,
                 Err(RecvTimeoutError::Disconnected) => {
+                    if self.browser.is_some() {
+                        // The application is done, but we keep the browser up until the user
+                        // closes it.
+                        std::thread::sleep(Duration::from_millis(33));
+                        continue;
+                    }
                     done = true;
                     break;
                 }
@@ -520,23 +550,27 @@ impl FancyUi {
         disable_raw_mode()?;
         stdout().write_all(self.collection_output.as_bytes())?;
 
+        let results = mem::take(&mut self.results);
+        let browser = self.browser.take();
         *self = Self::new(false /* list */, true /* stdout_is_tty */).unwrap();
         self.blank = true;
         self.height = height;
+        self.results = results;
+        self.browser = browser;
 
         Ok(!done)
     }
 }
 
 impl Ui for FancyUi {
-    fn run(&mut self, recv: Receiver<UiMessage>) -> Result<()> {
+    fn run(&mut self, recv: Receiver<UiMessage>, requests: Sender<UiRequest>) -> Result<()> {
         let hook = std::panic::take_hook();
         std::panic::set_hook(Box::new(move |info| {
             let _ = restore_terminal(None);
             hook(info)
         }));
 
-        while self.run_inner(&recv)? {}
+        while self.run_inner(&recv, &requests)? {}
 
         restore_terminal(Some(self.height))?;
         Ok(())
@@ -544,7 +578,7 @@ impl Ui for FancyUi {
 }
 
 impl FancyUi {
-    fn handle_key(&mut self, key: KeyEvent) {
+    fn handle_key(&mut self, key: KeyEvent, requests: &Sender<UiRequest>) {
         if key.kind != KeyEventKind::Press {
             return;
         }
@@ -554,6 +588,71 @@ impl FancyUi {
             linux::raise(linux::Signal::INT).unwrap();
             unreachable!();
         }
+
+        let Some(mut browser) = self.browser.take() else {
+            if key.code == KeyCode::Char('b') {
+                self.browser = Some(Browser::default());
+            }
+            return;
+        };
+
+        let entries = browser.filter(self.browser_entries(browser.tab()));
+        match browser.handle_key(key, &entries) {
+            BrowserAction::None => {}
+            BrowserAction::Close => return,
+            BrowserAction::Rerun(names) => {
+                let count = names.len();
+                if count == 0 {
+                    browser.set_message("no tests to rerun");
+                } else if requests.send(UiRequest::RerunTests(names)).is_ok() {
+                    browser.set_message(format!("rerunning {count} test(s)"));
+                } else {
+                    browser.set_message("rerunning tests requires --watch");
+                }
+            }
+        }
+        self.browser = Some(browser);
+    }
+
+    /// Get the unfiltered tests to show in the given browser tab.
+    fn browser_entries(&self, tab: BrowserTab) -> Vec<BrowserEntry<'_>> {
+        match tab {
+            BrowserTab::Running => {
+                let mut running_tests: Vec<_> = self.jobs.running_tests().collect();
+                running_tests.sort_by_key(|(name, _)| *name);
+                running_tests
+                    .into_iter()
+                    .map(|(name, t)| BrowserEntry {
+                        name,
+                        status: format!("{}s", t.elapsed().as_secs()).into(),
+                        result: None,
+                    })
+                    .collect()
+            }
+            BrowserTab::Failed | BrowserTab::Passed => self
+                .results
+                .values()
+                .filter(|res| tab.includes(&res.status))
+                .map(|res| BrowserEntry {
+                    name: &res.name,
+                    status: match &res.status {
+                        UiJobStatus::Ok => "passed".green(),
+                        UiJobStatus::TimedOut => "timed out".red(),
+                        UiJobStatus::Error(_) => "error".red(),
+                        _ => "failed".red(),
+                    },
+                    result: Some(res),
+                })
+                .collect(),
+        }
+    }
+
+    fn render_browser(&mut self, area: Rect, buf: &mut Buffer) {
+        let browser = self.browser.as_ref().unwrap();
+        let counts = [BrowserTab::Running, BrowserTab::Failed, BrowserTab::Passed]
+            .map(|tab| self.browser_entries(tab).len());
+        let entries = browser.filter(self.browser_entries(browser.tab()));
+        browser.render(counts, &entries, area, buf);
     }
 
     fn render_running_tests(&mut self, area: Rect, buf: &mut Buffer) {
@@ -756,12 +855,18 @@ type SectionFnPtr = fn(&mut FancyUi, Rect, &mut Buffer);
 impl Widget for &mut FancyUi {
     fn render(self, area: Rect, buf: &mut Buffer) {
         let mut sections = vec![];
+        let browsing = self.browser.is_some();
 
+        if browsing {
+            sections.push((Constraint::Fill(1), FancyUi::render_browser as SectionFnPtr));
+        }
         if !self.blank {
-            sections.push((
-                Constraint::Fill(1),
-                FancyUi::render_running_tests as SectionFnPtr,
-            ));
+            if !browsing {
+                sections.push((
+                    Constraint::Fill(1),
+                    FancyUi::render_running_tests as SectionFnPtr,
+                ));
+            }
             if !self.remote_progress.is_empty() {
                 let max_height = self.remote_progress.len().try_into().unwrap_or(u16::MAX);
                 sections.push((
@@ -772,7 +877,7 @@ impl Widget for &mut FancyUi {
             if self.producing_build_output {
                 sections.push((Constraint::Length(5), FancyUi::render_build_output as _));
             }
-            if self.jobs.failed() > 0 {
+            if !browsing && self.jobs.failed() > 0 {
                 let max_height = (self.jobs.failed() + 2).try_into().unwrap_or(u16::MAX);
                 sections.push((
                     Constraint::Max(max_height),
@@ -0,0 +1,376 @@
+//! An interactive browser for the fancy UI. It lets the user look through the running, failed, and
+//! passed tests, filter them by name, look at a test's output, and ask for tests to be rerun.
+
+use super::format_output;
+use crate::ui::{UiJobResult, UiJobStatus};
+use ratatui::{
+    buffer::Buffer,
+    crossterm::event::{KeyCode, KeyEvent},
+    layout::{Constraint, Layout, Rect},
+    style::{Modifier, Style, Stylize as _},
+    text::{Line, Span},
+    widgets::{Block, Cell, Paragraph, Row, StatefulWidget, Table, TableState, Tabs, Widget},
+};
+use std::collections::BTreeSet;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Tab {
+    #[default]
+    Running,
+    Failed,
+    Passed,
+}
+
+impl Tab {
+    const ALL: [Self; 3] = [Self::Running, Self::Failed, Self::Passed];
+
+    fn index(self) -> usize {
+        Self::ALL.iter().position(|t| *t == self).unwrap()
+    }
+
+    fn next(self) -> Self {
+        Self::ALL[(self.index() + 1) % Self::ALL.len()]
+    }
+
+    fn prev(self) -> Self {
+        Self::ALL[(self.index() + Self::ALL.len() - 1) % Self::ALL.len()]
+    }
+
+    fn title(self) -> &'static str {
+        match self {
+            Self::Running => "Running",
+            Self::Failed => "Failed",
+            Self::Passed => "Passed",
+        }
+    }
+
+    /// Does a completed test with the given status belong in this tab?
+    pub fn includes(self, status: &UiJobStatus) -> bool {
+        match self {
+            Self::Running => false,
+            Self::Failed => matches!(
+                status,
+                UiJobStatus::Failure(_) | UiJobStatus::Error(_) | UiJobStatus::TimedOut
+            ),
+            Self::Passed => matches!(status, UiJobStatus::Ok),
+        }
+    }
+}
+
+/// A test shown in the browser.
+pub struct Entry<'a> {
+    pub name: &'a str,
+    pub status: Span<'static>,
+    /// The result of the test, if it has completed.
+    pub result: Option<&'a UiJobResult>,
+}
+
+/// What the fancy UI should do in response to a key press in the browser.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Action {
+    None,
+    Close,
+    Rerun(BTreeSet<String>),
+}
+
+#[derive(Default)]
+pub struct Browser {
+    tab: Tab,
+    selected: usize,
+    filter: String,
+    editing_filter: bool,
+    /// When set, we're showing the output of the selected test, scrolled down this many lines.
+    output_scroll: Option<u16>,
+    message: Option<String>,
+}
+
+impl Browser {
+    pub fn tab(&self) -> Tab {
+        self.tab
+    }
+
+    /// Set the message shown at the bottom of the browser until the next key press.
+    pub fn set_message(&mut self, message: impl Into<String>) {
+        self.message = Some(message.into());
+    }
+
+    /// Remove the entries that don't match the filter. Matching is case-insensitive.
+    pub fn filter<'a>(&self, entries: impl IntoIterator<Item = Entry<'a>>) -> Vec<Entry<'a>> {
+        let filter = self.filter.to_lowercase();
+        entries
+            .into_iter()
+            .filter(|e| e.name.to_lowercase().contains(&filter))
+            .collect()
+    }
+
+    fn set_tab(&mut self, tab: Tab) {
+        self.tab = tab;
+        self.selected = 0;
+        self.output_scroll = None;
+    }
+
+    /// Handle a key press. `entries` must be the filtered entries for the current tab.
+    pub fn handle_key(&mut self, key: KeyEvent, entries: &[Entry<'_>]) -> Action {
+        self.message = None;
+        self.selected = self.selected.min(entries.len().saturating_sub(1));
+        let selected = entries.get(self.selected);
+
+        if self.editing_filter {
+            match key.code {
+                KeyCode::Char(c) => self.filter.push(c),
+                KeyCode::Backspace => {
+                    self.filter.pop();
+                }
+                KeyCode::Enter => self.editing_filter = false,
+                KeyCode::Esc => {
+                    self.filter.clear();
+                    self.editing_filter = false;
+                }
+                _ => {}
+            }
+            self.selected = 0;
+            return Action::None;
+        }
+
+        if let Some(scroll) = &mut self.output_scroll {
+            match key.code {
+                KeyCode::Up | KeyCode::Char('k') => *scroll = scroll.saturating_sub(1),
+                KeyCode::Down | KeyCode::Char('j') => *scroll = scroll.saturating_add(1),
+                KeyCode::PageUp => *scroll = scroll.saturating_sub(10),
+                KeyCode::PageDown | KeyCode::Char(' ') => *scroll = scroll.saturating_add(10),
+                KeyCode::Home | KeyCode::Char('g') => *scroll = 0,
+                KeyCode::Char('r') => return rerun(selected),
+                KeyCode::Esc | KeyCode::Enter | KeyCode::Left | KeyCode::Char('q') => {
+                    self.output_scroll = None
+                }
+                _ => {}
+            }
+            return Action::None;
+        }
+
+        match key.code {
+            KeyCode::Up | KeyCode::Char('k') => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Down | KeyCode::Char('j') => {
+                self.selected = (self.selected + 1).min(entries.len().saturating_sub(1))
+            }
+            KeyCode::Home | KeyCode::Char('g') => self.selected = 0,
+            KeyCode::End | KeyCode::Char('G') => self.selected = entries.len().saturating_sub(1),
+            KeyCode::Tab | KeyCode::Right | KeyCode::Char('l') => self.set_tab(self.tab.next()),
+            KeyCode::BackTab | KeyCode::Left | KeyCode::Char('h') => self.set_tab(self.tab.prev()),
+            KeyCode::Char('1') => self.set_tab(Tab::Running),
+            KeyCode::Char('2') => self.set_tab(Tab::Failed),
+            KeyCode::Char('3') => self.set_tab(Tab::Passed),
+            KeyCode::Enter if selected.is_some_and(|e| e.result.is_some()) => {
+                self.output_scroll = Some(0)
+            }
+            KeyCode::Char('/') => self.editing_filter = true,
+            KeyCode::Char('r') => return rerun(selected),
+            KeyCode::Char('R') => {
+                return Action::Rerun(entries.iter().map(|e| e.name.to_owned()).collect())
+            }
+            KeyCode::Esc if !self.filter.is_empty() => {
+                self.filter.clear();
+                self.selected = 0;
+            }
+            KeyCode::Esc | KeyCode::Char('q') => return Action::Close,
+            _ => {}
+        }
+        Action::None
+    }
+
+    /// Render the browser. `counts` has the unfiltered number of tests in each tab, and `entries`
+    /// must be the filtered entries for the current tab.
+    pub fn render(&self, counts: [usize; 3], entries: &[Entry<'_>], area: Rect, buf: &mut Buffer) {
+        let [tabs_area, body_area, footer_area] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Fill(1),
+            Constraint::Length(1),
+        ])
+        .areas(area);
+
+        Tabs::new(
+            Tab::ALL
+                .iter()
+                .zip(counts)
+                .map(|(tab, count)| format!("{} ({count})", tab.title())),
+        )
+        .select(self.tab.index())
+        .highlight_style(Style::default().bold().reversed())
+        .render(tabs_area, buf);
+
+        let selected = self.selected.min(entries.len().saturating_sub(1));
+        match (self.output_scroll, entries.get(selected)) {
+            (
+                Some(scroll),
+                Some(Entry {
+                    name,
+                    result: Some(result),
+                    ..
+                }),
+            ) => {
+                Paragraph::new(format_output(result))
+                    .block(Block::bordered().gray().title(name.bold()))
+                    .scroll((scroll, 0))
+                    .render(body_area, buf);
+            }
+            _ => {
+                let rows = entries.iter().map(|e| {
+                    Row::new([Cell::from(e.name.to_owned()), Cell::from(e.status.clone())])
+                });
+                let mut state = TableState::default().with_selected(Some(selected));
+                StatefulWidget::render(
+                    Table::new(rows, [Constraint::Fill(1), Constraint::Length(10)])
+                        .block(Block::bordered().gray())
+                        .row_highlight_style(Style::default().add_modifier(Modifier::REVERSED)),
+                    body_area,
+                    buf,
+                    &mut state,
+                );
+            }
+        }
+
+        let footer = if self.editing_filter {
+            Line::from(vec!["/".bold(), Span::raw(self.filter.clone()), "█".into()])
+        } else if let Some(message) = &self.message {
+            Line::from(message.clone().yellow())
+        } else if self.output_scroll.is_some() {
+            Line::from("↑↓ scroll  r rerun  esc back").dark_gray()
+        } else {
+            let mut spans = vec![];
+            if !self.filter.is_empty() {
+                spans.push(format!("filter: {}  ", self.filter).bold());
+            }
+            spans.push(
+                "↑↓ select  ←→ tab  enter output  / filter  r rerun  R rerun all  q close"
+                    .dark_gray(),
+            );
+            Line::from(spans)
+        };
+        footer.render(footer_area, buf);
+    }
+}
+
+fn rerun(entry: Option<&Entry<'_>>) -> Action {
+    match entry {
+        Some(entry) => Action::Rerun([entry.name.to_owned()].into()),
+        None => Action::None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ui::UiJobId;
+    use ratatui::crossterm::event::KeyModifiers;
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    fn result(name: &str, status: UiJobStatus) -> UiJobResult {
+        UiJobResult {
+            name: name.into(),
+            job_id: UiJobId::from(1),
+            duration: None,
+            status,
+            stdout: vec![],
+            stderr: vec![],
+        }
+    }
+
+    fn entries(results: &[UiJobResult]) -> Vec<Entry<'_>> {
+        results
+            .iter()
+            .map(|r| Entry {
+                name: &r.name,
+                status: "".into(),
+                result: Some(r),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn tabs_wrap_around() {
+        let mut browser = Browser::default();
+        assert_eq!(browser.tab(), Tab::Running);
+        browser.handle_key(key(KeyCode::BackTab), &[]);
+        assert_eq!(browser.tab(), Tab::Passed);
+        browser.handle_key(key(KeyCode::Tab), &[]);
+        assert_eq!(browser.tab(), Tab::Running);
+        browser.handle_key(key(KeyCode::Char('2')), &[]);
+        assert_eq!(browser.tab(), Tab::Failed);
+    }
+
+    #[test]
+    fn selection_is_clamped() {
+        let results = [
+            result("foo test_a", UiJobStatus::Ok),
+            result("foo test_b", UiJobStatus::Ok),
+        ];
+        let entries = entries(&results);
+        let mut browser = Browser::default();
+        for _ in 0..3 {
+            browser.handle_key(key(KeyCode::Down), &entries);
+        }
+        assert_eq!(
+            browser.handle_key(key(KeyCode::Char('r')), &entries),
+            Action::Rerun(["foo test_b".into()].into())
+        );
+        browser.handle_key(key(KeyCode::Up), &entries);
+        assert_eq!(
+            browser.handle_key(key(KeyCode::Char('r')), &entries[..1]),
+            Action::Rerun(["foo test_a".into()].into())
+        );
+        assert_eq!(
+            browser.handle_key(key(KeyCode::Char('r')), &[]),
+            Action::None
+        );
+    }
+
+    #[test]
+    fn filter_by_name() {
+        let results = [
+            result("foo test_a", UiJobStatus::Ok),
+            result("bar Test_B", UiJobStatus::Ok),
+            result("baz test_c", UiJobStatus::Ok),
+        ];
+        let mut browser = Browser::default();
+        for c in "/test_b".chars() {
+            browser.handle_key(key(KeyCode::Char(c)), &[]);
+        }
+        browser.handle_key(key(KeyCode::Enter), &[]);
+        let filtered = browser.filter(entries(&results));
+        assert_eq!(
+            browser.handle_key(key(KeyCode::Char('R')), &filtered),
+            Action::Rerun(["bar Test_B".into()].into())
+        );
+
+        // Escape clears the filter before closing the browser.
+        assert_eq!(
+            browser.handle_key(key(KeyCode::Esc), &filtered),
+            Action::None
+        );
+        assert_eq!(browser.filter(entries(&results)).len(), 3);
+        assert_eq!(browser.handle_key(key(KeyCode::Esc), &[]), Action::Close);
+    }
+
+    #[test]
+    fn output_pane() {
+        let results = [result("foo test_a", UiJobStatus::Failure(None))];
+        let entries = entries(&results);
+        let mut browser = Browser::default();
+        browser.handle_key(key(KeyCode::Enter), &entries);
+        assert_eq!(browser.output_scroll, Some(0));
+        browser.handle_key(key(KeyCode::Down), &entries);
+        assert_eq!(browser.output_scroll, Some(1));
+        assert_eq!(
+            browser.handle_key(key(KeyCode::Char('q')), &entries),
+            Action::None
+        );
+        assert_eq!(browser.output_scroll, None);
+        assert_eq!(
+            browser.handle_key(key(KeyCode::Char('q')), &entries),
+            Action::Close
+        );
+    }
+}
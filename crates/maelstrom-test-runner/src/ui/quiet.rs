@@ -1,7 +1,7 @@
-use super::{Terminal, Ui, UiJobSummary, UiMessage};
+use super::{Terminal, Ui, UiJobSummary, UiMessage, UiRequest};
 use anyhow::{bail, Result};
 use indicatif::{ProgressBar, ProgressDrawTarget, ProgressStyle};
-use std::sync::mpsc::Receiver;
+use std::sync::mpsc::{Receiver, Sender};
 
 fn create_bar<TermT: Terminal>(term: TermT) -> ProgressBar {
     let bar = ProgressBar::new(0).with_message("tests").with_style(
@@ -62,7 +62,7 @@ impl<TermT> Ui for QuietUi<TermT>
 where
     TermT: Terminal,
 {
-    fn run(&mut self, recv: Receiver<UiMessage>) -> Result<()> {
+    fn run(&mut self, recv: Receiver<UiMessage>, _requests: Sender<UiRequest>) -> Result<()> {
         let mut collection_output = String::new();
         while let Ok(msg) = recv.recv() {
             match msg {
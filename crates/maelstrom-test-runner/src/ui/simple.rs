@@ -1,6 +1,8 @@
 mod progress;
 
-use super::{JobStatuses, Terminal, Ui, UiJobResult, UiJobStatus, UiJobSummary, UiMessage};
+use super::{
+    JobStatuses, Terminal, Ui, UiJobResult, UiJobStatus, UiJobSummary, UiMessage, UiRequest,
+};
 use anyhow::Result;
 use colored::Colorize as _;
 use derive_more::From;
@@ -11,7 +13,7 @@ use progress::{
 use slog::Drain as _;
 use std::cell::RefCell;
 use std::io::{self, Write as _};
-use std::sync::mpsc::{Receiver, RecvTimeoutError};
+use std::sync::mpsc::{Receiver, RecvTimeoutError, Sender};
 use std::time::{Duration, Instant};
 use unicode_truncate::UnicodeTruncateStr as _;
 use unicode_width::UnicodeWidthStr as _;
@@ -59,7 +61,7 @@ impl<TermT> Ui for SimpleUi<TermT>
 where
     TermT: Terminal,
 {
-    fn run(&mut self, recv: Receiver<UiMessage>) -> Result<()> {
+    fn run(&mut self, recv: Receiver<UiMessage>, _requests: Sender<UiRequest>) -> Result<()> {
         match &mut self.prog_impl {
             ProgressImpl::TestListingProgress(p) => run_simple_ui(p, recv, self.stdout_is_tty),
             ProgressImpl::TestListingProgressNoSpinner(p) => {
@@ -82,6 +82,7 @@ macro_rules! completed {
                 stdout: maelstrom_base::JobOutputResult::None,
                 stderr: maelstrom_base::JobOutputResult::None,
                 duration: std::time::Duration::from_secs(1),
+                captured_files: vec![],
             }
         }
     };
@@ -92,6 +93,7 @@ macro_rules! completed {
                 stdout: maelstrom_base::JobOutputResult::None,
                 stderr: maelstrom_base::JobOutputResult::None,
                 duration: std::time::Duration::from_secs(1),
+                captured_files: vec![],
             }
         }
     };
@@ -102,6 +104,7 @@ macro_rules! completed {
                 stdout: maelstrom_base::JobOutputResult::None,
                 stderr: maelstrom_base::JobOutputResult::None,
                 duration: std::time::Duration::from_secs(1),
+                captured_files: vec![],
             }
         }
     };
@@ -112,6 +115,7 @@ macro_rules! completed {
                 stdout: maelstrom_base::JobOutputResult::None,
                 stderr: maelstrom_base::JobOutputResult::None,
                 duration: std::time::Duration::from_secs(1),
+                captured_files: vec![],
             }
         }
     };
@@ -3,6 +3,7 @@ pub use crate::fs::{GetPath, Metadata};
 use anyhow::{Context as _, Result};
 use fs2::FileExt as _;
 use futures_lite::stream::StreamExt;
+use maelstrom_base::manifest::ExtendedAttributes;
 use std::{
     ffi::OsString,
     io,
@@ -173,6 +174,16 @@ impl Fs {
         })
     }
 
+    pub async fn symlink_extended_attributes<P: AsRef<Path>>(
+        &self,
+        path: P,
+    ) -> Result<ExtendedAttributes> {
+        let path = path.as_ref().to_owned();
+        tokio::task::spawn_blocking(move || crate::fs::Fs.symlink_extended_attributes(path))
+            .await
+            .unwrap()
+    }
+
     pub async fn rename<P: AsRef<Path>, Q: AsRef<Path>>(&self, from: P, to: Q) -> Result<()> {
         fs_trampoline!(tokio::fs::rename, from, to)
     }
@@ -5,12 +5,14 @@ pub mod fs;
 use crate::{
     config::common::CacheSize,
     heap::{Heap, HeapDeps, HeapIndex},
+    metrics::{Encoder, MetricType},
     root::{Root, RootBuf},
 };
-use anyhow::{bail, Error, Result};
+use anyhow::{anyhow, bail, Error, Result};
 use bytesize::ByteSize;
 use derive_more::{Debug, Deref, DerefMut};
 use fs::{FileType, Fs, Metadata};
+use itertools::Itertools as _;
 use maelstrom_base::{JobId, Sha256Digest};
 use slog::{debug, info, warn, Logger};
 use std::{
@@ -23,7 +25,7 @@ use std::{
     num::NonZeroU32,
     path::{Path, PathBuf},
     result,
-    str::FromStr as _,
+    str::{self, FromStr as _},
     string::ToString,
 };
 
@@ -32,6 +34,7 @@ const CACHEDIR_TAG_CONTENTS: [u8; 43] = *b"Signature: 8a477f597d28d172789f068868
 const CACHEDIR_TAG_CONTENTS_LEN: usize = CACHEDIR_TAG_CONTENTS.len();
 const CACHEDIR_TAG_CONTENTS_LEN_U64: u64 = CACHEDIR_TAG_CONTENTS_LEN as u64;
 const LOCK_FILE: &str = "lock";
+const PINNED: &str = "pinned";
 const REMOVING: &str = "removing";
 const SHA256: &str = "sha256";
 
@@ -250,6 +253,73 @@ impl<KeyT: Key, GetStrategyT: GetStrategy> HeapDeps for Map<KeyT, GetStrategyT>
     }
 }
 
+/// A snapshot of a [`Cache`]'s activity, as returned by [`Cache::metrics`]. The counters are
+/// cumulative since the cache was created, while the remaining fields describe its current state.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct CacheMetrics {
+    /// The number of [`Cache::get_artifact`] calls that found the artifact already in the cache.
+    pub hits: u64,
+    /// The number of [`Cache::get_artifact`] calls that had to wait for the artifact to be
+    /// fetched.
+    pub misses: u64,
+    /// The number of entries removed to bring the cache back under its target size.
+    pub evictions: u64,
+    /// The total size of the entries counted in `evictions`.
+    pub evicted_bytes: u64,
+    /// The number of entries currently in the cache, not counting those being fetched.
+    pub entries: u64,
+    /// The number of bytes currently used by entries in the cache.
+    pub bytes_used: u64,
+    /// The part of `bytes_used` taken up by entries that are referenced, and so can't be evicted.
+    pub bytes_in_use: u64,
+    /// The goal on-disk size for the cache.
+    pub bytes_used_target: u64,
+}
+
+impl CacheMetrics {
+    /// Encode these metrics as metric families whose names all start with `prefix`.
+    pub fn encode(&self, encoder: &mut Encoder, prefix: &str) {
+        let lookups = format!("{prefix}_cache_lookups_total");
+        encoder.header(
+            &lookups,
+            "Number of artifact lookups in the cache, by result.",
+            MetricType::Counter,
+        );
+        encoder.sample(&lookups, &[("result", "hit")], self.hits);
+        encoder.sample(&lookups, &[("result", "miss")], self.misses);
+        encoder.counter(
+            &format!("{prefix}_cache_evictions_total"),
+            "Number of entries evicted from the cache.",
+            self.evictions,
+        );
+        encoder.counter(
+            &format!("{prefix}_cache_evicted_bytes_total"),
+            "Number of bytes evicted from the cache.",
+            self.evicted_bytes,
+        );
+        encoder.gauge(
+            &format!("{prefix}_cache_entries"),
+            "Number of entries in the cache.",
+            self.entries,
+        );
+        encoder.gauge(
+            &format!("{prefix}_cache_bytes_used"),
+            "Number of bytes used by entries in the cache.",
+            self.bytes_used,
+        );
+        encoder.gauge(
+            &format!("{prefix}_cache_bytes_in_use"),
+            "Number of bytes used by entries in the cache that are referenced.",
+            self.bytes_in_use,
+        );
+        encoder.gauge(
+            &format!("{prefix}_cache_bytes_used_target"),
+            "Target size of the cache in bytes.",
+            self.bytes_used_target,
+        );
+    }
+}
+
 #[derive(Clone)]
 pub struct TempFileFactory<FsT> {
     fs: FsT,
@@ -266,12 +336,23 @@ impl<FsT: Fs> TempFileFactory<FsT> {
     }
 }
 
+/// Information about an entry in a [`Cache`], as returned by [`Cache::entries`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct EntryInfo<'a, KeyT> {
+    pub key: &'a KeyT,
+    pub file_type: FileType,
+    pub bytes_used: u64,
+    /// Whether the entry's digest has been pinned with [`Cache::pin`].
+    pub pinned: bool,
+}
+
 pub struct CacheDir;
 
 pub struct EntryPath;
 
 struct CachedirTagPath;
 struct LockFilePath;
+struct PinnedFilePath;
 struct RemovingRoot;
 struct RemovingPath;
 struct TmpDir;
@@ -287,12 +368,16 @@ pub struct Cache<FsT: Fs, KeyT: Key, GetStrategyT: GetStrategy> {
     root: RootBuf<CacheDir>,
     removing: RootBuf<RemovingRoot>,
     sha256: RootBuf<Sha256Dir>,
+    pinned_file: RootBuf<PinnedFilePath>,
+    pinned: HashSet<Sha256Digest>,
     entries: Map<KeyT, GetStrategyT>,
     heap: Heap<Map<KeyT, GetStrategyT>>,
     next_priority: u64,
     bytes_used: u64,
+    bytes_in_use: u64,
     bytes_used_target: u64,
     getting: usize,
+    metrics: CacheMetrics,
     log: Logger,
     _lock_file: FsT::FileLock,
 }
@@ -303,9 +388,11 @@ impl<FsT: Fs, KeyT: Key, GetStrategyT: GetStrategy> Cache<FsT, KeyT, GetStrategy
     /// entries in `{root}/removing` and `{root}/{kind}/sha256` will be removed. That implies that
     /// the [Cache] doesn't currently keep data stored across invocations.
     ///
-    /// `bytes_used_target` is the goal on-disk size for the cache. The cache will periodically grow
-    /// larger than this size, but then shrink back down to this size. Ideally, the cache would use
-    /// this as a hard upper bound, but that's not how it currently works.
+    /// `bytes_used_target` is the goal on-disk size for the cache. Whenever the cache grows larger
+    /// than this size, it removes unused entries until it's back under it. Entries that are in use
+    /// can't be removed, so the cache can stay over this size for as long as they're in use. Users
+    /// that want to treat this size as a hard limit should compare it with the bytes used, as
+    /// reported by [`Self::metrics`], before getting new artifacts.
     pub fn new(
         fs: FsT,
         root: RootBuf<CacheDir>,
@@ -315,6 +402,7 @@ impl<FsT: Fs, KeyT: Key, GetStrategyT: GetStrategy> Cache<FsT, KeyT, GetStrategy
     ) -> Result<(Self, TempFileFactory<FsT>)> {
         let cachedir_tag = root.join::<CachedirTagPath>(CACHEDIR_TAG);
         let lock_file_path = root.join::<LockFilePath>(LOCK_FILE);
+        let pinned_file = root.join::<PinnedFilePath>(PINNED);
         let removing = root.join::<RemovingRoot>(REMOVING);
         let sha256 = root.join::<Sha256Dir>(SHA256);
         let tmp = root.join::<TmpDir>("tmp");
@@ -328,6 +416,10 @@ impl<FsT: Fs, KeyT: Key, GetStrategyT: GetStrategy> Cache<FsT, KeyT, GetStrategy
             bail!("lock file {lock_file_path:?} is held by a different process");
         };
 
+        // Next, read the set of pinned digests, if there is one. These survive even if we clear
+        // the rest of the directory's contents.
+        let pinned = read_pinned_file(&fs, &pinned_file)?;
+
         // Next, see if the `CACHEDIR.TAG` file exists and is correctly formed. We use this to
         // decide if this is a "new style" cache directory that can be re-used across invocations.
         // In the future, if we change the layout of the cache directory, we may need a proper
@@ -346,7 +438,15 @@ impl<FsT: Fs, KeyT: Key, GetStrategyT: GetStrategy> Cache<FsT, KeyT, GetStrategy
 
         let (cache, temp_file_factory) = if preserve_directory_contents {
             Self::new_preserve_directory_contents(
-                fs, lock_file, root, removing, tmp, sha256, size, log,
+                fs,
+                lock_file,
+                root,
+                removing,
+                tmp,
+                sha256,
+                (pinned_file, pinned),
+                size,
+                log,
             )
         } else {
             Self::new_clear_directory_contents(
@@ -357,6 +457,7 @@ impl<FsT: Fs, KeyT: Key, GetStrategyT: GetStrategy> Cache<FsT, KeyT, GetStrategy
                 removing,
                 tmp,
                 sha256,
+                (pinned_file, pinned),
                 size,
                 log,
             )
@@ -388,6 +489,7 @@ impl<FsT: Fs, KeyT: Key, GetStrategyT: GetStrategy> Cache<FsT, KeyT, GetStrategy
         removing: RootBuf<RemovingRoot>,
         tmp: RootBuf<TmpDir>,
         sha256: RootBuf<Sha256Dir>,
+        (pinned_file, pinned): (RootBuf<PinnedFilePath>, HashSet<Sha256Digest>),
         size: CacheSize,
         log: Logger,
     ) -> Result<(Self, TempFileFactory<FsT>)> {
@@ -395,7 +497,7 @@ impl<FsT: Fs, KeyT: Key, GetStrategyT: GetStrategy> Cache<FsT, KeyT, GetStrategy
         ensure_removing_directory(&fs, &root, &removing)?;
 
         // Next, remove everything else in the cache directory.
-        remove_all_from_directory_except(&fs, &removing, &root, [LOCK_FILE, REMOVING])?;
+        remove_all_from_directory_except(&fs, &removing, &root, [LOCK_FILE, REMOVING, PINNED])?;
 
         // Finally, create all of the files all directories that should be there.
         fs.create_file(&cachedir_tag, &CACHEDIR_TAG_CONTENTS)?;
@@ -410,12 +512,16 @@ impl<FsT: Fs, KeyT: Key, GetStrategyT: GetStrategy> Cache<FsT, KeyT, GetStrategy
             root,
             removing,
             sha256,
+            pinned_file,
+            pinned,
             entries: Map::default(),
             heap: Heap::default(),
             getting: 0,
             next_priority: 0,
             bytes_used: 0,
+            bytes_in_use: 0,
             bytes_used_target: size.into(),
+            metrics: CacheMetrics::default(),
             log,
             _lock_file: lock_file,
         };
@@ -431,6 +537,7 @@ impl<FsT: Fs, KeyT: Key, GetStrategyT: GetStrategy> Cache<FsT, KeyT, GetStrategy
         removing: RootBuf<RemovingRoot>,
         tmp: RootBuf<TmpDir>,
         sha256: RootBuf<Sha256Dir>,
+        (pinned_file, pinned): (RootBuf<PinnedFilePath>, HashSet<Sha256Digest>),
         size: CacheSize,
         log: Logger,
     ) -> Result<(Self, TempFileFactory<FsT>)> {
@@ -442,7 +549,7 @@ impl<FsT: Fs, KeyT: Key, GetStrategyT: GetStrategy> Cache<FsT, KeyT, GetStrategy
             &fs,
             &removing,
             &root,
-            [LOCK_FILE, REMOVING, SHA256, CACHEDIR_TAG],
+            [LOCK_FILE, REMOVING, SHA256, CACHEDIR_TAG, PINNED],
         )?;
 
         // Next, create the sha256 and tmp directories.
@@ -468,18 +575,15 @@ impl<FsT: Fs, KeyT: Key, GetStrategyT: GetStrategy> Cache<FsT, KeyT, GetStrategy
                 let (name, metadata) = entry?;
                 match cache_file_name_type(&fs, &kind_dir, &name, metadata) {
                     CacheFileNameType::File(digest, size) => {
-                        let key = KeyT::from_kind_and_digest(kind, digest);
-                        entries.insert(
-                            key.clone(),
-                            Entry::InHeap {
-                                file_type: metadata.type_,
-                                bytes_used: metadata.size,
-                                priority: next_priority,
-                                heap_index: Default::default(),
-                            },
+                        insert_existing_entry(
+                            &mut entries,
+                            &mut heap,
+                            &mut next_priority,
+                            &pinned,
+                            KeyT::from_kind_and_digest(kind, digest),
+                            metadata.type_,
+                            size,
                         );
-                        heap.push(&mut entries, key);
-                        next_priority = next_priority.checked_add(1).unwrap();
                         bytes_used = bytes_used.checked_add(size).unwrap();
                     }
                     CacheFileNameType::Directory(digest) => {
@@ -502,18 +606,15 @@ impl<FsT: Fs, KeyT: Key, GetStrategyT: GetStrategy> Cache<FsT, KeyT, GetStrategy
             for (digest, entry) in directory_sizes {
                 match entry {
                     (true, Some(size)) => {
-                        let key = KeyT::from_kind_and_digest(kind, digest);
-                        entries.insert(
-                            key.clone(),
-                            Entry::InHeap {
-                                file_type: FileType::Directory,
-                                bytes_used: size,
-                                priority: next_priority,
-                                heap_index: Default::default(),
-                            },
+                        insert_existing_entry(
+                            &mut entries,
+                            &mut heap,
+                            &mut next_priority,
+                            &pinned,
+                            KeyT::from_kind_and_digest(kind, digest),
+                            FileType::Directory,
+                            size,
                         );
-                        heap.push(&mut entries, key);
-                        next_priority = next_priority.checked_add(1).unwrap();
                         bytes_used = bytes_used.checked_add(size).unwrap();
                     }
                     (false, Some(_)) => {
@@ -529,17 +630,30 @@ impl<FsT: Fs, KeyT: Key, GetStrategyT: GetStrategy> Cache<FsT, KeyT, GetStrategy
             }
         }
 
+        // Pinned entries start out referenced.
+        let bytes_in_use = entries
+            .values()
+            .filter_map(|entry| match entry {
+                Entry::InUse { bytes_used, .. } => Some(*bytes_used),
+                _ => None,
+            })
+            .sum();
+
         let mut cache = Cache {
             fs: fs.clone(),
             root,
             removing,
             sha256,
+            pinned_file,
+            pinned,
             entries,
             heap,
             getting: 0,
             next_priority,
             bytes_used,
+            bytes_in_use,
             bytes_used_target: size.into(),
+            metrics: CacheMetrics::default(),
             log,
             _lock_file: lock_file,
         };
@@ -559,12 +673,14 @@ impl<FsT: Fs, KeyT: Key, GetStrategyT: GetStrategy> Cache<FsT, KeyT, GetStrategy
                     getters: HashSet::from([GetStrategyT::getter_from_job_id(jid)]),
                 });
                 self.getting = self.getting.checked_add(1).unwrap();
+                self.metrics.misses += 1;
                 GetArtifact::Get
             }
             HashEntry::Occupied(entry) => {
                 let entry = entry.into_mut();
                 match entry {
                     Entry::Getting { jobs, getters } => {
+                        self.metrics.misses += 1;
                         jobs.push(jid);
                         let getter = GetStrategyT::getter_from_job_id(jid);
                         if getters.insert(getter) {
@@ -575,6 +691,7 @@ impl<FsT: Fs, KeyT: Key, GetStrategyT: GetStrategy> Cache<FsT, KeyT, GetStrategy
                     }
                     Entry::InUse { ref_count, .. } => {
                         *ref_count = ref_count.checked_add(1).unwrap();
+                        self.metrics.hits += 1;
                         GetArtifact::Success
                     }
                     Entry::InHeap {
@@ -584,12 +701,14 @@ impl<FsT: Fs, KeyT: Key, GetStrategyT: GetStrategy> Cache<FsT, KeyT, GetStrategy
                         ..
                     } => {
                         let heap_index = *heap_index;
+                        self.bytes_in_use = self.bytes_in_use.checked_add(*bytes_used).unwrap();
                         *entry = Entry::InUse {
                             file_type: *file_type,
                             ref_count: NonZeroU32::new(1).unwrap(),
                             bytes_used: *bytes_used,
                         };
                         self.heap.remove(&mut self.entries, heap_index);
+                        self.metrics.hits += 1;
                         GetArtifact::Success
                     }
                 }
@@ -699,8 +818,11 @@ impl<FsT: Fs, KeyT: Key, GetStrategyT: GetStrategy> Cache<FsT, KeyT, GetStrategy
             Ok((file_type, bytes_used)) => (file_type, bytes_used),
         };
 
-        // Reference count must be > 0 since we don't allow cancellation of gets.
-        let ref_count = NonZeroU32::new(jobs.len().try_into().unwrap()).unwrap();
+        // Reference count must be > 0 since we don't allow cancellation of gets. Pinned entries
+        // hold one extra reference so that they never make it into the heap.
+        let jobs_len: u32 = jobs.len().try_into().unwrap();
+        let pins = u32::from(self.pinned.contains(key.digest()));
+        let ref_count = NonZeroU32::new(jobs_len.checked_add(pins).unwrap()).unwrap();
         let jobs = mem::take(jobs);
         *entry = Entry::InUse {
             file_type,
@@ -709,6 +831,7 @@ impl<FsT: Fs, KeyT: Key, GetStrategyT: GetStrategy> Cache<FsT, KeyT, GetStrategy
         };
         self.getting = self.getting.checked_sub(1).unwrap();
         self.bytes_used = self.bytes_used.checked_add(bytes_used).unwrap();
+        self.bytes_in_use = self.bytes_in_use.checked_add(bytes_used).unwrap();
         debug!(self.log, "cache added artifact";
             "key" => ?key,
             "artifact_bytes_used" => %ByteSize::b(bytes_used),
@@ -739,6 +862,52 @@ impl<FsT: Fs, KeyT: Key, GetStrategyT: GetStrategy> Cache<FsT, KeyT, GetStrategy
         }
     }
 
+    /// If the artifact is in the cache, whether or not anyone is currently using it, take a
+    /// reference count on it and return its size. Unlike [`Self::get_artifact`], this never
+    /// results in the artifact being fetched, and it isn't tied to a job. The reference count must
+    /// be released with [`Self::decrement_ref_count`].
+    #[must_use]
+    pub fn try_get_artifact(&mut self, key: &KeyT) -> Option<u64> {
+        let entry = self.entries.get_mut(key)?;
+        match entry {
+            Entry::Getting { .. } => None,
+            Entry::InUse {
+                ref_count,
+                bytes_used,
+                ..
+            } => {
+                *ref_count = ref_count.checked_add(1).unwrap();
+                Some(*bytes_used)
+            }
+            Entry::InHeap {
+                file_type,
+                bytes_used,
+                heap_index,
+                ..
+            } => {
+                let heap_index = *heap_index;
+                let bytes_used = *bytes_used;
+                *entry = Entry::InUse {
+                    file_type: *file_type,
+                    ref_count: NonZeroU32::new(1).unwrap(),
+                    bytes_used,
+                };
+                self.heap.remove(&mut self.entries, heap_index);
+                self.bytes_in_use = self.bytes_in_use.checked_add(bytes_used).unwrap();
+                Some(bytes_used)
+            }
+        }
+    }
+
+    /// Return whether the artifact is in the cache. This doesn't take a reference count, so the
+    /// artifact may be removed at any time if nobody is using it.
+    pub fn contains(&self, key: &KeyT) -> bool {
+        matches!(
+            self.entries.get(key),
+            Some(Entry::InUse { .. } | Entry::InHeap { .. })
+        )
+    }
+
     /// Assuming that a reference count is already is already held for the artifact, return the
     /// size of the artifact, otherwise return None.
     #[must_use]
@@ -767,13 +936,16 @@ impl<FsT: Fs, KeyT: Key, GetStrategyT: GetStrategy> Cache<FsT, KeyT, GetStrategy
         match NonZeroU32::new(ref_count.get() - 1) {
             Some(new_ref_count) => *ref_count = new_ref_count,
             None => {
+                let file_type = *file_type;
+                self.bytes_in_use = self.bytes_in_use.checked_sub(*bytes_used).unwrap();
                 *entry = Entry::InHeap {
-                    file_type: *file_type,
+                    file_type,
                     bytes_used: *bytes_used,
                     priority: self.next_priority,
                     heap_index: HeapIndex::default(),
                 };
                 self.heap.push(&mut self.entries, key.clone());
+                self.record_last_use_ignore_error(key, file_type);
                 self.next_priority = self.next_priority.checked_add(1).unwrap();
                 self.possibly_remove_some_ignore_error();
             }
@@ -804,6 +976,149 @@ impl<FsT: Fs, KeyT: Key, GetStrategyT: GetStrategy> Cache<FsT, KeyT, GetStrategy
         cache_file_name(self.sha256.join(key.kind()).as_root(), key.digest())
     }
 
+    /// Return a snapshot of the cache's activity and current size.
+    pub fn metrics(&self) -> CacheMetrics {
+        CacheMetrics {
+            entries: (self.entries.len() - self.getting) as u64,
+            bytes_used: self.bytes_used,
+            bytes_in_use: self.bytes_in_use,
+            bytes_used_target: self.bytes_used_target,
+            ..self.metrics
+        }
+    }
+
+    /// Return information about every entry currently in the cache. Artifacts that are still being
+    /// gotten aren't included.
+    pub fn entries(&self) -> impl Iterator<Item = EntryInfo<'_, KeyT>> {
+        self.entries.iter().filter_map(|(key, entry)| {
+            let (file_type, bytes_used) = match entry {
+                Entry::Getting { .. } => return None,
+                Entry::InUse {
+                    file_type,
+                    bytes_used,
+                    ..
+                }
+                | Entry::InHeap {
+                    file_type,
+                    bytes_used,
+                    ..
+                } => (*file_type, *bytes_used),
+            };
+            Some(EntryInfo {
+                key,
+                file_type,
+                bytes_used,
+                pinned: self.pinned.contains(key.digest()),
+            })
+        })
+    }
+
+    /// Return the path whose modification time records when the entry for `key` was last used.
+    /// This is updated whenever the last job using the entry is done with it. Return `None` if
+    /// there is no such entry, or if it is a symlink, since we don't track last use for those.
+    pub fn last_use_path(&self, key: &KeyT) -> Option<PathBuf> {
+        match self.entries.get(key)? {
+            Entry::Getting { .. } => None,
+            Entry::InUse { file_type, .. } | Entry::InHeap { file_type, .. } => {
+                self.last_use_path_for_file_type(key, *file_type)
+            }
+        }
+    }
+
+    /// Remove the entry for `key` if nobody is using it. Return `false` if there is no such entry,
+    /// or if it is in use or pinned.
+    pub fn remove(&mut self, key: &KeyT) -> Result<bool> {
+        let Some(Entry::InHeap { heap_index, .. }) = self.entries.get(key) else {
+            return Ok(false);
+        };
+        let heap_index = *heap_index;
+        self.heap.remove(&mut self.entries, heap_index);
+        self.remove_entry_from_heap_and_disk(key.clone())?;
+        Ok(true)
+    }
+
+    /// Pin `digest` so that no entry for it, of any kind, is ever removed to reclaim space. Pins
+    /// are stored in the cache directory, so they persist across invocations and also apply to
+    /// artifacts that haven't been gotten yet. Return `false` if `digest` was already pinned.
+    pub fn pin(&mut self, digest: Sha256Digest) -> Result<bool> {
+        if !self.pinned.insert(digest.clone()) {
+            return Ok(false);
+        }
+        if let Err(err) = self.write_pinned_file() {
+            self.pinned.remove(&digest);
+            return Err(err);
+        }
+        for kind in KeyT::kinds() {
+            let _ = self.try_get_artifact(&KeyT::from_kind_and_digest(kind, digest.clone()));
+        }
+        Ok(true)
+    }
+
+    /// Undo a previous [`Self::pin`]. Return `false` if `digest` wasn't pinned.
+    pub fn unpin(&mut self, digest: &Sha256Digest) -> Result<bool> {
+        if !self.pinned.remove(digest) {
+            return Ok(false);
+        }
+        if let Err(err) = self.write_pinned_file() {
+            self.pinned.insert(digest.clone());
+            return Err(err);
+        }
+        for kind in KeyT::kinds() {
+            let key = KeyT::from_kind_and_digest(kind, digest.clone());
+            if let Some(Entry::InUse { .. }) = self.entries.get(&key) {
+                self.decrement_ref_count(&key);
+            }
+        }
+        Ok(true)
+    }
+
+    /// Return the pinned digests. See [`Self::pin`].
+    pub fn pinned(&self) -> impl Iterator<Item = &Sha256Digest> {
+        self.pinned.iter()
+    }
+
+    fn write_pinned_file(&self) -> Result<()> {
+        if self.pinned.is_empty() {
+            if self.fs.metadata(&self.pinned_file)?.is_some() {
+                self.fs.remove(&self.pinned_file)?;
+            }
+            return Ok(());
+        }
+        let contents = self
+            .pinned
+            .iter()
+            .map(|digest| format!("{digest}\n"))
+            .sorted()
+            .collect::<String>();
+        // Write the new file off to the side and then rename it into place so that we never leave
+        // a partially-written file behind.
+        let path = get_unused_removing_path(&self.fs, &self.removing)?;
+        self.fs.create_file(&path, contents.as_bytes())?;
+        self.fs.rename(&path, &self.pinned_file)?;
+        Ok(())
+    }
+
+    fn last_use_path_for_file_type(&self, key: &KeyT, file_type: FileType) -> Option<PathBuf> {
+        let cache_path = self.cache_path(key);
+        match file_type {
+            FileType::File => Some(cache_path.into_path_buf()),
+            FileType::Directory => Some(size_file_name(&cache_path).into_path_buf()),
+            FileType::Symlink | FileType::Other => None,
+        }
+    }
+
+    fn record_last_use_ignore_error(&self, key: &KeyT, file_type: FileType) {
+        let Some(path) = self.last_use_path_for_file_type(key, file_type) else {
+            return;
+        };
+        if let Err(err) = self.fs.set_modified_to_now(&path) {
+            warn!(self.log, "error recording last use of cache entry";
+                "key" => ?key,
+                "error" => %err
+            );
+        }
+    }
+
     /// Check to see if the cache is over its goal size, and if so, try to remove the least
     /// recently used artifacts.
     fn possibly_remove_some_ignore_error(&mut self) {
@@ -817,32 +1132,40 @@ impl<FsT: Fs, KeyT: Key, GetStrategyT: GetStrategy> Cache<FsT, KeyT, GetStrategy
             let Some(key) = self.heap.pop(&mut self.entries) else {
                 break;
             };
-            let Some(Entry::InHeap {
-                file_type,
-                bytes_used,
-                ..
-            }) = self.entries.remove(&key)
-            else {
-                panic!("Entry popped off of heap was in unexpected state");
-            };
-            let cache_path = self.cache_path(&key);
-            if file_type == FileType::Directory {
-                rmdir_in_background(&self.fs, &self.removing, &cache_path)?;
-                self.fs.remove(&size_file_name(&cache_path))?;
-            } else {
-                self.fs.remove(&cache_path)?;
-            }
-            self.bytes_used = self.bytes_used.checked_sub(bytes_used).unwrap();
-            debug!(self.log, "cache removed unused artifact";
-                "key" => ?key,
-                "artifact_bytes_used" => %ByteSize::b(bytes_used),
-                "entries" => %(self.entries.len() - self.getting),
-                "bytes_used" => %ByteSize::b(self.bytes_used),
-                "byte_used_target" => %ByteSize::b(self.bytes_used_target)
-            );
+            self.remove_entry_from_heap_and_disk(key)?;
         }
         Ok(())
     }
+
+    /// Remove an entry that has just been taken out of the heap, along with its files.
+    fn remove_entry_from_heap_and_disk(&mut self, key: KeyT) -> Result<()> {
+        let Some(Entry::InHeap {
+            file_type,
+            bytes_used,
+            ..
+        }) = self.entries.remove(&key)
+        else {
+            panic!("Entry popped off of heap was in unexpected state");
+        };
+        let cache_path = self.cache_path(&key);
+        if file_type == FileType::Directory {
+            rmdir_in_background(&self.fs, &self.removing, &cache_path)?;
+            self.fs.remove(&size_file_name(&cache_path))?;
+        } else {
+            self.fs.remove(&cache_path)?;
+        }
+        self.bytes_used = self.bytes_used.checked_sub(bytes_used).unwrap();
+        self.metrics.evictions += 1;
+        self.metrics.evicted_bytes += bytes_used;
+        debug!(self.log, "cache removed unused artifact";
+            "key" => ?key,
+            "artifact_bytes_used" => %ByteSize::b(bytes_used),
+            "entries" => %(self.entries.len() - self.getting),
+            "bytes_used" => %ByteSize::b(self.bytes_used),
+            "byte_used_target" => %ByteSize::b(self.bytes_used_target)
+        );
+        Ok(())
+    }
 }
 
 enum CacheFileNameType {
@@ -985,6 +1308,64 @@ fn ensure_removing_directory(
     Ok(())
 }
 
+/// Add an entry found in the cache directory on start-up. Pinned entries are given a reference
+/// count so that they're never removed, while everything else goes into the heap.
+fn insert_existing_entry<KeyT: Key, GetStrategyT: GetStrategy>(
+    entries: &mut Map<KeyT, GetStrategyT>,
+    heap: &mut Heap<Map<KeyT, GetStrategyT>>,
+    next_priority: &mut u64,
+    pinned: &HashSet<Sha256Digest>,
+    key: KeyT,
+    file_type: FileType,
+    bytes_used: u64,
+) {
+    if pinned.contains(key.digest()) {
+        entries.insert(
+            key,
+            Entry::InUse {
+                file_type,
+                bytes_used,
+                ref_count: NonZeroU32::new(1).unwrap(),
+            },
+        );
+    } else {
+        entries.insert(
+            key.clone(),
+            Entry::InHeap {
+                file_type,
+                bytes_used,
+                priority: *next_priority,
+                heap_index: Default::default(),
+            },
+        );
+        heap.push(entries, key);
+        *next_priority = next_priority.checked_add(1).unwrap();
+    }
+}
+
+/// Read the set of pinned digests, one per line, from `path`. If there's no file, nothing is
+/// pinned.
+fn read_pinned_file(fs: &impl Fs, path: &Root<PinnedFilePath>) -> Result<HashSet<Sha256Digest>> {
+    let Some(metadata) = fs.metadata(path)? else {
+        return Ok(HashSet::new());
+    };
+    if !metadata.is_file() {
+        bail!("pinned file {path:?} isn't a file");
+    }
+    let mut contents = vec![0u8; metadata.size.try_into()?];
+    let bytes_read = fs.read_file(path, &mut contents)?;
+    contents.truncate(bytes_read);
+    str::from_utf8(&contents)?
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            Sha256Digest::from_str(line)
+                .map_err(|err| anyhow!("invalid digest {line:?} in pinned file {path:?}: {err}"))
+        })
+        .collect()
+}
+
 fn kind_dir(sha256: &Root<Sha256Dir>, kind: &'static str) -> RootBuf<KindDir> {
     sha256.join(kind)
 }
@@ -1208,9 +1589,33 @@ mod tests {
             assert_eq!(self.cache.try_increment_ref_count(&key), expected);
         }
 
+        fn try_get_artifact(&mut self, key: TestKey, expected: Option<u64>) {
+            assert_eq!(self.cache.try_get_artifact(&key), expected);
+        }
+
+        #[track_caller]
+        fn contains(&self, key: TestKey, expected: bool) {
+            assert_eq!(self.cache.contains(&key), expected);
+        }
+
         fn decrement_ref_count(&mut self, key: TestKey) {
             self.cache.decrement_ref_count(&key);
         }
+
+        #[track_caller]
+        fn remove(&mut self, key: TestKey, expected: bool) {
+            assert_eq!(self.cache.remove(&key).unwrap(), expected);
+        }
+
+        #[track_caller]
+        fn pin(&mut self, digest: Sha256Digest, expected: bool) {
+            assert_eq!(self.cache.pin(digest).unwrap(), expected);
+        }
+
+        #[track_caller]
+        fn unpin(&mut self, digest: Sha256Digest, expected: bool) {
+            assert_eq!(self.cache.unpin(&digest).unwrap(), expected);
+        }
     }
 
     #[test]
@@ -1446,6 +1851,63 @@ mod tests {
         fixture.get_artifact(apple!(42), jid!(1), GetArtifact::Get);
     }
 
+    #[test]
+    fn metrics() {
+        let mut fixture = Fixture::new(5, fs! {});
+
+        fixture.get_artifact(apple!(1), jid!(1), GetArtifact::Get);
+        fixture.get_artifact(apple!(1), jid!(2), GetArtifact::Wait);
+        fixture.got_artifact_success_file(apple!(1), b"abc", vec![jid!(1), jid!(2)]);
+        fixture.get_artifact(apple!(1), jid!(3), GetArtifact::Success);
+        assert_eq!(
+            fixture.cache.metrics(),
+            CacheMetrics {
+                hits: 1,
+                misses: 2,
+                evictions: 0,
+                evicted_bytes: 0,
+                entries: 1,
+                bytes_used: 3,
+                bytes_in_use: 3,
+                bytes_used_target: 5,
+            }
+        );
+
+        fixture.get_artifact(apple!(2), jid!(4), GetArtifact::Get);
+        fixture.got_artifact_success_file(apple!(2), b"def", vec![jid!(4)]);
+        for _ in 0..3 {
+            fixture.decrement_ref_count(apple!(1));
+        }
+        assert_eq!(
+            fixture.cache.metrics(),
+            CacheMetrics {
+                hits: 1,
+                misses: 3,
+                evictions: 1,
+                evicted_bytes: 3,
+                entries: 1,
+                bytes_used: 3,
+                bytes_in_use: 3,
+                bytes_used_target: 5,
+            }
+        );
+
+        fixture.decrement_ref_count(apple!(2));
+        assert_eq!(
+            fixture.cache.metrics(),
+            CacheMetrics {
+                hits: 1,
+                misses: 3,
+                evictions: 1,
+                evicted_bytes: 3,
+                entries: 1,
+                bytes_used: 3,
+                bytes_in_use: 0,
+                bytes_used_target: 5,
+            }
+        );
+    }
+
     #[test]
     fn different_key_kinds_are_independent() {
         let mut fixture = Fixture::new(1, fs! {});
@@ -1608,6 +2070,56 @@ mod tests {
         fixture.assert_bytes_used(10);
     }
 
+    #[test]
+    fn try_get_artifact() {
+        let mut fixture = Fixture::new(10, fs! {});
+
+        fixture.get_artifact(apple!(2), jid!(1), GetArtifact::Get);
+
+        fixture.get_artifact(apple!(3), jid!(1), GetArtifact::Get);
+        fixture.got_artifact_success_file(apple!(3), b"abc", vec![jid!(1)]);
+        fixture.decrement_ref_count(apple!(3));
+
+        fixture.get_artifact(apple!(4), jid!(1), GetArtifact::Get);
+        fixture.got_artifact_success_file(apple!(4), b"def", vec![jid!(1)]);
+
+        fixture.try_get_artifact(apple!(1), None);
+        fixture.try_get_artifact(apple!(2), None);
+        fixture.try_get_artifact(apple!(3), Some(3));
+        fixture.try_get_artifact(apple!(4), Some(3));
+
+        // Neither of the artifacts we have references to can be removed.
+        fixture.get_artifact(apple!(5), jid!(1), GetArtifact::Get);
+        fixture.got_artifact_success_file(apple!(5), b"0123456789", vec![jid!(1)]);
+        fixture.assert_bytes_used(16);
+
+        fixture.decrement_ref_count(apple!(3));
+        fixture.assert_bytes_used(13);
+        fixture.try_get_artifact(apple!(3), None);
+
+        fixture.decrement_ref_count(apple!(4));
+        fixture.assert_bytes_used(13);
+
+        fixture.decrement_ref_count(apple!(4));
+        fixture.assert_bytes_used(10);
+    }
+
+    #[test]
+    fn contains() {
+        let mut fixture = Fixture::new(10, fs! {});
+
+        fixture.get_artifact(apple!(1), jid!(1), GetArtifact::Get);
+        fixture.contains(apple!(1), false);
+
+        fixture.got_artifact_success_file(apple!(1), b"abc", vec![jid!(1)]);
+        fixture.contains(apple!(1), true);
+
+        fixture.decrement_ref_count(apple!(1));
+        fixture.contains(apple!(1), true);
+
+        fixture.contains(apple!(2), false);
+    }
+
     #[test]
     fn got_artifact_failure_no_entry() {
         let mut fixture = Fixture::new(1, fs! {});
@@ -2423,4 +2935,276 @@ mod tests {
             "lock file \"/z/lock\" is held by a different process"
         );
     }
+
+    #[test]
+    fn entries() {
+        let mut fixture = Fixture::new(10, fs! {});
+
+        fixture.get_artifact(apple!(1), jid!(1), GetArtifact::Get);
+        fixture.got_artifact_success_file(apple!(1), b"a", vec![jid!(1)]);
+        fixture.get_artifact(orange!(2), jid!(1), GetArtifact::Get);
+        fixture.got_artifact_success_file(orange!(2), b"bb", vec![jid!(1)]);
+        fixture.decrement_ref_count(orange!(2));
+        fixture.get_artifact(apple!(3), jid!(1), GetArtifact::Get);
+        fixture.pin(digest!(2), true);
+
+        let mut entries = fixture.cache.entries().collect_vec();
+        entries.sort_by_key(|entry| entry.bytes_used);
+        assert_eq!(
+            entries,
+            vec![
+                EntryInfo {
+                    key: &apple!(1),
+                    file_type: FileType::File,
+                    bytes_used: 1,
+                    pinned: false,
+                },
+                EntryInfo {
+                    key: &orange!(2),
+                    file_type: FileType::File,
+                    bytes_used: 2,
+                    pinned: true,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn last_use_path() {
+        let mut fixture = Fixture::new(10, fs! {});
+
+        fixture.get_artifact(apple!(1), jid!(1), GetArtifact::Get);
+        fixture.got_artifact_success_file(apple!(1), b"a", vec![jid!(1)]);
+        fixture.get_artifact(apple!(2), jid!(1), GetArtifact::Get);
+        fixture.got_artifact_success_directory(apple!(2), fs! {}, 1, vec![jid!(1)]);
+        fixture.get_artifact(apple!(3), jid!(1), GetArtifact::Get);
+        fixture.got_artifact_success_symlink(apple!(3), "/target", vec![jid!(1)]);
+        fixture.get_artifact(apple!(4), jid!(1), GetArtifact::Get);
+
+        assert_eq!(
+            fixture.cache.last_use_path(&apple!(1)),
+            Some(fixture.cache.cache_path(&apple!(1)).into_path_buf()),
+        );
+        assert_eq!(
+            fixture.cache.last_use_path(&apple!(2)),
+            Some(PathBuf::from(
+                "/z/sha256/apple/0000000000000000000000000000000000000000000000000000000000000002.size"
+            )),
+        );
+        assert_eq!(fixture.cache.last_use_path(&apple!(3)), None);
+        assert_eq!(fixture.cache.last_use_path(&apple!(4)), None);
+        assert_eq!(fixture.cache.last_use_path(&apple!(5)), None);
+    }
+
+    #[test]
+    fn remove() {
+        let mut fixture = Fixture::new(10, fs! {});
+
+        fixture.get_artifact(apple!(1), jid!(1), GetArtifact::Get);
+        fixture.got_artifact_success_file(apple!(1), b"a", vec![jid!(1)]);
+        fixture.get_artifact(apple!(2), jid!(1), GetArtifact::Get);
+        fixture.got_artifact_success_directory(apple!(2), fs! { foo(b"bar") }, 3, vec![jid!(1)]);
+        fixture.decrement_ref_count(apple!(2));
+        fixture.get_artifact(apple!(3), jid!(1), GetArtifact::Get);
+        fixture.assert_bytes_used(4);
+
+        fixture.remove(apple!(1), false);
+        fixture.remove(apple!(3), false);
+        fixture.remove(apple!(4), false);
+        fixture.remove(apple!(2), true);
+        fixture.remove(apple!(2), false);
+
+        fixture.assert_bytes_used(1);
+        fixture.assert_file_exists(apple!(1), Metadata::file(1));
+        fixture.assert_file_does_not_exist(apple!(2));
+        fixture.assert_pending_recursive_rmdirs(["/z/removing/0000000000000001"]);
+        assert_eq!(fixture.cache.metrics().evictions, 1);
+
+        // The entry for apple!(2) is gone, so this has to get it again.
+        fixture.get_artifact(apple!(2), jid!(1), GetArtifact::Get);
+    }
+
+    #[test]
+    fn pinned_entry_is_not_evicted() {
+        let mut fixture = Fixture::new(1, fs! {});
+
+        fixture.get_artifact(apple!(1), jid!(1), GetArtifact::Get);
+        fixture.got_artifact_success_file(apple!(1), b"a", vec![jid!(1)]);
+        fixture.pin(digest!(1), true);
+        fixture.pin(digest!(1), false);
+        fixture.decrement_ref_count(apple!(1));
+
+        fixture.get_artifact(apple!(2), jid!(1), GetArtifact::Get);
+        fixture.got_artifact_success_file(apple!(2), b"b", vec![jid!(1)]);
+        fixture.decrement_ref_count(apple!(2));
+
+        fixture.assert_file_exists(apple!(1), Metadata::file(1));
+        fixture.assert_file_does_not_exist(apple!(2));
+        fixture.assert_bytes_used(1);
+        fixture.remove(apple!(1), false);
+        fixture.assert_fs_entry(
+            "/z/pinned",
+            Entry::file(b"0000000000000000000000000000000000000000000000000000000000000001\n"),
+        );
+    }
+
+    #[test]
+    fn pin_applies_to_all_kinds_and_future_entries() {
+        let mut fixture = Fixture::new(0, fs! {});
+
+        fixture.pin(digest!(1), true);
+        fixture.get_artifact(apple!(1), jid!(1), GetArtifact::Get);
+        fixture.got_artifact_success_file(apple!(1), b"a", vec![jid!(1)]);
+        fixture.decrement_ref_count(apple!(1));
+        fixture.get_artifact(orange!(1), jid!(1), GetArtifact::Get);
+        fixture.got_artifact_success_file(orange!(1), b"b", vec![jid!(1)]);
+        fixture.decrement_ref_count(orange!(1));
+
+        fixture.assert_file_exists(apple!(1), Metadata::file(1));
+        fixture.assert_file_exists(orange!(1), Metadata::file(1));
+        fixture.assert_bytes_used(2);
+    }
+
+    #[test]
+    fn pin_while_getting() {
+        let mut fixture = Fixture::new(0, fs! {});
+
+        fixture.get_artifact(apple!(1), jid!(1), GetArtifact::Get);
+        fixture.pin(digest!(1), true);
+        fixture.got_artifact_success_file(apple!(1), b"a", vec![jid!(1)]);
+        fixture.decrement_ref_count(apple!(1));
+        fixture.assert_file_exists(apple!(1), Metadata::file(1));
+
+        fixture.unpin(digest!(1), true);
+        fixture.assert_file_does_not_exist(apple!(1));
+        fixture.assert_bytes_used(0);
+    }
+
+    #[test]
+    fn unpin_lets_entry_be_evicted() {
+        let mut fixture = Fixture::new(0, fs! {});
+
+        fixture.get_artifact(apple!(1), jid!(1), GetArtifact::Get);
+        fixture.got_artifact_success_file(apple!(1), b"a", vec![jid!(1)]);
+        fixture.pin(digest!(1), true);
+        fixture.pin(digest!(2), true);
+        fixture.decrement_ref_count(apple!(1));
+        fixture.assert_file_exists(apple!(1), Metadata::file(1));
+
+        fixture.unpin(digest!(1), true);
+        fixture.unpin(digest!(1), false);
+        fixture.assert_file_does_not_exist(apple!(1));
+        fixture.assert_bytes_used(0);
+        fixture.assert_fs_entry(
+            "/z/pinned",
+            Entry::file(b"0000000000000000000000000000000000000000000000000000000000000002\n"),
+        );
+
+        fixture.unpin(digest!(2), true);
+        assert!(fixture
+            .fs
+            .metadata(Path::new("/z/pinned"))
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn unpin_while_in_use() {
+        let mut fixture = Fixture::new(0, fs! {});
+
+        fixture.get_artifact(apple!(1), jid!(1), GetArtifact::Get);
+        fixture.got_artifact_success_file(apple!(1), b"a", vec![jid!(1)]);
+        fixture.pin(digest!(1), true);
+        fixture.unpin(digest!(1), true);
+        fixture.assert_file_exists(apple!(1), Metadata::file(1));
+
+        fixture.decrement_ref_count(apple!(1));
+        fixture.assert_file_does_not_exist(apple!(1));
+    }
+
+    #[test]
+    fn new_with_cachedir_keeps_pinned_entries() {
+        let mut fixture = Fixture::new(
+            0,
+            fs! {
+                z {
+                    "CACHEDIR.TAG"(&CACHEDIR_TAG_CONTENTS),
+                    pinned(b"0000000000000000000000000000000000000000000000000000000000000002\n\n"),
+                    sha256 {
+                        apple {
+                            "0000000000000000000000000000000000000000000000000000000000000001"(b"01"),
+                            "0000000000000000000000000000000000000000000000000000000000000002"(b"02"),
+                        },
+                        orange {
+                            "0000000000000000000000000000000000000000000000000000000000000002" {
+                                foo(b"foo contents"),
+                            },
+                            "0000000000000000000000000000000000000000000000000000000000000002.size"(b"\0\0\0\0\0\0\0\x03"),
+                        },
+                    },
+                },
+            },
+        );
+        fixture.assert_file_does_not_exist(apple!(1));
+        fixture.assert_file_exists(apple!(2), Metadata::file(2));
+        fixture.assert_file_exists(orange!(2), Metadata::directory(1));
+        fixture.assert_bytes_used(5);
+
+        fixture.unpin(digest!(2), true);
+        fixture.assert_file_does_not_exist(apple!(2));
+        fixture.assert_file_does_not_exist(orange!(2));
+        fixture.assert_bytes_used(0);
+    }
+
+    #[test]
+    fn new_without_cachedir_tag_keeps_pinned_file() {
+        let mut fixture = Fixture::new(
+            0,
+            fs! {
+                z {
+                    pinned(b"0000000000000000000000000000000000000000000000000000000000000001\n"),
+                    garbage(b"garbage"),
+                },
+            },
+        );
+        fixture.assert_fs(fs! {
+            z {
+                "CACHEDIR.TAG"(&CACHEDIR_TAG_CONTENTS),
+                "lock"(b""),
+                pinned(b"0000000000000000000000000000000000000000000000000000000000000001\n"),
+                removing {},
+                sha256 {
+                    apple {},
+                    orange {},
+                },
+                tmp {},
+            },
+        });
+
+        fixture.get_artifact(apple!(1), jid!(1), GetArtifact::Get);
+        fixture.got_artifact_success_file(apple!(1), b"a", vec![jid!(1)]);
+        fixture.decrement_ref_count(apple!(1));
+        fixture.assert_file_exists(apple!(1), Metadata::file(1));
+    }
+
+    #[test]
+    fn new_with_invalid_pinned_file() {
+        let fs = test::Fs::new(fs! {
+            z {
+                pinned(b"not a digest\n"),
+            },
+        });
+        let Err(err) = Cache::<test::Fs, TestKey, TestGetStrategy>::new(
+            fs,
+            "/z".parse().unwrap(),
+            ByteSize::b(1).into(),
+            Logger::root(Discard, o!()),
+            false,
+        ) else {
+            panic!("expected error");
+        };
+        assert!(err
+            .to_string()
+            .starts_with("invalid digest \"not a digest\" in pinned file \"/z/pinned\""));
+    }
 }
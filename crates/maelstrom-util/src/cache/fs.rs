@@ -45,6 +45,11 @@ pub trait Fs: Clone {
     /// `path`, but its parent directory must be exist.
     fn create_file(&self, path: &Path, contents: &[u8]) -> Result<(), Self::Error>;
 
+    /// Set the modification time of the file at `path` to now. If `path` resolves to a symlink, it
+    /// will be resolved, recursively, until a file is found or an error occurs. [`Cache`] uses this
+    /// to record when an entry was last used.
+    fn set_modified_to_now(&self, path: &Path) -> Result<(), Self::Error>;
+
     /// Type used to hold an exclusive lock on a file open. The lock should be released when the
     /// `FileLock` is dropped.
     type FileLock;
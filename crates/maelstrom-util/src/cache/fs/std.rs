@@ -8,6 +8,7 @@ use std::{
     os::unix::fs as unix_fs,
     path::{Path, PathBuf},
     thread,
+    time::SystemTime,
 };
 use tempfile::{self, NamedTempFile};
 
@@ -50,6 +51,10 @@ impl super::Fs for Fs {
         File::create_new(path)?.write_all(contents)
     }
 
+    fn set_modified_to_now(&self, path: &Path) -> io::Result<()> {
+        File::open(path)?.set_modified(SystemTime::now())
+    }
+
     type FileLock = FileLock;
 
     fn possibly_create_file_and_try_exclusive_lock(
@@ -172,6 +172,10 @@ impl super::Fs for Fs {
         self.state.borrow_mut().create_file(path, contents)
     }
 
+    fn set_modified_to_now(&self, path: &Path) -> Result<()> {
+        self.state.borrow().set_modified_to_now(path)
+    }
+
     type FileLock = FileLock;
 
     fn possibly_create_file_and_try_exclusive_lock(
@@ -451,6 +455,18 @@ impl State {
         Ok(())
     }
 
+    fn set_modified_to_now(&self, path: &Path) -> Result<()> {
+        // This file system doesn't keep track of modification times, so all there is to do is to
+        // check that there is a file to update.
+        self.check_failure()?;
+        match self.root.lookup_leaf(path)? {
+            FollowSymlinks::FoundDirectory(_, _) => Err(Error::IsDir),
+            FollowSymlinks::DanglingSymlink => Err(Error::NoEnt),
+            FollowSymlinks::FileAncestor => Err(Error::NotDir),
+            FollowSymlinks::FoundFile(_, _) => Ok(()),
+        }
+    }
+
     fn possibly_create_file_and_try_exclusive_lock(
         &mut self,
         path: &Path,
@@ -188,6 +188,46 @@ impl FromStr for InlineLimit {
     }
 }
 
+#[derive(Clone, Copy, Debug, Display, Deserialize, Eq, PartialEq, From, Into)]
+#[debug("{_0:?}")]
+#[display("{_0}")]
+#[serde(transparent)]
+pub struct CaptureLimit(#[serde(with = "bytesize_serde")] ByteSize);
+
+byte_size_u64_from_impls!(CaptureLimit);
+
+impl Default for CaptureLimit {
+    fn default() -> Self {
+        Self(ByteSize::mb(100))
+    }
+}
+
+impl FromStr for CaptureLimit {
+    type Err = StringError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self(
+            <ByteSize as FromStr>::from_str(s).map_err(StringError)?,
+        ))
+    }
+}
+
+#[derive(Clone, Copy, Debug, Display, Deserialize, Eq, PartialEq, From, Into)]
+#[debug("{_0:?}")]
+#[display("{_0}")]
+#[serde(transparent)]
+pub struct MinFreeSpace(#[serde(with = "bytesize_serde")] ByteSize);
+
+byte_size_u64_from_impls!(MinFreeSpace);
+
+impl FromStr for MinFreeSpace {
+    type Err = StringError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self(
+            <ByteSize as FromStr>::from_str(s).map_err(StringError)?,
+        ))
+    }
+}
+
 #[pocket_definition(export)]
 #[derive(Clone, Copy, Debug, Display, Deserialize, Into)]
 #[serde(try_from = "u16")]
@@ -1,9 +1,15 @@
 use anyhow::{Context as _, Result};
 use derive_more::Debug;
 use fs2::FileExt as _;
+use maelstrom_base::manifest::ExtendedAttributes;
+use maelstrom_linux::{self as linux, Errno};
 use std::{
+    ffi::CString,
     io::{self},
-    os::fd::{AsRawFd, RawFd},
+    os::{
+        fd::{AsRawFd, RawFd},
+        unix::ffi::OsStrExt as _,
+    },
     path::{Path, PathBuf},
 };
 
@@ -52,6 +58,52 @@ where
     }
 }
 
+/// Call one of the xattr syscalls that fill a caller-provided buffer. The first call finds out how
+/// big the buffer needs to be, and the second fills it. If the value grows in between, try again.
+fn read_xattr_buffer(
+    mut f: impl FnMut(&mut [u8]) -> Result<usize, Errno>,
+) -> Result<Vec<u8>, Errno> {
+    loop {
+        let size = f(&mut [])?;
+        let mut buffer = vec![0; size];
+        match f(&mut buffer) {
+            Ok(size) => {
+                buffer.truncate(size);
+                return Ok(buffer);
+            }
+            Err(Errno::ERANGE) => continue,
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+/// Read all of the extended attributes of the file at `path`, without following symlinks. File
+/// systems that don't support extended attributes are treated as if the file has none.
+pub(crate) fn symlink_extended_attributes(path: &Path) -> io::Result<ExtendedAttributes> {
+    let path = CString::new(path.as_os_str().as_bytes())?;
+    let names = match read_xattr_buffer(|buffer| linux::llistxattr(&path, buffer)) {
+        Err(Errno::EOPNOTSUPP) => return Ok(ExtendedAttributes::new()),
+        res => res?,
+    };
+    let mut xattrs = ExtendedAttributes::new();
+    for name in names.split(|b| *b == 0).filter(|name| !name.is_empty()) {
+        let c_name = CString::new(name)?;
+        let value = match read_xattr_buffer(|buffer| linux::lgetxattr(&path, &c_name, buffer)) {
+            // The attribute was removed after we listed it.
+            Err(Errno::ENODATA) => continue,
+            res => res?,
+        };
+        let name = String::from_utf8(name.to_vec()).map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                "non-UTF-8 extended attribute name",
+            )
+        })?;
+        xattrs.insert(name, value);
+    }
+    Ok(xattrs)
+}
+
 pub struct Fs;
 
 impl Fs {
@@ -273,6 +325,13 @@ impl Fs {
         })
     }
 
+    pub fn symlink_extended_attributes<P: AsRef<Path>>(
+        &self,
+        path: P,
+    ) -> Result<ExtendedAttributes> {
+        fs_trampoline!(symlink_extended_attributes, path)
+    }
+
     pub fn rename<P: AsRef<Path>, Q: AsRef<Path>>(&self, from: P, to: Q) -> Result<()> {
         fs_trampoline!(std::fs::rename, from, to)
     }
@@ -1,7 +1,7 @@
 //! Useful [`Read`]ers.
 
 use crate::ext::OptionExt as _;
-use byteorder::{BigEndian, ReadBytesExt as _, WriteBytesExt as _};
+use byteorder::{BigEndian, LittleEndian, ReadBytesExt as _, WriteBytesExt as _};
 use lru::LruCache;
 use maelstrom_base::Sha256Digest;
 use maelstrom_linux::{self as linux};
@@ -45,6 +45,84 @@ impl<InnerT: Read> Read for FixedSizeReader<InnerT> {
     }
 }
 
+/// An [`AsyncRead`]er wrapper that returns an error if the inner, wrapped, reader doesn't return
+/// exactly a specific number of bytes. It never reads more than one byte past that number from
+/// the inner reader, so it can be used to bound how much we read from a source we don't trust,
+/// like a decompressor.
+#[pin_project]
+pub struct ExactSizeReader<InnerT> {
+    #[pin]
+    inner: tokio_io::Take<InnerT>,
+    remaining: u64,
+}
+
+impl<InnerT: AsyncRead> ExactSizeReader<InnerT> {
+    pub fn new(inner: InnerT, size: u64) -> Self {
+        Self {
+            inner: tokio_io::AsyncReadExt::take(inner, size.saturating_add(1)),
+            remaining: size,
+        }
+    }
+}
+
+impl<InnerT: AsyncRead> AsyncRead for ExactSizeReader<InnerT> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        dst: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.project();
+        let had_room = dst.remaining() > 0;
+        let start_len = dst.filled().len();
+        ready!(this.inner.poll_read(cx, dst))?;
+        let read = (dst.filled().len() - start_len) as u64;
+        if had_room && read == 0 && *this.remaining > 0 {
+            return Poll::Ready(Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                format!("stream ended {} bytes early", *this.remaining),
+            )));
+        }
+        let Some(remaining) = this.remaining.checked_sub(read) else {
+            // Readers aren't supposed to return any data along with an error.
+            dst.set_filled(start_len);
+            return Poll::Ready(Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "stream is longer than expected",
+            )));
+        };
+        *this.remaining = remaining;
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// The magic number of the zstd skippable frame written by [`write_zstd_size_frame`].
+const ZSTD_SIZE_FRAME_MAGIC: u32 = 0x184D2A50;
+
+/// Write a zstd skippable frame that records `size`, the uncompressed size of the zstd frame that
+/// will follow it. Decoders ignore skippable frames, so the result is still a valid zstd file, but
+/// readers can use [`read_zstd_size_frame`] to find out how much they should get when they
+/// decompress it, before they start.
+pub fn write_zstd_size_frame(mut writer: impl Write, size: u64) -> io::Result<()> {
+    writer.write_u32::<LittleEndian>(ZSTD_SIZE_FRAME_MAGIC)?;
+    writer.write_u32::<LittleEndian>(8)?;
+    writer.write_u64::<LittleEndian>(size)
+}
+
+/// Read the frame written by [`write_zstd_size_frame`] and return the size it records.
+pub async fn read_zstd_size_frame(mut reader: impl AsyncRead + Unpin) -> io::Result<u64> {
+    use tokio_io::AsyncReadExt as _;
+
+    let magic = reader.read_u32_le().await?;
+    let frame_size = reader.read_u32_le().await?;
+    if magic != ZSTD_SIZE_FRAME_MAGIC || frame_size != 8 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "stream doesn't start with a zstd size frame",
+        ));
+    }
+    reader.read_u64_le().await
+}
+
 /// A IO wrapper that computes the SHA-256 digest of the bytes that are read from or written to it.
 pub struct Sha256Stream<InnerT> {
     inner: InnerT,
@@ -940,6 +1018,64 @@ mod tests {
     use super::*;
     use tokio::io::AsyncReadExt as _;
 
+    async fn read_exact_size(input: &[u8], size: u64) -> io::Result<Vec<u8>> {
+        let mut output = vec![];
+        ExactSizeReader::new(input, size)
+            .read_to_end(&mut output)
+            .await?;
+        Ok(output)
+    }
+
+    #[tokio::test]
+    async fn exact_size_reader_right_size() {
+        assert_eq!(read_exact_size(b"hello", 5).await.unwrap(), b"hello");
+        assert_eq!(read_exact_size(b"", 0).await.unwrap(), b"");
+    }
+
+    #[tokio::test]
+    async fn exact_size_reader_too_short() {
+        let err = read_exact_size(b"hello", 6).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[tokio::test]
+    async fn exact_size_reader_too_long() {
+        let err = read_exact_size(b"hello", 4).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[tokio::test]
+    async fn exact_size_reader_reads_at_most_one_extra_byte() {
+        let mut input = &b"hello world"[..];
+        let mut output = vec![];
+        ExactSizeReader::new(&mut input, 4)
+            .read_to_end(&mut output)
+            .await
+            .unwrap_err();
+        assert_eq!(input, b" world");
+    }
+
+    #[tokio::test]
+    async fn zstd_size_frame_write_read() {
+        let mut buffer = vec![];
+        write_zstd_size_frame(&mut buffer, 1234).unwrap();
+        buffer.extend(zstd::encode_all(&b"hello"[..], 0).unwrap());
+
+        // Standard decoders skip the frame.
+        assert_eq!(zstd::decode_all(&buffer[..]).unwrap(), b"hello");
+
+        let mut reader = &buffer[..];
+        assert_eq!(read_zstd_size_frame(&mut reader).await.unwrap(), 1234);
+        assert_eq!(zstd::decode_all(reader).unwrap(), b"hello");
+    }
+
+    #[tokio::test]
+    async fn zstd_size_frame_missing() {
+        let buffer = zstd::encode_all(&b"hello world, hello world"[..], 0).unwrap();
+        let err = read_zstd_size_frame(&buffer[..]).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
     fn calculate_read_hash(mut input: &[u8]) -> Sha256Digest {
         let mut reader = Sha256Stream::new(&mut input);
         std::io::copy(&mut reader, &mut std::io::sink()).unwrap();
@@ -15,6 +15,7 @@ pub mod heap;
 pub mod io;
 pub mod log;
 pub mod manifest;
+pub mod metrics;
 pub mod net;
 pub mod process;
 pub mod root;
@@ -3,12 +3,12 @@ use anyhow::{anyhow, Result};
 use async_trait::async_trait;
 use maelstrom_base::{
     manifest::{
-        ManifestEntry, ManifestEntryData, ManifestEntryMetadata, ManifestFileData, ManifestVersion,
-        Mode, UnixTimestamp,
+        ExtendedAttributes, ManifestEntry, ManifestEntryData, ManifestEntryMetadata,
+        ManifestFileData, ManifestVersion, Mode, UnixTimestamp,
     },
-    proto, Sha256Digest, Utf8PathBuf,
+    proto, GroupId, Sha256Digest, UserId, Utf8PathBuf,
 };
-use serde::{de::DeserializeOwned, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use std::io;
 use std::os::unix::fs::MetadataExt as _;
 use std::path::Path;
@@ -58,21 +58,81 @@ pub fn encode<T: Serialize>(mut stream: impl io::Write, t: &T) -> io::Result<()>
     Ok(())
 }
 
-pub struct ManifestReader<ReadT>(ReadT);
+/// How entries were encoded in [`ManifestVersion::V1`] manifests, before they recorded ownership and
+/// extended attributes.
+#[derive(Deserialize, Serialize)]
+struct ManifestEntryV1 {
+    path: Utf8PathBuf,
+    metadata: ManifestEntryMetadataV1,
+    data: ManifestEntryData,
+}
 
-impl<ReadT: io::Read> ManifestReader<ReadT> {
-    pub fn new(mut r: ReadT) -> io::Result<Self> {
-        let version: ManifestVersion = decode(&mut r)?;
-        if version != ManifestVersion::default() {
-            return Err(io::Error::new(io::ErrorKind::Other, "bad manifest version"));
+#[derive(Deserialize, Serialize)]
+struct ManifestEntryMetadataV1 {
+    size: u64,
+    mode: Mode,
+    mtime: UnixTimestamp,
+}
+
+impl From<ManifestEntryV1> for ManifestEntry {
+    fn from(entry: ManifestEntryV1) -> Self {
+        let ManifestEntryMetadataV1 { size, mode, mtime } = entry.metadata;
+        Self {
+            path: entry.path,
+            metadata: ManifestEntryMetadata {
+                size,
+                mode,
+                mtime,
+                owner: None,
+                xattrs: ExtendedAttributes::new(),
+            },
+            data: entry.data,
         }
+    }
+}
+
+/// We can still read manifests written before the current version, as long as they're at least
+/// [`ManifestVersion::V1`].
+fn check_version(version: ManifestVersion) -> io::Result<ManifestVersion> {
+    match version {
+        ManifestVersion::V0 => Err(io::Error::new(io::ErrorKind::Other, "bad manifest version")),
+        ManifestVersion::V1 | ManifestVersion::V2 => Ok(version),
+    }
+}
 
-        Ok(Self(r))
+fn decode_entry(version: ManifestVersion, stream: impl io::Read) -> io::Result<ManifestEntry> {
+    match version {
+        ManifestVersion::V1 => decode::<ManifestEntryV1>(stream).map(Into::into),
+        _ => decode(stream),
+    }
+}
+
+async fn decode_entry_async(
+    version: ManifestVersion,
+    stream: impl AsyncRead + Unpin,
+) -> io::Result<ManifestEntry> {
+    match version {
+        ManifestVersion::V1 => decode_async::<ManifestEntryV1>(stream)
+            .await
+            .map(Into::into),
+        _ => decode_async(stream).await,
+    }
+}
+
+pub struct ManifestReader<ReadT> {
+    r: ReadT,
+    version: ManifestVersion,
+}
+
+impl<ReadT: io::Read> ManifestReader<ReadT> {
+    pub fn new(mut r: ReadT) -> io::Result<Self> {
+        let version = check_version(decode(&mut r)?)?;
+        Ok(Self { r, version })
     }
 
     fn next_inner(&mut self) -> io::Result<Option<ManifestEntry>> {
-        let mut counter = countio::Counter::new(&mut self.0);
-        match decode(&mut counter) {
+        let mut counter = countio::Counter::new(&mut self.r);
+        match decode_entry(self.version, &mut counter) {
             Ok(entry) => Ok(Some(entry)),
             Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => {
                 if counter.reader_bytes() > 0 {
@@ -97,21 +157,20 @@ impl<ReadT: io::Read> Iterator for ManifestReader<ReadT> {
     }
 }
 
-pub struct AsyncManifestReader<ReadT>(ReadT);
+pub struct AsyncManifestReader<ReadT> {
+    r: ReadT,
+    version: ManifestVersion,
+}
 
 impl<ReadT: AsyncRead + Unpin> AsyncManifestReader<ReadT> {
     pub async fn new(mut r: ReadT) -> io::Result<Self> {
-        let version: ManifestVersion = decode_async(&mut r).await?;
-        if version != ManifestVersion::default() {
-            return Err(io::Error::new(io::ErrorKind::Other, "bad manifest version"));
-        }
-
-        Ok(Self(r))
+        let version = check_version(decode_async(&mut r).await?)?;
+        Ok(Self { r, version })
     }
 
     pub async fn next(&mut self) -> io::Result<Option<ManifestEntry>> {
-        let mut counter = countio::Counter::new(&mut self.0);
-        match decode_async(&mut counter).await {
+        let mut counter = countio::Counter::new(&mut self.r);
+        match decode_entry_async(self.version, &mut counter).await {
             Ok(entry) => Ok(Some(entry)),
             Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => {
                 if counter.reader_bytes() > 0 {
@@ -184,11 +243,17 @@ fn to_utf8_path(path: impl AsRef<Path>) -> Utf8PathBuf {
     path.as_ref().to_owned().try_into().unwrap()
 }
 
-fn convert_metadata(meta: &async_fs::Metadata) -> ManifestEntryMetadata {
+fn convert_metadata(
+    meta: &async_fs::Metadata,
+    xattrs: ExtendedAttributes,
+    preserve_ownership: bool,
+) -> ManifestEntryMetadata {
     ManifestEntryMetadata {
         size: meta.is_file().then(|| meta.size()).unwrap_or(0),
         mode: Mode(meta.mode()),
         mtime: UnixTimestamp(meta.mtime()),
+        owner: preserve_ownership.then(|| (UserId::new(meta.uid()), GroupId::new(meta.gid()))),
+        xattrs,
     }
 }
 
@@ -201,14 +266,18 @@ pub struct ManifestBuilder<'cb, WriteT> {
     fs: Fs,
     writer: AsyncManifestWriter<WriteT>,
     follow_symlinks: bool,
+    preserve_ownership: bool,
     data_upload: Box<dyn DataUpload + 'cb>,
     inline_limit: u64,
 }
 
 impl<'cb, WriteT: AsyncWrite + Unpin> ManifestBuilder<'cb, WriteT> {
+    /// Unless `preserve_ownership` is true, the entries don't record who owns the files, so
+    /// they're owned by whoever runs the job.
     pub async fn new(
         writer: WriteT,
         follow_symlinks: bool,
+        preserve_ownership: bool,
         data_upload: impl DataUpload + 'cb,
         inline_limit: u64,
     ) -> io::Result<Self> {
@@ -217,6 +286,7 @@ impl<'cb, WriteT: AsyncWrite + Unpin> ManifestBuilder<'cb, WriteT> {
             writer: AsyncManifestWriter::new(writer).await?,
             data_upload: Box::new(data_upload),
             follow_symlinks,
+            preserve_ownership,
             inline_limit,
         })
     }
@@ -224,12 +294,13 @@ impl<'cb, WriteT: AsyncWrite + Unpin> ManifestBuilder<'cb, WriteT> {
     async fn add_entry(
         &mut self,
         meta: &async_fs::Metadata,
+        xattrs: ExtendedAttributes,
         path: impl AsRef<Path>,
         data: ManifestEntryData,
     ) -> Result<()> {
         let entry = ManifestEntry {
             path: to_utf8_path(path),
-            metadata: convert_metadata(meta),
+            metadata: convert_metadata(meta, xattrs, self.preserve_ownership),
             data,
         };
         self.writer.write_entry(&entry).await?;
@@ -241,10 +312,17 @@ impl<'cb, WriteT: AsyncWrite + Unpin> ManifestBuilder<'cb, WriteT> {
         source: impl AsRef<Path>,
         dest: impl AsRef<Path>,
     ) -> Result<()> {
-        let meta = if self.follow_symlinks {
-            self.fs.metadata(source.as_ref()).await?
+        let (meta, xattrs) = if self.follow_symlinks {
+            let canonical = self.fs.canonicalize(source.as_ref()).await?;
+            (
+                self.fs.metadata(&canonical).await?,
+                self.fs.symlink_extended_attributes(&canonical).await?,
+            )
         } else {
-            self.fs.symlink_metadata(source.as_ref()).await?
+            (
+                self.fs.symlink_metadata(source.as_ref()).await?,
+                self.fs.symlink_extended_attributes(source.as_ref()).await?,
+            )
         };
         if meta.is_file() {
             let file_size = meta.size();
@@ -255,15 +333,21 @@ impl<'cb, WriteT: AsyncWrite + Unpin> ManifestBuilder<'cb, WriteT> {
             } else {
                 ManifestFileData::Empty
             };
-            self.add_entry(&meta, dest, ManifestEntryData::File(data))
+            self.add_entry(&meta, xattrs, dest, ManifestEntryData::File(data))
                 .await
         } else if meta.is_dir() {
-            self.add_entry(&meta, dest, ManifestEntryData::Directory { opaque: false })
-                .await
+            self.add_entry(
+                &meta,
+                xattrs,
+                dest,
+                ManifestEntryData::Directory { opaque: false },
+            )
+            .await
         } else if meta.is_symlink() {
             let data = self.fs.read_link(source.as_ref()).await?;
             self.add_entry(
                 &meta,
+                xattrs,
                 dest,
                 ManifestEntryData::Symlink(data.into_os_string().into_encoded_bytes()),
             )
@@ -315,6 +399,7 @@ mod tests {
     async fn assert_entry<BuildT>(
         build: BuildT,
         follow_symlinks: bool,
+        preserve_ownership: bool,
         expected_path: &str,
         expected_size: u64,
         data: ManifestEntryData,
@@ -325,9 +410,15 @@ mod tests {
         ) -> Pin<Box<dyn Future<Output = ()> + 'a>>,
     {
         let mut buffer = vec![];
-        let mut builder = ManifestBuilder::new(&mut buffer, follow_symlinks, TestDataUpload, 5)
-            .await
-            .unwrap();
+        let mut builder = ManifestBuilder::new(
+            &mut buffer,
+            follow_symlinks,
+            preserve_ownership,
+            TestDataUpload,
+            5,
+        )
+        .await
+        .unwrap();
 
         let mut fixture = Fixture::new();
         build(&mut fixture, &mut builder).await;
@@ -337,14 +428,29 @@ mod tests {
             .map(|e| e.unwrap())
             .collect();
 
-        let input_meta = if follow_symlinks {
-            fixture.fs.metadata(&fixture.input_path).await.unwrap()
+        let (input_meta, input_xattrs) = if follow_symlinks {
+            let canonical = fixture.fs.canonicalize(&fixture.input_path).await.unwrap();
+            (
+                fixture.fs.metadata(&canonical).await.unwrap(),
+                fixture
+                    .fs
+                    .symlink_extended_attributes(&canonical)
+                    .await
+                    .unwrap(),
+            )
         } else {
-            fixture
-                .fs
-                .symlink_metadata(&fixture.input_path)
-                .await
-                .unwrap()
+            (
+                fixture
+                    .fs
+                    .symlink_metadata(&fixture.input_path)
+                    .await
+                    .unwrap(),
+                fixture
+                    .fs
+                    .symlink_extended_attributes(&fixture.input_path)
+                    .await
+                    .unwrap(),
+            )
         };
         assert_eq!(
             actual_entries,
@@ -352,7 +458,7 @@ mod tests {
                 path: to_utf8_path(expected_path),
                 metadata: ManifestEntryMetadata {
                     size: expected_size,
-                    ..convert_metadata(&input_meta)
+                    ..convert_metadata(&input_meta, input_xattrs, preserve_ownership)
                 },
                 data,
             }]
@@ -376,6 +482,7 @@ mod tests {
                 })
             },
             false, /* follow_symlinks */
+            false, /* preserve_ownership */
             "foo/bar.txt",
             6,
             ManifestEntryData::File(ManifestFileData::Digest(42u64.into())),
@@ -383,6 +490,27 @@ mod tests {
         .await;
     }
 
+    #[tokio::test]
+    async fn builder_preserves_ownership() {
+        assert_entry(
+            |fixture, builder| {
+                Box::pin(async {
+                    fixture.fs.create_dir(&fixture.input_path).await.unwrap();
+                    builder
+                        .add_file(&fixture.input_path, "foo/bar")
+                        .await
+                        .unwrap();
+                })
+            },
+            false, /* follow_symlinks */
+            true,  /* preserve_ownership */
+            "foo/bar",
+            0,
+            ManifestEntryData::Directory { opaque: false },
+        )
+        .await;
+    }
+
     #[tokio::test]
     async fn builder_directory() {
         assert_entry(
@@ -396,6 +524,7 @@ mod tests {
                 })
             },
             false, /* follow_symlinks */
+            false, /* preserve_ownership */
             "foo/bar",
             0,
             ManifestEntryData::Directory { opaque: false },
@@ -420,6 +549,7 @@ mod tests {
                 })
             },
             false, /* follow_symlinks */
+            false, /* preserve_ownership */
             "foo/bar",
             0,
             ManifestEntryData::Symlink(b"../baz".to_vec()),
@@ -445,7 +575,8 @@ mod tests {
                         .unwrap();
                 })
             },
-            true, /* follow_symlinks */
+            true,  /* follow_symlinks */
+            false, /* preserve_ownership */
             "foo/bar.txt",
             6,
             ManifestEntryData::File(ManifestFileData::Digest(42u64.into())),
@@ -461,6 +592,8 @@ mod tests {
                     size: 12,
                     mode: Mode(0o774),
                     mtime: UnixTimestamp(12345),
+                    owner: None,
+                    xattrs: ExtendedAttributes::new(),
                 },
                 data: ManifestEntryData::File(ManifestFileData::Digest(digest![42])),
             },
@@ -470,6 +603,11 @@ mod tests {
                     size: 77,
                     mode: Mode(0o663),
                     mtime: UnixTimestamp(12346),
+                    owner: Some((UserId::new(1000), GroupId::new(100))),
+                    xattrs: ExtendedAttributes::from([(
+                        "security.capability".into(),
+                        vec![1, 2, 3],
+                    )]),
                 },
                 data: ManifestEntryData::File(ManifestFileData::Digest(digest![43])),
             },
@@ -489,6 +627,50 @@ mod tests {
         assert_eq!(reader.next().transpose().unwrap(), None);
     }
 
+    fn test_v1_manifest() -> Vec<u8> {
+        let mut buffer = vec![];
+        encode(&mut buffer, &ManifestVersion::V1).unwrap();
+        for entry in test_entries() {
+            let v1_entry = ManifestEntryV1 {
+                path: entry.path,
+                metadata: ManifestEntryMetadataV1 {
+                    size: entry.metadata.size,
+                    mode: entry.metadata.mode,
+                    mtime: entry.metadata.mtime,
+                },
+                data: entry.data,
+            };
+            encode(&mut buffer, &v1_entry).unwrap();
+        }
+        buffer
+    }
+
+    fn test_v1_entries() -> [ManifestEntry; 2] {
+        test_entries().map(|mut entry| {
+            entry.metadata.owner = None;
+            entry.metadata.xattrs = ExtendedAttributes::new();
+            entry
+        })
+    }
+
+    #[test]
+    fn manifest_read_v1() {
+        let buffer = test_v1_manifest();
+        let entries = test_v1_entries();
+
+        let mut reader = ManifestReader::new(&buffer[..]).unwrap();
+        assert_eq!(reader.next().transpose().unwrap(), Some(entries[0].clone()));
+        assert_eq!(reader.next().transpose().unwrap(), Some(entries[1].clone()));
+        assert_eq!(reader.next().transpose().unwrap(), None);
+    }
+
+    #[test]
+    fn manifest_read_v0() {
+        let mut buffer = vec![];
+        encode(&mut buffer, &ManifestVersion::V0).unwrap();
+        assert!(ManifestReader::new(&buffer[..]).is_err());
+    }
+
     #[test]
     fn manifest_read_truncated() {
         let mut buffer = vec![];
@@ -523,6 +705,17 @@ mod tests {
         assert_eq!(reader.next().await.unwrap(), None);
     }
 
+    #[tokio::test]
+    async fn async_manifest_read_v1() {
+        let buffer = test_v1_manifest();
+        let entries = test_v1_entries();
+
+        let mut reader = AsyncManifestReader::new(&buffer[..]).await.unwrap();
+        assert_eq!(reader.next().await.unwrap(), Some(entries[0].clone()));
+        assert_eq!(reader.next().await.unwrap(), Some(entries[1].clone()));
+        assert_eq!(reader.next().await.unwrap(), None);
+    }
+
     #[tokio::test]
     async fn async_manifest_read_truncated() {
         let mut buffer = vec![];
@@ -0,0 +1,249 @@
+//! Encoding of metrics in the [Prometheus text exposition
+//! format](https://prometheus.io/docs/instrumenting/exposition_formats/#text-based-format).
+//!
+//! Metrics are owned by whichever task is responsible for the underlying state. When a scrape
+//! comes in, that task is asked to encode everything it knows into an [`Encoder`], and the
+//! resulting string is returned as the body of the response.
+
+use std::{fmt::Display, fmt::Write as _, time::Duration};
+
+/// The value to use for the `Content-Type` header when serving the output of an [`Encoder`].
+pub const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// Bucket upper bounds, in seconds, suitable for observing job durations. These span from 10
+/// milliseconds to roughly an hour.
+pub const DURATION_BUCKETS: &[f64] = &[
+    0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 120.0, 300.0, 600.0,
+    1800.0, 3600.0,
+];
+
+/// The type of a metric family, as reported in the `# TYPE` line.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MetricType {
+    Counter,
+    Gauge,
+    Histogram,
+}
+
+impl MetricType {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Counter => "counter",
+            Self::Gauge => "gauge",
+            Self::Histogram => "histogram",
+        }
+    }
+}
+
+/// A histogram with fixed bucket boundaries. Buckets are stored non-cumulatively and converted to
+/// Prometheus's cumulative representation when encoded.
+#[derive(Clone, Debug)]
+pub struct Histogram {
+    bounds: &'static [f64],
+    buckets: Vec<u64>,
+    count: u64,
+    sum: f64,
+}
+
+impl Histogram {
+    /// Create a new, empty histogram. The `bounds` must be sorted in increasing order. An implicit
+    /// `+Inf` bucket is always added.
+    pub fn new(bounds: &'static [f64]) -> Self {
+        assert!(bounds.windows(2).all(|w| w[0] < w[1]));
+        Self {
+            bounds,
+            buckets: vec![0; bounds.len()],
+            count: 0,
+            sum: 0.0,
+        }
+    }
+
+    pub fn observe(&mut self, value: f64) {
+        if let Some(idx) = self.bounds.iter().position(|bound| value <= *bound) {
+            self.buckets[idx] += 1;
+        }
+        self.count += 1;
+        self.sum += value;
+    }
+
+    pub fn observe_duration(&mut self, duration: Duration) {
+        self.observe(duration.as_secs_f64());
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    pub fn sum(&self) -> f64 {
+        self.sum
+    }
+}
+
+/// Accumulates metric families into a string in the text exposition format.
+///
+/// Each family must be introduced with [`Encoder::header`] before its samples are added. The
+/// [`Encoder::counter`] and [`Encoder::gauge`] helpers do both for unlabeled metrics.
+#[derive(Default)]
+pub struct Encoder {
+    out: String,
+}
+
+impl Encoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Write the `# HELP` and `# TYPE` lines for a metric family.
+    pub fn header(&mut self, name: &str, help: &str, metric_type: MetricType) {
+        let help = help.replace('\\', r"\\").replace('\n', r"\n");
+        writeln!(self.out, "# HELP {name} {help}").unwrap();
+        writeln!(self.out, "# TYPE {name} {}", metric_type.as_str()).unwrap();
+    }
+
+    /// Write a single sample with the given labels.
+    pub fn sample(&mut self, name: &str, labels: &[(&str, &str)], value: impl Display) {
+        self.out.push_str(name);
+        self.write_labels(labels, None);
+        writeln!(self.out, " {value}").unwrap();
+    }
+
+    /// Write the `_bucket`, `_sum`, and `_count` samples for a histogram with the given labels.
+    pub fn histogram_sample(&mut self, name: &str, labels: &[(&str, &str)], histogram: &Histogram) {
+        let mut cumulative = 0;
+        for (bound, count) in histogram.bounds.iter().zip(&histogram.buckets) {
+            cumulative += count;
+            write!(self.out, "{name}_bucket").unwrap();
+            self.write_labels(labels, Some(&bound.to_string()));
+            writeln!(self.out, " {cumulative}").unwrap();
+        }
+        write!(self.out, "{name}_bucket").unwrap();
+        self.write_labels(labels, Some("+Inf"));
+        writeln!(self.out, " {}", histogram.count).unwrap();
+        self.sample(&format!("{name}_sum"), labels, histogram.sum);
+        self.sample(&format!("{name}_count"), labels, histogram.count);
+    }
+
+    /// Write an unlabeled counter family.
+    pub fn counter(&mut self, name: &str, help: &str, value: u64) {
+        self.header(name, help, MetricType::Counter);
+        self.sample(name, &[], value);
+    }
+
+    /// Write an unlabeled gauge family.
+    pub fn gauge(&mut self, name: &str, help: &str, value: impl Display) {
+        self.header(name, help, MetricType::Gauge);
+        self.sample(name, &[], value);
+    }
+
+    /// Write an unlabeled histogram family.
+    pub fn histogram(&mut self, name: &str, help: &str, histogram: &Histogram) {
+        self.header(name, help, MetricType::Histogram);
+        self.histogram_sample(name, &[], histogram);
+    }
+
+    pub fn finish(self) -> String {
+        self.out
+    }
+
+    fn write_labels(&mut self, labels: &[(&str, &str)], le: Option<&str>) {
+        if labels.is_empty() && le.is_none() {
+            return;
+        }
+        self.out.push('{');
+        let le = le.map(|le| ("le", le));
+        for (i, (key, value)) in labels.iter().copied().chain(le).enumerate() {
+            if i > 0 {
+                self.out.push(',');
+            }
+            let value = value
+                .replace('\\', r"\\")
+                .replace('"', r#"\""#)
+                .replace('\n', r"\n");
+            write!(self.out, "{key}=\"{value}\"").unwrap();
+        }
+        self.out.push('}');
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indoc::indoc;
+
+    #[test]
+    fn counter_and_gauge() {
+        let mut encoder = Encoder::new();
+        encoder.counter("requests_total", "Total requests.", 3);
+        encoder.gauge("temperature", "Current temperature.", 1.5);
+        assert_eq!(
+            encoder.finish(),
+            indoc! {"
+                # HELP requests_total Total requests.
+                # TYPE requests_total counter
+                requests_total 3
+                # HELP temperature Current temperature.
+                # TYPE temperature gauge
+                temperature 1.5
+            "}
+        );
+    }
+
+    #[test]
+    fn labels_are_escaped() {
+        let mut encoder = Encoder::new();
+        encoder.header("jobs", "Jobs by \\ state\nand client.", MetricType::Gauge);
+        encoder.sample("jobs", &[("client", "1"), ("state", "a\"b\\c\nd")], 2);
+        assert_eq!(
+            encoder.finish(),
+            indoc! {r#"
+                # HELP jobs Jobs by \\ state\nand client.
+                # TYPE jobs gauge
+                jobs{client="1",state="a\"b\\c\nd"} 2
+            "#}
+        );
+    }
+
+    #[test]
+    fn histogram() {
+        let mut histogram = Histogram::new(&[0.5, 1.0]);
+        histogram.observe(0.25);
+        histogram.observe(0.75);
+        histogram.observe_duration(Duration::from_millis(750));
+        histogram.observe(3.0);
+        assert_eq!(histogram.count(), 4);
+        assert_eq!(histogram.sum(), 4.75);
+
+        let mut encoder = Encoder::new();
+        encoder.header("latency", "Latency.", MetricType::Histogram);
+        encoder.histogram_sample("latency", &[("worker", "1")], &histogram);
+        assert_eq!(
+            encoder.finish(),
+            indoc! {r#"
+                # HELP latency Latency.
+                # TYPE latency histogram
+                latency_bucket{worker="1",le="0.5"} 1
+                latency_bucket{worker="1",le="1"} 3
+                latency_bucket{worker="1",le="+Inf"} 4
+                latency_sum{worker="1"} 4.75
+                latency_count{worker="1"} 4
+            "#}
+        );
+    }
+
+    #[test]
+    fn empty_histogram() {
+        let mut encoder = Encoder::new();
+        encoder.histogram("latency", "Latency.", &Histogram::new(&[1.0]));
+        assert_eq!(
+            encoder.finish(),
+            indoc! {r#"
+                # HELP latency Latency.
+                # TYPE latency histogram
+                latency_bucket{le="1"} 0
+                latency_bucket{le="+Inf"} 0
+                latency_sum 0
+                latency_count 0
+            "#}
+        );
+    }
+}
@@ -23,7 +23,7 @@ fn template_re() -> &'static Regex {
     })
 }
 
-#[derive(Default)]
+#[derive(Clone, Default)]
 pub struct TemplateVars(HashMap<String, String>);
 
 impl TemplateVars {
@@ -40,6 +40,20 @@ impl TemplateVars {
         })))
     }
 
+    /// Add more variables, replacing any existing ones with the same names.
+    pub fn extend<I, K, V>(&mut self, iter: I)
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: Into<String>,
+        V: Into<String>,
+    {
+        self.0.extend(iter.into_iter().map(|(k, v)| {
+            let k = k.into();
+            validate_ident(&k);
+            (k, v.into())
+        }));
+    }
+
     pub fn replace(&self, input: &(impl AsRef<str> + ?Sized)) -> Result<String> {
         let input = input.as_ref();
         let mut last = 0;
@@ -166,4 +180,20 @@ mod tests {
             "/he<llo>/bar/bar",
         );
     }
+
+    #[test]
+    fn extend() {
+        let mut vars = TemplateVars::new([("foo", "bar"), ("baz", "qux")]);
+        vars.extend([("baz", "quux"), ("case", "test_a")]);
+        assert_eq!(
+            vars.replace("<foo>/<baz>/<case>").unwrap(),
+            "bar/quux/test_a"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = r#"invalid identifier "foo_bar""#)]
+    fn extend_invalid_ident() {
+        TemplateVars::default().extend([("foo_bar", "")]);
+    }
 }
@@ -1,18 +1,28 @@
 use crate::wasm::rpc::MonitorConnection;
 use anyhow::Result;
 use eframe::{App, CreationContext, Frame};
-use egui::{Align2, CentralPanel, CollapsingHeader, Color32, Context, ScrollArea, Ui};
+use egui::{Align2, CentralPanel, CollapsingHeader, Color32, Context, Grid, ScrollArea, Ui};
 use egui_gauge::Gauge;
 use egui_toast::{Toast, ToastKind, ToastOptions, Toasts};
 use maelstrom_base::{
     proto::{BrokerToMonitor, MonitorToBroker},
-    stats::{BrokerStatistics, JobState, JobStateCounts, BROKER_STATISTICS_INTERVAL},
+    stats::{
+        BrokerStatistics, ClientStatistics, JobState, JobStateCounts, JobStatistics,
+        BROKER_STATISTICS_INTERVAL,
+    },
+    ClientId, JobBrokerStatus, JobWorkerStatus, WorkerId,
 };
-use maelstrom_plot::{Legend, Plot, PlotBounds, PlotPoints, PlotUi, StackedLine};
+use maelstrom_plot::{Legend, Line, Plot, PlotBounds, PlotPoints, PlotUi, StackedLine};
 use std::{collections::BTreeSet, time::Duration};
 
 const REFRESH_INTERVAL: Duration = BROKER_STATISTICS_INTERVAL;
 
+/// The number of statistics samples over which worker throughput is averaged.
+const THROUGHPUT_SAMPLES: usize = 20;
+
+/// The most rows shown in the jobs table.
+const MAX_JOB_ROWS: usize = 500;
+
 fn merge_job_state_counts(mut a: JobStateCounts, b: &JobStateCounts) -> JobStateCounts {
     for state in JobState::iter() {
         a[state] += b[state];
@@ -20,9 +30,41 @@ fn merge_job_state_counts(mut a: JobStateCounts, b: &JobStateCounts) -> JobState
     a
 }
 
+fn job_status_description(status: &JobBrokerStatus) -> &'static str {
+    match status {
+        JobBrokerStatus::WaitingForLayers => "waiting for artifacts",
+        JobBrokerStatus::WaitingForWorker => "waiting for worker",
+        JobBrokerStatus::AtWorker(_, JobWorkerStatus::WaitingForLayers) => "fetching layers",
+        JobBrokerStatus::AtWorker(_, JobWorkerStatus::WaitingToExecute) => "waiting for slot",
+        JobBrokerStatus::AtWorker(_, JobWorkerStatus::Executing) => "executing",
+    }
+}
+
+/// Jobs completed per second by the given worker, averaged over the last [`THROUGHPUT_SAMPLES`]
+/// samples it was present for.
+fn worker_throughput(stats: &BrokerStatistics, wid: WorkerId) -> f64 {
+    let completed: Vec<_> = stats
+        .job_statistics
+        .iter()
+        .filter_map(|s| s.worker_to_stats.get(&wid))
+        .map(|s| s.completed)
+        .collect();
+    let window = &completed[completed.len().saturating_sub(THROUGHPUT_SAMPLES + 1)..];
+    match (window.first(), window.last()) {
+        (Some(first), Some(last)) if window.len() > 1 => {
+            (last - first) as f64
+                / ((window.len() - 1) as f64 * BROKER_STATISTICS_INTERVAL.as_secs_f64())
+        }
+        _ => 0.0,
+    }
+}
+
 pub struct UiHandler<RpcConnectionT> {
     rpc: Option<RpcConnectionT>,
     stats: Option<BrokerStatistics>,
+    jobs: Option<Vec<JobStatistics>>,
+    /// The client whose details are being shown, and its statistics once we've received them.
+    selected_client: Option<(ClientId, Option<ClientStatistics>)>,
     freshness: f64,
 }
 
@@ -83,6 +125,8 @@ impl<RpcConnectionT: MonitorConnection> UiHandler<RpcConnectionT> {
         Self {
             rpc: Some(rpc),
             stats: None,
+            jobs: None,
+            selected_client: None,
             freshness: 0.0,
         }
     }
@@ -135,7 +179,7 @@ impl<RpcConnectionT: MonitorConnection> UiHandler<RpcConnectionT> {
             });
     }
 
-    fn draw_client_graphs(&self, ui: &mut Ui, stats: &BrokerStatistics) {
+    fn draw_client_graphs(&mut self, ui: &mut Ui, stats: &BrokerStatistics) {
         let capacity = stats.job_statistics.capacity();
 
         let last_entry = stats.job_statistics.iter().last();
@@ -150,6 +194,12 @@ impl<RpcConnectionT: MonitorConnection> UiHandler<RpcConnectionT> {
                 .iter()
                 .filter_map(|s| s.client_to_stats.get(client));
             ui.collapsing(format!("Client {client} Job Graph"), |ui| {
+                let selected = matches!(self.selected_client, Some((cid, _)) if cid == *client);
+                if ui.selectable_label(selected, "Show details").clicked() {
+                    // Clear the freshness so we immediately ask for the client's statistics.
+                    self.selected_client = (!selected).then_some((*client, None));
+                    self.freshness = 0.0;
+                }
                 Plot::new(format!("client_{client}_job_statistics"))
                     .width(1000.0)
                     .height(200.0)
@@ -159,7 +209,151 @@ impl<RpcConnectionT: MonitorConnection> UiHandler<RpcConnectionT> {
         }
     }
 
-    fn draw_stats(&self, ui: &mut Ui, stats: &BrokerStatistics) {
+    fn draw_client_details(&self, ui: &mut Ui) {
+        let Some((cid, client_stats)) = &self.selected_client else {
+            return;
+        };
+        CollapsingHeader::new(format!("Client {cid} Details"))
+            .default_open(true)
+            .show(ui, |ui| {
+                let Some(client_stats) = client_stats else {
+                    ui.label("loading..");
+                    return;
+                };
+                match client_stats.failure_rate() {
+                    Some(rate) => ui.label(format!(
+                        "{:.1}% of the last {} completed job(s) failed",
+                        rate * 100.0,
+                        client_stats.recent_completions.len()
+                    )),
+                    None => ui.label("no completed jobs"),
+                };
+                Grid::new(format!("client_{cid}_completions"))
+                    .striped(true)
+                    .num_columns(4)
+                    .show(ui, |ui| {
+                        ui.strong("Job");
+                        ui.strong("Worker");
+                        ui.strong("Result");
+                        ui.strong("Latency");
+                        ui.end_row();
+                        for completion in client_stats.recent_completions.iter().rev() {
+                            ui.label(format!("{cid}.{}", completion.cjid));
+                            ui.label(
+                                completion
+                                    .worker
+                                    .map(|wid| wid.to_string())
+                                    .unwrap_or_default(),
+                            );
+                            if completion.succeeded {
+                                ui.label("succeeded");
+                            } else {
+                                ui.colored_label(Color32::RED, "failed");
+                            }
+                            ui.label(format!("{:.1}s", completion.latency.as_secs_f64()));
+                            ui.end_row();
+                        }
+                    });
+            });
+    }
+
+    fn draw_workers(&self, ui: &mut Ui, stats: &BrokerStatistics) {
+        let last_stat = stats.job_statistics.iter().last();
+        let workers: BTreeSet<_> = stats.worker_statistics.keys().copied().collect();
+
+        CollapsingHeader::new("Workers")
+            .default_open(true)
+            .show(ui, |ui| {
+                Grid::new("workers")
+                    .striped(true)
+                    .num_columns(4)
+                    .show(ui, |ui| {
+                        ui.strong("Worker");
+                        ui.strong("Slots");
+                        ui.strong("Jobs");
+                        ui.strong("Throughput");
+                        ui.end_row();
+                        for wid in &workers {
+                            let slots = stats.worker_statistics[wid].slots;
+                            let jobs = last_stat
+                                .and_then(|s| s.worker_to_stats.get(wid))
+                                .map(|s| s.jobs)
+                                .unwrap_or(0);
+                            ui.label(wid.to_string());
+                            ui.label(slots.to_string());
+                            ui.label(jobs.to_string());
+                            ui.label(format!("{:.2} jobs/s", worker_throughput(stats, *wid)));
+                            ui.end_row();
+                        }
+                    });
+            });
+
+        let capacity = stats.job_statistics.capacity();
+        for wid in workers {
+            let slots = stats.worker_statistics[&wid].slots.max(1);
+            let points: Vec<_> = stats
+                .job_statistics
+                .iter()
+                .enumerate()
+                .filter_map(|(i, s)| {
+                    let jobs = s.worker_to_stats.get(&wid)?.jobs.min(slots);
+                    Some([i as f64, jobs as f64 * 100.0 / slots as f64])
+                })
+                .collect();
+            ui.collapsing(format!("Worker {wid} Utilization"), |ui| {
+                Plot::new(format!("worker_{wid}_utilization"))
+                    .width(1000.0)
+                    .height(200.0)
+                    .legend(Legend::default())
+                    .show(ui, |plot_ui| {
+                        plot_ui.line(Line::new(PlotPoints::new(points)).name("% slots used"));
+                        plot_ui.set_plot_bounds(PlotBounds::from_min_max(
+                            [0.0, 0.0],
+                            [capacity as f64, 100.0],
+                        ));
+                    });
+            });
+        }
+    }
+
+    fn draw_jobs(&self, ui: &mut Ui) {
+        let Some(jobs) = &self.jobs else {
+            return;
+        };
+        CollapsingHeader::new(format!("Jobs ({})", jobs.len()))
+            .default_open(false)
+            .show(ui, |ui| {
+                Grid::new("jobs")
+                    .striped(true)
+                    .num_columns(6)
+                    .show(ui, |ui| {
+                        ui.strong("Job");
+                        ui.strong("Client");
+                        ui.strong("Priority");
+                        ui.strong("State");
+                        ui.strong("Worker");
+                        ui.strong("Elapsed");
+                        ui.end_row();
+                        for job in jobs.iter().take(MAX_JOB_ROWS) {
+                            ui.label(job.id.to_string());
+                            ui.label(job.id.cid.to_string());
+                            ui.label(job.priority.to_string());
+                            ui.label(job_status_description(&job.status));
+                            match &job.status {
+                                JobBrokerStatus::AtWorker(wid, _) => ui.label(wid.to_string()),
+                                _ => ui.label(""),
+                            };
+                            ui.label(format!("{:.1}s", job.elapsed.as_secs_f64()));
+                            ui.end_row();
+                        }
+                    });
+                if jobs.len() > MAX_JOB_ROWS {
+                    ui.label(format!("and {} more", jobs.len() - MAX_JOB_ROWS));
+                }
+            });
+    }
+
+    fn draw_stats(&mut self, ui: &mut Ui, stats: &BrokerStatistics) {
         let last_stat = stats.job_statistics.iter().last();
         let num_clients = last_stat.map(|s| s.client_to_stats.len()).unwrap_or(0);
         let num_workers = stats.worker_statistics.len();
@@ -211,6 +405,9 @@ impl<RpcConnectionT: MonitorConnection> UiHandler<RpcConnectionT> {
 
         self.draw_all_clients_graph(ui, stats);
         self.draw_client_graphs(ui, stats);
+        self.draw_client_details(ui);
+        self.draw_workers(ui, stats);
+        self.draw_jobs(ui);
     }
 
     fn handle_rpcs(&mut self) -> Result<()> {
@@ -218,11 +415,33 @@ impl<RpcConnectionT: MonitorConnection> UiHandler<RpcConnectionT> {
             let now = crate::wasm::window().performance().unwrap().now();
             if now - self.freshness > REFRESH_INTERVAL.as_millis() as f64 {
                 rpc.send(MonitorToBroker::StatisticsRequest)?;
+                rpc.send(MonitorToBroker::JobsRequest)?;
+                if let Some((cid, _)) = &self.selected_client {
+                    rpc.send(MonitorToBroker::ClientStatisticsRequest(*cid))?;
+                }
                 self.freshness = now;
             }
 
-            if let Some(BrokerToMonitor::StatisticsResponse(stats)) = rpc.try_recv()? {
-                self.stats = Some(stats);
+            while let Some(msg) = rpc.try_recv()? {
+                match msg {
+                    BrokerToMonitor::StatisticsResponse(stats) => self.stats = Some(stats),
+                    BrokerToMonitor::JobsResponse(jobs) => self.jobs = Some(jobs),
+                    BrokerToMonitor::ClientStatisticsResponse(cid, client_stats) => {
+                        match &mut self.selected_client {
+                            Some((selected, selected_stats)) if *selected == cid => {
+                                // If the client has disconnected, keep showing what we last knew
+                                // about it.
+                                match client_stats {
+                                    Some(client_stats) => *selected_stats = Some(client_stats),
+                                    None => {
+                                        selected_stats.get_or_insert_with(Default::default);
+                                    }
+                                }
+                            }
+                            _ => {}
+                        }
+                    }
+                }
             }
         }
 
@@ -230,8 +449,9 @@ impl<RpcConnectionT: MonitorConnection> UiHandler<RpcConnectionT> {
     }
 
     fn update_failable(&mut self, ui: &mut Ui) -> Result<()> {
-        if let Some(stats) = &self.stats {
-            self.draw_stats(ui, stats)
+        if let Some(stats) = self.stats.take() {
+            self.draw_stats(ui, &stats);
+            self.stats = Some(stats);
         } else {
             ui.label("loading..");
         }
@@ -3,16 +3,18 @@ use crate::{
     types::{DispatcherSender, TempFile, TempFileFactory},
 };
 use anyhow::{anyhow, Result};
+use async_compression::tokio::bufread::ZstdDecoder;
 use maelstrom_base::Sha256Digest;
 use maelstrom_github::GitHubClient;
 use maelstrom_util::{
     async_fs::Fs,
     cache::{fs::TempFile as _, GotArtifact},
+    io::{read_zstd_size_frame, ExactSizeReader},
     r#async::Pool,
 };
 use slog::{debug, o, warn, Logger};
 use std::{num::NonZeroU32, sync::Arc};
-use tokio::task;
+use tokio::{io::BufReader, task};
 
 pub struct GitHubArtifactFetcher {
     github_client: Arc<GitHubClient>,
@@ -51,9 +53,18 @@ impl dispatcher::ArtifactFetcher for GitHubArtifactFetcher {
         let pool = self.pool.clone();
         let temp_file_factory = self.temp_file_factory.clone();
         task::spawn(async move {
+            let report_size = |size| {
+                let _ = dispatcher_sender.send(Message::ArtifactFetchSize(digest.clone(), size));
+            };
             let result = pool
                 .call_with_item(|_: Option<()>| {
-                    main(github_client, &digest, &log, temp_file_factory)
+                    main(
+                        github_client,
+                        &digest,
+                        &log,
+                        temp_file_factory,
+                        &report_size,
+                    )
                 })
                 .await;
             debug!(log, "artifact fetcher request completed"; "result" => ?result);
@@ -70,6 +81,7 @@ async fn main(
     digest: &Sha256Digest,
     log: &Logger,
     temp_file_factory: TempFileFactory,
+    report_size: &(dyn Fn(u64) + Send + Sync),
 ) -> Result<((), TempFile)> {
     let temp_file = task::spawn_blocking(move || temp_file_factory.temp_file())
         .await
@@ -78,14 +90,20 @@ async fn main(
             warn!(log, "artifact fetcher failed to create a temporary file"; "error" => %err);
         })?;
 
-    let artifact_name = format!("maelstrom-cache-sha256-{digest}");
+    let artifact_name = format!("maelstrom-cache-sha256-{digest}.zst");
     let artifact = github_client
         .get(&artifact_name)
         .await?
         .ok_or_else(|| anyhow!("artifact {digest} not found"))?;
-    let mut stream = github_client
+    let stream = github_client
         .download(artifact.backend_ids, &artifact_name)
         .await?;
+    let mut stream = BufReader::new(stream);
+    let size = read_zstd_size_frame(&mut stream).await?;
+    report_size(size);
+
+    // Don't let a small artifact decompress into something that fills up our disk.
+    let mut stream = ExactSizeReader::new(ZstdDecoder::new(stream), size);
 
     let fs = Fs::new();
     let mut file = fs.create_file(temp_file.path()).await?;
@@ -4,22 +4,38 @@ use crate::{
 };
 use anyhow::{anyhow, bail, Result};
 use maelstrom_base::{
-    proto::{ArtifactFetcherToBroker, BrokerToArtifactFetcher, Hello},
+    proto::{
+        ArtifactCompression, ArtifactFetcherToBroker, ArtifactFetcherToPeer, ArtifactSource,
+        BrokerToArtifactFetcher, Hello, PeerToArtifactFetcher,
+    },
     Sha256Digest,
 };
 use maelstrom_util::{
     cache::{fs::TempFile as _, GotArtifact},
     config::common::BrokerAddr,
     fs::Fs,
-    io,
+    io::{self, Sha256Stream},
     net::{self, AsRawFdExt as _},
     sync::Pool,
 };
 use slog::{debug, o, warn, Logger};
-use std::{net::TcpStream, num::NonZeroU32, sync::Arc, thread};
+use std::{
+    io::Read as _,
+    net::{SocketAddr, TcpStream},
+    num::NonZeroU32,
+    path::Path,
+    sync::Arc,
+    thread,
+    time::Duration,
+};
+
+/// How long we'll wait on a peer, either to connect or for data, before giving up on it and
+/// falling back to the broker.
+const PEER_TIMEOUT: Duration = Duration::from_secs(10);
 
 pub struct TcpArtifactFetcher {
     broker_addr: BrokerAddr,
+    compression: ArtifactCompression,
     dispatcher_sender: DispatcherSender,
     log: Logger,
     pool: Arc<Pool<TcpStream>>,
@@ -31,11 +47,17 @@ impl TcpArtifactFetcher {
         max_simultaneous_fetches: NonZeroU32,
         dispatcher_sender: DispatcherSender,
         broker_addr: BrokerAddr,
+        compress_artifacts: bool,
         log: Logger,
         temp_file_factory: TempFileFactory,
     ) -> Self {
         Self {
             broker_addr,
+            compression: if compress_artifacts {
+                ArtifactCompression::Zstd
+            } else {
+                ArtifactCompression::None
+            },
             dispatcher_sender,
             log,
             pool: Arc::new(Pool::new(max_simultaneous_fetches)),
@@ -52,12 +74,24 @@ impl dispatcher::ArtifactFetcher for TcpArtifactFetcher {
         ));
         debug!(log, "artifact fetcher request enqueued");
         let broker_addr = self.broker_addr;
+        let compression = self.compression;
         let dispatcher_sender = self.dispatcher_sender.clone();
         let pool = self.pool.clone();
         let temp_file_factory = self.temp_file_factory.clone();
         thread::spawn(move || {
+            let report_size = |size| {
+                let _ = dispatcher_sender.send(Message::ArtifactFetchSize(digest.clone(), size));
+            };
             let result = pool.call_with_item(|stream| {
-                main(broker_addr, &digest, &log, stream, temp_file_factory)
+                main(
+                    broker_addr,
+                    compression,
+                    &digest,
+                    &log,
+                    stream,
+                    temp_file_factory,
+                    &report_size,
+                )
             });
             debug!(log, "artifact fetcher request completed"; "result" => ?result);
             let _ = dispatcher_sender.send(Message::ArtifactFetchCompleted(
@@ -70,10 +104,12 @@ impl dispatcher::ArtifactFetcher for TcpArtifactFetcher {
 
 fn main(
     broker_addr: BrokerAddr,
+    accept_compression: ArtifactCompression,
     digest: &Sha256Digest,
     log: &Logger,
     stream_option: Option<TcpStream>,
     temp_file_factory: TempFileFactory,
+    report_size: &dyn Fn(u64),
 ) -> Result<(TcpStream, TempFile)> {
     if stream_option.is_some() {
         debug!(log, "artifact fetcher reusing existing connection");
@@ -95,18 +131,117 @@ fn main(
         }
     };
 
-    net::write_message_to_socket(&mut stream, ArtifactFetcherToBroker(digest.clone()), log)?;
-
-    let BrokerToArtifactFetcher(result) = net::read_message_from_socket(&mut stream, log)?;
-    let size = result.map_err(|e| anyhow!("broker error reading artifact: {e}"))?;
-
     let fs = Fs::new();
+    let mut source = request_from_broker(&mut stream, digest, accept_compression, true, log)?;
+    if let ArtifactSource::Peer(peer) = source {
+        let result = fetch_from_peer(
+            peer,
+            digest,
+            accept_compression,
+            &fs,
+            temp_file.path(),
+            log,
+            report_size,
+        );
+        match result {
+            Ok(()) => return Ok((stream, temp_file)),
+            Err(err) => {
+                debug!(log, "artifact fetcher failed to fetch from peer, falling back to broker";
+                    "peer" => %peer, "error" => %err);
+            }
+        }
+        source = request_from_broker(&mut stream, digest, accept_compression, false, log)?;
+    }
+    let ArtifactSource::Body(size, compression) = source else {
+        bail!("broker redirected to a peer when asked not to");
+    };
+    report_size(size);
+
     let mut file = fs.create_file(temp_file.path())?;
-    let copied = io::copy_using_splice(&mut stream, &mut file, size, log)?;
+    let copied = match compression {
+        ArtifactCompression::None => io::copy_using_splice(&mut stream, &mut file, size, log)?,
+        ArtifactCompression::Zstd => {
+            // The body is a single frame, so the decoder will stop reading from the stream once
+            // it has reached the end of it. We read until the decoder tells us that, instead of
+            // stopping after `size` bytes, so that none of the frame is left on the connection.
+            // Reading one extra byte still lets us tell if the frame holds more than it should,
+            // without filling up our disk if it holds a lot more. In that case, we return an error
+            // and the connection is dropped, so it doesn't matter what's left on it.
+            let decoder = zstd::Decoder::new(&mut stream)?.single_frame();
+            std::io::copy(&mut decoder.take(size.saturating_add(1)), &mut file)?
+        }
+    };
+    check_copied_size(copied, size, log)?;
+
+    Ok((stream, temp_file))
+}
+
+fn request_from_broker(
+    stream: &mut TcpStream,
+    digest: &Sha256Digest,
+    accept_compression: ArtifactCompression,
+    accept_peer: bool,
+    log: &Logger,
+) -> Result<ArtifactSource> {
+    net::write_message_to_socket(
+        &mut *stream,
+        ArtifactFetcherToBroker(digest.clone(), accept_compression, accept_peer),
+        log,
+    )?;
+    let BrokerToArtifactFetcher(result) = net::read_message_from_socket(stream, log)?;
+    result.map_err(|e| anyhow!("broker error reading artifact: {e}"))
+}
+
+/// Fetch the artifact from another worker's artifact server into `path`. Unlike the broker, the
+/// peer isn't trusted to give us the right contents, so we check the digest of what we got.
+fn fetch_from_peer(
+    peer: SocketAddr,
+    digest: &Sha256Digest,
+    accept_compression: ArtifactCompression,
+    fs: &Fs,
+    path: &Path,
+    log: &Logger,
+    report_size: &dyn Fn(u64),
+) -> Result<()> {
+    debug!(log, "artifact fetcher connecting to peer"; "peer" => %peer);
+    let mut stream = TcpStream::connect_timeout(&peer, PEER_TIMEOUT)?.set_socket_options()?;
+    stream.set_read_timeout(Some(PEER_TIMEOUT))?;
+    net::write_message_to_socket(
+        &mut stream,
+        ArtifactFetcherToPeer(digest.clone(), accept_compression),
+        log,
+    )?;
+    let PeerToArtifactFetcher(result) = net::read_message_from_socket(&mut stream, log)?;
+    let (size, compression) = result.map_err(|e| anyhow!("peer error reading artifact: {e}"))?;
+    report_size(size);
+
+    let mut file = Sha256Stream::new(fs.create_file(path)?);
+    let copied = match compression {
+        ArtifactCompression::None => std::io::copy(&mut (&mut stream).take(size), &mut file)?,
+        ArtifactCompression::Zstd => {
+            // Don't let a peer fill up our disk by sending more than it said it would. Reading one
+            // extra byte is enough to tell that it did.
+            let decoder = zstd::Decoder::new(&mut stream)?.single_frame();
+            std::io::copy(&mut decoder.take(size.saturating_add(1)), &mut file)?
+        }
+    };
+    check_copied_size(copied, size, log)?;
+
+    let (_, actual_digest) = file.finalize();
+    if actual_digest != *digest {
+        bail!("peer sent artifact with wrong digest {actual_digest}");
+    }
+    Ok(())
+}
+
+fn check_copied_size(copied: u64, size: u64, log: &Logger) -> Result<()> {
+    if copied > size {
+        debug!(log, "artifact fetcher got more data than expected");
+        bail!("artifact larger than expected");
+    }
     if copied < size {
         debug!(log, "artifact fetcher got premature EOF copying file");
         bail!("premature EOF reading artifact");
     }
-
-    Ok((stream, temp_file))
+    Ok(())
 }
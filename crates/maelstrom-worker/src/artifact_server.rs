@@ -0,0 +1,178 @@
+//! The worker's optional artifact server. Other workers connect to it when the broker tells them
+//! that this worker has a blob they need. Each request names a blob by digest. If the blob is in
+//! our cache, we pin it, send it, and then unpin it. Otherwise, we send back an error and the peer
+//! falls back to fetching the blob from the broker.
+//!
+//! Peers aren't authenticated. Anyone who can connect to the server and knows a blob's digest can
+//! read that blob, so the server should only be reachable from the cluster's network.
+
+use crate::{dispatcher::Message, types::DispatcherSender};
+use anyhow::{anyhow, Result};
+use maelstrom_base::{
+    proto::{ArtifactCompression, ArtifactFetcherToPeer, PeerToArtifactFetcher},
+    Sha256Digest,
+};
+use maelstrom_linux::Errno;
+use maelstrom_util::{
+    fs::Fs,
+    net::{self, AsRawFdExt as _},
+};
+use slog::{debug, o, warn, Logger};
+use std::{
+    io::{self, Read as _},
+    net::TcpStream,
+    path::PathBuf,
+    sync::Arc,
+    thread,
+    time::Duration,
+};
+use tokio::{
+    net::TcpListener,
+    sync::{oneshot, Semaphore},
+    time,
+};
+
+/// The most peers we'll serve at once. Connections beyond this are closed right away, and those
+/// peers fall back to fetching from the broker.
+const MAX_CONNECTIONS: usize = 64;
+
+/// How long to wait before accepting another connection after running out of file descriptors.
+/// Until some are closed, accepting would just fail again right away.
+const ACCEPT_BACKOFF: Duration = Duration::from_millis(100);
+
+/// Accept connections on `listener` forever, handling each on its own thread. Errors accepting or
+/// setting up a connection are logged, and the server carries on with the next one.
+pub async fn listener_main(
+    listener: TcpListener,
+    dispatcher_sender: DispatcherSender,
+    log: Logger,
+) {
+    let connections = Arc::new(Semaphore::new(MAX_CONNECTIONS));
+    loop {
+        let (stream, peer_addr) = match listener.accept().await {
+            Ok(accepted) => accepted,
+            Err(err) => {
+                warn!(log, "error accepting artifact peer connection"; "error" => %err);
+                let errno = err.raw_os_error();
+                if errno == Some(Errno::EMFILE.as_i32()) || errno == Some(Errno::ENFILE.as_i32()) {
+                    time::sleep(ACCEPT_BACKOFF).await;
+                }
+                continue;
+            }
+        };
+        let log = log.new(o!("peer_addr" => peer_addr));
+        let Ok(permit) = connections.clone().try_acquire_owned() else {
+            debug!(log, "artifact peer rejected, too many connections");
+            continue;
+        };
+        let stream = match into_blocking_stream(stream) {
+            Ok(stream) => stream,
+            Err(err) => {
+                warn!(log, "error setting up artifact peer connection"; "error" => %err);
+                continue;
+            }
+        };
+        let dispatcher_sender = dispatcher_sender.clone();
+        thread::spawn(move || {
+            debug!(log, "artifact peer connected");
+            let err = connection_loop(stream, &dispatcher_sender, &log).unwrap_err();
+            debug!(log, "artifact peer disconnected"; "error" => %err);
+            drop(permit);
+        });
+    }
+}
+
+fn into_blocking_stream(stream: tokio::net::TcpStream) -> Result<TcpStream> {
+    let stream = stream.into_std()?;
+    stream.set_nonblocking(false)?;
+    stream.set_socket_options()
+}
+
+/// A blob pinned in the cache by [`Message::GetArtifactForPeer`]. The pin is released when this is
+/// dropped, however we're done with the request.
+struct PinnedArtifact<'a> {
+    digest: Sha256Digest,
+    dispatcher_sender: &'a DispatcherSender,
+}
+
+impl Drop for PinnedArtifact<'_> {
+    fn drop(&mut self) {
+        let _ = self
+            .dispatcher_sender
+            .send(Message::ArtifactSentToPeer(self.digest.clone()));
+    }
+}
+
+fn connection_loop(
+    mut stream: TcpStream,
+    dispatcher_sender: &DispatcherSender,
+    log: &Logger,
+) -> Result<()> {
+    loop {
+        let msg = net::read_message_from_socket(&mut stream, log)?;
+        handle_one_message(msg, &mut stream, dispatcher_sender, log)?;
+    }
+}
+
+fn get_artifact<'a>(
+    digest: &Sha256Digest,
+    dispatcher_sender: &'a DispatcherSender,
+) -> Result<(PathBuf, u64, PinnedArtifact<'a>)> {
+    let (sender, receiver) = oneshot::channel();
+    dispatcher_sender
+        .send(Message::GetArtifactForPeer(digest.clone(), sender))
+        .map_err(|_| anyhow!("dispatcher has exited"))?;
+    let (path, size) = receiver
+        .blocking_recv()?
+        .ok_or_else(|| anyhow!("artifact not in cache"))?;
+    let pin = PinnedArtifact {
+        digest: digest.clone(),
+        dispatcher_sender,
+    };
+    Ok((path, size, pin))
+}
+
+fn handle_one_message(
+    msg: ArtifactFetcherToPeer,
+    mut stream: &mut TcpStream,
+    dispatcher_sender: &DispatcherSender,
+    log: &Logger,
+) -> Result<()> {
+    let ArtifactFetcherToPeer(digest, compression) = msg;
+    let result = get_artifact(&digest, dispatcher_sender);
+    debug!(log, "artifact peer request"; "digest" => %digest, "found" => result.is_ok());
+    let msg = PeerToArtifactFetcher(
+        result
+            .as_ref()
+            .map(|(_, size, _)| (*size, compression))
+            .map_err(|e| e.to_string()),
+    );
+    net::write_message_to_socket(&mut stream, msg, log)?;
+
+    let (path, size, _pin) = result?;
+    send_artifact(path, &mut stream, size, compression)
+}
+
+fn send_artifact(
+    path: PathBuf,
+    stream: &mut impl io::Write,
+    size: u64,
+    compression: ArtifactCompression,
+) -> Result<()> {
+    let fs = Fs::new();
+    let file = fs.open_file(path)?;
+    let copied = match compression {
+        ArtifactCompression::None => io::copy(&mut file.take(size), stream),
+        ArtifactCompression::Zstd => zstd::Encoder::new(stream, zstd::DEFAULT_COMPRESSION_LEVEL)
+            .and_then(|mut encoder| {
+                let copied = io::copy(&mut file.take(size), &mut encoder)?;
+                encoder.finish()?;
+                Ok(copied)
+            }),
+    }?;
+    if copied == size {
+        Ok(())
+    } else {
+        Err(anyhow!("unexpected EOF"))
+    }
+}
@@ -1,8 +1,8 @@
 use maelstrom_macro::Config;
 use maelstrom_util::{
     config::common::{
-        ArtifactTransferStrategy, BrokerAddr, BrokerConnection, CacheSize, InlineLimit, LogLevel,
-        Slots,
+        ArtifactTransferStrategy, BrokerAddr, BrokerConnection, CacheSize, CaptureLimit,
+        InlineLimit, LogLevel, MinFreeSpace, Slots,
     },
     root::RootBuf,
 };
@@ -32,15 +32,32 @@ pub struct Config {
     )]
     pub cache_root: RootBuf<CacheDir>,
 
-    /// The target amount of disk space to use for the cache. This bound won't be followed
-    /// strictly, so it's best to be conservative. SI and binary suffixes are supported.
+    /// The target amount of disk space to use for the cache. Unless `cache-hard-limit` is set,
+    /// this bound won't be followed strictly, so it's best to be conservative. SI and binary
+    /// suffixes are supported.
     #[config(value_name = "BYTES", default = "CacheSize::default()")]
     pub cache_size: CacheSize,
 
+    /// Treat `cache-size` as a hard limit. Layers are fetched for one job at a time, and only while
+    /// the layers in use by jobs and the artifacts being downloaded fit in `cache-size`. Jobs whose
+    /// layers alone are larger than `cache-size` will fail.
+    #[config(flag)]
+    pub cache_hard_limit: bool,
+
+    /// Stop fetching layers for new jobs while the file system holding the cache has less than this
+    /// much free space. SI and binary suffixes are supported.
+    #[config(option, value_name = "BYTES", default = r#""no minimum""#)]
+    pub min_free_space: Option<MinFreeSpace>,
+
     /// The maximum amount of bytes to return inline for captured stdout and stderr.
     #[config(value_name = "BYTES", default = "InlineLimit::default()")]
     pub inline_limit: InlineLimit,
 
+    /// The maximum total size of the files a job leaves in its capture mounts. Jobs that leave
+    /// more fail. SI and binary suffixes are supported.
+    #[config(value_name = "BYTES", default = "CaptureLimit::default()")]
+    pub capture_limit: CaptureLimit,
+
     /// Minimum log level to output.
     #[config(short = 'l', value_name = "LEVEL", default = r#""info""#)]
     pub log_level: LogLevel,
@@ -56,4 +73,20 @@ pub struct Config {
     /// Controls how we connect to the broker.
     #[config(value_name = "BROKER_CONNECTION", default = r#""tcp""#, hide)]
     pub broker_connection: BrokerConnection,
+
+    /// The port to serve metrics on, in the Prometheus text format, at `/metrics`.
+    #[config(option, value_name = "PORT", default = r#""no metrics listener""#)]
+    pub metrics_port: Option<u16>,
+
+    /// The port to serve cached artifacts to other workers on. When set, the broker may tell other
+    /// workers to fetch artifacts from this worker instead of from the broker. Use 0 to have the
+    /// operating system pick a port. Peers aren't authenticated, so only enable this on a trusted
+    /// network.
+    #[config(option, value_name = "PORT", default = r#""no artifact server""#)]
+    pub artifact_server_port: Option<u16>,
+
+    /// Ask the broker and other workers to compress artifacts with zstd when sending them to us.
+    /// This trades CPU time on both ends for less network traffic.
+    #[config(flag)]
+    pub compress_artifacts: bool,
 }
@@ -18,6 +18,7 @@ pub trait BrokerConnection: Sized {
     async fn connect(
         addr: &BrokerAddr,
         slots: Slots,
+        artifact_server_port: Option<u16>,
         log: &Logger,
     ) -> Result<(Self::Read, Self::Write)>;
 }
@@ -29,6 +30,7 @@ impl BrokerConnection for TcpStream {
     async fn connect(
         addr: &BrokerAddr,
         slots: Slots,
+        artifact_server_port: Option<u16>,
         log: &Logger,
     ) -> Result<(Self::Read, Self::Write)> {
         let (read, mut write) = TcpStream::connect(addr.inner())
@@ -44,6 +46,7 @@ impl BrokerConnection for TcpStream {
             &mut write,
             Hello::Worker {
                 slots: slots.into_inner().into(),
+                artifact_server_port,
             },
             log,
         )
@@ -96,6 +99,7 @@ impl BrokerConnection for GitHubQueue {
     async fn connect(
         _addr: &BrokerAddr,
         slots: Slots,
+        _artifact_server_port: Option<u16>,
         log: &Logger,
     ) -> Result<(Self::Read, Self::Write)> {
         let client = crate::github_client_factory()?;
@@ -109,8 +113,10 @@ impl BrokerConnection for GitHubQueue {
 
         net::write_message_to_github_queue(
             &mut write,
+            // Peers can't reach each other over GitHub, so don't advertise an artifact server.
             &Hello::Worker {
                 slots: slots.into_inner().into(),
+                artifact_server_port: None,
             },
             log,
         )
@@ -0,0 +1,47 @@
+//! A task that watches the free space on the file system holding the cache, and tells the
+//! dispatcher when it drops below, or rises back above, the configured minimum.
+
+use crate::{dispatcher::Message, types::DispatcherSender};
+use anyhow::{anyhow, Context as _, Result};
+use bytesize::ByteSize;
+use slog::{info, warn, Logger};
+use std::{path::PathBuf, time::Duration};
+use tokio::time;
+
+/// How often we check the free space.
+const CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Check the free space on the file system holding `path` forever. This only returns if checking
+/// fails or the dispatcher has exited.
+pub async fn monitor_main(
+    path: PathBuf,
+    min_free_space: u64,
+    dispatcher_sender: DispatcherSender,
+    log: Logger,
+) -> Result<()> {
+    let mut low = false;
+    let mut interval = time::interval(CHECK_INTERVAL);
+    loop {
+        interval.tick().await;
+        let available = fs2::available_space(&path)
+            .with_context(|| format!("checking free space on {path:?}"))?;
+        if (available < min_free_space) == low {
+            continue;
+        }
+        low = !low;
+        if low {
+            warn!(log, "free disk space is low, pausing new jobs";
+                "available" => %ByteSize::b(available),
+                "min_free_space" => %ByteSize::b(min_free_space),
+            );
+        } else {
+            info!(log, "free disk space has recovered, resuming new jobs";
+                "available" => %ByteSize::b(available),
+                "min_free_space" => %ByteSize::b(min_free_space),
+            );
+        }
+        dispatcher_sender
+            .send(Message::DiskSpaceLow(low))
+            .map_err(|_| anyhow!("dispatcher has exited"))?;
+    }
+}
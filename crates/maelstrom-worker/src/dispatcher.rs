@@ -5,24 +5,27 @@ mod tracker;
 
 use crate::types::CacheKey;
 use anyhow::{Error, Result};
+use bytesize::ByteSize;
 use maelstrom_base::{
     proto::{BrokerToWorker, WorkerToBroker},
     ArtifactType, JobCompleted, JobError, JobId, JobOutcome, JobResult, JobSpec, JobWorkerStatus,
     Sha256Digest,
 };
 use maelstrom_util::{
-    cache::{fs::Fs, GetArtifact, GotArtifact, Key as _},
+    cache::{fs::Fs, CacheMetrics, GetArtifact, GotArtifact, Key as _},
     config::common::Slots,
     duration,
     ext::OptionExt as _,
+    metrics::{Encoder, MetricType},
 };
 use std::{
     cmp::Ordering,
-    collections::{hash_map::Entry, BinaryHeap, HashMap, HashSet},
+    collections::{BinaryHeap, HashMap, HashSet, VecDeque},
     path::{Path, PathBuf},
     result,
     time::Duration,
 };
+use tokio::sync::oneshot;
 use tracker::{FetcherResult, LayerTracker};
 
 /*              _     _ _
@@ -36,6 +39,7 @@ use tracker::{FetcherResult, LayerTracker};
 
 /// An input message for the dispatcher. These come from various sources.
 #[derive(Debug)]
+#[allow(clippy::large_enum_variant)]
 pub enum Message<FsT: Fs> {
     /// A message from the broker. These messages enqueue and cancel jobs.
     Broker(BrokerToWorker),
@@ -56,6 +60,12 @@ pub enum Message<FsT: Fs> {
     /// each call to eventually result in one of these messages.
     ArtifactFetchCompleted(Sha256Digest, Result<GotArtifact<FsT>>),
 
+    /// A message telling the dispatcher how big an artifact being fetched is, once the artifact
+    /// fetcher has learned it. The dispatcher counts that much of the cache as reserved until the
+    /// corresponding [`Message::ArtifactFetchCompleted`] arrives. Artifact fetchers that don't
+    /// learn the size before the fetch completes don't send this.
+    ArtifactFetchSize(Sha256Digest, u64),
+
     /// A message notifying the dispatcher that the building of a bottom FS layer has completed.
     /// The dispatcher starts building of bottom FS layers by calling
     /// [`Deps::build_bottom_fs_layer`], and expects each call to eventually result in one of these
@@ -78,6 +88,25 @@ pub enum Message<FsT: Fs> {
     /// the dispatcher will schedule no new work, but will continue to process job completions. The
     /// sender can check [`Dispatcher::num_executing`] to know when all jobs have completed.
     ShutDown(Error),
+
+    /// A request for the worker's current metrics, encoded in the Prometheus text format, to be
+    /// sent back on the given sender.
+    MetricsRequest(oneshot::Sender<String>),
+
+    /// A request from the artifact server for a blob that a peer wants to fetch. If the blob is in
+    /// the cache, its path and size are sent back on the given sender, and the blob is pinned in
+    /// the cache until a corresponding [`Message::ArtifactSentToPeer`] is received.
+    GetArtifactForPeer(Sha256Digest, oneshot::Sender<Option<(PathBuf, u64)>>),
+
+    /// A message notifying the dispatcher that the artifact server is done sending a blob that was
+    /// pinned by [`Message::GetArtifactForPeer`].
+    ArtifactSentToPeer(Sha256Digest),
+
+    /// A message notifying the dispatcher that the free space on the cache's file system has
+    /// dropped below (`true`), or risen back above (`false`), the configured minimum. While it's
+    /// below, the dispatcher doesn't start fetching layers for new jobs. The dispatcher passes
+    /// these on to the broker, so that it can send new jobs to other workers in the meantime.
+    DiskSpaceLow(bool),
 }
 
 impl<DepsT, ArtifactFetcherT, BrokerSenderT, CacheT>
@@ -89,13 +118,16 @@ where
     CacheT: Cache,
 {
     /// Create a new [`Dispatcher`] with the provided slot count. The slot count must be a positive
-    /// number.
+    /// number. If `cache_hard_limit` is true, the cache's target size is treated as a hard limit:
+    /// layers are fetched for one job at a time, only while the cache has room, and jobs whose
+    /// layers don't fit in the cache at all fail.
     pub fn new(
         deps: DepsT,
         artifact_fetcher: ArtifactFetcherT,
         broker_sender: BrokerSenderT,
         cache: CacheT,
         slots: Slots,
+        cache_hard_limit: bool,
     ) -> Self {
         Dispatcher {
             deps,
@@ -103,11 +135,16 @@ where
             broker_sender,
             cache,
             slots: slots.into_inner().into(),
+            cache_hard_limit,
+            disk_space_low: false,
+            paused: Default::default(),
+            reserved: Default::default(),
             awaiting_layers: Default::default(),
             available: Default::default(),
             executing: Default::default(),
             shut_down: false,
             shutdown_error: None,
+            job_counts: Default::default(),
         }
     }
 
@@ -122,11 +159,16 @@ where
             Message::JobCompleted(jid, result) => self.receive_job_completed(jid, result),
             Message::JobTimer(jid) => self.receive_job_timer(jid),
             Message::ArtifactFetchCompleted(digest, Ok(artifact)) => {
+                self.reserved.remove(&digest);
                 self.receive_artifact_success(digest, artifact)
             }
             Message::ArtifactFetchCompleted(digest, Err(err)) => {
+                self.reserved.remove(&digest);
                 self.receive_artifact_failure(digest, err)
             }
+            Message::ArtifactFetchSize(digest, size) => {
+                self.reserved.insert(digest, size);
+            }
             Message::BuiltBottomFsLayer(digest, Ok(artifact)) => {
                 self.receive_build_bottom_fs_layer_success(digest, artifact)
             }
@@ -146,7 +188,14 @@ where
                 self.receive_read_manifest_digests_failure(digest, jid, err)
             }
             Message::ShutDown(err) => self.receive_shut_down(err),
+            Message::MetricsRequest(sender) => self.receive_metrics_request(sender),
+            Message::GetArtifactForPeer(digest, sender) => {
+                self.receive_get_artifact_for_peer(digest, sender)
+            }
+            Message::ArtifactSentToPeer(digest) => self.receive_artifact_sent_to_peer(digest),
+            Message::DiskSpaceLow(low) => self.receive_disk_space_low(low),
         };
+        self.possibly_resume_paused_jobs();
         if self.shut_down && self.executing.is_empty() {
             Err(self.shutdown_error.take().unwrap())
         } else {
@@ -194,6 +243,9 @@ pub trait Deps {
 
     /// Start a task to read the digests out of the given path to a manfiest.
     fn read_manifest_digests(&mut self, digest: Sha256Digest, path: PathBuf, jid: JobId);
+
+    /// Encode metrics about work done outside of the dispatcher, like serving FUSE file systems.
+    fn encode_metrics(&self, encoder: &mut Encoder);
 }
 
 /// The artifact fetcher is split out of [`Deps`] for convenience. Artifact fetching is different
@@ -227,7 +279,10 @@ pub trait Cache {
         artifact: GotArtifact<Self::Fs>,
     ) -> result::Result<Vec<JobId>, (Error, Vec<JobId>)>;
     fn decrement_ref_count(&mut self, key: &CacheKey);
+    fn try_get_artifact(&mut self, key: &CacheKey) -> Option<u64>;
+    fn try_get_size(&mut self, key: &CacheKey) -> Option<u64>;
     fn cache_path(&self, key: &CacheKey) -> PathBuf;
+    fn metrics(&self) -> CacheMetrics;
 }
 
 /*             _            _
@@ -239,6 +294,14 @@ pub trait Cache {
  *  FIGLET: private
  */
 
+/// This struct represents a job that we've accepted but haven't started fetching layers for,
+/// because the cache or the disk it's on is full. These jobs sit in a FIFO queue until there's
+/// room. At that point, they become `AwaitingLayersJob`s.
+struct PausedJob {
+    jid: JobId,
+    spec: JobSpec,
+}
+
 /// This struct represents a job where there's still work to do to fetch and/or build layers. These
 /// jobs sit on the sideline until we have their layers ready. At that point, they become
 /// `AvailableJob`s.
@@ -315,6 +378,7 @@ enum ExecutingJobState<DepsT: Deps> {
 struct ExecutingJob<DepsT: Deps> {
     state: ExecutingJobState<DepsT>,
     cache_keys: HashSet<CacheKey>,
+    exclusive: bool,
 }
 
 /// Manage jobs based on the slot count and requests from the broker. If the broker sends more job
@@ -328,11 +392,26 @@ pub struct Dispatcher<DepsT: Deps, ArtifactFetcherT, BrokerSenderT, CacheT> {
     broker_sender: BrokerSenderT,
     cache: CacheT,
     slots: usize,
+    cache_hard_limit: bool,
+    disk_space_low: bool,
+    paused: VecDeque<PausedJob>,
+    reserved: HashMap<Sha256Digest, u64>,
     awaiting_layers: HashMap<JobId, AwaitingLayersJob>,
     available: BinaryHeap<AvailableJob>,
     executing: HashMap<JobId, ExecutingJob<DepsT>>,
     shut_down: bool,
     shutdown_error: Option<Error>,
+    job_counts: JobCounts,
+}
+
+/// Cumulative counts of jobs started and finished, reported as metrics.
+#[derive(Default)]
+struct JobCounts {
+    started: u64,
+    completed: u64,
+    failed: u64,
+    timed_out: u64,
+    canceled: u64,
 }
 
 impl<DepsT, ArtifactFetcherT, BrokerSenderT, CacheT>
@@ -344,9 +423,13 @@ where
     CacheT: Cache,
 {
     /// Start at most one job, depending on whether there are any queued jobs and if there are any
-    /// available slots.
+    /// available slots. An exclusive job only runs when nothing else is running.
     fn possibly_start_job(&mut self) -> bool {
-        if self.executing.len() >= self.slots {
+        if self.executing.len() >= self.slots
+            || self.executing.values().any(|job| job.exclusive)
+            || (!self.executing.is_empty()
+                && self.available.peek().is_some_and(|job| job.spec.exclusive))
+        {
             return false;
         }
         let Some(AvailableJob {
@@ -361,6 +444,7 @@ where
         let timer_handle = spec
             .timeout
             .map(|timeout| self.deps.start_timer(jid, Duration::from(timeout)));
+        let exclusive = spec.exclusive;
         let job_handle = self.deps.start_job(jid, spec, path);
         let executing_job = ExecutingJob {
             state: ExecutingJobState::Nominal {
@@ -368,8 +452,10 @@ where
                 _timer_handle: timer_handle,
             },
             cache_keys,
+            exclusive,
         };
         self.executing.insert(jid, executing_job).assert_is_none();
+        self.job_counts.started += 1;
         self.broker_sender
             .send_message_to_broker(WorkerToBroker::JobStatusUpdate(
                 jid,
@@ -396,32 +482,88 @@ where
         }
     }
 
-    fn receive_enqueue_job(&mut self, jid: JobId, spec: JobSpec) {
-        if !self.shut_down {
-            let mut fetcher = Fetcher {
-                deps: &mut self.deps,
-                artifact_fetcher: &mut self.artifact_fetcher,
-                cache: &mut self.cache,
-                jid,
+    /// Whether we should hold off on fetching layers for new jobs. With a hard cache limit, we
+    /// don't know how much room a job's layers need until we've fetched them, so we only let one
+    /// job fetch layers at a time, and only while the cache has room after what's in use and what's
+    /// reserved by fetches in progress. Entries that nobody is using don't count, since the cache
+    /// will evict them to make room. If there's nothing else in progress that could free up room,
+    /// we let the next job try anyway.
+    fn new_jobs_paused(&self) -> bool {
+        if self.disk_space_low {
+            return true;
+        }
+        if !self.cache_hard_limit
+            || (self.awaiting_layers.is_empty()
+                && self.available.is_empty()
+                && self.executing.is_empty())
+        {
+            return false;
+        }
+        let CacheMetrics {
+            bytes_in_use,
+            bytes_used_target,
+            ..
+        } = self.cache.metrics();
+        let reserved: u64 = self.reserved.values().sum();
+        !self.awaiting_layers.is_empty() || bytes_in_use + reserved >= bytes_used_target
+    }
+
+    /// Start fetching layers for paused jobs, in the order they were received, for as long as
+    /// there's room.
+    fn possibly_resume_paused_jobs(&mut self) {
+        while !self.new_jobs_paused() {
+            let Some(PausedJob { jid, spec }) = self.paused.pop_front() else {
+                break;
             };
-            let tracker = LayerTracker::new(&spec.layers, &mut fetcher);
-            if tracker.is_complete() {
-                self.make_job_available(jid, spec, tracker);
-            } else {
-                self.awaiting_layers
-                    .insert(jid, AwaitingLayersJob { spec, tracker })
-                    .assert_is_none();
-                self.broker_sender
-                    .send_message_to_broker(WorkerToBroker::JobStatusUpdate(
-                        jid,
-                        JobWorkerStatus::WaitingForLayers,
-                    ));
-            }
+            self.start_fetching_layers(jid, spec);
+        }
+    }
+
+    fn receive_enqueue_job(&mut self, jid: JobId, spec: JobSpec) {
+        if self.shut_down {
+            return;
+        }
+        if !self.paused.is_empty() || self.new_jobs_paused() {
+            self.paused.push_back(PausedJob { jid, spec });
+            self.broker_sender
+                .send_message_to_broker(WorkerToBroker::JobStatusUpdate(
+                    jid,
+                    JobWorkerStatus::WaitingForLayers,
+                ));
+        } else {
+            self.start_fetching_layers(jid, spec);
+        }
+    }
+
+    fn start_fetching_layers(&mut self, jid: JobId, spec: JobSpec) {
+        let mut fetcher = Fetcher {
+            deps: &mut self.deps,
+            artifact_fetcher: &mut self.artifact_fetcher,
+            cache: &mut self.cache,
+            jid,
+        };
+        let tracker = LayerTracker::new(&spec.layers, &mut fetcher);
+        if let Some(bytes_used) = self.layers_over_hard_limit(&tracker) {
+            self.fail_job_over_hard_limit(jid, tracker, bytes_used);
+        } else if tracker.is_complete() {
+            self.make_job_available(jid, spec, tracker);
+        } else {
+            self.awaiting_layers
+                .insert(jid, AwaitingLayersJob { spec, tracker })
+                .assert_is_none();
+            self.broker_sender
+                .send_message_to_broker(WorkerToBroker::JobStatusUpdate(
+                    jid,
+                    JobWorkerStatus::WaitingForLayers,
+                ));
         }
     }
 
     fn receive_cancel_job(&mut self, jid: JobId) {
-        if let Some(entry) = self.awaiting_layers.remove(&jid) {
+        if let Some(index) = self.paused.iter().position(|job| job.jid == jid) {
+            // We haven't gotten any layers yet, so there's nothing to release.
+            self.paused.remove(index);
+        } else if let Some(entry) = self.awaiting_layers.remove(&jid) {
             // We may have already gotten some layers. Make sure we release those.
             for key in entry.tracker.into_cache_keys() {
                 self.cache.decrement_ref_count(&key);
@@ -451,20 +593,31 @@ where
     }
 
     fn receive_job_completed(&mut self, jid: JobId, result: JobResult<JobCompleted, String>) {
-        let Some(ExecutingJob { state, cache_keys }) = self.executing.remove(&jid) else {
+        let Some(ExecutingJob {
+            state, cache_keys, ..
+        }) = self.executing.remove(&jid)
+        else {
             panic!("missing entry for {jid:?}");
         };
 
         match state {
             ExecutingJobState::Nominal { .. } => {
+                if result.is_ok() {
+                    self.job_counts.completed += 1;
+                } else {
+                    self.job_counts.failed += 1;
+                }
                 self.broker_sender
                     .send_message_to_broker(WorkerToBroker::JobResponse(
                         jid,
                         result.map(JobOutcome::Completed),
                     ));
             }
-            ExecutingJobState::Canceled => {}
+            ExecutingJobState::Canceled => {
+                self.job_counts.canceled += 1;
+            }
             ExecutingJobState::TimedOut => {
+                self.job_counts.timed_out += 1;
                 self.broker_sender
                     .send_message_to_broker(WorkerToBroker::JobResponse(
                         jid,
@@ -476,13 +629,15 @@ where
         for key in cache_keys {
             self.cache.decrement_ref_count(&key);
         }
-        self.possibly_start_job();
+        // If an exclusive job just completed, there may be room for a lot of jobs.
+        while self.possibly_start_job() {}
     }
 
     fn receive_job_timer(&mut self, jid: JobId) {
         let Some(&mut ExecutingJob {
             ref mut state,
             cache_keys: _,
+            exclusive: _,
         }) = self.executing.get_mut(&jid)
         else {
             return;
@@ -501,6 +656,78 @@ where
         }
     }
 
+    fn receive_get_artifact_for_peer(
+        &mut self,
+        digest: Sha256Digest,
+        sender: oneshot::Sender<Option<(PathBuf, u64)>>,
+    ) {
+        let key = CacheKey::blob(digest);
+        let response = self
+            .cache
+            .try_get_artifact(&key)
+            .map(|size| (self.cache.cache_path(&key), size));
+        if let Err(Some(_)) = sender.send(response) {
+            // The requester went away, so nobody is going to tell us when it's done.
+            self.cache.decrement_ref_count(&key);
+        }
+    }
+
+    fn receive_artifact_sent_to_peer(&mut self, digest: Sha256Digest) {
+        self.cache.decrement_ref_count(&CacheKey::blob(digest));
+    }
+
+    fn receive_metrics_request(&mut self, sender: oneshot::Sender<String>) {
+        let mut encoder = Encoder::new();
+        encoder.gauge("maelstrom_worker_slots", "Number of job slots.", self.slots);
+        encoder.gauge(
+            "maelstrom_worker_executing_jobs",
+            "Number of jobs currently executing.",
+            self.executing.len(),
+        );
+        encoder.gauge(
+            "maelstrom_worker_available_jobs",
+            "Number of jobs with all of their layers that are waiting for a slot.",
+            self.available.len(),
+        );
+        encoder.gauge(
+            "maelstrom_worker_awaiting_layers_jobs",
+            "Number of jobs waiting for their layers to be fetched or built.",
+            self.awaiting_layers.len(),
+        );
+        encoder.gauge(
+            "maelstrom_worker_paused_jobs",
+            "Number of jobs waiting for room in the cache or on disk before fetching their layers.",
+            self.paused.len(),
+        );
+        encoder.counter(
+            "maelstrom_worker_jobs_started_total",
+            "Number of jobs started.",
+            self.job_counts.started,
+        );
+        encoder.header(
+            "maelstrom_worker_jobs_finished_total",
+            "Number of jobs that finished executing, by outcome.",
+            MetricType::Counter,
+        );
+        for (outcome, count) in [
+            ("completed", self.job_counts.completed),
+            ("failed", self.job_counts.failed),
+            ("timed_out", self.job_counts.timed_out),
+            ("canceled", self.job_counts.canceled),
+        ] {
+            encoder.sample(
+                "maelstrom_worker_jobs_finished_total",
+                &[("outcome", outcome)],
+                count,
+            );
+        }
+        self.cache
+            .metrics()
+            .encode(&mut encoder, "maelstrom_worker");
+        self.deps.encode_metrics(&mut encoder);
+        let _ = sender.send(encoder.finish());
+    }
+
     fn job_failure(&mut self, digest: &Sha256Digest, jid: JobId, msg: &str, err: &Error) {
         if let Some(entry) = self.awaiting_layers.remove(&jid) {
             // If this was the first layer error for this request, then we'll find something in
@@ -519,6 +746,37 @@ where
         }
     }
 
+    /// With a hard cache limit, return how much of the cache the layers a job has gotten so far
+    /// use, if that's already more than the limit. If the job's layers don't fit in the cache by
+    /// themselves, waiting won't help. If we let the job run, it would just keep the cache over its
+    /// limit.
+    fn layers_over_hard_limit(&mut self, tracker: &LayerTracker) -> Option<u64> {
+        if !self.cache_hard_limit {
+            return None;
+        }
+        let bytes_used: u64 = tracker
+            .cache_keys()
+            .iter()
+            .filter_map(|key| self.cache.try_get_size(key))
+            .sum();
+        (bytes_used > self.cache.metrics().bytes_used_target).then_some(bytes_used)
+    }
+
+    fn fail_job_over_hard_limit(&mut self, jid: JobId, tracker: LayerTracker, bytes_used: u64) {
+        self.broker_sender
+            .send_message_to_broker(WorkerToBroker::JobResponse(
+                jid,
+                Err(JobError::System(format!(
+                "job's layers use at least {}, which is more than the worker's cache limit of {}",
+                ByteSize::b(bytes_used),
+                ByteSize::b(self.cache.metrics().bytes_used_target),
+            ))),
+            ));
+        for key in tracker.into_cache_keys() {
+            self.cache.decrement_ref_count(&key);
+        }
+    }
+
     fn cache_fill_failure(&mut self, key: CacheKey, msg: &str, err: Error) {
         for jid in self.cache.got_artifact_failure(&key) {
             self.job_failure(key.digest(), jid, msg, &err)
@@ -535,27 +793,27 @@ where
             &mut Fetcher<'_, DepsT, ArtifactFetcherT, CacheT>,
         ),
     ) {
-        match self.awaiting_layers.entry(jid) {
-            Entry::Vacant(_) => {
-                // If there were previous errors for this job, or the job was canceled, then
-                // we'll find nothing in the hash table, and we'll need to release this layer.
-                self.cache.decrement_ref_count(key);
-            }
-            Entry::Occupied(mut entry) => {
-                // So far all is good. We then need to check if we've gotten all layers. If we
-                // have, then we can go ahead and schedule the job.
-                let mut fetcher = Fetcher {
-                    deps: &mut self.deps,
-                    artifact_fetcher: &mut self.artifact_fetcher,
-                    cache: &mut self.cache,
-                    jid,
-                };
-                cb(&mut entry.get_mut().tracker, key.digest(), &mut fetcher);
-                if entry.get().tracker.is_complete() {
-                    let AwaitingLayersJob { spec, tracker } = entry.remove();
-                    self.make_job_available(jid, spec, tracker);
-                }
-            }
+        let Some(mut job) = self.awaiting_layers.remove(&jid) else {
+            // If there were previous errors for this job, or the job was canceled, then we'll find
+            // nothing in the hash table, and we'll need to release this layer.
+            self.cache.decrement_ref_count(key);
+            return;
+        };
+        // So far all is good. We then need to check if we've gotten all layers. If we have, then
+        // we can go ahead and schedule the job.
+        let mut fetcher = Fetcher {
+            deps: &mut self.deps,
+            artifact_fetcher: &mut self.artifact_fetcher,
+            cache: &mut self.cache,
+            jid,
+        };
+        cb(&mut job.tracker, key.digest(), &mut fetcher);
+        if let Some(bytes_used) = self.layers_over_hard_limit(&job.tracker) {
+            self.fail_job_over_hard_limit(jid, job.tracker, bytes_used);
+        } else if job.tracker.is_complete() {
+            self.make_job_available(jid, job.spec, job.tracker);
+        } else {
+            self.awaiting_layers.insert(jid, job).assert_is_none();
         }
     }
 
@@ -666,10 +924,20 @@ where
         self.job_failure(&digest, jid, "failed to read manifest", &err);
     }
 
+    fn receive_disk_space_low(&mut self, low: bool) {
+        if self.disk_space_low != low {
+            self.disk_space_low = low;
+            self.broker_sender
+                .send_message_to_broker(WorkerToBroker::DiskSpaceLow(low));
+        }
+    }
+
     /// Close our connection to the broker, drop pending work, and cancel all jobs.
     fn receive_shut_down(&mut self, shutdown_error: Error) {
         if !self.shut_down {
             self.broker_sender.close();
+            self.paused = Default::default();
+            self.reserved = Default::default();
             self.awaiting_layers = Default::default();
             self.available = Default::default();
             self.shut_down = true;
@@ -792,6 +1060,8 @@ mod tests {
         CacheGotArtifactSuccess(CacheKey, GotArtifact<TestFs>),
         CacheGotArtifactFailure(CacheKey),
         CacheDecrementRefCount(CacheKey),
+        CacheTryGetArtifact(CacheKey),
+        CacheTryGetSize(CacheKey),
         CachePath(CacheKey),
         JobHandleDropped(JobId),
         StartTimer(JobId, Duration),
@@ -806,6 +1076,11 @@ mod tests {
         got_artifact_success_returns: HashMap<CacheKey, Vec<JobId>>,
         got_artifact_failure_returns: HashMap<CacheKey, Vec<JobId>>,
         cache_path_returns: HashMap<CacheKey, PathBuf>,
+        try_get_artifact_returns: HashMap<CacheKey, u64>,
+        try_get_size_returns: HashMap<CacheKey, u64>,
+        bytes_used: u64,
+        bytes_in_use: u64,
+        bytes_used_target: u64,
         closed: bool,
     }
 
@@ -864,6 +1139,10 @@ mod tests {
                 .messages
                 .push(TestMessage::ReadManifestDigests(digest, path, jid));
         }
+
+        fn encode_metrics(&self, encoder: &mut Encoder) {
+            encoder.counter("test_deps_total", "Test deps metric.", 1);
+        }
     }
 
     impl ArtifactFetcher for Rc<RefCell<TestState>> {
@@ -930,6 +1209,18 @@ mod tests {
                 .push(CacheDecrementRefCount(key.clone()))
         }
 
+        fn try_get_artifact(&mut self, key: &CacheKey) -> Option<u64> {
+            let mut b = self.borrow_mut();
+            b.messages.push(CacheTryGetArtifact(key.clone()));
+            b.try_get_artifact_returns.get(key).copied()
+        }
+
+        fn try_get_size(&mut self, key: &CacheKey) -> Option<u64> {
+            let mut b = self.borrow_mut();
+            b.messages.push(CacheTryGetSize(key.clone()));
+            b.try_get_size_returns.get(key).copied()
+        }
+
         fn cache_path(&self, key: &CacheKey) -> PathBuf {
             self.borrow_mut().messages.push(CachePath(key.clone()));
             self.borrow()
@@ -938,6 +1229,16 @@ mod tests {
                 .unwrap_or_else(|| panic!("unexpected cache_path of {key:?}"))
                 .clone()
         }
+
+        fn metrics(&self) -> CacheMetrics {
+            CacheMetrics {
+                hits: 2,
+                bytes_used: self.borrow().bytes_used,
+                bytes_in_use: self.borrow().bytes_in_use,
+                bytes_used_target: self.borrow().bytes_used_target,
+                ..Default::default()
+            }
+        }
     }
 
     struct Fixture {
@@ -965,6 +1266,11 @@ mod tests {
                 got_artifact_success_returns: HashMap::from(got_artifact_success_returns),
                 got_artifact_failure_returns: HashMap::from(got_artifact_failure_returns),
                 cache_path_returns: HashMap::from(cache_path_returns),
+                try_get_artifact_returns: HashMap::default(),
+                try_get_size_returns: HashMap::default(),
+                bytes_used: 0,
+                bytes_in_use: 0,
+                bytes_used_target: 0,
                 closed: false,
             }));
             let dispatcher = Dispatcher::new(
@@ -973,6 +1279,7 @@ mod tests {
                 test_state.clone(),
                 test_state.clone(),
                 Slots::try_from(slots).unwrap(),
+                false,
             );
             Fixture {
                 test_state,
@@ -1511,6 +1818,63 @@ mod tests {
         };
     }
 
+    script_test! {
+        exclusive_job_runs_alone,
+        Fixture::new(2, [
+            (blob!(1), GetArtifact::Success),
+            (blob!(2), GetArtifact::Success),
+            (blob!(3), GetArtifact::Success),
+            (bottom_fs_layer!(1), GetArtifact::Success),
+            (bottom_fs_layer!(2), GetArtifact::Success),
+            (bottom_fs_layer!(3), GetArtifact::Success),
+        ], [], [], [
+            (blob!(1), path_buf!("/z/b/1")),
+            (blob!(2), path_buf!("/z/b/2")),
+            (blob!(3), path_buf!("/z/b/3")),
+            (bottom_fs_layer!(1), path_buf!("/z/bl/1")),
+            (bottom_fs_layer!(2), path_buf!("/z/bl/2")),
+            (bottom_fs_layer!(3), path_buf!("/z/bl/3")),
+        ]),
+        Broker(EnqueueJob(jid!(1), spec!(1))) => {
+            CacheGetArtifact(blob!(1), jid!(1)),
+            CachePath(blob!(1)),
+            CacheGetArtifact(bottom_fs_layer!(1), jid!(1)),
+            CachePath(bottom_fs_layer!(1)),
+            StartJob(jid!(1), spec!(1), path_buf!("/z/bl/1")),
+            SendMessageToBroker(WorkerToBroker::JobStatusUpdate(jid!(1), JobWorkerStatus::Executing)),
+        };
+        Broker(EnqueueJob(jid!(2), spec!(2, priority: 1, exclusive: true))) => {
+            CacheGetArtifact(blob!(2), jid!(2)),
+            CachePath(blob!(2)),
+            CacheGetArtifact(bottom_fs_layer!(2), jid!(2)),
+            CachePath(bottom_fs_layer!(2)),
+            SendMessageToBroker(WorkerToBroker::JobStatusUpdate(jid!(2), JobWorkerStatus::WaitingToExecute)),
+        };
+        Broker(EnqueueJob(jid!(3), spec!(3))) => {
+            CacheGetArtifact(blob!(3), jid!(3)),
+            CachePath(blob!(3)),
+            CacheGetArtifact(bottom_fs_layer!(3), jid!(3)),
+            CachePath(bottom_fs_layer!(3)),
+            SendMessageToBroker(WorkerToBroker::JobStatusUpdate(jid!(3), JobWorkerStatus::WaitingToExecute)),
+        };
+        Message::JobCompleted(jid!(1), Ok(completed!(1))) => {
+            SendMessageToBroker(WorkerToBroker::JobResponse(jid!(1), Ok(outcome!(1)))),
+            CacheDecrementRefCount(blob!(1)),
+            CacheDecrementRefCount(bottom_fs_layer!(1)),
+            JobHandleDropped(jid!(1)),
+            StartJob(jid!(2), spec!(2, priority: 1, exclusive: true), path_buf!("/z/bl/2")),
+            SendMessageToBroker(WorkerToBroker::JobStatusUpdate(jid!(2), JobWorkerStatus::Executing)),
+        };
+        Message::JobCompleted(jid!(2), Ok(completed!(1))) => {
+            SendMessageToBroker(WorkerToBroker::JobResponse(jid!(2), Ok(outcome!(1)))),
+            CacheDecrementRefCount(blob!(2)),
+            CacheDecrementRefCount(bottom_fs_layer!(2)),
+            JobHandleDropped(jid!(2)),
+            StartJob(jid!(3), spec!(3), path_buf!("/z/bl/3")),
+            SendMessageToBroker(WorkerToBroker::JobStatusUpdate(jid!(3), JobWorkerStatus::Executing)),
+        };
+    }
+
     script_test! {
         cancel_unknown,
         Fixture::new(1, [], [], [], []),
@@ -1823,6 +2187,419 @@ mod tests {
         fixture.receive_message(Message::JobCompleted(jid!(1), Ok(completed!(1))));
     }
 
+    #[test]
+    fn metrics_request() {
+        let mut fixture = Fixture::new(
+            2,
+            [
+                (blob!(1), GetArtifact::Success),
+                (bottom_fs_layer!(1), GetArtifact::Success),
+            ],
+            [],
+            [],
+            [
+                (blob!(1), path_buf!("/z/b/1")),
+                (bottom_fs_layer!(1), path_buf!("/z/bl/1")),
+            ],
+        );
+        fixture.receive_message(Broker(EnqueueJob(jid!(1), spec!(1))));
+        fixture.receive_message(Message::JobCompleted(jid!(1), Ok(completed!(1))));
+        fixture.test_state.borrow_mut().messages.clear();
+
+        let (sender, mut receiver) = oneshot::channel();
+        fixture.receive_message(Message::MetricsRequest(sender));
+        fixture.expect_messages_in_any_order(vec![]);
+        let metrics = receiver.try_recv().unwrap();
+        for line in [
+            "maelstrom_worker_slots 2",
+            "maelstrom_worker_executing_jobs 0",
+            "maelstrom_worker_paused_jobs 0",
+            "maelstrom_worker_jobs_started_total 1",
+            r#"maelstrom_worker_jobs_finished_total{outcome="completed"} 1"#,
+            r#"maelstrom_worker_jobs_finished_total{outcome="timed_out"} 0"#,
+            r#"maelstrom_worker_cache_lookups_total{result="hit"} 2"#,
+            "test_deps_total 1",
+        ] {
+            assert!(
+                metrics.lines().any(|l| l == line),
+                "{line:?} not in {metrics}"
+            );
+        }
+    }
+
+    fn hard_limit_fixture(slots: u16) -> Fixture {
+        let mut fixture = Fixture::new(
+            slots,
+            [
+                (blob!(1), GetArtifact::Success),
+                (blob!(2), GetArtifact::Success),
+                (bottom_fs_layer!(1), GetArtifact::Success),
+                (bottom_fs_layer!(2), GetArtifact::Success),
+            ],
+            [],
+            [],
+            [
+                (blob!(1), path_buf!("/z/b/1")),
+                (blob!(2), path_buf!("/z/b/2")),
+                (bottom_fs_layer!(1), path_buf!("/z/bl/1")),
+                (bottom_fs_layer!(2), path_buf!("/z/bl/2")),
+            ],
+        );
+        fixture.dispatcher.cache_hard_limit = true;
+        let mut test_state = fixture.test_state.borrow_mut();
+        test_state.bytes_used_target = 100;
+        test_state.try_get_size_returns = HashMap::from([
+            (blob!(1), 10),
+            (blob!(2), 10),
+            (bottom_fs_layer!(1), 20),
+            (bottom_fs_layer!(2), 20),
+        ]);
+        drop(test_state);
+        fixture
+    }
+
+    #[test]
+    fn disk_space_low_pauses_new_jobs() {
+        let mut fixture = hard_limit_fixture(1);
+        fixture.dispatcher.cache_hard_limit = false;
+
+        fixture.receive_message(Message::DiskSpaceLow(true));
+        fixture.expect_messages_in_any_order(vec![SendMessageToBroker(
+            WorkerToBroker::DiskSpaceLow(true),
+        )]);
+
+        fixture.receive_message(Broker(EnqueueJob(jid!(1), spec!(1))));
+        fixture.expect_messages_in_any_order(vec![SendMessageToBroker(
+            WorkerToBroker::JobStatusUpdate(jid!(1), JobWorkerStatus::WaitingForLayers),
+        )]);
+
+        fixture.receive_message(Message::DiskSpaceLow(false));
+        fixture.expect_messages_in_any_order(vec![
+            SendMessageToBroker(WorkerToBroker::DiskSpaceLow(false)),
+            CacheGetArtifact(blob!(1), jid!(1)),
+            CachePath(blob!(1)),
+            CacheGetArtifact(bottom_fs_layer!(1), jid!(1)),
+            CachePath(bottom_fs_layer!(1)),
+            StartJob(jid!(1), spec!(1), path_buf!("/z/bl/1")),
+            SendMessageToBroker(WorkerToBroker::JobStatusUpdate(
+                jid!(1),
+                JobWorkerStatus::Executing,
+            )),
+        ]);
+    }
+
+    #[test]
+    fn cancel_paused_job() {
+        let mut fixture = hard_limit_fixture(1);
+
+        fixture.receive_message(Message::DiskSpaceLow(true));
+        fixture.receive_message(Broker(EnqueueJob(jid!(1), spec!(1))));
+        fixture.test_state.borrow_mut().messages.clear();
+
+        fixture.receive_message(Broker(CancelJob(jid!(1))));
+        fixture.expect_messages_in_any_order(vec![]);
+
+        fixture.receive_message(Message::DiskSpaceLow(false));
+        fixture.expect_messages_in_any_order(vec![SendMessageToBroker(
+            WorkerToBroker::DiskSpaceLow(false),
+        )]);
+    }
+
+    #[test]
+    fn cache_hard_limit_pauses_new_jobs_while_cache_is_full() {
+        let mut fixture = hard_limit_fixture(2);
+
+        fixture.receive_message(Broker(EnqueueJob(jid!(1), spec!(1))));
+        fixture.expect_messages_in_any_order(vec![
+            CacheGetArtifact(blob!(1), jid!(1)),
+            CachePath(blob!(1)),
+            CacheGetArtifact(bottom_fs_layer!(1), jid!(1)),
+            CachePath(bottom_fs_layer!(1)),
+            CacheTryGetSize(blob!(1)),
+            CacheTryGetSize(bottom_fs_layer!(1)),
+            StartJob(jid!(1), spec!(1), path_buf!("/z/bl/1")),
+            SendMessageToBroker(WorkerToBroker::JobStatusUpdate(
+                jid!(1),
+                JobWorkerStatus::Executing,
+            )),
+        ]);
+
+        let mut test_state = fixture.test_state.borrow_mut();
+        test_state.bytes_used = 100;
+        test_state.bytes_in_use = 100;
+        drop(test_state);
+        fixture.receive_message(Broker(EnqueueJob(jid!(2), spec!(2))));
+        fixture.expect_messages_in_any_order(vec![SendMessageToBroker(
+            WorkerToBroker::JobStatusUpdate(jid!(2), JobWorkerStatus::WaitingForLayers),
+        )]);
+
+        // Once nothing else is using the cache, there's no point in waiting any longer.
+        fixture.receive_message(Message::JobCompleted(jid!(1), Ok(completed!(1))));
+        fixture.expect_messages_in_any_order(vec![
+            JobHandleDropped(jid!(1)),
+            SendMessageToBroker(WorkerToBroker::JobResponse(jid!(1), Ok(outcome!(1)))),
+            CacheDecrementRefCount(blob!(1)),
+            CacheDecrementRefCount(bottom_fs_layer!(1)),
+            CacheGetArtifact(blob!(2), jid!(2)),
+            CachePath(blob!(2)),
+            CacheGetArtifact(bottom_fs_layer!(2), jid!(2)),
+            CachePath(bottom_fs_layer!(2)),
+            CacheTryGetSize(blob!(2)),
+            CacheTryGetSize(bottom_fs_layer!(2)),
+            StartJob(jid!(2), spec!(2), path_buf!("/z/bl/2")),
+            SendMessageToBroker(WorkerToBroker::JobStatusUpdate(
+                jid!(2),
+                JobWorkerStatus::Executing,
+            )),
+        ]);
+    }
+
+    #[test]
+    fn cache_hard_limit_does_not_count_unused_entries_as_full() {
+        let mut fixture = hard_limit_fixture(2);
+
+        fixture.receive_message(Broker(EnqueueJob(jid!(1), spec!(1))));
+        fixture.test_state.borrow_mut().messages.clear();
+
+        // The cache is at its target size, but everything except the first job's layers can be
+        // evicted to make room.
+        let mut test_state = fixture.test_state.borrow_mut();
+        test_state.bytes_used = 100;
+        test_state.bytes_in_use = 30;
+        drop(test_state);
+        fixture.receive_message(Broker(EnqueueJob(jid!(2), spec!(2))));
+        fixture.expect_messages_in_any_order(vec![
+            CacheGetArtifact(blob!(2), jid!(2)),
+            CachePath(blob!(2)),
+            CacheGetArtifact(bottom_fs_layer!(2), jid!(2)),
+            CachePath(bottom_fs_layer!(2)),
+            CacheTryGetSize(blob!(2)),
+            CacheTryGetSize(bottom_fs_layer!(2)),
+            StartJob(jid!(2), spec!(2), path_buf!("/z/bl/2")),
+            SendMessageToBroker(WorkerToBroker::JobStatusUpdate(
+                jid!(2),
+                JobWorkerStatus::Executing,
+            )),
+        ]);
+    }
+
+    #[test]
+    fn cache_hard_limit_resumes_one_paused_job_at_a_time() {
+        let mut fixture = Fixture::new(
+            3,
+            [
+                (blob!(1), GetArtifact::Get),
+                (blob!(2), GetArtifact::Get),
+                (blob!(3), GetArtifact::Get),
+                (bottom_fs_layer!(1), GetArtifact::Success),
+            ],
+            [(blob!(1), vec![jid!(1)])],
+            [(blob!(2), vec![])],
+            [
+                (blob!(1), path_buf!("/z/b/1")),
+                (bottom_fs_layer!(1), path_buf!("/z/bl/1")),
+            ],
+        );
+        fixture.dispatcher.cache_hard_limit = true;
+        fixture.test_state.borrow_mut().bytes_used_target = 100;
+
+        fixture.receive_message(Message::DiskSpaceLow(true));
+        fixture.receive_message(Broker(EnqueueJob(jid!(1), spec!(1))));
+        fixture.receive_message(Broker(EnqueueJob(jid!(2), spec!(2))));
+        fixture.receive_message(Broker(EnqueueJob(jid!(3), spec!(3))));
+        fixture.test_state.borrow_mut().messages.clear();
+
+        // We don't know how big the first job's layers are, so it's the only one that resumes.
+        fixture.receive_message(Message::DiskSpaceLow(false));
+        fixture.expect_messages_in_any_order(vec![
+            SendMessageToBroker(WorkerToBroker::DiskSpaceLow(false)),
+            CacheGetArtifact(blob!(1), jid!(1)),
+            StartArtifactFetch(digest!(1)),
+            SendMessageToBroker(WorkerToBroker::JobStatusUpdate(
+                jid!(1),
+                JobWorkerStatus::WaitingForLayers,
+            )),
+        ]);
+
+        let mut test_state = fixture.test_state.borrow_mut();
+        test_state.bytes_used = 60;
+        test_state.bytes_in_use = 60;
+        drop(test_state);
+        fixture.receive_message(ArtifactFetchCompleted(
+            digest!(1),
+            Ok(GotArtifact::file("/tmp/foo".into())),
+        ));
+        fixture.expect_messages_in_any_order(vec![
+            CacheGotArtifactSuccess(blob!(1), GotArtifact::file("/tmp/foo".into())),
+            CachePath(blob!(1)),
+            CacheGetArtifact(bottom_fs_layer!(1), jid!(1)),
+            CachePath(bottom_fs_layer!(1)),
+            CacheTryGetSize(blob!(1)),
+            CacheTryGetSize(bottom_fs_layer!(1)),
+            StartJob(jid!(1), spec!(1), path_buf!("/z/bl/1")),
+            SendMessageToBroker(WorkerToBroker::JobStatusUpdate(
+                jid!(1),
+                JobWorkerStatus::Executing,
+            )),
+            CacheGetArtifact(blob!(2), jid!(2)),
+            StartArtifactFetch(digest!(2)),
+            SendMessageToBroker(WorkerToBroker::JobStatusUpdate(
+                jid!(2),
+                JobWorkerStatus::WaitingForLayers,
+            )),
+        ]);
+
+        // The second job's fetch keeps its space reserved even after the job is canceled.
+        fixture.receive_message(ArtifactFetchSize(digest!(2), 40));
+        fixture.receive_message(Broker(CancelJob(jid!(2))));
+        fixture.expect_messages_in_any_order(vec![]);
+
+        fixture.receive_message(ArtifactFetchCompleted(digest!(2), Err(anyhow!("foo"))));
+        fixture.expect_messages_in_any_order(vec![
+            CacheGotArtifactFailure(blob!(2)),
+            CacheGetArtifact(blob!(3), jid!(3)),
+            StartArtifactFetch(digest!(3)),
+            SendMessageToBroker(WorkerToBroker::JobStatusUpdate(
+                jid!(3),
+                JobWorkerStatus::WaitingForLayers,
+            )),
+        ]);
+    }
+
+    #[test]
+    fn cache_hard_limit_fails_job_whose_layers_do_not_fit() {
+        let mut fixture = hard_limit_fixture(1);
+        fixture.test_state.borrow_mut().bytes_used_target = 25;
+
+        fixture.receive_message(Broker(EnqueueJob(jid!(1), spec!(1))));
+        fixture.expect_messages_in_any_order(vec![
+            CacheGetArtifact(blob!(1), jid!(1)),
+            CachePath(blob!(1)),
+            CacheGetArtifact(bottom_fs_layer!(1), jid!(1)),
+            CachePath(bottom_fs_layer!(1)),
+            CacheTryGetSize(blob!(1)),
+            CacheTryGetSize(bottom_fs_layer!(1)),
+            SendMessageToBroker(WorkerToBroker::JobResponse(
+                jid!(1),
+                Err(JobError::System(
+                    "job's layers use at least 30 B, which is more than the worker's cache limit of 25 B"
+                        .into(),
+                )),
+            )),
+            CacheDecrementRefCount(blob!(1)),
+            CacheDecrementRefCount(bottom_fs_layer!(1)),
+        ]);
+    }
+
+    #[test]
+    fn cache_hard_limit_fails_job_as_soon_as_its_layers_do_not_fit() {
+        let mut fixture = Fixture::new(
+            1,
+            [
+                (blob!(1), GetArtifact::Get),
+                (blob!(2), GetArtifact::Get),
+                (bottom_fs_layer!(1), GetArtifact::Get),
+            ],
+            [(blob!(1), vec![jid!(1)]), (blob!(2), vec![jid!(1)])],
+            [],
+            [
+                (blob!(1), path_buf!("/z/b/1")),
+                (blob!(2), path_buf!("/z/b/2")),
+            ],
+        );
+        fixture.dispatcher.cache_hard_limit = true;
+        let mut test_state = fixture.test_state.borrow_mut();
+        test_state.bytes_used_target = 25;
+        test_state.try_get_size_returns = HashMap::from([(blob!(1), 30)]);
+        drop(test_state);
+
+        let spec = job_spec!("1", [tar_digest!(1), tar_digest!(2)]);
+        fixture.receive_message(Broker(EnqueueJob(jid!(1), spec)));
+        fixture.test_state.borrow_mut().messages.clear();
+
+        // We don't wait for the second layer before failing the job.
+        fixture.receive_message(ArtifactFetchCompleted(
+            digest!(1),
+            Ok(GotArtifact::file("/tmp/foo".into())),
+        ));
+        fixture.expect_messages_in_any_order(vec![
+            CacheGotArtifactSuccess(blob!(1), GotArtifact::file("/tmp/foo".into())),
+            CachePath(blob!(1)),
+            CacheGetArtifact(bottom_fs_layer!(1), jid!(1)),
+            BuildBottomFsLayer(digest!(1), ArtifactType::Tar, path_buf!("/z/b/1")),
+            CacheTryGetSize(blob!(1)),
+            SendMessageToBroker(WorkerToBroker::JobResponse(
+                jid!(1),
+                Err(JobError::System(
+                    "job's layers use at least 30 B, which is more than the worker's cache limit of 25 B"
+                        .into(),
+                )),
+            )),
+            CacheDecrementRefCount(blob!(1)),
+        ]);
+
+        fixture.receive_message(ArtifactFetchCompleted(
+            digest!(2),
+            Ok(GotArtifact::file("/tmp/bar".into())),
+        ));
+        fixture.expect_messages_in_any_order(vec![
+            CacheGotArtifactSuccess(blob!(2), GotArtifact::file("/tmp/bar".into())),
+            CachePath(blob!(2)),
+            CacheDecrementRefCount(blob!(2)),
+        ]);
+    }
+
+    #[test]
+    fn get_artifact_for_peer_in_cache() {
+        let mut fixture = Fixture::new(1, [], [], [], [(blob!(1), path_buf!("/z/b/1"))]);
+        fixture
+            .test_state
+            .borrow_mut()
+            .try_get_artifact_returns
+            .insert(blob!(1), 42);
+
+        let (sender, mut receiver) = oneshot::channel();
+        fixture.receive_message(Message::GetArtifactForPeer(digest!(1), sender));
+        fixture
+            .expect_messages_in_any_order(vec![CacheTryGetArtifact(blob!(1)), CachePath(blob!(1))]);
+        assert_eq!(
+            receiver.try_recv().unwrap(),
+            Some((path_buf!("/z/b/1"), 42))
+        );
+
+        fixture.receive_message(Message::ArtifactSentToPeer(digest!(1)));
+        fixture.expect_messages_in_any_order(vec![CacheDecrementRefCount(blob!(1))]);
+    }
+
+    #[test]
+    fn get_artifact_for_peer_not_in_cache() {
+        let mut fixture = Fixture::new(1, [], [], [], []);
+
+        let (sender, mut receiver) = oneshot::channel();
+        fixture.receive_message(Message::GetArtifactForPeer(digest!(1), sender));
+        fixture.expect_messages_in_any_order(vec![CacheTryGetArtifact(blob!(1))]);
+        assert_eq!(receiver.try_recv().unwrap(), None);
+    }
+
+    #[test]
+    fn get_artifact_for_peer_requester_gone() {
+        let mut fixture = Fixture::new(1, [], [], [], [(blob!(1), path_buf!("/z/b/1"))]);
+        fixture
+            .test_state
+            .borrow_mut()
+            .try_get_artifact_returns
+            .insert(blob!(1), 42);
+
+        let (sender, receiver) = oneshot::channel();
+        drop(receiver);
+        fixture.receive_message(Message::GetArtifactForPeer(digest!(1), sender));
+        fixture.expect_messages_in_any_order(vec![
+            CacheTryGetArtifact(blob!(1)),
+            CachePath(blob!(1)),
+            CacheDecrementRefCount(blob!(1)),
+        ]);
+    }
+
     script_test! {
         timer_scheduled_then_canceled_on_success,
         Fixture::new(1, [
@@ -1913,6 +2690,7 @@ mod tests {
                 stdout: JobOutputResult::Inline(boxed_u8!(b"stdout")),
                 stderr: JobOutputResult::Inline(boxed_u8!(b"stderr")),
                 duration: std::time::Duration::from_secs(1),
+                captured_files: vec![],
             }
         })) => {
             CacheDecrementRefCount(blob!(1)),
@@ -1921,6 +2699,7 @@ mod tests {
                 stdout: JobOutputResult::Inline(boxed_u8!(b"stdout")),
                 stderr: JobOutputResult::Inline(boxed_u8!(b"stderr")),
                 duration: std::time::Duration::from_secs(1),
+                captured_files: vec![],
             })))),
             StartJob(jid!(2), spec!(2), path_buf!("/z/bl/2")),
             SendMessageToBroker(WorkerToBroker::JobStatusUpdate(jid!(2), JobWorkerStatus::Executing)),
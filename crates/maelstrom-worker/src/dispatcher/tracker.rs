@@ -351,6 +351,11 @@ impl LayerTracker {
         ) || (self.bottom_layers_all_ready() && self.layers.len() < 2)
     }
 
+    /// The keys of the artifacts and layers gotten from the cache so far.
+    pub fn cache_keys(&self) -> &HashSet<CacheKey> {
+        &self.cache_keys
+    }
+
     pub fn into_cache_keys(self) -> HashSet<CacheKey> {
         self.cache_keys
     }
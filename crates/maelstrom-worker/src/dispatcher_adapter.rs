@@ -6,20 +6,33 @@ use crate::{
     types::{DispatcherSender, TempFileFactory},
     MAX_PENDING_LAYERS_BUILDS,
 };
-use anyhow::Result;
-use maelstrom_base::{ArtifactType, JobError, JobId, JobSpec, Sha256Digest};
+use anyhow::{anyhow, Result};
+use maelstrom_base::{
+    ArtifactType, CapturedFile, JobDns, JobError, JobId, JobMount, JobNetwork, JobResult, JobSpec,
+    Sha256Digest, Utf8Path, Utf8PathBuf,
+};
 use maelstrom_layer_fs::{BlobDir, LayerFs, ReaderCache};
 use maelstrom_util::{
-    cache::GotArtifact,
-    config::common::InlineLimit,
+    cache::{
+        fs::{std::TempDir, TempDir as _},
+        GotArtifact,
+    },
+    config::common::{CaptureLimit, InlineLimit},
     fs::Fs,
+    metrics::Encoder,
     root::RootBuf,
     sync::{self, EventSender},
     time::SystemMonotonicClock,
 };
 use slog::{debug, o, Logger};
 use std::{
-    sync::Arc,
+    fmt::Write as _,
+    io::Read as _,
+    path::Path,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
     {path::PathBuf, time::Duration},
 };
 use tokio::{
@@ -28,10 +41,21 @@ use tokio::{
 };
 
 const MANIFEST_DIGEST_CACHE_SIZE: usize = 10_000;
+const RESOLV_CONF_PATH: &str = "/etc/resolv.conf";
+const HOSTS_PATH: &str = "/etc/hosts";
+
+/// Counts of the FUSE sessions served for jobs. These are updated by the tasks serving the sessions.
+#[derive(Default)]
+struct FuseMetrics {
+    sessions: AtomicU64,
+    active: AtomicU64,
+    errors: AtomicU64,
+}
 
 pub struct DispatcherAdapter {
     dispatcher_sender: DispatcherSender,
     inline_limit: InlineLimit,
+    capture_limit: CaptureLimit,
     log: Logger,
     executor: Arc<Executor<'static, SystemMonotonicClock>>,
     blob_dir: RootBuf<BlobDir>,
@@ -39,24 +63,38 @@ pub struct DispatcherAdapter {
     manifest_digest_cache: ManifestDigestCache,
     layer_building_semaphore: Arc<tokio::sync::Semaphore>,
     temp_file_factory: TempFileFactory,
+    fuse_metrics: Arc<FuseMetrics>,
+}
+
+pub struct DispatcherAdapterArgs {
+    pub dispatcher_sender: DispatcherSender,
+    pub inline_limit: InlineLimit,
+    pub capture_limit: CaptureLimit,
+    pub log: Logger,
+    pub mount_dir: RootBuf<MountDir>,
+    pub tmpfs_dir: RootBuf<TmpfsDir>,
+    pub blob_dir: RootBuf<BlobDir>,
+    pub temp_file_factory: TempFileFactory,
 }
 
 impl DispatcherAdapter {
-    #[allow(clippy::too_many_arguments)]
-    pub fn new(
-        dispatcher_sender: DispatcherSender,
-        inline_limit: InlineLimit,
-        log: Logger,
-        mount_dir: RootBuf<MountDir>,
-        tmpfs_dir: RootBuf<TmpfsDir>,
-        blob_dir: RootBuf<BlobDir>,
-        temp_file_factory: TempFileFactory,
-    ) -> Result<Self> {
+    pub fn new(args: DispatcherAdapterArgs) -> Result<Self> {
+        let DispatcherAdapterArgs {
+            dispatcher_sender,
+            inline_limit,
+            capture_limit,
+            log,
+            mount_dir,
+            tmpfs_dir,
+            blob_dir,
+            temp_file_factory,
+        } = args;
         let fs = Fs::new();
         fs.create_dir_all(&mount_dir)?;
         fs.create_dir_all(&tmpfs_dir)?;
         Ok(DispatcherAdapter {
             inline_limit,
+            capture_limit,
             executor: Arc::new(Executor::new(mount_dir, tmpfs_dir, &SystemMonotonicClock)?),
             blob_dir,
             layer_fs_cache: Arc::new(tokio::sync::Mutex::new(ReaderCache::new())),
@@ -71,10 +109,165 @@ impl DispatcherAdapter {
                 MAX_PENDING_LAYERS_BUILDS,
             )),
             temp_file_factory,
+            fuse_metrics: Default::default(),
         })
     }
 }
 
+impl DispatcherAdapter {
+    /// Replace every capture mount in `spec` with a writable bind mount of a new temporary
+    /// directory. The temporary directories are returned along with their mount points so their
+    /// contents can be sent back with the job's results. They are removed when dropped.
+    fn replace_capture_mounts(
+        &self,
+        mut spec: JobSpec,
+    ) -> Result<(JobSpec, Vec<(Utf8PathBuf, TempDir)>), String> {
+        let mut capture_dirs = vec![];
+        for mount in &mut spec.mounts {
+            if let JobMount::Capture { mount_point } = mount {
+                let temp_dir = self
+                    .temp_file_factory
+                    .temp_dir()
+                    .map_err(|err| format!("creating directory for capture mount: {err}"))?;
+                let local_path = Utf8Path::from_path(temp_dir.path())
+                    .ok_or_else(|| "capture mount directory path isn't UTF-8".to_owned())?
+                    .to_owned();
+                let mount_point = std::mem::take(mount_point);
+                *mount = JobMount::Bind {
+                    mount_point: mount_point.clone(),
+                    local_path,
+                    read_only: false,
+                };
+                capture_dirs.push((mount_point, temp_dir));
+            }
+        }
+        Ok((spec, capture_dirs))
+    }
+
+    /// Write the `/etc/resolv.conf` and `/etc/hosts` files called for by the job's [`JobDns`] into
+    /// a new temporary directory, and add read-only bind mounts of them to `spec`. Jobs without
+    /// network access are left alone. The temporary directory is removed when dropped.
+    fn add_dns_mounts(&self, mut spec: JobSpec) -> Result<(JobSpec, Option<TempDir>), String> {
+        if spec.network == JobNetwork::Disabled {
+            return Ok((spec, None));
+        }
+        let fs = Fs::new();
+        let resolv_conf = resolv_conf(&spec.dns, || fs.read_to_string(RESOLV_CONF_PATH))
+            .map_err(|err| format!("reading {RESOLV_CONF_PATH}: {err}"))?;
+        let files = [
+            (RESOLV_CONF_PATH, resolv_conf),
+            (HOSTS_PATH, hosts(&spec.dns)),
+        ];
+        if files.iter().all(|(_, contents)| contents.is_none()) {
+            return Ok((spec, None));
+        }
+
+        let temp_dir = self
+            .temp_file_factory
+            .temp_dir()
+            .map_err(|err| format!("creating directory for DNS files: {err}"))?;
+        for (mount_point, contents) in files {
+            let Some(contents) = contents else {
+                continue;
+            };
+            let mount_point = Utf8Path::new(mount_point);
+            let local_path = Utf8Path::from_path(temp_dir.path())
+                .ok_or_else(|| "DNS file directory path isn't UTF-8".to_owned())?
+                .join(mount_point.file_name().unwrap());
+            fs.write(&local_path, contents)
+                .map_err(|err| format!("writing file for {mount_point}: {err}"))?;
+            spec.mounts.push(JobMount::Bind {
+                mount_point: mount_point.to_owned(),
+                local_path,
+                read_only: true,
+            });
+        }
+        Ok((spec, Some(temp_dir)))
+    }
+}
+
+/// The contents of the `/etc/resolv.conf` to give a job, or `None` if the container's should be
+/// left alone. The job's name servers come first, so they take precedence over any inherited ones,
+/// and its search domains come last, since the last `search` line wins.
+fn resolv_conf(
+    dns: &JobDns,
+    host_resolv_conf: impl FnOnce() -> Result<String>,
+) -> Result<Option<String>> {
+    if !dns.inherit_host && dns.nameservers.is_empty() && dns.search.is_empty() {
+        return Ok(None);
+    }
+    let mut contents = String::new();
+    for nameserver in &dns.nameservers {
+        writeln!(contents, "nameserver {nameserver}").unwrap();
+    }
+    if dns.inherit_host {
+        contents += &host_resolv_conf()?;
+        if !contents.is_empty() && !contents.ends_with('\n') {
+            contents.push('\n');
+        }
+    }
+    if !dns.search.is_empty() {
+        writeln!(contents, "search {}", dns.search.join(" ")).unwrap();
+    }
+    Ok(Some(contents))
+}
+
+/// The contents of the `/etc/hosts` to give a job, or `None` if the container's should be left
+/// alone.
+fn hosts(dns: &JobDns) -> Option<String> {
+    if dns.hosts.is_empty() {
+        return None;
+    }
+    let mut contents = String::from("127.0.0.1 localhost\n::1 localhost\n");
+    for entry in &dns.hosts {
+        writeln!(contents, "{} {}", entry.address, entry.names.join(" ")).unwrap();
+    }
+    Some(contents)
+}
+
+/// Read all of the regular files under `dir`. The returned paths are `dir` replaced with
+/// `mount_point`. Anything that isn't a regular file, like a symlink, is ignored.
+///
+/// The size of each file read is added to `total`. If that brings `total` over `limit`, an
+/// execution error is returned, since it's the job's fault for leaving too much behind.
+fn read_captured_files(
+    dir: &Path,
+    mount_point: &Utf8Path,
+    limit: u64,
+    total: &mut u64,
+) -> JobResult<Vec<CapturedFile>, String> {
+    let system = |err: anyhow::Error| JobError::System(err.to_string());
+    let fs = Fs::new();
+    let mut captured_files = vec![];
+    for path in fs.walk(dir) {
+        let path = path.map_err(system)?;
+        let metadata = fs.symlink_metadata(&path).map_err(system)?;
+        if !metadata.is_file() {
+            continue;
+        }
+        let relative_path =
+            Utf8Path::from_path(path.strip_prefix(dir).map_err(|err| system(err.into()))?)
+                .ok_or_else(|| system(anyhow!("captured file path {path:?} isn't UTF-8")))?;
+        *total = total.saturating_add(metadata.len());
+        if *total > limit {
+            return Err(JobError::Execution(format!(
+                "captured files are larger than the limit of {limit} bytes"
+            )));
+        }
+        let mut contents = vec![];
+        fs.open_file(&path)
+            .map_err(system)?
+            .take(metadata.len())
+            .read_to_end(&mut contents)
+            .map_err(|err| system(err.into()))?;
+        captured_files.push(CapturedFile {
+            path: mount_point.join(relative_path),
+            contents: contents.into_boxed_slice(),
+        });
+    }
+    Ok(captured_files)
+}
+
 pub struct TimerHandle(JoinHandle<()>);
 
 impl Drop for TimerHandle {
@@ -103,20 +296,47 @@ impl Deps for DispatcherAdapter {
             }
         };
         let layer_fs_cache = self.layer_fs_cache.clone();
+        let fuse_metrics = self.fuse_metrics.clone();
         let fuse_spawn = move |fd| {
             tokio::spawn(async move {
+                fuse_metrics.sessions.fetch_add(1, Ordering::Relaxed);
+                fuse_metrics.active.fetch_add(1, Ordering::Relaxed);
                 if let Err(e) = layer_fs.run_fuse(log.clone(), layer_fs_cache, fd).await {
+                    fuse_metrics.errors.fetch_add(1, Ordering::Relaxed);
                     slog::error!(log, "FUSE handling got error {e:?}");
                 }
+                fuse_metrics.active.fetch_sub(1, Ordering::Relaxed);
             });
         };
 
+        let (spec, capture_dirs) = match self.replace_capture_mounts(spec) {
+            Ok(result) => result,
+            Err(err) => {
+                let _ = self
+                    .dispatcher_sender
+                    .send(Message::JobCompleted(jid, Err(JobError::System(err))));
+                return kill_event_sender;
+            }
+        };
+        let (spec, dns_dir) = match self.add_dns_mounts(spec) {
+            Ok(result) => result,
+            Err(err) => {
+                let _ = self
+                    .dispatcher_sender
+                    .send(Message::JobCompleted(jid, Err(JobError::System(err))));
+                return kill_event_sender;
+            }
+        };
+
         let executor = self.executor.clone();
         let spec = executor::JobSpec::from_spec(spec);
         let inline_limit = self.inline_limit;
+        let capture_limit = self.capture_limit.into();
         let dispatcher_sender = self.dispatcher_sender.clone();
         let runtime = tokio::runtime::Handle::current();
         task::spawn_blocking(move || {
+            // Keep the DNS files around until the job is done.
+            let _dns_dir = dns_dir;
             dispatcher_sender
                 .send(Message::JobCompleted(
                     jid,
@@ -128,7 +348,19 @@ impl Deps for DispatcherAdapter {
                             fuse_spawn,
                             runtime,
                         )
-                        .map_err(|e| e.map(|inner| inner.to_string())),
+                        .map_err(|e| e.map(|inner| inner.to_string()))
+                        .and_then(|mut completed| {
+                            let mut total = 0;
+                            for (mount_point, temp_dir) in &capture_dirs {
+                                completed.effects.captured_files.extend(read_captured_files(
+                                    temp_dir.path(),
+                                    mount_point,
+                                    capture_limit,
+                                    &mut total,
+                                )?);
+                            }
+                            Ok(completed)
+                        }),
                 ))
                 .ok()
         });
@@ -216,4 +448,149 @@ impl Deps for DispatcherAdapter {
     fn read_manifest_digests(&mut self, digest: Sha256Digest, path: PathBuf, jid: JobId) {
         self.manifest_digest_cache.get(digest, path, jid);
     }
+
+    fn encode_metrics(&self, encoder: &mut Encoder) {
+        encoder.counter(
+            "maelstrom_worker_fuse_sessions_total",
+            "Number of FUSE file systems served for jobs.",
+            self.fuse_metrics.sessions.load(Ordering::Relaxed),
+        );
+        encoder.gauge(
+            "maelstrom_worker_fuse_sessions",
+            "Number of FUSE file systems currently being served.",
+            self.fuse_metrics.active.load(Ordering::Relaxed),
+        );
+        encoder.counter(
+            "maelstrom_worker_fuse_errors_total",
+            "Number of FUSE file systems that stopped because of an error.",
+            self.fuse_metrics.errors.load(Ordering::Relaxed),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use maelstrom_base::JobHostsEntry;
+    use maelstrom_test::utf8_path_buf;
+
+    #[test]
+    fn read_captured_files_reads_regular_files_recursively() {
+        let fs = Fs::new();
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+        fs.write(dir.join("a.profraw"), b"a").unwrap();
+        fs.create_dir(dir.join("sub")).unwrap();
+        fs.write(dir.join("sub/b.profraw"), b"bb").unwrap();
+        fs.symlink("a.profraw", dir.join("link")).unwrap();
+
+        let mut total = 0;
+        let mut captured_files =
+            read_captured_files(dir, Utf8Path::new("/out"), 3, &mut total).unwrap();
+        captured_files.sort();
+        assert_eq!(total, 3);
+        assert_eq!(
+            captured_files,
+            vec![
+                CapturedFile {
+                    path: utf8_path_buf!("/out/a.profraw"),
+                    contents: Box::new(*b"a"),
+                },
+                CapturedFile {
+                    path: utf8_path_buf!("/out/sub/b.profraw"),
+                    contents: Box::new(*b"bb"),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn read_captured_files_fails_over_limit() {
+        let fs = Fs::new();
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+        fs.write(dir.join("a.profraw"), b"aaa").unwrap();
+
+        let mut total = 1;
+        assert_eq!(
+            read_captured_files(dir, Utf8Path::new("/out"), 3, &mut total),
+            Err(JobError::Execution(
+                "captured files are larger than the limit of 3 bytes".into()
+            ))
+        );
+    }
+
+    #[test]
+    fn resolv_conf_default_leaves_container_alone() {
+        let host = || -> Result<String> { panic!("host's resolv.conf shouldn't be read") };
+        assert_eq!(resolv_conf(&JobDns::default(), host).unwrap(), None);
+    }
+
+    #[test]
+    fn resolv_conf_nameservers_and_search() {
+        let dns = JobDns {
+            nameservers: vec!["10.0.0.1".into(), "10.0.0.2".into()],
+            search: vec!["corp.example.com".into(), "example.com".into()],
+            ..Default::default()
+        };
+        assert_eq!(
+            resolv_conf(&dns, || panic!()).unwrap().unwrap(),
+            "nameserver 10.0.0.1\n\
+            nameserver 10.0.0.2\n\
+            search corp.example.com example.com\n"
+        );
+    }
+
+    #[test]
+    fn resolv_conf_inherit_host() {
+        let dns = JobDns {
+            inherit_host: true,
+            nameservers: vec!["10.0.0.1".into()],
+            search: vec!["corp.example.com".into()],
+            ..Default::default()
+        };
+        assert_eq!(
+            resolv_conf(&dns, || Ok("nameserver 127.0.0.53\nsearch lan".into()))
+                .unwrap()
+                .unwrap(),
+            "nameserver 10.0.0.1\n\
+            nameserver 127.0.0.53\n\
+            search lan\n\
+            search corp.example.com\n"
+        );
+    }
+
+    #[test]
+    fn resolv_conf_inherit_host_error() {
+        let dns = JobDns {
+            inherit_host: true,
+            ..Default::default()
+        };
+        resolv_conf(&dns, || Err(anyhow!("no such file"))).unwrap_err();
+    }
+
+    #[test]
+    fn hosts_entries() {
+        assert_eq!(hosts(&JobDns::default()), None);
+        let dns = JobDns {
+            hosts: vec![
+                JobHostsEntry {
+                    address: "10.0.0.3".into(),
+                    names: vec!["db".into(), "db.internal".into()],
+                },
+                JobHostsEntry {
+                    address: "fd00::3".into(),
+                    names: vec!["cache".into()],
+                },
+            ],
+            ..Default::default()
+        };
+        assert_eq!(
+            hosts(&dns).unwrap(),
+            "127.0.0.1 localhost\n\
+            ::1 localhost\n\
+            10.0.0.3 db db.internal\n\
+            fd00::3 cache\n"
+        );
+    }
 }
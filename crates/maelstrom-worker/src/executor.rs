@@ -77,6 +77,7 @@ impl JobSpec {
             layers: _,
             mounts,
             network,
+            dns: _,
             root_overlay,
             working_directory,
             user,
@@ -85,6 +86,7 @@ impl JobSpec {
             estimated_duration: _,
             allocate_tty,
             priority: _,
+            exclusive: _,
         } = spec;
         JobSpec {
             program,
@@ -957,6 +959,13 @@ impl<ClockT: Clock> Executor<'_, ClockT> {
                 JobMount::Tmp { .. } => {
                     normal_mount(bump, builder, mount_fds, c"tmpfs", "tmpfs")?;
                 }
+                JobMount::Capture { mount_point } => {
+                    // The worker replaces capture mounts with bind mounts of a temporary directory
+                    // before handing the job to the executor.
+                    return Err(syserr(anyhow!(
+                        "capture mount at {mount_point} wasn't replaced with a bind mount"
+                    )));
+                }
             }
         }
         Ok(())
@@ -1104,6 +1113,7 @@ impl<ClockT: Clock> Executor<'_, ClockT> {
                     mount_fds.next().unwrap(),
                     mount_point,
                 )?,
+                JobMount::Capture { .. } => unreachable!(),
             };
         }
         Ok(())
@@ -1591,6 +1601,7 @@ impl<ClockT: Clock> Executor<'_, ClockT> {
                 stdout: read_from_receiver(stdout_receiver)?,
                 stderr: read_from_receiver(stderr_receiver)?,
                 duration,
+                captured_files: vec![],
             },
         })
     }
@@ -1752,6 +1763,7 @@ mod tests {
                         stdout,
                         stderr,
                         duration,
+                        ..
                     },
             } = run(self.spec, self.inline_limit).await.unwrap();
 
@@ -4,34 +4,42 @@ pub mod config;
 pub mod local_worker;
 
 mod artifact_fetcher;
+mod artifact_server;
 mod connection;
+mod disk_space;
 mod dispatcher;
 mod dispatcher_adapter;
 mod executor;
 mod layer_fs;
 mod manifest_digest_cache;
+mod metrics;
 mod types;
 
-use anyhow::{anyhow, bail, Error, Result};
+use anyhow::{anyhow, bail, Context as _, Error, Result};
 use artifact_fetcher::{GitHubArtifactFetcher, TcpArtifactFetcher};
 use config::Config;
 use connection::{BrokerConnection, BrokerReadConnection as _, BrokerWriteConnection as _};
 use dispatcher::{Dispatcher, Message};
-use dispatcher_adapter::DispatcherAdapter;
+use dispatcher_adapter::{DispatcherAdapter, DispatcherAdapterArgs};
 use executor::{MountDir, TmpfsDir};
 use maelstrom_github::{GitHubClient, GitHubQueue};
 use maelstrom_layer_fs::BlobDir;
 use maelstrom_linux::{self as linux};
 use maelstrom_util::{
     cache::{self, fs::std::Fs as StdFs, TempFileFactory},
-    config::common::{ArtifactTransferStrategy, CacheSize, InlineLimit, Slots},
+    config::common::{ArtifactTransferStrategy, CacheSize, CaptureLimit, InlineLimit, Slots},
     root::RootBuf,
     signal,
 };
 use slog::{debug, error, info, Logger};
-use std::{future::Future, process, sync::Arc};
+use std::{
+    future::Future,
+    net::{Ipv6Addr, SocketAddrV6},
+    process,
+    sync::Arc,
+};
 use tokio::{
-    net::TcpStream,
+    net::{TcpListener, TcpStream},
     sync::mpsc,
     task::{self, JoinHandle},
 };
@@ -71,8 +79,25 @@ pub fn main(config: Config, log: Logger) -> Result<()> {
 async fn main_inner<ConnectionT: BrokerConnection>(config: Config, log: &Logger) -> Result<()> {
     check_open_file_limit(log, config.slots, 0)?;
 
+    // Bind the artifact server's socket before connecting, since we tell the broker its port.
+    let artifact_listener = match config.artifact_server_port {
+        Some(port) => {
+            let sock_addr = SocketAddrV6::new(Ipv6Addr::UNSPECIFIED, port, 0, 0);
+            let listener = TcpListener::bind(sock_addr)
+                .await
+                .context("binding artifact server listener socket")?;
+            info!(log, "serving artifacts to peers"; "addr" => %listener.local_addr()?);
+            Some(listener)
+        }
+        None => None,
+    };
+    let artifact_server_port = artifact_listener
+        .as_ref()
+        .map(|listener| listener.local_addr().map(|addr| addr.port()))
+        .transpose()?;
+
     let (read_stream, write_stream) =
-        ConnectionT::connect(&config.broker, config.slots, log).await?;
+        ConnectionT::connect(&config.broker, config.slots, artifact_server_port, log).await?;
 
     let (dispatcher_sender, dispatcher_receiver) = mpsc::unbounded_channel();
     let (broker_socket_outgoing_sender, broker_socket_outgoing_receiver) =
@@ -96,6 +121,47 @@ async fn main_inner<ConnectionT: BrokerConnection>(config: Config, log: &Logger)
         dispatcher_sender.clone(),
     ));
 
+    // Metrics are optional, so a problem with the listener is logged instead of stopping the
+    // worker.
+    if let Some(port) = config.metrics_port {
+        let sock_addr = SocketAddrV6::new(Ipv6Addr::UNSPECIFIED, port, 0, 0);
+        match TcpListener::bind(sock_addr).await {
+            Ok(listener) => {
+                if let Ok(addr) = listener.local_addr() {
+                    info!(log, "serving metrics"; "addr" => %addr);
+                }
+                task::spawn(metrics::listener_main(
+                    listener,
+                    dispatcher_sender.clone(),
+                    log.clone(),
+                ));
+            }
+            Err(err) => {
+                error!(log, "error binding metrics listener socket"; "addr" => %sock_addr, "error" => %err);
+            }
+        }
+    }
+
+    if let Some(min_free_space) = config.min_free_space {
+        task::spawn(shutdown_on_error(
+            disk_space::monitor_main(
+                config.cache_root.clone().into_path_buf(),
+                min_free_space.into(),
+                dispatcher_sender.clone(),
+                log.clone(),
+            ),
+            dispatcher_sender.clone(),
+        ));
+    }
+
+    if let Some(listener) = artifact_listener {
+        task::spawn(artifact_server::listener_main(
+            listener,
+            dispatcher_sender.clone(),
+            log.clone(),
+        ));
+    }
+
     Err(start_dispatcher_task(
         config,
         dispatcher_receiver,
@@ -170,8 +236,10 @@ fn start_dispatcher_task(
 
     let args = DispatcherArgs {
         broker_sender,
+        cache_hard_limit: config.cache_hard_limit,
         cache_size: config.cache_size,
         cache_root: config.cache_root,
+        capture_limit: config.capture_limit,
         dispatcher_receiver,
         dispatcher_sender,
         inline_limit: config.inline_limit,
@@ -187,6 +255,7 @@ fn start_dispatcher_task(
                     max_simultaneous_fetches,
                     dispatcher_sender_clone,
                     config.broker,
+                    config.compress_artifacts,
                     log_clone,
                     temp_file_factory,
                 )
@@ -211,8 +280,10 @@ fn start_dispatcher_task(
 
 struct DispatcherArgs<BrokerSenderT> {
     broker_sender: BrokerSenderT,
+    cache_hard_limit: bool,
     cache_size: CacheSize,
     cache_root: RootBuf<config::CacheDir>,
+    capture_limit: CaptureLimit,
     dispatcher_receiver: DispatcherReceiver,
     dispatcher_sender: DispatcherSender,
     inline_limit: InlineLimit,
@@ -240,15 +311,16 @@ fn start_dispatcher_task_common<
 
     let artifact_fetcher = artifact_fetcher_factory(temp_file_factory.clone());
 
-    let dispatcher_adapter = DispatcherAdapter::new(
-        args.dispatcher_sender,
-        args.inline_limit,
-        args.log.clone(),
-        args.cache_root.join::<MountDir>("mount"),
-        args.cache_root.join::<TmpfsDir>("upper"),
-        cache.root().join::<BlobDir>("sha256/blob"),
+    let dispatcher_adapter = DispatcherAdapter::new(DispatcherAdapterArgs {
+        dispatcher_sender: args.dispatcher_sender,
+        inline_limit: args.inline_limit,
+        capture_limit: args.capture_limit,
+        log: args.log.clone(),
+        mount_dir: args.cache_root.join::<MountDir>("mount"),
+        tmpfs_dir: args.cache_root.join::<TmpfsDir>("upper"),
+        blob_dir: cache.root().join::<BlobDir>("sha256/blob"),
         temp_file_factory,
-    )?;
+    })?;
 
     let mut dispatcher = Dispatcher::new(
         dispatcher_adapter,
@@ -256,6 +328,7 @@ fn start_dispatcher_task_common<
         args.broker_sender,
         cache,
         args.slots,
+        args.cache_hard_limit,
     );
 
     let mut dispatcher_receiver = args.dispatcher_receiver;
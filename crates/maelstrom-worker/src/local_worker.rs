@@ -8,7 +8,7 @@ pub use maelstrom_util::cache::GotArtifact;
 
 use anyhow::{Error, Result};
 use maelstrom_util::{
-    config::common::{CacheSize, InlineLimit, Slots},
+    config::common::{CacheSize, CaptureLimit, InlineLimit, Slots},
     root::RootBuf,
 };
 use slog::Logger;
@@ -38,8 +38,10 @@ pub fn start_task(
 ) -> Result<JoinHandle<Error>> {
     let args = crate::DispatcherArgs {
         broker_sender,
+        cache_hard_limit: false,
         cache_size: config.cache_size,
         cache_root: config.cache_root,
+        capture_limit: CaptureLimit::default(),
         dispatcher_receiver,
         dispatcher_sender,
         inline_limit: config.inline_limit,
@@ -0,0 +1,120 @@
+//! The HTTP server for the worker's optional metrics listener. It answers `GET /metrics` by asking
+//! the dispatcher to encode its metrics in the Prometheus text format. Every other request gets a
+//! 404.
+
+use crate::{dispatcher::Message, types::DispatcherSender};
+use anyhow::{anyhow, Error, Result};
+use hyper::{server::conn::Http, service::service_fn, Body, Method, Request, Response};
+use maelstrom_util::metrics::CONTENT_TYPE;
+use slog::{debug, error, o, Logger};
+use std::time::Duration;
+use tokio::{net::TcpListener, sync::oneshot, task};
+
+/// How long a client has to send a request's headers before its connection is closed.
+const HEADER_READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Accept connections on `listener`, handling each on its own task. This only returns if accepting
+/// a connection fails, in which case the error is logged and the worker carries on without a
+/// metrics listener.
+pub async fn listener_main(
+    listener: TcpListener,
+    dispatcher_sender: DispatcherSender,
+    log: Logger,
+) {
+    let mut http = Http::new();
+    http.http1_only(true);
+    http.http1_keep_alive(true);
+    http.http1_header_read_timeout(HEADER_READ_TIMEOUT);
+
+    loop {
+        match listener.accept().await {
+            Ok((stream, peer_addr)) => {
+                let log = log.new(o!("peer_addr" => peer_addr));
+                let dispatcher_sender = dispatcher_sender.clone();
+                let connection = http.serve_connection(
+                    stream,
+                    service_fn(move |request| {
+                        handle_request(request, dispatcher_sender.clone(), log.clone())
+                    }),
+                );
+                task::spawn(async move { connection.await.ok() });
+            }
+            Err(err) => {
+                error!(log, "error accepting metrics connection"; "error" => %err);
+                return;
+            }
+        }
+    }
+}
+
+async fn handle_request(
+    request: Request<Body>,
+    dispatcher_sender: DispatcherSender,
+    log: Logger,
+) -> Result<Response<Body>, Error> {
+    let response = if request.method() == Method::GET && request.uri().path() == "/metrics" {
+        let (sender, receiver) = oneshot::channel();
+        dispatcher_sender
+            .send(Message::MetricsRequest(sender))
+            .map_err(|_| anyhow!("dispatcher has exited"))?;
+        Response::builder()
+            .status(200)
+            .header("Content-Type", CONTENT_TYPE)
+            .body(Body::from(receiver.await?))
+            .unwrap()
+    } else {
+        Response::builder().status(404).body(Body::empty()).unwrap()
+    };
+    debug!(log, "received metrics request";
+        "method" => %request.method(), "path" => request.uri().path(),
+        "resp" => response.status().as_u16());
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use maelstrom_util::log::test_logger;
+    use tokio::sync::mpsc;
+
+    async fn request(method: Method, uri: &str) -> (u16, String) {
+        let (dispatcher_sender, mut dispatcher_receiver) = mpsc::unbounded_channel();
+        task::spawn(async move {
+            while let Some(message) = dispatcher_receiver.recv().await {
+                if let Message::MetricsRequest(sender) = message {
+                    sender.send("metrics\n".into()).ok();
+                }
+            }
+        });
+        let request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .body(Body::empty())
+            .unwrap();
+        let response = handle_request(request, dispatcher_sender, test_logger())
+            .await
+            .unwrap();
+        let status = response.status().as_u16();
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        (status, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn metrics_request() {
+        assert_eq!(
+            request(Method::GET, "/metrics").await,
+            (200, "metrics\n".into())
+        );
+        assert_eq!(
+            request(Method::GET, "/metrics?name=foo").await,
+            (200, "metrics\n".into())
+        );
+    }
+
+    #[tokio::test]
+    async fn not_metrics_request() {
+        assert_eq!(request(Method::POST, "/metrics").await, (404, "".into()));
+        assert_eq!(request(Method::GET, "/").await, (404, "".into()));
+        assert_eq!(request(Method::GET, "/metrics/foo").await, (404, "".into()));
+    }
+}
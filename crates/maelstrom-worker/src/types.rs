@@ -21,7 +21,11 @@ use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender};
 #[strum(serialize_all = "snake_case")]
 pub enum CacheKeyKind {
     Blob,
+    // The LayerFS kinds carry the on-disk format version, so layers built by an older worker get
+    // removed on start-up instead of being served.
+    #[strum(serialize = "bottom_fs_layer_v1")]
     BottomFsLayer,
+    #[strum(serialize = "upper_fs_layer_v1")]
     UpperFsLayer,
 }
 
@@ -110,9 +114,21 @@ impl dispatcher::Cache for Cache {
         self.decrement_ref_count(key)
     }
 
+    fn try_get_artifact(&mut self, key: &CacheKey) -> Option<u64> {
+        self.try_get_artifact(key)
+    }
+
+    fn try_get_size(&mut self, key: &CacheKey) -> Option<u64> {
+        self.try_get_size(key)
+    }
+
     fn cache_path(&self, key: &CacheKey) -> PathBuf {
         self.cache_path(key).into_path_buf()
     }
+
+    fn metrics(&self) -> cache::CacheMetrics {
+        self.metrics()
+    }
 }
 
 pub struct BrokerSender {